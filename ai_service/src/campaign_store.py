@@ -0,0 +1,115 @@
+import copy
+from typing import Dict, List, Any
+
+
+class CampaignConflict(Exception):
+    """Raised when a turn is submitted against a stale revision"""
+
+
+class NotYourTurn(Exception):
+    """Raised when a player submits a turn out of order"""
+
+
+class InvalidTurn(Exception):
+    """Raised when a turn hands play to someone who isn't in the campaign"""
+
+
+class CampaignStore:
+    def __init__(self):
+        """In-memory store for play-by-post campaigns"""
+        self.campaigns: Dict[str, Dict[str, Any]] = {}
+        self.diffs: Dict[str, List[Dict[str, Any]]] = {}
+
+    def join(self, campaign_id: str, player: str) -> Dict[str, Any]:
+        """Add a player to the turn order, creating the campaign on first join"""
+        campaign = self.campaigns.setdefault(campaign_id, {
+            "campaign_id": campaign_id,
+            "revision": 0,
+            "players": [],
+            "active_player": None,
+            "party": [],
+            "log": []
+        })
+        diffs = self.diffs.setdefault(campaign_id, [])
+
+        # Joining is a revision of its own, so players already in the campaign hear about it
+        if player not in campaign["players"]:
+            diff = {
+                "revision": campaign["revision"] + 1,
+                "author": player,
+                "changes": [{"type": "player_joined", "player": player}]
+            }
+            self._apply(campaign, diff)
+            diffs.append(diff)
+
+        return copy.deepcopy(campaign)
+
+    def diffs_since(self, campaign_id: str, since: int) -> Dict[str, Any]:
+        """Get every diff newer than the given revision"""
+        campaign = self._get(campaign_id)
+        return {
+            "revision": campaign["revision"],
+            "diffs": [diff for diff in self.diffs[campaign_id] if diff["revision"] > since]
+        }
+
+    def submit_turn(
+        self,
+        campaign_id: str,
+        player: str,
+        base_revision: int,
+        changes: List[Dict[str, Any]]
+    ) -> Dict[str, Any]:
+        """Apply a player's turn as a new revision"""
+        campaign = self._get(campaign_id)
+
+        if campaign["active_player"] != player:
+            raise NotYourTurn(f"It is {campaign['active_player']}'s turn")
+        if base_revision != campaign["revision"]:
+            raise CampaignConflict(
+                f"Turn was based on revision {base_revision} but the campaign is at {campaign['revision']}"
+            )
+        for change in changes:
+            if change.get("type") == "pass_turn" and change.get("next_player") not in campaign["players"]:
+                raise InvalidTurn(f"{change.get('next_player')} has not joined the campaign")
+
+        diff = {
+            "revision": campaign["revision"] + 1,
+            "author": player,
+            "changes": changes
+        }
+        self._apply(campaign, diff)
+        self.diffs[campaign_id].append(diff)
+
+        return diff
+
+    def _get(self, campaign_id: str) -> Dict[str, Any]:
+        if campaign_id not in self.campaigns:
+            raise KeyError(campaign_id)
+        return self.campaigns[campaign_id]
+
+    def _apply(self, campaign: Dict[str, Any], diff: Dict[str, Any]):
+        """Keep the full state current so late joiners get a complete snapshot"""
+        for change in diff["changes"]:
+            change_type = change.get("type")
+
+            if change_type == "update_character":
+                # Members are told apart by their campaign id, not their names
+                character = dict(change["character"], id=change["id"])
+                party = campaign["party"]
+                for index, member in enumerate(party):
+                    if member.get("id") == character["id"]:
+                        party[index] = character
+                        break
+                else:
+                    party.append(character)
+            elif change_type == "add_log_entry":
+                campaign["log"].append(f"{diff['author']}: {change['text']}")
+            elif change_type == "pass_turn":
+                campaign["active_player"] = change["next_player"]
+            elif change_type == "player_joined":
+                campaign["players"].append(change["player"])
+                # The first to join takes the first turn
+                if campaign["active_player"] is None:
+                    campaign["active_player"] = change["player"]
+
+        campaign["revision"] = diff["revision"]
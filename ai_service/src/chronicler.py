@@ -0,0 +1,53 @@
+import random
+from typing import List
+
+
+class SessionChronicler:
+    def __init__(self):
+        """Writes prose recaps of play sessions for the campaign diary"""
+        self.openings = [
+            "So it was written in the chronicles of the company:",
+            "Let the bards remember this day.",
+            "Here begins the tale of another venture into peril.",
+            "The candles burned low as the scribe set down these deeds."
+        ]
+        self.closings = [
+            "What fate awaits them next, only the dice can tell.",
+            "And so the company rested, counting their wounds and their coin.",
+            "The road goes ever on, and darker places still await.",
+            "Thus ends this chapter; the next is yet unwritten."
+        ]
+
+    async def recap(
+        self,
+        party: List[str],
+        events: List[str],
+        dialogue: List[str],
+        locations: List[str]
+    ) -> str:
+        """Turn the session journal into a few paragraphs of prose"""
+        paragraphs = [random.choice(self.openings)]
+
+        if party:
+            paragraphs.append(f"The company numbered {self._join(party)}.")
+
+        if locations:
+            paragraphs.append(f"Their travels brought them to {self._join(locations)}.")
+
+        if events:
+            # Keep the recap readable for long sessions
+            highlights = events if len(events) <= 8 else events[:4] + events[-4:]
+            paragraphs.append(" ".join(highlights))
+
+        if dialogue:
+            paragraphs.append(
+                f"Words were exchanged along the way, most memorably: \"{dialogue[-1]}\""
+            )
+
+        paragraphs.append(random.choice(self.closings))
+        return "\n\n".join(paragraphs)
+
+    def _join(self, items: List[str]) -> str:
+        if len(items) == 1:
+            return items[0]
+        return ", ".join(items[:-1]) + " and " + items[-1]
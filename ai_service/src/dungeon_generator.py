@@ -517,7 +517,14 @@ class DungeonGenerator:
             "forest": ["Rustling Leaves", "Dense Undergrowth"],
             "cave": ["Echoing Sounds", "Stalactites"],
             "city": ["Narrow Alleys", "Crowded Streets"],
-            "dungeon": ["Dark Corridors", "Ancient Stone"]
+            "dungeon": ["Dark Corridors", "Ancient Stone"],
+            "clear": ["Open Grassland", "Distant Smoke"],
+            "woods": ["Rustling Leaves", "Dense Undergrowth"],
+            "river": ["Muddy Banks", "Rushing Water"],
+            "hills": ["Rocky Slopes", "Hidden Hollows"],
+            "mountains": ["Narrow Pass", "Falling Scree"],
+            "swamp": ["Sucking Mire", "Biting Insects"],
+            "desert": ["Shifting Sand", "Blazing Sun"]
         }
         
         environment = location_modifiers.get(location.lower(), ["Unknown Area"])
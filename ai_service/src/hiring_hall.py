@@ -0,0 +1,40 @@
+import random
+from typing import Any, Dict, List
+
+
+class HiringHall:
+    def __init__(self):
+        """Makes up the hirelings looking for work in town"""
+        self.names = [
+            "Aldo", "Brigid", "Cuthbert", "Dunstan", "Elsa", "Fenwick",
+            "Gilda", "Hamish", "Ingrid", "Jasper", "Kestrel", "Lorcan"
+        ]
+        self.personalities = [
+            "A nervous young guard who is eager to prove himself",
+            "A cunning thief who always has an angle",
+            "A noble knight who values honor above all",
+            "A sullen veteran who wants paying on time",
+            "A wide-eyed farmhand who has never seen a dungeon",
+            "A cheerful drunk who is braver than they look"
+        ]
+        self.classes = ["Fighter", "Cleric", "MagicUser", "Thief", "Dwarf", "Elf", "Halfling"]
+
+    async def candidates(self, location: str, party_level: int, count: int) -> List[Dict[str, Any]]:
+        """Torchbearers, men-at-arms and adventurers a little less experienced than the party"""
+        return [self._candidate(party_level) for _ in range(count)]
+
+    def _candidate(self, party_level: int) -> Dict[str, Any]:
+        kind = random.choice(["torchbearer", "man_at_arms", "adventurer"])
+        if kind == "adventurer":
+            character_class = random.choice(self.classes)
+            level = random.randint(1, max(1, party_level - 1))
+        else:
+            character_class = "Fighter"
+            level = 1
+        return {
+            "name": random.choice(self.names),
+            "kind": kind,
+            "class": character_class,
+            "level": level,
+            "personality": random.choice(self.personalities)
+        }
@@ -1,7 +1,10 @@
 from fastapi import FastAPI, HTTPException
+from fastapi.responses import StreamingResponse
 from fastapi.middleware.cors import CORSMiddleware
 from pydantic import BaseModel
 from typing import List, Optional, Dict, Any
+import asyncio
+import json
 import uvicorn
 import os
 from dotenv import load_dotenv
@@ -10,6 +13,11 @@ from dotenv import load_dotenv
 from .npc_ai import NPCAI
 from .dungeon_generator import DungeonGenerator
 from .quest_generator import QuestGenerator
+from .chronicler import SessionChronicler
+from .narrator import DungeonNarrator
+from .rumor_mill import RumorMill
+from .hiring_hall import HiringHall
+from .campaign_store import CampaignStore, CampaignConflict, InvalidTurn, NotYourTurn
 
 load_dotenv()
 
@@ -32,6 +40,11 @@ app.add_middleware(
 npc_ai = NPCAI()
 dungeon_generator = DungeonGenerator()
 quest_generator = QuestGenerator()
+campaign_store = CampaignStore()
+chronicler = SessionChronicler()
+narrator = DungeonNarrator()
+rumor_mill = RumorMill()
+hiring_hall = HiringHall()
 
 # Pydantic models for API requests/responses
 class NPCData(BaseModel):
@@ -73,6 +86,56 @@ class QuestGenerationRequest(BaseModel):
     player_level: int
     context: Dict[str, Any]
 
+class CampaignJoinRequest(BaseModel):
+    player: str
+
+class CampaignTurnRequest(BaseModel):
+    player: str
+    base_revision: int
+    changes: List[Dict[str, Any]]
+
+class RumorRequest(BaseModel):
+    location: str
+    party_level: int
+    known_sites: List[str]
+    known_npcs: List[str] = []
+    quests: List[str] = []
+
+class RumorResponse(BaseModel):
+    text: str
+    truthful: bool = True
+    site: Optional[str] = None
+    theme: Optional[str] = None
+    level: Optional[int] = None
+    quest: Optional[Dict[str, Any]] = None
+
+class HirelingRequest(BaseModel):
+    location: str
+    party_level: int
+    count: int
+
+class HirelingsResponse(BaseModel):
+    candidates: List[Dict[str, Any]]
+
+class RecapRequest(BaseModel):
+    party: List[str]
+    events: List[str]
+    dialogue: List[str]
+    locations: List[str]
+
+class RecapResponse(BaseModel):
+    recap: str
+
+class NarrationRequest(BaseModel):
+    moment: str
+    subject: str
+    room: Optional[str] = None
+    party: List[str]
+    recent_actions: List[str]
+
+class NarrationResponse(BaseModel):
+    text: str
+
 class QuestData(BaseModel):
     title: str
     description: str
@@ -88,9 +151,17 @@ async def root():
         "version": "0.1.0",
         "endpoints": [
             "/conversation",
+            "/conversation/stream",
             "/generate_dungeon", 
             "/generate_quest",
-            "/generate_encounter"
+            "/generate_encounter",
+            "/rumor",
+            "/hirelings",
+            "/recap",
+            "/narrate",
+            "/campaign/{campaign_id}/join",
+            "/campaign/{campaign_id}/diffs",
+            "/campaign/{campaign_id}/turn"
         ]
     }
 
@@ -99,7 +170,7 @@ async def converse_with_npc(request: ConversationRequest):
     """Handle NPC conversations with AI-powered responses"""
     try:
         response = await npc_ai.converse(
-            npc_data=request.npc_data,
+            npc_data=request.npc_data.model_dump(),
             player_message=request.player_message,
             player_name=request.player_name,
             context=request.context
@@ -108,6 +179,32 @@ async def converse_with_npc(request: ConversationRequest):
     except Exception as e:
         raise HTTPException(status_code=500, detail=f"Conversation failed: {str(e)}")
 
+@app.post("/conversation/stream")
+async def stream_conversation(request: ConversationRequest):
+    """As /conversation, with the reply sent a few words at a time as lines of JSON:
+    {"chunk": ...} for each piece, then {"response": ...} with the whole response"""
+    try:
+        response = ConversationResponse(**await npc_ai.converse(
+            npc_data=request.npc_data.model_dump(),
+            player_message=request.player_message,
+            player_name=request.player_name,
+            context=request.context
+        ))
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Conversation failed: {str(e)}")
+
+    async def lines():
+        words = response.npc_response.split(" ")
+        for start in range(0, len(words), 3):
+            chunk = " ".join(words[start:start + 3])
+            if start + 3 < len(words):
+                chunk += " "
+            yield json.dumps({"chunk": chunk}) + "\n"
+            await asyncio.sleep(0.05)
+        yield json.dumps({"response": response.model_dump()}) + "\n"
+
+    return StreamingResponse(lines(), media_type="application/x-ndjson")
+
 @app.post("/generate_dungeon", response_model=DungeonData)
 async def generate_dungeon(request: DungeonGenerationRequest):
     """Generate procedural dungeon content"""
@@ -148,6 +245,94 @@ async def generate_encounter(request: Dict[str, Any]):
     except Exception as e:
         raise HTTPException(status_code=500, detail=f"Encounter generation failed: {str(e)}")
 
+@app.post("/rumor", response_model=RumorResponse)
+async def hear_rumor(request: RumorRequest):
+    """Gossip overheard at the inn, possibly pointing to a dungeon or a quest"""
+    try:
+        return await rumor_mill.rumor(
+            location=request.location,
+            party_level=request.party_level,
+            known_sites=request.known_sites,
+            known_npcs=request.known_npcs,
+            quests=request.quests
+        )
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Rumor failed: {str(e)}")
+
+@app.post("/hirelings", response_model=HirelingsResponse)
+async def find_hirelings(request: HirelingRequest):
+    """Hirelings looking for work, for the party to take on as retainers"""
+    try:
+        candidates = await hiring_hall.candidates(
+            location=request.location,
+            party_level=request.party_level,
+            count=request.count
+        )
+        return {"candidates": candidates}
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Hireling search failed: {str(e)}")
+
+@app.post("/recap", response_model=RecapResponse)
+async def write_recap(request: RecapRequest):
+    """Write a prose recap of a play session"""
+    try:
+        recap = await chronicler.recap(
+            party=request.party,
+            events=request.events,
+            dialogue=request.dialogue,
+            locations=request.locations
+        )
+        return {"recap": recap}
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Recap failed: {str(e)}")
+
+@app.post("/narrate", response_model=NarrationResponse)
+async def narrate(request: NarrationRequest):
+    """Describe a moment in the dungeon as the Dungeon Master would"""
+    try:
+        text = await narrator.narrate(
+            moment=request.moment,
+            subject=request.subject,
+            room=request.room,
+            party=request.party,
+            recent_actions=request.recent_actions
+        )
+        return {"text": text}
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=f"Narration failed: {str(e)}")
+
+@app.post("/campaign/{campaign_id}/join")
+async def join_campaign(campaign_id: str, request: CampaignJoinRequest):
+    """Join a play-by-post campaign and get its full state"""
+    return campaign_store.join(campaign_id, request.player)
+
+@app.get("/campaign/{campaign_id}/diffs")
+async def campaign_diffs(campaign_id: str, since: int = 0):
+    """Get campaign diffs newer than a revision"""
+    try:
+        return campaign_store.diffs_since(campaign_id, since)
+    except KeyError:
+        raise HTTPException(status_code=404, detail=f"Unknown campaign: {campaign_id}")
+
+@app.post("/campaign/{campaign_id}/turn")
+async def submit_campaign_turn(campaign_id: str, request: CampaignTurnRequest):
+    """Submit the active player's turn as a state diff"""
+    try:
+        return campaign_store.submit_turn(
+            campaign_id=campaign_id,
+            player=request.player,
+            base_revision=request.base_revision,
+            changes=request.changes
+        )
+    except KeyError:
+        raise HTTPException(status_code=404, detail=f"Unknown campaign: {campaign_id}")
+    except NotYourTurn as e:
+        raise HTTPException(status_code=403, detail=str(e))
+    except CampaignConflict as e:
+        raise HTTPException(status_code=409, detail=str(e))
+    except InvalidTurn as e:
+        raise HTTPException(status_code=400, detail=str(e))
+
 @app.get("/health")
 async def health_check():
     """Health check endpoint"""
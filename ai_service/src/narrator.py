@@ -0,0 +1,60 @@
+import random
+from typing import List, Optional
+
+
+class DungeonNarrator:
+    def __init__(self):
+        """Writes the Dungeon Master's boxed text for moments in the dungeon"""
+        self.room_openings = [
+            "You step into {subject}.",
+            "The passage gives way to {subject}.",
+            "Before you lies {subject}.",
+        ]
+        self.treasure_lines = [
+            "{subject}. Coin and trinket glint in the lamplight.",
+            "{subject}. Someone will miss this, though not for long.",
+            "{subject}. The weight of it is a comfort in a dark place.",
+        ]
+        self.killing_blows = [
+            "The blow lands true, and {subject} crumples to the flagstones.",
+            "{subject} reels, gasps, and falls still.",
+            "With a last cry {subject} goes down, and the echoes die away.",
+        ]
+        self.wounded_asides = [
+            "Blood is on more than one blade now.",
+            "Not everyone in the company is standing as straight as before.",
+        ]
+
+    async def narrate(
+        self,
+        moment: str,
+        subject: str,
+        room: Optional[str],
+        party: List[str],
+        recent_actions: List[str]
+    ) -> str:
+        """A few evocative sentences for entering a room, taking treasure or a killing blow"""
+        if moment == "enter_room":
+            lines = [random.choice(self.room_openings).format(subject=subject)]
+            if room and ": " in room:
+                lines.append(room.split(": ", 1)[1])
+        elif moment == "treasure":
+            lines = [random.choice(self.treasure_lines).format(subject=subject)]
+        else:
+            lines = [random.choice(self.killing_blows).format(subject=subject)]
+            if len(recent_actions) > 3:
+                lines.append("It was a hard-fought thing.")
+
+        if any(self._wounded(member) for member in party):
+            lines.append(random.choice(self.wounded_asides))
+
+        text = " ".join(lines)
+        return text[0].upper() + text[1:] if text else text
+
+    def _wounded(self, member: str) -> bool:
+        # Party members come as "Name, level 1 Fighter, 3/8 hp"
+        try:
+            current, maximum = member.rsplit(", ", 1)[1].split(" ")[0].split("/")
+            return int(current) * 2 < int(maximum)
+        except (IndexError, ValueError):
+            return False
@@ -105,8 +105,9 @@ class NPCAI:
         # Generate response based on NPC personality
         response = self._generate_response(npc_data, player_message, context, sentiment)
         
-        # Update NPC memory
+        # Update NPC memory, and how they feel about whoever spoke to them
         updated_memory = self._update_memory(npc_data, player_message, response)
+        updated_relationships = self._update_relationships(npc_data, player_name, sentiment)
         
         # Check if NPC should offer a quest
         quest_offered = self._check_quest_opportunity(npc_data, context, player_message)
@@ -115,7 +116,8 @@ class NPCAI:
         updated_npc_data = {
             **npc_data,
             "current_mood": updated_mood,
-            "memory": updated_memory
+            "memory": updated_memory,
+            "relationships": updated_relationships
         }
         
         return {
@@ -239,6 +241,11 @@ class NPCAI:
         if any(word in player_message.lower() for word in ["hello", "hi", "greetings", "hey"]):
             return self._generate_greeting(npc_type, mood, name)
         
+        # Asked for news, they pass on what they've heard going round town
+        gossip = [event[len("Rumor: "):] for event in context.get("recent_events", []) if event.startswith("Rumor: ")]
+        if gossip and any(word in player_message.lower() for word in ["news", "rumor", "gossip"]):
+            return f"Well, I did hear this: {random.choice(gossip)}"
+        
         # Question responses
         if "?" in player_message:
             return self._generate_answer(npc_type, template, player_message, context)
@@ -369,6 +376,25 @@ class NPCAI:
         
         return memory
 
+    def _update_relationships(
+        self,
+        npc_data: Dict[str, Any],
+        player_name: str,
+        sentiment: str
+    ) -> Dict[str, Dict[str, Any]]:
+        """Each conversation makes the NPC a little more familiar with the speaker, and
+        kind or harsh words move their trust"""
+        relationships = dict(npc_data.get("relationships", {}))
+        relationship = dict(relationships.get(player_name, {"trust": 0, "familiarity": 0, "last_interaction": ""}))
+
+        trust_change = {"positive": 1, "negative": -1}.get(sentiment, 0)
+        relationship["trust"] = max(-10, min(10, relationship.get("trust", 0) + trust_change))
+        relationship["familiarity"] = min(10, relationship.get("familiarity", 0) + 1)
+        relationship["last_interaction"] = sentiment
+        relationships[player_name] = relationship
+
+        return relationships
+
     def _check_quest_opportunity(
         self,
         npc_data: Dict[str, Any],
@@ -0,0 +1,94 @@
+import random
+from typing import Any, Dict, List
+
+
+class RumorMill:
+    def __init__(self):
+        """Makes up the gossip heard over a round of drinks at the inn"""
+        self.sites = [
+            ("the Barrow of Kings", "Ancient crypt of a forgotten king"),
+            ("Mordren's Spire", "Abandoned wizard's tower"),
+            ("the Warrens", "Goblin warren beneath the city"),
+            ("the Black Chapel", "Temple of an evil cult"),
+            ("the Glittering Caves", "Natural cave system with magical properties"),
+            ("Khazad Dun", "Underground dwarven city"),
+            ("Gallows Hill", "Haunted mansion on the hill"),
+            ("Elderglade", "Ruins of an elven settlement")
+        ]
+        self.site_rumors = [
+            "They say there's treasure still lying in {site}, for those who dare.",
+            "A shepherd saw lights moving at {site} three nights running.",
+            "My cousin went into {site} last spring. He never came out.",
+            "An old map to {site} sold at the market for ten gold pieces."
+        ]
+        self.known_site_rumors = [
+            "Folk say {site} has been quiet since the last party went in.",
+            "There's a new master at {site}, and he's worse than the last.",
+            "Goblins have been carrying loot out of {site} by night."
+        ]
+        self.npc_rumors = [
+            "{npc} has been asking after adventurers, quiet-like.",
+            "{npc} owes money all over town, they say.",
+            "{npc} was seen talking to a stranger in a black cloak."
+        ]
+        self.quest_rumors = [
+            "Whoever sees to '{quest}' will be well paid, or so I hear.",
+            "Two parties have already failed at '{quest}'.",
+            "'{quest}'? That's a trap, if you ask me."
+        ]
+        self.idle_rumors = [
+            "The miller's daughter is to marry a knight, or so she says.",
+            "The tax collector is coming early this year.",
+            "A dragon was seen flying north, though nobody sober saw it.",
+            "The temple pays well for the return of stolen relics."
+        ]
+
+    async def rumor(
+        self,
+        location: str,
+        party_level: int,
+        known_sites: List[str],
+        known_npcs: List[str] = None,
+        quests: List[str] = None
+    ) -> Dict[str, Any]:
+        """Gossip, half the time pointing to a site the party hasn't heard of and
+        otherwise about places, people and quests they know. A third of it is false."""
+        truthful = random.random() >= 1 / 3
+        unknown = [site for site in self.sites if site[0] not in known_sites]
+        if not unknown or random.random() < 0.5:
+            return {"text": self._talk(known_sites, known_npcs or [], quests or []), "truthful": truthful}
+
+        site, theme = random.choice(unknown)
+        level = max(1, party_level + random.choice([-1, 0, 0, 1]))
+        rumor = {
+            "text": random.choice(self.site_rumors).format(site=site),
+            "site": site,
+            "theme": theme,
+            "level": level,
+            "truthful": truthful
+        }
+        if random.random() < 0.5:
+            rumor["quest"] = {
+                "title": f"Explore {site}",
+                "description": f"Rumor in {location} has it that {site} is worth the trip.",
+                "objectives": [f"Find {site}", "Return with proof of what lies there"],
+                "reward": {
+                    "experience": 100 * level,
+                    "gold": 50 * level,
+                    "items": [],
+                    "reputation_change": 1
+                },
+                "difficulty": level,
+                "time_limit": None
+            }
+        return rumor
+
+    def _talk(self, known_sites: List[str], known_npcs: List[str], quests: List[str]) -> str:
+        """Idle gossip, about something the party knows when there's anything"""
+        subjects = [(self.known_site_rumors, "site", site) for site in known_sites]
+        subjects += [(self.npc_rumors, "npc", npc) for npc in known_npcs]
+        subjects += [(self.quest_rumors, "quest", quest) for quest in quests]
+        if not subjects or random.random() < 0.25:
+            return random.choice(self.idle_rumors)
+        templates, key, subject = random.choice(subjects)
+        return random.choice(templates).format(**{key: subject})
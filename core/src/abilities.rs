@@ -0,0 +1,174 @@
+use rand::Rng;
+
+use crate::ailments::Affliction;
+use crate::character::{Character, CharacterClass, Spell};
+use crate::combat::{AttackRoll, DamageType, EffectType, StatusEffect, WHOLE_FIGHT};
+use crate::dice;
+use crate::monster::EnemyData;
+use crate::saves::{SaveRoll, SavingThrow};
+use crate::spells::SpellCompendium;
+
+// What a monster's special abilities do, read from the free text of its stat block
+// (`"special_abilities": ["Breath Weapon (Fire)", "Spell: Sleep"]`). Some it uses on its
+// turn instead of attacking: a breath weapon, as hot as the monster has hit points left,
+// washes over the whole party (a save vs Dragon Breath halves it), three times a fight at
+// most, the first chance it gets and half the time after; and a spell-like ability casts
+// its spell once, half the time it has the chance. Others ride on its blows: paralysis
+// holds for 2d4 turns (elves shrug off the ghoul's touch), petrification turns to stone,
+// both unless saved against, and a monster that can swallow whole does so on a natural
+// 20, the victim held and digested a few hit points a round until it's killed. Poison
+// and disease are in `crate::ailments`, and regeneration in `EnemyData::innate_effects`.
+// Anything else is left as a description.
+
+pub const BREATHS_PER_FIGHT: u8 = 3;
+pub const DIGESTION_PER_ROUND: i16 = 3;
+// A held victim's turns are counted in rounds
+const ROUNDS_PER_TURN: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecialAbility {
+    Breath(DamageType),
+    Paralysis,
+    Petrification,
+    SwallowWhole,
+    SpellLike(String),
+    Regeneration,
+    Afflicts(Affliction),
+}
+
+// What a blow carrying paralysis, petrification or a swallowing did to its target
+#[derive(Debug, Clone)]
+pub enum AbilityOutcome {
+    Resisted(SaveRoll),
+    Immune,
+    Held(StatusEffect),
+    Petrified(SaveRoll),
+    Swallowed(Vec<StatusEffect>),
+}
+
+impl SpecialAbility {
+    // "Fire Breath", "Breath Weapon (Cold)", "Paralyzing touch", "Petrifying gaze",
+    // "Swallow Whole", "Spell: Sleep" or "Casts Sleep", "Regeneration", "Poison"
+    pub fn parse(text: &str) -> Option<SpecialAbility> {
+        let text = text.trim();
+        let lower = text.to_lowercase();
+        for prefix in ["spell:", "casts "] {
+            if let Some(spell) = text.get(prefix.len()..).filter(|_| lower.starts_with(prefix)) {
+                return Some(SpecialAbility::SpellLike(spell.trim().to_string()));
+            }
+        }
+        if lower.contains("breath") {
+            let kinds = [
+                ("cold", DamageType::Cold),
+                ("frost", DamageType::Cold),
+                ("lightning", DamageType::Lightning),
+                ("acid", DamageType::Acid),
+                ("poison", DamageType::Poison),
+                ("gas", DamageType::Poison),
+            ];
+            let kind = kinds.into_iter().find(|(word, _)| lower.contains(word));
+            return Some(SpecialAbility::Breath(kind.map_or(DamageType::Fire, |(_, kind)| kind)));
+        }
+        if lower.starts_with("paraly") {
+            return Some(SpecialAbility::Paralysis);
+        }
+        if lower.starts_with("petrif") {
+            return Some(SpecialAbility::Petrification);
+        }
+        if lower.starts_with("swallow") {
+            return Some(SpecialAbility::SwallowWhole);
+        }
+        if lower == "regeneration" {
+            return Some(SpecialAbility::Regeneration);
+        }
+        Affliction::from_ability(&lower).map(SpecialAbility::Afflicts)
+    }
+
+    // Whether the ability goes with a blow that landed, rather than being used on its own
+    pub fn rides_on(&self, roll: &AttackRoll) -> bool {
+        match self {
+            SpecialAbility::Paralysis | SpecialAbility::Petrification => roll.hit,
+            SpecialAbility::SwallowWhole => roll.is_critical(),
+            _ => false,
+        }
+    }
+}
+
+impl EnemyData {
+    pub fn abilities(&self) -> Vec<SpecialAbility> {
+        self.special_abilities.iter().filter_map(|ability| SpecialAbility::parse(ability)).collect()
+    }
+
+    // The spells its spell-like abilities cast, as the compendium has them
+    pub fn innate_spells(&self) -> Vec<Spell> {
+        self.abilities()
+            .into_iter()
+            .filter_map(|ability| match ability {
+                SpecialAbility::SpellLike(spell) => SpellCompendium::builtin().get(&spell).cloned(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The ability the monster uses on its turn instead of attacking, if any; `breaths` is
+    // how many times it has breathed this fight and `caster` what it fights as, with any
+    // spells it has left
+    pub fn choose_ability(&self, breaths: u8, caster: &Character, rng: &mut impl Rng) -> Option<SpecialAbility> {
+        let ready = caster.ready_spells();
+        self.abilities().into_iter().find(|ability| match ability {
+            SpecialAbility::Breath(_) => breaths == 0 || (breaths < BREATHS_PER_FIGHT && rng.gen_bool(0.5)),
+            SpecialAbility::SpellLike(spell) => {
+                ready.iter().any(|(name, _)| name.eq_ignore_ascii_case(spell)) && rng.gen_bool(0.5)
+            }
+            _ => false,
+        })
+    }
+}
+
+impl Character {
+    // What a blow carrying `ability` from `monster` does to the character; anything but
+    // petrification is left for the caller to put on them
+    pub fn suffer_ability(&mut self, ability: &SpecialAbility, monster: &str, rng: &mut impl Rng) -> AbilityOutcome {
+        if *ability == SpecialAbility::SwallowWhole {
+            let held = StatusEffect {
+                name: "Swallowed".to_string(),
+                duration: WHOLE_FIGHT,
+                effect_type: EffectType::Stun,
+                magnitude: 0,
+            };
+            let digested = StatusEffect {
+                name: "Digestion".to_string(),
+                duration: WHOLE_FIGHT,
+                effect_type: EffectType::Damage,
+                magnitude: DIGESTION_PER_ROUND,
+            };
+            return AbilityOutcome::Swallowed(vec![held, digested]);
+        }
+        let ghoul = monster.to_lowercase().contains("ghoul");
+        if *ability == SpecialAbility::Paralysis && self.class == CharacterClass::Elf && ghoul {
+            return AbilityOutcome::Immune;
+        }
+        let save = self.roll_save(SavingThrow::ParalysisPetrify, rng);
+        if save.success {
+            return AbilityOutcome::Resisted(save);
+        }
+        if *ability == SpecialAbility::Petrification {
+            self.hit_points.current = 0;
+            return AbilityOutcome::Petrified(save);
+        }
+        AbilityOutcome::Held(StatusEffect {
+            name: "Paralyzed".to_string(),
+            duration: dice::roll(rng, 2, 4) as u8 * ROUNDS_PER_TURN,
+            effect_type: EffectType::Stun,
+            magnitude: 0,
+        })
+    }
+
+    // A breath weapon's damage, as the breather has hit points left, and the save that
+    // halves it
+    pub fn breathed_on(&self, breather: &Character, rng: &mut impl Rng) -> (i16, SaveRoll) {
+        let damage = breather.hit_points.current.max(1);
+        let save = self.roll_save(SavingThrow::Breath, rng);
+        (if save.success { (damage / 2).max(1) } else { damage }, save)
+    }
+}
@@ -0,0 +1,258 @@
+use rand::Rng;
+
+use crate::character::{Character, CharacterClass, Spell, SpellCaster};
+use crate::combat::level_attack_bonus;
+use crate::dice;
+use crate::ruleset::Ruleset;
+use crate::saves::SavingThrow;
+use crate::skills::ThiefSkill;
+use crate::spells::SpellCompendium;
+
+// Going up a level. Experience only makes a character due one (`level_up_due`, by the
+// ruleset's experience tables, which also set each class's level limit); the game then
+// sets out what the next level brings as an `Advancement` and applies it once the
+// player has seen it. Up to 9th level the class's hit die is rolled for the new hit
+// points, plus Constitution and at least 1; past it a set number is added, without
+// Constitution. Characters made at a higher level (retainers, the simulator) go up with
+// `level_up`, which doesn't roll.
+//
+// A level that opens a new spell level brings its spells: a cleric can call on every
+// prayer of it, and a magic-user or elf writes one spell of it into their book, chosen
+// from those they don't know yet (`level_up` takes the first).
+
+// Hit points a level past 9th, by class
+const FIGHTER_PAST_NINTH: i16 = 2;
+const CLERIC_PAST_NINTH: i16 = 1;
+const MAGIC_USER_PAST_NINTH: i16 = 1;
+const THIEF_PAST_NINTH: i16 = 2;
+const DWARF_PAST_NINTH: i16 = 3;
+const ELF_PAST_NINTH: i16 = 2;
+const HALFLING_PAST_NINTH: i16 = 1;
+// The last level the hit die is rolled for
+const LAST_ROLLED_LEVEL: u8 = 9;
+
+// The next level, before it's taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advancement {
+    // The level being reached
+    pub level: u8,
+    // Sides of the hit die, or None past 9th level
+    pub hit_die: Option<u8>,
+    // Added to the die (Constitution), or the hit points themselves past 9th
+    pub bonus: i16,
+    // The die as it came up, once it's rolled
+    pub roll: Option<i16>,
+    // What else comes with the level, a line each
+    pub gains: Vec<String>,
+    // The spell level it opens, if it does
+    pub spell_level: Option<u8>,
+    // For a magic-user or elf, the spells of it to choose one from, and the one chosen
+    pub spell_choices: Vec<String>,
+    pub chosen: usize,
+}
+
+impl CharacterClass {
+    pub fn hit_die(&self) -> u8 {
+        match self {
+            CharacterClass::Fighter | CharacterClass::Dwarf => 8,
+            CharacterClass::Cleric | CharacterClass::Elf | CharacterClass::Halfling => 6,
+            CharacterClass::MagicUser | CharacterClass::Thief => 4,
+        }
+    }
+
+    fn hit_points_past_ninth(&self) -> i16 {
+        match self {
+            CharacterClass::Fighter => FIGHTER_PAST_NINTH,
+            CharacterClass::Cleric => CLERIC_PAST_NINTH,
+            CharacterClass::MagicUser => MAGIC_USER_PAST_NINTH,
+            CharacterClass::Thief => THIEF_PAST_NINTH,
+            CharacterClass::Dwarf => DWARF_PAST_NINTH,
+            CharacterClass::Elf => ELF_PAST_NINTH,
+            CharacterClass::Halfling => HALFLING_PAST_NINTH,
+        }
+    }
+}
+
+impl Advancement {
+    // Rolls the hit die if it hasn't been; the die as it came up
+    pub fn roll(&mut self, rng: &mut impl Rng) -> Option<i16> {
+        if let (Some(sides), None) = (self.hit_die, self.roll) {
+            self.roll = Some(dice::roll(rng, 1, sides));
+        }
+        self.roll
+    }
+
+    // The hit points gained, once there's nothing left to roll
+    pub fn hit_points(&self) -> Option<i16> {
+        match (self.hit_die, self.roll) {
+            (None, _) => Some(self.bonus),
+            (Some(_), Some(roll)) => Some((roll + self.bonus).max(1)),
+            (Some(_), None) => None,
+        }
+    }
+
+    // "1d8+1", or "+2" past 9th level
+    pub fn describe_hit_die(&self) -> String {
+        match self.hit_die {
+            Some(sides) if self.bonus != 0 => format!("1d{}{:+}", sides, self.bonus),
+            Some(sides) => format!("1d{}", sides),
+            None => format!("{:+}", self.bonus),
+        }
+    }
+}
+
+impl Character {
+    // The spell level the next level opens up, if it does
+    pub fn new_spell_level(&self) -> Option<u8> {
+        let (now, next) = (self.class.spell_slots(self.level).len(), self.class.spell_slots(self.level + 1).len());
+        (next > now).then_some(next as u8)
+    }
+
+    // The spells of `spell_level` on the character's list that they don't know yet, in
+    // compendium order
+    pub fn unknown_spells<'a>(&self, compendium: &'a SpellCompendium, spell_level: u8) -> Vec<&'a Spell> {
+        compendium
+            .of_level(spell_level)
+            .filter(|spell| spell.castable_by(&self.class))
+            .filter(|spell| !self.spells.iter().any(|known| known.name.eq_ignore_ascii_case(&spell.name)))
+            .collect()
+    }
+
+    // Learns what a new spell level brings: every prayer of it for a cleric, and `choice`
+    // (or else the first spell they could) for a magic-user or elf. The names learned.
+    pub fn learn_spell_level(
+        &mut self,
+        spell_level: u8,
+        compendium: &SpellCompendium,
+        choice: Option<&str>,
+    ) -> Vec<String> {
+        let unknown = self.unknown_spells(compendium, spell_level);
+        let learned: Vec<Spell> = match self.class.spell_caster() {
+            Some(SpellCaster::Divine) => unknown.into_iter().cloned().collect(),
+            Some(SpellCaster::Arcane) => {
+                let chosen = choice.and_then(|name| unknown.iter().find(|spell| spell.name.eq_ignore_ascii_case(name)));
+                chosen.or(unknown.first()).map(|spell| (*spell).clone()).into_iter().collect()
+            }
+            None => Vec::new(),
+        };
+        let names = learned.iter().map(|spell| spell.name.clone()).collect();
+        self.spells.extend(learned);
+        names
+    }
+
+    pub fn level_up_due(&self, ruleset: &Ruleset) -> bool {
+        self.is_alive() && self.get_xp_for_next_level(ruleset).is_some_and(|needed| self.experience >= needed)
+    }
+
+    // What the next level brings: its hit die, and the spells, saving throws, attacks and
+    // skills that improve with it
+    pub fn advancement(&self, ruleset: &Ruleset, compendium: &SpellCompendium) -> Advancement {
+        let mut next = self.clone();
+        next.level += 1;
+        let (hit_die, bonus) = if next.level <= LAST_ROLLED_LEVEL {
+            (Some(self.class.hit_die()), Character::get_constitution_modifier(self.stats.constitution))
+        } else {
+            (None, self.class.hit_points_past_ninth())
+        };
+
+        let mut gains = Vec::new();
+        let (before, after) = (level_attack_bonus(ruleset, self), level_attack_bonus(ruleset, &next));
+        if after != before {
+            gains.push(format!("Attack bonus {:+} (was {:+})", after, before));
+        }
+        let saves: Vec<String> = [
+            SavingThrow::DeathPoison,
+            SavingThrow::Wands,
+            SavingThrow::ParalysisPetrify,
+            SavingThrow::Breath,
+            SavingThrow::Spells,
+        ]
+        .into_iter()
+        .filter(|&save| next.saving_throw(save) != self.saving_throw(save))
+        .map(|save| format!("{} {} (was {})", save.name(), next.saving_throw(save), self.saving_throw(save)))
+        .collect();
+        if !saves.is_empty() {
+            gains.push(format!("Saving throws: {}", saves.join(", ")));
+        }
+        let (slots, slots_before) = (self.class.spell_slots(next.level), self.class.spell_slots(self.level));
+        if slots != slots_before {
+            gains.push(format!("Spells a day: {} (was {})", describe_slots(slots), describe_slots(slots_before)));
+        }
+        if self.class == CharacterClass::Thief {
+            let skills: Vec<String> = ThiefSkill::ALL
+                .into_iter()
+                .filter_map(|skill| {
+                    let (now, was) = (next.skill_chance(skill)?, self.skill_chance(skill)?);
+                    (now != was).then(|| format!("{} {}% (was {}%)", skill.name(), now, was))
+                })
+                .collect();
+            if !skills.is_empty() {
+                gains.push(format!("Thief skills: {}", skills.join(", ")));
+            }
+        }
+        if self.class == CharacterClass::Fighter {
+            gains.push(format!("{} attacks a round against foes of 1 Hit Die or less", next.level));
+        }
+
+        let spell_level = self.new_spell_level();
+        let unknown = spell_level.map(|level| self.unknown_spells(compendium, level)).unwrap_or_default();
+        let names: Vec<String> = unknown.iter().map(|spell| spell.name.clone()).collect();
+        let spell_choices = match self.class.spell_caster() {
+            Some(SpellCaster::Arcane) => names,
+            _ => {
+                if !names.is_empty() {
+                    gains.push(format!("New prayers: {}", names.join(", ")));
+                }
+                Vec::new()
+            }
+        };
+
+        Advancement {
+            level: next.level,
+            hit_die,
+            bonus,
+            roll: None,
+            gains,
+            spell_level,
+            spell_choices,
+            chosen: 0,
+        }
+    }
+
+    // Takes the level, rolling the hit die if the player didn't, and learns its spells.
+    // The names of the spells learned.
+    pub fn advance(
+        &mut self,
+        advancement: &mut Advancement,
+        compendium: &SpellCompendium,
+        rng: &mut impl Rng,
+    ) -> Vec<String> {
+        advancement.roll(rng);
+        let hit_points = advancement.hit_points().unwrap_or(1);
+        self.level = advancement.level;
+        self.hit_points.maximum += hit_points;
+        self.hit_points.current += hit_points;
+
+        let Some(spell_level) = advancement.spell_level else {
+            return Vec::new();
+        };
+        let choice = advancement.spell_choices.get(advancement.chosen).map(String::as_str);
+        self.learn_spell_level(spell_level, compendium, choice)
+    }
+}
+
+// "2 1st, 1 2nd", or "none"
+fn describe_slots(slots: &[u8]) -> String {
+    let ordinals = ["1st", "2nd", "3rd", "4th", "5th", "6th", "7th"];
+    let described: Vec<String> = slots
+        .iter()
+        .zip(ordinals)
+        .filter(|(count, _)| **count > 0)
+        .map(|(count, ordinal)| format!("{} {}", count, ordinal))
+        .collect();
+    if described.is_empty() {
+        "none".to_string()
+    } else {
+        described.join(", ")
+    }
+}
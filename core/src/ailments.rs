@@ -0,0 +1,203 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::abilities::SpecialAbility;
+use crate::character::Character;
+use crate::combat::{EffectType, StatusEffect};
+use crate::dice;
+use crate::monster::EnemyData;
+use crate::saves::{SaveRoll, SavingThrow};
+use crate::traps::POISON_DAMAGE_PER_ROUND;
+
+// Poisons and diseases, as monsters pass them on. A monster's special abilities say
+// which: "Poison" is the deadly kind, save vs Death Ray or Poison or die on the spot,
+// while "Venom" sickens for a few rounds of damage (as a poison needle trap does) that
+// runs its course outside a fight. Neutralize poison stops it.
+//
+// Diseases are status effects that last until cured, by the Cure Disease spell or the
+// temple, and get worse as the days go by rather than the rounds. A mummy's touch
+// ("Mummy Rot") rots the flesh: no wound heals, by rest or by magic, and a hit point is
+// lost every day until the rot is cured or the victim dies of it. A lycanthrope's bite
+// ("Lycanthropy") infects anyone it leaves at half their hit points or less, and 2d12
+// days later the curse takes hold and the victim runs off to join their new kind.
+
+// Sickening venom: a hit point a round for this many rounds
+pub const VENOM_ROUNDS: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affliction {
+    DeadlyPoison,
+    Venom,
+    Disease(Disease),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Disease {
+    MummyRot,
+    Lycanthropy,
+}
+
+// What a spell or the temple's priests can cure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ailment {
+    Poison,
+    Disease,
+}
+
+// How a poisoned character fared
+#[derive(Debug, Clone)]
+pub enum Poisoning {
+    Resisted(SaveRoll),
+    Killed(SaveRoll),
+    Sickened(StatusEffect),
+}
+
+// What a day of a disease did to its victim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiseaseDay {
+    // Still waiting to take hold
+    Incubating,
+    Wasted(i16),
+    Died,
+    TookHold,
+}
+
+impl Affliction {
+    pub fn from_ability(ability: &str) -> Option<Affliction> {
+        match ability.to_lowercase().as_str() {
+            "poison" => Some(Affliction::DeadlyPoison),
+            "venom" => Some(Affliction::Venom),
+            "mummy rot" => Some(Affliction::Disease(Disease::MummyRot)),
+            "lycanthropy" => Some(Affliction::Disease(Disease::Lycanthropy)),
+            _ => None,
+        }
+    }
+}
+
+impl EnemyData {
+    // What the monster's attacks pass on, from its special abilities
+    pub fn afflictions(&self) -> Vec<Affliction> {
+        self.abilities()
+            .into_iter()
+            .filter_map(|ability| match ability {
+                SpecialAbility::Afflicts(affliction) => Some(affliction),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Disease {
+    pub const ALL: [Disease; 2] = [Disease::MummyRot, Disease::Lycanthropy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Disease::MummyRot => "Mummy rot",
+            Disease::Lycanthropy => "Lycanthropy",
+        }
+    }
+
+    // The disease a status effect is, if it's one
+    pub fn of(effect: &StatusEffect) -> Option<Disease> {
+        if !matches!(effect.effect_type, EffectType::Disease) {
+            return None;
+        }
+        Disease::ALL.into_iter().find(|disease| disease.name() == effect.name)
+    }
+
+    // The disease as caught; its duration is the days left before it takes hold, for
+    // those that do
+    pub fn infect(self, rng: &mut impl Rng) -> StatusEffect {
+        let days = match self {
+            Disease::MummyRot => 0,
+            Disease::Lycanthropy => dice::roll(rng, 2, 12) as u8,
+        };
+        StatusEffect {
+            name: self.name().to_string(),
+            duration: days,
+            effect_type: EffectType::Disease,
+            magnitude: 0,
+        }
+    }
+
+    // Whether the attack that just landed passes the disease on to `victim`
+    pub fn passes_to(self, victim: &Character) -> bool {
+        match self {
+            Disease::MummyRot => victim.is_alive(),
+            Disease::Lycanthropy => victim.is_alive() && victim.hit_points.current * 2 <= victim.hit_points.maximum,
+        }
+    }
+
+    pub fn describe(&self, effect: &StatusEffect) -> String {
+        match self {
+            Disease::MummyRot => "No healing; loses a hit point a day until cured".to_string(),
+            Disease::Lycanthropy if effect.duration == 1 => "Takes hold tomorrow unless cured".to_string(),
+            Disease::Lycanthropy => format!("Takes hold in {} days unless cured", effect.duration),
+        }
+    }
+}
+
+impl Ailment {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ailment::Poison => "poison",
+            Ailment::Disease => "disease",
+        }
+    }
+
+    pub fn afflicts(self, effect: &StatusEffect) -> bool {
+        match self {
+            Ailment::Poison => matches!(effect.effect_type, EffectType::Poison),
+            Ailment::Disease => matches!(effect.effect_type, EffectType::Disease),
+        }
+    }
+
+    // Takes the ailment off; true if there was any to take
+    pub fn cure(self, effects: &mut Vec<StatusEffect>) -> bool {
+        let before = effects.len();
+        effects.retain(|effect| !self.afflicts(effect));
+        effects.len() < before
+    }
+}
+
+// Wounds don't close while any of the effects is mummy rot
+pub fn blocks_healing(effects: &[StatusEffect]) -> bool {
+    effects.iter().any(|effect| Disease::of(effect) == Some(Disease::MummyRot))
+}
+
+impl Character {
+    // Deadly poison kills outright on a failed save; venom leaves a sickness for the
+    // caller to put on the character
+    pub fn poison(&mut self, deadly: bool, rng: &mut impl Rng) -> Poisoning {
+        let save = self.roll_save(SavingThrow::DeathPoison, rng);
+        if save.success {
+            return Poisoning::Resisted(save);
+        }
+        if deadly {
+            self.hit_points.current = 0;
+            return Poisoning::Killed(save);
+        }
+        Poisoning::Sickened(StatusEffect {
+            name: "Venom".to_string(),
+            duration: VENOM_ROUNDS,
+            effect_type: EffectType::Poison,
+            magnitude: POISON_DAMAGE_PER_ROUND,
+        })
+    }
+
+    // A day of the disease `effect`
+    pub fn suffer_disease_day(&mut self, effect: &mut StatusEffect) -> DiseaseDay {
+        match Disease::of(effect) {
+            Some(Disease::MummyRot) => {
+                self.take_damage(1);
+                if self.is_alive() { DiseaseDay::Wasted(1) } else { DiseaseDay::Died }
+            }
+            Some(Disease::Lycanthropy) => {
+                effect.duration = effect.duration.saturating_sub(1);
+                if effect.duration == 0 { DiseaseDay::TookHold } else { DiseaseDay::Incubating }
+            }
+            None => DiseaseDay::Incubating,
+        }
+    }
+}
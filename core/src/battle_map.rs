@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fov::{line, line_of_sight};
+
+// Optional tactical positioning. Combatants stand on a grid of 5' squares, move a number
+// of squares each turn set by their movement rate, strike in melee only at squares next
+// to them and shoot only within their weapon's range, with nobody standing in the way.
+// Area spells lay a template over the grid and catch everyone standing in it, whichever
+// side they're on.
+
+pub const FEET_PER_SQUARE: u16 = 5;
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct GridPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattleMap {
+    pub width: i32,
+    pub height: i32,
+}
+
+// The shape of an area spell's template: a burst around the square it's aimed at, or a
+// cone or line spreading from the caster toward it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AreaShape {
+    #[default]
+    Burst,
+    Cone,
+    Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeBand {
+    Short,
+    Medium,
+    Long,
+}
+
+impl GridPosition {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    // In squares; a diagonal step counts as one
+    pub fn distance(&self, other: &GridPosition) -> u32 {
+        (self.x - other.x).unsigned_abs().max((self.y - other.y).unsigned_abs())
+    }
+
+    pub fn distance_feet(&self, other: &GridPosition) -> u16 {
+        (self.distance(other) as u16).saturating_mul(FEET_PER_SQUARE)
+    }
+
+    pub fn is_adjacent(&self, other: &GridPosition) -> bool {
+        self.distance(other) == 1
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32) -> GridPosition {
+        GridPosition::new(self.x + dx, self.y + dy)
+    }
+
+    // One square closer to `target`, diagonally if need be
+    pub fn step_toward(&self, target: &GridPosition) -> GridPosition {
+        self.offset((target.x - self.x).signum(), (target.y - self.y).signum())
+    }
+}
+
+impl Default for BattleMap {
+    fn default() -> Self {
+        Self { width: 16, height: 10 }
+    }
+}
+
+impl BattleMap {
+    pub fn contains(&self, position: &GridPosition) -> bool {
+        (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y)
+    }
+
+    // The party lines up two squares in from the left edge and the enemies two in from
+    // the right, a square apart, wrapping into further ranks when a line is full
+    pub fn deploy(&self, party: usize, enemies: usize) -> (Vec<GridPosition>, Vec<GridPosition>) {
+        let per_rank = ((self.height + 1) / 2).max(1) as usize;
+        let line = |count: usize, x: i32, step: i32| -> Vec<GridPosition> {
+            (0..count)
+                .map(|index| {
+                    let rank = (index / per_rank) as i32;
+                    let file = (index % per_rank) as i32;
+                    GridPosition::new((x + rank * step).clamp(0, self.width - 1), file * 2)
+                })
+                .collect()
+        };
+        (line(party, 1, -1), line(enemies, self.width - 2, 1))
+    }
+
+    // One character per square, '.' where nobody stands
+    pub fn render(&self, markers: &[(GridPosition, char)]) -> String {
+        let mut rows = Vec::with_capacity(self.height as usize);
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| {
+                    markers
+                        .iter()
+                        .find(|(position, _)| position.x == x && position.y == y)
+                        .map_or('.', |(_, marker)| *marker)
+                })
+                .collect();
+            rows.push(row);
+        }
+        rows.join("\n")
+    }
+}
+
+impl AreaShape {
+    pub fn is_burst(&self) -> bool {
+        *self == AreaShape::Burst
+    }
+
+    // How the size in feet reads: "20' radius", "15' cone"
+    pub fn name(self) -> &'static str {
+        match self {
+            AreaShape::Burst => "radius",
+            AreaShape::Cone => "cone",
+            AreaShape::Line => "line",
+        }
+    }
+}
+
+// The squares an area of `shape`, `feet` across (the radius of a burst, the length of a
+// cone or line), covers when cast from `origin` at `aim`. A cone widens by a square on
+// each side for every two it goes out; cones and lines don't include the caster's own
+// square, and have no direction to go in when aimed at it.
+pub fn area_squares(shape: AreaShape, feet: u16, origin: GridPosition, aim: GridPosition) -> Vec<GridPosition> {
+    let reach = (feet / FEET_PER_SQUARE).max(1) as i32;
+    let (dx, dy) = (aim.x - origin.x, aim.y - origin.y);
+    if shape != AreaShape::Burst && dx == 0 && dy == 0 {
+        return Vec::new();
+    }
+    match shape {
+        AreaShape::Burst => (-reach..=reach)
+            .flat_map(|y| (-reach..=reach).map(move |x| aim.offset(x, y)))
+            .collect(),
+        AreaShape::Cone => (-reach..=reach)
+            .flat_map(|y| (-reach..=reach).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let along = x * dx + y * dy;
+                let across = x * dy - y * dx;
+                along > 0 && 2 * across.abs() <= along && origin.distance(&origin.offset(x, y)) <= reach as u32
+            })
+            .map(|(x, y)| origin.offset(x, y))
+            .collect(),
+        AreaShape::Line => {
+            // Carried on past the aim to its full length
+            let steps = (reach as u32).div_ceil(origin.distance(&aim)) as i32;
+            let far = origin.offset(dx * steps, dy * steps);
+            line(origin, far).into_iter().skip(1).take(reach as usize).collect()
+        }
+    }
+}
+
+// Squares a combatant can cover in one round at a movement rate in feet per round
+pub fn squares_per_round(movement_rate: u16) -> u16 {
+    movement_rate / FEET_PER_SQUARE
+}
+
+// B/X missile ranges in feet: short, medium and long
+pub fn missile_range(weapon: &str) -> Option<[u16; 3]> {
+    match weapon.to_lowercase().as_str() {
+        "bow" | "short bow" => Some([50, 100, 150]),
+        "long bow" | "longbow" => Some([70, 140, 210]),
+        "crossbow" | "light crossbow" => Some([60, 120, 180]),
+        "sling" => Some([40, 80, 160]),
+        _ => None,
+    }
+}
+
+impl RangeBand {
+    // B/X: +1 to hit at short range, -1 at long
+    pub fn attack_modifier(&self) -> i16 {
+        match self {
+            RangeBand::Short => 1,
+            RangeBand::Medium => 0,
+            RangeBand::Long => -1,
+        }
+    }
+}
+
+// Whether an attack with `weapon` can reach from one square to another, and the range
+// modifier to apply. Anything without a missile range is a melee attack.
+pub fn attack_reach(weapon: Option<&str>, from: &GridPosition, to: &GridPosition) -> Result<i16, String> {
+    let Some([short, medium, long]) = weapon.and_then(missile_range) else {
+        return if from.is_adjacent(to) {
+            Ok(0)
+        } else {
+            Err(format!("out of reach ({}' away)", from.distance_feet(to)))
+        };
+    };
+
+    let distance = from.distance_feet(to);
+    let band = match distance {
+        d if d <= short => RangeBand::Short,
+        d if d <= medium => RangeBand::Medium,
+        d if d <= long => RangeBand::Long,
+        _ => return Err(format!("out of range ({}' away)", distance)),
+    };
+    Ok(band.attack_modifier())
+}
+
+// Like `attack_reach`, but a missile also needs a clear line to the target: it can't be
+// shot through anyone standing on the squares in between (`occupied`)
+pub fn shot_reach(
+    weapon: Option<&str>,
+    from: &GridPosition,
+    to: &GridPosition,
+    occupied: &[GridPosition],
+) -> Result<i16, String> {
+    let modifier = attack_reach(weapon, from, to)?;
+    if !from.is_adjacent(to) && !line_of_sight(*from, *to, |square| occupied.contains(square)) {
+        return Err("no clear line of sight".to_string());
+    }
+    Ok(modifier)
+}
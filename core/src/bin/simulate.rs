@@ -0,0 +1,153 @@
+use std::process::ExitCode;
+
+use old_school_core::character::CharacterClass;
+use old_school_core::dungeon::DungeonData;
+use old_school_core::monster::builtin_monster;
+use old_school_core::rng::GameRng;
+use old_school_core::ruleset::{AttackMethod, Ruleset};
+use old_school_core::simulation::{
+    create_party, parse_class, simulate_delve, simulate_encounter, SimCombatant, SimulationReport,
+    DEFAULT_MAX_ROUNDS,
+};
+
+const USAGE: &str = "\
+Usage: simulate [options]
+
+Runs encounters or whole dungeon delves headlessly and prints aggregate results.
+
+Options:
+  --trials <n>        Number of simulations to run (default 1000)
+  --party <classes>   Comma-separated party classes (default fighter,cleric,magic-user,thief)
+  --level <n>         Party level (default 1)
+  --monster <id>      Built-in monster to fight: goblin, orc, skeleton, troll (default goblin)
+  --count <n>         Number of monsters in the encounter (default 3)
+  --dungeon <file>    Simulate a full delve through a DungeonData JSON file instead
+  --max-rounds <n>    Round limit per encounter (default 50)
+  --seed <n>          Seed the dice so a run can be repeated exactly
+  --attacks <method>  To-hit rules: matrix (B/X attack tables, default) or level (+1 per 3 levels)
+  --help              Show this message";
+
+struct Options {
+    trials: u32,
+    party: Vec<CharacterClass>,
+    level: u8,
+    monster: String,
+    count: usize,
+    dungeon: Option<String>,
+    max_rounds: u32,
+    seed: Option<u64>,
+    ruleset: Ruleset,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            trials: 1000,
+            party: vec![
+                CharacterClass::Fighter,
+                CharacterClass::Cleric,
+                CharacterClass::MagicUser,
+                CharacterClass::Thief,
+            ],
+            level: 1,
+            monster: "goblin".to_string(),
+            count: 3,
+            dungeon: None,
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            seed: None,
+            ruleset: Ruleset::default(),
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" {
+            return Err(String::new());
+        }
+
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--trials" => options.trials = value.parse().map_err(|_| format!("invalid trial count: {}", value))?,
+            "--level" => options.level = value.parse().map_err(|_| format!("invalid level: {}", value))?,
+            "--count" => options.count = value.parse().map_err(|_| format!("invalid count: {}", value))?,
+            "--max-rounds" => {
+                options.max_rounds = value.parse().map_err(|_| format!("invalid round limit: {}", value))?
+            }
+            "--seed" => options.seed = Some(value.parse().map_err(|_| format!("invalid seed: {}", value))?),
+            "--attacks" => {
+                options.ruleset.attack_method =
+                    AttackMethod::parse(&value).ok_or_else(|| format!("unknown attack method: {}", value))?
+            }
+            "--monster" => options.monster = value,
+            "--dungeon" => options.dungeon = Some(value),
+            "--party" => {
+                options.party = value
+                    .split(',')
+                    .map(|name| parse_class(name.trim()).ok_or_else(|| format!("unknown class: {}", name)))
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(format!("unknown option: {}", flag)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<SimulationReport, String> {
+    let mut report = SimulationReport::new(options.party.len());
+    let mut rng = options.seed.map(GameRng::from_seed).unwrap_or_default();
+    println!("Seed {}", rng.seed());
+
+    if let Some(path) = &options.dungeon {
+        let contents = std::fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        let dungeon: DungeonData =
+            serde_json::from_str(&contents).map_err(|e| format!("invalid dungeon file {}: {}", path, e))?;
+
+        println!("Simulating {} delves through {}", options.trials, dungeon.name);
+        for _ in 0..options.trials {
+            let mut party = create_party(&options.party, options.level, &options.ruleset, &mut rng);
+            report.record_delve(&simulate_delve(&mut party, &dungeon, options.max_rounds, &options.ruleset, &mut rng));
+        }
+    } else {
+        let monster = builtin_monster(&options.monster).ok_or_else(|| format!("unknown monster: {}", options.monster))?;
+
+        println!(
+            "Simulating {} encounters: level {} party vs {} x {}",
+            options.trials, options.level, options.count, monster.name
+        );
+        for _ in 0..options.trials {
+            let mut party = create_party(&options.party, options.level, &options.ruleset, &mut rng);
+            let mut enemies: Vec<SimCombatant> = (0..options.count).map(|_| SimCombatant::monster(&monster)).collect();
+            report.record_encounter(&simulate_encounter(&mut party, &mut enemies, options.max_rounds, &options.ruleset, &mut rng));
+        }
+    }
+
+    Ok(report)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("error: {}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            return if message.is_empty() { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+        }
+    };
+
+    match run(&options) {
+        Ok(report) => {
+            println!("{}", report);
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::dice::DiceExpr;
+use crate::item::{Item, ItemType};
+use crate::treasure::treasure_item;
+
+// The item catalog: every kind of item there is, by id, with its damage dice, armour,
+// weight and price. Shops stock from it, treasure and quest rewards are made from it and
+// retainers are kitted out from it; the rules read a weapon's dice and a suit of
+// armour's protection from it when the item itself doesn't say. Its entries are in
+// core/data/core.items.json, built in here for the game, the terminal game and the
+// simulator alike; the game adds the *.items.json files of mods over them.
+
+const BUILTIN_ITEMS: &str = include_str!("../data/core.items.json");
+
+// An item's key in the catalog, always in lower case
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemId(String);
+
+impl ItemId {
+    pub fn new(id: &str) -> Self {
+        ItemId(id.to_lowercase())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for ItemId {
+    fn from(id: &str) -> Self {
+        ItemId::new(id)
+    }
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemCatalog {
+    pub items: HashMap<ItemId, Item>,
+}
+
+impl ItemCatalog {
+    pub fn builtin() -> &'static ItemCatalog {
+        static BUILTIN: OnceLock<ItemCatalog> = OnceLock::new();
+        BUILTIN.get_or_init(|| serde_json::from_str(BUILTIN_ITEMS).expect("core.items.json is a valid item catalog"))
+    }
+
+    pub fn get(&self, id: impl Into<ItemId>) -> Option<&Item> {
+        self.items.get(&id.into())
+    }
+
+    // A new one of the item, to hand out
+    pub fn create(&self, id: impl Into<ItemId>) -> Option<Item> {
+        self.get(id).cloned()
+    }
+
+    // Ids in order, so anything going through them goes the same way every run
+    pub fn ids(&self) -> Vec<&ItemId> {
+        let mut ids: Vec<&ItemId> = self.items.keys().collect();
+        ids.sort();
+        ids
+    }
+
+    // Every item, in id order
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.ids().into_iter().map(|id| &self.items[id])
+    }
+
+    // Another catalog's items added to these, replacing any with the same id
+    pub fn extend(&mut self, other: &ItemCatalog) {
+        self.items.extend(other.items.iter().map(|(id, item)| (id.clone(), item.clone())));
+    }
+
+    // The first item, by id, of the given type
+    pub fn of_type(&self, item_type: &ItemType) -> Option<&Item> {
+        self.iter().find(|item| item.item_type == *item_type)
+    }
+
+    // A weapon family ("sword", "bow") by id, or else by weapon type
+    pub fn weapon(&self, kind: &str) -> Option<&Item> {
+        let kind = kind.to_lowercase();
+        self.get(kind.as_str()).filter(|item| matches!(item.item_type, ItemType::Weapon(_))).or_else(|| {
+            self.iter().find(|item| match &item.item_type {
+                ItemType::Weapon(weapon) => format!("{:?}", weapon).to_lowercase() == kind,
+                _ => false,
+            })
+        })
+    }
+
+    pub fn weapon_damage(&self, kind: &str) -> Option<DiceExpr> {
+        self.weapon(kind)?.properties.damage.as_deref()?.parse().ok()
+    }
+
+    // An item by the name it's shown under, in any case
+    pub fn named(&self, name: &str) -> Option<&Item> {
+        self.iter().find(|item| item.name.eq_ignore_ascii_case(name))
+    }
+
+    // A treasure or reward named in a dungeon or a quest: an item by id or name, a magic
+    // version of one ("Sword +1", "Chain Mail +1") with its bonus, or else whatever
+    // `treasure_item` makes of the name
+    pub fn treasure(&self, name: &str) -> Item {
+        self.get(name)
+            .or_else(|| self.named(name))
+            .cloned()
+            .or_else(|| self.magic_version(name))
+            .unwrap_or_else(|| treasure_item(name))
+    }
+
+    // "Sword +1, +3 vs Undead" is a sword with a +1 bonus, keeping its whole name
+    fn magic_version(&self, name: &str) -> Option<Item> {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let at = words.iter().position(|word| word.starts_with(['+', '-']))?;
+        let bonus: i8 = words[at].trim_end_matches(',').parse().ok()?;
+        let base = words[..at].join(" ");
+        let mut item = self.named(&base).or_else(|| self.get(base.as_str()))?.clone();
+        item.name = name.to_string();
+        item.properties.magic_bonus = Some(bonus);
+        Some(item)
+    }
+}
@@ -0,0 +1,517 @@
+use serde::{Deserialize, Serialize};
+use rand::Rng;
+
+use crate::death::Death;
+use crate::dice;
+use crate::spells::{SpellCompendium, SpellDescriptor};
+use crate::item::Item;
+use crate::provisions::Sustenance;
+use crate::ruleset::Ruleset;
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Character {
+    pub name: String,
+    pub class: CharacterClass,
+    pub level: u8,
+    pub experience: u32,
+    // Percent added to (or taken from) the experience they earn, for their prime
+    // requisites as they were made; see `CharacterClass::experience_bonus`
+    #[serde(default)]
+    pub experience_bonus: i8,
+    pub stats: CharacterStats,
+    pub hit_points: HitPoints,
+    pub armor_class: i8,
+    pub equipment: Equipment,
+    pub inventory: Inventory,
+    // Spells known: the spellbook for magic-users and elves, the prayers a cleric can call on
+    pub spells: Vec<Spell>,
+    // What is held in the mind today; see `crate::spells`
+    #[serde(default)]
+    pub memorized_spells: Vec<MemorizedSpell>,
+    // Days gone hungry or thirsty; see `crate::provisions`
+    #[serde(default)]
+    pub sustenance: Sustenance,
+    // When and how they died, while they lie dead; see `crate::death`
+    #[serde(default)]
+    pub death: Option<Death>,
+    // Days of bed rest still to go after being raised
+    #[serde(default)]
+    pub recovery_days: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CharacterClass {
+    Fighter,
+    MagicUser,
+    Cleric,
+    Thief,
+    Dwarf,
+    Elf,
+    Halfling,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterStats {
+    pub strength: u8,
+    pub dexterity: u8,
+    pub constitution: u8,
+    pub intelligence: u8,
+    pub wisdom: u8,
+    pub charisma: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HitPoints {
+    pub current: i16,
+    pub maximum: i16,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<Item>,
+    pub armor: Option<Item>,
+    pub shield: Option<Item>,
+    pub helmet: Option<Item>,
+    pub cloak: Option<Item>,
+    pub boots: Option<Item>,
+    pub left_ring: Option<Item>,
+    pub right_ring: Option<Item>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    pub gold: u32,
+    pub weight_capacity: f32,
+    pub current_weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spell {
+    pub name: String,
+    pub level: u8,
+    pub school: SpellSchool,
+    pub casting_time: String,
+    pub range: String,
+    pub duration: String,
+    pub description: String,
+    // Which spell lists the spell appears on; most are magic-user spells
+    #[serde(default = "default_casters")]
+    pub casters: Vec<SpellCaster>,
+    // What it does in a fight, for the rules to resolve (see spells.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effect: Option<SpellDescriptor>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpellCaster {
+    Arcane,
+    Divine,
+}
+
+fn default_casters() -> Vec<SpellCaster> {
+    vec![SpellCaster::Arcane]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemorizedSpell {
+    pub name: String,
+    pub level: u8,
+    pub cast: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpellSchool {
+    Abjuration,
+    Conjuration,
+    Divination,
+    Enchantment,
+    Evocation,
+    Illusion,
+    Necromancy,
+    Transmutation,
+}
+
+impl Character {
+    // Rolls the new character's abilities with the caller's RNG, so seeded sessions
+    // generate the same party. Scores that don't qualify for the class are rerolled.
+    pub fn new_with(name: String, class: CharacterClass, rng: &mut impl Rng) -> Self {
+        let stats = loop {
+            let stats = CharacterStats::roll_with(rng);
+            if class.accepts(&stats) {
+                break stats;
+            }
+        };
+        Self::with_stats(name, class, stats)
+    }
+
+    // A first level character with the given scores. Callers check the class minimums
+    // with `CharacterClass::accepts` first.
+    pub fn with_stats(name: String, class: CharacterClass, stats: CharacterStats) -> Self {
+        let level = 1;
+        let hit_points = HitPoints::new(&class, &stats, level);
+        let equipment = Equipment::default();
+        let armor_class = Self::calculate_armor_class(&stats, &equipment);
+        let experience_bonus = class.experience_bonus(&stats);
+        
+        Self {
+            name,
+            class,
+            level,
+            experience: 0,
+            experience_bonus,
+            stats,
+            hit_points,
+            armor_class,
+            equipment,
+            inventory: Inventory::default(),
+            spells: Vec::new(),
+            memorized_spells: Vec::new(),
+            sustenance: Sustenance::default(),
+            death: None,
+            recovery_days: 0,
+        }
+    }
+
+    // The lowest of the class's prime requisites, which is what the experience bonus
+    // and penalty are judged on
+    pub fn prime_requisite_score(&self) -> u8 {
+        self.class
+            .prime_requisites()
+            .iter()
+            .map(|&ability| self.stats.get(ability))
+            .min()
+            .unwrap_or(10)
+    }
+
+    // Ascending AC: 10 unarmoured, plus Dexterity and whatever is worn
+    pub fn calculate_armor_class(stats: &CharacterStats, equipment: &Equipment) -> i8 {
+        let dex_modifier = Self::get_dexterity_modifier(stats.dexterity);
+        10 + dex_modifier + equipment.armor_class_bonus()
+    }
+
+    // Call after changing equipment or Dexterity
+    pub fn recalculate_armor_class(&mut self) {
+        self.armor_class = Self::calculate_armor_class(&self.stats, &self.equipment);
+    }
+
+    pub fn get_dexterity_modifier(dexterity: u8) -> i8 {
+        match dexterity {
+            3 => -3,
+            4..=5 => -2,
+            6..=8 => -1,
+            9..=12 => 0,
+            13..=15 => 1,
+            16..=17 => 2,
+            18 => 3,
+            _ => 0,
+        }
+    }
+
+    pub fn get_strength_modifier(strength: u8) -> i8 {
+        match strength {
+            3 => -3,
+            4..=5 => -2,
+            6..=8 => -1,
+            9..=12 => 0,
+            13..=15 => 1,
+            16..=17 => 2,
+            18 => 3,
+            _ => 0,
+        }
+    }
+
+    // Adds `xp`, less or more for their prime requisites, and returns what was earned.
+    // Going up a level is left to the caller; see `crate::advancement`.
+    pub fn gain_experience(&mut self, xp: u32) -> u32 {
+        let earned = (xp as i64 * (100 + self.experience_bonus as i64) / 100).max(0) as u32;
+        self.experience += earned;
+        earned
+    }
+
+    // Takes every level the experience is enough for, without rolling
+    pub fn check_level_up(&mut self, ruleset: &Ruleset) {
+        while self.level_up_due(ruleset) {
+            self.level_up();
+        }
+    }
+
+    pub fn level_up(&mut self) {
+        let spell_level = self.new_spell_level();
+        self.level += 1;
+        let new_hp = self.calculate_hit_points();
+        self.hit_points.maximum += new_hp;
+        self.hit_points.current += new_hp;
+
+        if let Some(spell_level) = spell_level {
+            self.learn_spell_level(spell_level, SpellCompendium::builtin(), None);
+        }
+    }
+
+    // None once the character is at their class's level limit
+    pub fn get_xp_for_next_level(&self, ruleset: &Ruleset) -> Option<u32> {
+        ruleset.experience_tables.for_level(&self.class, self.level.saturating_add(1))
+    }
+
+    pub fn calculate_hit_points(&self) -> i16 {
+        let base_hp = match self.class {
+            CharacterClass::Fighter => 10,
+            CharacterClass::MagicUser => 4,
+            CharacterClass::Cleric => 8,
+            CharacterClass::Thief => 6,
+            CharacterClass::Dwarf => 8,
+            CharacterClass::Elf => 6,
+            CharacterClass::Halfling => 6,
+        };
+        
+        let con_modifier = Self::get_constitution_modifier(self.stats.constitution);
+        (base_hp as i16 + con_modifier).max(1)
+    }
+
+    pub fn get_constitution_modifier(constitution: u8) -> i16 {
+        match constitution {
+            3 => -2,
+            4..=5 => -1,
+            6..=8 => -1,
+            9..=12 => 0,
+            13..=15 => 1,
+            16..=17 => 2,
+            18 => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hit_points.current > 0
+    }
+
+    pub fn take_damage(&mut self, damage: i16) {
+        self.hit_points.current -= damage;
+        if self.hit_points.current < 0 {
+            self.hit_points.current = 0;
+        }
+    }
+
+    // The dead don't heal; they have to be raised (see `crate::death`)
+    pub fn heal(&mut self, amount: i16) {
+        if !self.is_alive() {
+            return;
+        }
+        self.hit_points.current += amount;
+        if self.hit_points.current > self.hit_points.maximum {
+            self.hit_points.current = self.hit_points.maximum;
+        }
+    }
+}
+
+impl CharacterClass {
+    pub const ALL: [CharacterClass; 7] = [
+        CharacterClass::Fighter,
+        CharacterClass::MagicUser,
+        CharacterClass::Cleric,
+        CharacterClass::Thief,
+        CharacterClass::Dwarf,
+        CharacterClass::Elf,
+        CharacterClass::Halfling,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CharacterClass::Fighter => "Fighter",
+            CharacterClass::MagicUser => "Magic-User",
+            CharacterClass::Cleric => "Cleric",
+            CharacterClass::Thief => "Thief",
+            CharacterClass::Dwarf => "Dwarf",
+            CharacterClass::Elf => "Elf",
+            CharacterClass::Halfling => "Halfling",
+        }
+    }
+
+    // B/X minimum scores to take up the class
+    pub fn requirements(&self) -> &'static [(Ability, u8)] {
+        match self {
+            CharacterClass::Dwarf => &[(Ability::Constitution, 9)],
+            CharacterClass::Elf => &[(Ability::Intelligence, 9)],
+            CharacterClass::Halfling => &[(Ability::Dexterity, 9), (Ability::Constitution, 9)],
+            _ => &[],
+        }
+    }
+
+    pub fn prime_requisites(&self) -> &'static [Ability] {
+        match self {
+            CharacterClass::Fighter | CharacterClass::Dwarf => &[Ability::Strength],
+            CharacterClass::MagicUser => &[Ability::Intelligence],
+            CharacterClass::Cleric => &[Ability::Wisdom],
+            CharacterClass::Thief => &[Ability::Dexterity],
+            CharacterClass::Elf => &[Ability::Strength, Ability::Intelligence],
+            CharacterClass::Halfling => &[Ability::Strength, Ability::Dexterity],
+        }
+    }
+
+    // The percentage of experience B/X adds or takes away for the prime requisites: -20%
+    // for a score of 3-5, -10% for 6-8, +5% for 13-15 and +10% for 16-18. An elf gets +5%
+    // with Strength and Intelligence both 13 or more and +10% if Intelligence is 16 too; a
+    // halfling +5% with Strength or Dexterity 13 or more and +10% with both. Neither of
+    // them ever loses any.
+    pub fn experience_bonus(&self, stats: &CharacterStats) -> i8 {
+        let (strength, dexterity, intelligence) = (stats.strength, stats.dexterity, stats.intelligence);
+        match self {
+            CharacterClass::Elf if strength >= 13 && intelligence >= 16 => 10,
+            CharacterClass::Elf if strength >= 13 && intelligence >= 13 => 5,
+            CharacterClass::Halfling if strength >= 13 && dexterity >= 13 => 10,
+            CharacterClass::Halfling if strength >= 13 || dexterity >= 13 => 5,
+            CharacterClass::Elf | CharacterClass::Halfling => 0,
+            _ => match self.prime_requisites().first().map_or(10, |&ability| stats.get(ability)) {
+                0..=5 => -20,
+                6..=8 => -10,
+                9..=12 => 0,
+                13..=15 => 5,
+                _ => 10,
+            },
+        }
+    }
+
+    // Requirements the scores fall short of; empty if the class is open to them
+    pub fn unmet_requirements(&self, stats: &CharacterStats) -> Vec<(Ability, u8)> {
+        self.requirements()
+            .iter()
+            .copied()
+            .filter(|&(ability, minimum)| stats.get(ability) < minimum)
+            .collect()
+    }
+
+    pub fn accepts(&self, stats: &CharacterStats) -> bool {
+        self.unmet_requirements(stats).is_empty()
+    }
+}
+
+impl Ability {
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Ability::Strength => "STR",
+            Ability::Dexterity => "DEX",
+            Ability::Constitution => "CON",
+            Ability::Intelligence => "INT",
+            Ability::Wisdom => "WIS",
+            Ability::Charisma => "CHA",
+        }
+    }
+}
+
+impl CharacterStats {
+    pub fn get(&self, ability: Ability) -> u8 {
+        match ability {
+            Ability::Strength => self.strength,
+            Ability::Dexterity => self.dexterity,
+            Ability::Constitution => self.constitution,
+            Ability::Intelligence => self.intelligence,
+            Ability::Wisdom => self.wisdom,
+            Ability::Charisma => self.charisma,
+        }
+    }
+
+    pub fn roll_with(rng: &mut impl Rng) -> Self {
+        Self {
+            strength: Self::roll_ability_score(rng),
+            dexterity: Self::roll_ability_score(rng),
+            constitution: Self::roll_ability_score(rng),
+            intelligence: Self::roll_ability_score(rng),
+            wisdom: Self::roll_ability_score(rng),
+            charisma: Self::roll_ability_score(rng),
+        }
+    }
+
+    fn roll_ability_score(rng: &mut impl Rng) -> u8 {
+        // Roll 4d6, drop lowest
+        let mut rolls = [dice::d6(rng), dice::d6(rng), dice::d6(rng), dice::d6(rng)];
+        rolls.sort();
+        rolls[1..].iter().sum()
+    }
+}
+
+impl HitPoints {
+    pub fn new(class: &CharacterClass, stats: &CharacterStats, _level: u8) -> Self {
+        let base_hp = match class {
+            CharacterClass::Fighter => 10,
+            CharacterClass::MagicUser => 4,
+            CharacterClass::Cleric => 8,
+            CharacterClass::Thief => 6,
+            CharacterClass::Dwarf => 8,
+            CharacterClass::Elf => 6,
+            CharacterClass::Halfling => 6,
+        };
+        
+        let con_modifier = Character::get_constitution_modifier(stats.constitution);
+        let max_hp = (base_hp as i16 + con_modifier).max(1);
+        
+        Self {
+            current: max_hp,
+            maximum: max_hp,
+        }
+    }
+}
+
+impl Equipment {
+    pub fn armor_class_bonus(&self) -> i8 {
+        self.worn().map(Item::armor_class_bonus).sum()
+    }
+}
+
+impl Inventory {
+    // Goes onto any stacks of the same thing with room, and what's left into a new one
+    pub fn add_item(&mut self, mut item: Item) {
+        self.current_weight += item.total_weight();
+        for stack in self.items.iter_mut() {
+            if !stack.stacks_with(&item) {
+                continue;
+            }
+            let moved = item.quantity.min(stack.stack_room());
+            stack.quantity += moved;
+            item.quantity -= moved;
+            if item.quantity == 0 {
+                return;
+            }
+        }
+        self.items.push(item);
+    }
+
+    pub fn remove_item(&mut self, index: usize) -> Option<Item> {
+        if index >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(index);
+        self.current_weight = (self.current_weight - item.total_weight()).max(0.0);
+        Some(item)
+    }
+
+    pub fn spend_gold(&mut self, amount: u32) -> Result<(), String> {
+        if amount > self.gold {
+            return Err(format!("{} gp needed, only {} gp on hand", amount, self.gold));
+        }
+        self.gold -= amount;
+        Ok(())
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            gold: 0,
+            weight_capacity: 50.0, // Base capacity
+            current_weight: 0.0,
+        }
+    }
+}
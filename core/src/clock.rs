@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+// Game time. B/X keeps time in rounds of ten seconds during encounters and turns of ten
+// minutes while exploring; six turns make an hour. The clock counts rounds from
+// midnight on the first day, and a campaign starts at eight in the morning.
+
+pub const ROUNDS_PER_TURN: u64 = 60;
+pub const TURNS_PER_HOUR: u64 = 6;
+pub const HOURS_PER_DAY: u64 = 24;
+pub const TURNS_PER_DAY: u64 = TURNS_PER_HOUR * HOURS_PER_DAY;
+
+const ROUNDS_PER_HOUR: u64 = ROUNDS_PER_TURN * TURNS_PER_HOUR;
+const ROUNDS_PER_DAY: u64 = ROUNDS_PER_HOUR * HOURS_PER_DAY;
+const START_HOUR: u64 = 8;
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GameClock {
+    pub rounds: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TimeSpan {
+    Rounds(u32),
+    Turns(u32),
+    Hours(u32),
+    Days(u32),
+}
+
+// Boundaries the clock crossed in one advance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Elapsed {
+    pub turns: u64,
+    pub days: u64,
+}
+
+impl TimeSpan {
+    pub fn rounds(&self) -> u64 {
+        match *self {
+            TimeSpan::Rounds(rounds) => rounds as u64,
+            TimeSpan::Turns(turns) => turns as u64 * ROUNDS_PER_TURN,
+            TimeSpan::Hours(hours) => hours as u64 * ROUNDS_PER_HOUR,
+            TimeSpan::Days(days) => days as u64 * ROUNDS_PER_DAY,
+        }
+    }
+
+    // Exploring, a party covers three times its encounter movement rate each turn
+    // (120' a turn at 40' a round), mapping and watching as it goes
+    pub fn exploring(feet: u32, movement_rate: u16) -> TimeSpan {
+        let feet_per_turn = (movement_rate as u64 * 3).max(1);
+        TimeSpan::Rounds((feet as u64 * ROUNDS_PER_TURN).div_ceil(feet_per_turn) as u32)
+    }
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            rounds: START_HOUR * ROUNDS_PER_HOUR,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn advance(&mut self, span: TimeSpan) -> Elapsed {
+        let before = *self;
+        self.rounds += span.rounds();
+        Elapsed {
+            turns: self.turns() - before.turns(),
+            days: self.day() - before.day(),
+        }
+    }
+
+    // Whole turns since the clock started
+    pub fn turns(&self) -> u64 {
+        self.rounds / ROUNDS_PER_TURN
+    }
+
+    // Counting from day 1
+    pub fn day(&self) -> u64 {
+        self.rounds / ROUNDS_PER_DAY + 1
+    }
+
+    pub fn hour(&self) -> u64 {
+        self.rounds % ROUNDS_PER_DAY / ROUNDS_PER_HOUR
+    }
+
+    pub fn minute(&self) -> u64 {
+        self.rounds % ROUNDS_PER_HOUR / 6
+    }
+
+    pub fn is_night(&self) -> bool {
+        !(6..20).contains(&self.hour())
+    }
+
+    // In words, for narration and the AI service
+    pub fn time_of_day(&self) -> &'static str {
+        match self.hour() {
+            5..=6 => "dawn",
+            7..=11 => "morning",
+            12..=13 => "midday",
+            14..=17 => "afternoon",
+            18..=20 => "evening",
+            _ => "night",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("Day {}, {:02}:{:02}", self.day(), self.hour(), self.minute())
+    }
+}
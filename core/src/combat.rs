@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use rand::Rng;
+
+use crate::catalog::ItemCatalog;
+use crate::character::Character;
+use crate::dice::{self, DiceExpr};
+use crate::ruleset::{AttackMethod, Ruleset};
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Combatant {
+    pub initiative: i8,
+    pub is_player: bool,
+    pub actions_remaining: u8,
+    pub status_effects: Vec<StatusEffect>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CombatState {
+    // The sides have just met: surprise and the monsters' reaction are still to roll
+    Encounter,
+    // The monsters are willing to talk; the party can fight or back away
+    Parley,
+    Initiative,
+    PlayerTurn,
+    EnemyTurn,
+    Victory,
+    Defeat,
+}
+
+// A duration for effects that last as long as the fight, like a troll's regeneration
+pub const WHOLE_FIGHT: u8 = u8::MAX;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub name: String,
+    pub duration: u8,
+    pub effect_type: EffectType,
+    pub magnitude: i16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EffectType {
+    Damage,
+    Healing,
+    StatModifier,
+    Stun,
+    Poison,
+    // Added to armor class (ascending) while it lasts
+    ArmorBonus,
+    // A sickness that only a cure lifts
+    Disease,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FleeOutcome {
+    // Outran the pursuit
+    Escaped,
+    // The pursuers stopped for the dropped treasure
+    Distracted,
+    Caught,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DamageType {
+    Slashing,
+    Piercing,
+    Bludgeoning,
+    Fire,
+    Cold,
+    Lightning,
+    Acid,
+    Poison,
+    Magic,
+}
+
+pub fn roll_attack(
+    attacker: &Character,
+    target: &Character,
+    weapon: Option<&str>,
+    rng: &mut impl Rng,
+) -> (bool, i16) {
+    roll_attack_with(&Ruleset::default(), attacker, target, weapon, rng)
+}
+
+pub fn roll_attack_with(
+    ruleset: &Ruleset,
+    attacker: &Character,
+    target: &Character,
+    weapon: Option<&str>,
+    rng: &mut impl Rng,
+) -> (bool, i16) {
+    let roll = roll_attack_detailed(ruleset, attacker, target, weapon, rng);
+    (roll.hit, roll.damage)
+}
+
+// An attack as it was rolled, the die as well as the outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttackRoll {
+    pub natural: i16,
+    pub hit: bool,
+    pub damage: i16,
+}
+
+impl AttackRoll {
+    // A natural 20. The rules make nothing more of it than a hit, but it's worth showing.
+    pub fn is_critical(&self) -> bool {
+        self.hit && self.natural == 20
+    }
+}
+
+pub fn roll_attack_detailed(
+    ruleset: &Ruleset,
+    attacker: &Character,
+    target: &Character,
+    weapon: Option<&str>,
+    rng: &mut impl Rng,
+) -> AttackRoll {
+    // Roll d20
+    let attack_roll = dice::roll(rng, 1, 20);
+    let total_attack = attack_roll + attack_bonus(ruleset, attacker, weapon);
+    
+    // Check if hit
+    let hit = total_attack >= target.armor_class as i16;
+    
+    // Calculate damage if hit
+    let damage = if hit {
+        calculate_damage(attacker, weapon, rng)
+    } else {
+        0
+    };
+    
+    AttackRoll {
+        natural: attack_roll,
+        hit,
+        damage,
+    }
+}
+
+// What the attacker adds to the d20: their level, a magic weapon and, in melee, their
+// Strength
+pub fn attack_bonus(ruleset: &Ruleset, attacker: &Character, weapon: Option<&str>) -> i16 {
+    let mut bonus = level_attack_bonus(ruleset, attacker) + weapon_magic_bonus(attacker);
+    if is_melee_attack(attacker, weapon) {
+        bonus += Character::get_strength_modifier(attacker.stats.strength) as i16;
+    }
+    bonus
+}
+
+// The chance in 100 that the attack hits, going by the d20 rolls that reach the target's
+// armour class
+pub fn hit_chance(ruleset: &Ruleset, attacker: &Character, target: &Character, weapon: Option<&str>) -> u8 {
+    let needed = target.armor_class as i16 - attack_bonus(ruleset, attacker, weapon);
+    ((21 - needed.max(1)).clamp(0, 20) * 5) as u8
+}
+
+// Armor class here is ascending, so THAC0 19 (a first level character) is a +0 bonus
+pub fn level_attack_bonus(ruleset: &Ruleset, attacker: &Character) -> i16 {
+    match ruleset.attack_method {
+        AttackMethod::AttackMatrix => 19 - ruleset.attack_matrix.thac0(&attacker.class, attacker.level),
+        AttackMethod::LevelBonus => (attacker.level as i16 - 1) / 3, // +1 every 3 levels
+    }
+}
+
+pub fn is_melee_weapon(weapon: &str) -> bool {
+    matches!(weapon.to_lowercase().as_str(), 
+        "sword" | "axe" | "mace" | "dagger" | "staff" | "hammer"
+    )
+}
+
+// Whether the attack is made in melee. The equipped weapon's type decides; attackers
+// with nothing equipped fall back to the weapon name.
+pub fn is_melee_attack(attacker: &Character, weapon: Option<&str>) -> bool {
+    match &attacker.equipment.weapon {
+        Some(item) => !item.is_missile_weapon(),
+        None => weapon.is_some_and(is_melee_weapon),
+    }
+}
+
+pub fn weapon_magic_bonus(attacker: &Character) -> i16 {
+    attacker.equipment.weapon.as_ref().map_or(0, |item| item.magic_bonus() as i16)
+}
+
+pub fn calculate_damage(attacker: &Character, weapon: Option<&str>, rng: &mut impl Rng) -> i16 {
+    let mut damage = weapon_damage(attacker, weapon).roll(rng) + weapon_magic_bonus(attacker);
+    
+    // Add strength modifier for melee weapons
+    if is_melee_attack(attacker, weapon) {
+        let str_mod = Character::get_strength_modifier(attacker.stats.strength) as i16;
+        damage += str_mod.max(0); // Only positive modifiers apply to damage
+    }
+    
+    damage.max(1) // Minimum 1 damage
+}
+
+// The equipped weapon's own damage dice win over the catalog's for its family, so magic
+// weapons and monster attacks roll what their data says
+pub fn weapon_damage(attacker: &Character, weapon: Option<&str>) -> DiceExpr {
+    attacker
+        .equipment
+        .weapon
+        .as_ref()
+        .and_then(|item| item.properties.damage.as_deref())
+        .and_then(|damage| damage.parse().ok())
+        .unwrap_or_else(|| base_weapon_damage(weapon))
+}
+
+// What the item catalog gives the weapon family, or 1d4 unarmed
+pub fn base_weapon_damage(weapon: Option<&str>) -> DiceExpr {
+    weapon
+        .and_then(|weapon| ItemCatalog::builtin().weapon_damage(weapon))
+        .unwrap_or(DiceExpr::new(1, 4, 0))
+}
+
+// Armor class with any protective effects (Shield, Protection from Evil) applied
+pub fn effective_armor_class(character: &Character, effects: &[StatusEffect]) -> i8 {
+    let bonus: i16 = effects
+        .iter()
+        .filter(|effect| matches!(effect.effect_type, EffectType::ArmorBonus))
+        .map(|effect| effect.magnitude)
+        .sum();
+    (character.armor_class as i16 + bonus).clamp(i8::MIN as i16, i8::MAX as i16) as i8
+}
+
+pub fn is_incapacitated(effects: &[StatusEffect]) -> bool {
+    effects.iter().any(|effect| matches!(effect.effect_type, EffectType::Stun))
+}
+
+// Hit points an effect like poison takes each round it lasts
+pub fn damage_per_round(effect: &StatusEffect) -> i16 {
+    match effect.effect_type {
+        EffectType::Damage | EffectType::Poison => effect.magnitude,
+        _ => 0,
+    }
+}
+
+// Hit points an effect like regeneration gives back each round it lasts
+pub fn healing_per_round(effect: &StatusEffect) -> i16 {
+    match effect.effect_type {
+        EffectType::Healing => effect.magnitude,
+        _ => 0,
+    }
+}
+
+// What blessings and curses add to the character's attack rolls
+pub fn roll_modifier(effects: &[StatusEffect]) -> i16 {
+    effects
+        .iter()
+        .filter(|effect| matches!(effect.effect_type, EffectType::StatModifier))
+        .map(|effect| effect.magnitude)
+        .sum()
+}
+
+// Whether the effect helps whoever has it, for telling friend from foe at a glance
+pub fn is_beneficial(effect: &StatusEffect) -> bool {
+    match effect.effect_type {
+        EffectType::Healing | EffectType::ArmorBonus => true,
+        EffectType::StatModifier => effect.magnitude >= 0,
+        EffectType::Damage | EffectType::Stun | EffectType::Poison | EffectType::Disease => false,
+    }
+}
+
+// A party moves at the pace of its slowest member and is chased at the pace of the
+// fastest pursuer. A faster party always gets away; otherwise dropped treasure stops
+// the pursuit on 1-3 in 6, and failing that the party escapes on 1-3 in 6 at equal
+// speed or a 1 if slower. A party too loaded down to move can't run at all.
+pub fn attempt_flight(party_rate: u16, pursuer_rate: u16, dropped_treasure: bool, rng: &mut impl Rng) -> FleeOutcome {
+    if party_rate == 0 {
+        return FleeOutcome::Caught;
+    }
+    if party_rate > pursuer_rate {
+        return FleeOutcome::Escaped;
+    }
+    if dropped_treasure && dice::d6(rng) <= 3 {
+        return FleeOutcome::Distracted;
+    }
+
+    let escape_on = if party_rate == pursuer_rate { 3 } else { 1 };
+    if dice::d6(rng) <= escape_on {
+        FleeOutcome::Escaped
+    } else {
+        FleeOutcome::Caught
+    }
+}
+
+// Combat UI helper functions
+pub fn get_combat_text(attacker: &Character, target: &Character, hit: bool, damage: i16) -> String {
+    if hit {
+        format!("{} hits {} for {} damage!", attacker.name, target.name, damage)
+    } else {
+        format!("{} misses {}!", attacker.name, target.name)
+    }
+}
+
+pub fn get_initiative_text(combatant: &Character, initiative: i8) -> String {
+    format!("{} rolls initiative: {}", combatant.name, initiative)
+}
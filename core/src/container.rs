@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::Inventory;
+use crate::item::Item;
+
+// Items that hold other items. A container's capacity is in pounds, counted against
+// everything inside it including the contents of containers within it, and all of it
+// weighs on whoever carries the outermost one.
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Container {
+    pub capacity: f32,
+    #[serde(default)]
+    pub contents: Vec<Item>,
+}
+
+impl Container {
+    pub fn load(&self) -> f32 {
+        self.contents.iter().map(Item::total_weight).sum()
+    }
+
+    pub fn free_capacity(&self) -> f32 {
+        (self.capacity - self.load()).max(0.0)
+    }
+
+    pub fn can_hold(&self, item: &Item) -> bool {
+        item.total_weight() <= self.free_capacity()
+    }
+}
+
+impl Item {
+    pub fn is_container(&self) -> bool {
+        self.container.is_some()
+    }
+
+    // The weight of the whole stack plus everything in it, however deeply nested
+    pub fn total_weight(&self) -> f32 {
+        self.weight * self.quantity as f32 + self.container.as_ref().map_or(0.0, Container::load)
+    }
+
+    pub fn is_empty_container(&self) -> bool {
+        self.container.as_ref().is_some_and(|container| container.contents.is_empty())
+    }
+}
+
+impl Inventory {
+    // Puts the pack item at `item` into the container at `container`. Returns where the
+    // container is in the pack afterwards, since taking out an earlier item moves it up.
+    pub fn stow(&mut self, item: usize, container: usize) -> Result<usize, String> {
+        if item == container {
+            return Err("A container can't go inside itself".to_string());
+        }
+        let (Some(stowed), Some(holder)) = (self.items.get(item), self.items.get(container)) else {
+            return Err(format!("There is no item {}", item.max(container) + 1));
+        };
+        let Some(space) = &holder.container else {
+            return Err(format!("{} can't hold anything", holder.name));
+        };
+        if !space.can_hold(stowed) {
+            return Err(format!(
+                "{} won't fit in {} ({} lb of {} lb free)",
+                stowed.name,
+                holder.name,
+                space.free_capacity(),
+                space.capacity
+            ));
+        }
+
+        let stowed = self.items.remove(item);
+        let container = if item < container { container - 1 } else { container };
+        if let Some(space) = self.items[container].container.as_mut() {
+            space.contents.push(stowed);
+        }
+        Ok(container)
+    }
+
+    // Takes the item at `item` out of the container at `container` and into the pack
+    pub fn take_out(&mut self, container: usize, item: usize) -> Result<(), String> {
+        let holder = self
+            .items
+            .get_mut(container)
+            .ok_or_else(|| format!("There is no item {}", container + 1))?;
+        let name = holder.name.clone();
+        let space = holder.container.as_mut().ok_or_else(|| format!("{} can't hold anything", name))?;
+        if item >= space.contents.len() {
+            return Err(format!("{} has no item {}", name, item + 1));
+        }
+        let taken = space.contents.remove(item);
+        self.items.push(taken);
+        Ok(())
+    }
+
+    // Moves the pack item at `from` into the place of the one at `to`, shifting the
+    // items between along by one
+    pub fn move_item(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from >= self.items.len() {
+            return Err(format!("There is no item {}", from + 1));
+        }
+        let item = self.items.remove(from);
+        let to = to.min(self.items.len());
+        self.items.insert(to, item);
+        Ok(())
+    }
+
+    // Everything in the pack, container contents included
+    pub fn total_weight(&self) -> f32 {
+        self.items.iter().map(Item::total_weight).sum()
+    }
+}
@@ -0,0 +1,139 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+
+// Death and coming back from it. A character at 0 hit points is dead, but their body
+// stays with the party, carried by the living and shared out evenly on top of their own
+// loads, until it is raised or buried. Raise Dead (from the temple's priests, or read
+// from a scroll by a cleric) works only on someone dead no more than four days for every
+// level the caster has over 7th. The raised must then survive the shock on a roll set by
+// their Constitution: a character who fails is gone for good. One who lives comes back
+// with 1 hit point and two weeks of bed rest to get through, unable to fight or cast and
+// travelling at half speed until it's over.
+
+// About 150 lb of body, in coins; the gear on it is weighed as well
+pub const BODY_COINS: u32 = 1500;
+
+pub const RECOVERY_DAYS: u32 = 14;
+
+// The temple's high priest is a Patriarch of 11th level
+pub const TEMPLE_CASTER_LEVEL: u8 = 11;
+
+// A scroll casts the spell at the lowest level a cleric could write it
+pub const SCROLL_CASTER_LEVEL: u8 = 9;
+
+// When and how the character died
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Death {
+    pub day: u64,
+    pub cause: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaiseOutcome {
+    Raised,
+    // Failed the survival roll
+    Lost,
+}
+
+// The chance in 100 of surviving being raised, by Constitution
+pub fn survival_chance(constitution: u8) -> u8 {
+    match constitution {
+        0..=3 => 40,
+        4..=12 => 45 + (constitution - 4) * 5,
+        13 => 90,
+        14 => 92,
+        15 => 94,
+        16 => 96,
+        17 => 98,
+        _ => 100,
+    }
+}
+
+// How long dead a caster of `level` can still raise someone from
+pub fn raise_window_days(level: u8) -> u64 {
+    4 * level.saturating_sub(7) as u64
+}
+
+// How fast the party goes: the slowest of the living, each carrying an even share of
+// the dead, and those still recovering from being raised going at half speed
+pub fn party_movement_rate<'a>(party: impl IntoIterator<Item = &'a Character>) -> u16 {
+    let party: Vec<&Character> = party.into_iter().collect();
+    let living: Vec<&Character> = party.iter().copied().filter(|character| character.is_alive()).collect();
+    if living.is_empty() {
+        return 0;
+    }
+    let bodies: u32 = party.iter().filter(|character| !character.is_alive()).map(|body| body.body_coins()).sum();
+    let share = bodies / living.len() as u32;
+    living
+        .iter()
+        .map(|character| {
+            let rate = character.movement_rate_carrying(share);
+            if character.is_recovering() { rate / 2 } else { rate }
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+impl Character {
+    // What it takes to carry the character's body and everything on it
+    pub fn body_coins(&self) -> u32 {
+        BODY_COINS + self.carried_coins()
+    }
+
+    pub fn die(&mut self, day: u64, cause: &str) {
+        self.hit_points.current = 0;
+        self.recovery_days = 0;
+        if self.death.is_none() {
+            self.death = Some(Death { day, cause: cause.to_string() });
+        }
+    }
+
+    // Why Raise Dead cast at `level` on `today` wouldn't work, if it wouldn't
+    pub fn raise_refusal(&self, level: u8, today: u64) -> Option<String> {
+        if self.is_alive() {
+            return Some(format!("{} isn't dead", self.name));
+        }
+        let died = self.death.as_ref().map_or(today, |death| death.day);
+        let window = raise_window_days(level);
+        if today.saturating_sub(died) > window {
+            return Some(format!("{} has been dead too long to raise (more than {} days)", self.name, window));
+        }
+        None
+    }
+
+    // The survival roll, then the raising; says how it went
+    pub fn raise(&mut self, rng: &mut impl Rng) -> (RaiseOutcome, String) {
+        let chance = survival_chance(self.stats.constitution);
+        let roll = rng.gen_range(1..=100);
+        if roll > chance {
+            let text = format!(
+                "{} doesn't survive the raising (rolled {} against {}%) and the body crumbles to dust",
+                self.name, roll, chance
+            );
+            return (RaiseOutcome::Lost, text);
+        }
+        self.hit_points.current = 1;
+        self.death = None;
+        self.recovery_days = RECOVERY_DAYS;
+        let text = format!(
+            "{} draws breath again (rolled {} against {}%) but needs {} days' rest",
+            self.name, roll, chance, RECOVERY_DAYS
+        );
+        (RaiseOutcome::Raised, text)
+    }
+
+    pub fn is_recovering(&self) -> bool {
+        self.is_alive() && self.recovery_days > 0
+    }
+
+    // A day of bed rest done; true once the last one is over
+    pub fn recover_a_day(&mut self) -> bool {
+        if !self.is_recovering() {
+            return false;
+        }
+        self.recovery_days -= 1;
+        self.recovery_days == 0
+    }
+}
@@ -0,0 +1,161 @@
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Dice helpers for the rules. Everything takes the caller's RNG so seeded sessions and
+// simulations roll the same numbers.
+
+// Sum of `count` dice with `sides` sides
+pub fn roll(rng: &mut impl Rng, count: u8, sides: u8) -> i16 {
+    (0..count).map(|_| rng.gen_range(1..=sides as i16)).sum()
+}
+
+pub fn d6(rng: &mut impl Rng) -> u8 {
+    rng.gen_range(1..=6)
+}
+
+// An `NdM+K` expression as written in stat blocks and item data: "1d6", "2d4+1", "d8",
+// "1d6-1" or a flat "3". A count of zero means no dice, just the modifier. Parsing
+// turns away anything that could total more than an i16 holds, like "255d255".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiceExpr {
+    pub count: u8,
+    pub sides: u8,
+    pub modifier: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceParseError {
+    pub input: String,
+    pub reason: &'static str,
+}
+
+impl DiceExpr {
+    pub const fn new(count: u8, sides: u8, modifier: i16) -> Self {
+        Self { count, sides, modifier }
+    }
+
+    pub const fn flat(value: i16) -> Self {
+        Self::new(0, 0, value)
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> i16 {
+        roll(rng, self.count, self.sides) + self.modifier
+    }
+
+    pub fn min(&self) -> i16 {
+        self.count as i16 + self.modifier
+    }
+
+    pub fn max(&self) -> i16 {
+        self.count as i16 * self.sides as i16 + self.modifier
+    }
+
+    pub fn average(&self) -> f32 {
+        self.count as f32 * (self.sides as f32 + 1.0) / 2.0 + self.modifier as f32
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = DiceParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = |reason| DiceParseError {
+            input: input.to_string(),
+            reason,
+        };
+        let text: String = input.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+        if text.is_empty() {
+            return Err(error("empty expression"));
+        }
+
+        // Split off the modifier at the last sign that isn't leading
+        let (dice, modifier) = match text.rfind(['+', '-']).filter(|&index| index > 0) {
+            Some(index) => {
+                let modifier = text[index..].parse::<i16>().map_err(|_| error("invalid modifier"))?;
+                (&text[..index], modifier)
+            }
+            None => (text.as_str(), 0),
+        };
+
+        let Some((count, sides)) = dice.split_once('d') else {
+            let value = dice.parse::<i16>().map_err(|_| error("expected NdM or a number"))?;
+            return value.checked_add(modifier).map(Self::flat).ok_or_else(|| error("total out of range"));
+        };
+
+        let count = if count.is_empty() {
+            1
+        } else {
+            count.parse::<u8>().map_err(|_| error("invalid dice count"))?
+        };
+        let sides = sides.parse::<u8>().map_err(|_| error("invalid die size"))?;
+        if sides == 0 {
+            return Err(error("dice need at least one side"));
+        }
+        let (min, max) = (count as i32 + modifier as i32, count as i32 * sides as i32 + modifier as i32);
+        if min < i16::MIN as i32 || max > i16::MAX as i32 {
+            return Err(error("total out of range"));
+        }
+
+        Ok(Self::new(count, sides, modifier))
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "{}", self.modifier);
+        }
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            modifier if modifier > 0 => write!(f, "+{}", modifier),
+            modifier => write!(f, "{}", modifier),
+        }
+    }
+}
+
+// Written out in data files the way they're written everywhere else, "2d4+1"
+impl Serialize for DiceExpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DiceExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for DiceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid dice expression \"{}\": {}", self.input, self.reason)
+    }
+}
+
+impl std::error::Error for DiceParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_block_dice() {
+        assert_eq!("2d4+1".parse(), Ok(DiceExpr::new(2, 4, 1)));
+        assert_eq!("d8".parse(), Ok(DiceExpr::new(1, 8, 0)));
+        assert_eq!("1d6-1".parse(), Ok(DiceExpr::new(1, 6, -1)));
+        assert_eq!("3".parse(), Ok(DiceExpr::flat(3)));
+        assert_eq!("2d4+1".parse::<DiceExpr>().unwrap().to_string(), "2d4+1");
+    }
+
+    #[test]
+    fn turns_away_totals_too_big_to_roll() {
+        assert!("255d255".parse::<DiceExpr>().is_err());
+        assert!("10d6+32767".parse::<DiceExpr>().is_err());
+        assert!("30000+30000".parse::<DiceExpr>().is_err());
+        let most: DiceExpr = "128d255".parse().unwrap();
+        assert_eq!(most.max(), 32640);
+    }
+}
@@ -0,0 +1,62 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::dice;
+
+// Dungeon doors, B/X style. A closed door may turn out to be stuck (2 in 6) when the
+// party first tries it, and a stuck door only gives to an open-doors roll: a d6 at or
+// under a number set by Strength. Bashing at a door is loud whether or not it gives.
+// A locked door needs a key or a thief's Open Locks; it can't be forced.
+
+// Chance in six a closed door turns out to be stuck
+pub const STUCK_CHANCE_IN_SIX: u8 = 2;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DoorState {
+    #[default]
+    Closed,
+    Stuck,
+    Locked,
+    Open,
+}
+
+impl DoorState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DoorState::Closed => "closed",
+            DoorState::Stuck => "stuck",
+            DoorState::Locked => "locked",
+            DoorState::Open => "open",
+        }
+    }
+}
+
+// Open doors is 1 on a d6 for the weak, 1-2 for most and up to 1-5 at Strength 18
+pub fn open_doors_chance(strength: u8) -> u8 {
+    match strength {
+        0..=8 => 1,
+        9..=12 => 2,
+        13..=15 => 3,
+        16..=17 => 4,
+        _ => 5,
+    }
+}
+
+pub fn roll_stuck(rng: &mut impl Rng) -> bool {
+    dice::d6(rng) <= STUCK_CHANCE_IN_SIX
+}
+
+impl Character {
+    // Any key opens a lock; it isn't used up
+    pub fn has_key(&self) -> bool {
+        self.inventory
+            .items
+            .iter()
+            .any(|item| item.name.to_lowercase().split_whitespace().any(|word| word == "key"))
+    }
+
+    pub fn force_door(&self, rng: &mut impl Rng) -> bool {
+        dice::d6(rng) <= open_doors_chance(self.stats.strength)
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::monster::EnemyData;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DungeonData {
+    pub name: String,
+    pub description: String,
+    pub rooms: Vec<RoomData>,
+    pub encounters: Vec<EncounterData>,
+    pub treasures: Vec<TreasureData>,
+    pub connections: Vec<RoomConnection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomData {
+    pub id: u32,
+    pub name: String,
+    pub description: String,
+    pub room_type: RoomType,
+    pub contents: Vec<String>,
+    pub exits: Vec<ExitData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")] // matches the AI service's room_type strings
+pub enum RoomType {
+    Entrance,
+    Corridor,
+    Chamber,
+    Treasury,
+    Boss,
+    Trap,
+    Empty,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitData {
+    pub direction: String,
+    pub destination_room: u32,
+    pub is_secret: bool,
+    pub is_locked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomConnection {
+    pub from_room: u32,
+    pub to_room: u32,
+    pub direction: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncounterData {
+    pub room_id: u32,
+    pub enemies: Vec<EnemyData>,
+    pub difficulty: u8,
+    pub is_ambush: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasureData {
+    pub room_id: u32,
+    pub items: Vec<String>,
+    pub gold: u32,
+    pub is_hidden: bool,
+    pub trap_difficulty: Option<u8>,
+}
@@ -0,0 +1,456 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::battle_map::GridPosition;
+use crate::doors::DoorState;
+use crate::dungeon::{DungeonData, RoomType};
+use crate::traps::{Trap, TrapKind, TrapState};
+
+// Lays a generated dungeon out on a grid of tiles. The AI describes rooms and the
+// exits between them by compass direction, not coordinates, so each room gets a cell
+// of a coarse grid: the entrance at the origin and every other room one cell from the
+// room it was reached from, in the direction of the exit. Rooms are carved into their
+// cells and joined by corridors, with a door where an exit leads into a room. Trap
+// rooms get a trap in the middle of the floor, pit or falling block by turns, and a
+// trapped treasure a poison needle in its chest, which stands in the middle of the room
+// (a square to the west if the room has a trap of its own).
+
+// Each tile is a 10' square, as on dungeon graph paper
+pub const FEET_PER_TILE: u32 = 10;
+// Tiles across a room; corridor rooms are smaller
+pub const ROOM_SIZE: i32 = 5;
+pub const CORRIDOR_ROOM_SIZE: i32 = 3;
+// Tiles across a cell: a room and the passage to the next one
+const CELL_SIZE: i32 = ROOM_SIZE + 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Tile {
+    Wall,
+    Floor,
+    Door,
+}
+
+// The rectangle a room takes up, in tiles
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomArea {
+    pub room_id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+// A door on the way from one room into another, from an exit in the dungeon data. Every
+// door starts out closed, or locked if the exit was.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Door {
+    pub position: GridPosition,
+    pub from_room: u32,
+    pub to_room: u32,
+    pub is_secret: bool,
+    pub is_locked: bool,
+    #[serde(default)]
+    pub state: DoorState,
+}
+
+// Stairs up or down out of a room, from an exit with no compass direction. They're drawn
+// in the room's top right corner.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Stairs {
+    pub position: GridPosition,
+    pub room_id: u32,
+    pub to_room: u32,
+    pub down: bool,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DungeonMap {
+    pub width: i32,
+    pub height: i32,
+    // Row by row, `width` tiles to a row
+    pub tiles: Vec<Tile>,
+    pub rooms: Vec<RoomArea>,
+    pub doors: Vec<Door>,
+    #[serde(default)]
+    pub stairs: Vec<Stairs>,
+    #[serde(default)]
+    pub traps: Vec<Trap>,
+    pub entrance: GridPosition,
+}
+
+// A room reached from another: its id, the direction and, for an exit, (is_secret, is_locked)
+type Neighbour = (u32, String, Option<(bool, bool)>);
+
+// A passage to carve between two rooms once they are placed
+struct Link {
+    from_room: u32,
+    to_room: u32,
+    door: Option<(bool, bool)>,
+}
+
+impl RoomArea {
+    pub fn contains(&self, position: &GridPosition) -> bool {
+        (self.x..self.x + self.width).contains(&position.x) && (self.y..self.y + self.height).contains(&position.y)
+    }
+
+    pub fn center(&self) -> GridPosition {
+        GridPosition::new(self.x + self.width / 2, self.y + self.height / 2)
+    }
+}
+
+// Cell offset for an exit direction. Up and down (stairs) and anything the AI makes up
+// have no direction on the map.
+pub fn direction_offset(direction: &str) -> Option<(i32, i32)> {
+    match direction.to_lowercase().as_str() {
+        "north" | "n" => Some((0, -1)),
+        "south" | "s" => Some((0, 1)),
+        "east" | "e" => Some((1, 0)),
+        "west" | "w" => Some((-1, 0)),
+        "northeast" | "ne" => Some((1, -1)),
+        "northwest" | "nw" => Some((-1, -1)),
+        "southeast" | "se" => Some((1, 1)),
+        "southwest" | "sw" => Some((-1, 1)),
+        _ => None,
+    }
+}
+
+// Some(true) for a way down, Some(false) for a way up
+pub fn stairs_direction(direction: &str) -> Option<bool> {
+    let direction = direction.to_lowercase();
+    if direction_offset(&direction).is_some() {
+        None
+    } else if direction.contains("down") {
+        Some(true)
+    } else if direction.contains("up") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// The free cell nearest `wanted`, searching outward ring by ring
+fn nearest_free_cell(cells: &HashMap<(i32, i32), u32>, wanted: (i32, i32)) -> (i32, i32) {
+    for radius in 0i32.. {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx.abs().max(dy.abs()) != radius {
+                    continue;
+                }
+                let cell = (wanted.0 + dx, wanted.1 + dy);
+                if !cells.contains_key(&cell) {
+                    return cell;
+                }
+            }
+        }
+    }
+    unreachable!("there is always a free cell")
+}
+
+impl DungeonMap {
+    pub fn build(dungeon: &DungeonData) -> DungeonMap {
+        let mut cells: HashMap<(i32, i32), u32> = HashMap::new();
+        let mut placed: HashMap<u32, (i32, i32)> = HashMap::new();
+        let mut links = Vec::new();
+
+        // Exits first, then connections the rooms' exits don't already cover
+        let mut neighbours: HashMap<u32, Vec<Neighbour>> = HashMap::new();
+        for room in &dungeon.rooms {
+            for exit in &room.exits {
+                neighbours.entry(room.id).or_default().push((
+                    exit.destination_room,
+                    exit.direction.clone(),
+                    Some((exit.is_secret, exit.is_locked)),
+                ));
+            }
+        }
+        for connection in &dungeon.connections {
+            let listed = |from: u32, to: u32| {
+                neighbours
+                    .get(&from)
+                    .is_some_and(|exits| exits.iter().any(|(destination, _, _)| *destination == to))
+            };
+            if !listed(connection.from_room, connection.to_room) && !listed(connection.to_room, connection.from_room) {
+                neighbours
+                    .entry(connection.from_room)
+                    .or_default()
+                    .push((connection.to_room, connection.direction.clone(), None));
+            }
+        }
+
+        let entrance_id = dungeon
+            .rooms
+            .iter()
+            .find(|room| matches!(room.room_type, RoomType::Entrance))
+            .or(dungeon.rooms.first())
+            .map(|room| room.id);
+
+        // Rooms no exit reaches are set down near the origin, unconnected
+        let starts = entrance_id.into_iter().chain(dungeon.rooms.iter().map(|room| room.id));
+        for start in starts {
+            if placed.contains_key(&start) {
+                continue;
+            }
+            let cell = nearest_free_cell(&cells, (0, 0));
+            cells.insert(cell, start);
+            placed.insert(start, cell);
+
+            let mut queue = VecDeque::from([start]);
+            while let Some(room_id) = queue.pop_front() {
+                let from = placed[&room_id];
+                for (destination, direction, door) in neighbours.get(&room_id).into_iter().flatten() {
+                    if !dungeon.rooms.iter().any(|room| room.id == *destination) {
+                        continue;
+                    }
+                    // An exit back the way the party came shares the passage already there
+                    let returning = links
+                        .iter()
+                        .any(|link: &Link| link.from_room == *destination && link.to_room == room_id);
+                    if !returning {
+                        links.push(Link { from_room: room_id, to_room: *destination, door: *door });
+                    }
+                    if placed.contains_key(destination) {
+                        continue;
+                    }
+                    let (dx, dy) = direction_offset(direction).unwrap_or((1, 0));
+                    let cell = nearest_free_cell(&cells, (from.0 + dx, from.1 + dy));
+                    cells.insert(cell, *destination);
+                    placed.insert(*destination, cell);
+                    queue.push_back(*destination);
+                }
+            }
+        }
+
+        let min_x = cells.keys().map(|cell| cell.0).min().unwrap_or(0);
+        let min_y = cells.keys().map(|cell| cell.1).min().unwrap_or(0);
+        let max_x = cells.keys().map(|cell| cell.0).max().unwrap_or(0);
+        let max_y = cells.keys().map(|cell| cell.1).max().unwrap_or(0);
+        let width = (max_x - min_x + 1) * CELL_SIZE;
+        let height = (max_y - min_y + 1) * CELL_SIZE;
+
+        let mut map = DungeonMap {
+            width,
+            height,
+            tiles: vec![Tile::Wall; (width * height) as usize],
+            rooms: Vec::new(),
+            doors: Vec::new(),
+            stairs: Vec::new(),
+            traps: Vec::new(),
+            entrance: GridPosition::new(0, 0),
+        };
+
+        for room in &dungeon.rooms {
+            let Some(&(cell_x, cell_y)) = placed.get(&room.id) else {
+                continue;
+            };
+            let size = match room.room_type {
+                RoomType::Corridor => CORRIDOR_ROOM_SIZE,
+                _ => ROOM_SIZE,
+            };
+            let inset = (CELL_SIZE - 1 - size) / 2 + 1;
+            let area = RoomArea {
+                room_id: room.id,
+                x: (cell_x - min_x) * CELL_SIZE + inset,
+                y: (cell_y - min_y) * CELL_SIZE + inset,
+                width: size,
+                height: size,
+            };
+            for y in area.y..area.y + area.height {
+                for x in area.x..area.x + area.width {
+                    map.set(GridPosition::new(x, y), Tile::Floor);
+                }
+            }
+            map.rooms.push(area);
+        }
+
+        for link in &links {
+            map.carve_link(link);
+        }
+
+        for room in &dungeon.rooms {
+            let Some(area) = map.room_area(room.id).cloned() else {
+                continue;
+            };
+            let stairs = room
+                .exits
+                .iter()
+                .filter_map(|exit| stairs_direction(&exit.direction).map(|down| (exit.destination_room, down)));
+            for (index, (to_room, down)) in stairs.enumerate().take(area.width as usize) {
+                map.stairs.push(Stairs {
+                    position: GridPosition::new(area.x + area.width - 1 - index as i32, area.y),
+                    room_id: room.id,
+                    to_room,
+                    down,
+                });
+            }
+
+            let center = area.center();
+            if matches!(room.room_type, RoomType::Trap) {
+                let kind = if room.id % 2 == 0 { TrapKind::Pit } else { TrapKind::FallingBlock };
+                map.traps.push(Trap { position: center, room_id: room.id, kind, difficulty: 0, state: TrapState::Hidden });
+            }
+            let trapped = dungeon
+                .treasures
+                .iter()
+                .filter(|treasure| treasure.room_id == room.id)
+                .find_map(|treasure| treasure.trap_difficulty);
+            if let Some(difficulty) = trapped {
+                let position = if map.trap_at(&center).is_some() { center.offset(-1, 0) } else { center };
+                map.traps.push(Trap {
+                    position,
+                    room_id: room.id,
+                    kind: TrapKind::PoisonNeedle,
+                    difficulty,
+                    state: TrapState::Hidden,
+                });
+            }
+        }
+
+        map.entrance = entrance_id
+            .and_then(|id| map.room_area(id))
+            .map(RoomArea::center)
+            .unwrap_or(GridPosition::new(0, 0));
+        map
+    }
+
+    // An L-shaped passage from the centre of one room to the centre of the other, along
+    // and then across. The door, if any, goes on the last tile before the second room.
+    fn carve_link(&mut self, link: &Link) {
+        let (Some(from), Some(to)) = (self.room_area(link.from_room).cloned(), self.room_area(link.to_room).cloned())
+        else {
+            return;
+        };
+        let (start, end) = (from.center(), to.center());
+        let corner = GridPosition::new(end.x, start.y);
+        let mut path = Vec::new();
+        let mut position = start;
+        for target in [corner, end] {
+            while position != target {
+                position = position.step_toward(&target);
+                path.push(position);
+            }
+        }
+
+        let mut door_at = None;
+        for (index, position) in path.iter().enumerate() {
+            if self.room_at(position).is_none() {
+                if self.tile(position) == Tile::Wall {
+                    self.set(*position, Tile::Floor);
+                }
+                if path.get(index + 1).is_some_and(|next| to.contains(next)) {
+                    door_at = Some(*position);
+                }
+            }
+        }
+
+        let (Some((is_secret, is_locked)), Some(position)) = (link.door, door_at) else {
+            return;
+        };
+        if self.doors.iter().any(|door| door.position == position) {
+            return;
+        }
+        self.set(position, Tile::Door);
+        self.doors.push(Door {
+            position,
+            from_room: link.from_room,
+            to_room: link.to_room,
+            is_secret,
+            is_locked,
+            state: if is_locked { DoorState::Locked } else { DoorState::Closed },
+        });
+    }
+
+    pub fn contains(&self, position: &GridPosition) -> bool {
+        (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y)
+    }
+
+    // Outside the map is solid rock
+    pub fn tile(&self, position: &GridPosition) -> Tile {
+        if !self.contains(position) {
+            return Tile::Wall;
+        }
+        self.tiles[(position.y * self.width + position.x) as usize]
+    }
+
+    // Rock is solid and so is any door that isn't open
+    pub fn is_passable(&self, position: &GridPosition) -> bool {
+        match self.tile(position) {
+            Tile::Wall => false,
+            Tile::Floor => true,
+            Tile::Door => self.door_at(position).is_some_and(|door| door.state == DoorState::Open),
+        }
+    }
+
+    pub fn door_at_mut(&mut self, position: &GridPosition) -> Option<&mut Door> {
+        self.doors.iter_mut().find(|door| door.position == *position)
+    }
+
+    fn set(&mut self, position: GridPosition, tile: Tile) {
+        if self.contains(&position) {
+            self.tiles[(position.y * self.width + position.x) as usize] = tile;
+        }
+    }
+
+    pub fn room_area(&self, room_id: u32) -> Option<&RoomArea> {
+        self.rooms.iter().find(|area| area.room_id == room_id)
+    }
+
+    // The room a tile is in; None in the passages between rooms
+    pub fn room_at(&self, position: &GridPosition) -> Option<u32> {
+        self.rooms.iter().find(|area| area.contains(position)).map(|area| area.room_id)
+    }
+
+    pub fn door_at(&self, position: &GridPosition) -> Option<&Door> {
+        self.doors.iter().find(|door| door.position == *position)
+    }
+
+    pub fn stairs_at(&self, position: &GridPosition) -> Option<&Stairs> {
+        self.stairs.iter().find(|stairs| stairs.position == *position)
+    }
+
+    pub fn trap_at(&self, position: &GridPosition) -> Option<&Trap> {
+        self.traps.iter().find(|trap| trap.position == *position)
+    }
+
+    pub fn trap_at_mut(&mut self, position: &GridPosition) -> Option<&mut Trap> {
+        self.traps.iter_mut().find(|trap| trap.position == *position)
+    }
+
+    // '#' for rock, '.' for floor, '+' for closed doors, '/' for open ones, '>' and '<'
+    // for stairs down and up and '^' for traps that have been found or gone off. Secret
+    // doors look like the rock around them.
+    pub fn glyph(&self, position: &GridPosition) -> char {
+        match self.tile(position) {
+            Tile::Wall => '#',
+            Tile::Floor => match (self.stairs_at(position), self.trap_at(position)) {
+                (Some(stairs), _) if stairs.down => '>',
+                (Some(_), _) => '<',
+                (None, Some(trap)) if matches!(trap.state, TrapState::Found | TrapState::Sprung) => '^',
+                _ => '.',
+            },
+            Tile::Door => match self.door_at(position) {
+                Some(door) if door.is_secret => '#',
+                Some(door) if door.state == DoorState::Open => '/',
+                _ => '+',
+            },
+        }
+    }
+
+    // The whole map with `markers` drawn over the top
+    pub fn render(&self, markers: &[(GridPosition, char)]) -> String {
+        let mut rows = Vec::with_capacity(self.height as usize);
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| {
+                    let position = GridPosition::new(x, y);
+                    markers
+                        .iter()
+                        .find(|(marked, _)| *marked == position)
+                        .map_or_else(|| self.glyph(&position), |(_, marker)| *marker)
+                })
+                .collect();
+            rows.push(row);
+        }
+        rows.join("\n")
+    }
+}
@@ -0,0 +1,149 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::dice;
+
+// The moment two groups meet, before any blows: who is caught off guard, and how the
+// monsters take to the party. B/X rolls surprise for each side (1-2 on a d6) and a 2d6
+// reaction adjusted by the Charisma of whoever does the talking.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Side {
+    Party,
+    Enemies,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Reaction {
+    Attacks,
+    Hostile,
+    Uncertain,
+    Indifferent,
+    Friendly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncounterStart {
+    // The side that loses the first round; None if neither or both were surprised
+    pub surprised: Option<Side>,
+    pub reaction_roll: i16,
+    pub reaction: Reaction,
+}
+
+impl Side {
+    pub fn is_party(&self) -> bool {
+        matches!(self, Side::Party)
+    }
+}
+
+impl Reaction {
+    // 2: attacks, 3-5: hostile, 6-8: uncertain, 9-11: no attack, 12: friendly
+    pub fn from_roll(total: i16) -> Self {
+        match total {
+            i16::MIN..=2 => Reaction::Attacks,
+            3..=5 => Reaction::Hostile,
+            6..=8 => Reaction::Uncertain,
+            9..=11 => Reaction::Indifferent,
+            _ => Reaction::Friendly,
+        }
+    }
+
+    // Whether the encounter goes straight to blows
+    pub fn fights(&self) -> bool {
+        matches!(self, Reaction::Attacks | Reaction::Hostile)
+    }
+
+    // Whether the monsters will hold off and talk
+    pub fn parleys(&self) -> bool {
+        matches!(self, Reaction::Uncertain | Reaction::Friendly)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Reaction::Attacks => "attack at once",
+            Reaction::Hostile => "are hostile",
+            Reaction::Uncertain => "hesitate, uncertain",
+            Reaction::Indifferent => "ignore the party",
+            Reaction::Friendly => "seem friendly",
+        }
+    }
+}
+
+pub fn charisma_reaction_modifier(charisma: u8) -> i16 {
+    match charisma {
+        0..=3 => -2,
+        4..=8 => -1,
+        9..=12 => 0,
+        13..=17 => 1,
+        _ => 2,
+    }
+}
+
+pub fn roll_surprise(rng: &mut impl Rng) -> bool {
+    dice::d6(rng) <= 2
+}
+
+// `charisma` is the party spokesperson's score
+pub fn open_encounter(charisma: u8, rng: &mut impl Rng) -> EncounterStart {
+    let surprised = match (roll_surprise(rng), roll_surprise(rng)) {
+        (true, false) => Some(Side::Party),
+        (false, true) => Some(Side::Enemies),
+        _ => None,
+    };
+    let reaction_roll = dice::roll(rng, 2, 6) + charisma_reaction_modifier(charisma);
+
+    EncounterStart {
+        surprised,
+        reaction_roll,
+        reaction: Reaction::from_roll(reaction_roll),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn reactions_follow_the_2d6_table() {
+        let rolls = [-1, 2, 3, 5, 6, 8, 9, 11, 12, 14];
+        let reactions: Vec<Reaction> = rolls.into_iter().map(Reaction::from_roll).collect();
+        assert_eq!(
+            reactions,
+            [
+                Reaction::Attacks,
+                Reaction::Attacks,
+                Reaction::Hostile,
+                Reaction::Hostile,
+                Reaction::Uncertain,
+                Reaction::Uncertain,
+                Reaction::Indifferent,
+                Reaction::Indifferent,
+                Reaction::Friendly,
+                Reaction::Friendly,
+            ]
+        );
+    }
+
+    #[test]
+    fn charisma_shifts_the_reaction_roll() {
+        let modifiers: Vec<i16> = [3, 4, 8, 9, 12, 13, 17, 18].into_iter().map(charisma_reaction_modifier).collect();
+        assert_eq!(modifiers, [-2, -1, -1, 0, 0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn an_encounter_opens_with_surprise_and_a_reaction() {
+        let mut rng = StdRng::seed_from_u64(269);
+        let mut surprised = Vec::new();
+        for _ in 0..500 {
+            let start = open_encounter(18, &mut rng);
+            assert!((4..=14).contains(&start.reaction_roll));
+            assert_eq!(start.reaction, Reaction::from_roll(start.reaction_roll));
+            surprised.push(start.surprised);
+        }
+        for side in [None, Some(Side::Party), Some(Side::Enemies)] {
+            assert!(surprised.contains(&side), "{:?} never came up", side);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::item::{ArmorType, Item, ItemType};
+use crate::ruleset::Ruleset;
+
+// B/X detailed encumbrance. Everything carried is weighed in coins (10 coins to the
+// pound, item weights being in pounds), worn and wielded gear included, and the total
+// sets a movement rate. Armour caps the rate as well, so armour without a listed
+// weight still slows its wearer.
+
+pub const COINS_PER_POUND: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Encumbrance {
+    // Up to 400 coins
+    Unencumbered,
+    // Up to 800
+    Light,
+    // Up to 1200
+    Heavy,
+    // Up to 1600
+    Severe,
+    // More than 1600: can't move at all
+    Overloaded,
+}
+
+impl Encumbrance {
+    pub fn from_coins(coins: u32) -> Self {
+        match coins {
+            0..=400 => Encumbrance::Unencumbered,
+            401..=800 => Encumbrance::Light,
+            801..=1200 => Encumbrance::Heavy,
+            1201..=1600 => Encumbrance::Severe,
+            _ => Encumbrance::Overloaded,
+        }
+    }
+
+    // Encounter movement in feet per round
+    pub fn movement_rate(&self) -> u16 {
+        match self {
+            Encumbrance::Unencumbered => 40,
+            Encumbrance::Light => 30,
+            Encumbrance::Heavy => 20,
+            Encumbrance::Severe => 10,
+            Encumbrance::Overloaded => 0,
+        }
+    }
+
+    // With `Ruleset::encumbrance_penalties`, the heavier tiers cost AC and initiative
+    pub fn penalty(&self) -> i8 {
+        match self {
+            Encumbrance::Unencumbered | Encumbrance::Light => 0,
+            Encumbrance::Heavy => 1,
+            Encumbrance::Severe => 2,
+            Encumbrance::Overloaded => 3,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encumbrance::Unencumbered => "Unencumbered",
+            Encumbrance::Light => "Lightly encumbered",
+            Encumbrance::Heavy => "Heavily encumbered",
+            Encumbrance::Severe => "Severely encumbered",
+            Encumbrance::Overloaded => "Overloaded",
+        }
+    }
+}
+
+impl Character {
+    // Pounds of everything in the pack (and in the containers in it) and everything worn
+    pub fn carried_weight(&self) -> f32 {
+        self.inventory.items.iter().chain(self.equipment.worn()).map(Item::total_weight).sum()
+    }
+
+    // Brings the inventory's running weight back in line after items have moved around
+    pub fn recalculate_weight(&mut self) {
+        self.inventory.current_weight = self.carried_weight();
+    }
+
+    // The carried weight plus the coins in the purse
+    pub fn carried_coins(&self) -> u32 {
+        (self.carried_weight() * COINS_PER_POUND).round() as u32 + self.inventory.gold
+    }
+
+    pub fn encumbrance(&self) -> Encumbrance {
+        Encumbrance::from_coins(self.carried_coins())
+    }
+
+    // The slower of what the load allows and what the armour allows: 30' a round in
+    // leather, 20' in metal armour
+    pub fn movement_rate(&self) -> u16 {
+        self.movement_rate_carrying(0)
+    }
+
+    // The same with `extra` coins' weight of someone else's to carry
+    pub fn movement_rate_carrying(&self, extra: u32) -> u16 {
+        let armor_limit = match self.equipment.armor.as_ref().map(|item| &item.item_type) {
+            Some(ItemType::Armor(ArmorType::Chain | ArmorType::Plate)) => 20,
+            Some(ItemType::Armor(ArmorType::Leather)) => 30,
+            _ => 40,
+        };
+        Encumbrance::from_coins(self.carried_coins() + extra).movement_rate().min(armor_limit)
+    }
+
+    // How much the load costs in AC and initiative under the ruleset
+    pub fn encumbrance_penalty(&self, ruleset: &Ruleset) -> i8 {
+        if ruleset.encumbrance_penalties {
+            self.encumbrance().penalty()
+        } else {
+            0
+        }
+    }
+}
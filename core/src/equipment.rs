@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass, Equipment};
+use crate::item::{ArmorType, Item, ItemType, WeaponType};
+
+// Wearing and wielding. Items move between the inventory and the equipment slots, the
+// class decides what may be used, and armour class is recomputed on every change.
+// Cursed items stay on until a remove curse lifts them. A ring goes on either hand;
+// equipped without saying which, it takes the first free one.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Shield,
+    Helmet,
+    Cloak,
+    Boots,
+    LeftRing,
+    RightRing,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 8] = [
+        EquipmentSlot::Weapon,
+        EquipmentSlot::Armor,
+        EquipmentSlot::Shield,
+        EquipmentSlot::Helmet,
+        EquipmentSlot::Cloak,
+        EquipmentSlot::Boots,
+        EquipmentSlot::LeftRing,
+        EquipmentSlot::RightRing,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Weapon => "Weapon",
+            EquipmentSlot::Armor => "Armor",
+            EquipmentSlot::Shield => "Shield",
+            EquipmentSlot::Helmet => "Helmet",
+            EquipmentSlot::Cloak => "Cloak",
+            EquipmentSlot::Boots => "Boots",
+            EquipmentSlot::LeftRing => "Left ring",
+            EquipmentSlot::RightRing => "Right ring",
+        }
+    }
+
+    pub fn is_ring(&self) -> bool {
+        matches!(self, EquipmentSlot::LeftRing | EquipmentSlot::RightRing)
+    }
+
+    // Whether `item` can go in this slot
+    pub fn fits(&self, item: &Item) -> bool {
+        item.slot().is_some_and(|slot| slot == *self || slot.is_ring() && self.is_ring())
+    }
+}
+
+impl Item {
+    // Where the item goes when equipped, if it can be equipped at all; a ring can go on
+    // the right hand too
+    pub fn slot(&self) -> Option<EquipmentSlot> {
+        match self.item_type {
+            ItemType::Weapon(_) => Some(EquipmentSlot::Weapon),
+            ItemType::Armor(_) => Some(EquipmentSlot::Armor),
+            ItemType::Shield => Some(EquipmentSlot::Shield),
+            ItemType::Helmet => Some(EquipmentSlot::Helmet),
+            ItemType::Cloak => Some(EquipmentSlot::Cloak),
+            ItemType::Boots => Some(EquipmentSlot::Boots),
+            ItemType::Ring => Some(EquipmentSlot::LeftRing),
+            _ => None,
+        }
+    }
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&Item> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon.as_ref(),
+            EquipmentSlot::Armor => self.armor.as_ref(),
+            EquipmentSlot::Shield => self.shield.as_ref(),
+            EquipmentSlot::Helmet => self.helmet.as_ref(),
+            EquipmentSlot::Cloak => self.cloak.as_ref(),
+            EquipmentSlot::Boots => self.boots.as_ref(),
+            EquipmentSlot::LeftRing => self.left_ring.as_ref(),
+            EquipmentSlot::RightRing => self.right_ring.as_ref(),
+        }
+    }
+
+    // Everything worn or wielded
+    pub fn worn(&self) -> impl Iterator<Item = &Item> {
+        EquipmentSlot::ALL.into_iter().filter_map(|slot| self.get(slot))
+    }
+
+    fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<Item> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Shield => &mut self.shield,
+            EquipmentSlot::Helmet => &mut self.helmet,
+            EquipmentSlot::Cloak => &mut self.cloak,
+            EquipmentSlot::Boots => &mut self.boots,
+            EquipmentSlot::LeftRing => &mut self.left_ring,
+            EquipmentSlot::RightRing => &mut self.right_ring,
+        }
+    }
+}
+
+impl CharacterClass {
+    // B/X restrictions: magic-users fight only with a dagger or staff and wear no armour;
+    // clerics use only blunt weapons; thieves wear nothing heavier than leather and
+    // carry no shield
+    pub fn can_use(&self, item: &Item) -> Result<(), String> {
+        let allowed = match (self, &item.item_type) {
+            (CharacterClass::MagicUser, ItemType::Weapon(weapon)) => {
+                matches!(weapon, WeaponType::Dagger | WeaponType::Staff)
+            }
+            (CharacterClass::MagicUser, ItemType::Armor(armor)) => matches!(armor, ArmorType::Robes),
+            (CharacterClass::MagicUser, ItemType::Shield) => false,
+            (CharacterClass::Cleric, ItemType::Weapon(weapon)) => matches!(weapon, WeaponType::Mace | WeaponType::Staff),
+            (CharacterClass::Thief, ItemType::Armor(armor)) => matches!(armor, ArmorType::Leather | ArmorType::Robes),
+            (CharacterClass::Thief, ItemType::Shield) => false,
+            _ => true,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("A {} can't use {}", self.name(), item.name))
+        }
+    }
+}
+
+impl Character {
+    // Moves the inventory item at `index` into its slot; whatever was there goes back
+    // into the inventory. Carried weight doesn't change, since worn gear is still carried.
+    pub fn equip_item(&mut self, index: usize) -> Result<(), String> {
+        let item = self
+            .inventory
+            .items
+            .get(index)
+            .ok_or_else(|| format!("{} has no item {}", self.name, index + 1))?;
+        let slot = item.slot().ok_or_else(|| format!("{} can't be equipped", item.name))?;
+        let equipment = &self.equipment;
+        let slot = if slot.is_ring() && equipment.left_ring.is_some() && equipment.right_ring.is_none() {
+            EquipmentSlot::RightRing
+        } else {
+            slot
+        };
+        self.equip_item_to(index, slot)
+    }
+
+    // As equip_item, into a given slot. A cursed item already there stays put.
+    pub fn equip_item_to(&mut self, index: usize, slot: EquipmentSlot) -> Result<(), String> {
+        let item = self
+            .inventory
+            .items
+            .get(index)
+            .ok_or_else(|| format!("{} has no item {}", self.name, index + 1))?;
+        if item.slot().is_none() {
+            return Err(format!("{} can't be equipped", item.name));
+        }
+        if !slot.fits(item) {
+            return Err(format!("The {} doesn't go in the {} slot", item.name, slot.name().to_lowercase()));
+        }
+        self.class.can_use(item)?;
+        if let Some(worn) = self.equipment.get(slot).filter(|worn| worn.is_cursed()) {
+            return Err(format!("The {} won't come off", worn.name));
+        }
+
+        let item = self.inventory.items.remove(index);
+        if let Some(previous) = self.equipment.slot_mut(slot).replace(item) {
+            self.inventory.items.push(previous);
+        }
+        self.recalculate_armor_class();
+        Ok(())
+    }
+
+    // A cursed item won't come off until the curse is lifted
+    pub fn unequip_item(&mut self, slot: EquipmentSlot) -> Result<(), String> {
+        let worn = self.equipment.slot_mut(slot);
+        if let Some(item) = worn.as_ref().filter(|item| item.is_cursed()) {
+            return Err(format!("The {} won't come off", item.name));
+        }
+        let item = worn
+            .take()
+            .ok_or_else(|| format!("{} has nothing in the {} slot", self.name, slot.name().to_lowercase()))?;
+        self.inventory.items.push(item);
+        self.recalculate_armor_class();
+        Ok(())
+    }
+
+    // Takes off and gets rid of every cursed item, worn or carried; returns their names
+    pub fn lift_curses(&mut self) -> Vec<String> {
+        let mut lifted = Vec::new();
+        for slot in EquipmentSlot::ALL {
+            if self.equipment.get(slot).is_some_and(Item::is_cursed) {
+                if let Some(item) = self.equipment.slot_mut(slot).take() {
+                    self.inventory.current_weight = (self.inventory.current_weight - item.total_weight()).max(0.0);
+                    lifted.push(item.name);
+                }
+            }
+        }
+        while let Some(index) = self.inventory.items.iter().position(Item::is_cursed) {
+            if let Some(item) = self.inventory.remove_item(index) {
+                lifted.push(item.name);
+            }
+        }
+        self.recalculate_armor_class();
+        lifted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::ItemCatalog;
+    use crate::rng::GameRng;
+
+    fn sword(magic_bonus: Option<i8>) -> Item {
+        let mut sword = ItemCatalog::builtin().create("sword").unwrap();
+        sword.properties.magic_bonus = magic_bonus;
+        sword
+    }
+
+    // A fighter wielding a cursed sword, with a plain one in their pack
+    fn cursed_fighter() -> Character {
+        let mut rng = GameRng::from_seed(1);
+        let mut fighter = Character::new_with("Fighter".to_string(), CharacterClass::Fighter, &mut rng);
+        fighter.equipment.weapon = Some(sword(Some(-1)));
+        fighter.inventory.items.push(sword(None));
+        fighter
+    }
+
+    #[test]
+    fn cursed_items_wont_come_off() {
+        let mut fighter = cursed_fighter();
+        assert!(fighter.unequip_item(EquipmentSlot::Weapon).is_err());
+        assert!(fighter.equipment.weapon.as_ref().is_some_and(Item::is_cursed));
+    }
+
+    #[test]
+    fn cursed_items_cant_be_swapped_out() {
+        let mut fighter = cursed_fighter();
+        let index = fighter.inventory.items.len() - 1;
+        assert!(fighter.equip_item_to(index, EquipmentSlot::Weapon).is_err());
+        assert!(fighter.equipment.weapon.as_ref().is_some_and(Item::is_cursed));
+        assert!(!fighter.inventory.items[index].is_cursed());
+    }
+
+    #[test]
+    fn remove_curse_lifts_them() {
+        let mut fighter = cursed_fighter();
+        assert_eq!(fighter.lift_curses(), vec!["Sword".to_string()]);
+        let index = fighter.inventory.items.len() - 1;
+        assert!(fighter.equip_item_to(index, EquipmentSlot::Weapon).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::monster::EnemyData;
+
+// Experience for a won fight, as B/X gives it. Each monster slain is worth a set amount
+// for its Hit Dice, more for the pluses, and a bonus for each special ability it has
+// (the asterisks of the monster lists). Treasure carried off is worth a point a gold
+// piece, unless the ruleset says otherwise. The total is split among the party members
+// still standing, retainers taking half a share each.
+
+// Shares of the award: a party member's and a retainer's
+pub const MEMBER_SHARE: u32 = 2;
+pub const RETAINER_SHARE: u32 = 1;
+
+// The B/X table, a row for each band of Hit Dice: the last Hit Dice in it (and whether
+// with a plus), the base award and the bonus for each special ability. Rows are checked
+// in order; "under 1" is 0 Hit Dice.
+const XP_BY_HIT_DICE: [((u8, bool), u32, u32); 20] = [
+    ((0, true), 5, 1),
+    ((1, false), 10, 3),
+    ((1, true), 15, 4),
+    ((2, false), 20, 5),
+    ((2, true), 25, 10),
+    ((3, false), 35, 15),
+    ((3, true), 50, 25),
+    ((4, false), 75, 50),
+    ((4, true), 125, 75),
+    ((5, false), 175, 125),
+    ((5, true), 225, 175),
+    ((6, false), 275, 225),
+    ((6, true), 350, 300),
+    ((7, true), 450, 400),
+    ((8, true), 650, 550),
+    ((10, true), 900, 700),
+    ((12, true), 1100, 800),
+    ((16, true), 1350, 950),
+    ((20, true), 2000, 1150),
+    ((u8::MAX, true), 2500, 2000),
+];
+
+// (base, bonus a special ability) for a monster of `hit_dice`, with a plus or not
+pub fn hit_dice_xp(hit_dice: u8, plus: bool) -> (u32, u32) {
+    let (_, base, bonus) = XP_BY_HIT_DICE
+        .into_iter()
+        .find(|(up_to, _, _)| (hit_dice, plus) <= *up_to)
+        .unwrap_or(XP_BY_HIT_DICE[XP_BY_HIT_DICE.len() - 1]);
+    (base, bonus)
+}
+
+// What slaying the monster is worth: by its hit dice, or its level without them
+pub fn monster_xp(enemy: &EnemyData) -> u32 {
+    let (base, bonus) = match enemy.hit_dice() {
+        Some(dice) if dice.sides < 8 => hit_dice_xp(0, false),
+        Some(dice) => hit_dice_xp(dice.count, dice.modifier > 0),
+        None => hit_dice_xp(enemy.level, false),
+    };
+    base + bonus * enemy.abilities().len() as u32
+}
+
+// `total` split among members, true for the retainers among them; the odd points go to
+// the first
+pub fn share_out(total: u32, retainers: &[bool]) -> Vec<u32> {
+    let share = |retainer: bool| if retainer { RETAINER_SHARE } else { MEMBER_SHARE };
+    let shares: u32 = retainers.iter().map(|retainer| share(*retainer)).sum();
+    if shares == 0 {
+        return Vec::new();
+    }
+    let mut awards: Vec<u32> = retainers.iter().map(|retainer| total * share(*retainer) / shares).collect();
+    awards[0] += total - awards.iter().sum::<u32>();
+    awards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monster(hit_dice: &str, abilities: &[&str]) -> EnemyData {
+        EnemyData {
+            name: "Monster".to_string(),
+            hit_dice: Some(hit_dice.to_string()),
+            special_abilities: abilities.iter().map(|ability| ability.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn awards_by_hit_dice() {
+        let awards: Vec<u32> = ["1/2", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]
+            .into_iter()
+            .map(|hit_dice| monster_xp(&monster(hit_dice, &[])))
+            .collect();
+        assert_eq!(awards, [5, 10, 20, 35, 75, 175, 275, 450, 650, 900, 900]);
+    }
+
+    #[test]
+    fn pluses_earn_more_up_to_six() {
+        let awards: Vec<u32> = ["1+1", "2+1", "3+1", "4+1", "5+1", "6+1", "7+1", "8+2"]
+            .into_iter()
+            .map(|hit_dice| monster_xp(&monster(hit_dice, &[])))
+            .collect();
+        assert_eq!(awards, [15, 25, 50, 125, 225, 350, 450, 650]);
+    }
+
+    #[test]
+    fn special_abilities_earn_the_band_bonus() {
+        assert_eq!(monster_xp(&monster("1+1", &["paralysis"])), 15 + 4);
+        assert_eq!(monster_xp(&monster("6+1", &["paralysis"])), 350 + 300);
+        assert_eq!(monster_xp(&monster("10", &["paralysis"])), 900 + 700);
+    }
+
+    #[test]
+    fn large_monsters_go_by_bands() {
+        let awards: Vec<u32> = ["11", "12+3", "13", "16+1", "17", "20", "21", "30"]
+            .into_iter()
+            .map(|hit_dice| monster_xp(&monster(hit_dice, &[])))
+            .collect();
+        assert_eq!(awards, [1100, 1100, 1350, 1350, 2000, 2000, 2500, 2500]);
+    }
+
+    #[test]
+    fn retainers_take_half_shares() {
+        assert_eq!(share_out(500, &[false, false, true]), vec![200, 200, 100]);
+        assert_eq!(share_out(101, &[false, false]), vec![51, 50]);
+        assert!(share_out(100, &[]).is_empty());
+    }
+}
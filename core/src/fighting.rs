@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass};
+use crate::item::{Item, ItemType, WeaponType};
+
+// Ways of going about a fight beyond a plain swing, picked in the combat UI. A weapon in
+// each hand gives a second, off-hand attack, at -2 to hit with the main weapon and -4
+// with the other; it takes a spare one-handed weapon from the pack and a free hand, so
+// no shield. Fighting defensively costs 2 to hit for 2 points of AC until the round is
+// out, and parrying gives up the attack altogether for 4. And a fighter among foes of 1
+// Hit Die or less (goblins, kobolds, skeletons) cuts through them with an attack for
+// every level, sweeping on to the next one in reach as each falls.
+
+pub const DEFENSIVE_ARMOR_BONUS: i16 = 2;
+pub const PARRY_ARMOR_BONUS: i16 = 4;
+// Added to the roll with the off-hand weapon
+pub const OFF_HAND_TO_HIT: i16 = -4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FightingStyle {
+    #[default]
+    Normal,
+    TwoWeapons,
+    Defensive,
+}
+
+impl FightingStyle {
+    pub const ALL: [FightingStyle; 3] = [FightingStyle::Normal, FightingStyle::TwoWeapons, FightingStyle::Defensive];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FightingStyle::Normal => "normal",
+            FightingStyle::TwoWeapons => "two weapons",
+            FightingStyle::Defensive => "defensively",
+        }
+    }
+
+    pub fn next(self) -> FightingStyle {
+        let index = FightingStyle::ALL.iter().position(|style| *style == self).unwrap_or(0);
+        FightingStyle::ALL[(index + 1) % FightingStyle::ALL.len()]
+    }
+
+    // Added to the roll with the main weapon
+    pub fn to_hit(self) -> i16 {
+        match self {
+            FightingStyle::Normal => 0,
+            FightingStyle::TwoWeapons | FightingStyle::Defensive => -2,
+        }
+    }
+
+    // AC the fighter keeps up for the rest of the round
+    pub fn armor_bonus(self) -> i16 {
+        if self == FightingStyle::Defensive { DEFENSIVE_ARMOR_BONUS } else { 0 }
+    }
+}
+
+impl Character {
+    // The spare one-handed weapon two-weapon fighting takes: the first in the pack, with
+    // a melee weapon in hand and no shield
+    pub fn off_hand_weapon(&self) -> Option<&Item> {
+        let one_handed = |item: &Item| {
+            matches!(
+                item.item_type,
+                ItemType::Weapon(WeaponType::Sword | WeaponType::Axe | WeaponType::Mace | WeaponType::Dagger)
+            )
+        };
+        if self.equipment.shield.is_some() || !self.equipment.weapon.as_ref().is_some_and(one_handed) {
+            return None;
+        }
+        self.inventory.items.iter().find(|item| one_handed(item))
+    }
+
+    // Why the character can't fight in `style`, if they can't
+    pub fn style_refusal(&self, style: FightingStyle) -> Option<String> {
+        if style != FightingStyle::TwoWeapons || self.off_hand_weapon().is_some() {
+            return None;
+        }
+        Some(format!("{} needs a one-handed weapon in hand, a spare in the pack and no shield", self.name))
+    }
+
+    // Attacks against `foe` in a round: a fighter's level of them against a foe of 1 Hit
+    // Die or less, one otherwise
+    pub fn sweep_attacks(&self, foe: &Character) -> u8 {
+        if self.class == CharacterClass::Fighter && foe.level <= 1 {
+            self.level.max(1)
+        } else {
+            1
+        }
+    }
+}
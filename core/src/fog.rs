@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::battle_map::GridPosition;
+use crate::dungeon_map::DungeonMap;
+use crate::fov::field_of_view;
+
+// What the party knows of a dungeon's map. Every tile the party has seen is remembered
+// for as long as it stays in that dungeon (and in saves); of those, the ones in sight
+// right now are drawn at full brightness and the rest dimmed. Tiles never seen aren't
+// drawn at all. Notes the players put on the map are kept with it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sight {
+    Unexplored,
+    Remembered,
+    Visible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Annotation {
+    pub position: GridPosition,
+    pub text: String,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FogOfWar {
+    // The dungeon this belongs to, so a fresh one isn't mistaken for it
+    pub dungeon: String,
+    pub width: i32,
+    pub height: i32,
+    // Row by row, like the map's tiles
+    explored: Vec<bool>,
+    // Worked out again from wherever the party stands, so not saved
+    #[serde(skip)]
+    visible: Vec<bool>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl FogOfWar {
+    pub fn new(dungeon: &str, map: &DungeonMap) -> Self {
+        let tiles = (map.width * map.height) as usize;
+        Self {
+            dungeon: dungeon.to_string(),
+            width: map.width,
+            height: map.height,
+            explored: vec![false; tiles],
+            visible: vec![false; tiles],
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn belongs_to(&self, dungeon: &str, map: &DungeonMap) -> bool {
+        self.dungeon == dungeon && self.width == map.width && self.height == map.height
+    }
+
+    fn index(&self, position: &GridPosition) -> Option<usize> {
+        let inside = (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y);
+        inside.then(|| (position.y * self.width + position.x) as usize)
+    }
+
+    // `in_sight` replaces whatever was in sight before, and is remembered from now on
+    pub fn update(&mut self, in_sight: &[GridPosition]) {
+        self.visible = vec![false; self.explored.len()];
+        for position in in_sight {
+            if let Some(index) = self.index(position) {
+                self.visible[index] = true;
+                self.explored[index] = true;
+            }
+        }
+    }
+
+    pub fn sight(&self, position: &GridPosition) -> Sight {
+        let Some(index) = self.index(position) else {
+            return Sight::Unexplored;
+        };
+        if self.visible.get(index).copied().unwrap_or(false) {
+            Sight::Visible
+        } else if self.explored.get(index).copied().unwrap_or(false) {
+            Sight::Remembered
+        } else {
+            Sight::Unexplored
+        }
+    }
+
+    pub fn is_explored(&self, position: &GridPosition) -> bool {
+        self.sight(position) != Sight::Unexplored
+    }
+
+    // One note to a square; a new one replaces the old
+    pub fn annotate(&mut self, position: GridPosition, text: String) {
+        self.remove_annotation(&position);
+        self.annotations.push(Annotation { position, text });
+    }
+
+    pub fn remove_annotation(&mut self, position: &GridPosition) -> bool {
+        let before = self.annotations.len();
+        self.annotations.retain(|annotation| annotation.position != *position);
+        self.annotations.len() != before
+    }
+
+    // Numbered from 1 in the order they were made
+    pub fn annotation_at(&self, position: &GridPosition) -> Option<(usize, &Annotation)> {
+        self.annotations
+            .iter()
+            .enumerate()
+            .find(|(_, annotation)| annotation.position == *position)
+            .map(|(index, annotation)| (index + 1, annotation))
+    }
+
+    // The automap: `columns` by `rows` squares from `top_left`, explored squares drawn as
+    // on the dungeon map and the rest blank. Notes show as their number (1-9, then '*')
+    // and `markers` go over everything.
+    pub fn render_automap(
+        &self,
+        map: &DungeonMap,
+        top_left: GridPosition,
+        columns: i32,
+        rows: i32,
+        markers: &[(GridPosition, char)],
+    ) -> String {
+        let mut lines = Vec::with_capacity(rows.max(0) as usize);
+        for y in top_left.y..top_left.y + rows {
+            let line: String = (top_left.x..top_left.x + columns)
+                .map(|x| {
+                    let position = GridPosition::new(x, y);
+                    if let Some((_, marker)) = markers.iter().find(|(marked, _)| *marked == position) {
+                        *marker
+                    } else if let Some((number, _)) = self.annotation_at(&position) {
+                        char::from_digit(number as u32, 10).filter(|_| number < 10).unwrap_or('*')
+                    } else if self.is_explored(&position) {
+                        map.glyph(&position)
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+impl DungeonMap {
+    // What can be seen from `position` out to `radius` squares (see crate::fov). Rock and
+    // closed doors block sight; the walls and doors themselves are seen.
+    pub fn in_sight(&self, position: &GridPosition, radius: i32) -> Vec<GridPosition> {
+        field_of_view(*position, radius, |tile| !self.is_passable(tile))
+            .into_iter()
+            .filter(|tile| self.contains(tile))
+            .collect()
+    }
+}
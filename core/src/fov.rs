@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use crate::battle_map::GridPosition;
+
+// Field of view and line of sight on a square grid. Field of view is recursive
+// shadowcasting: each of the eight octants around the viewer is scanned row by row
+// outward, and every square that blocks sight narrows the arc still being scanned
+// beyond it. Squares that block sight are seen themselves (a wall, a closed door) but
+// hide what is behind them. Line of sight is a straight line between square centres.
+
+// Multipliers that turn an octant's (column, row) into a map offset
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+// Every square within `radius` squares of `origin` that can be seen from it
+pub fn field_of_view(
+    origin: GridPosition,
+    radius: i32,
+    blocks_sight: impl Fn(&GridPosition) -> bool,
+) -> HashSet<GridPosition> {
+    let mut visible = HashSet::from([origin]);
+    for octant in OCTANTS {
+        cast_light(origin, radius, 1, 1.0, 0.0, octant, &blocks_sight, &mut visible);
+    }
+    visible
+}
+
+// Scans one octant from `row` outward, between the slopes `start` and `end`
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    origin: GridPosition,
+    radius: i32,
+    row: i32,
+    mut start: f32,
+    end: f32,
+    (xx, xy, yx, yy): (i32, i32, i32, i32),
+    blocks_sight: &impl Fn(&GridPosition) -> bool,
+    visible: &mut HashSet<GridPosition>,
+) {
+    if start < end {
+        return;
+    }
+    let mut next_start = start;
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        for dx in -distance..=0 {
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start < right_slope {
+                continue;
+            }
+            if end > left_slope {
+                break;
+            }
+
+            let square = origin.offset(dx * xx + dy * xy, dx * yx + dy * yy);
+            if dx * dx + dy * dy <= radius * radius {
+                visible.insert(square);
+            }
+            let opaque = blocks_sight(&square);
+            if blocked {
+                if opaque {
+                    next_start = right_slope;
+                } else {
+                    blocked = false;
+                    start = next_start;
+                }
+            } else if opaque && distance < radius {
+                blocked = true;
+                cast_light(origin, radius, distance + 1, start, left_slope, (xx, xy, yx, yy), blocks_sight, visible);
+                next_start = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+// The squares on a straight line from `from` to `to`, both ends included
+pub fn line(from: GridPosition, to: GridPosition) -> Vec<GridPosition> {
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (step_x, step_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let mut square = from;
+    let mut squares = vec![from];
+    while square != to {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            square.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            square.y += step_y;
+        }
+        squares.push(square);
+    }
+    squares
+}
+
+// Whether nothing between the two squares blocks sight; the ends themselves don't count
+pub fn line_of_sight(from: GridPosition, to: GridPosition, blocks_sight: impl Fn(&GridPosition) -> bool) -> bool {
+    let squares = line(from, to);
+    squares.len() <= 2 || squares[1..squares.len() - 1].iter().all(|square| !blocks_sight(square))
+}
@@ -0,0 +1,70 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::dice;
+
+// Talk going round town. A rumor starts with whoever first passed it on, and each day
+// everyone who knows it has an even chance of telling someone else in town, who may
+// already have heard it. Rumors can be true or false, and nobody repeating one knows
+// which. After a fortnight a rumor is old news and forgotten.
+
+pub const RUMOR_LIFETIME_DAYS: u64 = 14;
+
+// In six, each day, that someone who knows a rumor passes it on
+pub const SPREAD_CHANCE: u8 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Gossip {
+    pub text: String,
+    pub truthful: bool,
+    // The site it points to, if any
+    #[serde(default)]
+    pub site: Option<String>,
+    // NPC ids of everyone in town who has heard it
+    pub known_to: Vec<String>,
+    // The day it started going round
+    pub started: u64,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Grapevine {
+    pub rumors: Vec<Gossip>,
+}
+
+impl Grapevine {
+    // Returns false for a rumor already going round
+    pub fn start(&mut self, gossip: Gossip) -> bool {
+        if self.rumors.iter().any(|rumor| rumor.text == gossip.text) {
+            return false;
+        }
+        self.rumors.push(gossip);
+        true
+    }
+
+    pub fn known_to<'a>(&'a self, npc_id: &'a str) -> impl Iterator<Item = &'a Gossip> + 'a {
+        self.rumors.iter().filter(move |rumor| rumor.known_to.iter().any(|id| id == npc_id))
+    }
+
+    // A day of gossip among `people`, by NPC id. Returns how many heard something new.
+    pub fn spread(&mut self, people: &[&str], today: u64, rng: &mut impl Rng) -> usize {
+        self.rumors.retain(|rumor| today.saturating_sub(rumor.started) < RUMOR_LIFETIME_DAYS);
+        let mut told = 0;
+        for rumor in &mut self.rumors {
+            for _ in 0..rumor.known_to.len() {
+                if dice::d6(rng) > SPREAD_CHANCE {
+                    continue;
+                }
+                let Some(listener) = people.choose(rng) else {
+                    continue;
+                };
+                if !rumor.known_to.iter().any(|id| id == listener) {
+                    rumor.known_to.push(listener.to_string());
+                    told += 1;
+                }
+            }
+        }
+        told
+    }
+}
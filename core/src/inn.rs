@@ -0,0 +1,114 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::dice;
+use crate::saves::SavingThrow;
+
+// The inn. A night's lodging is a gold piece a head, and a night in a bed rests the
+// party as safely as anywhere can. Buying the house a round loosens tongues for a rumor.
+// A character can also carouse: 1d6 x 100 gp per level spent on a wild night out,
+// which comes back as the same amount of experience. A character who can't cover the
+// night spends everything and lands in trouble; one who can still has to save vs.
+// death/poison or something goes wrong anyway, on the d8 mishap table.
+
+pub const ROOM_PRICE: u32 = 1;
+pub const ROUND_PRICE: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mishap {
+    // Every coin left on the character
+    Robbed(u32),
+    Fined(u32),
+    LostItem(String),
+    Brawl(i16),
+    GamblingDebt(u32),
+    MadeAnEnemy,
+    Tattooed,
+    Betrothed,
+}
+
+// One character's night out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Carousal {
+    pub spent: u32,
+    pub experience: u32,
+    pub mishap: Option<Mishap>,
+}
+
+impl Mishap {
+    pub fn describe(&self) -> String {
+        match self {
+            Mishap::Robbed(gold) => format!("wakes in an alley, robbed of {} gp", gold),
+            Mishap::Fined(gold) => format!("is hauled before the magistrate and fined {} gp", gold),
+            Mishap::LostItem(item) => format!("can't remember where the {} went", item),
+            Mishap::Brawl(damage) => format!("starts a brawl and takes {} damage", damage),
+            Mishap::GamblingDebt(gold) => format!("loses {} gp more at dice", gold),
+            Mishap::MadeAnEnemy => "insults someone important, who won't forget it".to_string(),
+            Mishap::Tattooed => "wakes up with a tattoo of uncertain meaning".to_string(),
+            Mishap::Betrothed => "is somehow engaged to be married".to_string(),
+        }
+    }
+}
+
+// Takes `amount` from the party's pooled purses, in order, or nothing if it's short
+pub fn pay_from_party(members: &mut [&mut Character], amount: u32) -> Result<(), String> {
+    let purse: u32 = members.iter().map(|character| character.inventory.gold).sum();
+    if purse < amount {
+        return Err(format!("{} gp needed, only {} gp in the party's purses", amount, purse));
+    }
+    let mut owed = amount;
+    for character in members.iter_mut() {
+        let paid = owed.min(character.inventory.gold);
+        character.inventory.gold -= paid;
+        owed -= paid;
+    }
+    Ok(())
+}
+
+// None if the character has no money to spend
+pub fn carouse(character: &mut Character, rng: &mut impl Rng) -> Option<Carousal> {
+    if character.inventory.gold == 0 {
+        return None;
+    }
+    let cost = dice::roll(rng, 1, 6) as u32 * 100 * character.level.max(1) as u32;
+    let spent = cost.min(character.inventory.gold);
+    character.inventory.gold -= spent;
+    let experience = character.gain_experience(spent);
+
+    let in_trouble = spent < cost || !character.roll_save(SavingThrow::DeathPoison, rng).success;
+    let mishap = in_trouble.then(|| roll_mishap(character, rng));
+    Some(Carousal { spent, experience, mishap })
+}
+
+// Rolls on the mishap table and applies the result to the character
+pub fn roll_mishap(character: &mut Character, rng: &mut impl Rng) -> Mishap {
+    let gold = &mut character.inventory.gold;
+    match dice::roll(rng, 1, 8) {
+        1 => Mishap::Robbed(std::mem::take(gold)),
+        2 => {
+            let fine = (dice::roll(rng, 1, 6) as u32 * 10).min(*gold);
+            *gold -= fine;
+            Mishap::Fined(fine)
+        }
+        3 if !character.inventory.items.is_empty() => {
+            let index = rng.gen_range(0..character.inventory.items.len());
+            let item = character.inventory.remove_item(index).map(|item| item.name).unwrap_or_default();
+            Mishap::LostItem(item)
+        }
+        3 | 4 => {
+            // Bruised, not killed
+            let damage = dice::roll(rng, 1, 6).min(character.hit_points.current - 1).max(0);
+            character.take_damage(damage);
+            Mishap::Brawl(damage)
+        }
+        5 => {
+            let debt = (dice::roll(rng, 1, 4) as u32 * 25).min(*gold);
+            *gold -= debt;
+            Mishap::GamblingDebt(debt)
+        }
+        6 => Mishap::MadeAnEnemy,
+        7 => Mishap::Tattooed,
+        _ => Mishap::Betrothed,
+    }
+}
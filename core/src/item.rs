@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::catalog::ItemCatalog;
+use crate::container::Container;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Item {
+    pub name: String,
+    pub item_type: ItemType,
+    pub weight: f32,
+    pub value: u32,
+    pub properties: ItemProperties,
+    // Backpacks, sacks and chests hold other items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+    // What's left of a consumable with several uses, like a week of rations. For a
+    // stack, what's left of the one in use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uses: Option<u16>,
+    // How many there are in the stack (see stack.rs); `weight` and `value` are for one
+    #[serde(default = "single", skip_serializing_if = "is_single")]
+    pub quantity: u16,
+}
+
+fn single() -> u16 {
+    1
+}
+
+fn is_single(quantity: &u16) -> bool {
+    *quantity == 1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemType {
+    Weapon(WeaponType),
+    Armor(ArmorType),
+    Shield,
+    Helmet,
+    Ring,
+    Cloak,
+    Boots,
+    Potion,
+    Scroll,
+    Treasure,
+    Misc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponType {
+    Sword,
+    Axe,
+    Mace,
+    Bow,
+    Crossbow,
+    Staff,
+    Dagger,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArmorType {
+    Leather,
+    Chain,
+    Plate,
+    Robes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemProperties {
+    pub damage: Option<String>, // e.g., "1d6", "1d8+1"
+    pub armor_bonus: Option<i8>,
+    pub magic_bonus: Option<i8>,
+    pub effects: Vec<String>,
+    // Whether the magic bonus and effects are known; items found unidentified show only
+    // that they are magical
+    #[serde(default = "default_identified")]
+    pub identified: bool,
+}
+
+fn default_identified() -> bool {
+    true
+}
+
+impl Item {
+    // Armour class this piece adds when worn: its own `armor_bonus` if it has one,
+    // otherwise what the item catalog gives its type, plus any magic bonus
+    pub fn armor_class_bonus(&self) -> i8 {
+        let base = self.properties.armor_bonus.or_else(|| {
+            let armor = matches!(self.item_type, ItemType::Armor(_) | ItemType::Shield | ItemType::Helmet);
+            armor.then(|| ItemCatalog::builtin().of_type(&self.item_type)?.properties.armor_bonus).flatten()
+        });
+        base.unwrap_or(0) + self.magic_bonus()
+    }
+
+    pub fn magic_bonus(&self) -> i8 {
+        self.properties.magic_bonus.unwrap_or(0)
+    }
+
+    pub fn is_magic(&self) -> bool {
+        self.properties.magic_bonus.is_some_and(|bonus| bonus != 0) || !self.properties.effects.is_empty()
+    }
+
+    // A magic item with a penalty, which its owner can't be rid of without a remove curse
+    pub fn is_cursed(&self) -> bool {
+        self.magic_bonus() < 0 || self.name.to_lowercase().contains("cursed")
+    }
+
+    pub fn is_missile_weapon(&self) -> bool {
+        matches!(self.item_type, ItemType::Weapon(WeaponType::Bow | WeaponType::Crossbow))
+    }
+}
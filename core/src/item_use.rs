@@ -0,0 +1,82 @@
+use rand::Rng;
+
+use crate::character::Character;
+use crate::dice::DiceExpr;
+use crate::item::{Item, ItemType};
+
+// Using and getting rid of what's in the pack. Effects are written in the item data as
+// "kind:argument"; a potion with "heal:1d6+1" heals that much when drunk. An item with
+// several uses loses one each time and is thrown away with the last; using one from a
+// stack takes it off the stack. A scroll with "raise_dead" is read over a body (see
+// `crate::death`).
+
+impl Item {
+    // The dice of a "heal:" effect, if the item has one
+    pub fn healing(&self) -> Option<DiceExpr> {
+        self.properties
+            .effects
+            .iter()
+            .find_map(|effect| effect.strip_prefix("heal:"))
+            .and_then(|dice| dice.trim().parse().ok())
+    }
+
+    pub fn raises_dead(&self) -> bool {
+        self.properties.effects.iter().any(|effect| effect == "raise_dead")
+    }
+}
+
+impl Character {
+    // Uses the pack item at `index` and says what came of it
+    pub fn use_item(&mut self, index: usize, rng: &mut impl Rng) -> Result<String, String> {
+        let item = self
+            .inventory
+            .items
+            .get(index)
+            .ok_or_else(|| format!("{} has no item {}", self.name, index + 1))?;
+        let Some(dice) = item.healing() else {
+            return Err(format!("Nothing comes of using the {}", item.name.to_lowercase()));
+        };
+        let verb = if matches!(item.item_type, ItemType::Potion) { "drinks" } else { "uses" };
+        let name = item.name.to_lowercase();
+
+        let before = self.hit_points.current;
+        self.heal(dice.roll(rng));
+        self.use_up(index);
+        Ok(format!(
+            "{} {} the {} and recovers {} HP ({}/{})",
+            self.name,
+            verb,
+            name,
+            self.hit_points.current - before,
+            self.hit_points.current,
+            self.hit_points.maximum
+        ))
+    }
+
+    // Takes one use off the pack item at `index`, throwing it away once there are none
+    // left; from a stack, one item goes
+    pub fn use_up(&mut self, index: usize) {
+        let Some(item) = self.inventory.items.get_mut(index) else {
+            return;
+        };
+        match item.uses {
+            Some(uses) if uses > 1 => item.uses = Some(uses - 1),
+            _ => {
+                self.inventory.remove_one(index);
+            }
+        }
+    }
+
+    // Leaves the pack item at `index` behind; cursed items keep coming back
+    pub fn drop_item(&mut self, index: usize) -> Result<Item, String> {
+        let item = self
+            .inventory
+            .items
+            .get(index)
+            .ok_or_else(|| format!("{} has no item {}", self.name, index + 1))?;
+        if item.is_cursed() {
+            return Err(format!("The {} won't leave {}", item.name.to_lowercase(), self.name));
+        }
+        self.inventory.remove_item(index).ok_or_else(|| format!("{} has no item {}", self.name, index + 1))
+    }
+}
@@ -0,0 +1,51 @@
+// Rules for Old School AI, independent of any frontend. The Bevy game, the terminal
+// frontend and the headless simulator all drive these types. With the `bevy` feature
+// the ECS-facing types also derive `Component`/`Resource`.
+
+pub mod abilities;
+pub mod advancement;
+pub mod ailments;
+pub mod battle_map;
+pub mod character;
+pub mod clock;
+pub mod item;
+pub mod catalog;
+pub mod container;
+pub mod stack;
+pub mod equipment;
+pub mod item_use;
+pub mod gossip;
+pub mod encumbrance;
+pub mod combat;
+pub mod death;
+pub mod dice;
+pub mod fighting;
+pub mod provisions;
+pub mod quest;
+pub mod resistances;
+pub mod retainers;
+pub mod monster;
+pub mod dungeon;
+pub mod dungeon_map;
+pub mod doors;
+pub mod encounter;
+pub mod experience;
+pub mod fog;
+pub mod fov;
+pub mod inn;
+pub mod light;
+pub mod rng;
+pub mod rounds;
+pub mod ruleset;
+pub mod saves;
+pub mod spells;
+pub mod temple;
+pub mod town;
+pub mod traps;
+pub mod treasure;
+pub mod wandering;
+pub mod wilderness;
+pub mod world;
+pub mod simulation;
+pub mod shop;
+pub mod skills;
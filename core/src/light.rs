@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass};
+use crate::dungeon::RoomData;
+
+// Light in the dungeon. A torch burns for 6 turns (an hour) and a flask of oil keeps a
+// lantern going for 24; either lights 30' around the party. Without light the party
+// sees nothing of a room beyond arm's reach and fights at -4, except dwarves and elves,
+// whose infravision picks out warm shapes to 60'.
+
+pub const DARKNESS_ATTACK_PENALTY: i16 = 4;
+pub const LIGHT_RADIUS_FEET: u16 = 30;
+// How far a character feels their way in the dark
+const TOUCH_FEET: u16 = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LightKind {
+    Torch,
+    Lantern,
+}
+
+// A light that is burning, and how many ten-minute turns it has left
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LitLight {
+    pub kind: LightKind,
+    pub turns_left: u16,
+}
+
+impl LightKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightKind::Torch => "Torch",
+            LightKind::Lantern => "Lantern",
+        }
+    }
+
+    pub fn burn_turns(&self) -> u16 {
+        match self {
+            LightKind::Torch => 6,
+            LightKind::Lantern => 24,
+        }
+    }
+
+    // The item used up when the light is lit
+    pub fn fuel(&self) -> &'static str {
+        match self {
+            LightKind::Torch => "Torch",
+            LightKind::Lantern => "Flask of Oil",
+        }
+    }
+}
+
+impl LitLight {
+    pub fn new(kind: LightKind) -> Self {
+        Self {
+            kind,
+            turns_left: kind.burn_turns(),
+        }
+    }
+
+    // Burns `turns` turns of fuel. Returns false once the light has gone out.
+    pub fn burn(&mut self, turns: u16) -> bool {
+        self.turns_left = self.turns_left.saturating_sub(turns);
+        self.turns_left > 0
+    }
+}
+
+impl CharacterClass {
+    // Infravision range in feet
+    pub fn infravision(&self) -> u16 {
+        match self {
+            CharacterClass::Dwarf | CharacterClass::Elf => 60,
+            _ => 0,
+        }
+    }
+}
+
+impl Character {
+    pub fn has_infravision(&self) -> bool {
+        self.class.infravision() > 0
+    }
+
+    // How far the character can see, in feet, with or without the party's light
+    pub fn sight_radius(&self, lit: bool) -> u16 {
+        let light = if lit { LIGHT_RADIUS_FEET } else { TOUCH_FEET };
+        light.max(self.class.infravision())
+    }
+
+    // Uses up a torch, or a flask of oil for a lantern the character is carrying
+    pub fn light(&mut self, kind: LightKind) -> Result<LitLight, String> {
+        let carried = |name: &str| {
+            self.inventory
+                .items
+                .iter()
+                .position(|item| item.name.eq_ignore_ascii_case(name))
+        };
+        if kind == LightKind::Lantern && carried(kind.name()).is_none() {
+            return Err(format!("{} has no lantern", self.name));
+        }
+        let fuel = carried(kind.fuel()).ok_or_else(|| format!("{} has no {}", self.name, kind.fuel().to_lowercase()))?;
+
+        self.inventory.remove_one(fuel);
+        Ok(LitLight::new(kind))
+    }
+}
+
+// What `character` loses on attack rolls when the party has no light
+pub fn darkness_attack_penalty(character: &Character, dark: bool) -> i16 {
+    if dark && !character.has_infravision() {
+        DARKNESS_ATTACK_PENALTY
+    } else {
+        0
+    }
+}
+
+// What the party can make out of a room: everything by light, only the room's shape
+// and anything warm in it by infravision, and nothing at all otherwise
+pub fn describe_room(room: &RoomData, lit: bool, infravision: bool) -> String {
+    if lit {
+        let mut text = format!("{}\n{}", room.name, room.description);
+        if !room.contents.is_empty() {
+            text.push_str(&format!("\nYou see: {}", room.contents.join(", ")));
+        }
+        text
+    } else if infravision {
+        format!("{}\nIn the dark, infravision shows only the walls and anything warm moving in them.", room.name)
+    } else {
+        "It is pitch dark. You can see nothing.".to_string()
+    }
+}
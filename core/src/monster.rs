@@ -0,0 +1,237 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::abilities::SpecialAbility;
+use crate::catalog::ItemCatalog;
+use crate::character::{Character, CharacterClass, CharacterStats, Equipment, HitPoints, Inventory, MemorizedSpell};
+use crate::combat::{DamageType, EffectType, StatusEffect, WHOLE_FIGHT};
+use crate::dice::DiceExpr;
+use crate::item::{Item, ItemProperties, ItemType};
+use crate::provisions::Sustenance;
+use crate::resistances::Material;
+
+// A monster's stat block, as the AI service writes it and as the bestiary files keep it.
+// The B/X lines the service doesn't send (hit dice, movement, morale and treasure type)
+// are optional. An encounter can name a bestiary monster by `id` and leave the rest out,
+// in which case the stat block comes from the bestiary (see `resolve`).
+pub const REGENERATION_PER_ROUND: i16 = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnemyData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub monster_type: String,
+    pub level: u8,
+    // Used as it is when there are no hit dice to roll
+    pub hit_points: i16,
+    pub armor_class: i8,
+    pub attacks: Vec<AttackData>,
+    pub special_abilities: Vec<String>,
+    pub loot_table: Vec<String>,
+    // "2", "1+1", "3-1" or "1/2", in eight-sided dice (four-sided for a half)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_dice: Option<String>,
+    // Feet a turn, as stat blocks give it: 120 is 40' a round
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movement: Option<u16>,
+    // 2 to 12; the higher, the longer it fights on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub morale: Option<u8>,
+    // A letter from the treasure tables, A to V
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub treasure_type: Option<String>,
+    // Kinds of damage it takes half of, none of or double; see `crate::resistances`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub resistances: Vec<DamageType>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub immunities: Vec<DamageType>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vulnerabilities: Vec<DamageType>,
+    // "silver" or "magic", for a monster ordinary weapons don't harm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub harmed_by: Option<Material>,
+    // Kinds of damage its regeneration can't mend
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub regeneration_stopped_by: Vec<DamageType>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttackData {
+    pub name: String,
+    pub damage: String, // e.g., "1d6+1"
+    pub attack_bonus: i8,
+    pub range: String,
+}
+
+impl EnemyData {
+    // The hit dice as dice to roll, if there are any that make sense
+    pub fn hit_dice(&self) -> Option<DiceExpr> {
+        let text: String = self.hit_dice.as_deref()?.chars().filter(|c| !c.is_whitespace()).collect();
+        if text == "1/2" {
+            return Some(DiceExpr::new(1, 4, 0));
+        }
+        let (count, modifier) = match text.find(['+', '-']) {
+            Some(index) => (&text[..index], text[index..].parse::<i16>().ok()?),
+            None => (text.as_str(), 0),
+        };
+        Some(DiceExpr::new(count.parse().ok()?, 8, modifier))
+    }
+
+    // One of these monsters as met: hit points rolled from the hit dice, and the level
+    // (what it attacks and saves as) the number of them. Without hit dice the stat block
+    // is used as it is.
+    pub fn roll(&self, rng: &mut impl Rng) -> EnemyData {
+        let Some(dice) = self.hit_dice() else {
+            return self.clone();
+        };
+        EnemyData {
+            level: dice.count.max(1),
+            hit_points: dice.roll(rng).max(1),
+            ..self.clone()
+        }
+    }
+
+    // What the monster fights with from the start: trolls regenerate 3 hit points a round
+    pub fn innate_effects(&self) -> Vec<StatusEffect> {
+        self.abilities()
+            .into_iter()
+            .filter(|ability| *ability == SpecialAbility::Regeneration)
+            .map(|_| StatusEffect {
+                name: "Regeneration".to_string(),
+                duration: WHOLE_FIGHT,
+                effect_type: EffectType::Healing,
+                magnitude: REGENERATION_PER_ROUND,
+            })
+            .collect()
+    }
+
+    // Feet a round in a fight: a third of the movement a turn, or what an unarmoured
+    // man manages for stat blocks that don't say
+    pub fn movement_rate(&self) -> u16 {
+        match self.movement {
+            Some(movement) => movement / 3,
+            None => monster_character(self).movement_rate(),
+        }
+    }
+
+    // The bestiary's stat block for a monster named by id, keeping any name the encounter
+    // gave it; `lookup` is the bestiary, and the built-in stat lines fill in for it.
+    // Monsters with no id, or one neither knows, are left as they are.
+    pub fn resolve<'a>(&self, lookup: impl Fn(&str) -> Option<&'a EnemyData>) -> EnemyData {
+        let found = self.id.as_deref().and_then(|id| lookup(id).cloned().or_else(|| builtin_monster(id)));
+        let Some(found) = found else {
+            return self.clone();
+        };
+        EnemyData {
+            id: self.id.clone(),
+            name: if self.name.is_empty() { found.name.clone() } else { self.name.clone() },
+            ..found
+        }
+    }
+}
+
+// Monster stat blocks use descending AC; the player rules compare against ascending AC,
+// so convert before the monster is handed to `roll_attack`.
+pub fn monster_character(enemy: &EnemyData) -> Character {
+    let stats = CharacterStats {
+        strength: 10,
+        dexterity: 10,
+        constitution: 10,
+        intelligence: 10,
+        wisdom: 10,
+        charisma: 10,
+    };
+    let hit_points = enemy.hit_points.max(1);
+    let innate_spells = enemy.innate_spells();
+
+    Character {
+        name: enemy.name.clone(),
+        class: CharacterClass::Fighter,
+        level: enemy.level.max(1),
+        experience: 0,
+        experience_bonus: 0,
+        stats,
+        hit_points: HitPoints {
+            current: hit_points,
+            maximum: hit_points,
+        },
+        armor_class: 19 - enemy.armor_class,
+        equipment: Equipment {
+            weapon: enemy.attacks.first().map(attack_item),
+            ..Equipment::default()
+        },
+        inventory: Inventory::default(),
+        // A spell-like ability is good for one casting a fight
+        memorized_spells: innate_spells
+            .iter()
+            .map(|spell| MemorizedSpell { name: spell.name.clone(), level: spell.level, cast: false })
+            .collect(),
+        spells: innate_spells,
+        sustenance: Sustenance::default(),
+        death: None,
+        recovery_days: 0,
+    }
+}
+
+// The monster's main attack, carried as its weapon so the attack's damage dice are
+// what `calculate_damage` rolls. Its type is the catalog's weapon of that family.
+fn attack_item(attack: &AttackData) -> Item {
+    let item_type = weapon_key(&attack.name)
+        .and_then(|kind| ItemCatalog::builtin().weapon(kind))
+        .map_or(ItemType::Misc, |weapon| weapon.item_type.clone());
+
+    Item {
+        name: attack.name.clone(),
+        item_type,
+        weight: 0.0,
+        value: 0,
+        properties: ItemProperties {
+            damage: Some(attack.damage.clone()),
+            armor_bonus: None,
+            magic_bonus: None,
+            effects: Vec::new(),
+            identified: true,
+        },
+        container: None,
+        uses: None,
+        quantity: 1,
+    }
+}
+
+// Monster attack names are free text ("Short Sword", "Battle Axe"), so pick out the
+// weapon family the damage tables know about. Natural attacks fall back to unarmed.
+pub fn weapon_key(attack_name: &str) -> Option<&'static str> {
+    let name = attack_name.to_lowercase();
+    ["crossbow", "sword", "axe", "mace", "dagger", "staff", "bow"]
+        .into_iter()
+        .find(|weapon| name.contains(weapon))
+}
+
+// Stat lines matching the AI service's enemy templates, for simulating without it
+pub fn builtin_monster(id: &str) -> Option<EnemyData> {
+    let (name, monster_type, level, hit_points, armor_class, attack, damage, attack_bonus) = match id.to_lowercase().as_str() {
+        "goblin" => ("Goblin", "Humanoid", 1, 8, 6, "Short Sword", "1d6", 0),
+        "orc" => ("Orc", "Humanoid", 2, 15, 7, "Battle Axe", "1d8", 1),
+        "skeleton" => ("Skeleton", "Undead", 1, 12, 7, "Short Sword", "1d6", 0),
+        "troll" => ("Troll", "Giant", 5, 35, 4, "Claw", "1d6+1", 2),
+        _ => return None,
+    };
+
+    Some(EnemyData {
+        id: Some(id.to_lowercase()),
+        name: name.to_string(),
+        monster_type: monster_type.to_string(),
+        level,
+        hit_points,
+        armor_class,
+        attacks: vec![AttackData {
+            name: attack.to_string(),
+            damage: damage.to_string(),
+            attack_bonus,
+            range: "melee".to_string(),
+        }],
+        ..EnemyData::default()
+    })
+}
@@ -0,0 +1,164 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::dice;
+use crate::item::Item;
+use crate::stack::RATION_DAYS;
+
+// Food and water on an expedition. Each day away from town every character eats a
+// day's rations and drinks a waterskin. A day without either stops natural healing;
+// after that, each further day without water costs a point of Constitution, and each
+// day past the second without food a point of Strength. Points come back one a day
+// once the character is eating and drinking again. In town food and water are to hand
+// and waterskins are refilled.
+
+// A full waterskin holds a day's water
+pub const WATERSKIN_DAYS: u16 = 1;
+
+// Days a character can go hungry before it starts to tell on Strength
+const FOOD_GRACE_DAYS: u16 = 2;
+// Abilities don't drop below this from hunger or thirst
+const ABILITY_FLOOR: u8 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Provision {
+    Food,
+    Water,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Sustenance {
+    pub days_without_food: u16,
+    pub days_without_water: u16,
+    // Ability points lost to hunger and thirst, to be recovered
+    pub lost_strength: u8,
+    pub lost_constitution: u8,
+}
+
+// What a day did to one character
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DailyUpkeep {
+    pub ate: bool,
+    pub drank: bool,
+    pub healed: i16,
+    pub strength_change: i8,
+    pub constitution_change: i8,
+}
+
+impl Item {
+    pub fn provision(&self) -> Option<Provision> {
+        let name = self.name.to_lowercase();
+        if name.contains("ration") {
+            Some(Provision::Food)
+        } else if name.contains("waterskin") {
+            Some(Provision::Water)
+        } else {
+            None
+        }
+    }
+}
+
+impl Sustenance {
+    pub fn is_starving(&self) -> bool {
+        self.days_without_food > 0 || self.days_without_water > 0
+    }
+}
+
+impl Character {
+    // Uses one day's worth of `kind` from the pack. Rations are thrown away once eaten,
+    // a pack at a time from a stack; an empty waterskin is kept for refilling.
+    pub fn consume_provision(&mut self, kind: Provision) -> bool {
+        let Some(index) = self
+            .inventory
+            .items
+            .iter()
+            .position(|item| item.provision() == Some(kind) && item.uses.unwrap_or(1) > 0)
+        else {
+            return false;
+        };
+
+        let item = &mut self.inventory.items[index];
+        let left = item.uses.unwrap_or(1) - 1;
+        item.uses = Some(left);
+        if left == 0 && kind == Provision::Food {
+            // The next pack in the stack is opened
+            if item.is_stack() {
+                item.uses = Some(RATION_DAYS);
+            }
+            self.inventory.remove_one(index);
+        }
+        true
+    }
+
+    pub fn refill_waterskins(&mut self) {
+        for item in &mut self.inventory.items {
+            if item.provision() == Some(Provision::Water) {
+                item.uses = Some(WATERSKIN_DAYS);
+            }
+        }
+    }
+
+    pub fn can_heal_naturally(&self) -> bool {
+        !self.sustenance.is_starving()
+    }
+
+    // A day's rest heals 1d3, if the character has eaten and drunk
+    pub fn natural_healing(&mut self, rng: &mut impl Rng) -> i16 {
+        if !self.can_heal_naturally() || self.hit_points.current >= self.hit_points.maximum {
+            return 0;
+        }
+        let before = self.hit_points.current;
+        self.heal(dice::roll(rng, 1, 3));
+        self.hit_points.current - before
+    }
+
+    // One day passes. `in_town` means food and water cost nothing from the pack, and
+    // `heals` is false for a character whose wounds won't close (mummy rot).
+    pub fn daily_upkeep(&mut self, in_town: bool, heals: bool, rng: &mut impl Rng) -> DailyUpkeep {
+        if in_town {
+            self.refill_waterskins();
+        }
+        let ate = in_town || self.consume_provision(Provision::Food);
+        let drank = in_town || self.consume_provision(Provision::Water);
+        let (strength, constitution) = (self.stats.strength, self.stats.constitution);
+        let sustenance = &mut self.sustenance;
+
+        if ate {
+            sustenance.days_without_food = 0;
+        } else {
+            sustenance.days_without_food += 1;
+            if sustenance.days_without_food > FOOD_GRACE_DAYS && self.stats.strength > ABILITY_FLOOR {
+                self.stats.strength -= 1;
+                sustenance.lost_strength += 1;
+            }
+        }
+        if drank {
+            sustenance.days_without_water = 0;
+        } else {
+            sustenance.days_without_water += 1;
+            if sustenance.days_without_water > 1 && self.stats.constitution > ABILITY_FLOOR {
+                self.stats.constitution -= 1;
+                sustenance.lost_constitution += 1;
+            }
+        }
+        if ate && drank {
+            if sustenance.lost_strength > 0 {
+                sustenance.lost_strength -= 1;
+                self.stats.strength += 1;
+            }
+            if sustenance.lost_constitution > 0 {
+                sustenance.lost_constitution -= 1;
+                self.stats.constitution += 1;
+            }
+        }
+
+        DailyUpkeep {
+            ate,
+            drank,
+            healed: if heals { self.natural_healing(rng) } else { 0 },
+            strength_change: self.stats.strength as i8 - strength as i8,
+            constitution_change: self.stats.constitution as i8 - constitution as i8,
+        }
+    }
+}
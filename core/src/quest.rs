@@ -0,0 +1,514 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Quests the party has taken on are active until their objectives are done or they
+// come to nothing. Quests offered in conversation wait in the log until the party
+// accepts or declines them; those from rumors are taken on as heard.
+//
+// Objectives are written out as the AI service words them, and read as one of four
+// kinds: kill some monsters, retrieve an item, visit a place, or talk to someone (see
+// Objective::parse). The game tells the log what the party does, and each deed ticks
+// off whatever it satisfies. Objectives can be done in any order, except that going
+// back ("Return to town", "Return it to the sage") only counts once the objectives
+// before it are done. A quest is complete once every objective that could be read is
+// done; the rest are colour, and go along with it.
+//
+// A quest with a time limit has to be done within that many days of the party taking
+// it on. The journal warns as the deadline nears, and a quest still active once it has
+// passed fails, costing the party the reputation it would have earned (at least 1).
+//
+// Seeing every objective through isn't the only way a quest ends (see Resolution).
+// The party can come to terms partway, sell out whoever gave the quest, or give up,
+// and each ending pays out and weighs on the party's good name differently. How each
+// quest ended stays in the log, and is what the party is known for in town.
+
+// How many days ahead of a deadline the party is warned
+pub const DEADLINE_WARNING_DAYS: i64 = 2;
+
+// How many of the party's latest quest endings the town talks about
+pub const REMEMBERED_OUTCOMES: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestData {
+    pub title: String,
+    pub description: String,
+    pub objectives: Vec<String>,
+    pub reward: QuestReward,
+    pub difficulty: u8,
+    pub time_limit: Option<u32>, // in game days
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestReward {
+    pub experience: u32,
+    pub gold: u32,
+    pub items: Vec<String>,
+    pub reputation_change: i8,
+}
+
+// What an objective asks of the party
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Objective {
+    Kill { monster: String, count: u32 },
+    Retrieve { item: String },
+    Visit { location: String },
+    TalkTo { npc: String },
+}
+
+// Something the party has done that may count towards an objective
+#[derive(Debug, Clone, Copy)]
+pub enum Deed<'a> {
+    Killed(&'a str),
+    // Names of everything the party is carrying
+    Holding(&'a [String]),
+    Visited(&'a str),
+    TalkedTo(&'a str),
+}
+
+// An objective a deed ticked off, by index in the log's quests and the quest's objectives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub quest: usize,
+    pub objective: usize,
+    // The last objective left, so the quest is complete
+    pub completes: bool,
+}
+
+// How a quest came to an end
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Resolution {
+    // Every objective done, for the whole reward
+    Fulfilled,
+    // Settled by a deal partway through, for half the experience and gold and half the
+    // good name. It takes at least one objective done to have something to bargain with.
+    Negotiated,
+    // Sold out whoever gave the quest, for the gold alone and twice the loss of face
+    Betrayed,
+    // Given up partway, for experience in proportion to the objectives done and a
+    // point of reputation
+    Abandoned,
+    // Left until its deadline passed, for nothing but the reputation it would have earned lost
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum QuestStatus {
+    #[default]
+    Active,
+    Completed,
+    Failed,
+}
+
+// A quest in the party's log. Saves from before quests were tracked hold just the
+// quest data, and load as active quests with nothing done.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quest {
+    #[serde(flatten)]
+    pub data: QuestData,
+    #[serde(default)]
+    pub status: QuestStatus,
+    // Who gave it, if anyone
+    #[serde(default)]
+    pub giver: Option<String>,
+    // Indices into the objectives of those done
+    #[serde(default)]
+    pub done: Vec<usize>,
+    // Monsters killed so far for kill objectives, by objective
+    #[serde(default)]
+    pub kills: BTreeMap<usize, u32>,
+    // The last day it can be done on, for a quest with a time limit
+    #[serde(default)]
+    pub deadline: Option<u64>,
+    // How it ended, once it has. Saves from before quests could end more than one way
+    // hold none; a completed one of those was fulfilled and a failed one abandoned.
+    #[serde(default)]
+    pub resolution: Option<Resolution>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestOffer {
+    pub giver: String,
+    pub quest: QuestData,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default)]
+pub struct QuestLog {
+    pub quests: Vec<Quest>,
+    pub offers: Vec<QuestOffer>,
+    // The party's good name, earned with quest rewards
+    pub reputation: i8,
+}
+
+impl Objective {
+    // Reads an objective the way the AI service writes them ("Kill 3 goblins", "Recover
+    // the tome", "Find the Barrow of Kings", "Speak with the sage", "Return to town");
+    // None for one it can't make out
+    pub fn parse(text: &str) -> Option<Objective> {
+        let lower = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+        let after = |prefixes: &[&str]| {
+            prefixes
+                .iter()
+                .find_map(|prefix| lower.strip_prefix(prefix))
+                .map(|rest| rest.trim().to_string())
+                .filter(|rest| !rest.is_empty())
+        };
+
+        if let Some(target) = after(&["kill ", "slay ", "defeat ", "destroy "]) {
+            let (count, monster) = match target.split_once(' ') {
+                Some((number, monster)) => match number.parse::<u32>() {
+                    Ok(count) => (count.max(1), monster.to_string()),
+                    Err(_) => (1, target.clone()),
+                },
+                None => (1, target.clone()),
+            };
+            return Some(Objective::Kill { monster: singular(&monster), count });
+        }
+        if let Some(npc) = after(&["talk to ", "speak with ", "speak to ", "return it to ", "return to "]) {
+            if npc != "town" {
+                return Some(Objective::TalkTo { npc });
+            }
+        }
+        if lower.starts_with("return") {
+            return Some(Objective::Visit { location: "town".to_string() });
+        }
+        if let Some(item) = after(&["retrieve ", "recover ", "bring back ", "fetch "]) {
+            return Some(Objective::Retrieve { item });
+        }
+        after(&["visit ", "find ", "explore ", "reach ", "go to "]).map(|location| Objective::Visit { location })
+    }
+
+    pub fn is_met_by(&self, deed: &Deed) -> bool {
+        match (self, deed) {
+            (Objective::Kill { monster, .. }, Deed::Killed(name)) => names(name, monster),
+            (Objective::Retrieve { item }, Deed::Holding(items)) => items.iter().any(|held| names(held, item)),
+            (Objective::Visit { location }, Deed::Visited(place)) => names(place, location),
+            (Objective::TalkTo { npc }, Deed::TalkedTo(name)) => names(name, npc),
+            _ => false,
+        }
+    }
+}
+
+fn is_return(text: &str) -> bool {
+    text.trim().to_lowercase().starts_with("return")
+}
+
+fn singular(noun: &str) -> String {
+    match noun.strip_suffix('s') {
+        Some(stem) if !stem.ends_with('s') => stem.to_string(),
+        _ => noun.to_string(),
+    }
+}
+
+// Whether `name` is what `wanted` refers to, which takes every word of `wanted` in
+// order: "Goblin 2" is a goblin, "Marta the Innkeeper" is the innkeeper, and the Barrow
+// of Kings is "the barrow of kings", but a goblin isn't the goblin king
+fn names(name: &str, wanted: &str) -> bool {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect()
+    };
+    let (name, mut wanted) = (words(name), words(wanted));
+    if wanted.first().is_some_and(|word| word == "the") {
+        wanted.remove(0);
+    }
+    !wanted.is_empty() && name.windows(wanted.len()).any(|window| window == wanted.as_slice())
+}
+
+impl Resolution {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resolution::Fulfilled => "fulfilled",
+            Resolution::Negotiated => "negotiated",
+            Resolution::Betrayed => "betrayed",
+            Resolution::Abandoned => "abandoned",
+            Resolution::Expired => "expired",
+        }
+    }
+
+    // Which part of the journal a quest ended this way goes under
+    pub fn status(&self) -> QuestStatus {
+        match self {
+            Resolution::Fulfilled | Resolution::Negotiated | Resolution::Betrayed => QuestStatus::Completed,
+            Resolution::Abandoned | Resolution::Expired => QuestStatus::Failed,
+        }
+    }
+}
+
+impl QuestStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuestStatus::Active => "Active",
+            QuestStatus::Completed => "Completed",
+            QuestStatus::Failed => "Failed",
+        }
+    }
+}
+
+impl Quest {
+    // Taken on today
+    pub fn new(data: QuestData, giver: Option<String>, today: u64) -> Self {
+        let deadline = data.time_limit.map(|days| today + days as u64);
+        Self {
+            data,
+            status: QuestStatus::Active,
+            giver,
+            done: Vec::new(),
+            kills: BTreeMap::new(),
+            deadline,
+            resolution: None,
+        }
+    }
+
+    // Days until the deadline, zero on the day itself
+    pub fn days_left(&self, today: u64) -> Option<i64> {
+        self.deadline.map(|deadline| deadline as i64 - today as i64)
+    }
+
+    pub fn is_done(&self, objective: usize) -> bool {
+        self.done.contains(&objective)
+    }
+
+    pub fn objective(&self, index: usize) -> Option<Objective> {
+        self.data.objectives.get(index).and_then(|text| Objective::parse(text))
+    }
+
+    // Every objective that can be read is done, and there was at least one
+    pub fn objectives_met(&self) -> bool {
+        let (done, readable) = self.objectives_done();
+        readable > 0 && done == readable
+    }
+
+    // How many of the objectives that can be read are done, out of how many
+    pub fn objectives_done(&self) -> (usize, usize) {
+        let readable: Vec<usize> = (0..self.data.objectives.len()).filter(|index| self.objective(*index).is_some()).collect();
+        (readable.iter().filter(|index| self.is_done(**index)).count(), readable.len())
+    }
+
+    // What ending it the way it ended earns the party: nothing before it has ended
+    pub fn payout(&self) -> QuestReward {
+        let reward = &self.data.reward;
+        let nothing = QuestReward { experience: 0, gold: 0, items: Vec::new(), reputation_change: 0 };
+        let face = reward.reputation_change.max(1);
+        match self.resolution {
+            None => nothing,
+            Some(Resolution::Fulfilled) => reward.clone(),
+            Some(Resolution::Negotiated) => QuestReward {
+                experience: reward.experience / 2,
+                gold: reward.gold / 2,
+                items: Vec::new(),
+                reputation_change: reward.reputation_change / 2,
+            },
+            Some(Resolution::Betrayed) => QuestReward {
+                gold: reward.gold,
+                reputation_change: face.saturating_mul(-2),
+                ..nothing
+            },
+            Some(Resolution::Abandoned) => {
+                let (done, readable) = self.objectives_done();
+                QuestReward {
+                    experience: reward.experience * done as u32 / readable.max(1) as u32,
+                    reputation_change: -1,
+                    ..nothing
+                }
+            }
+            Some(Resolution::Expired) => QuestReward { reputation_change: -face, ..nothing },
+        }
+    }
+
+    // How it ended, as word of it goes round town
+    pub fn outcome(&self) -> Option<String> {
+        let title = &self.data.title;
+        let giver = self.giver.as_deref().unwrap_or("whoever asked");
+        Some(match self.resolution? {
+            Resolution::Fulfilled => format!("The party saw \"{}\" through for {}", title, giver),
+            Resolution::Negotiated => format!("The party settled \"{}\" with a deal instead of finishing it", title),
+            Resolution::Betrayed => format!("The party betrayed {} over \"{}\"", giver, title),
+            Resolution::Abandoned => format!("The party gave up on \"{}\" partway through", title),
+            Resolution::Expired => format!("The party let \"{}\" run out of time", title),
+        })
+    }
+
+    // Ends it, if it's still active and can end that way
+    fn resolve(&mut self, resolution: Resolution) -> bool {
+        let (done, _) = self.objectives_done();
+        if self.status != QuestStatus::Active || (resolution == Resolution::Negotiated && done == 0) {
+            return false;
+        }
+        self.status = resolution.status();
+        self.resolution = Some(resolution);
+        true
+    }
+
+    // Every objective before this one that can be read is done
+    fn done_before(&self, index: usize) -> bool {
+        (0..index).all(|earlier| self.objective(earlier).is_none() || self.is_done(earlier))
+    }
+
+    // Ticks off the objectives the deed satisfies, and returns them
+    fn record(&mut self, deed: &Deed) -> Vec<usize> {
+        let mut ticked = Vec::new();
+        for index in 0..self.data.objectives.len() {
+            let Some(objective) = self.objective(index).filter(|objective| objective.is_met_by(deed)) else {
+                continue;
+            };
+            if self.is_done(index) || (is_return(&self.data.objectives[index]) && !self.done_before(index)) {
+                continue;
+            }
+            if let Objective::Kill { count, .. } = objective {
+                let kills = self.kills.entry(index).or_default();
+                *kills += 1;
+                if *kills < count {
+                    continue;
+                }
+            }
+            self.done.push(index);
+            ticked.push(index);
+        }
+        ticked
+    }
+}
+
+impl QuestLog {
+    // Taken on without being asked, as quests from rumors are. A quest the party already
+    // has isn't added twice.
+    pub fn take(&mut self, data: QuestData, giver: Option<String>, today: u64) -> bool {
+        if self.has(&data.title) {
+            return false;
+        }
+        self.quests.push(Quest::new(data, giver, today));
+        true
+    }
+
+    // Waits for the party to accept or decline it. Returns false for a quest already in
+    // the log or on offer.
+    pub fn offer(&mut self, giver: String, quest: QuestData) -> bool {
+        if self.has(&quest.title) || self.offers.iter().any(|offer| offer.quest.title == quest.title) {
+            return false;
+        }
+        self.offers.push(QuestOffer { giver, quest });
+        true
+    }
+
+    pub fn accept(&mut self, index: usize, today: u64) -> Option<&Quest> {
+        if index >= self.offers.len() {
+            return None;
+        }
+        let offer = self.offers.remove(index);
+        self.quests.push(Quest::new(offer.quest, Some(offer.giver), today));
+        self.quests.last()
+    }
+
+    // Ends an active quest and changes the party's reputation by what the ending is
+    // worth. Returns the rest of its payout for the caller to hand out, or None if the
+    // quest can't end that way.
+    pub fn resolve(&mut self, index: usize, resolution: Resolution) -> Option<QuestReward> {
+        let quest = self.quests.get_mut(index)?;
+        if !quest.resolve(resolution) {
+            return None;
+        }
+        let payout = quest.payout();
+        self.reputation = self.reputation.saturating_add(payout.reputation_change);
+        Some(payout)
+    }
+
+    // Ends the active quests whose deadlines have passed, and returns them
+    pub fn expire(&mut self, today: u64) -> Vec<usize> {
+        let expired: Vec<usize> = self
+            .quests
+            .iter()
+            .enumerate()
+            .filter(|(_, quest)| quest.status == QuestStatus::Active)
+            .filter(|(_, quest)| quest.days_left(today).is_some_and(|left| left < 0))
+            .map(|(index, _)| index)
+            .collect();
+        for index in &expired {
+            self.resolve(*index, Resolution::Expired);
+        }
+        expired
+    }
+
+    // How the quests the party took on last have ended, in the order it took them on
+    pub fn outcomes(&self) -> Vec<String> {
+        let ended: Vec<String> = self.quests.iter().filter_map(Quest::outcome).collect();
+        ended[ended.len().saturating_sub(REMEMBERED_OUTCOMES)..].to_vec()
+    }
+
+    // Active quests due within DEADLINE_WARNING_DAYS, with the days they have left
+    pub fn due_soon(&self, today: u64) -> impl Iterator<Item = (&Quest, i64)> {
+        self.with_status(QuestStatus::Active).filter_map(move |quest| {
+            quest.days_left(today).filter(|left| (0..=DEADLINE_WARNING_DAYS).contains(left)).map(|left| (quest, left))
+        })
+    }
+
+    pub fn decline(&mut self, index: usize) -> Option<QuestOffer> {
+        (index < self.offers.len()).then(|| self.offers.remove(index))
+    }
+
+    // Ticks off whatever the deed satisfies in the active quests. A quest whose
+    // objectives are all done is fulfilled; granting its reward, all but the
+    // reputation, is up to the caller.
+    pub fn record(&mut self, deed: &Deed) -> Vec<Progress> {
+        let mut progress = Vec::new();
+        for (index, quest) in self.quests.iter_mut().enumerate() {
+            if quest.status != QuestStatus::Active {
+                continue;
+            }
+            let ticked = quest.record(deed);
+            let completes = !ticked.is_empty() && quest.objectives_met();
+            if completes && quest.resolve(Resolution::Fulfilled) {
+                self.reputation = self.reputation.saturating_add(quest.payout().reputation_change);
+            }
+            let last = ticked.len().saturating_sub(1);
+            progress.extend(ticked.into_iter().enumerate().map(|(order, objective)| Progress {
+                quest: index,
+                objective,
+                completes: completes && order == last,
+            }));
+        }
+        progress
+    }
+
+    pub fn has(&self, title: &str) -> bool {
+        self.quests.iter().any(|quest| quest.data.title == title)
+    }
+
+    pub fn with_status(&self, status: QuestStatus) -> impl Iterator<Item = &Quest> {
+        self.quests.iter().filter(move |quest| quest.status == status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn objective(text: &str) -> Objective {
+        Objective::parse(text).unwrap()
+    }
+
+    #[test]
+    fn reads_objectives() {
+        assert_eq!(objective("Kill 3 goblins"), Objective::Kill { monster: "goblin".to_string(), count: 3 });
+        assert!(matches!(objective("Speak with the sage"), Objective::TalkTo { .. }));
+    }
+
+    #[test]
+    fn names_take_every_word_of_the_target() {
+        let king = objective("Kill the goblin king");
+        assert!(king.is_met_by(&Deed::Killed("Goblin King")));
+        assert!(!king.is_met_by(&Deed::Killed("Goblin")));
+        assert!(!king.is_met_by(&Deed::Killed("Goblin 2")));
+        assert!(objective("Kill 3 goblins").is_met_by(&Deed::Killed("Goblin 2")));
+
+        let crypt = objective("Visit the Crypt of Bones");
+        assert!(crypt.is_met_by(&Deed::Visited("The Crypt of Bones")));
+        assert!(!crypt.is_met_by(&Deed::Visited("Crypt")));
+        assert!(objective("Speak with the innkeeper").is_met_by(&Deed::TalkedTo("Marta the Innkeeper")));
+    }
+
+    #[test]
+    fn names_match_whole_words() {
+        assert!(!objective("Kill 2 rats").is_met_by(&Deed::Killed("Pirate")));
+        let held = ["Silver Sword".to_string()];
+        assert!(objective("Recover the sword").is_met_by(&Deed::Holding(&held)));
+    }
+}
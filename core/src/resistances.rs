@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::combat::DamageType;
+use crate::item::{Item, ItemType, WeaponType};
+use crate::monster::EnemyData;
+
+// What kinds of damage tell on a monster, as its bestiary entry lists them: resisted
+// kinds do half damage, immune ones none and those it's vulnerable to double. Some
+// monsters (lycanthropes, wights) are harmed only by silver or magic weapons, and a few
+// only by magic ones; spells, breath and poison get through regardless. And a troll's
+// regeneration can't mend what fire or acid did to it.
+//
+// Blows do the damage their weapon does: swords and axes slash, daggers and arrows
+// pierce, maces and staves bludgeon. Natural attacks go by their names: bites and stings
+// pierce, claws slash, anything else bludgeons.
+
+// What a weapon is made of, as far as the monsters care
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Material {
+    Ordinary,
+    Silver,
+    Magic,
+}
+
+// How a monster's defences changed the damage it took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Defense {
+    Resisted,
+    Immune,
+    Vulnerable,
+    // Only a weapon of this material (or better) harms it
+    Unharmed(Material),
+}
+
+impl Material {
+    // Whether a weapon of this material harms a monster that needs `needed`
+    pub fn harms(self, needed: Material) -> bool {
+        match needed {
+            Material::Ordinary => true,
+            Material::Silver => self != Material::Ordinary,
+            Material::Magic => self == Material::Magic,
+        }
+    }
+
+    // A weapon with a bonus is magic, and one with silver in its name is silver
+    pub fn of(weapon: Option<&Item>) -> Material {
+        match weapon {
+            Some(item) if item.magic_bonus() > 0 => Material::Magic,
+            Some(item) if item.name.to_lowercase().contains("silver") => Material::Silver,
+            _ => Material::Ordinary,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Material::Ordinary => "ordinary",
+            Material::Silver => "silver or magic",
+            Material::Magic => "magic",
+        }
+    }
+}
+
+// The damage a blow with `weapon` does; None is a bare fist
+pub fn weapon_damage_type(weapon: Option<&Item>) -> DamageType {
+    let Some(weapon) = weapon else {
+        return DamageType::Bludgeoning;
+    };
+    match &weapon.item_type {
+        ItemType::Weapon(WeaponType::Sword | WeaponType::Axe) => DamageType::Slashing,
+        ItemType::Weapon(WeaponType::Dagger | WeaponType::Bow | WeaponType::Crossbow) => DamageType::Piercing,
+        ItemType::Weapon(WeaponType::Mace | WeaponType::Staff) => DamageType::Bludgeoning,
+        _ => {
+            let name = weapon.name.to_lowercase();
+            if ["bite", "sting", "horn", "gore", "tusk"].iter().any(|word| name.contains(word)) {
+                DamageType::Piercing
+            } else if name.contains("claw") || name.contains("talon") {
+                DamageType::Slashing
+            } else {
+                DamageType::Bludgeoning
+            }
+        }
+    }
+}
+
+impl Defense {
+    // "The Skeleton resists piercing", for the combat log
+    pub fn describe(self, monster: &str, damage_type: &DamageType) -> String {
+        let kind = format!("{:?}", damage_type).to_lowercase();
+        match self {
+            Defense::Resisted => format!("{} resists {}", monster, kind),
+            Defense::Immune => format!("{} is immune to {}", monster, kind),
+            Defense::Vulnerable => format!("{} is vulnerable to {}", monster, kind),
+            Defense::Unharmed(material) => format!("Only {} weapons harm {}", material.name(), monster),
+        }
+    }
+}
+
+impl EnemyData {
+    // The damage the monster takes from `damage` of `damage_type`, dealt by a weapon of
+    // `material` (None for anything but a weapon), and what its defences made of it
+    pub fn defend(&self, damage: i16, damage_type: &DamageType, material: Option<Material>) -> (i16, Option<Defense>) {
+        if let (Some(material), Some(needed)) = (material, self.harmed_by) {
+            if !material.harms(needed) {
+                return (0, Some(Defense::Unharmed(needed)));
+            }
+        }
+        if self.immunities.contains(damage_type) {
+            (0, Some(Defense::Immune))
+        } else if self.resistances.contains(damage_type) {
+            ((damage / 2).max(1), Some(Defense::Resisted))
+        } else if self.vulnerabilities.contains(damage_type) {
+            (damage * 2, Some(Defense::Vulnerable))
+        } else {
+            (damage, None)
+        }
+    }
+
+    // Whether its regeneration can mend damage of `damage_type`
+    pub fn mends(&self, damage_type: &DamageType) -> bool {
+        !self.regeneration_stopped_by.contains(damage_type)
+    }
+}
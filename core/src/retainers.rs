@@ -0,0 +1,246 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass};
+use crate::dice;
+use crate::ruleset::Ruleset;
+
+// Hirelings. A party can take on torchbearers, men-at-arms and adventurers of its own
+// kind, who go where it goes and fight alongside it. Each is paid a daily wage, and a
+// character can only keep so many: B/X gives the number by Charisma, from one at 3 to
+// seven at 18, and the party's best Charisma sets the limit. Torchbearers are ordinary
+// folk with 1d4 hit points; men-at-arms are first level fighters.
+//
+// Every retainer has a loyalty score, 7 adjusted by the hiring party's best Charisma
+// (4 at 3 up to 10 at 18). After a fight that goes badly, or a day without pay, each of
+// them rolls 2d6 and stays loyal on a roll no higher than their loyalty, with 2 added to
+// the roll for going unpaid. One who fails deserts (1-2 on a d6), makes off with some of
+// the party's gold (3-4), or refuses to go into a dungeon until the party is back in
+// town (5-6).
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RetainerKind {
+    Torchbearer,
+    ManAtArms,
+    Adventurer,
+}
+
+// Marks a member of the party as hired help
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::component::Component))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Retainer {
+    pub kind: RetainerKind,
+    // Gold pieces a day
+    pub wage: u32,
+    #[serde(default)]
+    pub personality: String,
+    #[serde(default = "default_loyalty")]
+    pub loyalty: u8,
+    // Won't go into a dungeon until the party is back in town
+    #[serde(default)]
+    pub refusing: bool,
+}
+
+// Why a retainer's loyalty is tested
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Grievance {
+    // A fight in which someone in the party fell or a retainer was badly hurt
+    Danger,
+    Unpaid,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Disloyalty {
+    Desert,
+    // Takes this much of the party's gold, and goes
+    Steal(u32),
+    Refuse,
+}
+
+// Someone looking for work, as the AI service or the local tables offer them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    pub name: String,
+    pub kind: RetainerKind,
+    pub class: CharacterClass,
+    pub level: u8,
+    #[serde(default)]
+    pub personality: String,
+}
+
+const NAMES: &[&str] = &[
+    "Hob", "Wat", "Alys", "Tam", "Edric", "Maud", "Jory", "Nell", "Osric", "Piers", "Runa", "Sim",
+];
+
+const PERSONALITIES: &[&str] = &[
+    "Eager and green, and quick to boast",
+    "Dour and careful, and counts every copper",
+    "Cheerful, loud and fond of a song",
+    "Quiet and watchful, with a past they won't speak of",
+    "Superstitious and jumpy in the dark",
+    "A steady old hand who has seen a few dungeons",
+];
+
+impl RetainerKind {
+    pub const ALL: [RetainerKind; 3] = [RetainerKind::Torchbearer, RetainerKind::ManAtArms, RetainerKind::Adventurer];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RetainerKind::Torchbearer => "torchbearer",
+            RetainerKind::ManAtArms => "man-at-arms",
+            RetainerKind::Adventurer => "adventurer",
+        }
+    }
+
+    pub fn daily_wage(&self, level: u8) -> u32 {
+        match self {
+            RetainerKind::Torchbearer => 1,
+            RetainerKind::ManAtArms => 2,
+            RetainerKind::Adventurer => 5 * level.max(1) as u32,
+        }
+    }
+
+    // Item ids the party's coin outfits them with
+    pub fn kit(&self) -> &'static [&'static str] {
+        match self {
+            RetainerKind::Torchbearer => &["torch", "torch", "dagger"],
+            RetainerKind::ManAtArms => &["sword", "leather_armor", "shield"],
+            RetainerKind::Adventurer => &[],
+        }
+    }
+}
+
+fn default_loyalty() -> u8 {
+    base_loyalty(10)
+}
+
+// A new retainer's loyalty to a party whose best Charisma is this
+pub fn base_loyalty(charisma: u8) -> u8 {
+    match charisma {
+        0..=3 => 4,
+        4..=5 => 5,
+        6..=8 => 6,
+        9..=12 => 7,
+        13..=15 => 8,
+        16..=17 => 9,
+        _ => 10,
+    }
+}
+
+impl Grievance {
+    // Added to the 2d6 loyalty roll
+    pub fn modifier(&self) -> i16 {
+        match self {
+            Grievance::Danger => 0,
+            Grievance::Unpaid => 2,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Grievance::Danger => "a fight that nearly got them killed",
+            Grievance::Unpaid => "going unpaid",
+        }
+    }
+}
+
+impl Disloyalty {
+    pub fn describe(&self) -> String {
+        match self {
+            Disloyalty::Desert => "deserts the party".to_string(),
+            Disloyalty::Steal(gold) => format!("makes off with {} gp of the party's gold", gold),
+            Disloyalty::Refuse => "refuses to go into a dungeon".to_string(),
+        }
+    }
+}
+
+// None if the retainer stays loyal
+pub fn loyalty_check(retainer: &Retainer, grievance: Grievance, rng: &mut impl Rng) -> Option<Disloyalty> {
+    let roll = dice::roll(rng, 2, 6) + grievance.modifier();
+    if roll <= retainer.loyalty as i16 {
+        return None;
+    }
+    Some(match dice::d6(rng) {
+        1..=2 => Disloyalty::Desert,
+        3..=4 => Disloyalty::Steal(dice::roll(rng, 1, 6) as u32 * 10),
+        _ => Disloyalty::Refuse,
+    })
+}
+
+// How many retainers a character with this Charisma can keep
+pub fn max_retainers(charisma: u8) -> usize {
+    match charisma {
+        0..=3 => 1,
+        4..=5 => 2,
+        6..=8 => 3,
+        9..=12 => 4,
+        13..=15 => 5,
+        16..=17 => 6,
+        _ => 7,
+    }
+}
+
+// Local tables for when the AI service can't be asked. Adventurers come at most a level
+// below the party.
+pub fn roll_candidates(count: usize, party_level: u8, rng: &mut impl Rng) -> Vec<Candidate> {
+    (0..count)
+        .map(|_| {
+            let kind = *RetainerKind::ALL.choose(rng).unwrap_or(&RetainerKind::Torchbearer);
+            let (class, level) = match kind {
+                RetainerKind::Adventurer => (
+                    CharacterClass::ALL.choose(rng).cloned().unwrap_or(CharacterClass::Fighter),
+                    rng.gen_range(1..=party_level.saturating_sub(1).max(1)),
+                ),
+                _ => (CharacterClass::Fighter, 1),
+            };
+            Candidate {
+                name: NAMES.choose(rng).copied().unwrap_or("Hob").to_string(),
+                kind,
+                class,
+                level,
+                personality: PERSONALITIES.choose(rng).copied().unwrap_or_default().to_string(),
+            }
+        })
+        .collect()
+}
+
+impl Candidate {
+    pub fn wage(&self) -> u32 {
+        self.kind.daily_wage(self.level)
+    }
+
+    pub fn describe(&self) -> String {
+        let class = match self.kind {
+            RetainerKind::Adventurer => format!(", {} {}", self.class.name(), self.level),
+            _ => String::new(),
+        };
+        format!("{}, {}{} ({} gp a day)", self.name, self.kind.name(), class, self.wage())
+    }
+
+    // The hireling as a character, without their kit, no higher than their class's limit
+    pub fn character(&self, ruleset: &Ruleset, rng: &mut impl Rng) -> Character {
+        let mut character = Character::new_with(self.name.clone(), self.class.clone(), rng);
+        while character.level < self.level.min(ruleset.experience_tables.max_level(&self.class)) {
+            character.level_up();
+        }
+        if self.kind == RetainerKind::Torchbearer {
+            let hit_points = dice::roll(rng, 1, 4);
+            character.hit_points.maximum = hit_points;
+            character.hit_points.current = hit_points;
+        }
+        character
+    }
+
+    // Hired by a party whose best Charisma is `charisma`
+    pub fn retainer(&self, charisma: u8) -> Retainer {
+        Retainer {
+            kind: self.kind,
+            wage: self.wage(),
+            personality: self.personality.clone(),
+            loyalty: base_loyalty(charisma),
+            refusing: false,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+// The classic combat round, an optional rule. Rather than each combatant taking a whole
+// turn in initiative order, the round opens with everyone declaring what they mean to do
+// (the monsters too), then runs through movement, missile fire, magic and melee, each
+// phase in initiative order among those who declared something for it. What was
+// declared is what gets done: a character who declared a spell can't swing a sword in
+// the melee phase instead.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundPhase {
+    Declarations,
+    Movement,
+    Missiles,
+    Magic,
+    Melee,
+}
+
+// What a combatant means to do this round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Declaration {
+    // Close in during the movement phase, then fight hand to hand
+    Melee,
+    Missile,
+    Cast(String),
+    // Hold a guard up all round rather than attack
+    Parry,
+}
+
+impl RoundPhase {
+    pub const ALL: [RoundPhase; 5] = [
+        RoundPhase::Declarations,
+        RoundPhase::Movement,
+        RoundPhase::Missiles,
+        RoundPhase::Magic,
+        RoundPhase::Melee,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundPhase::Declarations => "Declarations",
+            RoundPhase::Movement => "Movement",
+            RoundPhase::Missiles => "Missile fire",
+            RoundPhase::Magic => "Magic",
+            RoundPhase::Melee => "Melee",
+        }
+    }
+
+    // The phase after this one; None once the round is over
+    pub fn next(self) -> Option<RoundPhase> {
+        let index = RoundPhase::ALL.iter().position(|phase| *phase == self)?;
+        RoundPhase::ALL.get(index + 1).copied()
+    }
+}
+
+impl Declaration {
+    // Whether the declaration gives its combatant something to do in `phase`
+    pub fn acts_in(&self, phase: RoundPhase) -> bool {
+        match phase {
+            RoundPhase::Declarations => false,
+            _ if *self == Declaration::Parry => false,
+            RoundPhase::Movement => *self == Declaration::Melee,
+            RoundPhase::Missiles => *self == Declaration::Missile,
+            RoundPhase::Magic => matches!(self, Declaration::Cast(_)),
+            RoundPhase::Melee => *self == Declaration::Melee,
+        }
+    }
+
+    // What the combatant will do: "fight in melee", "cast Sleep"
+    pub fn describe(&self) -> String {
+        match self {
+            Declaration::Melee => "fight in melee".to_string(),
+            Declaration::Missile => "shoot".to_string(),
+            Declaration::Cast(spell) => format!("cast {}", spell),
+            Declaration::Parry => "parry".to_string(),
+        }
+    }
+}
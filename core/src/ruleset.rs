@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::CharacterClass;
+
+// Optional rules and the tables behind them. Frontends hold one of these and pass it to
+// the rules functions that care; `Ruleset::default()` is B/X as written.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ruleset {
+    pub attack_method: AttackMethod,
+    pub attack_matrix: AttackMatrix,
+    // Fight on a square grid with movement and weapon ranges (see `battle_map`)
+    #[serde(default)]
+    pub battle_map: bool,
+    // Heavy loads cost AC and initiative as well as movement
+    #[serde(default)]
+    pub encumbrance_penalties: bool,
+    // Declarations, then movement, missiles, magic and melee (see `rounds`) rather than a
+    // whole turn each in initiative order
+    #[serde(default)]
+    pub phased_rounds: bool,
+    // Treasure carried off is worth an experience point a gold piece
+    #[serde(default = "counts_treasure")]
+    pub treasure_xp: bool,
+    // The experience each class needs for each level
+    #[serde(default)]
+    pub experience_tables: ExperienceTables,
+}
+
+fn counts_treasure() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackMethod {
+    // Class attack matrices: fighters improve fastest, magic-users slowest
+    AttackMatrix,
+    // The original house rule: +1 to hit every three levels, whatever the class
+    LevelBonus,
+}
+
+// THAC0 (the d20 roll needed to hit AC 0) by level band for each attack progression.
+// Bands are checked in order; levels past the last band use its value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackMatrix {
+    pub fighter: Vec<LevelBand>,
+    pub cleric: Vec<LevelBand>,
+    pub magic_user: Vec<LevelBand>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LevelBand {
+    pub up_to_level: u8,
+    pub thac0: i16,
+}
+
+// The experience points needed to reach each level by class, 1st level (0) first. A
+// class can go no higher than the last level in its table, so a shorter table is a
+// lower level limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExperienceTables {
+    pub fighter: Vec<u32>,
+    pub cleric: Vec<u32>,
+    pub magic_user: Vec<u32>,
+    pub thief: Vec<u32>,
+    pub dwarf: Vec<u32>,
+    pub elf: Vec<u32>,
+    pub halfling: Vec<u32>,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            attack_method: AttackMethod::AttackMatrix,
+            attack_matrix: AttackMatrix::default(),
+            battle_map: false,
+            encumbrance_penalties: false,
+            phased_rounds: false,
+            treasure_xp: true,
+            experience_tables: ExperienceTables::default(),
+        }
+    }
+}
+
+impl AttackMatrix {
+    pub fn thac0(&self, class: &CharacterClass, level: u8) -> i16 {
+        let bands = match class {
+            CharacterClass::Fighter | CharacterClass::Dwarf | CharacterClass::Elf | CharacterClass::Halfling => &self.fighter,
+            CharacterClass::Cleric | CharacterClass::Thief => &self.cleric,
+            CharacterClass::MagicUser => &self.magic_user,
+        };
+        bands
+            .iter()
+            .find(|band| level <= band.up_to_level)
+            .or(bands.last())
+            .map(|band| band.thac0)
+            .unwrap_or(19)
+    }
+}
+
+// B/X character attack tables
+impl Default for AttackMatrix {
+    fn default() -> Self {
+        let bands = |rows: &[(u8, i16)]| {
+            rows.iter()
+                .map(|&(up_to_level, thac0)| LevelBand { up_to_level, thac0 })
+                .collect()
+        };
+        Self {
+            fighter: bands(&[(3, 19), (6, 17), (9, 14), (12, 12), (15, 10), (18, 8), (21, 6), (u8::MAX, 5)]),
+            cleric: bands(&[(4, 19), (8, 17), (12, 14), (16, 12), (20, 10), (u8::MAX, 8)]),
+            magic_user: bands(&[(5, 19), (10, 17), (15, 14), (20, 12), (u8::MAX, 10)]),
+        }
+    }
+}
+
+// B/X experience tables: humans to 14th level, dwarves to 12th, elves to 10th and
+// halflings to 8th
+impl Default for ExperienceTables {
+    fn default() -> Self {
+        Self {
+            fighter: vec![
+                0, 2000, 4000, 8000, 16000, 32000, 64000, 120000, 240000, 360000, 480000, 600000, 720000, 840000,
+            ],
+            cleric: vec![
+                0, 1500, 3000, 6000, 12000, 25000, 50000, 100000, 200000, 300000, 400000, 500000, 600000, 700000,
+            ],
+            magic_user: vec![
+                0, 2500, 5000, 10000, 20000, 40000, 80000, 150000, 300000, 450000, 600000, 750000, 900000, 1050000,
+            ],
+            thief: vec![
+                0, 1200, 2400, 4800, 9600, 20000, 40000, 80000, 160000, 280000, 400000, 520000, 640000, 760000,
+            ],
+            dwarf: vec![0, 2200, 4400, 8800, 17000, 35000, 70000, 140000, 270000, 400000, 530000, 660000],
+            elf: vec![0, 4000, 8000, 16000, 32000, 64000, 120000, 250000, 400000, 600000],
+            halfling: vec![0, 2000, 4000, 8000, 16000, 32000, 64000, 120000],
+        }
+    }
+}
+
+impl ExperienceTables {
+    pub fn table(&self, class: &CharacterClass) -> &[u32] {
+        match class {
+            CharacterClass::Fighter => &self.fighter,
+            CharacterClass::Cleric => &self.cleric,
+            CharacterClass::MagicUser => &self.magic_user,
+            CharacterClass::Thief => &self.thief,
+            CharacterClass::Dwarf => &self.dwarf,
+            CharacterClass::Elf => &self.elf,
+            CharacterClass::Halfling => &self.halfling,
+        }
+    }
+
+    // The highest level the class can reach (at least 1st)
+    pub fn max_level(&self, class: &CharacterClass) -> u8 {
+        self.table(class).len().clamp(1, u8::MAX as usize) as u8
+    }
+
+    // The experience needed to reach `level`, or None past the class's limit
+    pub fn for_level(&self, class: &CharacterClass, level: u8) -> Option<u32> {
+        match level {
+            0 | 1 => Some(0),
+            _ => self.table(class).get(level as usize - 1).copied(),
+        }
+    }
+}
+
+impl AttackMethod {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "matrix" | "attackmatrix" | "thac0" => Some(AttackMethod::AttackMatrix),
+            "level" | "levelbonus" => Some(AttackMethod::LevelBonus),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes_stop_at_their_level_limits() {
+        let tables = ExperienceTables::default();
+        let limits: Vec<u8> = [
+            CharacterClass::Fighter,
+            CharacterClass::Cleric,
+            CharacterClass::MagicUser,
+            CharacterClass::Thief,
+            CharacterClass::Dwarf,
+            CharacterClass::Elf,
+            CharacterClass::Halfling,
+        ]
+        .iter()
+        .map(|class| tables.max_level(class))
+        .collect();
+        assert_eq!(limits, [14, 14, 14, 14, 12, 10, 8]);
+    }
+
+    #[test]
+    fn experience_for_a_level_follows_the_book() {
+        let tables = ExperienceTables::default();
+        assert_eq!(tables.for_level(&CharacterClass::Fighter, 1), Some(0));
+        assert_eq!(tables.for_level(&CharacterClass::Fighter, 2), Some(2000));
+        assert_eq!(tables.for_level(&CharacterClass::Cleric, 6), Some(25000));
+        assert_eq!(tables.for_level(&CharacterClass::MagicUser, 9), Some(300000));
+        assert_eq!(tables.for_level(&CharacterClass::Thief, 14), Some(760000));
+        assert_eq!(tables.for_level(&CharacterClass::Dwarf, 5), Some(17000));
+        assert_eq!(tables.for_level(&CharacterClass::Elf, 10), Some(600000));
+        assert_eq!(tables.for_level(&CharacterClass::Halfling, 8), Some(120000));
+    }
+
+    #[test]
+    fn no_level_past_the_limit() {
+        let tables = ExperienceTables::default();
+        assert_eq!(tables.for_level(&CharacterClass::Elf, 11), None);
+        assert_eq!(tables.for_level(&CharacterClass::Halfling, 9), None);
+        assert_eq!(tables.for_level(&CharacterClass::Fighter, 15), None);
+    }
+
+    #[test]
+    fn attack_matrix_improves_by_bands() {
+        let matrix = AttackMatrix::default();
+        assert_eq!(matrix.thac0(&CharacterClass::Fighter, 3), 19);
+        assert_eq!(matrix.thac0(&CharacterClass::Dwarf, 4), 17);
+        assert_eq!(matrix.thac0(&CharacterClass::Thief, 5), 17);
+        assert_eq!(matrix.thac0(&CharacterClass::MagicUser, 5), 19);
+        assert_eq!(matrix.thac0(&CharacterClass::MagicUser, 6), 17);
+        assert_eq!(matrix.thac0(&CharacterClass::Fighter, 30), 5);
+    }
+}
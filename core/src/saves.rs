@@ -0,0 +1,182 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass};
+use crate::dice;
+
+// Saving throws. Each class has five targets that improve in steps as it goes up in
+// level; a save succeeds when a d20 rolls the target or higher. Monsters save as
+// fighters of their level.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SavingThrow {
+    DeathPoison,
+    Wands,
+    ParalysisPetrify,
+    Breath,
+    Spells,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SaveRoll {
+    pub save: SavingThrow,
+    pub target: u8,
+    pub roll: u8,
+    pub success: bool,
+}
+
+// B/X saving throws: (highest level in the band, [death, wands, paralysis, breath, spells])
+const FIGHTER: [(u8, [u8; 5]); 5] = [
+    (3, [12, 13, 14, 15, 16]),
+    (6, [10, 11, 12, 13, 14]),
+    (9, [8, 9, 10, 10, 12]),
+    (12, [6, 7, 8, 8, 10]),
+    (14, [4, 5, 6, 5, 8]),
+];
+const CLERIC: [(u8, [u8; 5]); 4] = [
+    (4, [11, 12, 14, 16, 15]),
+    (8, [9, 10, 12, 14, 12]),
+    (12, [6, 7, 9, 11, 9]),
+    (14, [3, 5, 7, 8, 7]),
+];
+const MAGIC_USER: [(u8, [u8; 5]); 3] = [
+    (5, [13, 14, 13, 16, 15]),
+    (10, [11, 12, 11, 14, 12]),
+    (14, [8, 9, 8, 11, 8]),
+];
+const THIEF: [(u8, [u8; 5]); 4] = [
+    (4, [13, 14, 13, 16, 15]),
+    (8, [12, 13, 11, 14, 13]),
+    (12, [10, 11, 9, 12, 10]),
+    (14, [8, 9, 7, 10, 8]),
+];
+// Dwarves and halflings share a table
+const DWARF: [(u8, [u8; 5]); 4] = [
+    (3, [8, 9, 10, 13, 12]),
+    (6, [6, 7, 8, 10, 10]),
+    (9, [4, 5, 6, 7, 8]),
+    (12, [2, 3, 4, 4, 6]),
+];
+const ELF: [(u8, [u8; 5]); 4] = [
+    (3, [12, 13, 13, 15, 15]),
+    (6, [10, 11, 11, 13, 12]),
+    (9, [8, 9, 9, 10, 10]),
+    (10, [6, 7, 8, 8, 8]),
+];
+
+impl SavingThrow {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SavingThrow::DeathPoison => "Death Ray or Poison",
+            SavingThrow::Wands => "Magic Wands",
+            SavingThrow::ParalysisPetrify => "Paralysis or Turn to Stone",
+            SavingThrow::Breath => "Dragon Breath",
+            SavingThrow::Spells => "Rods, Staves or Spells",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            SavingThrow::DeathPoison => 0,
+            SavingThrow::Wands => 1,
+            SavingThrow::ParalysisPetrify => 2,
+            SavingThrow::Breath => 3,
+            SavingThrow::Spells => 4,
+        }
+    }
+}
+
+impl Character {
+    // The d20 roll needed to save; past the top of the table the last band applies
+    pub fn saving_throw(&self, save: SavingThrow) -> u8 {
+        let table: &[(u8, [u8; 5])] = match self.class {
+            CharacterClass::Fighter => &FIGHTER,
+            CharacterClass::Cleric => &CLERIC,
+            CharacterClass::MagicUser => &MAGIC_USER,
+            CharacterClass::Thief => &THIEF,
+            CharacterClass::Dwarf | CharacterClass::Halfling => &DWARF,
+            CharacterClass::Elf => &ELF,
+        };
+        let (_, targets) = table
+            .iter()
+            .find(|(top_level, _)| self.level <= *top_level)
+            .unwrap_or(&table[table.len() - 1]);
+        targets[save.index()]
+    }
+
+    pub fn roll_save(&self, save: SavingThrow, rng: &mut impl Rng) -> SaveRoll {
+        let target = self.saving_throw(save);
+        let roll = dice::roll(rng, 1, 20) as u8;
+        SaveRoll {
+            save,
+            target,
+            roll,
+            success: roll >= target,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const ALL: [SavingThrow; 5] = [
+        SavingThrow::DeathPoison,
+        SavingThrow::Wands,
+        SavingThrow::ParalysisPetrify,
+        SavingThrow::Breath,
+        SavingThrow::Spells,
+    ];
+
+    fn targets(class: CharacterClass, level: u8) -> [u8; 5] {
+        let mut character = Character::new_with("Test".to_string(), class, &mut StdRng::seed_from_u64(1));
+        character.level = level;
+        ALL.map(|save| character.saving_throw(save))
+    }
+
+    #[test]
+    fn first_level_saves_follow_the_book() {
+        assert_eq!(targets(CharacterClass::Fighter, 1), [12, 13, 14, 15, 16]);
+        assert_eq!(targets(CharacterClass::Cleric, 1), [11, 12, 14, 16, 15]);
+        assert_eq!(targets(CharacterClass::MagicUser, 1), [13, 14, 13, 16, 15]);
+        assert_eq!(targets(CharacterClass::Thief, 1), [13, 14, 13, 16, 15]);
+        assert_eq!(targets(CharacterClass::Dwarf, 1), [8, 9, 10, 13, 12]);
+        assert_eq!(targets(CharacterClass::Elf, 1), [12, 13, 13, 15, 15]);
+    }
+
+    #[test]
+    fn saves_improve_at_the_end_of_each_band() {
+        assert_eq!(targets(CharacterClass::Fighter, 3), [12, 13, 14, 15, 16]);
+        assert_eq!(targets(CharacterClass::Fighter, 4), [10, 11, 12, 13, 14]);
+        assert_eq!(targets(CharacterClass::MagicUser, 5), [13, 14, 13, 16, 15]);
+        assert_eq!(targets(CharacterClass::MagicUser, 6), [11, 12, 11, 14, 12]);
+        assert_eq!(targets(CharacterClass::Cleric, 9), [6, 7, 9, 11, 9]);
+    }
+
+    #[test]
+    fn halflings_save_as_dwarves() {
+        for level in 1..=8 {
+            assert_eq!(targets(CharacterClass::Halfling, level), targets(CharacterClass::Dwarf, level));
+        }
+    }
+
+    #[test]
+    fn past_the_table_the_last_band_applies() {
+        assert_eq!(targets(CharacterClass::Elf, 12), [6, 7, 8, 8, 8]);
+        assert_eq!(targets(CharacterClass::Fighter, 20), [4, 5, 6, 5, 8]);
+    }
+
+    #[test]
+    fn a_save_succeeds_on_the_target_or_higher() {
+        let character = Character::new_with("Test".to_string(), CharacterClass::Fighter, &mut StdRng::seed_from_u64(1));
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..100 {
+            let save = character.roll_save(SavingThrow::Spells, &mut rng);
+            assert_eq!(save.target, 16);
+            assert!((1..=20).contains(&save.roll));
+            assert_eq!(save.success, save.roll >= 16);
+        }
+    }
+}
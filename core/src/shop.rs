@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::item::{Item, ItemType};
+
+// Buying and selling. A merchant stocks a limited number of each item drawn from the
+// item data and sells at the item's listed value; they buy back at half value, but
+// only while their purse lasts.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MerchantKind {
+    // Weapons, armour, shields and helmets
+    Smith,
+    // Potions, scrolls and adventuring gear
+    Outfitter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockEntry {
+    pub item: Item,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Merchant {
+    pub name: String,
+    pub kind: MerchantKind,
+    pub stock: Vec<StockEntry>,
+    // Gold on hand to buy from the party
+    pub purse: u32,
+}
+
+impl MerchantKind {
+    pub const ALL: [MerchantKind; 2] = [MerchantKind::Smith, MerchantKind::Outfitter];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MerchantKind::Smith => "Smith",
+            MerchantKind::Outfitter => "Outfitter",
+        }
+    }
+
+    // How many of an item this merchant keeps; none if they don't deal in it
+    pub fn supply(&self, item: &Item) -> u32 {
+        match (self, &item.item_type) {
+            (MerchantKind::Smith, ItemType::Weapon(_)) => 3,
+            (MerchantKind::Smith, ItemType::Armor(_) | ItemType::Shield | ItemType::Helmet) => 2,
+            (MerchantKind::Outfitter, ItemType::Potion) => 2,
+            (MerchantKind::Outfitter, ItemType::Scroll) => 1,
+            (MerchantKind::Outfitter, ItemType::Cloak | ItemType::Boots) => 2,
+            (MerchantKind::Outfitter, ItemType::Misc) => 10,
+            _ => 0,
+        }
+    }
+
+    pub fn starting_purse(&self) -> u32 {
+        match self {
+            MerchantKind::Smith => 500,
+            MerchantKind::Outfitter => 200,
+        }
+    }
+}
+
+pub fn buy_price(item: &Item) -> u32 {
+    item.value
+}
+
+pub fn sell_price(item: &Item) -> u32 {
+    item.value / 2
+}
+
+impl Merchant {
+    // Stocks everything in `items` this kind of merchant deals in, cheapest first
+    pub fn stocked<'a>(name: impl Into<String>, kind: MerchantKind, items: impl IntoIterator<Item = &'a Item>) -> Self {
+        let mut stock: Vec<StockEntry> = items
+            .into_iter()
+            .filter_map(|item| {
+                let quantity = kind.supply(item);
+                (quantity > 0).then(|| StockEntry {
+                    item: item.clone(),
+                    quantity,
+                })
+            })
+            .collect();
+        stock.sort_by(|a, b| a.item.value.cmp(&b.item.value).then_with(|| a.item.name.cmp(&b.item.name)));
+
+        Self {
+            name: name.into(),
+            kind,
+            stock,
+            purse: kind.starting_purse(),
+        }
+    }
+
+    // The merchant's gold goes up by the price and the item goes into the buyer's pack
+    pub fn sell_to(&mut self, index: usize, buyer: &mut Character) -> Result<String, String> {
+        let entry = self
+            .stock
+            .get_mut(index)
+            .ok_or_else(|| format!("{} has no item {}", self.name, index + 1))?;
+        if entry.quantity == 0 {
+            return Err(format!("{} is sold out of {}", self.name, entry.item.name));
+        }
+        let price = buy_price(&entry.item);
+        buyer.inventory.spend_gold(price)?;
+
+        entry.quantity -= 1;
+        self.purse += price;
+        let item = entry.item.clone();
+        let text = format!("{} buys {} for {} gp", buyer.name, item.name, price);
+        buyer.inventory.add_item(item);
+        Ok(text)
+    }
+
+    // Takes the item at `index` in the seller's pack at half its value. The merchant
+    // only buys what they deal in, and only with gold they have.
+    pub fn buy_from(&mut self, index: usize, seller: &mut Character) -> Result<String, String> {
+        let item = seller
+            .inventory
+            .items
+            .get(index)
+            .ok_or_else(|| format!("{} has no item {}", seller.name, index + 1))?;
+        if self.kind.supply(item) == 0 {
+            return Err(format!("The {} doesn't deal in {}", self.kind.name().to_lowercase(), item.name));
+        }
+        if item.is_container() && !item.is_empty_container() {
+            return Err(format!("Empty {} before selling it", item.name));
+        }
+        let price = sell_price(item);
+        if price > self.purse {
+            return Err(format!("{} can't afford {} ({} gp left)", self.name, item.name, self.purse));
+        }
+
+        // One at a time from a stack
+        let item = seller.inventory.remove_one(index).expect("index checked above");
+        seller.inventory.gold += price;
+        self.purse -= price;
+        let text = format!("{} sells {} for {} gp", seller.name, item.name, price);
+        match self.stock.iter_mut().find(|entry| entry.item.name == item.name) {
+            Some(entry) => entry.quantity += 1,
+            None => self.stock.push(StockEntry { item, quantity: 1 }),
+        }
+        Ok(text)
+    }
+}
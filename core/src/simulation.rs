@@ -0,0 +1,321 @@
+use rand::Rng;
+use std::fmt;
+
+use crate::battle_map::GridPosition;
+use crate::character::{Character, CharacterClass};
+use crate::combat::{damage_per_round, roll_attack_with};
+use crate::dungeon::DungeonData;
+use crate::monster::{monster_character, weapon_key, EnemyData};
+use crate::ruleset::Ruleset;
+use crate::skills::{best_at, trap_modifier, ThiefSkill};
+use crate::traps::{Trap, TrapKind, TrapState};
+
+// Headless simulation of encounters and dungeon delves.
+// Everything here runs on the plain rules types, so it needs no Bevy app, window or
+// renderer and can be driven from the `simulate` binary for balance testing and CI.
+
+pub const DEFAULT_MAX_ROUNDS: u32 = 50;
+
+#[derive(Debug, Clone)]
+pub struct SimCombatant {
+    pub character: Character,
+    pub weapon: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EncounterOutcome {
+    pub victory: bool,
+    pub rounds: u32,
+    pub party_survivors: usize,
+    pub enemies_slain: usize,
+    pub damage_taken: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DelveOutcome {
+    pub encounters_fought: usize,
+    pub encounters_won: usize,
+    pub rooms_explored: usize,
+    pub gold_recovered: u32,
+    pub traps_disarmed: usize,
+    pub traps_sprung: usize,
+    pub party_survivors: usize,
+    pub rounds: u32,
+    pub damage_taken: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SimulationReport {
+    pub trials: u32,
+    pub victories: u32,
+    pub total_rounds: u64,
+    pub total_survivors: u64,
+    pub total_damage_taken: i64,
+    pub total_gold: u64,
+    pub party_size: usize,
+}
+
+impl SimCombatant {
+    pub fn hero(character: Character) -> Self {
+        let weapon = character
+            .equipment
+            .weapon
+            .as_ref()
+            .map(|item| item.name.to_lowercase())
+            .or_else(|| default_weapon(&character.class).map(str::to_string));
+
+        Self { character, weapon }
+    }
+
+    pub fn monster(enemy: &EnemyData) -> Self {
+        let weapon = enemy
+            .attacks
+            .first()
+            .and_then(|attack| weapon_key(&attack.name))
+            .map(str::to_string);
+
+        Self {
+            character: monster_character(enemy),
+            weapon,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.character.is_alive()
+    }
+}
+
+// Characters of the given classes at `level`, or their class's limit if that's lower
+pub fn create_party(classes: &[CharacterClass], level: u8, ruleset: &Ruleset, rng: &mut impl Rng) -> Vec<SimCombatant> {
+    classes
+        .iter()
+        .enumerate()
+        .map(|(index, class)| {
+            let mut character = Character::new_with(format!("{:?} {}", class, index + 1), class.clone(), rng);
+            while character.level < level.min(ruleset.experience_tables.max_level(class)) {
+                character.level_up();
+            }
+            SimCombatant::hero(character)
+        })
+        .collect()
+}
+
+pub fn simulate_encounter(
+    party: &mut [SimCombatant],
+    enemies: &mut [SimCombatant],
+    max_rounds: u32,
+    ruleset: &Ruleset,
+    rng: &mut impl Rng,
+) -> EncounterOutcome {
+    let starting_hp: i32 = party.iter().map(|member| member.character.hit_points.current as i32).sum();
+    let mut rounds = 0;
+
+    while rounds < max_rounds && party.iter().any(SimCombatant::is_alive) && enemies.iter().any(SimCombatant::is_alive) {
+        rounds += 1;
+
+        // Individual initiative, same as the ECS combat: 1d6 + DEX modifier, highest first
+        let mut order: Vec<(bool, usize, i8)> = Vec::new();
+        for (index, member) in party.iter().enumerate() {
+            let roll = rng.gen_range(1..=6) + Character::get_dexterity_modifier(member.character.stats.dexterity);
+            order.push((true, index, roll));
+        }
+        for (index, enemy) in enemies.iter().enumerate() {
+            let roll = rng.gen_range(1..=6) + Character::get_dexterity_modifier(enemy.character.stats.dexterity);
+            order.push((false, index, roll));
+        }
+        order.sort_by_key(|&(_, _, initiative)| std::cmp::Reverse(initiative));
+
+        for (is_player, index, _) in order {
+            let (attackers, defenders) = if is_player {
+                (&mut *party, &mut *enemies)
+            } else {
+                (&mut *enemies, &mut *party)
+            };
+
+            let attacker = &attackers[index];
+            if !attacker.is_alive() {
+                continue;
+            }
+
+            // Simple AI for both sides: attack the first living opponent
+            let Some(target) = defenders.iter_mut().find(|defender| defender.is_alive()) else {
+                break;
+            };
+
+            let (hit, damage) = roll_attack_with(ruleset, &attacker.character, &target.character, attacker.weapon.as_deref(), rng);
+            if hit {
+                target.character.take_damage(damage);
+            }
+        }
+    }
+
+    let remaining_hp: i32 = party.iter().map(|member| member.character.hit_points.current as i32).sum();
+
+    EncounterOutcome {
+        victory: !enemies.iter().any(SimCombatant::is_alive),
+        rounds,
+        party_survivors: party.iter().filter(|member| member.is_alive()).count(),
+        enemies_slain: enemies.iter().filter(|enemy| !enemy.is_alive()).count(),
+        damage_taken: starting_hp - remaining_hp,
+    }
+}
+
+// Walk the rooms in order, fighting every encounter and collecting treasure until the
+// dungeon is cleared or the party is wiped out. There is no resting between rooms.
+pub fn simulate_delve(
+    party: &mut [SimCombatant],
+    dungeon: &DungeonData,
+    max_rounds: u32,
+    ruleset: &Ruleset,
+    rng: &mut impl Rng,
+) -> DelveOutcome {
+    let mut outcome = DelveOutcome::default();
+    let mut room_ids: Vec<u32> = dungeon.rooms.iter().map(|room| room.id).collect();
+    room_ids.sort();
+
+    for room_id in room_ids {
+        if !party.iter().any(SimCombatant::is_alive) {
+            break;
+        }
+        outcome.rooms_explored += 1;
+
+        for encounter in dungeon.encounters.iter().filter(|encounter| encounter.room_id == room_id) {
+            let mut enemies: Vec<SimCombatant> = encounter.enemies.iter().map(SimCombatant::monster).collect();
+            let result = simulate_encounter(party, &mut enemies, max_rounds, ruleset, rng);
+
+            outcome.encounters_fought += 1;
+            outcome.rounds += result.rounds;
+            outcome.damage_taken += result.damage_taken;
+            if result.victory {
+                outcome.encounters_won += 1;
+            } else {
+                break;
+            }
+        }
+
+        if party.iter().any(SimCombatant::is_alive) {
+            for treasure in dungeon.treasures.iter().filter(|treasure| treasure.room_id == room_id) {
+                if let Some(difficulty) = treasure.trap_difficulty {
+                    match spring_trap(party, difficulty, rng) {
+                        Some(damage) => {
+                            outcome.traps_sprung += 1;
+                            outcome.damage_taken += damage as i32;
+                        }
+                        None => outcome.traps_disarmed += 1,
+                    }
+                }
+                outcome.gold_recovered += treasure.gold;
+            }
+        }
+    }
+
+    outcome.party_survivors = party.iter().filter(|member| member.is_alive()).count();
+    outcome
+}
+
+// The party's best trap-finder checks a trapped treasure; if they fail, its poison
+// needle goes off on whoever opens it, who saves against poison (see crate::traps). The
+// poison runs its course before the next room. Returns the damage dealt, or None if the
+// trap was found and disarmed.
+fn spring_trap(party: &mut [SimCombatant], difficulty: u8, rng: &mut impl Rng) -> Option<i16> {
+    let disarmed = best_at(party.iter().map(|member| &member.character), ThiefSkill::FindRemoveTraps)
+        .map(|thief| thief.check_skill(ThiefSkill::FindRemoveTraps, trap_modifier(difficulty), rng).success)
+        .unwrap_or(false);
+    if disarmed {
+        return None;
+    }
+
+    let trap = Trap {
+        position: GridPosition::new(0, 0),
+        room_id: 0,
+        kind: TrapKind::PoisonNeedle,
+        difficulty,
+        state: TrapState::Hidden,
+    };
+    let victim = party.iter_mut().find(|member| member.is_alive())?;
+    let outcome = trap.spring(&victim.character, rng);
+    let poison = outcome
+        .poison
+        .map_or(0, |effect| damage_per_round(&effect) * effect.duration as i16);
+    let damage = outcome.damage + poison;
+    victim.character.take_damage(damage);
+    Some(damage)
+}
+
+impl SimulationReport {
+    pub fn new(party_size: usize) -> Self {
+        Self {
+            party_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn record_encounter(&mut self, outcome: &EncounterOutcome) {
+        self.trials += 1;
+        self.victories += outcome.victory as u32;
+        self.total_rounds += outcome.rounds as u64;
+        self.total_survivors += outcome.party_survivors as u64;
+        self.total_damage_taken += outcome.damage_taken as i64;
+    }
+
+    pub fn record_delve(&mut self, outcome: &DelveOutcome) {
+        self.trials += 1;
+        self.victories += (outcome.encounters_won == outcome.encounters_fought && outcome.party_survivors > 0) as u32;
+        self.total_rounds += outcome.rounds as u64;
+        self.total_survivors += outcome.party_survivors as u64;
+        self.total_damage_taken += outcome.damage_taken as i64;
+        self.total_gold += outcome.gold_recovered as u64;
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        self.victories as f64 / self.trials as f64
+    }
+
+    fn average(&self, total: f64) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        total / self.trials as f64
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trials:            {}", self.trials)?;
+        writeln!(f, "Win rate:          {:.1}%", self.win_rate() * 100.0)?;
+        writeln!(f, "Average rounds:    {:.2}", self.average(self.total_rounds as f64))?;
+        writeln!(
+            f,
+            "Average survivors: {:.2} / {}",
+            self.average(self.total_survivors as f64),
+            self.party_size
+        )?;
+        writeln!(f, "Average damage:    {:.2}", self.average(self.total_damage_taken as f64))?;
+        write!(f, "Average gold:      {:.2}", self.average(self.total_gold as f64))
+    }
+}
+
+pub fn parse_class(name: &str) -> Option<CharacterClass> {
+    match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+        "fighter" => Some(CharacterClass::Fighter),
+        "magicuser" | "mage" => Some(CharacterClass::MagicUser),
+        "cleric" => Some(CharacterClass::Cleric),
+        "thief" => Some(CharacterClass::Thief),
+        "dwarf" => Some(CharacterClass::Dwarf),
+        "elf" => Some(CharacterClass::Elf),
+        "halfling" => Some(CharacterClass::Halfling),
+        _ => None,
+    }
+}
+
+fn default_weapon(class: &CharacterClass) -> Option<&'static str> {
+    match class {
+        CharacterClass::Fighter | CharacterClass::Dwarf | CharacterClass::Elf => Some("sword"),
+        CharacterClass::Cleric => Some("mace"),
+        CharacterClass::MagicUser => Some("dagger"),
+        CharacterClass::Thief | CharacterClass::Halfling => Some("sword"),
+    }
+}
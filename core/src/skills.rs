@@ -0,0 +1,153 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, CharacterClass};
+
+// Thief skills. Each is a percentile chance set by class and level; a check succeeds
+// when d100 rolls at or under the chance after any situational modifier.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ThiefSkill {
+    OpenLocks,
+    FindRemoveTraps,
+    PickPockets,
+    MoveSilently,
+    ClimbWalls,
+    HideInShadows,
+    HearNoise,
+}
+
+// B/X thief skills by level, one column per level 1-14. Hear Noise is 1-2, 1-3, 1-4 and
+// 1-5 in 6 in the book; it's kept here as the equivalent percentage.
+const OPEN_LOCKS: [u8; 14] = [15, 20, 25, 30, 35, 45, 55, 65, 75, 85, 95, 96, 97, 99];
+const FIND_REMOVE_TRAPS: [u8; 14] = [10, 15, 20, 25, 30, 40, 50, 60, 70, 80, 90, 95, 97, 99];
+const PICK_POCKETS: [u8; 14] = [20, 25, 30, 35, 40, 45, 55, 65, 75, 85, 95, 105, 115, 125];
+const MOVE_SILENTLY: [u8; 14] = [20, 25, 30, 35, 40, 45, 55, 65, 75, 85, 95, 96, 98, 99];
+const CLIMB_WALLS: [u8; 14] = [87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99, 99];
+const HIDE_IN_SHADOWS: [u8; 14] = [10, 15, 20, 25, 30, 36, 45, 55, 65, 75, 85, 90, 95, 99];
+const HEAR_NOISE: [u8; 14] = [33, 33, 50, 50, 50, 50, 67, 67, 67, 67, 83, 83, 83, 83];
+
+// Anyone can listen at a door: 1 in 6, or 2 in 6 for the demi-humans
+const HEAR_NOISE_HUMAN: u8 = 17;
+const HEAR_NOISE_DEMI_HUMAN: u8 = 33;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkillCheck {
+    pub skill: ThiefSkill,
+    pub chance: u8,
+    pub roll: u8,
+    pub success: bool,
+}
+
+impl ThiefSkill {
+    pub const ALL: [ThiefSkill; 7] = [
+        ThiefSkill::OpenLocks,
+        ThiefSkill::FindRemoveTraps,
+        ThiefSkill::PickPockets,
+        ThiefSkill::MoveSilently,
+        ThiefSkill::ClimbWalls,
+        ThiefSkill::HideInShadows,
+        ThiefSkill::HearNoise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThiefSkill::OpenLocks => "Open Locks",
+            ThiefSkill::FindRemoveTraps => "Find/Remove Traps",
+            ThiefSkill::PickPockets => "Pick Pockets",
+            ThiefSkill::MoveSilently => "Move Silently",
+            ThiefSkill::ClimbWalls => "Climb Walls",
+            ThiefSkill::HideInShadows => "Hide in Shadows",
+            ThiefSkill::HearNoise => "Hear Noise",
+        }
+    }
+
+    fn table(&self) -> &'static [u8; 14] {
+        match self {
+            ThiefSkill::OpenLocks => &OPEN_LOCKS,
+            ThiefSkill::FindRemoveTraps => &FIND_REMOVE_TRAPS,
+            ThiefSkill::PickPockets => &PICK_POCKETS,
+            ThiefSkill::MoveSilently => &MOVE_SILENTLY,
+            ThiefSkill::ClimbWalls => &CLIMB_WALLS,
+            ThiefSkill::HideInShadows => &HIDE_IN_SHADOWS,
+            ThiefSkill::HearNoise => &HEAR_NOISE,
+        }
+    }
+}
+
+impl Character {
+    // Percentage chance of the skill, or None if the character can't attempt it at all
+    pub fn skill_chance(&self, skill: ThiefSkill) -> Option<u8> {
+        match (&self.class, skill) {
+            (CharacterClass::Thief, _) => {
+                let index = (self.level.clamp(1, 14) - 1) as usize;
+                Some(skill.table()[index])
+            }
+            (CharacterClass::Dwarf | CharacterClass::Elf | CharacterClass::Halfling, ThiefSkill::HearNoise) => {
+                Some(HEAR_NOISE_DEMI_HUMAN)
+            }
+            (_, ThiefSkill::HearNoise) => Some(HEAR_NOISE_HUMAN),
+            _ => None,
+        }
+    }
+
+    // Rolls d100 against the skill; `modifier` is added to the chance (negative for a
+    // hard lock or a cunning trap). A character without the skill always fails.
+    pub fn check_skill(&self, skill: ThiefSkill, modifier: i16, rng: &mut impl Rng) -> SkillCheck {
+        let chance = self
+            .skill_chance(skill)
+            .map_or(0, |chance| (chance as i16 + modifier).clamp(0, 100) as u8);
+        let roll = rng.gen_range(1..=100);
+
+        SkillCheck {
+            skill,
+            chance,
+            roll,
+            success: roll <= chance,
+        }
+    }
+}
+
+// How much harder a trap of the given difficulty is to find and disarm
+pub fn trap_modifier(difficulty: u8) -> i16 {
+    -5 * difficulty as i16
+}
+
+// The party member best at a skill, if anyone alive has it
+pub fn best_at<'a>(party: impl IntoIterator<Item = &'a Character>, skill: ThiefSkill) -> Option<&'a Character> {
+    party
+        .into_iter()
+        .filter(|character| character.is_alive())
+        .filter_map(|character| character.skill_chance(skill).map(|chance| (chance, character)))
+        .max_by_key(|(chance, _)| *chance)
+        .map(|(_, character)| character)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::GameRng;
+
+    fn thief(level: u8) -> Character {
+        let mut character = Character::new_with("Thief".to_string(), CharacterClass::Thief, &mut GameRng::from_seed(1));
+        character.level = level;
+        character
+    }
+
+    #[test]
+    fn hide_in_shadows_follows_the_book() {
+        let chances: Vec<Option<u8>> =
+            (1..=14).map(|level| thief(level).skill_chance(ThiefSkill::HideInShadows)).collect();
+        let book = [10, 15, 20, 25, 30, 36, 45, 55, 65, 75, 85, 90, 95, 99];
+        assert_eq!(chances, book.map(Some));
+    }
+
+    #[test]
+    fn only_thieves_have_the_skills() {
+        let fighter = Character::new_with("Fighter".to_string(), CharacterClass::Fighter, &mut GameRng::from_seed(1));
+        assert_eq!(fighter.skill_chance(ThiefSkill::OpenLocks), None);
+        assert_eq!(fighter.skill_chance(ThiefSkill::HearNoise), Some(HEAR_NOISE_HUMAN));
+        let dwarf = Character::new_with("Dwarf".to_string(), CharacterClass::Dwarf, &mut GameRng::from_seed(1));
+        assert_eq!(dwarf.skill_chance(ThiefSkill::HearNoise), Some(HEAR_NOISE_DEMI_HUMAN));
+    }
+}
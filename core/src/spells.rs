@@ -0,0 +1,430 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::ailments::Ailment;
+use crate::battle_map::AreaShape;
+use crate::character::{Character, CharacterClass, MemorizedSpell, Spell, SpellCaster};
+use crate::combat::{DamageType, EffectType, StatusEffect};
+use crate::dice::DiceExpr;
+use crate::saves::SavingThrow;
+
+// Vancian spellcasting. A caster has a number of slots per spell level set by class and
+// level; each day (after a rest) they fill those slots from the spells they know, and
+// each memorized spell is gone once cast until the next rest.
+
+// B/X spells per day by caster level, one entry per spell level
+const MAGIC_USER_SLOTS: [&[u8]; 14] = [
+    &[1],
+    &[2],
+    &[2, 1],
+    &[2, 2],
+    &[2, 2, 1],
+    &[2, 2, 2],
+    &[3, 2, 2, 1],
+    &[3, 3, 2, 2],
+    &[3, 3, 3, 2, 1],
+    &[3, 3, 3, 3, 2],
+    &[4, 3, 3, 3, 2, 1],
+    &[4, 4, 3, 3, 3, 2],
+    &[4, 4, 4, 3, 3, 3],
+    &[4, 4, 4, 4, 3, 3],
+];
+
+const CLERIC_SLOTS: [&[u8]; 14] = [
+    &[],
+    &[1],
+    &[2],
+    &[2, 1],
+    &[2, 2],
+    &[2, 2, 1, 1],
+    &[2, 2, 2, 1, 1],
+    &[3, 3, 2, 2, 1],
+    &[3, 3, 3, 2, 2],
+    &[4, 4, 3, 3, 2],
+    &[4, 4, 4, 3, 3],
+    &[5, 5, 4, 4, 3],
+    &[5, 5, 5, 4, 4],
+    &[6, 5, 5, 5, 4],
+];
+
+impl CharacterClass {
+    pub fn spell_caster(&self) -> Option<SpellCaster> {
+        match self {
+            CharacterClass::MagicUser | CharacterClass::Elf => Some(SpellCaster::Arcane),
+            CharacterClass::Cleric => Some(SpellCaster::Divine),
+            _ => None,
+        }
+    }
+
+    // Slots per spell level (index 0 is 1st level) at the given character level
+    pub fn spell_slots(&self, level: u8) -> &'static [u8] {
+        let table = match self.spell_caster() {
+            Some(SpellCaster::Arcane) => &MAGIC_USER_SLOTS,
+            Some(SpellCaster::Divine) => &CLERIC_SLOTS,
+            None => return &[],
+        };
+        let index = (level.max(1) as usize - 1).min(table.len() - 1);
+        table[index]
+    }
+}
+
+impl Spell {
+    pub fn castable_by(&self, class: &CharacterClass) -> bool {
+        class.spell_caster().is_some_and(|caster| self.casters.contains(&caster))
+    }
+}
+
+impl Character {
+    pub fn spell_slots(&self, spell_level: u8) -> u8 {
+        match spell_level {
+            0 => 0,
+            level => self.class.spell_slots(self.level).get(level as usize - 1).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn memorized_at(&self, spell_level: u8) -> usize {
+        self.memorized_spells.iter().filter(|spell| spell.level == spell_level).count()
+    }
+
+    // Fills a free slot with a known spell
+    pub fn memorize(&mut self, name: &str) -> Result<(), String> {
+        let spell = self
+            .spells
+            .iter()
+            .find(|spell| spell.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("{} doesn't know {}", self.name, name))?;
+        if !spell.castable_by(&self.class) {
+            return Err(format!("A {} can't cast {}", self.class.name(), spell.name));
+        }
+        if self.memorized_at(spell.level) >= self.spell_slots(spell.level) as usize {
+            return Err(format!("{} has no level {} slots free", self.name, spell.level));
+        }
+
+        let memorized = MemorizedSpell {
+            name: spell.name.clone(),
+            level: spell.level,
+            cast: false,
+        };
+        self.memorized_spells.push(memorized);
+        Ok(())
+    }
+
+    // Gives up one memorized copy of the spell, freeing its slot; false if none is held
+    pub fn unmemorize(&mut self, name: &str) -> bool {
+        let Some(index) = self.memorized_spells.iter().position(|spell| spell.name.eq_ignore_ascii_case(name)) else {
+            return false;
+        };
+        self.memorized_spells.remove(index);
+        true
+    }
+
+    pub fn forget_spells(&mut self) {
+        self.memorized_spells.clear();
+    }
+
+    // The memorization step of a night's rest: yesterday's spells come back, and any
+    // slots left empty are filled with known spells in spellbook order
+    pub fn rest_and_memorize(&mut self) {
+        for spell in &mut self.memorized_spells {
+            spell.cast = false;
+        }
+
+        let known: Vec<String> = self
+            .spells
+            .iter()
+            .filter(|spell| spell.castable_by(&self.class))
+            .map(|spell| spell.name.clone())
+            .collect();
+        for name in known {
+            // Keeps failing quietly once the spell's level is full
+            let _ = self.memorize(&name);
+        }
+    }
+
+    // Uses up a memorized copy of the spell and returns it
+    pub fn cast_spell(&mut self, name: &str) -> Result<Spell, String> {
+        let spell = self
+            .spells
+            .iter()
+            .find(|spell| spell.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| format!("{} doesn't know {}", self.name, name))?;
+        let memorized = self
+            .memorized_spells
+            .iter_mut()
+            .find(|memorized| !memorized.cast && memorized.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("{} has no {} left to cast", self.name, spell.name))?;
+
+        memorized.cast = true;
+        Ok(spell)
+    }
+
+    pub fn castable_spells(&self) -> impl Iterator<Item = &MemorizedSpell> {
+        self.memorized_spells.iter().filter(|spell| !spell.cast)
+    }
+
+    // Each spell there's still a copy of to cast, once, with how many copies are left, in
+    // the order they were memorized
+    pub fn ready_spells(&self) -> Vec<(&str, usize)> {
+        let mut ready: Vec<(&str, usize)> = Vec::new();
+        for spell in self.castable_spells() {
+            match ready.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(&spell.name)) {
+                Some((_, copies)) => *copies += 1,
+                None => ready.push((&spell.name, 1)),
+            }
+        }
+        ready
+    }
+}
+
+// What a spell does when cast in combat, as the spell compendium writes it down:
+// `{"kind": "damage", "dice": "1d6+1", "damage_type": "Magic"}`. Spells without one can
+// be memorized and cast, but have no effect the rules model yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SpellEffect {
+    Damage { dice: DiceExpr, damage_type: DamageType },
+    // Damage dice per caster level (Fire Ball)
+    DamagePerLevel { die: DiceExpr, damage_type: DamageType },
+    Heal { dice: DiceExpr },
+    // Puts creatures of up to `max_level` to sleep, weakest first, until the rolled
+    // pool of hit dice runs out
+    Sleep { hit_dice: DiceExpr, max_level: u8, duration: DiceExpr },
+    // Holds each target fast for the rolled number of rounds (Hold Person, Web)
+    Hold { duration: DiceExpr },
+    ArmorBonus { bonus: i16, rounds: u8 },
+    // Added to attack rolls while it lasts (Bless)
+    RollModifier { modifier: i16, rounds: u8 },
+    // Rids the target of poison or disease (Neutralize Poison, Cure Disease)
+    Cure { cures: Ailment },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpellTarget {
+    Caster,
+    Ally,
+    Enemy,
+    AllEnemies,
+    // Everyone standing on the caster's side
+    Party,
+}
+
+// The effect with who it's aimed at, the save the targets get against it (a save halves
+// damage and shrugs anything else off) and, for spells that catch everyone nearby, the
+// size of the area in feet and its shape on the battle map (a burst unless it says)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpellDescriptor {
+    #[serde(flatten)]
+    pub effect: SpellEffect,
+    pub target: SpellTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save: Option<SavingThrow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<u16>,
+    #[serde(default, skip_serializing_if = "AreaShape::is_burst")]
+    pub shape: AreaShape,
+}
+
+impl SpellDescriptor {
+    // A line for the spellbook: "1d6 per level fire to every enemy, 20' radius, save vs
+    // Dragon Breath for half"
+    pub fn describe(&self) -> String {
+        let what = match &self.effect {
+            SpellEffect::Damage { dice, damage_type } => format!("{} {:?} damage", dice, damage_type).to_lowercase(),
+            SpellEffect::DamagePerLevel { die, damage_type } => {
+                format!("{} {:?} damage per level", die, damage_type).to_lowercase()
+            }
+            SpellEffect::Heal { dice } => format!("heals {}", dice),
+            SpellEffect::Sleep { hit_dice, max_level, duration } => {
+                format!("sleep for {} rounds, {} HD of level {} or less", duration, hit_dice, max_level)
+            }
+            SpellEffect::Hold { duration } => format!("held for {} rounds", duration),
+            SpellEffect::ArmorBonus { bonus, rounds } => format!("AC {:+} for {} rounds", bonus, rounds),
+            SpellEffect::RollModifier { modifier, rounds } => format!("{:+} to hit for {} rounds", modifier, rounds),
+            SpellEffect::Cure { cures } => format!("cures {}", cures.name()),
+        };
+        let whom = match self.target {
+            SpellTarget::Caster => "the caster",
+            SpellTarget::Ally => "one ally",
+            SpellTarget::Enemy => "one enemy",
+            SpellTarget::AllEnemies => "every enemy",
+            SpellTarget::Party => "the whole party",
+        };
+        let mut text = format!("{}, {}", what, whom);
+        if let Some(area) = self.area {
+            text.push_str(&format!(", {}' {}", area, self.shape.name()));
+        }
+        if let Some(save) = self.save {
+            let outcome = if self.halves_damage() { "for half" } else { "to resist" };
+            text.push_str(&format!(", save vs {} {}", save.name(), outcome));
+        }
+        text
+    }
+
+    fn halves_damage(&self) -> bool {
+        matches!(self.effect, SpellEffect::Damage { .. } | SpellEffect::DamagePerLevel { .. })
+    }
+}
+
+// Every spell there is, for learning, for the spellbook and for what casting one does.
+// Its entries are in core/data/core.spells.json, built in here for the game, the terminal
+// game and the simulator alike; the game adds the *.spells.json files of mods over them.
+// A spell on both lists at different levels (Hold Person) has an entry for each.
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpellCompendium {
+    pub spells: Vec<Spell>,
+}
+
+const BUILTIN_SPELLS: &str = include_str!("../data/core.spells.json");
+
+impl SpellCompendium {
+    pub fn builtin() -> &'static SpellCompendium {
+        static BUILTIN: OnceLock<SpellCompendium> = OnceLock::new();
+        BUILTIN.get_or_init(|| serde_json::from_str(BUILTIN_SPELLS).expect("core.spells.json is a valid compendium"))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Spell> {
+        self.spells.iter().find(|spell| spell.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn of_level(&self, level: u8) -> impl Iterator<Item = &Spell> {
+        self.spells.iter().filter(move |spell| spell.level == level)
+    }
+
+    // Another compendium's spells added to these, replacing any of the same name, level
+    // and list
+    pub fn extend(&mut self, other: &SpellCompendium) {
+        for spell in &other.spells {
+            let same = |existing: &Spell| {
+                existing.name.eq_ignore_ascii_case(&spell.name)
+                    && existing.level == spell.level
+                    && existing.casters == spell.casters
+            };
+            match self.spells.iter_mut().find(|existing| same(existing)) {
+                Some(existing) => *existing = spell.clone(),
+                None => self.spells.push(spell.clone()),
+            }
+        }
+    }
+
+    // What the spell does, by the compendium rather than a caster's copy of it, so edits
+    // reach spells already in spellbooks
+    pub fn descriptor(&self, spell: &str) -> Option<&SpellDescriptor> {
+        self.get(spell).and_then(|spell| spell.effect.as_ref())
+    }
+}
+
+// The outcome of a spell against each target, for the frontend to apply
+#[derive(Debug, Clone)]
+pub enum SpellOutcome {
+    Damage { target: usize, amount: i16, damage_type: DamageType, saved: bool },
+    Heal { target: usize, amount: i16 },
+    Status { target: usize, effect: StatusEffect },
+    Cure { target: usize, ailment: Ailment },
+    // Saved against a spell that would have put an effect on them
+    Resisted { target: usize },
+}
+
+// Resolves a spell against the chosen targets (indices into `targets`), each target
+// rolling its save if the spell allows one
+pub fn resolve_spell(
+    spell: &Spell,
+    descriptor: &SpellDescriptor,
+    caster: &Character,
+    targets: &[&Character],
+    rng: &mut impl Rng,
+) -> Vec<SpellOutcome> {
+    let saved = |target: usize, rng: &mut _| {
+        descriptor.save.is_some_and(|save| targets[target].roll_save(save, rng).success)
+    };
+    let status = |name: &str, duration: u8, effect_type: EffectType, magnitude: i16| StatusEffect {
+        name: name.to_string(),
+        duration,
+        effect_type,
+        magnitude,
+    };
+
+    match &descriptor.effect {
+        SpellEffect::Damage { dice, damage_type } => damage_outcomes(*dice, damage_type, targets.len(), saved, rng),
+        SpellEffect::DamagePerLevel { die, damage_type } => {
+            let dice = DiceExpr::new(caster.level.max(1), die.sides, die.modifier);
+            damage_outcomes(dice, damage_type, targets.len(), saved, rng)
+        }
+        SpellEffect::Heal { dice } => (0..targets.len())
+            .map(|target| SpellOutcome::Heal { target, amount: dice.roll(rng).max(1) })
+            .collect(),
+        SpellEffect::Sleep { hit_dice, max_level, duration } => {
+            let mut pool = hit_dice.roll(rng);
+            let mut order: Vec<usize> = (0..targets.len())
+                .filter(|&index| targets[index].is_alive() && targets[index].level <= *max_level)
+                .collect();
+            order.sort_by_key(|&index| targets[index].level);
+
+            let mut outcomes = Vec::new();
+            for target in order {
+                let level = targets[target].level.max(1) as i16;
+                if level > pool {
+                    break;
+                }
+                pool -= level;
+                outcomes.push(if saved(target, rng) {
+                    SpellOutcome::Resisted { target }
+                } else {
+                    let rounds = duration.roll(rng).clamp(1, u8::MAX as i16) as u8;
+                    SpellOutcome::Status { target, effect: status(&spell.name, rounds, EffectType::Stun, 0) }
+                });
+            }
+            outcomes
+        }
+        SpellEffect::Hold { duration } => (0..targets.len())
+            .filter(|&target| targets[target].is_alive())
+            .map(|target| {
+                if saved(target, rng) {
+                    return SpellOutcome::Resisted { target };
+                }
+                let rounds = duration.roll(rng).clamp(1, u8::MAX as i16) as u8;
+                SpellOutcome::Status { target, effect: status(&spell.name, rounds, EffectType::Stun, 0) }
+            })
+            .collect(),
+        SpellEffect::ArmorBonus { bonus, rounds } => (0..targets.len())
+            .map(|target| SpellOutcome::Status {
+                target,
+                effect: status(&spell.name, *rounds, EffectType::ArmorBonus, *bonus),
+            })
+            .collect(),
+        SpellEffect::RollModifier { modifier, rounds } => (0..targets.len())
+            .map(|target| SpellOutcome::Status {
+                target,
+                effect: status(&spell.name, *rounds, EffectType::StatModifier, *modifier),
+            })
+            .collect(),
+        SpellEffect::Cure { cures } => {
+            (0..targets.len()).map(|target| SpellOutcome::Cure { target, ailment: *cures }).collect()
+        }
+    }
+}
+
+// The dice are rolled for each target, and halved for anyone who saves
+fn damage_outcomes<R: Rng>(
+    dice: DiceExpr,
+    damage_type: &DamageType,
+    targets: usize,
+    saved: impl Fn(usize, &mut R) -> bool,
+    rng: &mut R,
+) -> Vec<SpellOutcome> {
+    (0..targets)
+        .map(|target| {
+            let amount = dice.roll(rng).max(1);
+            let saved = saved(target, rng);
+            SpellOutcome::Damage {
+                target,
+                amount: if saved { (amount / 2).max(1) } else { amount },
+                damage_type: damage_type.clone(),
+                saved,
+            }
+        })
+        .collect()
+}
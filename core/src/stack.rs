@@ -0,0 +1,111 @@
+use crate::character::Inventory;
+use crate::item::{Item, ItemType};
+
+// Stacks. Arrows, torches, flasks of oil, rations and potions of the same kind are
+// carried as one item with a quantity, up to a limit for the kind: a quiver of 20
+// arrows, a bundle of 6 torches. A stack weighs and is worth its quantity times one of
+// its items. Using or selling takes one from the top, splitting halves a stack, and
+// dropping one stack on another of the same thing merges them as far as the limit
+// allows.
+
+// How many days of food make up one pack of rations
+pub const RATION_DAYS: u16 = 7;
+
+impl Item {
+    // The most a stack of this can hold; 1 for things that don't stack
+    pub fn max_stack(&self) -> u16 {
+        if self.is_container() {
+            return 1;
+        }
+        let name = self.name.to_lowercase();
+        match self.item_type {
+            ItemType::Potion => 5,
+            ItemType::Misc if ["arrow", "quarrel", "bolt", "sling stone"].iter().any(|kind| name.contains(kind)) => 20,
+            ItemType::Misc if name.contains("torch") || name.contains("flask of oil") => 6,
+            ItemType::Misc if name.contains("ration") => 4,
+            _ => 1,
+        }
+    }
+
+    pub fn is_stack(&self) -> bool {
+        self.quantity > 1
+    }
+
+    pub fn total_value(&self) -> u32 {
+        self.value * self.quantity as u32
+    }
+
+    pub fn stack_room(&self) -> u16 {
+        self.max_stack().saturating_sub(self.quantity)
+    }
+
+    // Whether `other` is the same thing, so the two could be one stack. Rations only
+    // stack with rations as full as they are.
+    pub fn stacks_with(&self, other: &Item) -> bool {
+        self.max_stack() > 1
+            && self.name == other.name
+            && self.item_type == other.item_type
+            && self.uses == other.uses
+            && self.properties.magic_bonus == other.properties.magic_bonus
+            && self.properties.effects == other.properties.effects
+            && self.properties.identified == other.properties.identified
+    }
+
+    // Takes `count` off the stack as a stack of its own, leaving at least one behind
+    pub fn split_off(&mut self, count: u16) -> Option<Item> {
+        if count == 0 || count >= self.quantity {
+            return None;
+        }
+        self.quantity -= count;
+        let mut taken = self.clone();
+        taken.quantity = count;
+        Some(taken)
+    }
+}
+
+impl Inventory {
+    // Splits the stack at `index` in two, the new half going to the end of the pack
+    pub fn split_stack(&mut self, index: usize) -> Result<(), String> {
+        let item = self.items.get_mut(index).ok_or_else(|| format!("There is no item {}", index + 1))?;
+        let half = item.quantity / 2;
+        let taken = item.split_off(half).ok_or_else(|| format!("There is only one {}", item.name))?;
+        self.items.push(taken);
+        Ok(())
+    }
+
+    // Moves as much of the stack at `from` onto the one at `into` as it has room for;
+    // whatever doesn't fit stays where it was
+    pub fn merge_stacks(&mut self, from: usize, into: usize) -> Result<u16, String> {
+        if from == into {
+            return Err("That's the same stack".to_string());
+        }
+        let (Some(source), Some(target)) = (self.items.get(from), self.items.get(into)) else {
+            return Err(format!("There is no item {}", from.max(into) + 1));
+        };
+        if !target.stacks_with(source) {
+            return Err(format!("{} and {} don't stack", source.name, target.name));
+        }
+        let moved = source.quantity.min(target.stack_room());
+        if moved == 0 {
+            return Err(format!("That stack of {} is full", target.name));
+        }
+        self.items[into].quantity += moved;
+        self.items[from].quantity -= moved;
+        if self.items[from].quantity == 0 {
+            self.items.remove(from);
+        }
+        Ok(moved)
+    }
+
+    // Takes one item off the stack at `index`, or the item itself if it's the last
+    pub fn remove_one(&mut self, index: usize) -> Option<Item> {
+        let item = self.items.get_mut(index)?;
+        match item.split_off(1) {
+            Some(one) => {
+                self.current_weight = (self.current_weight - one.total_weight()).max(0.0);
+                Some(one)
+            }
+            None => self.remove_item(index),
+        }
+    }
+}
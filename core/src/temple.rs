@@ -0,0 +1,131 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::combat::{EffectType, StatusEffect};
+use crate::equipment::EquipmentSlot;
+use crate::item::Item;
+
+// The temple's services. The priests cure wounds, neutralize poison, cure disease,
+// remove curses and raise the dead, each for a price per level of the character they
+// work on. Remove curse and raise dead aren't for just anyone: the temple grants them
+// only to a party that has given enough, one point of standing for every 100 gp
+// donated. Raising the dead is the high priest's, with the limits and the survival roll
+// of `crate::death`.
+
+pub const DONATION_PER_STANDING: u32 = 100;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TempleService {
+    CureWounds,
+    NeutralizePoison,
+    CureDisease,
+    RemoveCurse,
+    RaiseDead,
+}
+
+// What the party has given the temple
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TempleFavor {
+    pub donated: u32,
+}
+
+impl TempleService {
+    pub const ALL: [TempleService; 5] = [
+        TempleService::CureWounds,
+        TempleService::NeutralizePoison,
+        TempleService::CureDisease,
+        TempleService::RemoveCurse,
+        TempleService::RaiseDead,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TempleService::CureWounds => "Cure wounds",
+            TempleService::NeutralizePoison => "Neutralize poison",
+            TempleService::CureDisease => "Cure disease",
+            TempleService::RemoveCurse => "Remove curse",
+            TempleService::RaiseDead => "Raise dead",
+        }
+    }
+
+    // Per level of the character
+    pub fn price_per_level(&self) -> u32 {
+        match self {
+            TempleService::CureWounds => 10,
+            TempleService::NeutralizePoison => 50,
+            TempleService::CureDisease => 50,
+            TempleService::RemoveCurse => 100,
+            TempleService::RaiseDead => 500,
+        }
+    }
+
+    pub fn price(&self, character: &Character) -> u32 {
+        self.price_per_level() * character.level.max(1) as u32
+    }
+
+    pub fn standing_required(&self) -> u32 {
+        match self {
+            TempleService::RemoveCurse => 1,
+            TempleService::RaiseDead => 5,
+            _ => 0,
+        }
+    }
+
+    // Whether the service would do the character any good
+    pub fn is_needed(&self, character: &Character, effects: &[StatusEffect]) -> bool {
+        let has = |wanted: fn(&EffectType) -> bool| effects.iter().any(|effect| wanted(&effect.effect_type));
+        match self {
+            TempleService::CureWounds => character.is_alive() && character.hit_points.current < character.hit_points.maximum,
+            TempleService::NeutralizePoison => character.is_alive() && has(|effect| matches!(effect, EffectType::Poison)),
+            TempleService::CureDisease => character.is_alive() && has(|effect| matches!(effect, EffectType::Disease)),
+            TempleService::RemoveCurse => {
+                EquipmentSlot::ALL.iter().filter_map(|slot| character.equipment.get(*slot)).any(Item::is_cursed)
+                    || character.inventory.items.iter().any(Item::is_cursed)
+            }
+            TempleService::RaiseDead => !character.is_alive(),
+        }
+    }
+
+    // Works the service on the character and says what it did
+    pub fn perform(&self, character: &mut Character, effects: &mut Vec<StatusEffect>, rng: &mut impl Rng) -> String {
+        match self {
+            TempleService::CureWounds => {
+                character.hit_points.current = character.hit_points.maximum;
+                format!("{}'s wounds close", character.name)
+            }
+            TempleService::NeutralizePoison => {
+                effects.retain(|effect| !matches!(effect.effect_type, EffectType::Poison));
+                format!("The poison leaves {}", character.name)
+            }
+            TempleService::CureDisease => {
+                effects.retain(|effect| !matches!(effect.effect_type, EffectType::Disease));
+                format!("{} is cured", character.name)
+            }
+            TempleService::RemoveCurse => {
+                let lifted = character.lift_curses();
+                format!("{} is rid of the {}", character.name, lifted.join(", "))
+            }
+            TempleService::RaiseDead => {
+                effects.clear();
+                character.raise(rng).1
+            }
+        }
+    }
+}
+
+impl TempleFavor {
+    pub fn standing(&self) -> u32 {
+        self.donated / DONATION_PER_STANDING
+    }
+
+    // Whether the temple will perform `service` for the party
+    pub fn grants(&self, service: TempleService) -> bool {
+        self.standing() >= service.standing_required()
+    }
+
+    pub fn donate(&mut self, gold: u32) {
+        self.donated += gold;
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shop::MerchantKind;
+
+// The town the party sets out from between expeditions. It has an inn, a temple, a
+// blacksmith, a general store and an adventurers' guild, each with someone there to
+// talk to; the blacksmith and the general store are the market's two merchants.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Location {
+    Inn,
+    Temple,
+    Blacksmith,
+    GeneralStore,
+    Guild,
+}
+
+// Someone the party can find at a location, as the AI service is told about them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resident {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub personality: &'static str,
+    pub background: &'static str,
+}
+
+const INN: &[Resident] = &[
+    Resident {
+        id: "innkeeper",
+        name: "Marta the Innkeeper",
+        personality: "A cheerful innkeeper who knows all the local gossip",
+        background: "Has kept the Green Dragon for twenty years and hears every traveller's tale",
+    },
+    Resident {
+        id: "old_soldier",
+        name: "Old Bram",
+        personality: "A grizzled veteran who trades stories for drinks",
+        background: "Lost an eye to goblins in the hills and never went back",
+    },
+];
+
+const TEMPLE: &[Resident] = &[Resident {
+    id: "high_priest",
+    name: "Father Aldous",
+    personality: "A stern priest who follows strict moral codes",
+    background: "Tends the sick and the dead of the town and asks a fair donation for it",
+}];
+
+const BLACKSMITH: &[Resident] = &[Resident {
+    id: "blacksmith",
+    name: "Gerd the Smith",
+    personality: "A gruff but honest merchant who values fair deals",
+    background: "Forges and mends arms and armour for the town watch and anyone with coin",
+}];
+
+const GENERAL_STORE: &[Resident] = &[Resident {
+    id: "storekeeper",
+    name: "Pell the Storekeeper",
+    personality: "A fussy, talkative shopkeeper who haggles over every copper",
+    background: "Outfits every party that goes out to the dungeons, and knows who never came back",
+}];
+
+const GUILD: &[Resident] = &[
+    Resident {
+        id: "guildmaster",
+        name: "Guildmaster Ysolde",
+        personality: "A shrewd retired adventurer who weighs everyone up at a glance",
+        background: "Runs the adventurers' guild, keeps its maps and hears of every new ruin",
+    },
+    Resident {
+        id: "sage",
+        name: "Corvin the Sage",
+        personality: "A wise old sage who speaks in riddles and ancient proverbs",
+        background: "Reads old inscriptions and identifies strange finds for the guild",
+    },
+];
+
+impl Location {
+    pub const ALL: [Location; 5] = [
+        Location::Inn,
+        Location::Temple,
+        Location::Blacksmith,
+        Location::GeneralStore,
+        Location::Guild,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Location::Inn => "The Green Dragon Inn",
+            Location::Temple => "The Temple",
+            Location::Blacksmith => "The Blacksmith",
+            Location::GeneralStore => "The General Store",
+            Location::Guild => "The Adventurers' Guild",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Location::Inn => "A low, smoky common room full of travellers, with rooms to let upstairs.",
+            Location::Temple => "Cool stone and candlelight. The priests tend the wounded in a side chapel.",
+            Location::Blacksmith => "The forge roars. Blades and mail hang along the walls.",
+            Location::GeneralStore => "Shelves of rope, lanterns, rations and everything else an expedition needs.",
+            Location::Guild => "Maps and trophies cover the walls of the guild hall. Adventurers come and go.",
+        }
+    }
+
+    pub fn residents(&self) -> &'static [Resident] {
+        match self {
+            Location::Inn => INN,
+            Location::Temple => TEMPLE,
+            Location::Blacksmith => BLACKSMITH,
+            Location::GeneralStore => GENERAL_STORE,
+            Location::Guild => GUILD,
+        }
+    }
+
+    // The market's merchant who keeps shop here
+    pub fn merchant(&self) -> Option<MerchantKind> {
+        match self {
+            Location::Blacksmith => Some(MerchantKind::Smith),
+            Location::GeneralStore => Some(MerchantKind::Outfitter),
+            _ => None,
+        }
+    }
+}
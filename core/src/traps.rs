@@ -0,0 +1,125 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::battle_map::GridPosition;
+use crate::character::{Character, CharacterClass};
+use crate::combat::{EffectType, StatusEffect};
+use crate::dice;
+use crate::saves::{SaveRoll, SavingThrow};
+use crate::skills::{trap_modifier, ThiefSkill};
+
+// Dungeon traps. A trap lies hidden on a square until someone finds it by searching:
+// anyone has a 1 in 6 chance, a dwarf 2 in 6, and a thief can use Find/Remove Traps as
+// well. A trap that hasn't been found goes off on whoever steps on the square; the
+// victim saves to get out of the worst of it. Only a thief can disarm one. Found traps
+// are stepped around.
+
+// Chance in six of spotting a trap by searching, for anyone and for a dwarf
+const SEARCH_CHANCE_IN_SIX: u8 = 1;
+const DWARF_SEARCH_CHANCE_IN_SIX: u8 = 2;
+
+// Poison from a needle does this much a round for three rounds, and one more per
+// point of difficulty
+pub const POISON_DAMAGE_PER_ROUND: i16 = 1;
+const POISON_ROUNDS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrapKind {
+    // A covered pit: 1d6 on a failed save against paralysis, nothing on a made one
+    Pit,
+    // A needle in a chest's lock: a prick, and poison on a failed save against poison
+    PoisonNeedle,
+    // A block from the ceiling: 2d6, half on a save against paralysis
+    FallingBlock,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TrapState {
+    #[default]
+    Hidden,
+    Found,
+    Disarmed,
+    Sprung,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Trap {
+    pub position: GridPosition,
+    pub room_id: u32,
+    pub kind: TrapKind,
+    // Added to the damage and taken off the chance to find and disarm it, 5% a point
+    pub difficulty: u8,
+    #[serde(default)]
+    pub state: TrapState,
+}
+
+// What a trap did to the one who set it off
+#[derive(Debug, Clone)]
+pub struct TrapOutcome {
+    pub save: SaveRoll,
+    pub damage: i16,
+    pub poison: Option<StatusEffect>,
+}
+
+impl TrapKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrapKind::Pit => "pit",
+            TrapKind::PoisonNeedle => "poison needle",
+            TrapKind::FallingBlock => "falling block",
+        }
+    }
+
+    pub fn save(&self) -> SavingThrow {
+        match self {
+            TrapKind::Pit | TrapKind::FallingBlock => SavingThrow::ParalysisPetrify,
+            TrapKind::PoisonNeedle => SavingThrow::DeathPoison,
+        }
+    }
+}
+
+impl Trap {
+    // Still able to go off
+    pub fn is_armed(&self) -> bool {
+        matches!(self.state, TrapState::Hidden | TrapState::Found)
+    }
+
+    pub fn spring(&self, victim: &Character, rng: &mut impl Rng) -> TrapOutcome {
+        let save = victim.roll_save(self.kind.save(), rng);
+        let difficulty = self.difficulty as i16;
+        let (damage, poison) = match self.kind {
+            TrapKind::Pit if save.success => (0, None),
+            TrapKind::Pit => (dice::roll(rng, 1, 6) + difficulty, None),
+            TrapKind::PoisonNeedle if save.success => (1, None),
+            TrapKind::PoisonNeedle => (
+                1,
+                Some(StatusEffect {
+                    name: "Poison".to_string(),
+                    duration: POISON_ROUNDS.saturating_add(self.difficulty),
+                    effect_type: EffectType::Poison,
+                    magnitude: POISON_DAMAGE_PER_ROUND,
+                }),
+            ),
+            TrapKind::FallingBlock => {
+                let damage = dice::roll(rng, 2, 6) + difficulty;
+                (if save.success { damage / 2 } else { damage }, None)
+            }
+        };
+        TrapOutcome { save, damage, poison }
+    }
+}
+
+impl Character {
+    pub fn search_chance(&self) -> u8 {
+        match self.class {
+            CharacterClass::Dwarf => DWARF_SEARCH_CHANCE_IN_SIX,
+            _ => SEARCH_CHANCE_IN_SIX,
+        }
+    }
+
+    // One search of the area around the party for this trap
+    pub fn search_for_trap(&self, trap: &Trap, rng: &mut impl Rng) -> bool {
+        dice::d6(rng) <= self.search_chance()
+            || self.check_skill(ThiefSkill::FindRemoveTraps, trap_modifier(trap.difficulty), rng).success
+    }
+}
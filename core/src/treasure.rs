@@ -0,0 +1,762 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::dice::{self, DiceExpr, DiceParseError};
+use crate::dungeon::{DungeonData, RoomType, TreasureData};
+use crate::item::{Item, ItemProperties, ItemType};
+use crate::monster::EnemyData;
+
+// B/X treasure types. A table is a list of lines, each a percent chance of some amount
+// of coins, gems, jewellery or magic items; gems, jewellery and magic items are then
+// rolled on their own sub-tables. Types A-O are lair hoards, P-T what individual
+// monsters carry and U-V small hoards. The same tables bound what the AI service may
+// place in a room, stock rooms it left empty and give what a won fight's monsters leave.
+
+pub const TREASURE_TYPES: &str = "ABCDEFGHIJKLMNOPQRSTUV";
+
+// The most a single gem (d20 table) or piece of jewellery (3d6 x 100) can be worth
+const MAX_GEM_VALUE: u32 = 1000;
+const MAX_JEWELLERY_VALUE: u32 = 1800;
+// A gem or a piece of jewellery weighs about as much as a coin
+const TREASURE_ITEM_WEIGHT: f32 = 0.1;
+// The types individual monsters carry, each their own
+const INDIVIDUAL_TYPES: &str = "PQRST";
+// How many coins "Gold Coins" on a loot table is
+const LOOT_TABLE_COINS: &str = "3d6";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TreasureKind {
+    Copper,
+    Silver,
+    Electrum,
+    Gold,
+    Platinum,
+    Gems,
+    Jewellery,
+    // Any magic item
+    MagicItem,
+    // A sword, a weapon or armour
+    MagicWeapon,
+    // Anything but a sword or a weapon
+    MagicNotWeapon,
+    Potion,
+    Scroll,
+}
+
+// One line of a treasure table: a `chance` in 100 of `amount` of `kind`, where the
+// amount is a dice expression with an optional multiplier ("1d6*1000"). Each kind in
+// `plus` comes along, one of each, whenever the line is rolled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasureRoll {
+    pub kind: TreasureKind,
+    pub chance: u8,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plus: Vec<TreasureKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MagicCategory {
+    Sword,
+    Weapon,
+    Armour,
+    Potion,
+    Scroll,
+    Ring,
+    WandStaffRod,
+    Miscellaneous,
+}
+
+// Gem and jewellery values are in gold pieces
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Hoard {
+    pub copper: u32,
+    pub silver: u32,
+    pub electrum: u32,
+    pub gold: u32,
+    pub platinum: u32,
+    pub gems: Vec<u32>,
+    pub jewellery: Vec<u32>,
+    pub magic_items: Vec<String>,
+}
+
+impl TreasureKind {
+    pub fn is_coin(&self) -> bool {
+        matches!(
+            self,
+            TreasureKind::Copper | TreasureKind::Silver | TreasureKind::Electrum | TreasureKind::Gold | TreasureKind::Platinum
+        )
+    }
+
+    pub fn is_magic(&self) -> bool {
+        matches!(
+            self,
+            TreasureKind::MagicItem
+                | TreasureKind::MagicWeapon
+                | TreasureKind::MagicNotWeapon
+                | TreasureKind::Potion
+                | TreasureKind::Scroll
+        )
+    }
+
+    // What `coins` of this kind are worth in gold pieces, for coins
+    pub fn gold_value(&self, coins: u32) -> Option<u32> {
+        match self {
+            TreasureKind::Copper => Some(coins / 100),
+            TreasureKind::Silver => Some(coins / 10),
+            TreasureKind::Electrum => Some(coins / 2),
+            TreasureKind::Gold => Some(coins),
+            TreasureKind::Platinum => Some(coins * 5),
+            _ => None,
+        }
+    }
+
+    fn accepts(&self, category: MagicCategory) -> bool {
+        match self {
+            TreasureKind::MagicWeapon => {
+                matches!(category, MagicCategory::Sword | MagicCategory::Weapon | MagicCategory::Armour)
+            }
+            TreasureKind::MagicNotWeapon => !matches!(category, MagicCategory::Sword | MagicCategory::Weapon),
+            TreasureKind::Potion => category == MagicCategory::Potion,
+            TreasureKind::Scroll => category == MagicCategory::Scroll,
+            _ => true,
+        }
+    }
+}
+
+impl MagicCategory {
+    // d100: swords 01-25, armour 26-35, weapons 36-40, potions 41-65, scrolls 66-85,
+    // rings 86-90, wands, staves and rods 91-95, miscellaneous 96-00
+    pub fn from_roll(roll: u8) -> Self {
+        match roll {
+            0..=25 => MagicCategory::Sword,
+            26..=35 => MagicCategory::Armour,
+            36..=40 => MagicCategory::Weapon,
+            41..=65 => MagicCategory::Potion,
+            66..=85 => MagicCategory::Scroll,
+            86..=90 => MagicCategory::Ring,
+            91..=95 => MagicCategory::WandStaffRod,
+            _ => MagicCategory::Miscellaneous,
+        }
+    }
+
+    pub fn items(&self) -> &'static [&'static str] {
+        match self {
+            MagicCategory::Sword => &[
+                "Sword +1",
+                "Sword +1, +2 vs Lycanthropes",
+                "Sword +1, +2 vs Spell Users",
+                "Sword +1, +3 vs Undead",
+                "Sword +2",
+                "Sword -1 (cursed)",
+            ],
+            MagicCategory::Weapon => &["Arrows +1", "Axe +1", "Dagger +1", "Mace +1", "Sling +1", "War Hammer +1"],
+            MagicCategory::Armour => &[
+                "Leather Armor +1",
+                "Chain Mail +1",
+                "Plate Mail +1",
+                "Shield +1",
+                "Chain Mail +1 and Shield +1",
+                "Armor -1 (cursed)",
+            ],
+            MagicCategory::Potion => &[
+                "Potion of Healing",
+                "Potion of Diminution",
+                "Potion of ESP",
+                "Potion of Gaseous Form",
+                "Potion of Growth",
+                "Potion of Invisibility",
+                "Potion of Levitation",
+                "Potion of Poison",
+            ],
+            MagicCategory::Scroll => &[
+                "Scroll (1 spell)",
+                "Scroll (2 spells)",
+                "Scroll (3 spells)",
+                "Scroll of Protection from Lycanthropes",
+                "Scroll of Protection from Undead",
+                "Treasure Map",
+                "Cursed Scroll",
+            ],
+            MagicCategory::Ring => &[
+                "Ring of Protection +1",
+                "Ring of Invisibility",
+                "Ring of Water Walking",
+                "Ring of Fire Resistance",
+                "Ring of Animal Control",
+                "Ring of Weakness",
+            ],
+            MagicCategory::WandStaffRod => &[
+                "Wand of Magic Detection",
+                "Wand of Enemy Detection",
+                "Wand of Secret Door Detection",
+                "Wand of Paralysation",
+                "Staff of Healing",
+                "Snake Staff",
+                "Rod of Cancellation",
+            ],
+            MagicCategory::Miscellaneous => &[
+                "Bag of Holding",
+                "Boots of Levitation",
+                "Crystal Ball",
+                "Elven Cloak and Boots",
+                "Gauntlets of Ogre Power",
+                "Helm of Telepathy",
+                "Rope of Climbing",
+            ],
+        }
+    }
+}
+
+impl Hoard {
+    // Everything valued in gold pieces, magic items aside
+    pub fn gold_value(&self) -> u32 {
+        self.coin_value() + self.gems.iter().sum::<u32>() + self.jewellery.iter().sum::<u32>()
+    }
+
+    pub fn coin_value(&self) -> u32 {
+        self.copper / 100 + self.silver / 10 + self.electrum / 2 + self.gold + self.platinum * 5
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.copper + self.silver + self.electrum + self.gold + self.platinum == 0
+            && self.gems.is_empty()
+            && self.jewellery.is_empty()
+            && self.magic_items.is_empty()
+    }
+
+    fn add_coins(&mut self, kind: TreasureKind, amount: u32) {
+        let purse = match kind {
+            TreasureKind::Copper => &mut self.copper,
+            TreasureKind::Silver => &mut self.silver,
+            TreasureKind::Electrum => &mut self.electrum,
+            TreasureKind::Gold => &mut self.gold,
+            TreasureKind::Platinum => &mut self.platinum,
+            _ => return,
+        };
+        *purse += amount;
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for (amount, coin) in [
+            (self.platinum, "pp"),
+            (self.gold, "gp"),
+            (self.electrum, "ep"),
+            (self.silver, "sp"),
+            (self.copper, "cp"),
+        ] {
+            if amount > 0 {
+                parts.push(format!("{} {}", amount, coin));
+            }
+        }
+        if !self.gems.is_empty() {
+            parts.push(format!("{} gems ({} gp)", self.gems.len(), self.gems.iter().sum::<u32>()));
+        }
+        if !self.jewellery.is_empty() {
+            parts.push(format!("{} jewellery ({} gp)", self.jewellery.len(), self.jewellery.iter().sum::<u32>()));
+        }
+        parts.extend(self.magic_items.iter().cloned());
+
+        if parts.is_empty() {
+            "nothing".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    // As the AI service would describe it: the coins as gold, every gem, piece of
+    // jewellery and magic item as an item
+    pub fn into_treasure(self, room_id: u32) -> TreasureData {
+        let gold = self.coin_value();
+        let items = self
+            .gems
+            .iter()
+            .map(|value| format!("Gem ({} gp)", value))
+            .chain(self.jewellery.iter().map(|value| format!("Jewellery ({} gp)", value)))
+            .chain(self.magic_items)
+            .collect();
+
+        TreasureData {
+            room_id,
+            items,
+            gold,
+            is_hidden: false,
+            trap_difficulty: None,
+        }
+    }
+}
+
+// A treasure from a room as something to carry. "Gem (50 gp)" and "Jewellery (300 gp)"
+// are worth what they say; anything else (a magic item, say) is valued at nothing until
+// someone who knows looks at it. Magic rings, cloaks and boots can be worn, with any
+// "+1" at the end of the name as their bonus.
+pub fn treasure_item(name: &str) -> Item {
+    let value = name
+        .rsplit_once('(')
+        .and_then(|(_, rest)| rest.strip_suffix("gp)"))
+        .and_then(|amount| amount.trim().parse().ok())
+        .unwrap_or(0);
+    let item_type = if name.starts_with("Ring of") {
+        ItemType::Ring
+    } else if name.contains("Cloak") {
+        ItemType::Cloak
+    } else if name.starts_with("Boots of") {
+        ItemType::Boots
+    } else {
+        ItemType::Treasure
+    };
+    let magic_bonus = match item_type {
+        ItemType::Treasure => None,
+        _ => name.rsplit_once(' ').and_then(|(_, bonus)| bonus.parse().ok()),
+    };
+    Item {
+        name: name.to_string(),
+        item_type,
+        weight: TREASURE_ITEM_WEIGHT,
+        value,
+        properties: ItemProperties {
+            damage: None,
+            armor_bonus: None,
+            magic_bonus,
+            effects: Vec::new(),
+            identified: true,
+        },
+        container: None,
+        uses: None,
+        quantity: 1,
+    }
+}
+
+// "1d6*1000" is 1d6 thousands; a plain dice expression has a multiplier of 1
+pub fn parse_amount(amount: &str) -> Result<(DiceExpr, u32), DiceParseError> {
+    let Some((dice, multiplier)) = amount.split_once('*') else {
+        return Ok((amount.parse()?, 1));
+    };
+    let multiplier = multiplier.trim().parse::<u32>().map_err(|_| DiceParseError {
+        input: amount.to_string(),
+        reason: "invalid multiplier",
+    })?;
+    Ok((dice.parse()?, multiplier))
+}
+
+// d20: 10 gp on 1-4, 50 on 5-9, 100 on 10-15, 500 on 16-19 and 1000 on a 20
+pub fn roll_gem_value(rng: &mut impl Rng) -> u32 {
+    match dice::roll(rng, 1, 20) {
+        1..=4 => 10,
+        5..=9 => 50,
+        10..=15 => 100,
+        16..=19 => 500,
+        _ => MAX_GEM_VALUE,
+    }
+}
+
+pub fn roll_jewellery_value(rng: &mut impl Rng) -> u32 {
+    dice::roll(rng, 3, 6) as u32 * 100
+}
+
+// Categories the kind doesn't allow are rerolled
+pub fn roll_magic_item(kind: TreasureKind, rng: &mut impl Rng) -> String {
+    let category = loop {
+        let category = MagicCategory::from_roll(rng.gen_range(1..=100));
+        if kind.accepts(category) {
+            break category;
+        }
+    };
+    let items = category.items();
+    items[rng.gen_range(0..items.len())].to_string()
+}
+
+pub fn roll_hoard(table: &[TreasureRoll], rng: &mut impl Rng) -> Result<Hoard, DiceParseError> {
+    let mut hoard = Hoard::default();
+    for line in table {
+        let (dice, multiplier) = parse_amount(&line.amount)?;
+        if rng.gen_range(1..=100) > line.chance {
+            continue;
+        }
+        let amount = dice.roll(rng).max(0) as u32 * multiplier;
+
+        match line.kind {
+            kind if kind.is_coin() => hoard.add_coins(kind, amount),
+            TreasureKind::Gems => hoard.gems.extend((0..amount).map(|_| roll_gem_value(rng))),
+            TreasureKind::Jewellery => hoard.jewellery.extend((0..amount).map(|_| roll_jewellery_value(rng))),
+            kind => hoard.magic_items.extend((0..amount).map(|_| roll_magic_item(kind, rng))),
+        }
+        for extra in &line.plus {
+            hoard.magic_items.push(roll_magic_item(*extra, rng));
+        }
+    }
+    Ok(hoard)
+}
+
+// What the monsters of a won fight leave behind, the coins in gold pieces and the rest
+// by name as a room's treasure has them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spoils {
+    pub gold: u32,
+    pub items: Vec<String>,
+}
+
+// Each monster with an individual treasure type (P-T) carries its own; every other type
+// among them is the group's hoard, rolled once however many there were. A monster
+// without a treasure type leaves each thing on its loot table half the time, "Gold
+// Coins" (or silver, or copper) being 3d6 of them. `table_for` looks up a treasure
+// type's table; types it doesn't know leave nothing.
+pub fn roll_spoils(
+    defeated: &[EnemyData],
+    table_for: impl Fn(&str) -> Option<Vec<TreasureRoll>>,
+    rng: &mut impl Rng,
+) -> Result<Spoils, DiceParseError> {
+    let mut spoils = Spoils::default();
+    let mut hoards: Vec<String> = Vec::new();
+    for monster in defeated {
+        match monster.treasure_type.as_deref().map(str::to_uppercase) {
+            Some(letter) if INDIVIDUAL_TYPES.contains(letter.as_str()) => {
+                if let Some(table) = table_for(&letter) {
+                    spoils.add_hoard(roll_hoard(&table, rng)?);
+                }
+            }
+            Some(letter) => {
+                if !hoards.contains(&letter) {
+                    hoards.push(letter);
+                }
+            }
+            None => {
+                for thing in &monster.loot_table {
+                    if rng.gen_bool(0.5) {
+                        spoils.add_loot(thing, rng)?;
+                    }
+                }
+            }
+        }
+    }
+    for letter in &hoards {
+        if let Some(table) = table_for(letter) {
+            spoils.add_hoard(roll_hoard(&table, rng)?);
+        }
+    }
+    Ok(spoils)
+}
+
+impl Spoils {
+    pub fn is_empty(&self) -> bool {
+        self.gold == 0 && self.items.is_empty()
+    }
+
+    fn add_hoard(&mut self, hoard: Hoard) {
+        let treasure = hoard.into_treasure(0);
+        self.gold += treasure.gold;
+        self.items.extend(treasure.items);
+    }
+
+    fn add_loot(&mut self, thing: &str, rng: &mut impl Rng) -> Result<(), DiceParseError> {
+        let lower = thing.to_lowercase();
+        if !lower.ends_with("coins") {
+            self.items.push(thing.to_string());
+            return Ok(());
+        }
+        let kinds = [
+            ("copper", TreasureKind::Copper),
+            ("silver", TreasureKind::Silver),
+            ("electrum", TreasureKind::Electrum),
+            ("platinum", TreasureKind::Platinum),
+        ];
+        let kind = kinds.into_iter().find(|(word, _)| lower.contains(word));
+        let kind = kind.map_or(TreasureKind::Gold, |(_, kind)| kind);
+        let coins = LOOT_TABLE_COINS.parse::<DiceExpr>()?.roll(rng).max(0) as u32;
+        self.gold += kind.gold_value(coins).unwrap_or(0);
+        Ok(())
+    }
+}
+
+// The most a table can hold in gold pieces, counting every gem and piece of jewellery
+// at the top of its sub-table. Lines that don't parse count for nothing.
+pub fn max_value(table: &[TreasureRoll]) -> u32 {
+    table
+        .iter()
+        .filter_map(|line| {
+            let (dice, multiplier) = parse_amount(&line.amount).ok()?;
+            let most = dice.max().max(0) as u32 * multiplier;
+            match line.kind {
+                TreasureKind::Gems => Some(most * MAX_GEM_VALUE),
+                TreasureKind::Jewellery => Some(most * MAX_JEWELLERY_VALUE),
+                kind => kind.gold_value(most),
+            }
+        })
+        .sum()
+}
+
+// The most separate items a table can yield: gems, jewellery and magic items
+pub fn max_items(table: &[TreasureRoll]) -> u32 {
+    table
+        .iter()
+        .filter(|line| !line.kind.is_coin())
+        .filter_map(|line| {
+            let (dice, multiplier) = parse_amount(&line.amount).ok()?;
+            Some(dice.max().max(0) as u32 * multiplier + line.plus.len() as u32)
+        })
+        .sum()
+}
+
+fn line(kind: TreasureKind, chance: u8, amount: &str) -> TreasureRoll {
+    line_plus(kind, chance, amount, &[])
+}
+
+fn line_plus(kind: TreasureKind, chance: u8, amount: &str, plus: &[TreasureKind]) -> TreasureRoll {
+    TreasureRoll {
+        kind,
+        chance,
+        amount: amount.to_string(),
+        plus: plus.to_vec(),
+    }
+}
+
+// The B/X treasure types, for when no table data is loaded
+pub fn builtin_table(treasure_type: &str) -> Option<Vec<TreasureRoll>> {
+    use TreasureKind::*;
+
+    let table = match treasure_type.to_uppercase().as_str() {
+        "A" => vec![
+            line(Copper, 25, "1d6*1000"),
+            line(Silver, 30, "1d6*1000"),
+            line(Electrum, 20, "1d4*1000"),
+            line(Gold, 35, "2d6*1000"),
+            line(Platinum, 25, "1d2*1000"),
+            line(Gems, 50, "6d6"),
+            line(Jewellery, 50, "6d6"),
+            line(MagicItem, 30, "3"),
+        ],
+        "B" => vec![
+            line(Copper, 50, "1d8*1000"),
+            line(Silver, 25, "1d6*1000"),
+            line(Electrum, 25, "1d4*1000"),
+            line(Gold, 25, "1d3*1000"),
+            line(Gems, 25, "1d6"),
+            line(Jewellery, 25, "1d6"),
+            line(MagicWeapon, 10, "1"),
+        ],
+        "C" => vec![
+            line(Copper, 20, "1d12*1000"),
+            line(Silver, 30, "1d4*1000"),
+            line(Electrum, 10, "1d4*1000"),
+            line(Gems, 25, "1d4"),
+            line(Jewellery, 25, "1d4"),
+            line(MagicItem, 10, "2"),
+        ],
+        "D" => vec![
+            line(Copper, 10, "1d8*1000"),
+            line(Silver, 15, "1d12*1000"),
+            line(Gold, 60, "1d6*1000"),
+            line(Gems, 30, "1d8"),
+            line(Jewellery, 30, "1d8"),
+            line_plus(MagicItem, 15, "2", &[Potion]),
+        ],
+        "E" => vec![
+            line(Copper, 5, "1d10*1000"),
+            line(Silver, 30, "1d12*1000"),
+            line(Electrum, 25, "1d4*1000"),
+            line(Gold, 25, "1d8*1000"),
+            line(Gems, 10, "1d10"),
+            line(Jewellery, 10, "1d10"),
+            line_plus(MagicItem, 25, "3", &[Scroll]),
+        ],
+        "F" => vec![
+            line(Silver, 10, "2d10*1000"),
+            line(Electrum, 20, "1d8*1000"),
+            line(Gold, 45, "1d12*1000"),
+            line(Platinum, 30, "1d3*1000"),
+            line(Gems, 20, "2d12"),
+            line(Jewellery, 10, "1d12"),
+            line_plus(MagicNotWeapon, 30, "3", &[Potion, Scroll]),
+        ],
+        "G" => vec![
+            line(Gold, 50, "10d4*1000"),
+            line(Platinum, 50, "1d6*1000"),
+            line(Gems, 25, "3d6"),
+            line(Jewellery, 25, "1d10"),
+            line_plus(MagicItem, 35, "4", &[Scroll]),
+        ],
+        "H" => vec![
+            line(Copper, 25, "3d8*1000"),
+            line(Silver, 50, "1d100*1000"),
+            line(Electrum, 50, "10d4*1000"),
+            line(Gold, 50, "10d6*1000"),
+            line(Platinum, 25, "5d4*1000"),
+            line(Gems, 50, "1d100"),
+            line(Jewellery, 50, "10d4"),
+            line_plus(MagicItem, 15, "4", &[Potion, Scroll]),
+        ],
+        "I" => vec![
+            line(Platinum, 30, "1d8*1000"),
+            line(Gems, 50, "2d6"),
+            line(Jewellery, 50, "2d6"),
+            line(MagicItem, 15, "1"),
+        ],
+        "J" => vec![line(Copper, 25, "1d4*1000"), line(Silver, 10, "1d3*1000")],
+        "K" => vec![line(Silver, 30, "1d6*1000"), line(Electrum, 10, "1d2*1000")],
+        "L" => vec![line(Gems, 50, "1d4")],
+        "M" => vec![line(Gold, 40, "2d4*1000"), line(Platinum, 50, "5d6*1000")],
+        "N" => vec![line(Potion, 40, "2d4")],
+        "O" => vec![line(Scroll, 50, "1d4")],
+        "P" => vec![line(Copper, 100, "3d8")],
+        "Q" => vec![line(Silver, 100, "3d6")],
+        "R" => vec![line(Electrum, 100, "2d6")],
+        "S" => vec![line(Gold, 100, "2d4")],
+        "T" => vec![line(Platinum, 100, "1d6")],
+        "U" => vec![
+            line(Copper, 10, "1d100"),
+            line(Silver, 10, "1d100"),
+            line(Gold, 5, "1d100"),
+            line(Gems, 5, "1d4"),
+            line(Jewellery, 5, "1d4"),
+            line(MagicItem, 2, "1"),
+        ],
+        "V" => vec![
+            line(Silver, 10, "1d100"),
+            line(Electrum, 5, "1d100"),
+            line(Gold, 10, "1d100"),
+            line(Platinum, 5, "1d100"),
+            line(Gems, 10, "1d4"),
+            line(Jewellery, 10, "1d4"),
+            line(MagicItem, 5, "1"),
+        ],
+        _ => return None,
+    };
+    Some(table)
+}
+
+// The type a room's treasure is held to: the dungeon's hoards sit in treasuries and
+// boss lairs, anywhere else has at most a small hoard
+pub fn room_treasure_type(room_type: &RoomType) -> &'static str {
+    match room_type {
+        RoomType::Treasury | RoomType::Boss => "A",
+        _ => "U",
+    }
+}
+
+// Trims a treasure to what `table` could possibly hold. Returns what was changed.
+pub fn validate_treasure(treasure: &mut TreasureData, table: &[TreasureRoll]) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    let most_gold = max_value(table);
+    if treasure.gold > most_gold {
+        changes.push(format!("room {}: {} gp cut to {}", treasure.room_id, treasure.gold, most_gold));
+        treasure.gold = most_gold;
+    }
+    let most_items = max_items(table) as usize;
+    if treasure.items.len() > most_items {
+        changes.push(format!(
+            "room {}: {} items cut to {}",
+            treasure.room_id,
+            treasure.items.len(),
+            most_items
+        ));
+        treasure.items.truncate(most_items);
+    }
+    changes
+}
+
+// Checks the treasure the AI service placed against each room's treasure type and
+// rolls a hoard for any treasury or boss room it left without one. `table_for` looks up
+// a treasure type's table. Returns a line for everything changed.
+pub fn reconcile_dungeon_treasure(
+    dungeon: &mut DungeonData,
+    table_for: impl Fn(&str) -> Option<Vec<TreasureRoll>>,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for room in &dungeon.rooms {
+        let Some(table) = table_for(room_treasure_type(&room.room_type)) else {
+            continue;
+        };
+        let mut stocked = false;
+        for treasure in dungeon.treasures.iter_mut().filter(|treasure| treasure.room_id == room.id) {
+            changes.extend(validate_treasure(treasure, &table));
+            stocked = true;
+        }
+
+        if !stocked && matches!(room.room_type, RoomType::Treasury | RoomType::Boss) {
+            match roll_hoard(&table, rng) {
+                Ok(hoard) if !hoard.is_empty() => {
+                    changes.push(format!("room {}: stocked with {}", room.id, hoard.describe()));
+                    dungeon.treasures.push(hoard.into_treasure(room.id));
+                }
+                Ok(_) => {}
+                Err(e) => changes.push(format!("room {}: {}", room.id, e)),
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // The most of `kind` a treasure type can hold
+    fn most(treasure_type: &str, kind: TreasureKind) -> u32 {
+        let table = builtin_table(treasure_type).unwrap();
+        let line = table.iter().find(|line| line.kind == kind).unwrap();
+        let (dice, multiplier) = parse_amount(&line.amount).unwrap();
+        dice.max() as u32 * multiplier
+    }
+
+    #[test]
+    fn type_h_platinum_is_5d4_thousand() {
+        assert_eq!(most("H", TreasureKind::Platinum), 20000);
+    }
+
+    #[test]
+    fn every_type_from_a_to_v_is_built_in() {
+        for letter in 'A'..='V' {
+            let table = builtin_table(&letter.to_string()).unwrap();
+            assert!(table.iter().all(|line| parse_amount(&line.amount).is_ok()), "type {}", letter);
+        }
+        assert!(builtin_table("W").is_none());
+        assert_eq!(builtin_table("a").unwrap().len(), builtin_table("A").unwrap().len());
+    }
+
+    #[test]
+    fn lair_types_hold_what_the_book_says() {
+        assert_eq!(most("A", TreasureKind::Gold), 12000);
+        assert_eq!(most("A", TreasureKind::Platinum), 2000);
+        assert_eq!(most("G", TreasureKind::Gold), 40000);
+        assert_eq!(most("H", TreasureKind::Silver), 100000);
+        assert_eq!(most("M", TreasureKind::Platinum), 30000);
+        assert_eq!(most("U", TreasureKind::Gold), 100);
+    }
+
+    #[test]
+    fn individual_treasure_is_always_there() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let hoard = roll_hoard(&builtin_table("P").unwrap(), &mut rng).unwrap();
+            assert!((3..=24).contains(&hoard.copper));
+        }
+    }
+
+    #[test]
+    fn coins_are_worth_their_rate_in_gold() {
+        assert_eq!(TreasureKind::Copper.gold_value(250), Some(2));
+        assert_eq!(TreasureKind::Silver.gold_value(50), Some(5));
+        assert_eq!(TreasureKind::Electrum.gold_value(10), Some(5));
+        assert_eq!(TreasureKind::Platinum.gold_value(3), Some(15));
+        assert_eq!(TreasureKind::Gems.gold_value(3), None);
+    }
+
+    #[test]
+    fn magic_items_keep_to_their_kind() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let potions = MagicCategory::Potion.items();
+        for _ in 0..50 {
+            assert!(potions.contains(&roll_magic_item(TreasureKind::Potion, &mut rng).as_str()));
+        }
+        assert_eq!(MagicCategory::from_roll(25), MagicCategory::Sword);
+        assert_eq!(MagicCategory::from_roll(26), MagicCategory::Armour);
+        assert_eq!(MagicCategory::from_roll(100), MagicCategory::Miscellaneous);
+    }
+}
@@ -0,0 +1,18 @@
+use rand::Rng;
+
+use crate::dice;
+
+// Wandering monsters. Every second turn in a dungeon the referee rolls a d6 and on a 1
+// something comes along. Noise the party makes (bashing doors, mostly) adds to the
+// chance of the next roll, up to 3 in 6, and the roll clears it.
+
+pub const WANDERING_CHECK_TURNS: u64 = 2;
+const MAX_CHANCE_IN_SIX: u8 = 3;
+
+pub fn wandering_chance(noise: u8) -> u8 {
+    (1 + noise).min(MAX_CHANCE_IN_SIX)
+}
+
+pub fn roll_wandering(noise: u8, rng: &mut impl Rng) -> bool {
+    dice::d6(rng) <= wandering_chance(noise)
+}
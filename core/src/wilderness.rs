@@ -0,0 +1,160 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::{attempt_flight, FleeOutcome};
+use crate::dice::{self, DiceExpr};
+use crate::encounter::{roll_surprise, Side};
+
+// Travelling overland. Each day on the road the referee rolls a d6 for an encounter,
+// and the terrain sets the odds: 1 in 6 on clear ground, 2 in 6 in woods, hills, desert
+// or along a river, 3 in 6 in swamp or mountains. What turns up comes off the terrain's
+// table: a monster and how many of it. The two sides sight each other 4d6 x 10 yards
+// apart, or 1d4 x 10 if either is surprised, and a party that wants to can try to get
+// away before the monsters close, which is a matter of who is faster. Rough going
+// slows a party down: a day's travel is a fifth of the party's exploring pace in miles
+// on clear ground (24 miles at 120'), two thirds of that in woods, hills or desert and
+// half in swamp or mountains.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Terrain {
+    Clear,
+    Woods,
+    River,
+    Hills,
+    Mountains,
+    Swamp,
+    Desert,
+}
+
+// One line of a wilderness table: `number` of the monster `monster` (by bestiary id)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WildernessEntry {
+    pub monster: String,
+    pub number: String,
+}
+
+// A wilderness encounter as the two sides first see each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sighting {
+    pub monster: String,
+    pub count: u32,
+    pub distance_yards: u32,
+    // The side caught unawares; None if neither or both were
+    pub surprised: Option<Side>,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 7] = [
+        Terrain::Clear,
+        Terrain::Woods,
+        Terrain::River,
+        Terrain::Hills,
+        Terrain::Mountains,
+        Terrain::Swamp,
+        Terrain::Desert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Terrain::Clear => "clear",
+            Terrain::Woods => "woods",
+            Terrain::River => "river",
+            Terrain::Hills => "hills",
+            Terrain::Mountains => "mountains",
+            Terrain::Swamp => "swamp",
+            Terrain::Desert => "desert",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Terrain> {
+        Terrain::ALL.into_iter().find(|terrain| terrain.name() == name.to_lowercase())
+    }
+
+    // In 6, rolled once for each day of travel
+    pub fn encounter_chance(&self) -> u8 {
+        match self {
+            Terrain::Clear => 1,
+            Terrain::Woods | Terrain::River | Terrain::Hills | Terrain::Desert => 2,
+            Terrain::Mountains | Terrain::Swamp => 3,
+        }
+    }
+
+    // A day's travel at an exploring pace of `movement_rate` per round
+    pub fn miles_per_day(&self, movement_rate: u16) -> u32 {
+        let clear = movement_rate as u32 * 3 / 5;
+        match self {
+            Terrain::Clear | Terrain::River => clear,
+            Terrain::Woods | Terrain::Hills | Terrain::Desert => clear * 2 / 3,
+            Terrain::Mountains | Terrain::Swamp => clear / 2,
+        }
+    }
+}
+
+pub fn roll_wilderness_encounter(terrain: Terrain, rng: &mut impl Rng) -> bool {
+    dice::d6(rng) <= terrain.encounter_chance()
+}
+
+// Rolls what turns up on `table`, or None for an empty table
+pub fn roll_sighting(table: &[WildernessEntry], rng: &mut impl Rng) -> Option<Sighting> {
+    let entry = table.choose(rng)?;
+    let count = entry.number.parse::<DiceExpr>().map_or(1, |number| number.roll(rng).max(1) as u32);
+    let surprised = match (roll_surprise(rng), roll_surprise(rng)) {
+        (true, false) => Some(Side::Party),
+        (false, true) => Some(Side::Enemies),
+        _ => None,
+    };
+    Some(Sighting {
+        monster: entry.monster.clone(),
+        count,
+        distance_yards: encounter_distance(surprised.is_some(), rng),
+        surprised,
+    })
+}
+
+pub fn encounter_distance(surprise: bool, rng: &mut impl Rng) -> u32 {
+    let dice = if surprise { dice::roll(rng, 1, 4) } else { dice::roll(rng, 4, 6) };
+    dice as u32 * 10
+}
+
+// Monsters caught unawares can't stop a party slipping away, and a party caught unawares
+// can't get away at all; otherwise it goes as fleeing a fight does, on pace
+pub fn attempt_evasion(surprised: Option<Side>, party_rate: u16, monster_rate: u16, rng: &mut impl Rng) -> bool {
+    match surprised {
+        Some(Side::Enemies) => party_rate > 0,
+        Some(Side::Party) => false,
+        None => attempt_flight(party_rate, monster_rate, false, rng) == FleeOutcome::Escaped,
+    }
+}
+
+fn entry(monster: &str, number: &str) -> WildernessEntry {
+    WildernessEntry {
+        monster: monster.to_string(),
+        number: number.to_string(),
+    }
+}
+
+// Tables for when the data files haven't loaded, from the built-in monsters alone
+pub fn builtin_wilderness_table(terrain: Terrain) -> Vec<WildernessEntry> {
+    match terrain {
+        Terrain::Clear | Terrain::River => vec![entry("goblin", "2d4"), entry("orc", "1d6")],
+        Terrain::Woods => vec![entry("goblin", "2d4"), entry("orc", "1d6"), entry("troll", "1")],
+        Terrain::Hills | Terrain::Mountains => vec![entry("orc", "2d4"), entry("troll", "1d2")],
+        Terrain::Swamp | Terrain::Desert => vec![entry("skeleton", "2d4"), entry("troll", "1")],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encounter_chances_follow_the_book() {
+        assert_eq!(Terrain::Clear.encounter_chance(), 1);
+        assert_eq!(Terrain::Desert.encounter_chance(), 2);
+        assert_eq!(Terrain::Woods.encounter_chance(), 2);
+        assert_eq!(Terrain::Swamp.encounter_chance(), 3);
+        assert_eq!(Terrain::Mountains.encounter_chance(), 3);
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::battle_map::GridPosition;
+use crate::doors::DoorState;
+use crate::dungeon::DungeonData;
+use crate::dungeon_map::DungeonMap;
+use crate::fog::FogOfWar;
+use crate::traps::TrapState;
+
+// Every dungeon the party has been to, by the name of the site it stands at. A dungeon
+// is only generated the first time the party goes there; after that it comes back from
+// here as the party left it: doors opened, traps gone off, treasure taken and monsters
+// fought, with the party's map of it. Sites the party has only heard about in rumors
+// are kept too, with what the rumor said lies there.
+
+// What the party has changed in a dungeon. The map itself is laid out again from the
+// dungeon data, so only the doors and traps that have moved on from how the layout
+// starts them are kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DungeonState {
+    #[serde(default)]
+    pub doors: Vec<(GridPosition, DoorState)>,
+    #[serde(default)]
+    pub traps: Vec<(GridPosition, TrapState)>,
+    // Indices into the dungeon's encounters and treasures
+    #[serde(default)]
+    pub met_encounters: Vec<usize>,
+    #[serde(default)]
+    pub looted_treasure: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DungeonRecord {
+    pub dungeon: DungeonData,
+    #[serde(default)]
+    pub state: DungeonState,
+    #[serde(default)]
+    pub fog: FogOfWar,
+}
+
+// A dungeon the party has heard of and not yet been to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SiteHook {
+    pub theme: String,
+    pub level: u8,
+    pub rumor: String,
+}
+
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldDatabase {
+    pub dungeons: BTreeMap<String, DungeonRecord>,
+    #[serde(default)]
+    pub hooks: BTreeMap<String, SiteHook>,
+}
+
+impl DungeonState {
+    pub fn capture(map: &DungeonMap, met_encounters: &[usize], looted_treasure: &[usize]) -> Self {
+        let fresh = |door_locked: bool| if door_locked { DoorState::Locked } else { DoorState::Closed };
+        Self {
+            doors: map
+                .doors
+                .iter()
+                .filter(|door| door.state != fresh(door.is_locked))
+                .map(|door| (door.position, door.state))
+                .collect(),
+            traps: map
+                .traps
+                .iter()
+                .filter(|trap| trap.state != TrapState::Hidden)
+                .map(|trap| (trap.position, trap.state))
+                .collect(),
+            met_encounters: met_encounters.to_vec(),
+            looted_treasure: looted_treasure.to_vec(),
+        }
+    }
+
+    // Puts a freshly laid out map back the way the party left it
+    pub fn apply(&self, map: &mut DungeonMap) {
+        for (position, state) in &self.doors {
+            if let Some(door) = map.door_at_mut(position) {
+                door.state = *state;
+            }
+        }
+        for (position, state) in &self.traps {
+            if let Some(trap) = map.trap_at_mut(position) {
+                trap.state = *state;
+            }
+        }
+    }
+}
+
+impl WorldDatabase {
+    pub fn get(&self, site: &str) -> Option<&DungeonRecord> {
+        self.dungeons.get(site)
+    }
+
+    pub fn store(&mut self, site: String, record: DungeonRecord) {
+        self.hooks.remove(&site);
+        self.dungeons.insert(site, record);
+    }
+
+    pub fn hook(&self, site: &str) -> Option<&SiteHook> {
+        self.hooks.get(site)
+    }
+
+    // Only news if the party hasn't been there; returns whether it was
+    pub fn add_hook(&mut self, site: String, hook: SiteHook) -> bool {
+        if self.dungeons.contains_key(&site) {
+            return false;
+        }
+        self.hooks.insert(site, hook);
+        true
+    }
+}
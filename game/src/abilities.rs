@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::combat::{process_damage_events, CastSpellEvent, CharacterFell, CombatLogEvent, Combatant, DamageEvent};
+use crate::rng::GameRng;
+use crate::GameState;
+use old_school_core::spells::{SpellCompendium, SpellTarget};
+
+pub use old_school_core::abilities::{AbilityOutcome, SpecialAbility, BREATHS_PER_FIGHT};
+
+// Monsters' special abilities in a fight (the rules are in old_school_core::abilities).
+// The enemy AI uses a breath weapon or a spell-like ability on its turn in place of an
+// attack, and a blow that lands passes on paralysis, petrification or a swallowing; both
+// come here as a `UseAbility` once the blow's damage is done. Whoever is swallowed is
+// freed when the swallower falls or the fight ends.
+
+// `user` turning `ability` on `target`; a breath weapon catches the whole party whoever
+// the target is
+#[derive(Event, Clone, Debug)]
+pub struct UseAbility {
+    pub user: Entity,
+    pub target: Entity,
+    pub ability: SpecialAbility,
+}
+
+// Times a monster has breathed this fight
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct BreathsTaken(pub u8);
+
+// A party member in a monster's belly
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SwallowedBy(pub Entity);
+
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<UseAbility>()
+            .add_systems(Update, (use_abilities, free_the_swallowed).chain().after(process_damage_events))
+            .add_systems(OnExit(GameState::Combat), free_everyone_swallowed);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn use_abilities(
+    mut commands: Commands,
+    mut uses: EventReader<UseAbility>,
+    mut characters: Query<(Entity, &mut Character, &mut Combatant, Option<&BreathsTaken>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut cast_events: EventWriter<CastSpellEvent>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut fallen: EventWriter<CharacterFell>,
+    compendium: Res<SpellCompendium>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in uses.read() {
+        let Ok((_, user, _, breaths)) = characters.get(event.user) else {
+            continue;
+        };
+        let (user, breaths) = (user.clone(), breaths.map_or(0, |breaths| breaths.0));
+        if !user.is_alive() {
+            continue;
+        }
+        let party = || {
+            characters.iter().filter(|(_, character, combatant, _)| combatant.is_player && character.is_alive())
+        };
+
+        match &event.ability {
+            SpecialAbility::Breath(kind) => {
+                let kind_name = format!("{:?}", kind).to_lowercase();
+                log_events.send(CombatLogEvent(format!("{} breathes {}!", user.name, kind_name)));
+                for (entity, victim, _, _) in party() {
+                    let (damage, save) = victim.breathed_on(&user, rng.as_mut());
+                    let text = if save.success {
+                        format!("{} dodges the worst of it and takes {} damage", victim.name, damage)
+                    } else {
+                        format!("{} takes {} damage", victim.name, damage)
+                    };
+                    log_events.send(CombatLogEvent(text));
+                    damage_events.send(DamageEvent {
+                        target: entity,
+                        damage,
+                        damage_type: kind.clone(),
+                        critical: false,
+                        source: Some(user.name.clone()),
+                        material: None,
+                    });
+                }
+                commands.entity(event.user).insert(BreathsTaken(breaths + 1));
+            }
+            // As a player's spell, but the party are its enemies
+            SpecialAbility::SpellLike(spell) => {
+                let targets = match compendium.descriptor(spell).map(|descriptor| descriptor.target) {
+                    Some(SpellTarget::AllEnemies) => party().map(|(entity, _, _, _)| entity).collect(),
+                    Some(SpellTarget::Enemy) => vec![event.target],
+                    _ => vec![event.user],
+                };
+                cast_events.send(CastSpellEvent { caster: event.user, spell: spell.clone(), targets });
+            }
+            ability @ (SpecialAbility::Paralysis | SpecialAbility::Petrification | SpecialAbility::SwallowWhole) => {
+                let Ok((_, mut victim, mut combatant, _)) = characters.get_mut(event.target) else {
+                    continue;
+                };
+                let swallowed = combatant.status_effects.iter().any(|effect| effect.name == "Swallowed");
+                if !victim.is_alive() || swallowed {
+                    continue;
+                }
+                let text = match victim.suffer_ability(ability, &user.name, rng.as_mut()) {
+                    AbilityOutcome::Resisted(save) => {
+                        let (name, monster) = (&victim.name, &user.name);
+                        format!("{} holds out against the {} ({} vs {})", name, monster, save.roll, save.target)
+                    }
+                    AbilityOutcome::Immune => format!("{} is untouched by the {}'s paralysis", victim.name, user.name),
+                    AbilityOutcome::Held(effect) => {
+                        let rounds = effect.duration;
+                        combatant.status_effects.retain(|existing| existing.name != effect.name);
+                        combatant.status_effects.push(effect);
+                        format!("{} is paralyzed for {} rounds", victim.name, rounds)
+                    }
+                    AbilityOutcome::Petrified(save) => {
+                        fallen.send(CharacterFell {
+                            entity: event.target,
+                            name: victim.name.clone(),
+                            is_player: combatant.is_player,
+                            cause: "turned to stone".to_string(),
+                            killer: Some(user.name.clone()),
+                        });
+                        format!("{} is turned to stone ({} vs {})", victim.name, save.roll, save.target)
+                    }
+                    AbilityOutcome::Swallowed(effects) => {
+                        combatant.status_effects.extend(effects);
+                        commands.entity(event.target).insert(SwallowedBy(event.user));
+                        format!("The {} swallows {} whole!", user.name, victim.name)
+                    }
+                };
+                info!("{}", text);
+                log_events.send(CombatLogEvent(text));
+            }
+            // Regeneration, poison and disease see to themselves
+            _ => {}
+        }
+    }
+}
+
+fn free_the_swallowed(
+    mut commands: Commands,
+    mut fallen: EventReader<CharacterFell>,
+    mut swallowed: Query<(Entity, &SwallowedBy, &Character, &mut Combatant)>,
+    mut log_events: EventWriter<CombatLogEvent>,
+) {
+    for fell in fallen.read() {
+        for (entity, swallower, character, mut combatant) in swallowed.iter_mut() {
+            if swallower.0 == fell.entity {
+                release(&mut commands, entity, &mut combatant);
+                if character.is_alive() {
+                    log_events.send(CombatLogEvent(format!("{} is cut free of the {}", character.name, fell.name)));
+                }
+            }
+        }
+    }
+}
+
+fn free_everyone_swallowed(mut commands: Commands, mut swallowed: Query<(Entity, &mut Combatant), With<SwallowedBy>>) {
+    for (entity, mut combatant) in swallowed.iter_mut() {
+        release(&mut commands, entity, &mut combatant);
+    }
+}
+
+fn release(commands: &mut Commands, entity: Entity, combatant: &mut Combatant) {
+    combatant.status_effects.retain(|effect| effect.name != "Swallowed" && effect.name != "Digestion");
+    commands.entity(entity).remove::<SwallowedBy>();
+}
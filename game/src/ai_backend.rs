@@ -0,0 +1,332 @@
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::ai_client::{AiClientError, ConversationResponse, NarrationMoment, PartialReply};
+use crate::data::PromptLibrary;
+use crate::narration::template;
+
+// Where AIClient's requests go. A backend takes a request for one of the service's
+// endpoints ("conversation", "generate_dungeon", "rumor" and so on) as JSON, and answers
+// with the JSON the Python service would have. Timeouts, retries and replay transcripts
+// are AIClient's business, so a backend only has to make one attempt.
+//
+// - The Python service in ai_service/ (the default), over HTTP.
+// - Any OpenAI-compatible chat completions API, asked to answer each endpoint in the
+//   service's format by the prompts under assets/prompts (see data.rs). The key comes
+//   from OPENAI_API_KEY.
+// - A mock that answers everything at once from canned replies, for playing without a
+//   service and for tests. AIClient also falls back on it while the backend it was
+//   given is down (see ai_health.rs).
+
+pub trait AiBackend: Send + Sync {
+    // The answer to a request at `endpoint`, as JSON text
+    fn request<'a>(&'a self, endpoint: &'a str, body: &'a Value) -> BoxedFuture<'a, Result<String, AiClientError>>;
+
+    // As request for "conversation", with the NPC's reply pushed onto `reply` as it's
+    // written. A backend that can't stream hands over the whole reply at once.
+    fn converse_streaming<'a>(
+        &'a self,
+        body: &'a Value,
+        reply: PartialReply,
+    ) -> BoxedFuture<'a, Result<String, AiClientError>> {
+        Box::pin(async move {
+            let text = self.request("conversation", body).await?;
+            let response: ConversationResponse = serde_json::from_str(&text)?;
+            reply.push(&response.npc_response);
+            Ok(text)
+        })
+    }
+
+    // Whether the backend is there to answer at all
+    fn health(&self) -> BoxedFuture<'_, Result<(), AiClientError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// Which backend to use, from GameConfig
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AiBackendKind {
+    // The Python service, at GameConfig::ai_service_url
+    #[default]
+    Service,
+    // An OpenAI-compatible API at `base_url`, e.g. "https://api.openai.com/v1"
+    OpenAi { base_url: String, model: String },
+    Mock,
+}
+
+pub const DEFAULT_OPENAI_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+
+impl AiBackendKind {
+    // "service", "openai" or "mock", as on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "service" => Some(AiBackendKind::Service),
+            "openai" => Some(AiBackendKind::OpenAi {
+                base_url: DEFAULT_OPENAI_URL.to_string(),
+                model: DEFAULT_OPENAI_MODEL.to_string(),
+            }),
+            "mock" => Some(AiBackendKind::Mock),
+            _ => None,
+        }
+    }
+
+    pub fn build(&self, service_url: &str, prompts: PromptLibrary) -> Arc<dyn AiBackend> {
+        match self {
+            AiBackendKind::Service => Arc::new(HttpBackend::new(service_url.to_string())),
+            AiBackendKind::OpenAi { base_url, model } => Arc::new(OpenAiBackend::new(
+                base_url.clone(),
+                model.clone(),
+                std::env::var("OPENAI_API_KEY").ok(),
+                prompts,
+            )),
+            AiBackendKind::Mock => Arc::new(MockBackend),
+        }
+    }
+}
+
+// Turns an error status into a service error
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, AiClientError> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(AiClientError::Service { status: status.as_u16(), message });
+    }
+    Ok(response)
+}
+
+pub struct HttpBackend {
+    client: Client,
+    base_url: String,
+}
+
+// One line of the service's streamed conversation: a piece of the reply, or the whole
+// response once it's done
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StreamedLine {
+    Chunk(String),
+    Response(Box<ConversationResponse>),
+}
+
+impl HttpBackend {
+    pub fn new(base_url: String) -> Self {
+        Self { client: Client::new(), base_url }
+    }
+
+    async fn send(&self, path: &str, body: &Value) -> Result<reqwest::Response, AiClientError> {
+        let response = self.client.post(format!("{}/{}", self.base_url, path)).json(body).send().await?;
+        check_status(response).await
+    }
+}
+
+impl AiBackend for HttpBackend {
+    fn request<'a>(&'a self, endpoint: &'a str, body: &'a Value) -> BoxedFuture<'a, Result<String, AiClientError>> {
+        Box::pin(async move { Ok(self.send(endpoint, body).await?.text().await?) })
+    }
+
+    fn health(&self) -> BoxedFuture<'_, Result<(), AiClientError>> {
+        Box::pin(async move {
+            let response = self.client.get(format!("{}/health", self.base_url)).send().await?;
+            let health: Value = check_status(response).await?.json().await?;
+            match health["status"].as_str() {
+                Some("healthy") => Ok(()),
+                status => Err(AiClientError::Service {
+                    status: 503,
+                    message: format!("the service says it is {}", status.unwrap_or("unwell")),
+                }),
+            }
+        })
+    }
+
+    // The service streams from conversation/stream; one without it answers all at once
+    fn converse_streaming<'a>(
+        &'a self,
+        body: &'a Value,
+        reply: PartialReply,
+    ) -> BoxedFuture<'a, Result<String, AiClientError>> {
+        Box::pin(async move {
+            let mut response = match self.send("conversation/stream", body).await {
+                Err(AiClientError::Service { status: 404, .. }) => {
+                    let text = self.request("conversation", body).await?;
+                    let response: ConversationResponse = serde_json::from_str(&text)?;
+                    reply.push(&response.npc_response);
+                    return Ok(text);
+                }
+                result => result?,
+            };
+
+            // Lines of JSON, each a chunk of the reply until the last, which is the response
+            let mut buffer = Vec::new();
+            while let Some(bytes) = response.chunk().await? {
+                buffer.extend_from_slice(&bytes);
+                while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    if line.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    match serde_json::from_slice::<StreamedLine>(&line)? {
+                        StreamedLine::Chunk(chunk) => reply.push(&chunk),
+                        StreamedLine::Response(done) => return Ok(serde_json::to_string(&done)?),
+                    }
+                }
+            }
+            Err(AiClientError::Network("the conversation stream ended before the response".to_string()))
+        })
+    }
+}
+
+pub struct OpenAiBackend {
+    client: Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    prompts: PromptLibrary,
+}
+
+#[derive(Deserialize)]
+struct Completion {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+impl OpenAiBackend {
+    pub fn new(base_url: String, model: String, api_key: Option<String>, prompts: PromptLibrary) -> Self {
+        Self { client: Client::new(), base_url, model, api_key, prompts }
+    }
+}
+
+impl AiBackend for OpenAiBackend {
+    fn request<'a>(&'a self, endpoint: &'a str, body: &'a Value) -> BoxedFuture<'a, Result<String, AiClientError>> {
+        Box::pin(async move {
+            let Some(prompt) = self.prompts.render(endpoint, body) else {
+                return Err(AiClientError::Service { status: 404, message: format!("no prompt for {}", endpoint) });
+            };
+            let mut messages = Vec::new();
+            if let Some(system) = self.prompts.render("system", body) {
+                messages.push(json!({ "role": "system", "content": system }));
+            }
+            messages.push(json!({ "role": "user", "content": prompt }));
+            let completion = json!({
+                "model": self.model,
+                "response_format": { "type": "json_object" },
+                "messages": messages,
+            });
+            let mut request = self.client.post(format!("{}/chat/completions", self.base_url)).json(&completion);
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let completion: Completion = check_status(request.send().await?).await?.json().await?;
+            let content = completion
+                .choices
+                .into_iter()
+                .next()
+                .map(|choice| choice.message.content)
+                .ok_or_else(|| AiClientError::Decode("the completion had no choices".to_string()))?;
+            // Some models fence their JSON even when asked not to
+            let content = content.trim().trim_start_matches("```json").trim_matches('`').trim();
+            Ok(content.to_string())
+        })
+    }
+
+    // Listing the models costs nothing and needs the key
+    fn health(&self) -> BoxedFuture<'_, Result<(), AiClientError>> {
+        Box::pin(async move {
+            let mut request = self.client.get(format!("{}/models", self.base_url));
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            check_status(request.send().await?).await?;
+            Ok(())
+        })
+    }
+}
+
+// Canned answers, built from the request where it helps
+pub struct MockBackend;
+
+impl MockBackend {
+    fn answer(endpoint: &str, body: &Value) -> Option<Value> {
+        let npc_name = body["npc_data"]["name"].as_str().unwrap_or("The stranger");
+        // By bestiary id, the rest filled in from the bestiary
+        let goblin = json!({ "id": "goblin" });
+        let answer = match endpoint {
+            "conversation" => json!({
+                "npc_response": format!("{} nods. \"Mind how you go out there.\"", npc_name),
+                "updated_npc_data": body["npc_data"],
+                "quest_offered": null,
+                "mood_change": null,
+            }),
+            "generate_dungeon" => json!({
+                "name": "The Mock Cellars",
+                "description": format!("A small {} beneath an old farmhouse.", body["theme"].as_str().unwrap_or("dungeon")),
+                "rooms": [
+                    {
+                        "id": 1, "name": "Cellar Steps", "description": "Damp steps lead down into the dark.",
+                        "room_type": "entrance", "contents": [],
+                        "exits": [{ "direction": "north", "destination_room": 2, "is_secret": false, "is_locked": false }],
+                    },
+                    {
+                        "id": 2, "name": "Root Cellar", "description": "Sacks of rotten turnips and a locked chest.",
+                        "room_type": "treasury", "contents": ["sacks", "chest"],
+                        "exits": [{ "direction": "south", "destination_room": 1, "is_secret": false, "is_locked": false }],
+                    },
+                ],
+                "encounters": [{ "room_id": 2, "enemies": [goblin], "difficulty": 1, "is_ambush": false }],
+                "treasures": [{ "room_id": 2, "items": [], "gold": 25, "is_hidden": false, "trap_difficulty": null }],
+                "connections": [{ "from_room": 1, "to_room": 2, "direction": "north" }],
+            }),
+            "generate_quest" => json!({
+                "title": "Goblins in the Cellar",
+                "description": format!("{} wants the goblins under the old farmhouse dealt with.", npc_name),
+                "objectives": ["Kill 1 goblin", "Return to town"],
+                "reward": { "experience": 50, "gold": 25, "items": [], "reputation_change": 1 },
+                "difficulty": 1,
+                "time_limit": null,
+            }),
+            "generate_encounter" => json!({
+                "location": body["location"],
+                "difficulty": body["difficulty"],
+                "enemies": [goblin],
+                "environment": [],
+                "is_ambush": false,
+            }),
+            "rumor" => json!({ "text": "They say goblins have been seen near the old farmhouse.", "truthful": true }),
+            "hirelings" => json!({
+                "candidates": [{ "name": "Hob", "kind": "man_at_arms", "class": "Fighter", "level": 1, "personality": "Steady" }],
+            }),
+            "recap" => json!({ "recap": "The company went adventuring, and lived to tell of it." }),
+            "narrate" => {
+                let moment = serde_json::from_value(body["moment"].clone()).unwrap_or(NarrationMoment::EnterRoom);
+                json!({ "text": template(moment, body["subject"].as_str().unwrap_or("the dark")) })
+            }
+            _ => return None,
+        };
+        Some(answer)
+    }
+}
+
+impl AiBackend for MockBackend {
+    fn request<'a>(&'a self, endpoint: &'a str, body: &'a Value) -> BoxedFuture<'a, Result<String, AiClientError>> {
+        Box::pin(async move {
+            debug!("Mock backend answering {}", endpoint);
+            match MockBackend::answer(endpoint, body) {
+                Some(answer) => Ok(answer.to_string()),
+                None => Err(AiClientError::Service { status: 404, message: format!("no mock answer for {}", endpoint) }),
+            }
+        })
+    }
+}
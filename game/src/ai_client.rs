@@ -1,25 +1,150 @@
 use bevy::prelude::*;
+use bevy::tasks::Task;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
+use bevy::utils::tracing::Instrument;
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use crate::ai_backend::{AiBackend, HttpBackend, MockBackend};
+use crate::ai_queue::{estimate_tokens, QueueStatus, RateLimits, RequestPriority, RequestQueue};
+use crate::character::Character;
+use crate::clock::GameClock;
+use crate::combat::Combatant;
+use crate::data::{Bestiary, PromptLibrary, TreasureTables};
+use crate::replay::AiTranscript;
+use crate::rng::GameRng;
+use crate::tasks::{poll_task, AsyncRuntime};
+use crate::GameConfig;
+use old_school_core::treasure::reconcile_dungeon_treasure;
 
-#[derive(Resource)]
+// Payloads shared with the rules crate
+pub use old_school_core::dungeon::*;
+pub use old_school_core::monster::{AttackData, EnemyData};
+pub use old_school_core::quest::*;
+pub use old_school_core::retainers::Candidate;
+
+// Requests to the AI, through whichever backend the game was configured with (see
+// ai_backend.rs), each waiting its turn in a queue shared by every clone (see
+// ai_queue.rs)
+#[derive(Resource, Clone)]
 pub struct AIClient {
-    client: Client,
-    base_url: String,
+    backend: Arc<dyn AiBackend>,
+    // Answers in the backend's place while it's down
+    fallback: Arc<dyn AiBackend>,
+    offline: Arc<AtomicBool>,
+    queue: Arc<RequestQueue>,
+    policy: RequestPolicy,
+    transcript: Option<AiTranscript>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// How long a request to the service may take, and how often one that fails for want of
+// an answer is tried again. Each retry waits twice as long as the last before going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    pub timeout: Duration,
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+// A health check gets less time than a request; the service has nothing to think about
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+// Why a request to the service came to nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AiClientError {
+    // The service couldn't be reached, or the connection dropped
+    Network(String),
+    // The service answered with something the game couldn't read
+    Decode(String),
+    // The service answered with an error status
+    Service { status: u16, message: String },
+    // No answer within the request policy's timeout
+    Timeout,
+}
+
+impl AiClientError {
+    // Worth trying again: the service may answer next time
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AiClientError::Network(_) | AiClientError::Timeout => true,
+            AiClientError::Service { status, .. } => *status >= 500,
+            AiClientError::Decode(_) => false,
+        }
+    }
+
+    // For the player, without the details
+    pub fn summary(&self) -> String {
+        match self {
+            AiClientError::Network(_) => "The AI service can't be reached".to_string(),
+            AiClientError::Decode(_) => "The AI service's answer made no sense".to_string(),
+            AiClientError::Service { status, .. } => format!("The AI service ran into trouble ({})", status),
+            AiClientError::Timeout => "The AI service took too long to answer".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AiClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiClientError::Network(message) => write!(f, "network error: {}", message),
+            AiClientError::Decode(message) => write!(f, "couldn't read the response: {}", message),
+            AiClientError::Service { status, message } => write!(f, "service error {}: {}", status, message),
+            AiClientError::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
+impl std::error::Error for AiClientError {}
+
+impl From<reqwest::Error> for AiClientError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            AiClientError::Timeout
+        } else if error.is_decode() {
+            AiClientError::Decode(error.to_string())
+        } else if let Some(status) = error.status() {
+            AiClientError::Service { status: status.as_u16(), message: error.to_string() }
+        } else {
+            AiClientError::Network(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AiClientError {
+    fn from(error: serde_json::Error) -> Self {
+        AiClientError::Decode(error.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NPCData {
     pub name: String,
     pub personality: String,
     pub background: String,
     pub current_mood: String,
+    #[serde(default)]
     pub memory: Vec<String>,
+    // How the NPC feels about each character who has spoken to them, by name
+    #[serde(default)]
     pub relationships: HashMap<String, Relationship>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Relationship {
     pub trust: i8, // -10 to 10
     pub familiarity: i8, // 0 to 10
@@ -34,7 +159,7 @@ pub struct ConversationRequest {
     pub context: ConversationContext,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationContext {
     pub location: String,
     pub time_of_day: String,
@@ -42,7 +167,7 @@ pub struct ConversationContext {
     pub player_reputation: i8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationResponse {
     pub npc_response: String,
     pub updated_npc_data: NPCData,
@@ -50,25 +175,7 @@ pub struct ConversationResponse {
     pub mood_change: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct QuestData {
-    pub title: String,
-    pub description: String,
-    pub objectives: Vec<String>,
-    pub reward: QuestReward,
-    pub difficulty: u8,
-    pub time_limit: Option<u32>, // in game days
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct QuestReward {
-    pub experience: u32,
-    pub gold: u32,
-    pub items: Vec<String>,
-    pub reputation_change: i8,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DungeonGenerationRequest {
     pub level: u8,
     pub theme: String,
@@ -76,7 +183,7 @@ pub struct DungeonGenerationRequest {
     pub difficulty: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DungeonSize {
     Small,
     Medium,
@@ -84,87 +191,102 @@ pub enum DungeonSize {
     Huge,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DungeonData {
-    pub name: String,
-    pub description: String,
-    pub rooms: Vec<RoomData>,
-    pub encounters: Vec<EncounterData>,
-    pub treasures: Vec<TreasureData>,
-    pub connections: Vec<RoomConnection>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncounterGenerationRequest {
+    pub difficulty: u8,
+    pub location: String,
+    pub party_size: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RoomData {
-    pub id: u32,
-    pub name: String,
-    pub description: String,
-    pub room_type: RoomType,
-    pub contents: Vec<String>,
-    pub exits: Vec<ExitData>,
+// An encounter made up by the service for somewhere the tables don't cover
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedEncounter {
+    pub location: String,
+    pub difficulty: u8,
+    pub enemies: Vec<EnemyData>,
+    #[serde(default)]
+    pub environment: Vec<String>,
+    #[serde(default)]
+    pub is_ambush: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum RoomType {
-    Entrance,
-    Corridor,
-    Chamber,
-    Treasury,
-    Boss,
-    Trap,
-    Empty,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RumorRequest {
+    pub location: String,
+    pub party_level: u8,
+    // Sites the party already knows of, so the rumor can be about somewhere new
+    pub known_sites: Vec<String>,
+    // People and quests the party knows, for gossip about them
+    pub known_npcs: Vec<String>,
+    pub quests: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ExitData {
-    pub direction: String,
-    pub destination_room: u32,
-    pub is_secret: bool,
-    pub is_locked: bool,
+// Talk overheard in town. A rumor may point to a dungeon at a site, with its theme and
+// level, and may come with a quest. It may also be false, and then it leads nowhere:
+// the site isn't there and the quest comes to nothing, though nobody says so.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rumor {
+    pub text: String,
+    #[serde(default = "truthful")]
+    pub truthful: bool,
+    #[serde(default)]
+    pub site: Option<String>,
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub level: Option<u8>,
+    #[serde(default)]
+    pub quest: Option<QuestData>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RoomConnection {
-    pub from_room: u32,
-    pub to_room: u32,
-    pub direction: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HirelingRequest {
+    pub location: String,
+    pub party_level: u8,
+    pub count: u8,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct EncounterData {
-    pub room_id: u32,
-    pub enemies: Vec<EnemyData>,
-    pub difficulty: u8,
-    pub is_ambush: bool,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HirelingsResponse {
+    pub candidates: Vec<Candidate>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct EnemyData {
-    pub name: String,
-    pub monster_type: String,
-    pub level: u8,
-    pub hit_points: i16,
-    pub armor_class: i8,
-    pub attacks: Vec<AttackData>,
-    pub special_abilities: Vec<String>,
-    pub loot_table: Vec<String>,
+pub struct RecapRequest {
+    pub party: Vec<String>,
+    pub events: Vec<String>,
+    pub dialogue: Vec<String>,
+    pub locations: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct AttackData {
-    pub name: String,
-    pub damage: String, // e.g., "1d6+1"
-    pub attack_bonus: i8,
-    pub range: String,
+pub struct RecapResponse {
+    pub recap: String,
+}
+
+// What the Dungeon Master is asked to describe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NarrationMoment {
+    EnterRoom,
+    Treasure,
+    KillingBlow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NarrationRequest {
+    pub moment: NarrationMoment,
+    // The room entered, the treasure taken or the monster slain
+    pub subject: String,
+    // Name and description of the room the party is in, if it's in one
+    pub room: Option<String>,
+    pub party: Vec<String>,
+    pub recent_actions: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct TreasureData {
-    pub room_id: u32,
-    pub items: Vec<String>,
-    pub gold: u32,
-    pub is_hidden: bool,
-    pub trap_difficulty: Option<u8>,
+pub struct NarrationResponse {
+    pub text: String,
 }
 
 #[derive(Event)]
@@ -179,54 +301,347 @@ pub struct DungeonGenerationEvent {
     pub request: DungeonGenerationRequest,
 }
 
+#[derive(Event)]
+pub struct EncounterGenerationEvent {
+    pub request: EncounterGenerationRequest,
+}
+
+// Stops waiting on an NPC's reply, e.g. when the player walks away mid-sentence
+#[derive(Event)]
+pub struct CancelConversation {
+    pub npc_id: String,
+}
+
+#[derive(Event)]
+pub struct RumorEvent {
+    pub request: RumorRequest,
+}
+
+#[derive(Event)]
+pub struct HirelingsEvent {
+    pub request: HirelingRequest,
+}
+
+// Replies delivered back to the ECS once the service answers
+#[derive(Event)]
+pub struct NPCConversationResponseEvent {
+    pub npc_id: String,
+    pub response: ConversationResponse,
+}
+
+#[derive(Event)]
+pub struct DungeonGeneratedEvent {
+    pub dungeon: DungeonData,
+}
+
+#[derive(Event)]
+pub struct EncounterGeneratedEvent {
+    pub encounter: GeneratedEncounter,
+}
+
+#[derive(Event)]
+pub struct RumorHeardEvent {
+    pub rumor: Rumor,
+}
+
+#[derive(Event)]
+pub struct HirelingsFoundEvent {
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Event)]
+pub struct AIRequestFailed {
+    pub endpoint: &'static str,
+    pub error: AiClientError,
+}
+
+// The NPCs the party has met, keyed by id. Conversations send the stored data and
+// replace it with whatever the service sends back, and save files keep it, so NPCs
+// remember the party from one session to the next.
+#[derive(Resource, Default)]
+pub struct NPCRoster {
+    pub npcs: HashMap<String, NPCData>,
+}
+
+// The dungeon the party is in, as last generated by the service
+#[derive(Resource, Default)]
+pub struct CurrentDungeon(pub Option<DungeonData>);
+
+type ConversationTask = Task<Option<Result<ConversationResponse, AiClientError>>>;
+type DungeonTask = Task<Option<Result<DungeonData, AiClientError>>>;
+type EncounterTask = Task<Option<Result<GeneratedEncounter, AiClientError>>>;
+type RumorTask = Task<Option<Result<Rumor, AiClientError>>>;
+type HirelingsTask = Task<Option<Result<HirelingsResponse, AiClientError>>>;
+
+// An NPC's reply as far as it has streamed in, shared between the task receiving it
+// and the game
+#[derive(Clone, Default, Debug)]
+pub struct PartialReply(Arc<Mutex<String>>);
+
+struct PendingConversation {
+    npc_id: String,
+    reply: PartialReply,
+    task: ConversationTask,
+    abort: AbortHandle,
+}
+
+// Requests in flight. Each one runs on the network runtime and is polled once a frame
+// until it finishes, so a slow service never stalls rendering.
+#[derive(Resource, Default)]
+pub struct AIRequests {
+    conversations: Vec<PendingConversation>,
+    dungeons: Vec<DungeonTask>,
+    encounters: Vec<EncounterTask>,
+    rumors: Vec<RumorTask>,
+    hirelings: Vec<HirelingsTask>,
+}
+
+impl PartialReply {
+    pub fn text(&self) -> String {
+        self.0.lock().map(|text| text.clone()).unwrap_or_default()
+    }
+
+    pub(crate) fn push(&self, chunk: &str) {
+        if let Ok(mut text) = self.0.lock() {
+            text.push_str(chunk);
+        }
+    }
+}
+
+impl AIRequests {
+    // What an NPC has said so far of a reply still coming in
+    pub fn partial_reply(&self, npc_id: &str) -> Option<String> {
+        self.conversations
+            .iter()
+            .find(|pending| pending.npc_id == npc_id)
+            .map(|pending| pending.reply.text())
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.conversations.len() + self.dungeons.len() + self.encounters.len() + self.rumors.len() + self.hirelings.len()
+    }
+}
+
 pub struct AIClientPlugin;
 
 impl Plugin for AIClientPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(AIClient::new("http://localhost:8000".to_string()))
+        let config = app.world.get_resource::<GameConfig>().cloned().unwrap_or_default();
+        // The prompt files load into the same library, whenever they do
+        let prompts = app.world.get_resource::<PromptLibrary>().cloned().unwrap_or_default();
+        let backend = config.ai_backend.build(&config.ai_service_url, prompts);
+        app.insert_resource(
+            AIClient::with_backend(backend).with_policy(config.ai_requests).with_limits(config.ai_limits),
+        )
+            .init_resource::<NPCRoster>()
+            .init_resource::<AIRequests>()
+            .init_resource::<CurrentDungeon>()
+            .init_resource::<QuestLog>()
             .add_event::<NPCConversationEvent>()
+            .add_event::<CancelConversation>()
             .add_event::<DungeonGenerationEvent>()
+            .add_event::<EncounterGenerationEvent>()
+            .add_event::<RumorEvent>()
+            .add_event::<HirelingsEvent>()
+            .add_event::<NPCConversationResponseEvent>()
+            .add_event::<DungeonGeneratedEvent>()
+            .add_event::<EncounterGeneratedEvent>()
+            .add_event::<RumorHeardEvent>()
+            .add_event::<HirelingsFoundEvent>()
+            .add_event::<AIRequestFailed>()
             .add_systems(Update, (
                 handle_npc_conversations,
+                cancel_conversations,
                 handle_dungeon_generation,
+                handle_encounter_generation,
+                handle_rumors,
+                handle_hirelings,
+                receive_npc_conversations,
+                receive_dungeon_generation,
+                receive_encounter_generation,
+                receive_rumors,
+                receive_hirelings,
             ));
     }
 }
 
 impl AIClient {
+    // The Python service at `base_url`
     pub fn new(base_url: String) -> Self {
+        Self::with_backend(Arc::new(HttpBackend::new(base_url)))
+    }
+
+    pub fn with_backend(backend: Arc<dyn AiBackend>) -> Self {
         Self {
-            client: Client::new(),
-            base_url,
+            backend,
+            fallback: Arc::new(MockBackend),
+            offline: Arc::new(AtomicBool::new(false)),
+            queue: Arc::new(RequestQueue::new(RateLimits::default())),
+            policy: RequestPolicy::default(),
+            transcript: None,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RequestPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: RateLimits) -> Self {
+        self.queue = Arc::new(RequestQueue::new(limits));
+        self
+    }
+
+    // While offline, every request is answered at once by the stand-ins
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::Relaxed);
+    }
+
+    // Asks the backend itself, not the stand-ins, whether it's there, and how long it took
+    // to say so
+    pub async fn check_health(&self) -> Result<Duration, AiClientError> {
+        let started = std::time::Instant::now();
+        match tokio::time::timeout(HEALTH_TIMEOUT, self.backend.health()).await {
+            Ok(result) => result.map(|()| started.elapsed()),
+            Err(_) => Err(AiClientError::Timeout),
+        }
+    }
+
+    pub fn queue_status(&self) -> QueueStatus {
+        self.queue.status()
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.queue.limits()
+    }
+
+    // Route every response through a replay transcript, either recording it or
+    // answering from it instead of contacting the service
+    pub fn set_transcript(&mut self, transcript: AiTranscript) {
+        self.transcript = Some(transcript);
+    }
+
+    // Runs `attempt` until it succeeds, fails for good, or has been retried as often as
+    // the policy allows. Each attempt gets the policy's timeout. `can_retry` says whether
+    // trying again is still safe.
+    async fn with_retries<T, F, Fut>(
+        &self,
+        endpoint: &str,
+        can_retry: impl Fn() -> bool,
+        mut attempt: F,
+    ) -> Result<T, AiClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AiClientError>>,
+    {
+        let mut delay = self.policy.backoff;
+        let mut retried = 0;
+        loop {
+            let result = match tokio::time::timeout(self.policy.timeout, attempt()).await {
+                Ok(result) => result,
+                Err(_) => Err(AiClientError::Timeout),
+            };
+            match result {
+                Err(error) if error.is_transient() && retried < self.policy.retries && can_retry() => {
+                    retried += 1;
+                    warn!("{} request failed ({}), retrying in {:?}", endpoint, error, delay);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn post_json<B: Serialize, T: DeserializeOwned>(&self, endpoint: &str, body: &B) -> Result<T, AiClientError> {
+        let span = info_span!("ai_request", endpoint);
+        async move {
+            if let Some(transcript) = &self.transcript {
+                if let Some(recorded) = transcript.next_playback(endpoint).await {
+                    debug!("Answered from replay transcript");
+                    return Ok(serde_json::from_str(&recorded)?);
+                }
+            }
+
+            let body = serde_json::to_value(body)?;
+            let text = if self.is_offline() {
+                debug!("Answered by the offline stand-in");
+                self.fallback.request(endpoint, &body).await?
+            } else {
+                let tokens = estimate_tokens(body.to_string().len());
+                let permit = self.queue.acquire(RequestPriority::of(endpoint), tokens).await;
+                let started = std::time::Instant::now();
+                let text = self
+                    .with_retries(endpoint, || true, || self.backend.request(endpoint, &body))
+                    .await
+                    .inspect_err(|e| warn!("Request failed: {}", e))?;
+                permit.charge(estimate_tokens(text.len()));
+                debug!(elapsed_ms = started.elapsed().as_millis() as u64, bytes = text.len(), "Response received");
+                text
+            };
+
+            if let Some(transcript) = &self.transcript {
+                transcript.record(endpoint, &text);
+            }
+
+            Ok(serde_json::from_str(&text)?)
         }
+        .instrument(span)
+        .await
     }
 
     pub async fn converse_with_npc(
         &self,
         request: ConversationRequest,
-    ) -> Result<ConversationResponse, Box<dyn std::error::Error>> {
-        let response = self.client
-            .post(&format!("{}/conversation", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
+    ) -> Result<ConversationResponse, AiClientError> {
+        self.post_json("conversation", &request).await
+    }
 
-        let conversation_response: ConversationResponse = response.json().await?;
-        Ok(conversation_response)
+    // As converse_with_npc, with the reply pushed onto `reply` piece by piece as the
+    // backend writes it. One that can't stream answers all at once.
+    pub async fn converse_with_npc_streaming(
+        &self,
+        request: ConversationRequest,
+        reply: PartialReply,
+    ) -> Result<ConversationResponse, AiClientError> {
+        if let Some(transcript) = &self.transcript {
+            if let Some(recorded) = transcript.next_playback("conversation").await {
+                debug!("Answered from replay transcript");
+                let response: ConversationResponse = serde_json::from_str(&recorded)?;
+                reply.push(&response.npc_response);
+                return Ok(response);
+            }
+        }
+
+        let body = serde_json::to_value(&request)?;
+        let text = if self.is_offline() {
+            self.fallback.converse_streaming(&body, reply.clone()).await?
+        } else {
+            let permit = self.queue.acquire(RequestPriority::Dialogue, estimate_tokens(body.to_string().len())).await;
+            // Once some of the reply is on screen, starting again would say it twice
+            let text = self
+                .with_retries("conversation", || reply.text().is_empty(), || {
+                    self.backend.converse_streaming(&body, reply.clone())
+                })
+                .await?;
+            permit.charge(estimate_tokens(text.len()));
+            text
+        };
+        if let Some(transcript) = &self.transcript {
+            transcript.record("conversation", &text);
+        }
+        Ok(serde_json::from_str(&text)?)
     }
 
     pub async fn generate_dungeon(
         &self,
         request: DungeonGenerationRequest,
-    ) -> Result<DungeonData, Box<dyn std::error::Error>> {
-        let response = self.client
-            .post(&format!("{}/generate_dungeon", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
-
-        let dungeon_data: DungeonData = response.json().await?;
-        Ok(dungeon_data)
+    ) -> Result<DungeonData, AiClientError> {
+        self.post_json("generate_dungeon", &request).await
     }
 
     pub async fn generate_quest(
@@ -234,68 +649,354 @@ impl AIClient {
         npc_data: &NPCData,
         player_level: u8,
         context: &ConversationContext,
-    ) -> Result<QuestData, Box<dyn std::error::Error>> {
+    ) -> Result<QuestData, AiClientError> {
         let request = serde_json::json!({
             "npc_data": npc_data,
             "player_level": player_level,
             "context": context,
         });
 
-        let response = self.client
-            .post(&format!("{}/generate_quest", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
-
-        let quest_data: QuestData = response.json().await?;
-        Ok(quest_data)
+        self.post_json("generate_quest", &request).await
     }
 
     pub async fn generate_encounter(
         &self,
-        difficulty: u8,
-        location: &str,
-        party_size: u8,
-    ) -> Result<EncounterData, Box<dyn std::error::Error>> {
-        let request = serde_json::json!({
-            "difficulty": difficulty,
-            "location": location,
-            "party_size": party_size,
-        });
+        request: EncounterGenerationRequest,
+    ) -> Result<GeneratedEncounter, AiClientError> {
+        self.post_json("generate_encounter", &request).await
+    }
+
+    pub async fn hear_rumor(
+        &self,
+        request: RumorRequest,
+    ) -> Result<Rumor, AiClientError> {
+        self.post_json("rumor", &request).await
+    }
+
+    pub async fn find_hirelings(
+        &self,
+        request: HirelingRequest,
+    ) -> Result<HirelingsResponse, AiClientError> {
+        self.post_json("hirelings", &request).await
+    }
 
-        let response = self.client
-            .post(&format!("{}/generate_encounter", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
+    // A few lines of boxed text for a moment in the dungeon
+    pub async fn narrate(
+        &self,
+        request: NarrationRequest,
+    ) -> Result<NarrationResponse, AiClientError> {
+        self.post_json("narrate", &request).await
+    }
 
-        let encounter_data: EncounterData = response.json().await?;
-        Ok(encounter_data)
+    // Prose summary of a play session for the campaign diary
+    pub async fn write_recap(
+        &self,
+        request: RecapRequest,
+    ) -> Result<RecapResponse, AiClientError> {
+        self.post_json("recap", &request).await
     }
 }
 
 fn handle_npc_conversations(
     mut conversation_events: EventReader<NPCConversationEvent>,
+    mut requests: ResMut<AIRequests>,
+    roster: Res<NPCRoster>,
+    characters: Query<(&Character, &Combatant)>,
+    clock: Res<GameClock>,
     ai_client: Res<AIClient>,
-    mut npc_data: Query<&mut NPCData>,
+    runtime: Res<AsyncRuntime>,
 ) {
+    let player_name = characters
+        .iter()
+        .find(|(_, combatant)| combatant.is_player)
+        .map(|(character, _)| character.name.clone())
+        .unwrap_or_else(|| "Adventurer".to_string());
+
     for event in conversation_events.read() {
-        // This would need to be handled asynchronously in a real implementation
-        // For now, we'll just log the event
-        println!("NPC conversation requested: {}", event.player_message);
+        debug!("NPC conversation requested: {}", event.player_message);
+        // NPCs met for the first time start out with a blank slate
+        let npc_data = roster.npcs.get(&event.npc_id).cloned().unwrap_or_else(|| {
+            create_npc(event.npc_id.clone(), NPC_PERSONALITIES[0].to_string(), String::new())
+        });
+        let request = ConversationRequest {
+            npc_data,
+            player_message: event.player_message.clone(),
+            player_name: player_name.clone(),
+            // Whatever the sender filled in, the NPC hears the time from the game clock
+            context: ConversationContext {
+                time_of_day: clock.time_of_day().to_string(),
+                ..event.context.clone()
+            },
+        };
+
+        let client = ai_client.clone();
+        let reply = PartialReply::default();
+        let streamed = reply.clone();
+        let (task, abort) = runtime.spawn_cancellable(async move {
+            client.converse_with_npc_streaming(request, streamed).await
+        });
+        requests.conversations.push(PendingConversation { npc_id: event.npc_id.clone(), reply, task, abort });
+    }
+}
+
+fn cancel_conversations(mut cancellations: EventReader<CancelConversation>, mut requests: ResMut<AIRequests>) {
+    for cancellation in cancellations.read() {
+        requests.conversations.retain(|pending| {
+            if pending.npc_id != cancellation.npc_id {
+                return true;
+            }
+            debug!("Conversation with {} cancelled", pending.npc_id);
+            pending.abort.abort();
+            false
+        });
     }
 }
 
 fn handle_dungeon_generation(
     mut dungeon_events: EventReader<DungeonGenerationEvent>,
+    mut requests: ResMut<AIRequests>,
     ai_client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
 ) {
     for event in dungeon_events.read() {
-        // This would need to be handled asynchronously in a real implementation
-        println!("Dungeon generation requested: {:?}", event.request);
+        debug!("Dungeon generation requested: {:?}", event.request);
+        let request = event.request.clone();
+        let client = ai_client.clone();
+        requests.dungeons.push(runtime.spawn(async move {
+            client.generate_dungeon(request).await
+        }));
     }
 }
 
+fn handle_encounter_generation(
+    mut encounter_events: EventReader<EncounterGenerationEvent>,
+    mut requests: ResMut<AIRequests>,
+    ai_client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+) {
+    for event in encounter_events.read() {
+        debug!("Encounter generation requested: {:?}", event.request);
+        let request = event.request.clone();
+        let client = ai_client.clone();
+        requests.encounters.push(runtime.spawn(async move {
+            client.generate_encounter(request).await
+        }));
+    }
+}
+
+fn handle_rumors(
+    mut rumor_events: EventReader<RumorEvent>,
+    mut requests: ResMut<AIRequests>,
+    ai_client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+) {
+    for event in rumor_events.read() {
+        debug!("Rumor requested: {:?}", event.request);
+        let request = event.request.clone();
+        let client = ai_client.clone();
+        requests.rumors.push(runtime.spawn(async move {
+            client.hear_rumor(request).await
+        }));
+    }
+}
+
+fn handle_hirelings(
+    mut hireling_events: EventReader<HirelingsEvent>,
+    mut requests: ResMut<AIRequests>,
+    ai_client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+) {
+    for event in hireling_events.read() {
+        debug!("Hirelings requested: {:?}", event.request);
+        let request = event.request.clone();
+        let client = ai_client.clone();
+        requests.hirelings.push(runtime.spawn(async move {
+            client.find_hirelings(request).await
+        }));
+    }
+}
+
+fn receive_npc_conversations(
+    mut requests: ResMut<AIRequests>,
+    mut roster: ResMut<NPCRoster>,
+    mut quest_log: ResMut<QuestLog>,
+    mut responses: EventWriter<NPCConversationResponseEvent>,
+    mut failures: EventWriter<AIRequestFailed>,
+) {
+    let mut index = 0;
+    while index < requests.conversations.len() {
+        let Some(result) = poll_task(&mut requests.conversations[index].task) else {
+            index += 1;
+            continue;
+        };
+        let npc_id = requests.conversations.swap_remove(index).npc_id;
+
+        match flatten_task_result(result) {
+            Ok(response) => {
+                roster.npcs.insert(npc_id.clone(), response.updated_npc_data.clone());
+                if let Some(quest) = &response.quest_offered {
+                    info!("{} offers a quest: {}", npc_id, quest.title);
+                    quest_log.offer(response.updated_npc_data.name.clone(), quest.clone());
+                }
+                responses.send(NPCConversationResponseEvent { npc_id, response });
+            }
+            Err(error) => {
+                warn!("Conversation with {} failed: {}", npc_id, error);
+                failures.send(AIRequestFailed { endpoint: "conversation", error });
+            }
+        }
+    }
+}
+
+// Monsters the service named only by bestiary id get the rest of their stat block
+fn resolve_monsters(enemies: &mut [EnemyData], bestiary: Option<&Bestiary>) {
+    for enemy in enemies {
+        *enemy = match bestiary {
+            Some(bestiary) => bestiary.resolve(enemy),
+            None => enemy.resolve(|_| None),
+        };
+    }
+}
+
+// Treasure in the generated dungeon is held to the B/X treasure types before anything
+// sees it, and treasuries or boss rooms the service left bare get a rolled hoard.
+// Its monsters are looked up in the bestiary.
+#[allow(clippy::too_many_arguments)]
+fn receive_dungeon_generation(
+    mut requests: ResMut<AIRequests>,
+    mut current_dungeon: ResMut<CurrentDungeon>,
+    mut generated: EventWriter<DungeonGeneratedEvent>,
+    mut failures: EventWriter<AIRequestFailed>,
+    tables: Res<TreasureTables>,
+    bestiary: Res<Bestiary>,
+    mut rng: ResMut<GameRng>,
+) {
+
+    let mut index = 0;
+    while index < requests.dungeons.len() {
+        let Some(result) = poll_task(&mut requests.dungeons[index]) else {
+            index += 1;
+            continue;
+        };
+        drop(requests.dungeons.swap_remove(index));
+
+        match flatten_task_result(result) {
+            Ok(mut dungeon) => {
+                info!("Generated dungeon: {}", dungeon.name);
+                let table_for = |treasure_type: &str| tables.table(treasure_type);
+                for change in reconcile_dungeon_treasure(&mut dungeon, table_for, rng.as_mut()) {
+                    info!("Treasure: {}", change);
+                }
+                for encounter in &mut dungeon.encounters {
+                    resolve_monsters(&mut encounter.enemies, Some(&bestiary));
+                }
+                current_dungeon.0 = Some(dungeon.clone());
+                generated.send(DungeonGeneratedEvent { dungeon });
+            }
+            Err(error) => {
+                warn!("Dungeon generation failed: {}", error);
+                failures.send(AIRequestFailed { endpoint: "generate_dungeon", error });
+            }
+        }
+    }
+}
+
+fn receive_encounter_generation(
+    mut requests: ResMut<AIRequests>,
+    mut generated: EventWriter<EncounterGeneratedEvent>,
+    mut failures: EventWriter<AIRequestFailed>,
+    bestiary: Res<Bestiary>,
+) {
+    let mut index = 0;
+    while index < requests.encounters.len() {
+        let Some(result) = poll_task(&mut requests.encounters[index]) else {
+            index += 1;
+            continue;
+        };
+        drop(requests.encounters.swap_remove(index));
+
+        match flatten_task_result(result) {
+            Ok(mut encounter) => {
+                resolve_monsters(&mut encounter.enemies, Some(&bestiary));
+                info!("Generated encounter: {} enemies in {}", encounter.enemies.len(), encounter.location);
+                generated.send(EncounterGeneratedEvent { encounter });
+            }
+            Err(error) => {
+                warn!("Encounter generation failed: {}", error);
+                failures.send(AIRequestFailed { endpoint: "generate_encounter", error });
+            }
+        }
+    }
+}
+
+// A rumor's quest goes straight into the log
+// Rumors from a service that doesn't say are taken as true
+fn truthful() -> bool {
+    true
+}
+
+fn receive_rumors(
+    mut requests: ResMut<AIRequests>,
+    clock: Res<GameClock>,
+    mut quest_log: ResMut<QuestLog>,
+    mut heard: EventWriter<RumorHeardEvent>,
+    mut failures: EventWriter<AIRequestFailed>,
+) {
+    let mut index = 0;
+    while index < requests.rumors.len() {
+        let Some(result) = poll_task(&mut requests.rumors[index]) else {
+            index += 1;
+            continue;
+        };
+        drop(requests.rumors.swap_remove(index));
+
+        match flatten_task_result(result) {
+            Ok(rumor) => {
+                info!("Heard a rumor: {}", rumor.text);
+                if let Some(quest) = rumor.quest.as_ref().filter(|_| rumor.truthful) {
+                    info!("Rumor points to a quest: {}", quest.title);
+                    quest_log.take(quest.clone(), None, clock.day());
+                }
+                heard.send(RumorHeardEvent { rumor });
+            }
+            Err(error) => {
+                warn!("Rumor request failed: {}", error);
+                failures.send(AIRequestFailed { endpoint: "rumor", error });
+            }
+        }
+    }
+}
+
+fn receive_hirelings(
+    mut requests: ResMut<AIRequests>,
+    mut found: EventWriter<HirelingsFoundEvent>,
+    mut failures: EventWriter<AIRequestFailed>,
+) {
+    let mut index = 0;
+    while index < requests.hirelings.len() {
+        let Some(result) = poll_task(&mut requests.hirelings[index]) else {
+            index += 1;
+            continue;
+        };
+        drop(requests.hirelings.swap_remove(index));
+
+        match flatten_task_result(result) {
+            Ok(response) => {
+                info!("{} hirelings looking for work", response.candidates.len());
+                found.send(HirelingsFoundEvent { candidates: response.candidates });
+            }
+            Err(error) => {
+                warn!("Hireling request failed: {}", error);
+                failures.send(AIRequestFailed { endpoint: "hirelings", error });
+            }
+        }
+    }
+}
+
+fn flatten_task_result<T>(result: Option<Result<T, AiClientError>>) -> Result<T, AiClientError> {
+    result.unwrap_or_else(|| Err(AiClientError::Network("the request task panicked".to_string())))
+}
+
 // Helper functions for creating NPCs
 pub fn create_npc(name: String, personality: String, background: String) -> NPCData {
     NPCData {
@@ -310,13 +1011,13 @@ pub fn create_npc(name: String, personality: String, background: String) -> NPCD
 
 pub fn create_conversation_context(
     location: String,
-    time_of_day: String,
+    clock: &GameClock,
     recent_events: Vec<String>,
     player_reputation: i8,
 ) -> ConversationContext {
     ConversationContext {
         location,
-        time_of_day,
+        time_of_day: clock.time_of_day().to_string(),
         recent_events,
         player_reputation,
     }
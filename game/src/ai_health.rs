@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::tasks::Task;
+use std::time::Duration;
+
+use crate::ai_client::{AIClient, AIRequestFailed, AiClientError};
+use crate::tasks::{poll_task, AsyncRuntime};
+
+// Whether the AI is there to answer. The backend is asked at startup, every half minute
+// after, and straight away when a request fails for want of an answer. While it's down,
+// AIClient answers everything from the offline stand-ins (see ai_backend.rs) instead of
+// waiting out timeouts, and it goes back to the backend as soon as a check gets through.
+
+pub const HEALTH_INTERVAL_SECS: f32 = 30.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Connection {
+    // No check has come back yet
+    #[default]
+    Checking,
+    Online,
+    Offline(AiClientError),
+}
+
+type HealthTask = Task<Option<Result<Duration, AiClientError>>>;
+
+#[derive(Resource)]
+pub struct AiStatus {
+    pub connection: Connection,
+    // How long the last check that got through took
+    pub latency: Option<Duration>,
+    timer: Timer,
+    pending: Option<HealthTask>,
+}
+
+impl Default for AiStatus {
+    fn default() -> Self {
+        Self {
+            connection: Connection::Checking,
+            latency: None,
+            timer: Timer::from_seconds(HEALTH_INTERVAL_SECS, TimerMode::Repeating),
+            pending: None,
+        }
+    }
+}
+
+impl AiStatus {
+    pub fn is_online(&self) -> bool {
+        self.connection == Connection::Online
+    }
+}
+
+// The AI went down, or came back
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ConnectionChanged {
+    pub online: bool,
+}
+
+pub struct AiHealthPlugin;
+
+impl Plugin for AiHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AiStatus>()
+            .add_event::<ConnectionChanged>()
+            .add_systems(Startup, check_at_startup)
+            .add_systems(Update, (schedule_checks, receive_checks).chain());
+    }
+}
+
+fn start_check(status: &mut AiStatus, client: &AIClient, runtime: &AsyncRuntime) {
+    if status.pending.is_some() {
+        return;
+    }
+    let client = client.clone();
+    status.pending = Some(runtime.spawn(async move { client.check_health().await }));
+}
+
+fn check_at_startup(mut status: ResMut<AiStatus>, client: Res<AIClient>, runtime: Res<AsyncRuntime>) {
+    start_check(&mut status, &client, &runtime);
+}
+
+fn schedule_checks(
+    time: Res<Time>,
+    mut failures: EventReader<AIRequestFailed>,
+    client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+    mut status: ResMut<AiStatus>,
+) {
+    let failed = failures.read().any(|failure| failure.error.is_transient());
+    if status.timer.tick(time.delta()).just_finished() || failed {
+        start_check(&mut status, &client, &runtime);
+    }
+}
+
+fn receive_checks(
+    client: Res<AIClient>,
+    mut status: ResMut<AiStatus>,
+    mut changed: EventWriter<ConnectionChanged>,
+) {
+    let Some(task) = status.pending.as_mut() else {
+        return;
+    };
+    let Some(result) = poll_task(task) else {
+        return;
+    };
+    status.pending = None;
+    let result = result.unwrap_or_else(|| Err(AiClientError::Network("the health check panicked".to_string())));
+    match result {
+        Ok(latency) => {
+            status.latency = Some(latency);
+            if status.connection != Connection::Online {
+                if matches!(status.connection, Connection::Offline(_)) {
+                    info!("The AI is back after {:?}; leaving the offline stand-ins", latency);
+                    changed.send(ConnectionChanged { online: true });
+                }
+                client.set_offline(false);
+                status.connection = Connection::Online;
+            }
+        }
+        Err(error) => {
+            if !matches!(status.connection, Connection::Offline(_)) {
+                warn!("The AI isn't answering ({}); using the offline stand-ins", error);
+                changed.send(ConnectionChanged { online: false });
+                client.set_offline(true);
+            }
+            status.connection = Connection::Offline(error);
+        }
+    }
+}
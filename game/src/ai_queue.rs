@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// Every request AIClient makes waits its turn here. Only so many go to the backend at
+// once, and no more than the limits allow in any minute, counting requests and a rough
+// count of tokens (a token is taken to be four bytes of JSON either way). Waiting
+// requests go in order of priority, so someone talking to an NPC never waits behind a
+// dungeon being generated, and in the order they were made within a priority.
+
+// How long a request and its response count against the per-minute limits
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Longest a waiting request sleeps before looking at the queue again
+const RECHECK: Duration = Duration::from_millis(250);
+
+// Lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    // Work the player isn't waiting on, like generating a dungeon ahead of the party
+    Background,
+    // Something the player will see shortly: a rumor, hirelings, an encounter
+    Foreground,
+    // A conversation the player is in the middle of
+    Dialogue,
+}
+
+impl RequestPriority {
+    pub fn of(endpoint: &str) -> Self {
+        match endpoint {
+            "conversation" | "generate_quest" => RequestPriority::Dialogue,
+            "generate_dungeon" | "recap" => RequestPriority::Background,
+            _ => RequestPriority::Foreground,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RequestPriority::Background => "background",
+            RequestPriority::Foreground => "foreground",
+            RequestPriority::Dialogue => "dialogue",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    pub concurrent: usize,
+    pub requests_per_minute: u32,
+    pub tokens_per_minute: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            concurrent: 2,
+            requests_per_minute: 30,
+            tokens_per_minute: 40_000,
+        }
+    }
+}
+
+// Rough token count of a request or response
+pub fn estimate_tokens(bytes: usize) -> u32 {
+    u32::try_from(bytes.div_ceil(4)).unwrap_or(u32::MAX)
+}
+
+#[derive(Default)]
+struct QueueState {
+    running: usize,
+    // Tickets of the requests waiting, with their priorities
+    waiting: Vec<(u64, RequestPriority)>,
+    next_ticket: u64,
+    // When each request in the last minute went, its ticket and the tokens charged for it
+    sent: VecDeque<(Instant, u64, u32)>,
+}
+
+impl QueueState {
+    fn forget_before(&mut self, now: Instant) {
+        while self.sent.front().is_some_and(|(at, _, _)| now.duration_since(*at) >= WINDOW) {
+            self.sent.pop_front();
+        }
+    }
+
+    fn tokens(&self) -> u32 {
+        self.sent.iter().map(|(_, _, tokens)| tokens).sum()
+    }
+
+    // The waiting request that goes next
+    fn first(&self) -> Option<u64> {
+        self.waiting
+            .iter()
+            .max_by(|(a, a_priority), (b, b_priority)| a_priority.cmp(b_priority).then(b.cmp(a)))
+            .map(|(ticket, _)| *ticket)
+    }
+}
+
+// How the queue stands, for the console
+#[derive(Debug, Clone, Default)]
+pub struct QueueStatus {
+    pub running: usize,
+    pub waiting: Vec<RequestPriority>,
+    pub requests_this_minute: usize,
+    pub tokens_this_minute: u32,
+}
+
+pub struct RequestQueue {
+    limits: RateLimits,
+    state: Mutex<QueueState>,
+    changed: Notify,
+}
+
+// A request's place at the backend, given back when it's dropped
+pub struct Permit<'a> {
+    queue: &'a RequestQueue,
+    ticket: u64,
+}
+
+// A request's place in line, given up if it's dropped before its turn, e.g. when a
+// conversation is cancelled
+struct Waiting<'a> {
+    queue: &'a RequestQueue,
+    ticket: u64,
+}
+
+impl RequestQueue {
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits, state: Mutex::new(QueueState::default()), changed: Notify::new() }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    // Waits until a request of `priority`, about `tokens` long, may go
+    pub async fn acquire(&self, priority: RequestPriority, tokens: u32) -> Permit<'_> {
+        let waiting = {
+            let mut state = self.lock();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiting.push((ticket, priority));
+            Waiting { queue: self, ticket }
+        };
+        loop {
+            // Taken before looking, so a request finishing in between still wakes this one
+            let changed = self.changed.notified();
+            let wait = {
+                let mut state = self.lock();
+                let now = Instant::now();
+                state.forget_before(now);
+                let turn = state.first() == Some(waiting.ticket) && state.running < self.limits.concurrent.max(1);
+                // A request bigger than the whole budget still goes, once the minute is clear
+                let within_budget = state.sent.is_empty()
+                    || (state.sent.len() < self.limits.requests_per_minute as usize
+                        && state.tokens().saturating_add(tokens) <= self.limits.tokens_per_minute);
+                if turn && within_budget {
+                    state.waiting.retain(|(ticket, _)| *ticket != waiting.ticket);
+                    state.running += 1;
+                    state.sent.push_back((now, waiting.ticket, tokens));
+                    return Permit { queue: self, ticket: waiting.ticket };
+                }
+                match (turn, state.sent.front()) {
+                    (true, Some((oldest, _, _))) => (*oldest + WINDOW).saturating_duration_since(now).min(RECHECK),
+                    _ => RECHECK,
+                }
+            };
+            let _ = tokio::time::timeout(wait, changed).await;
+        }
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let mut state = self.lock();
+        state.forget_before(Instant::now());
+        let mut waiting: Vec<_> = state.waiting.iter().map(|(_, priority)| *priority).collect();
+        waiting.sort_by(|a, b| b.cmp(a));
+        QueueStatus {
+            running: state.running,
+            waiting,
+            requests_this_minute: state.sent.len(),
+            tokens_this_minute: state.tokens(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Permit<'_> {
+    // Counts the response against this minute's tokens too
+    pub fn charge(&self, tokens: u32) {
+        let mut state = self.queue.lock();
+        if let Some((_, _, charged)) = state.sent.iter_mut().find(|(_, ticket, _)| *ticket == self.ticket) {
+            *charged = charged.saturating_add(tokens);
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.queue.lock().running -= 1;
+        self.queue.changed.notify_waiters();
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.queue.lock().waiting.retain(|(ticket, _)| *ticket != self.ticket);
+        self.queue.changed.notify_waiters();
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::clock::{ClockSet, DayPassed, GameClock};
+use crate::combat::{process_damage_events, CharacterFell, CombatLogEvent, Combatant};
+use crate::graveyard::Graveyard;
+use crate::message_log::{LogMessage, MessageCategory};
+use crate::rng::GameRng;
+
+pub use old_school_core::ailments::{blocks_healing, Affliction, Ailment, Disease, DiseaseDay, Poisoning};
+
+// Poison and disease (the rules are in old_school_core::ailments). A monster's blow that
+// lands passes on whatever its special abilities say, once its damage is done; diseases
+// then run a day at a time on the game clock, in a fight or out of one, until they're
+// cured at the temple or by a spell, or they finish their victim. Someone lost to
+// lycanthropy leaves the party and gets a grave in the graveyard all the same.
+
+// A blow from `source` carrying `affliction`
+#[derive(Event, Clone, Debug)]
+pub struct Afflicted {
+    pub target: Entity,
+    pub affliction: Affliction,
+    pub source: String,
+}
+
+pub struct AilmentsPlugin;
+
+impl Plugin for AilmentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Afflicted>()
+            .add_systems(Update, pass_on_afflictions.after(process_damage_events))
+            .add_systems(Update, run_diseases.after(ClockSet));
+    }
+}
+
+fn pass_on_afflictions(
+    mut afflictions: EventReader<Afflicted>,
+    mut characters: Query<(&mut Character, &mut Combatant)>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut fallen: EventWriter<CharacterFell>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in afflictions.read() {
+        let Ok((mut character, mut combatant)) = characters.get_mut(event.target) else {
+            continue;
+        };
+        if !character.is_alive() {
+            continue;
+        }
+        let text = match event.affliction {
+            Affliction::DeadlyPoison | Affliction::Venom => {
+                match character.poison(event.affliction == Affliction::DeadlyPoison, rng.as_mut()) {
+                    Poisoning::Resisted(save) => {
+                        format!("{} shrugs off the poison ({} vs {})", character.name, save.roll, save.target)
+                    }
+                    Poisoning::Killed(save) => {
+                        fallen.send(CharacterFell {
+                            entity: event.target,
+                            name: character.name.clone(),
+                            is_player: combatant.is_player,
+                            cause: "killed by poison".to_string(),
+                            killer: Some(event.source.clone()),
+                        });
+                        let (source, name) = (&event.source, &character.name);
+                        format!("{}'s poison kills {} ({} vs {})", source, name, save.roll, save.target)
+                    }
+                    Poisoning::Sickened(venom) => {
+                        combatant.status_effects.retain(|existing| existing.name != venom.name);
+                        combatant.status_effects.push(venom);
+                        format!("{} is sickened by the venom", character.name)
+                    }
+                }
+            }
+            Affliction::Disease(disease) => {
+                let has_it = combatant.status_effects.iter().any(|effect| Disease::of(effect) == Some(disease));
+                if has_it || !disease.passes_to(&character) {
+                    continue;
+                }
+                combatant.status_effects.push(disease.infect(rng.as_mut()));
+                format!("{} catches {} from {}", character.name, disease.name().to_lowercase(), event.source)
+            }
+        };
+        info!("{}", text);
+        log_events.send(CombatLogEvent(text));
+    }
+}
+
+fn run_diseases(
+    mut commands: Commands,
+    mut days: EventReader<DayPassed>,
+    mut party: Query<(Entity, &mut Character, &mut Combatant)>,
+    clock: Res<GameClock>,
+    mut graveyard: ResMut<Graveyard>,
+    mut log: EventWriter<LogMessage>,
+    mut fallen: EventWriter<CharacterFell>,
+) {
+    for _ in days.read() {
+        for (entity, mut character, mut combatant) in party.iter_mut() {
+            if !combatant.is_player || !character.is_alive() {
+                continue;
+            }
+            let mut effects = std::mem::take(&mut combatant.status_effects);
+            for effect in effects.iter_mut() {
+                let Some(disease) = Disease::of(effect) else {
+                    continue;
+                };
+                let text = match character.suffer_disease_day(effect) {
+                    DiseaseDay::Incubating => continue,
+                    DiseaseDay::Wasted(lost) => {
+                        format!("{} loses {} hit point to {}", character.name, lost, disease.name().to_lowercase())
+                    }
+                    DiseaseDay::Died => {
+                        let cause = format!("died of {}", disease.name().to_lowercase());
+                        fallen.send(CharacterFell {
+                            entity,
+                            name: character.name.clone(),
+                            is_player: true,
+                            cause: cause.clone(),
+                            killer: None,
+                        });
+                        format!("{} {}", character.name, cause)
+                    }
+                    DiseaseDay::TookHold => {
+                        graveyard.dig(&character, "lost to lycanthropy", None, clock.day());
+                        graveyard.keep();
+                        commands.entity(entity).despawn_recursive();
+                        let name = &character.name;
+                        format!("The curse takes hold of {}, who runs off into the wilds and is seen no more", name)
+                    }
+                };
+                info!("{}", text);
+                log.send(LogMessage { category: MessageCategory::Notice, text });
+                if !character.is_alive() {
+                    break;
+                }
+            }
+            combatant.status_effects = effects;
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+use crate::character::Character;
+use crate::combat::{is_melee_attack, AttackEvent, CastSpellEvent, DamageEvent};
+use crate::theme::{TextSize, UiTheme};
+use crate::ui::{CombatUI, LineupPortrait};
+use crate::GameState;
+
+// Animations on the combat screen, played on the lineup's portraits: an attacker lunges
+// toward their target, an arrow or a spell flies across to it as a small square, and
+// whoever is hurt flashes red while the damage rises over them and fades. A natural 20
+// shakes the whole screen. They are only for show; nothing waits for them, and anyone
+// without a portrait in the lineup just isn't animated.
+
+const LUNGE_SECONDS: f32 = 0.25;
+const LUNGE_DISTANCE: f32 = 16.0;
+const FLASH_SECONDS: f32 = 0.3;
+const PROJECTILE_SECONDS: f32 = 0.35;
+const PROJECTILE_SIZE: f32 = 8.0;
+const NUMBER_SECONDS: f32 = 1.0;
+const NUMBER_RISE: f32 = 40.0;
+const SHAKE_SECONDS: f32 = 0.3;
+const SHAKE_DISTANCE: f32 = 6.0;
+
+// Out toward the target and back, `direction` being which way that is
+#[derive(Component)]
+struct Lunge {
+    timer: Timer,
+    direction: f32,
+}
+
+// The portrait's own tint, to go back to
+#[derive(Component)]
+struct Flash {
+    timer: Timer,
+    original: Color,
+}
+
+#[derive(Component)]
+struct Projectile {
+    timer: Timer,
+    from: Vec2,
+    to: Vec2,
+}
+
+#[derive(Component)]
+struct FloatingNumber {
+    timer: Timer,
+    start: Vec2,
+}
+
+#[derive(Resource, Default)]
+struct Shake(Option<Timer>);
+
+// The combat screen's root, not the projectiles and numbers flying over it
+type CombatScreen = (With<CombatUI>, Without<Parent>, Without<Projectile>, Without<FloatingNumber>);
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Shake>()
+            .add_systems(OnExit(GameState::Combat), stop_shaking)
+            .add_systems(Update, (
+                animate_attacks,
+                animate_spells,
+                animate_damage,
+                lunge,
+                flash,
+                fly_projectiles,
+                float_numbers,
+                shake_screen,
+            ).chain().run_if(in_state(GameState::Combat)));
+    }
+}
+
+// Where each portrait is on screen: the middle, in the UI's own pixels, and its size
+fn portrait_at(
+    portraits: &Query<(Entity, &LineupPortrait, &GlobalTransform, &Node)>,
+    combatant: Entity,
+) -> Option<(Entity, Vec2, Vec2)> {
+    portraits
+        .iter()
+        .find(|(_, portrait, _, _)| portrait.0 == combatant)
+        .map(|(entity, _, transform, node)| (entity, transform.translation().truncate(), node.size()))
+}
+
+fn spawn_projectile(commands: &mut Commands, from: Vec2, to: Vec2, color: Color) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(from.x - PROJECTILE_SIZE / 2.0),
+                top: Val::Px(from.y - PROJECTILE_SIZE / 2.0),
+                width: Val::Px(PROJECTILE_SIZE),
+                height: Val::Px(PROJECTILE_SIZE),
+                ..default()
+            },
+            background_color: color.into(),
+            z_index: ZIndex::Global(50),
+            ..default()
+        },
+        Projectile { timer: Timer::from_seconds(PROJECTILE_SECONDS, TimerMode::Once), from, to },
+        CombatUI,
+    ));
+}
+
+// Missiles and spells cast as attacks fly across; everything else is close enough to lunge
+fn animate_attacks(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut attacks: EventReader<AttackEvent>,
+    characters: Query<&Character>,
+    portraits: Query<(Entity, &LineupPortrait, &GlobalTransform, &Node)>,
+) {
+    for event in attacks.read() {
+        let (Some((portrait, from, _)), Some((_, to, _))) =
+            (portrait_at(&portraits, event.attacker), portrait_at(&portraits, event.target))
+        else {
+            continue;
+        };
+        let direction = if to.x < from.x { -1.0 } else { 1.0 };
+        let timer = Timer::from_seconds(LUNGE_SECONDS, TimerMode::Once);
+        commands.entity(portrait).try_insert(Lunge { timer, direction });
+
+        let weapon = event.weapon.as_deref();
+        let melee = characters.get(event.attacker).is_ok_and(|attacker| is_melee_attack(attacker, weapon));
+        if event.spell.is_some() {
+            spawn_projectile(&mut commands, from, to, theme.palette.arcane);
+        } else if !melee {
+            spawn_projectile(&mut commands, from, to, theme.palette.parchment);
+        }
+    }
+}
+
+fn animate_spells(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut casts: EventReader<CastSpellEvent>,
+    portraits: Query<(Entity, &LineupPortrait, &GlobalTransform, &Node)>,
+) {
+    for event in casts.read() {
+        let Some((_, from, _)) = portrait_at(&portraits, event.caster) else {
+            continue;
+        };
+        for target in event.targets.iter().filter(|target| **target != event.caster) {
+            if let Some((_, to, _)) = portrait_at(&portraits, *target) {
+                spawn_projectile(&mut commands, from, to, theme.palette.arcane);
+            }
+        }
+    }
+}
+
+fn animate_damage(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut damage: EventReader<DamageEvent>,
+    portraits: Query<(Entity, &LineupPortrait, &GlobalTransform, &Node)>,
+    mut tints: Query<(&BackgroundColor, Option<&mut Flash>)>,
+    mut shake: ResMut<Shake>,
+) {
+    for event in damage.read().filter(|event| event.damage > 0) {
+        if event.critical {
+            shake.0 = Some(Timer::from_seconds(SHAKE_SECONDS, TimerMode::Once));
+        }
+        let Some((portrait, at, size)) = portrait_at(&portraits, event.target) else {
+            continue;
+        };
+        // A second hit while still flashing starts the flash again from the same tint
+        if let Ok((color, flashing)) = tints.get_mut(portrait) {
+            match flashing {
+                Some(mut flash) => flash.timer.reset(),
+                None => {
+                    let timer = Timer::from_seconds(FLASH_SECONDS, TimerMode::Once);
+                    commands.entity(portrait).try_insert(Flash { timer, original: color.0 });
+                }
+            }
+        }
+
+        let start = Vec2::new(at.x - size.x / 2.0, at.y - size.y / 2.0);
+        let size = if event.critical { TextSize::Large } else { TextSize::Body };
+        let style = theme.text(size, theme.palette.danger);
+        commands.spawn((
+            TextBundle::from_section(event.damage.to_string(), style).with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(start.x),
+                top: Val::Px(start.y),
+                ..default()
+            }),
+            ZIndex::Global(50),
+            FloatingNumber { timer: Timer::from_seconds(NUMBER_SECONDS, TimerMode::Once), start },
+            CombatUI,
+        ));
+    }
+}
+
+fn lunge(mut commands: Commands, time: Res<Time>, mut lunging: Query<(Entity, &mut Lunge, &mut Style)>) {
+    for (entity, mut lunge, mut style) in lunging.iter_mut() {
+        lunge.timer.tick(time.delta());
+        if lunge.timer.finished() {
+            style.left = Val::Auto;
+            commands.entity(entity).remove::<Lunge>();
+        } else {
+            let offset = (lunge.timer.percent() * PI).sin() * LUNGE_DISTANCE * lunge.direction;
+            style.left = Val::Px(offset);
+        }
+    }
+}
+
+fn flash(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    time: Res<Time>,
+    mut flashing: Query<(Entity, &mut Flash, &mut BackgroundColor)>,
+) {
+    for (entity, mut flash, mut color) in flashing.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            color.0 = flash.original;
+            commands.entity(entity).remove::<Flash>();
+        } else {
+            color.0 = mix(theme.palette.danger, flash.original, flash.timer.percent());
+        }
+    }
+}
+
+fn fly_projectiles(mut commands: Commands, time: Res<Time>, mut flying: Query<(Entity, &mut Projectile, &mut Style)>) {
+    for (entity, mut projectile, mut style) in flying.iter_mut() {
+        projectile.timer.tick(time.delta());
+        if projectile.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let at = projectile.from.lerp(projectile.to, projectile.timer.percent());
+        style.left = Val::Px(at.x - PROJECTILE_SIZE / 2.0);
+        style.top = Val::Px(at.y - PROJECTILE_SIZE / 2.0);
+    }
+}
+
+fn float_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut numbers: Query<(Entity, &mut FloatingNumber, &mut Style, &mut Text)>,
+) {
+    for (entity, mut number, mut style, mut text) in numbers.iter_mut() {
+        number.timer.tick(time.delta());
+        if number.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let done = number.timer.percent();
+        style.top = Val::Px(number.start.y - NUMBER_RISE * done);
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(1.0 - done);
+        }
+    }
+}
+
+// The whole screen jolts about, less and less until it settles back where it was
+fn shake_screen(
+    time: Res<Time>,
+    mut shake: ResMut<Shake>,
+    mut screens: Query<&mut Style, CombatScreen>,
+) {
+    let Some(timer) = shake.0.as_mut() else {
+        return;
+    };
+    timer.tick(time.delta());
+    let (x, y) = if timer.finished() {
+        shake.0 = None;
+        (0.0, 0.0)
+    } else {
+        let left = 1.0 - timer.percent();
+        let elapsed = time.elapsed_seconds();
+        ((elapsed * 90.0).sin() * SHAKE_DISTANCE * left, (elapsed * 70.0).cos() * SHAKE_DISTANCE * left)
+    };
+    for mut style in screens.iter_mut() {
+        style.left = Val::Px(x);
+        style.top = Val::Px(y);
+    }
+}
+
+fn stop_shaking(mut shake: ResMut<Shake>) {
+    shake.0 = None;
+}
+
+// `from` at the start, `to` at the end
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    let (from, to) = (from.as_rgba_f32(), to.as_rgba_f32());
+    let channel = |i: usize| from[i] + (to[i] - from[i]) * amount;
+    Color::rgba(channel(0), channel(1), channel(2), channel(3))
+}
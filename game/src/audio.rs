@@ -0,0 +1,365 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::LoadedFolder;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use std::any::TypeId;
+use std::collections::HashSet;
+
+use crate::combat::{AttackEvent, CastSpellEvent, DamageEvent};
+use crate::config::{save_settings, ConfigPath};
+use crate::doors::DoorState;
+use crate::dungeon::{DungeonMap, PartyToken, Position};
+use crate::game_events::GameEvent;
+use crate::{GameConfig, GameState};
+
+// Music. Each part of the game has a looping track: the menus, exploring (the dungeon
+// and the wilderness), combat and town. The tracks are whatever audio files in
+// assets/music are named for them (menu.ogg, exploration.ogg, combat.ogg, town.ogg).
+// The game comes with short WAV loops for each, to be replaced with real music; a part
+// without a track is quiet, which is logged the first time. Screens opened from more
+// than one place (the inventory, the map, quests, the spellbook) keep the track already
+// playing. On a change of track the old one fades out as the new one fades in.
+//
+// Sound effects are files in assets/sounds named for the Sound they are (swing.ogg,
+// hit.ogg, ...), with simple WAV placeholders for each. Anything can play one with a
+// PlaySound; the ones heard in play are picked up from what already goes on: an attack
+// swings and a blow that lands hits (so a miss is a swing alone), a spell is cast,
+// someone in the party goes up a level, a door opens, the party takes a step, a button
+// is clicked, and a fight starts.
+//
+// The volumes are `music_volume` and `sfx_volume` in config.toml, which the Settings
+// screen turns down and up with - and + for the music and [ and ] for the rest.
+
+pub const MUSIC_FOLDER: &str = "music";
+pub const SOUND_FOLDER: &str = "sounds";
+
+// How long a track takes to fade from silence to full volume, or back
+const CROSSFADE_SECS: f32 = 2.0;
+// How much a key on the Settings screen changes a volume by
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MusicTrack {
+    Menu,
+    Exploration,
+    Combat,
+    Town,
+}
+
+impl MusicTrack {
+    // The file name, without its extension, the track is looked for under
+    pub fn name(&self) -> &'static str {
+        match self {
+            MusicTrack::Menu => "menu",
+            MusicTrack::Exploration => "exploration",
+            MusicTrack::Combat => "combat",
+            MusicTrack::Town => "town",
+        }
+    }
+
+    // None for screens that keep whatever is playing
+    pub fn for_state(state: &GameState) -> Option<Self> {
+        match state {
+            GameState::MainMenu
+            | GameState::CharacterCreation
+            | GameState::Settings
+            | GameState::LoadGame
+            | GameState::GameOver
+            | GameState::Graveyard => Some(MusicTrack::Menu),
+            GameState::InGame => Some(MusicTrack::Exploration),
+            GameState::Combat => Some(MusicTrack::Combat),
+            GameState::Town | GameState::Shop => Some(MusicTrack::Town),
+            GameState::Inventory
+            | GameState::Map
+            | GameState::Quests
+            | GameState::Spellbook
+            | GameState::Loot
+            | GameState::LevelUp => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Swing,
+    Hit,
+    Spell,
+    LevelUp,
+    Door,
+    Footstep,
+    Click,
+    Encounter,
+}
+
+impl Sound {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sound::Swing => "swing",
+            Sound::Hit => "hit",
+            Sound::Spell => "spell",
+            Sound::LevelUp => "level_up",
+            Sound::Door => "door",
+            Sound::Footstep => "footstep",
+            Sound::Click => "click",
+            Sound::Encounter => "encounter",
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlaySound(pub Sound);
+
+#[derive(Resource, Debug, Default)]
+pub struct Music {
+    // None if there is no music folder
+    folder: Option<Handle<LoadedFolder>>,
+    pub wanted: Option<MusicTrack>,
+    pub playing: Option<MusicTrack>,
+    // Tracks already found to have no file, so each is only logged once
+    missing: HashSet<MusicTrack>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct Sounds {
+    folder: Option<Handle<LoadedFolder>>,
+}
+
+// A track being played; one fading out is despawned once it is silent
+#[derive(Component, Debug)]
+struct MusicPlayer {
+    fading_out: bool,
+}
+
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Music>()
+            .init_resource::<Sounds>()
+            .add_event::<PlaySound>()
+            .add_systems(Startup, load_audio)
+            .add_systems(Update, (
+                choose_music,
+                change_music,
+                fade_music,
+                adjust_volumes.run_if(in_state(GameState::Settings)),
+            ).chain())
+            .add_systems(Update, (
+                combat_sounds,
+                level_up_sounds,
+                door_sounds,
+                footstep_sounds,
+                click_sounds,
+                encounter_sounds,
+                play_sounds,
+            ).chain());
+    }
+}
+
+fn load_audio(asset_server: Res<AssetServer>, mut music: ResMut<Music>, mut sounds: ResMut<Sounds>) {
+    music.folder = load_folder_if_there(&asset_server, MUSIC_FOLDER);
+    sounds.folder = load_folder_if_there(&asset_server, SOUND_FOLDER);
+}
+
+// Only a folder that is there is loaded, so a game without music or sounds doesn't complain
+fn load_folder_if_there(asset_server: &AssetServer, folder: &'static str) -> Option<Handle<LoadedFolder>> {
+    let path = FileAssetReader::get_base_path().join("assets").join(folder);
+    if path.is_dir() {
+        Some(asset_server.load_folder(folder))
+    } else {
+        info!("No {}: {} isn't there", folder, path.display());
+        None
+    }
+}
+
+// The audio file in `folder` called `name`, whatever its extension
+fn find_audio(folder: &LoadedFolder, asset_server: &AssetServer, name: &str) -> Option<Handle<AudioSource>> {
+    folder
+        .handles
+        .iter()
+        .find(|handle| {
+            handle.type_id() == TypeId::of::<AudioSource>()
+                && asset_server.get_path(handle.id()).is_some_and(|path| {
+                    path.path().file_stem().is_some_and(|stem| stem.to_string_lossy() == name)
+                })
+        })
+        .map(|handle| handle.clone().typed::<AudioSource>())
+}
+
+fn choose_music(state: Res<State<GameState>>, mut music: ResMut<Music>) {
+    if !state.is_changed() {
+        return;
+    }
+    if let Some(track) = MusicTrack::for_state(state.get()) {
+        if music.wanted != Some(track) {
+            music.wanted = Some(track);
+        }
+    }
+}
+
+// Waits for the folder to load, then fades out what is playing and starts the track
+// wanted, if there is a file for it
+fn change_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut music: ResMut<Music>,
+    mut players: Query<&mut MusicPlayer>,
+) {
+    if music.wanted == music.playing {
+        return;
+    }
+    let Some(folder) = music.folder.as_ref().and_then(|folder| folders.get(folder)) else {
+        return;
+    };
+    let source = music.wanted.and_then(|track| find_audio(folder, &asset_server, track.name()));
+    if let (Some(track), None) = (music.wanted, &source) {
+        if music.missing.insert(track) {
+            info!("No {} music: there is no {} file in {}", track.name(), track.name(), MUSIC_FOLDER);
+        }
+    }
+
+    for mut player in players.iter_mut() {
+        player.fading_out = true;
+    }
+    if let Some(source) = source {
+        commands.spawn((
+            AudioBundle {
+                source,
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_absolute(0.0)),
+            },
+            MusicPlayer { fading_out: false },
+        ));
+    }
+    music.playing = music.wanted;
+}
+
+// Brings each track toward its volume a little each frame: the music volume for the
+// one playing, nothing for the ones on their way out
+fn fade_music(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    players: Query<(Entity, &MusicPlayer, &AudioSink)>,
+) {
+    let step = time.delta_seconds() / CROSSFADE_SECS;
+    for (entity, player, sink) in players.iter() {
+        let target = if player.fading_out { 0.0 } else { config.music_volume };
+        let volume = sink.volume();
+        let faded = if volume < target { (volume + step).min(target) } else { (volume - step).max(target) };
+        if faded != volume {
+            sink.set_volume(faded);
+        }
+        if player.fading_out && faded <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// - and + for the music and [ and ] for sound effects on the Settings screen, saved to
+// config.toml straight away
+fn adjust_volumes(
+    keyboard_input: Res<Input<KeyCode>>,
+    path: Option<Res<ConfigPath>>,
+    mut config: ResMut<GameConfig>,
+) {
+    let step = |down: &[KeyCode], up: &[KeyCode]| {
+        if keyboard_input.any_just_pressed(down.iter().copied()) {
+            -VOLUME_STEP
+        } else if keyboard_input.any_just_pressed(up.iter().copied()) {
+            VOLUME_STEP
+        } else {
+            0.0
+        }
+    };
+    let music = step(&[KeyCode::Minus, KeyCode::NumpadSubtract], &[KeyCode::Equals, KeyCode::NumpadAdd]);
+    let sfx = step(&[KeyCode::BracketLeft], &[KeyCode::BracketRight]);
+    if music == 0.0 && sfx == 0.0 {
+        return;
+    }
+    // Kept to whole steps, so repeated presses don't drift
+    let stepped = |volume: f32, change: f32| (((volume + change) / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0);
+    config.music_volume = stepped(config.music_volume, music);
+    config.sfx_volume = stepped(config.sfx_volume, sfx);
+    save_settings(&config, path.as_deref());
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut requests: EventReader<PlaySound>,
+    asset_server: Res<AssetServer>,
+    folders: Res<Assets<LoadedFolder>>,
+    sounds: Res<Sounds>,
+    config: Res<GameConfig>,
+) {
+    let folder = sounds.folder.as_ref().and_then(|folder| folders.get(folder));
+    let (Some(folder), true) = (folder, config.sfx_volume > 0.0) else {
+        requests.clear();
+        return;
+    };
+    for PlaySound(sound) in requests.read() {
+        if let Some(source) = find_audio(folder, &asset_server, sound.name()) {
+            commands.spawn(AudioBundle {
+                source,
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_absolute(config.sfx_volume)),
+            });
+        }
+    }
+}
+
+fn combat_sounds(
+    mut attacks: EventReader<AttackEvent>,
+    mut damage: EventReader<DamageEvent>,
+    mut casts: EventReader<CastSpellEvent>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for _ in attacks.read() {
+        sounds.send(PlaySound(Sound::Swing));
+    }
+    for _ in damage.read().filter(|event| event.damage > 0) {
+        sounds.send(PlaySound(Sound::Hit));
+    }
+    for _ in casts.read() {
+        sounds.send(PlaySound(Sound::Spell));
+    }
+}
+
+fn level_up_sounds(mut events: EventReader<GameEvent>, mut sounds: EventWriter<PlaySound>) {
+    for event in events.read() {
+        if let GameEvent::LevelUp { .. } = event {
+            sounds.send(PlaySound(Sound::LevelUp));
+        }
+    }
+}
+
+// A door creaks when the map has more open doors than before; a new map doesn't count
+fn door_sounds(map: Option<Res<DungeonMap>>, mut open: Local<usize>, mut sounds: EventWriter<PlaySound>) {
+    let Some(map) = map.filter(|map| map.is_changed()) else {
+        return;
+    };
+    let now = map.doors.iter().filter(|door| door.state == DoorState::Open).count();
+    if now > *open && !map.is_added() {
+        sounds.send(PlaySound(Sound::Door));
+    }
+    *open = now;
+}
+
+fn footstep_sounds(tokens: Query<Ref<Position>, With<PartyToken>>, mut sounds: EventWriter<PlaySound>) {
+    for position in tokens.iter() {
+        if position.is_changed() && !position.is_added() {
+            sounds.send(PlaySound(Sound::Footstep));
+        }
+    }
+}
+
+fn click_sounds(buttons: Query<&Interaction, (Changed<Interaction>, With<Button>)>, mut sounds: EventWriter<PlaySound>) {
+    if buttons.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        sounds.send(PlaySound(Sound::Click));
+    }
+}
+
+fn encounter_sounds(state: Res<State<GameState>>, mut sounds: EventWriter<PlaySound>) {
+    if state.is_changed() && *state.get() == GameState::Combat {
+        sounds.send(PlaySound(Sound::Encounter));
+    }
+}
@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::combat::GridPosition;
+use crate::dungeon::{FogOfWar, PartyToken, Position};
+use crate::GameState;
+
+// The automap. Tab while exploring opens a full-screen map of everything the party has
+// seen of the dungeon: rooms, passages, doors and stairs, with the party marked '@'.
+// WASD or the arrow keys move a cursor (Shift for five squares at a time) and the map
+// scrolls to keep it in the middle; + and - zoom. N puts a numbered note on the square
+// under the cursor and Delete takes it off again; `note <text>` in the debug console
+// writes one where the party stands. Notes are kept with the exploration memory, so
+// they're saved with the game.
+
+// Font size and the squares that fit across and down at each zoom level, widest first
+pub const ZOOM_LEVELS: [(f32, i32, i32); 3] = [(10.0, 110, 46), (14.0, 78, 33), (20.0, 54, 23)];
+
+#[derive(Resource, Debug)]
+pub struct AutomapView {
+    pub cursor: GridPosition,
+    pub zoom: usize,
+}
+
+pub struct AutomapPlugin;
+
+impl Plugin for AutomapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutomapView>()
+            .add_systems(OnEnter(GameState::Map), center_on_party)
+            .add_systems(Update, automap_keys.run_if(in_state(GameState::Map)));
+    }
+}
+
+impl Default for AutomapView {
+    fn default() -> Self {
+        Self {
+            cursor: GridPosition::new(0, 0),
+            zoom: 1,
+        }
+    }
+}
+
+impl AutomapView {
+    pub fn font_size(&self) -> f32 {
+        ZOOM_LEVELS[self.zoom].0
+    }
+
+    // Columns and rows on screen
+    pub fn size(&self) -> (i32, i32) {
+        let (_, columns, rows) = ZOOM_LEVELS[self.zoom];
+        (columns, rows)
+    }
+
+    // The square in the top left corner, with the cursor in the middle of the screen
+    pub fn top_left(&self) -> GridPosition {
+        let (columns, rows) = self.size();
+        self.cursor.offset(-columns / 2, -rows / 2)
+    }
+}
+
+fn center_on_party(tokens: Query<&Position, With<PartyToken>>, mut view: ResMut<AutomapView>) {
+    if let Ok(position) = tokens.get_single() {
+        view.cursor = position.0;
+    }
+}
+
+fn automap_keys(keyboard_input: Res<Input<KeyCode>>, mut view: ResMut<AutomapView>, mut fog: ResMut<FogOfWar>) {
+    let pressed = |keys: [KeyCode; 2]| keyboard_input.any_just_pressed(keys) as i32;
+    let dx = pressed([KeyCode::D, KeyCode::Right]) - pressed([KeyCode::A, KeyCode::Left]);
+    let dy = pressed([KeyCode::S, KeyCode::Down]) - pressed([KeyCode::W, KeyCode::Up]);
+    if dx != 0 || dy != 0 {
+        let step = if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) { 5 } else { 1 };
+        view.cursor = view.cursor.offset(dx * step, dy * step);
+    }
+
+    if keyboard_input.any_just_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        view.zoom = (view.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+    } else if keyboard_input.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        view.zoom = view.zoom.saturating_sub(1);
+    }
+
+    let cursor = view.cursor;
+    if keyboard_input.just_pressed(KeyCode::N) && fog.annotation_at(&cursor).is_none() {
+        fog.annotate(cursor, "Note".to_string());
+    } else if keyboard_input.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        fog.remove_annotation(&cursor);
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{save_settings, ConfigPath, KeyBindings};
+use crate::dungeon::{CurrentRoom, DungeonMap, PartyToken, TILE_SIZE};
+use crate::{GameConfig, GameState};
+
+// The camera over the dungeon. It either follows the party, catching up with each step,
+// or holds the room the party is in in the middle of the screen and only moves when
+// they walk into another, the way the old dungeon crawls did; in the passages, and in
+// any room too big to fit on screen, it follows the party either way. C while exploring
+// switches between the two, and the choice is kept in config.toml (`camera` under
+// `[window]`). = and - zoom in and out.
+
+// How quickly the camera catches up with where it's going, per second
+const CAMERA_FOLLOW_RATE: f32 = 8.0;
+// Screen pixels to a world pixel at each zoom level
+pub const ZOOM_LEVELS: [f32; 5] = [0.5, 1.0, 1.5, 2.0, 3.0];
+const DEFAULT_ZOOM: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    #[default]
+    Follow,
+    Room,
+}
+
+impl CameraMode {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CameraMode::Follow => "follows the party",
+            CameraMode::Room => "centres each room",
+        }
+    }
+}
+
+// The zoom level, for the session only
+#[derive(Resource, Debug)]
+pub struct DungeonCamera {
+    pub zoom: usize,
+}
+
+impl Default for DungeonCamera {
+    fn default() -> Self {
+        Self { zoom: DEFAULT_ZOOM }
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DungeonCamera>()
+            .add_systems(Update, (
+                camera_keys.run_if(in_state(GameState::InGame)),
+                zoom_camera,
+                aim_camera,
+            ).chain());
+    }
+}
+
+fn camera_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    path: Option<Res<ConfigPath>>,
+    mut config: ResMut<GameConfig>,
+    mut camera: ResMut<DungeonCamera>,
+) {
+    if keyboard_input.just_pressed(keys.camera) {
+        config.camera = match config.camera {
+            CameraMode::Follow => CameraMode::Room,
+            CameraMode::Room => CameraMode::Follow,
+        };
+        info!("The camera {}", config.camera.describe());
+        save_settings(&config, path.as_deref());
+    }
+    if keyboard_input.any_just_pressed([keys.zoom_in, KeyCode::NumpadAdd]) {
+        camera.zoom = (camera.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+    } else if keyboard_input.any_just_pressed([keys.zoom_out, KeyCode::NumpadSubtract]) {
+        camera.zoom = camera.zoom.saturating_sub(1);
+    }
+}
+
+fn zoom_camera(camera: Res<DungeonCamera>, mut projections: Query<&mut OrthographicProjection, With<Camera2d>>) {
+    if !camera.is_changed() {
+        return;
+    }
+    let scale = 1.0 / ZOOM_LEVELS[camera.zoom.min(ZOOM_LEVELS.len() - 1)];
+    for mut projection in projections.iter_mut() {
+        projection.scale = scale;
+    }
+}
+
+// The middle of the party's room if the camera centres rooms and the room fits on
+// screen, and otherwise the party
+fn camera_target(
+    config: &GameConfig,
+    party: Vec2,
+    room: Option<u32>,
+    map: Option<&DungeonMap>,
+    view: Option<Vec2>,
+) -> Vec2 {
+    if config.camera != CameraMode::Room {
+        return party;
+    }
+    let Some(area) = room.zip(map).and_then(|(room, map)| map.room_area(room)) else {
+        return party;
+    };
+    let size = Vec2::new(area.width as f32, area.height as f32) * TILE_SIZE;
+    if view.is_some_and(|view| size.x > view.x || size.y > view.y) {
+        return party;
+    }
+    // Tile centres run from the first tile's to the last's
+    let first = Vec2::new(area.x as f32, -(area.y as f32)) * TILE_SIZE;
+    first + Vec2::new(size.x - TILE_SIZE, -(size.y - TILE_SIZE)) / 2.0
+}
+
+fn aim_camera(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    current_room: Res<CurrentRoom>,
+    map: Option<Res<DungeonMap>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    tokens: Query<&Transform, (With<PartyToken>, Without<Camera2d>)>,
+    mut cameras: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let Ok(token) = tokens.get_single() else {
+        return;
+    };
+    let catch_up = (CAMERA_FOLLOW_RATE * time.delta_seconds()).min(1.0);
+    for (mut camera, projection) in cameras.iter_mut() {
+        let window = windows.get_single().ok();
+        let view = window.map(|window| Vec2::new(window.width(), window.height()) * projection.scale);
+        let target = camera_target(&config, token.translation.truncate(), current_room.0, map.as_deref(), view);
+        let moved = camera.translation.truncate().lerp(target, catch_up);
+        camera.translation = moved.extend(camera.translation.z);
+    }
+}
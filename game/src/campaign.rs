@@ -0,0 +1,496 @@
+use bevy::prelude::*;
+use bevy::tasks::Task;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::combat::Combatant;
+use crate::config::KeyBindings;
+use crate::journal::SessionJournal;
+use crate::tasks::{poll_task, AsyncRuntime};
+use crate::GameState;
+
+// Play-by-post campaigns. The shared campaign state lives on the AI service; each
+// client polls for diffs since the last revision it saw, records changes to the party
+// while it is that player's turn, and submits them as one diff when the turn ends.
+//
+// Every party member carries an id of its own (CampaignMember), which is how the shared
+// party tells members apart, whatever their names. Joining brings the shared party in
+// over the local one, and local members it doesn't have yet go out with the first turn.
+
+pub const DEFAULT_POLL_INTERVAL_SECS: f32 = 15.0;
+
+#[derive(Debug, Clone)]
+pub struct CampaignConfig {
+    pub server_url: String,
+    pub campaign_id: String,
+    pub player: String,
+    pub poll_interval_secs: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CampaignState {
+    pub campaign_id: String,
+    pub revision: u64,
+    pub players: Vec<String>,
+    pub active_player: Option<String>,
+    pub party: Vec<PartyMember>,
+    pub log: Vec<String>,
+}
+
+// A member of the shared party: the character with its campaign id alongside
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyMember {
+    pub id: String,
+    #[serde(flatten)]
+    pub character: Character,
+}
+
+// A party member's id in the campaign, the same on every player's machine
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct CampaignMember(pub String);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CampaignChange {
+    UpdateCharacter { id: String, character: Box<Character> },
+    AddLogEntry { text: String },
+    PassTurn { next_player: String },
+    PlayerJoined { player: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignDiff {
+    pub revision: u64,
+    pub author: String,
+    pub changes: Vec<CampaignChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct JoinRequest<'a> {
+    player: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct TurnSubmission {
+    player: String,
+    base_revision: u64,
+    changes: Vec<CampaignChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffsResponse {
+    diffs: Vec<CampaignDiff>,
+}
+
+enum SyncReply {
+    Joined(CampaignState),
+    Diffs(Vec<CampaignDiff>),
+    Submitted(CampaignDiff),
+    Rejected(String),
+}
+
+type SyncTask = Task<Option<Result<SyncReply, String>>>;
+
+#[derive(Resource)]
+pub struct CampaignSession {
+    pub config: CampaignConfig,
+    pub state: CampaignState,
+    pub pending: Vec<CampaignChange>,
+    client: Client,
+    poll_timer: Timer,
+    request: Option<SyncTask>,
+    submitted: usize,
+    joined: bool,
+    submit_requested: bool,
+    was_my_turn: bool,
+}
+
+#[derive(Event)]
+pub struct CampaignTurnStarted {
+    pub campaign_id: String,
+}
+
+#[derive(Event)]
+pub struct EndCampaignTurn;
+
+#[derive(Event)]
+pub struct CampaignSyncFailed {
+    pub message: String,
+}
+
+pub struct CampaignPlugin {
+    pub config: Option<CampaignConfig>,
+}
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CampaignTurnStarted>()
+            .add_event::<EndCampaignTurn>()
+            .add_event::<CampaignSyncFailed>();
+
+        let Some(config) = self.config.clone() else {
+            return;
+        };
+
+        app.insert_resource(CampaignSession::new(config))
+            .add_systems(Update, (
+                assign_member_ids,
+                apply_deferred,
+                record_local_changes,
+                share_journal_entries,
+                end_turn_on_key.run_if(in_state(GameState::InGame)),
+                request_campaign_sync,
+                receive_campaign_sync,
+            ).chain());
+    }
+}
+
+impl CampaignConfig {
+    // Playing as `player` in `campaign_id` on the service at `server_url`
+    pub fn new(server_url: &str, campaign_id: String, player: String) -> Self {
+        Self {
+            server_url: server_url.trim_end_matches('/').to_string(),
+            campaign_id,
+            player,
+            poll_interval_secs: DEFAULT_POLL_INTERVAL_SECS,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/campaign/{}/{}", self.server_url, self.campaign_id, path)
+    }
+}
+
+impl CampaignState {
+    pub fn apply(&mut self, diff: &CampaignDiff) {
+        if diff.revision <= self.revision {
+            return;
+        }
+
+        for change in &diff.changes {
+            match change {
+                CampaignChange::UpdateCharacter { id, character } => {
+                    let character = (**character).clone();
+                    match self.party.iter_mut().find(|member| member.id == *id) {
+                        Some(member) => member.character = character,
+                        None => self.party.push(PartyMember { id: id.clone(), character }),
+                    }
+                }
+                CampaignChange::AddLogEntry { text } => {
+                    self.log.push(format!("{}: {}", diff.author, text));
+                }
+                CampaignChange::PassTurn { next_player } => {
+                    self.active_player = Some(next_player.clone());
+                }
+                CampaignChange::PlayerJoined { player } => {
+                    if !self.players.contains(player) {
+                        self.players.push(player.clone());
+                    }
+                    if self.active_player.is_none() {
+                        self.active_player = Some(player.clone());
+                    }
+                }
+            }
+        }
+        self.revision = diff.revision;
+    }
+
+    pub fn is_turn_of(&self, player: &str) -> bool {
+        self.active_player.as_deref() == Some(player)
+    }
+
+    // Turn order is the order players joined; a lone player keeps the turn
+    pub fn next_player_after(&self, player: &str) -> Option<String> {
+        let index = self.players.iter().position(|p| p == player)?;
+        self.players.get((index + 1) % self.players.len()).cloned()
+    }
+}
+
+impl CampaignSession {
+    pub fn new(config: CampaignConfig) -> Self {
+        let poll_timer = Timer::from_seconds(config.poll_interval_secs, TimerMode::Repeating);
+        Self {
+            state: CampaignState {
+                campaign_id: config.campaign_id.clone(),
+                ..default()
+            },
+            config,
+            pending: Vec::new(),
+            client: Client::new(),
+            poll_timer,
+            request: None,
+            submitted: 0,
+            joined: false,
+            submit_requested: false,
+            was_my_turn: false,
+        }
+    }
+
+    pub fn is_my_turn(&self) -> bool {
+        self.state.is_turn_of(&self.config.player)
+    }
+
+    // Queue a journal line to go out with this turn's diff
+    pub fn add_log_entry(&mut self, text: impl Into<String>) {
+        self.pending.push(CampaignChange::AddLogEntry { text: text.into() });
+    }
+
+    // Only the latest snapshot of each member needs to be sent
+    fn queue_update(&mut self, id: &str, character: &Character) {
+        self.pending
+            .retain(|change| !matches!(change, CampaignChange::UpdateCharacter { id: queued, .. } if queued == id));
+        self.pending.push(CampaignChange::UpdateCharacter {
+            id: id.to_string(),
+            character: Box::new(character.clone()),
+        });
+    }
+}
+
+// Party members made or hired here get an id the first frame they're about
+fn assign_member_ids(mut commands: Commands, party: Query<(Entity, &Combatant), Without<CampaignMember>>) {
+    for (entity, _) in party.iter().filter(|(_, combatant)| combatant.is_player) {
+        commands.entity(entity).insert(CampaignMember(uuid::Uuid::new_v4().to_string()));
+    }
+}
+
+// Monsters aren't part of the shared party, so only members with an id are recorded
+fn record_local_changes(
+    mut session: ResMut<CampaignSession>,
+    changed: Query<(&Character, &CampaignMember), Changed<Character>>,
+) {
+    if !session.joined || !session.is_my_turn() {
+        return;
+    }
+
+    for (character, member) in changed.iter() {
+        session.queue_update(&member.0, character);
+    }
+}
+
+// What the journal records during the player's turn goes into the campaign log with it
+fn share_journal_entries(
+    mut session: ResMut<CampaignSession>,
+    journal: Res<SessionJournal>,
+    mut shared: Local<usize>,
+) {
+    if session.joined && session.is_my_turn() {
+        for entry in journal.entries.iter().skip(*shared) {
+            session.add_log_entry(entry.text.clone());
+        }
+    }
+    *shared = journal.entries.len();
+}
+
+// Brings a member of the shared party in over the local one with its id, or into the
+// party if there isn't one. The write bypasses change detection, or record_local_changes
+// would take it for a change of ours and send the other player's snapshot back.
+fn update_member(
+    commands: &mut Commands,
+    party: &mut Query<(&mut Character, &CampaignMember)>,
+    id: &str,
+    remote: &Character,
+) {
+    match party.iter_mut().find(|(_, member)| member.0 == id) {
+        Some((mut character, _)) => *character.bypass_change_detection() = remote.clone(),
+        None => {
+            commands.spawn((
+                remote.clone(),
+                Combatant {
+                    initiative: 0,
+                    is_player: true,
+                    actions_remaining: 1,
+                    status_effects: Vec::new(),
+                },
+                CampaignMember(id.to_string()),
+            ));
+        }
+    }
+}
+
+fn end_turn_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut end_turn: EventWriter<EndCampaignTurn>,
+) {
+    if keyboard_input.just_pressed(keys.end_turn) {
+        end_turn.send(EndCampaignTurn);
+    }
+}
+
+fn request_campaign_sync(
+    mut session: ResMut<CampaignSession>,
+    mut end_turn: EventReader<EndCampaignTurn>,
+    runtime: Res<AsyncRuntime>,
+    time: Res<Time>,
+) {
+    if end_turn.read().next().is_some() {
+        session.submit_requested = true;
+    }
+    let poll_due = session.poll_timer.tick(time.delta()).just_finished();
+
+    if session.request.is_some() {
+        return;
+    }
+
+    let client = session.client.clone();
+    let player = session.config.player.clone();
+
+    let task = if !session.joined {
+        let url = session.config.url("join");
+        runtime.spawn(async move { join_campaign(client, url, player).await })
+    } else if session.submit_requested && session.is_my_turn() {
+        session.submit_requested = false;
+
+        // Pending changes stay queued until the server accepts them
+        let mut changes = session.pending.clone();
+        session.submitted = changes.len();
+        let next_player = session.state.next_player_after(&player).unwrap_or_else(|| player.clone());
+        changes.push(CampaignChange::PassTurn { next_player });
+
+        let submission = TurnSubmission {
+            player,
+            base_revision: session.state.revision,
+            changes,
+        };
+        let url = session.config.url("turn");
+        runtime.spawn(async move { submit_turn(client, url, submission).await })
+    } else if poll_due {
+        let url = format!("{}?since={}", session.config.url("diffs"), session.state.revision);
+        runtime.spawn(async move { fetch_diffs(client, url).await })
+    } else {
+        return;
+    };
+
+    session.request = Some(task);
+}
+
+fn receive_campaign_sync(
+    mut commands: Commands,
+    mut session: ResMut<CampaignSession>,
+    mut party: Query<(&mut Character, &CampaignMember)>,
+    mut turn_started: EventWriter<CampaignTurnStarted>,
+    mut sync_failed: EventWriter<CampaignSyncFailed>,
+) {
+    let Some(task) = session.request.as_mut() else {
+        return;
+    };
+    let Some(result) = poll_task(task) else {
+        return;
+    };
+    session.request = None;
+
+    let reply = match result {
+        Some(Ok(reply)) => reply,
+        Some(Err(message)) => {
+            warn!("Campaign sync failed: {}", message);
+            sync_failed.send(CampaignSyncFailed { message });
+            return;
+        }
+        None => {
+            sync_failed.send(CampaignSyncFailed {
+                message: "campaign sync task panicked".to_string(),
+            });
+            return;
+        }
+    };
+
+    let player = session.config.player.clone();
+    let remote_diffs = match reply {
+        SyncReply::Joined(state) => {
+            for member in &state.party {
+                update_member(&mut commands, &mut party, &member.id, &member.character);
+            }
+            for (character, member) in party.iter() {
+                if !state.party.iter().any(|shared| shared.id == member.0) {
+                    session.queue_update(&member.0, character);
+                }
+            }
+            session.state = state;
+            session.joined = true;
+            info!(
+                "Joined campaign {} at revision {} as {}",
+                session.state.campaign_id, session.state.revision, player
+            );
+            Vec::new()
+        }
+        SyncReply::Diffs(diffs) => diffs,
+        SyncReply::Submitted(diff) => {
+            let submitted = session.submitted.min(session.pending.len());
+            session.pending.drain(..submitted);
+            session.state.apply(&diff);
+            Vec::new()
+        }
+        SyncReply::Rejected(message) => {
+            // Somebody else's diff landed first; catch up and let the player end the turn again
+            warn!("Campaign turn rejected: {}", message);
+            session.poll_timer.reset();
+            sync_failed.send(CampaignSyncFailed { message });
+            return;
+        }
+    };
+
+    for diff in remote_diffs.iter().filter(|diff| diff.author != player) {
+        session.state.apply(diff);
+        for change in &diff.changes {
+            if let CampaignChange::UpdateCharacter { id, character } = change {
+                update_member(&mut commands, &mut party, id, character);
+            }
+        }
+    }
+
+    let my_turn = session.is_my_turn();
+    if my_turn && !session.was_my_turn {
+        turn_started.send(CampaignTurnStarted {
+            campaign_id: session.state.campaign_id.clone(),
+        });
+    }
+    session.was_my_turn = my_turn;
+}
+
+async fn join_campaign(client: Client, url: String, player: String) -> Result<SyncReply, String> {
+    let response = client
+        .post(url)
+        .json(&JoinRequest { player: &player })
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let state = response
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .json::<CampaignState>()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(SyncReply::Joined(state))
+}
+
+async fn fetch_diffs(client: Client, url: String) -> Result<SyncReply, String> {
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let diffs = response
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .json::<DiffsResponse>()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(SyncReply::Diffs(diffs.diffs))
+}
+
+async fn submit_turn(client: Client, url: String, submission: TurnSubmission) -> Result<SyncReply, String> {
+    let response = client.post(url).json(&submission).send().await.map_err(|e| e.to_string())?;
+
+    match response.status() {
+        StatusCode::CONFLICT | StatusCode::FORBIDDEN => {
+            let message = response.text().await.unwrap_or_default();
+            Ok(SyncReply::Rejected(message))
+        }
+        _ => {
+            let diff = response
+                .error_for_status()
+                .map_err(|e| e.to_string())?
+                .json::<CampaignDiff>()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(SyncReply::Submitted(diff))
+        }
+    }
+}
@@ -1,357 +1,272 @@
 use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
-use rand::Rng;
-
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-pub struct Character {
-    pub name: String,
-    pub class: CharacterClass,
-    pub level: u8,
-    pub experience: u32,
+use rand::seq::SliceRandom;
+
+use crate::combat::Combatant;
+use crate::config::KeyBindings;
+use crate::data::SpellCompendium;
+use crate::rng::GameRng;
+use crate::text_input::{CharFilter, FieldAction, TextField, Typing};
+use crate::GameState;
+
+// The character and item rules live in old_school_core; re-exported so the rest of the
+// game can keep using `crate::character::*`
+pub use old_school_core::character::*;
+pub use old_school_core::item::*;
+pub use old_school_core::skills::*;
+
+// The character being rolled up on the creation screen. R rerolls the scores, 1-7 picks
+// a class (only those whose minimums the scores meet) and Enter asks for a name; Enter
+// again brings the character into the world, and ESC goes back to the scores.
+#[derive(Resource)]
+pub struct CharacterDraft {
     pub stats: CharacterStats,
-    pub hit_points: HitPoints,
-    pub armor_class: i8,
-    pub equipment: Equipment,
-    pub inventory: Inventory,
-    pub spells: Vec<Spell>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum CharacterClass {
-    Fighter,
-    MagicUser,
-    Cleric,
-    Thief,
-    Dwarf,
-    Elf,
-    Halfling,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CharacterStats {
-    pub strength: u8,
-    pub dexterity: u8,
-    pub constitution: u8,
-    pub intelligence: u8,
-    pub wisdom: u8,
-    pub charisma: u8,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HitPoints {
-    pub current: i16,
-    pub maximum: i16,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Equipment {
-    pub weapon: Option<Item>,
-    pub armor: Option<Item>,
-    pub shield: Option<Item>,
-    pub helmet: Option<Item>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Inventory {
-    pub items: Vec<Item>,
-    pub gold: u32,
-    pub weight_capacity: f32,
-    pub current_weight: f32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Item {
-    pub name: String,
-    pub item_type: ItemType,
-    pub weight: f32,
-    pub value: u32,
-    pub properties: ItemProperties,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ItemType {
-    Weapon(WeaponType),
-    Armor(ArmorType),
-    Shield,
-    Helmet,
-    Potion,
-    Scroll,
-    Treasure,
-    Misc,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum WeaponType {
-    Sword,
-    Axe,
-    Mace,
-    Bow,
-    Crossbow,
-    Staff,
-    Dagger,
+    pub class: Option<CharacterClass>,
+    // Some while the name is being typed
+    pub name: Option<TextField>,
+    pub message: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ArmorType {
-    Leather,
-    Chain,
-    Plate,
-    Robes,
+// A night's rest: spellcasters get their spells back
+#[derive(Event)]
+pub struct RestEvent;
+
+// Ask for a thief skill roll. Exploration and trap systems send these and read the
+// matching SkillCheckResult; `modifier` is added to the percentage (negative for
+// harder locks and traps).
+#[derive(Event, Clone, Debug)]
+pub struct SkillCheckEvent {
+    pub character: Entity,
+    pub skill: ThiefSkill,
+    pub modifier: i16,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ItemProperties {
-    pub damage: Option<String>, // e.g., "1d6", "1d8+1"
-    pub armor_bonus: Option<i8>,
-    pub magic_bonus: Option<i8>,
-    pub effects: Vec<String>,
+#[derive(Event, Clone, Debug)]
+pub struct SkillCheckResult {
+    pub character: Entity,
+    pub check: SkillCheck,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Spell {
-    pub name: String,
-    pub level: u8,
-    pub school: SpellSchool,
-    pub casting_time: String,
-    pub range: String,
-    pub duration: String,
-    pub description: String,
-}
+pub struct CharacterPlugin;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum SpellSchool {
-    Abjuration,
-    Conjuration,
-    Divination,
-    Enchantment,
-    Evocation,
-    Illusion,
-    Necromancy,
-    Transmutation,
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RestEvent>()
+            .add_event::<SkillCheckEvent>()
+            .add_event::<SkillCheckResult>()
+            .add_systems(OnEnter(GameState::CharacterCreation), roll_character_draft)
+            .add_systems(Update, (
+                update_character_ui,
+                handle_character_actions,
+                handle_character_creation_input.run_if(in_state(GameState::CharacterCreation)),
+                rest_on_key.run_if(in_state(GameState::InGame)),
+                handle_rest,
+                resolve_skill_checks,
+                sync_armor_class,
+            ));
+    }
 }
 
-impl Character {
-    pub fn new(name: String, class: CharacterClass) -> Self {
-        let stats = CharacterStats::roll();
-        let level = 1;
-        let hit_points = HitPoints::new(&class, &stats, level);
-        let armor_class = Self::calculate_armor_class(&stats);
-        
+impl CharacterDraft {
+    pub fn roll(rng: &mut GameRng) -> Self {
         Self {
-            name,
-            class,
-            level,
-            experience: 0,
-            stats,
-            hit_points,
-            armor_class,
-            equipment: Equipment::default(),
-            inventory: Inventory::default(),
-            spells: Vec::new(),
-        }
-    }
-
-    pub fn calculate_armor_class(stats: &CharacterStats) -> i8 {
-        let dex_modifier = Self::get_dexterity_modifier(stats.dexterity);
-        10 + dex_modifier
-    }
-
-    pub fn get_dexterity_modifier(dexterity: u8) -> i8 {
-        match dexterity {
-            3 => -3,
-            4..=5 => -2,
-            6..=8 => -1,
-            9..=12 => 0,
-            13..=15 => 1,
-            16..=17 => 2,
-            18 => 3,
-            _ => 0,
+            stats: CharacterStats::roll_with(rng),
+            class: None,
+            name: None,
+            message: None,
         }
     }
 
-    pub fn get_strength_modifier(strength: u8) -> i8 {
-        match strength {
-            3 => -3,
-            4..=5 => -2,
-            6..=8 => -1,
-            9..=12 => 0,
-            13..=15 => 1,
-            16..=17 => 2,
-            18 => 3,
-            _ => 0,
+    pub fn select(&mut self, class: CharacterClass) {
+        let unmet = class.unmet_requirements(&self.stats);
+        if unmet.is_empty() {
+            self.message = None;
+            self.class = Some(class);
+        } else {
+            let needs: Vec<String> = unmet
+                .iter()
+                .map(|(ability, minimum)| format!("{} {}", ability.abbreviation(), minimum))
+                .collect();
+            self.message = Some(format!("A {} needs {}", class.name(), needs.join(" and ")));
         }
     }
+}
 
-    pub fn gain_experience(&mut self, xp: u32) {
-        self.experience += xp;
-        self.check_level_up();
-    }
+fn roll_character_draft(mut commands: Commands, mut rng: ResMut<GameRng>) {
+    commands.insert_resource(CharacterDraft::roll(rng.as_mut()));
+}
 
-    pub fn check_level_up(&mut self) {
-        let xp_needed = self.get_xp_for_next_level();
-        if self.experience >= xp_needed {
-            self.level_up();
+#[allow(clippy::too_many_arguments)]
+fn handle_character_creation_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut typing: ResMut<Typing>,
+    draft: Option<ResMut<CharacterDraft>>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+    compendium: Res<SpellCompendium>,
+) {
+    let Some(mut draft) = draft else {
+        return;
+    };
+
+    if let Some(name) = draft.name.as_mut() {
+        match name.edit(&mut typing) {
+            Some(FieldAction::Cancel) => draft.name = None,
+            Some(FieldAction::Submit) if name.is_blank() => {
+                draft.message = Some("Every adventurer needs a name".to_string());
+            }
+            Some(FieldAction::Submit) => {
+                let name = name.text().trim().to_string();
+                enter_world(&mut commands, &draft, name, &compendium, rng.as_mut());
+                next_state.set(GameState::Town);
+            }
+            None => {}
         }
+        return;
     }
 
-    pub fn level_up(&mut self) {
-        self.level += 1;
-        let new_hp = self.calculate_hit_points();
-        self.hit_points.maximum += new_hp;
-        self.hit_points.current += new_hp;
-        
-        // TODO: Add spell learning for spellcasters
-    }
-
-    pub fn get_xp_for_next_level(&self) -> u32 {
-        match self.class {
-            CharacterClass::Fighter => self.level as u32 * 2000,
-            CharacterClass::MagicUser => self.level as u32 * 2500,
-            CharacterClass::Cleric => self.level as u32 * 1500,
-            CharacterClass::Thief => self.level as u32 * 1200,
-            CharacterClass::Dwarf => self.level as u32 * 2200,
-            CharacterClass::Elf => self.level as u32 * 4000,
-            CharacterClass::Halfling => self.level as u32 * 2000,
+    const CLASS_KEYS: [KeyCode; 7] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+    ];
+    for (key, class) in CLASS_KEYS.into_iter().zip(CharacterClass::ALL) {
+        if keyboard_input.just_pressed(key) {
+            draft.select(class);
         }
     }
 
-    pub fn calculate_hit_points(&self) -> i16 {
-        let base_hp = match self.class {
-            CharacterClass::Fighter => 10,
-            CharacterClass::MagicUser => 4,
-            CharacterClass::Cleric => 8,
-            CharacterClass::Thief => 6,
-            CharacterClass::Dwarf => 8,
-            CharacterClass::Elf => 6,
-            CharacterClass::Halfling => 6,
-        };
-        
-        let con_modifier = Self::get_constitution_modifier(self.stats.constitution);
-        (base_hp as i16 + con_modifier).max(1)
-    }
-
-    pub fn get_constitution_modifier(constitution: u8) -> i16 {
-        match constitution {
-            3 => -2,
-            4..=5 => -1,
-            6..=8 => -1,
-            9..=12 => 0,
-            13..=15 => 1,
-            16..=17 => 2,
-            18 => 2,
-            _ => 0,
+    if keyboard_input.just_pressed(KeyCode::R) {
+        *draft = CharacterDraft::roll(rng.as_mut());
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        if draft.class.is_none() {
+            draft.message = Some("Choose a class first".to_string());
+            return;
         }
+        draft.message = None;
+        draft.name = Some(TextField::new(CharFilter::Name));
     }
+}
 
-    pub fn is_alive(&self) -> bool {
-        self.hit_points.current > 0
-    }
+fn enter_world(
+    commands: &mut Commands,
+    draft: &CharacterDraft,
+    name: String,
+    compendium: &SpellCompendium,
+    rng: &mut GameRng,
+) {
+    let Some(class) = draft.class.clone() else {
+        return;
+    };
+    let mut character = Character::with_stats(name, class, draft.stats.clone());
+    learn_starting_spells(&mut character, compendium, rng);
+    character.rest_and_memorize();
+    info!("{} the {} enters the world", character.name, character.class.name());
+    commands.spawn((
+        character,
+        Combatant {
+            initiative: 0,
+            is_player: true,
+            actions_remaining: 1,
+            status_effects: Vec::new(),
+        },
+    ));
+}
 
-    pub fn take_damage(&mut self, damage: i16) {
-        self.hit_points.current -= damage;
-        if self.hit_points.current < 0 {
-            self.hit_points.current = 0;
+// B/X: a magic-user or elf starts with one 1st level spell in the book; a cleric can
+// call on every prayer of the levels they can cast
+fn learn_starting_spells(character: &mut Character, compendium: &SpellCompendium, rng: &mut GameRng) {
+    let available: Vec<&Spell> = compendium
+        .of_level(1)
+        .filter(|spell| spell.castable_by(&character.class))
+        .collect();
+
+    match character.class.spell_caster() {
+        Some(SpellCaster::Arcane) => {
+            if let Some(&spell) = available.choose(rng) {
+                character.spells.push(spell.clone());
+            }
         }
+        Some(SpellCaster::Divine) => character.spells.extend(available.into_iter().cloned()),
+        None => {}
     }
+}
 
-    pub fn heal(&mut self, amount: i16) {
-        self.hit_points.current += amount;
-        if self.hit_points.current > self.hit_points.maximum {
-            self.hit_points.current = self.hit_points.maximum;
-        }
+fn rest_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut rest_events: EventWriter<RestEvent>,
+) {
+    if keyboard_input.just_pressed(keys.rest) {
+        rest_events.send(RestEvent);
     }
 }
 
-impl CharacterStats {
-    pub fn roll() -> Self {
-        let mut rng = rand::thread_rng();
-        
-        Self {
-            strength: Self::roll_ability_score(&mut rng),
-            dexterity: Self::roll_ability_score(&mut rng),
-            constitution: Self::roll_ability_score(&mut rng),
-            intelligence: Self::roll_ability_score(&mut rng),
-            wisdom: Self::roll_ability_score(&mut rng),
-            charisma: Self::roll_ability_score(&mut rng),
-        }
+fn handle_rest(mut rest_events: EventReader<RestEvent>, mut characters: Query<(&mut Character, &Combatant)>) {
+    if rest_events.read().last().is_none() {
+        return;
     }
 
-    fn roll_ability_score(rng: &mut rand::rngs::ThreadRng) -> u8 {
-        // Roll 4d6, drop lowest
-        let mut rolls = vec![
-            rng.gen_range(1..=6),
-            rng.gen_range(1..=6),
-            rng.gen_range(1..=6),
-            rng.gen_range(1..=6),
-        ];
-        rolls.sort();
-        rolls[1..].iter().sum()
+    for (mut character, combatant) in characters.iter_mut() {
+        if !combatant.is_player || character.class.spell_caster().is_none() {
+            continue;
+        }
+        character.rest_and_memorize();
+        let memorized: Vec<&str> = character.memorized_spells.iter().map(|spell| spell.name.as_str()).collect();
+        info!("{} rests and memorizes: {}", character.name, memorized.join(", "));
     }
 }
 
-impl HitPoints {
-    pub fn new(class: &CharacterClass, stats: &CharacterStats, level: u8) -> Self {
-        let base_hp = match class {
-            CharacterClass::Fighter => 10,
-            CharacterClass::MagicUser => 4,
-            CharacterClass::Cleric => 8,
-            CharacterClass::Thief => 6,
-            CharacterClass::Dwarf => 8,
-            CharacterClass::Elf => 6,
-            CharacterClass::Halfling => 6,
+fn resolve_skill_checks(
+    mut checks: EventReader<SkillCheckEvent>,
+    mut results: EventWriter<SkillCheckResult>,
+    characters: Query<&Character>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in checks.read() {
+        let Ok(character) = characters.get(event.character) else {
+            continue;
         };
-        
-        let con_modifier = Character::get_constitution_modifier(stats.constitution);
-        let max_hp = (base_hp as i16 + con_modifier).max(1);
-        
-        Self {
-            current: max_hp,
-            maximum: max_hp,
-        }
+        let check = character.check_skill(event.skill, event.modifier, rng.as_mut());
+        info!(
+            "{} tries {}: rolled {} against {}% ({})",
+            character.name,
+            event.skill.name(),
+            check.roll,
+            check.chance,
+            if check.success { "success" } else { "failure" }
+        );
+        results.send(SkillCheckResult { character: event.character, check });
     }
 }
 
-impl Default for Equipment {
-    fn default() -> Self {
-        Self {
-            weapon: None,
-            armor: None,
-            shield: None,
-            helmet: None,
+// Armour class follows what a party member is wearing. It's only written back when it
+// differs, so the write doesn't set off this system again. Monsters keep the AC from
+// their stat block.
+fn sync_armor_class(mut characters: Query<(&mut Character, Option<&Combatant>), Changed<Character>>) {
+    for (mut character, combatant) in characters.iter_mut() {
+        if combatant.is_some_and(|combatant| !combatant.is_player) {
+            continue;
         }
-    }
-}
-
-impl Default for Inventory {
-    fn default() -> Self {
-        Self {
-            items: Vec::new(),
-            gold: 0,
-            weight_capacity: 50.0, // Base capacity
-            current_weight: 0.0,
+        let armor_class = Character::calculate_armor_class(&character.stats, &character.equipment);
+        if character.armor_class != armor_class {
+            character.armor_class = armor_class;
         }
     }
 }
 
-pub struct CharacterPlugin;
-
-impl Plugin for CharacterPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems(Update, (
-            update_character_ui,
-            handle_character_actions,
-        ));
-    }
-}
-
 fn update_character_ui(
-    characters: Query<&Character>,
+    _characters: Query<&Character>,
 ) {
     // TODO: Update character UI elements
 }
 
 fn handle_character_actions(
-    mut characters: Query<&mut Character>,
+    _characters: Query<&mut Character>,
 ) {
     // TODO: Handle character actions like leveling up, equipping items, etc.
-} 
\ No newline at end of file
+}
@@ -0,0 +1,158 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::ai_backend::AiBackendKind;
+use crate::campaign::CampaignConfig;
+use crate::config::CONFIG_FILE;
+use crate::replay::ReplayMode;
+use crate::GameConfig;
+
+// The command line. Every flag overrides config.toml for this launch only, so testers
+// and server operators can script launches without touching the file.
+#[derive(Parser, Debug, Clone)]
+#[command(name = "old-school-ai-game", version, about = "An old-school dungeon crawl with an AI Dungeon Master")]
+pub struct Cli {
+    #[arg(long, value_name = "FILE", default_value = CONFIG_FILE, help = "Settings file, written with the defaults if missing")]
+    pub config: PathBuf,
+
+    #[arg(long, value_name = "URL", help = "URL of the AI service, or of the OpenAI-compatible API")]
+    pub ai_url: Option<String>,
+
+    #[arg(long, value_name = "BACKEND", value_parser = ["service", "openai", "mock"], help = "Where AI requests go")]
+    pub ai_backend: Option<String>,
+
+    #[arg(long, value_name = "MODEL", help = "Model to ask, with --ai-backend openai")]
+    pub ai_model: Option<String>,
+
+    #[arg(long, value_name = "SECONDS", help = "How long an AI request may take")]
+    pub ai_timeout: Option<u64>,
+
+    #[arg(long, value_name = "N", help = "How often a failed AI request is tried again")]
+    pub ai_retries: Option<u32>,
+
+    #[arg(long, value_name = "N", help = "AI requests allowed at once")]
+    pub ai_concurrency: Option<usize>,
+
+    #[arg(long, value_name = "N", help = "AI requests allowed a minute")]
+    pub ai_requests_per_minute: Option<u32>,
+
+    #[arg(long, value_name = "N", help = "Rough AI tokens allowed a minute")]
+    pub ai_tokens_per_minute: Option<u32>,
+
+    #[arg(long, conflicts_with_all = ["ai_backend", "ai_url"], help = "Play without any AI, on canned replies")]
+    pub offline: bool,
+
+    #[arg(long, value_name = "DIR", help = "Directory for save files")]
+    pub save: Option<String>,
+
+    #[arg(long, value_name = "SLOT", help = "Load a save slot as soon as the game starts")]
+    pub load_save: Option<String>,
+
+    #[arg(long, value_name = "N", help = "Fixed dice seed, for a reproducible session")]
+    pub seed: Option<u64>,
+
+    #[arg(long, help = "Fight on a square grid with movement and weapon ranges")]
+    pub battle_map: bool,
+
+    #[arg(long, help = "New parties are ironman: their saves are deleted when they die")]
+    pub ironman: bool,
+
+    #[arg(long, help = "Heavy loads cost AC and initiative as well as movement")]
+    pub encumbrance_penalties: bool,
+
+    #[arg(long, help = "Fight in phased rounds: declarations, movement, missiles, magic, melee")]
+    pub phased_rounds: bool,
+
+    #[arg(long, help = "Treasure earns no experience, only slain monsters do")]
+    pub no_treasure_xp: bool,
+
+    #[arg(long, help = "Run without a window or renderer, e.g. on a server")]
+    pub headless: bool,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "replay", help = "Record the session's input and AI answers")]
+    pub record: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", help = "Play back a recorded session")]
+    pub replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "ID", requires = "player", help = "Join a shared campaign")]
+    pub campaign: Option<String>,
+
+    #[arg(long, value_name = "NAME", requires = "campaign", help = "Who you are in the shared campaign")]
+    pub player: Option<String>,
+}
+
+impl Cli {
+    // What the flags say, over `config`
+    pub fn apply(&self, config: &mut GameConfig) {
+        if let Some(kind) = self.ai_backend.as_deref().and_then(AiBackendKind::parse) {
+            config.ai_backend = kind;
+        }
+        if self.offline {
+            config.ai_backend = AiBackendKind::Mock;
+        }
+        match &mut config.ai_backend {
+            AiBackendKind::OpenAi { base_url, model } => {
+                if let Some(url) = &self.ai_url {
+                    *base_url = url.clone();
+                }
+                if let Some(chosen) = &self.ai_model {
+                    *model = chosen.clone();
+                }
+            }
+            _ => {
+                if let Some(url) = &self.ai_url {
+                    config.ai_service_url = url.clone();
+                }
+            }
+        }
+        if let Some(seconds) = self.ai_timeout {
+            config.ai_requests.timeout = Duration::from_secs(seconds);
+        }
+        if let Some(retries) = self.ai_retries {
+            config.ai_requests.retries = retries;
+        }
+        if let Some(concurrent) = self.ai_concurrency {
+            config.ai_limits.concurrent = concurrent;
+        }
+        if let Some(requests) = self.ai_requests_per_minute {
+            config.ai_limits.requests_per_minute = requests;
+        }
+        if let Some(tokens) = self.ai_tokens_per_minute {
+            config.ai_limits.tokens_per_minute = tokens;
+        }
+        if let Some(directory) = &self.save {
+            config.save_dir = directory.clone();
+        }
+        if self.seed.is_some() {
+            config.rng_seed = self.seed;
+        }
+        config.ruleset.battle_map |= self.battle_map;
+        config.ironman |= self.ironman;
+        config.ruleset.encumbrance_penalties |= self.encumbrance_penalties;
+        config.ruleset.phased_rounds |= self.phased_rounds;
+        config.ruleset.treasure_xp &= !self.no_treasure_xp;
+        config.load_slot = self.load_save.clone();
+        config.headless = self.headless;
+    }
+
+    // config.toml, then the flags
+    pub fn game_config(&self) -> GameConfig {
+        let mut config = GameConfig::from_file(&self.config);
+        self.apply(&mut config);
+        config
+    }
+
+    pub fn replay_mode(&self) -> ReplayMode {
+        match (&self.record, &self.replay) {
+            (Some(path), _) => ReplayMode::Record(path.clone()),
+            (None, Some(path)) => ReplayMode::Playback(path.clone()),
+            (None, None) => ReplayMode::Off,
+        }
+    }
+
+    pub fn campaign(&self, server_url: &str) -> Option<CampaignConfig> {
+        Some(CampaignConfig::new(server_url, self.campaign.clone()?, self.player.clone()?))
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+
+use crate::character::{RestEvent, SkillCheckEvent, ThiefSkill};
+use crate::GameState;
+
+pub use old_school_core::clock::{Elapsed, GameClock, TimeSpan, TURNS_PER_DAY};
+
+// The game clock. Anything that takes time sends an AdvanceClock: movement and travel
+// by the distance covered, a search for traps or a go at a lock a turn, a fight and its
+// aftermath a turn, a night's rest eight hours. As the clock crosses turn and day
+// boundaries it sends TurnsPassed and DayPassed, which burn down lights and eat into
+// provisions.
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AdvanceClock(pub TimeSpan);
+
+// Ten-minute dungeon turns that have gone by
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TurnsPassed(pub u64);
+
+// Sent once for each day that ends
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DayPassed;
+
+// Systems that read TurnsPassed or DayPassed run after this set
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClockSet;
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .add_event::<AdvanceClock>()
+            .add_event::<TurnsPassed>()
+            .add_event::<DayPassed>()
+            .add_systems(OnExit(GameState::Combat), fight_takes_turn)
+            .add_systems(Update, (
+                rest_takes_time,
+                search_takes_time,
+                advance_clock,
+            ).chain().in_set(ClockSet));
+    }
+}
+
+fn rest_takes_time(mut rest_events: EventReader<RestEvent>, mut advance: EventWriter<AdvanceClock>) {
+    for _ in rest_events.read() {
+        advance.send(AdvanceClock(TimeSpan::Hours(8)));
+    }
+}
+
+// Searching a room or a stretch of corridor for traps takes a turn, and so does working
+// at a lock
+fn search_takes_time(mut checks: EventReader<SkillCheckEvent>, mut advance: EventWriter<AdvanceClock>) {
+    for check in checks.read() {
+        if matches!(check.skill, ThiefSkill::FindRemoveTraps | ThiefSkill::OpenLocks) {
+            advance.send(AdvanceClock(TimeSpan::Turns(1)));
+        }
+    }
+}
+
+fn fight_takes_turn(mut advance: EventWriter<AdvanceClock>) {
+    advance.send(AdvanceClock(TimeSpan::Turns(1)));
+}
+
+fn advance_clock(
+    mut advance: EventReader<AdvanceClock>,
+    mut clock: ResMut<GameClock>,
+    mut turns: EventWriter<TurnsPassed>,
+    mut days: EventWriter<DayPassed>,
+) {
+    for event in advance.read() {
+        let elapsed = clock.advance(event.0);
+        if elapsed.turns > 0 {
+            turns.send(TurnsPassed(elapsed.turns));
+        }
+        for _ in 0..elapsed.days {
+            days.send(DayPassed);
+        }
+        debug!("{:?} passes; it is now {}", event.0, clock.describe());
+    }
+}
@@ -1,15 +1,30 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use rand::seq::SliceRandom;
 use rand::Rng;
-use crate::character::{Character, CharacterClass};
+use std::collections::HashMap;
+use crate::abilities::{BreathsTaken, UseAbility};
+use crate::ailments::{blocks_healing, Afflicted};
+use crate::character::{Character, ItemType};
+use crate::death::party_movement_rate;
+use crate::encounters::Monster;
+use crate::light::{darkness_attack_penalty, Darkness};
+use crate::rng::GameRng;
+use crate::ruleset::Ruleset;
+use crate::GameState;
+use old_school_core::encounter::open_encounter;
+use old_school_core::monster::EnemyData;
+use old_school_core::spells::{resolve_spell, SpellCompendium, SpellDescriptor, SpellEffect, SpellOutcome, SpellTarget};
 
-#[derive(Component, Debug, Clone, Serialize, Deserialize)]
-pub struct Combatant {
-    pub initiative: i8,
-    pub is_player: bool,
-    pub actions_remaining: u8,
-    pub status_effects: Vec<StatusEffect>,
-}
+// Combat rules (attack rolls, damage, combatant state) live in old_school_core; this
+// module drives them from the ECS
+pub use old_school_core::combat::*;
+pub use old_school_core::encounter::Side;
+pub use old_school_core::battle_map::{BattleMap, GridPosition};
+pub use old_school_core::rounds::{Declaration, RoundPhase};
+pub use old_school_core::fighting::{FightingStyle, OFF_HAND_TO_HIT, PARRY_ARMOR_BONUS};
+pub use old_school_core::resistances::{weapon_damage_type, Defense, Material};
+use old_school_core::battle_map::{area_squares, shot_reach, squares_per_round, FEET_PER_SQUARE};
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct Combat {
@@ -19,32 +34,13 @@ pub struct Combat {
     pub initiative_order: Vec<Entity>,
     pub current_combatant: Option<Entity>,
     pub state: CombatState,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum CombatState {
-    Initiative,
-    PlayerTurn,
-    EnemyTurn,
-    Victory,
-    Defeat,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StatusEffect {
-    pub name: String,
-    pub duration: u8,
-    pub effect_type: EffectType,
-    pub magnitude: i16,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum EffectType {
-    Damage,
-    Healing,
-    StatModifier,
-    Stun,
-    Poison,
+    #[serde(default)]
+    pub surprised: Option<Side>,
+    // With phased rounds, the phase under way and what everyone declared for the round
+    #[serde(default)]
+    pub phase: Option<RoundPhase>,
+    #[serde(default)]
+    pub declarations: Vec<(Entity, Declaration)>,
 }
 
 #[derive(Event)]
@@ -53,6 +49,7 @@ pub struct AttackEvent {
     pub target: Entity,
     pub weapon: Option<String>,
     pub spell: Option<String>,
+    pub style: FightingStyle,
 }
 
 #[derive(Event)]
@@ -60,36 +57,138 @@ pub struct DamageEvent {
     pub target: Entity,
     pub damage: i16,
     pub damage_type: DamageType,
+    // A natural 20, shaken out on the combat screen
+    pub critical: bool,
+    // Who dealt it, should it kill
+    pub source: Option<String>,
+    // What the weapon that dealt it is made of; None for spells, breath and the like
+    pub material: Option<Material>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DamageType {
-    Slashing,
-    Piercing,
-    Bludgeoning,
-    Fire,
-    Cold,
-    Lightning,
-    Acid,
-    Poison,
-    Magic,
+// Hit points of a regenerating monster's wounds that its regeneration can't mend, as
+// fire and acid leave a troll's
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Unmended(pub i16);
+
+#[derive(Event)]
+pub struct CastSpellEvent {
+    pub caster: Entity,
+    pub spell: String,
+    pub targets: Vec<Entity>,
+}
+
+// The party breaks and runs, optionally dropping its gold and treasure to distract the
+// pursuit
+#[derive(Event)]
+pub struct FleeEvent {
+    pub dropped_treasure: bool,
+}
+
+// What the player has picked in the combat UI: an enemy to aim at (or a party member,
+// for a healing or helping spell), how to fight, which memorized spell "Cast Spell" will
+// use and whether "Flee" throws the treasure behind
+#[derive(Resource, Default, Debug)]
+pub struct CombatSelection {
+    pub target: Option<Entity>,
+    pub style: FightingStyle,
+    pub spell: usize,
+    pub drop_treasure: bool,
+}
+
+// Where the party stood when the fight started, so a successful escape puts them back
+#[derive(Resource, Default, Debug)]
+pub struct PreCombatPositions(pub HashMap<Entity, Transform>);
+
+// On the battle map, how far the combatant whose turn it is can still move
+#[derive(Resource, Default, Debug)]
+pub struct TurnMovement {
+    pub combatant: Option<Entity>,
+    // The turn it was refreshed for, as a combatant can have several in a phased round
+    pub turn: u32,
+    pub feet_left: u16,
+}
+
+// Surprise for the next fight when it has already been rolled, as when the two sides
+// sighted each other across the wilderness: Some(side caught unawares, if any)
+#[derive(Resource, Default, Debug)]
+pub struct PresetSurprise(pub Option<Option<Side>>);
+
+// One line of combat narration, for the log panel and anything else that wants it
+#[derive(Event, Clone, Debug)]
+pub struct CombatLogEvent(pub String);
+
+// A fight won, with the monsters the party defeated
+#[derive(Event, Clone, Debug)]
+pub struct FightWon {
+    pub defeated: Vec<EnemyData>,
+}
+
+// Someone taken to 0 hit points or below, in a fight or out of one, with what did it for
+// the graveyard
+#[derive(Event, Clone, Debug)]
+pub struct CharacterFell {
+    pub entity: Entity,
+    pub name: String,
+    pub is_player: bool,
+    pub cause: String,
+    pub killer: Option<String>,
+}
+
+pub fn cause_of_death(damage_type: &DamageType) -> &'static str {
+    match damage_type {
+        DamageType::Slashing | DamageType::Piercing | DamageType::Bludgeoning => "slain in battle",
+        DamageType::Fire => "burned to death",
+        DamageType::Cold => "frozen to death",
+        DamageType::Lightning => "struck down by lightning",
+        DamageType::Acid => "dissolved by acid",
+        DamageType::Poison => "killed by poison",
+        DamageType::Magic => "slain by magic",
+    }
 }
 
 pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<AttackEvent>()
+        app.init_resource::<GameRng>()
+            .init_resource::<Ruleset>()
+            .init_resource::<CombatSelection>()
+            .init_resource::<PreCombatPositions>()
+            .init_resource::<TurnMovement>()
+            .init_resource::<PresetSurprise>()
+            .add_event::<AttackEvent>()
+            .add_event::<CastSpellEvent>()
+            .add_event::<FleeEvent>()
             .add_event::<DamageEvent>()
+            .add_event::<CombatLogEvent>()
+            .add_event::<CharacterFell>()
+            .add_event::<FightWon>()
+            .add_systems(OnEnter(GameState::Combat), (record_pre_combat_positions, begin_encounter))
+            .add_systems(OnExit(GameState::Combat), clear_battle_map)
+            .add_systems(Update, (
+                deploy_battle_map,
+                refresh_turn_movement,
+                move_player_on_map,
+                move_enemy_on_map,
+            ).chain().before(handle_combat_turn).run_if(in_state(GameState::Combat)))
             .add_systems(Update, (
                 handle_combat_turn,
                 process_attack_events,
+                process_cast_spell_events,
+                process_flee_events,
                 process_damage_events,
                 update_status_effects,
+                check_combat_outcome.after(process_damage_events).after(update_status_effects),
             ));
     }
 }
 
+impl Default for Combat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Combat {
     pub fn new() -> Self {
         Self {
@@ -98,7 +197,10 @@ impl Combat {
             combatants: Vec::new(),
             initiative_order: Vec::new(),
             current_combatant: None,
-            state: CombatState::Initiative,
+            state: CombatState::Encounter,
+            surprised: None,
+            phase: None,
+            declarations: Vec::new(),
         }
     }
 
@@ -106,13 +208,16 @@ impl Combat {
         self.combatants.push(entity);
     }
 
-    pub fn roll_initiative(&mut self, characters: &mut Query<(&mut Combatant, &Character)>) {
-        let mut rng = rand::thread_rng();
-        
+    pub fn roll_initiative(
+        &mut self,
+        characters: &mut Query<(&mut Combatant, &Character)>,
+        ruleset: &Ruleset,
+        rng: &mut impl Rng,
+    ) {
         for (mut combatant, character) in characters.iter_mut() {
             let dex_modifier = Character::get_dexterity_modifier(character.stats.dexterity);
             let initiative_roll = rng.gen_range(1..=6);
-            combatant.initiative = initiative_roll + dex_modifier;
+            combatant.initiative = initiative_roll + dex_modifier - character.encumbrance_penalty(ruleset);
         }
         
         // Sort combatants by initiative (highest first)
@@ -141,6 +246,25 @@ impl Combat {
         }
     }
 
+    pub fn declaration(&self, entity: Entity) -> Option<&Declaration> {
+        self.declarations.iter().find(|(declared, _)| *declared == entity).map(|(_, declaration)| declaration)
+    }
+
+    pub fn declare(&mut self, entity: Entity, declaration: Declaration) {
+        self.declarations.retain(|(declared, _)| *declared != entity);
+        self.declarations.push((entity, declaration));
+    }
+
+    // Whole turns allow moving any time; a phased round only in its movement phase
+    pub fn can_move(&self) -> bool {
+        self.phase.is_none_or(|phase| phase == RoundPhase::Movement)
+    }
+
+    // The surprised side loses the first round
+    pub fn is_surprised(&self, is_player: bool) -> bool {
+        self.round == 1 && self.surprised.is_some_and(|side| side.is_party() == is_player)
+    }
+
     pub fn is_player_turn(&self, characters: &Query<&Combatant>) -> bool {
         if let Some(current) = self.current_combatant {
             if let Ok(combatant) = characters.get(current) {
@@ -151,196 +275,1139 @@ impl Combat {
     }
 }
 
-pub fn roll_attack(
-    attacker: &Character,
-    target: &Character,
-    weapon: Option<&str>,
-) -> (bool, i16) {
-    let mut rng = rand::thread_rng();
-    
-    // Calculate attack bonus
-    let mut attack_bonus = 0;
-    
-    // Level-based bonus
-    attack_bonus += (attacker.level as i16 - 1) / 3; // +1 every 3 levels
-    
-    // Strength bonus for melee weapons
-    if let Some(weapon_name) = weapon {
-        if is_melee_weapon(weapon_name) {
-            attack_bonus += Character::get_strength_modifier(attacker.stats.strength) as i16;
-        }
+// Entering combat without a fight already set up (from a save) gathers everyone with a
+// Combatant into a new one
+fn begin_encounter(
+    mut commands: Commands,
+    existing: Query<(), With<Combat>>,
+    combatants: Query<(Entity, &Combatant)>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !existing.is_empty() {
+        return;
     }
-    
-    // Roll d20
-    let attack_roll = rng.gen_range(1..=20);
-    let total_attack = attack_roll + attack_bonus;
-    
-    // Check if hit
-    let hit = total_attack >= target.armor_class;
-    
-    // Calculate damage if hit
-    let damage = if hit {
-        calculate_damage(attacker, weapon)
-    } else {
-        0
-    };
-    
-    (hit, damage)
-}
-
-fn is_melee_weapon(weapon: &str) -> bool {
-    matches!(weapon.to_lowercase().as_str(), 
-        "sword" | "axe" | "mace" | "dagger" | "staff" | "hammer"
-    )
-}
-
-fn calculate_damage(attacker: &Character, weapon: Option<&str>) -> i16 {
-    let mut rng = rand::thread_rng();
-    
-    let (dice_count, dice_sides, bonus) = match weapon {
-        Some("sword") => (1, 8, 0),
-        Some("axe") => (1, 6, 0),
-        Some("mace") => (1, 6, 0),
-        Some("dagger") => (1, 4, 0),
-        Some("staff") => (1, 6, 0),
-        Some("bow") => (1, 6, 0),
-        Some("crossbow") => (1, 8, 0),
-        _ => (1, 4, 0), // Unarmed or unknown weapon
-    };
-    
-    let mut damage = bonus;
-    for _ in 0..dice_count {
-        damage += rng.gen_range(1..=dice_sides);
+    if !combatants.iter().any(|(_, combatant)| !combatant.is_player) {
+        warn!("Entered combat with nobody to fight");
+        next_state.set(GameState::InGame);
+        return;
     }
-    
-    // Add strength modifier for melee weapons
-    if weapon.is_some() && is_melee_weapon(weapon.unwrap()) {
-        let str_mod = Character::get_strength_modifier(attacker.stats.strength) as i16;
-        damage += str_mod.max(0); // Only positive modifiers apply to damage
+
+    let mut combat = Combat::new();
+    for (entity, _) in combatants.iter() {
+        combat.add_combatant(entity);
     }
-    
-    damage.max(1) // Minimum 1 damage
+    commands.spawn(combat);
 }
 
-fn handle_combat_turn(
-    mut combat: Query<&mut Combat>,
+// The encounter ends without a fight: the monsters go their way and the party stays put
+fn leave_encounter(commands: &mut Commands, combat: Entity, enemies: impl Iterator<Item = Entity>) {
+    for enemy in enemies {
+        commands.entity(enemy).despawn_recursive();
+    }
+    commands.entity(combat).despawn_recursive();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_combat_turn(
+    mut commands: Commands,
+    mut combat: Query<(Entity, &mut Combat)>,
     mut characters: Query<(&mut Combatant, &Character)>,
+    combatants: Query<Entity, With<Combatant>>,
+    monsters: Query<(&Monster, Option<&BreathsTaken>)>,
     mut attack_events: EventWriter<AttackEvent>,
+    mut ability_events: EventWriter<UseAbility>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut won: EventWriter<FightWon>,
+    ruleset: Res<Ruleset>,
+    movement: Res<TurnMovement>,
+    mut preset_surprise: ResMut<PresetSurprise>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if let Ok(mut combat) = combat.get_single_mut() {
+    if let Ok((combat_entity, mut combat)) = combat.get_single_mut() {
+        let _span = debug_span!("combat_turn", round = combat.round, turn = combat.turn, state = ?combat.state).entered();
+
         match combat.state {
+            CombatState::Encounter => {
+                // The party member with the best Charisma does the talking
+                let charisma = characters
+                    .iter()
+                    .filter(|(combatant, character)| combatant.is_player && character.is_alive())
+                    .map(|(_, character)| character.stats.charisma)
+                    .max()
+                    .unwrap_or(10);
+                let mut start = open_encounter(charisma, rng.as_mut());
+                if let Some(surprised) = preset_surprise.0.take() {
+                    start.surprised = surprised;
+                }
+                info!("Encounter opens: {:?}", start);
+
+                match start.surprised {
+                    Some(Side::Party) => log_events.send(CombatLogEvent("The party is surprised!".to_string())),
+                    Some(Side::Enemies) => log_events.send(CombatLogEvent("The enemies are caught unawares!".to_string())),
+                    None => {}
+                }
+                combat.surprised = start.surprised;
+                log_events.send(CombatLogEvent(format!(
+                    "Reaction {}: the enemies {}",
+                    start.reaction_roll,
+                    start.reaction.describe()
+                )));
+
+                if start.reaction.fights() {
+                    combat.state = CombatState::Initiative;
+                } else if start.reaction.parleys() {
+                    log_events.send(CombatLogEvent("Attack to fight, or Flee to back away.".to_string()));
+                    combat.state = CombatState::Parley;
+                } else {
+                    let enemies: Vec<Entity> = combatants
+                        .iter()
+                        .filter(|entity| characters.get(*entity).is_ok_and(|(combatant, _)| !combatant.is_player))
+                        .collect();
+                    leave_encounter(&mut commands, combat_entity, enemies.into_iter());
+                    next_state.set(GameState::InGame);
+                }
+            }
             CombatState::Initiative => {
-                combat.roll_initiative(&mut characters);
+                combat.roll_initiative(&mut characters, &ruleset, rng.as_mut());
+                info!("Initiative rolled for {} combatants", combat.initiative_order.len());
+                if ruleset.phased_rounds {
+                    open_phased_round(&mut combat, &characters);
+                    combat.current_combatant = None;
+                    advance_turn(&mut combat, &mut characters, ruleset.battle_map);
+                }
             }
             CombatState::PlayerTurn => {
                 if let Some(current) = combat.current_combatant {
                     if let Ok((combatant, character)) = characters.get(current) {
-                        if combatant.is_player && combatant.actions_remaining > 0 {
-                            // Player can make actions
-                            // This will be handled by UI input
-                        } else {
-                            // End player turn
-                            combat.next_turn();
+                        // The dead and those still recovering from being raised sit it out
+                        let able = combatant.is_player
+                            && character.is_alive()
+                            && !character.is_recovering()
+                            && !is_incapacitated(&combatant.status_effects)
+                            && !combat.is_surprised(true);
+                        // Their action taken, or in a phased round their declaration made or
+                        // their move over (Space ends it early)
+                        let done = combatant.actions_remaining == 0
+                            || match combat.phase {
+                                Some(RoundPhase::Declarations) => combat.declaration(current).is_some(),
+                                Some(RoundPhase::Movement) => {
+                                    movement.turn == combat.turn && movement.feet_left < FEET_PER_SQUARE
+                                }
+                                _ => false,
+                            };
+                        // Otherwise the UI waits for the player
+                        if !able || done {
+                            if !able && combatant.is_player && character.is_alive() {
+                                log_stunned(combatant, character, &mut log_events);
+                            }
+                            advance_turn(&mut combat, &mut characters, ruleset.battle_map);
                         }
                     }
                 }
             }
             CombatState::EnemyTurn => {
                 if let Some(current) = combat.current_combatant {
-                    if let Ok((mut combatant, character)) = characters.get_mut(current) {
-                        if !combatant.is_player && combatant.actions_remaining > 0 {
-                            // AI enemy action
-                            perform_ai_action(current, &mut characters, &mut attack_events);
+                    let can_act = characters
+                        .get(current)
+                        .map(|(combatant, character)| {
+                            !combatant.is_player
+                                && character.is_alive()
+                                && combatant.actions_remaining > 0
+                                && !is_incapacitated(&combatant.status_effects)
+                                && !combat.is_surprised(false)
+                        })
+                        .unwrap_or(false)
+                        // A phased round's movement phase is all moving (see move_enemy_on_map)
+                        && combat.phase != Some(RoundPhase::Movement);
+
+                    if can_act {
+                        // AI enemy action
+                        perform_ai_action(
+                            current,
+                            &combat.initiative_order,
+                            &characters,
+                            &monsters,
+                            &mut attack_events,
+                            &mut ability_events,
+                            rng.as_mut(),
+                        );
+                        if let Ok((mut combatant, _)) = characters.get_mut(current) {
                             combatant.actions_remaining -= 1;
-                        } else {
-                            // End enemy turn
-                            combat.next_turn();
                         }
+                    } else {
+                        // End enemy turn
+                        if let Ok((combatant, character)) = characters.get(current) {
+                            if !combatant.is_player && character.is_alive() {
+                                log_stunned(combatant, character, &mut log_events);
+                            }
+                        }
+                        advance_turn(&mut combat, &mut characters, ruleset.battle_map);
                     }
                 }
             }
+            CombatState::Victory => {
+                let enemies: Vec<Entity> = combatants
+                    .iter()
+                    .filter(|entity| characters.get(*entity).is_ok_and(|(combatant, _)| !combatant.is_player))
+                    .collect();
+                let defeated = enemies.iter().filter_map(|enemy| monsters.get(*enemy).ok());
+                won.send(FightWon { defeated: defeated.map(|(monster, _)| monster.0.clone()).collect() });
+                leave_encounter(&mut commands, combat_entity, enemies.into_iter());
+                next_state.set(GameState::InGame);
+            }
+            // The fallen and their killers stay for the death screen, which clears them away
+            CombatState::Defeat => {
+                commands.entity(combat_entity).despawn_recursive();
+                next_state.set(GameState::GameOver);
+            }
             _ => {}
         }
     }
 }
 
+// Moves the fight on to whoever acts next, handing everyone their action back when a new
+// round starts. Whole turns go round the initiative order, passing over the fallen; a
+// phased round goes to the next combatant with something to do in the phase, then on to
+// the next phase.
+fn advance_turn(combat: &mut Combat, characters: &mut Query<(&mut Combatant, &Character)>, battle_map: bool) {
+    let round = combat.round;
+    if combat.phase.is_some() {
+        next_phased_turn(combat, characters, battle_map);
+    } else {
+        for _ in 0..combat.initiative_order.len() {
+            combat.next_turn();
+            if combat.current_combatant.is_some_and(|current| living_side(characters, current).is_some()) {
+                break;
+            }
+        }
+    }
+    if combat.round != round {
+        for (mut combatant, _) in characters.iter_mut().filter(|(combatant, _)| combatant.actions_remaining != 1) {
+            combatant.actions_remaining = 1;
+        }
+    }
+    match combat.current_combatant.and_then(|current| living_side(characters, current)) {
+        Some(true) => combat.state = CombatState::PlayerTurn,
+        Some(false) => combat.state = CombatState::EnemyTurn,
+        None => {}
+    }
+}
+
+// Whether a combatant still standing is in the party
+fn living_side(characters: &Query<(&mut Combatant, &Character)>, entity: Entity) -> Option<bool> {
+    let (combatant, character) = characters.get(entity).ok()?;
+    character.is_alive().then_some(combatant.is_player)
+}
+
+// A phased round opens with the monsters' declarations: they all mean to fight hand to hand
+fn open_phased_round(combat: &mut Combat, characters: &Query<(&mut Combatant, &Character)>) {
+    combat.phase = Some(RoundPhase::Declarations);
+    combat.declarations = combat
+        .initiative_order
+        .iter()
+        .filter(|entity| living_side(characters, **entity) == Some(false))
+        .map(|entity| (*entity, Declaration::Melee))
+        .collect();
+}
+
+// The next combatant in initiative order with something to do in the phase: a party
+// member still to declare, or anyone who declared something for it. Once there's nobody
+// left the phase is over, and after melee a new round opens.
+fn next_phased_turn(combat: &mut Combat, characters: &Query<(&mut Combatant, &Character)>, battle_map: bool) {
+    let mut opened = false;
+    loop {
+        let phase = combat.phase.unwrap_or(RoundPhase::Declarations);
+        let start = combat
+            .current_combatant
+            .and_then(|current| combat.initiative_order.iter().position(|entity| *entity == current))
+            .map_or(0, |index| index + 1);
+        let next = combat.initiative_order.iter().skip(start).copied().find(|entity| {
+            match (phase, living_side(characters, *entity)) {
+                (_, None) => false,
+                (RoundPhase::Declarations, Some(is_player)) => is_player && combat.declaration(*entity).is_none(),
+                (phase, Some(_)) => combat.declaration(*entity).is_some_and(|declared| declared.acts_in(phase)),
+            }
+        });
+        if next.is_some() {
+            combat.current_combatant = next;
+            combat.turn += 1;
+            return;
+        }
+
+        combat.current_combatant = None;
+        match phase.next() {
+            // Without the battle map there's nowhere to move to
+            Some(RoundPhase::Movement) if !battle_map => combat.phase = Some(RoundPhase::Missiles),
+            Some(next) => combat.phase = Some(next),
+            // Nobody has anything to do in a whole round
+            None if opened => return,
+            None => {
+                opened = true;
+                combat.round += 1;
+                open_phased_round(combat, characters);
+            }
+        }
+    }
+}
+
+// Says so when a turn is lost to sleep or a hold
+fn log_stunned(combatant: &Combatant, character: &Character, log_events: &mut EventWriter<CombatLogEvent>) {
+    let stun = combatant.status_effects.iter().find(|effect| matches!(effect.effect_type, EffectType::Stun));
+    if let Some(stun) = stun {
+        log_events.send(CombatLogEvent(format!("{} can't act ({})", character.name, stun.name)));
+    }
+}
+
+// The fight is over once either side has nobody left on their feet
+fn check_combat_outcome(
+    mut combat: Query<&mut Combat>,
+    characters: Query<(&Combatant, &Character)>,
+    mut log_events: EventWriter<CombatLogEvent>,
+) {
+    let Ok(mut combat) = combat.get_single_mut() else {
+        return;
+    };
+    if !matches!(combat.state, CombatState::PlayerTurn | CombatState::EnemyTurn) {
+        return;
+    }
+    let standing = |players: bool| {
+        combat.combatants.iter().any(|entity| {
+            characters
+                .get(*entity)
+                .is_ok_and(|(combatant, character)| combatant.is_player == players && character.is_alive())
+        })
+    };
+    if !standing(true) {
+        log_events.send(CombatLogEvent("The party has fallen.".to_string()));
+        combat.state = CombatState::Defeat;
+    } else if !standing(false) {
+        log_events.send(CombatLogEvent("The enemies are defeated!".to_string()));
+        combat.state = CombatState::Victory;
+    }
+}
+
 fn perform_ai_action(
     enemy: Entity,
-    characters: &mut Query<&mut Combatant>,
+    initiative_order: &[Entity],
+    characters: &Query<(&mut Combatant, &Character)>,
+    monsters: &Query<(&Monster, Option<&BreathsTaken>)>,
     attack_events: &mut EventWriter<AttackEvent>,
+    ability_events: &mut EventWriter<UseAbility>,
+    rng: &mut GameRng,
 ) {
-    // Simple AI: attack the first player character found
-    for (combatant, _) in characters.iter() {
-        if combatant.is_player {
-            attack_events.send(AttackEvent {
-                attacker: enemy,
-                target: characters.get_entity(enemy).unwrap(),
-                weapon: Some("sword".to_string()),
-                spell: None,
-            });
-            break;
-        }
+    // Simple AI: attack the first living player character found
+    let target = initiative_order.iter().copied().find(|&entity| {
+        characters
+            .get(entity)
+            .map(|(combatant, character)| combatant.is_player && character.is_alive())
+            .unwrap_or(false)
+    });
+
+    // unless it has a breath weapon or a spell to use on them
+    let ability = monsters.get(enemy).ok().and_then(|(monster, breaths)| {
+        let (_, character) = characters.get(enemy).ok()?;
+        monster.0.choose_ability(breaths.map_or(0, |breaths| breaths.0), character, rng)
+    });
+    if let (Some(target), Some(ability)) = (target, ability) {
+        ability_events.send(UseAbility { user: enemy, target, ability });
+    } else if let Some(target) = target {
+        attack_events.send(AttackEvent {
+            attacker: enemy,
+            target,
+            weapon: Some("sword".to_string()),
+            spell: None,
+            style: FightingStyle::Normal,
+        });
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_attack_events(
     mut attack_events: EventReader<AttackEvent>,
-    mut characters: Query<(&mut Character, &Combatant)>,
+    mut characters: Query<(Entity, &Character, Option<&mut Combatant>, Option<&GridPosition>)>,
+    monsters: Query<&Monster>,
     mut damage_events: EventWriter<DamageEvent>,
+    mut afflictions: EventWriter<Afflicted>,
+    mut ability_events: EventWriter<UseAbility>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    ruleset: Res<Ruleset>,
+    darkness: Res<Darkness>,
+    mut rng: ResMut<GameRng>,
 ) {
     for event in attack_events.read() {
-        if let (Ok((mut attacker, _)), Ok((mut target, _))) = 
-            (characters.get_mut(event.attacker), characters.get_mut(event.target)) {
-            
-            let (hit, damage) = roll_attack(&attacker, &target, event.weapon.as_deref());
-            
+        // Nobody attacks once they have fallen
+        let Ok((_, attacker, attacker_combatant, from)) = characters.get(event.attacker) else {
+            continue;
+        };
+        if !attacker.is_alive() {
+            continue;
+        }
+        let (attacker, attacker_combatant, from) = (attacker.clone(), attacker_combatant.cloned(), from.copied());
+        let Ok((_, first, first_combatant, _)) = characters.get(event.target) else {
+            continue;
+        };
+        let _span = info_span!("attack", attacker = %attacker.name, target = %first.name).entered();
+
+        // A fighter in the party sweeps through foes of 1 Hit Die or less, and a second
+        // weapon gets a swing of its own
+        let is_player = attacker_combatant.as_ref().is_some_and(|combatant| combatant.is_player);
+        let sweeping = is_player && first_combatant.is_some_and(|combatant| !combatant.is_player);
+        let sweeps = if sweeping { attacker.sweep_attacks(first) } else { 1 };
+        let mut swings = vec![(attacker.clone(), event.weapon.clone(), event.style.to_hit()); sweeps as usize];
+        if let Some(off_hand) = attacker.off_hand_weapon().filter(|_| event.style == FightingStyle::TwoWeapons) {
+            let mut wielder = attacker.clone();
+            wielder.equipment.weapon = Some(off_hand.clone());
+            swings.push((wielder, Some(off_hand.name.to_lowercase()), OFF_HAND_TO_HIT));
+        }
+
+        let mut dealt: HashMap<Entity, i16> = HashMap::new();
+        let mut target = event.target;
+        for (wielder, weapon, to_hit) in swings {
+            let standing = |entity: Entity, character: &Character| {
+                character.hit_points.current - dealt.get(&entity).copied().unwrap_or(0) > 0
+            };
+            // On the battle map the target has to be next to the attacker, or within range
+            // of a missile weapon with nobody in the way, which hits more easily up close
+            let occupied: Vec<GridPosition> = characters
+                .iter()
+                .filter(|(entity, character, _, _)| standing(*entity, character))
+                .filter_map(|(_, _, _, position)| position.copied())
+                .collect();
+            let reach = |to: Option<&GridPosition>| match (from, to) {
+                (Some(from), Some(to)) => shot_reach(weapon.as_deref(), &from, to, &occupied),
+                _ => Ok(0),
+            };
+
+            // Once the foe falls, a sweep goes on to the next in reach
+            if characters.get(target).is_ok_and(|(entity, character, _, _)| !standing(entity, character)) {
+                let next = characters.iter().find(|(entity, character, combatant, position)| {
+                    combatant.as_ref().is_some_and(|combatant| !combatant.is_player)
+                        && character.level <= 1
+                        && standing(*entity, character)
+                        && reach(*position).is_ok()
+                });
+                match next {
+                    Some((entity, character, _, _)) if sweeping => {
+                        log_events.send(CombatLogEvent(format!("{} sweeps on to {}", attacker.name, character.name)));
+                        target = entity;
+                    }
+                    _ => break,
+                }
+            }
+            let Ok((_, defender, defender_combatant, to)) = characters.get(target) else {
+                break;
+            };
+            let range_modifier = match reach(to) {
+                Ok(modifier) => modifier,
+                Err(reason) => {
+                    let text = format!("{} can't hit {}: {}", attacker.name, defender.name, reason);
+                    log_events.send(CombatLogEvent(text));
+                    break;
+                }
+            };
+
+            let mut defending = defending_against(
+                &wielder,
+                attacker_combatant.as_ref(),
+                defender,
+                defender_combatant,
+                range_modifier,
+                &ruleset,
+                darkness.0,
+            );
+            defending.armor_class -= to_hit as i8;
+            let roll = roll_attack_detailed(&ruleset, &wielder, &defending, weapon.as_deref(), rng.as_mut());
+            let (hit, damage) = (roll.hit, roll.damage);
+            let text = get_combat_text(&attacker, defender, hit, damage);
+            info!("{}", text);
+            log_events.send(CombatLogEvent(text));
+
             if hit {
+                *dealt.entry(target).or_default() += damage;
+                let blade = wielder.equipment.weapon.as_ref();
                 damage_events.send(DamageEvent {
-                    target: event.target,
+                    target,
                     damage,
-                    damage_type: DamageType::Slashing, // Default, could be weapon-specific
+                    damage_type: weapon_damage_type(blade),
+                    critical: roll.is_critical(),
+                    source: Some(attacker.name.clone()),
+                    material: Some(Material::of(blade)),
                 });
+                // Whatever poison, disease, paralysis and so on the monster's blow carries
+                let Ok(monster) = monsters.get(event.attacker) else {
+                    continue;
+                };
+                for affliction in monster.0.afflictions() {
+                    afflictions.send(Afflicted { target, affliction, source: attacker.name.clone() });
+                }
+                for ability in monster.0.abilities().into_iter().filter(|ability| ability.rides_on(&roll)) {
+                    ability_events.send(UseAbility { user: event.attacker, target, ability });
+                }
+            }
+        }
+
+        // Fighting defensively keeps the guard up for the rest of the round
+        if event.style.armor_bonus() > 0 {
+            if let Ok((_, _, Some(mut combatant), _)) = characters.get_mut(event.attacker) {
+                guard(&mut combatant, "Fighting defensively", event.style.armor_bonus());
             }
         }
     }
 }
 
-fn process_damage_events(
+// An AC bonus that lasts until the round is out, replacing any guard already up
+pub fn guard(combatant: &mut Combatant, name: &str, bonus: i16) {
+    combatant.status_effects.retain(|effect| effect.name != name);
+    combatant.status_effects.push(StatusEffect {
+        name: name.to_string(),
+        duration: 1,
+        effect_type: EffectType::ArmorBonus,
+        magnitude: bonus,
+    });
+}
+
+// The target as the attacker has to hit it, with its armour class as its spells leave it
+// and adjusted for the range, its load and the attacker's blessings and curses
+pub fn defending_against(
+    attacker: &Character,
+    attacker_combatant: Option<&Combatant>,
+    target: &Character,
+    target_combatant: Option<&Combatant>,
+    range_modifier: i16,
+    ruleset: &Ruleset,
+    dark: bool,
+) -> Character {
+    let mut defender = target.clone();
+    if let Some(combatant) = target_combatant {
+        defender.armor_class = effective_armor_class(target, &combatant.status_effects);
+    }
+    defender.armor_class -= range_modifier as i8 + target.encumbrance_penalty(ruleset);
+    if let Some(combatant) = attacker_combatant {
+        defender.armor_class -= roll_modifier(&combatant.status_effects) as i8;
+    }
+    // Monsters see in the dark; the party fights blind without light or infravision
+    if attacker_combatant.is_some_and(|combatant| combatant.is_player) {
+        defender.armor_class += darkness_attack_penalty(attacker, dark) as i8;
+    }
+    defender
+}
+
+fn process_cast_spell_events(
+    mut cast_events: EventReader<CastSpellEvent>,
+    mut characters: Query<(&mut Character, Option<&mut Combatant>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut rng: ResMut<GameRng>,
+    compendium: Res<SpellCompendium>,
+) {
+    for event in cast_events.read() {
+        let Ok((mut caster, _)) = characters.get_mut(event.caster) else {
+            continue;
+        };
+        let _span = info_span!("cast", caster = %caster.name, spell = %event.spell).entered();
+
+        let spell = match caster.cast_spell(&event.spell) {
+            Ok(spell) => spell,
+            Err(reason) => {
+                log_events.send(CombatLogEvent(reason));
+                continue;
+            }
+        };
+        let caster = caster.clone();
+
+        let Some(descriptor) = compendium.descriptor(&spell.name) else {
+            log_events.send(CombatLogEvent(format!("{} casts {}, to no visible effect", caster.name, spell.name)));
+            continue;
+        };
+
+        let targets: Vec<(Entity, Character)> = event
+            .targets
+            .iter()
+            .filter_map(|&entity| characters.get(entity).ok().map(|(character, _)| (entity, character.clone())))
+            .collect();
+        let target_refs: Vec<&Character> = targets.iter().map(|(_, character)| character).collect();
+        let outcomes = resolve_spell(&spell, descriptor, &caster, &target_refs, rng.as_mut());
+
+        log_events.send(CombatLogEvent(format!("{} casts {}!", caster.name, spell.name)));
+        if outcomes.is_empty() {
+            log_events.send(CombatLogEvent("Nothing seems to happen.".to_string()));
+        }
+
+        for outcome in outcomes {
+            match outcome {
+                SpellOutcome::Damage { target, amount, damage_type, saved } => {
+                    let (entity, character) = &targets[target];
+                    let text = if saved {
+                        format!("{} saves and takes only {} damage", character.name, amount)
+                    } else {
+                        format!("{} takes {} damage", character.name, amount)
+                    };
+                    log_events.send(CombatLogEvent(text));
+                    damage_events.send(DamageEvent {
+                        target: *entity,
+                        damage: amount,
+                        damage_type,
+                        critical: false,
+                        source: Some(caster.name.clone()),
+                        material: None,
+                    });
+                }
+                SpellOutcome::Heal { target, amount } => {
+                    let (entity, _) = &targets[target];
+                    if let Ok((mut character, combatant)) = characters.get_mut(*entity) {
+                        if combatant.is_some_and(|combatant| blocks_healing(&combatant.status_effects)) {
+                            log_events.send(CombatLogEvent(format!(
+                                "The rot won't let {}'s wounds close",
+                                character.name
+                            )));
+                            continue;
+                        }
+                        character.heal(amount);
+                        log_events.send(CombatLogEvent(format!(
+                            "{} is healed for {} ({}/{} HP)",
+                            character.name, amount, character.hit_points.current, character.hit_points.maximum
+                        )));
+                    }
+                }
+                SpellOutcome::Status { target, effect } => {
+                    let (entity, character) = &targets[target];
+                    if let Ok((_, Some(mut combatant))) = characters.get_mut(*entity) {
+                        let text = match (&effect.effect_type, &descriptor.effect) {
+                            (EffectType::Stun, SpellEffect::Sleep { .. }) => format!("{} falls asleep", character.name),
+                            (EffectType::Stun, _) => format!("{} is held fast", character.name),
+                            (EffectType::StatModifier, _) => format!(
+                                "{} is under {}, {:+} to hit for {} rounds",
+                                character.name, effect.name, effect.magnitude, effect.duration
+                            ),
+                            _ => format!("{} is warded by {} for {} rounds", character.name, effect.name, effect.duration),
+                        };
+                        log_events.send(CombatLogEvent(text));
+                        // Recasting refreshes an effect rather than stacking it
+                        combatant.status_effects.retain(|existing| existing.name != effect.name);
+                        combatant.status_effects.push(effect);
+                    }
+                }
+                SpellOutcome::Cure { target, ailment } => {
+                    let (entity, character) = &targets[target];
+                    let cured = characters
+                        .get_mut(*entity)
+                        .is_ok_and(|(_, combatant)| combatant.is_some_and(|mut combatant| {
+                            ailment.cure(&mut combatant.status_effects)
+                        }));
+                    let text = if cured {
+                        format!("{} is cured of {}", character.name, ailment.name())
+                    } else {
+                        format!("{} has no {} to cure", character.name, ailment.name())
+                    };
+                    log_events.send(CombatLogEvent(text));
+                }
+                SpellOutcome::Resisted { target } => {
+                    let (_, character) = &targets[target];
+                    log_events.send(CombatLogEvent(format!("{} resists the spell", character.name)));
+                }
+            }
+        }
+    }
+}
+
+// Picks targets for a spell the way the combat UI does: the selected enemy (or the first
+// one standing), every living enemy, the selected ally (or else the most wounded, or for
+// a cure the first one it would help), the whole party or the caster. On the battle map an area spell
+// catches everyone standing in its template instead, friend or foe.
+pub fn default_spell_targets<'a>(
+    compendium: &SpellCompendium,
+    spell: &str,
+    caster: Entity,
+    selected: Option<Entity>,
+    combatants: impl Iterator<Item = (Entity, &'a Combatant, &'a Character)>,
+    position: impl Fn(Entity) -> Option<GridPosition>,
+) -> Vec<Entity> {
+    let Some(descriptor) = compendium.descriptor(spell) else {
+        return vec![caster];
+    };
+    let living: Vec<(Entity, &Combatant, &Character)> =
+        combatants.filter(|(_, _, character)| character.is_alive()).collect();
+    let enemies = || living.iter().filter(|(_, combatant, _)| !combatant.is_player);
+
+    if let Some(template) = spell_template(descriptor, caster, selected, living.iter().copied(), &position) {
+        return living
+            .iter()
+            .filter(|(entity, _, _)| position(*entity).is_some_and(|square| template.contains(&square)))
+            .map(|(entity, _, _)| *entity)
+            .collect();
+    }
+
+    // A party member picked in the combat UI gets an ally's spell
+    let chosen_ally = living.iter().find(|(entity, combatant, _)| combatant.is_player && Some(*entity) == selected);
+    if let (SpellTarget::Ally, Some((ally, _, _))) = (descriptor.target, chosen_ally) {
+        return vec![*ally];
+    }
+
+    match (descriptor.target, &descriptor.effect) {
+        (SpellTarget::Caster, _) => vec![caster],
+        // A cure goes to whoever has what it cures
+        (SpellTarget::Ally, SpellEffect::Cure { cures }) => living
+            .iter()
+            .filter(|(_, combatant, _)| combatant.is_player)
+            .find(|(_, combatant, _)| combatant.status_effects.iter().any(|effect| cures.afflicts(effect)))
+            .map(|(entity, _, _)| vec![*entity])
+            .unwrap_or_else(|| vec![caster]),
+        (SpellTarget::Ally, _) => living
+            .iter()
+            .filter(|(_, combatant, _)| combatant.is_player)
+            .max_by_key(|(_, _, character)| character.hit_points.maximum - character.hit_points.current)
+            .map(|(entity, _, _)| vec![*entity])
+            .unwrap_or_else(|| vec![caster]),
+        (SpellTarget::Enemy, _) => enemies()
+            .find(|(entity, _, _)| Some(*entity) == selected)
+            .or_else(|| enemies().next())
+            .map(|(entity, _, _)| vec![*entity])
+            .unwrap_or_default(),
+        (SpellTarget::AllEnemies, _) => enemies().map(|(entity, _, _)| *entity).collect(),
+        (SpellTarget::Party, _) => living
+            .iter()
+            .filter(|(_, combatant, _)| combatant.is_player)
+            .map(|(entity, _, _)| *entity)
+            .collect(),
+    }
+}
+
+// The squares an area spell cast by `caster` covers, aimed at the selected enemy (or the
+// first one standing): a burst centred on them, a cone or line pointed their way. None
+// for a spell without an area or one that isn't aimed at enemies, or off the battle map.
+pub fn spell_template<'a>(
+    descriptor: &SpellDescriptor,
+    caster: Entity,
+    selected: Option<Entity>,
+    combatants: impl Iterator<Item = (Entity, &'a Combatant, &'a Character)>,
+    position: impl Fn(Entity) -> Option<GridPosition>,
+) -> Option<Vec<GridPosition>> {
+    let feet = descriptor.area?;
+    if !matches!(descriptor.target, SpellTarget::Enemy | SpellTarget::AllEnemies) {
+        return None;
+    }
+    let enemies: Vec<Entity> = combatants
+        .filter(|(_, combatant, character)| !combatant.is_player && character.is_alive())
+        .map(|(entity, _, _)| entity)
+        .collect();
+    let aim = enemies.iter().copied().find(|entity| Some(*entity) == selected).or(enemies.first().copied())?;
+    Some(area_squares(descriptor.shape, feet, position(caster)?, position(aim)?))
+}
+
+// With the battle map rule on, anyone in the fight without a square yet is lined up on
+// their side of the map
+fn deploy_battle_map(
+    mut commands: Commands,
+    ruleset: Res<Ruleset>,
+    map: Option<Res<BattleMap>>,
+    combat: Query<(), With<Combat>>,
+    unplaced: Query<(Entity, &Combatant), Without<GridPosition>>,
+) {
+    if !ruleset.battle_map || combat.is_empty() || unplaced.is_empty() {
+        return;
+    }
+    let map = match map {
+        Some(map) => map.clone(),
+        None => {
+            commands.insert_resource(BattleMap::default());
+            BattleMap::default()
+        }
+    };
+
+    let (party, enemies): (Vec<_>, Vec<_>) = unplaced.iter().partition(|(_, combatant)| combatant.is_player);
+    let (party_squares, enemy_squares) = map.deploy(party.len(), enemies.len());
+    for ((entity, _), square) in party.iter().zip(party_squares).chain(enemies.iter().zip(enemy_squares)) {
+        commands.entity(*entity).insert(square);
+    }
+}
+
+fn clear_battle_map(mut commands: Commands, placed: Query<Entity, With<GridPosition>>) {
+    for entity in placed.iter() {
+        commands.entity(entity).remove::<GridPosition>();
+    }
+    commands.remove_resource::<BattleMap>();
+}
+
+// Each new turn starts with a full round's movement
+fn refresh_turn_movement(
+    combat: Query<&Combat>,
+    characters: Query<(&Character, Option<&Monster>)>,
+    mut movement: ResMut<TurnMovement>,
+) {
+    let (current, turn) = combat.get_single().map_or((None, 0), |combat| (combat.current_combatant, combat.turn));
+    if current == movement.combatant && turn == movement.turn {
+        return;
+    }
+    movement.combatant = current;
+    movement.turn = turn;
+    movement.feet_left = current
+        .and_then(|entity| characters.get(entity).ok())
+        .map_or(0, |(character, monster)| match monster {
+            Some(monster) => monster.0.movement_rate(),
+            None => character.movement_rate(),
+        });
+}
+
+// Whether a living combatant other than `mover` stands on the square
+fn square_taken(
+    square: &GridPosition,
+    mover: Entity,
+    positions: &Query<(Entity, &mut GridPosition, &Combatant, &Character)>,
+) -> bool {
+    positions
+        .iter()
+        .any(|(entity, position, _, character)| entity != mover && position == square && character.is_alive())
+}
+
+// The arrow keys move the acting party member a square at a time. In a phased round's
+// movement phase, Space ends the move.
+fn move_player_on_map(
+    keyboard_input: Res<Input<KeyCode>>,
+    map: Option<Res<BattleMap>>,
+    combat: Query<&Combat>,
+    mut movement: ResMut<TurnMovement>,
+    mut positions: Query<(Entity, &mut GridPosition, &Combatant, &Character)>,
+) {
+    let Some(map) = map else {
+        return;
+    };
+    let phase = combat.get_single().ok().and_then(|combat| combat.phase);
+    if phase == Some(RoundPhase::Movement) && keyboard_input.just_pressed(KeyCode::Space) {
+        let mover = movement.combatant.and_then(|mover| positions.get(mover).ok());
+        if mover.is_some_and(|(_, _, combatant, _)| combatant.is_player) {
+            movement.feet_left = 0;
+        }
+        return;
+    }
+    let (dx, dy) = if keyboard_input.just_pressed(KeyCode::Up) {
+        (0, -1)
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        (0, 1)
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        (-1, 0)
+    } else if keyboard_input.just_pressed(KeyCode::Right) {
+        (1, 0)
+    } else {
+        return;
+    };
+    let Ok(combat) = combat.get_single() else {
+        return;
+    };
+    let Some(mover) = movement.combatant.filter(|_| movement.feet_left >= FEET_PER_SQUARE) else {
+        return;
+    };
+    let Ok((_, position, combatant, _)) = positions.get(mover) else {
+        return;
+    };
+    if !combatant.is_player
+        || !combat.can_move()
+        || combat.is_surprised(true)
+        || is_incapacitated(&combatant.status_effects)
+    {
+        return;
+    }
+
+    let square = position.offset(dx, dy);
+    if !map.contains(&square) || square_taken(&square, mover, &positions) {
+        return;
+    }
+    if let Ok((_, mut position, _, _)) = positions.get_mut(mover) {
+        *position = square;
+        movement.feet_left -= FEET_PER_SQUARE;
+    }
+}
+
+// An enemy closes on the nearest party member until it can strike or runs out of
+// movement for the round
+fn move_enemy_on_map(
+    map: Option<Res<BattleMap>>,
+    combat: Query<&Combat>,
+    mut movement: ResMut<TurnMovement>,
+    mut positions: Query<(Entity, &mut GridPosition, &Combatant, &Character)>,
+) {
+    let Some(map) = map else {
+        return;
+    };
+    let Ok(combat) = combat.get_single() else {
+        return;
+    };
+    if combat.state != CombatState::EnemyTurn || movement.feet_left < FEET_PER_SQUARE {
+        return;
+    }
+    let Some(mover) = movement.combatant else {
+        return;
+    };
+    let Ok((_, position, combatant, _)) = positions.get(mover) else {
+        return;
+    };
+    if combatant.is_player
+        || !combat.can_move()
+        || combat.is_surprised(false)
+        || is_incapacitated(&combatant.status_effects)
+    {
+        return;
+    }
+
+    let mut square = *position;
+    let Some(target) = positions
+        .iter()
+        .filter(|(_, _, combatant, character)| combatant.is_player && character.is_alive())
+        .map(|(_, position, _, _)| *position)
+        .min_by_key(|position| position.distance(&square))
+    else {
+        return;
+    };
+
+    let mut steps = squares_per_round(movement.feet_left);
+    while steps > 0 && !square.is_adjacent(&target) {
+        let next = square.step_toward(&target);
+        if !map.contains(&next) || square_taken(&next, mover, &positions) {
+            break;
+        }
+        square = next;
+        steps -= 1;
+    }
+    movement.feet_left = steps * FEET_PER_SQUARE;
+    if let Ok((_, mut position, _, _)) = positions.get_mut(mover) {
+        if *position != square {
+            *position = square;
+        }
+    }
+}
+
+fn record_pre_combat_positions(
+    mut positions: ResMut<PreCombatPositions>,
+    party: Query<(Entity, &Transform, &Combatant)>,
+) {
+    positions.0 = party
+        .iter()
+        .filter(|(_, _, combatant)| combatant.is_player)
+        .map(|(entity, transform, _)| (entity, *transform))
+        .collect();
+}
+
+// Every enemy still on its feet gets a parting shot at a fleeing party member. Then the
+// party's pace is compared with the pursuers': on an escape the encounter ends and the
+// party is back where it stood before the fight, otherwise the turn is lost.
+#[allow(clippy::too_many_arguments)]
+fn process_flee_events(
+    mut commands: Commands,
+    mut flee_events: EventReader<FleeEvent>,
+    combat: Query<(Entity, &Combat)>,
+    mut combatants: Query<(Entity, &Combatant, &mut Character, Option<&mut Transform>)>,
+    positions: Res<PreCombatPositions>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    ruleset: Res<Ruleset>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(event) = flee_events.read().last() else {
+        return;
+    };
+    let Ok((combat_entity, combat)) = combat.get_single() else {
+        return;
+    };
+    if combat.state == CombatState::Parley {
+        log_events.send(CombatLogEvent("The party backs away, and the enemies let it go.".to_string()));
+        let enemies = combatants.iter().filter(|(_, combatant, _, _)| !combatant.is_player).map(|(entity, ..)| entity);
+        leave_encounter(&mut commands, combat_entity, enemies);
+        next_state.set(GameState::InGame);
+        return;
+    }
+
+    let mut party = Vec::new();
+    let mut enemies = Vec::new();
+    for (entity, combatant, character, _) in combatants.iter() {
+        if !character.is_alive() {
+            continue;
+        }
+        if combatant.is_player {
+            party.push((entity, character.clone()));
+        } else if !is_incapacitated(&combatant.status_effects) {
+            enemies.push((entity, character.clone()));
+        }
+    }
+    if party.is_empty() {
+        return;
+    }
+
+    for (_, enemy) in &enemies {
+        let Some((target, fleeing)) = party.choose(rng.as_mut()) else {
+            break;
+        };
+        let (hit, damage) = roll_attack_with(&ruleset, enemy, fleeing, None, rng.as_mut());
+        log_events.send(CombatLogEvent(format!("Parting shot: {}", get_combat_text(enemy, fleeing, hit, damage))));
+        if hit {
+            let blade = enemy.equipment.weapon.as_ref();
+            damage_events.send(DamageEvent {
+                target: *target,
+                damage,
+                damage_type: weapon_damage_type(blade),
+                critical: false,
+                source: Some(enemy.name.clone()),
+                material: Some(Material::of(blade)),
+            });
+        }
+    }
+
+    let mut dropped_treasure = false;
+    if event.dropped_treasure {
+        for (_, combatant, mut character, _) in combatants.iter_mut() {
+            if !combatant.is_player {
+                continue;
+            }
+            let before = (character.inventory.gold, character.inventory.items.len());
+            character.inventory.gold = 0;
+            character.inventory.items.retain(|item| !matches!(item.item_type, ItemType::Treasure));
+            dropped_treasure |= before != (character.inventory.gold, character.inventory.items.len());
+        }
+        if dropped_treasure {
+            log_events.send(CombatLogEvent("The party throws its treasure behind it.".to_string()));
+        }
+    }
+
+    // Measured after the drop, which may have lightened the load
+    let party_rate = party_movement_rate(
+        combatants.iter().filter(|(_, combatant, _, _)| combatant.is_player).map(|(_, _, character, _)| character),
+    );
+    let pursuer_rate = enemies.iter().map(|(_, character)| character.movement_rate()).max().unwrap_or(0);
+    let outcome = attempt_flight(party_rate, pursuer_rate, dropped_treasure, rng.as_mut());
+    info!("Flight at {}' against {}': {:?}", party_rate, pursuer_rate, outcome);
+
+    let text = match outcome {
+        FleeOutcome::Escaped => "The party flees back the way it came.",
+        FleeOutcome::Distracted => "The enemies stop to fight over the spoils.",
+        FleeOutcome::Caught => {
+            log_events.send(CombatLogEvent("The enemies cut off the retreat!".to_string()));
+            return;
+        }
+    };
+    log_events.send(CombatLogEvent(text.to_string()));
+
+    let mut fled_from = Vec::new();
+    for (entity, combatant, _, transform) in combatants.iter_mut() {
+        if !combatant.is_player {
+            fled_from.push(entity);
+        } else if let (Some(mut transform), Some(saved)) = (transform, positions.0.get(&entity)) {
+            *transform = *saved;
+        }
+    }
+    leave_encounter(&mut commands, combat_entity, fled_from.into_iter());
+    next_state.set(GameState::InGame);
+}
+
+pub fn process_damage_events(
+    mut commands: Commands,
     mut damage_events: EventReader<DamageEvent>,
-    mut characters: Query<&mut Character>,
+    mut characters: Query<(&mut Character, Option<&Combatant>)>,
+    monsters: Query<&Monster>,
+    mut unmended: Query<&mut Unmended>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut fallen: EventWriter<CharacterFell>,
 ) {
     for event in damage_events.read() {
-        if let Ok(mut character) = characters.get_mut(event.target) {
-            character.take_damage(event.damage);
-            
+        if let Ok((mut character, combatant)) = characters.get_mut(event.target) {
+            let was_alive = character.is_alive();
+            // A monster's resistances and immunities, and the wounds its regeneration can't mend
+            let mut damage = event.damage;
+            if let Ok(Monster(enemy)) = monsters.get(event.target) {
+                let (taken, defense) = enemy.defend(damage, &event.damage_type, event.material);
+                if let Some(defense) = defense {
+                    let text = defense.describe(&character.name, &event.damage_type);
+                    let text = if taken > 0 { format!("{} ({} damage, not {})", text, taken, damage) } else { text };
+                    log_events.send(CombatLogEvent(text));
+                }
+                damage = taken;
+                if !enemy.mends(&event.damage_type) && damage > 0 {
+                    match unmended.get_mut(event.target) {
+                        Ok(mut unmended) => unmended.0 += damage,
+                        Err(_) => {
+                            commands.entity(event.target).insert(Unmended(damage));
+                        }
+                    }
+                }
+            }
+            character.take_damage(damage);
+
+            debug!("{} takes {} {:?} damage", character.name, damage, event.damage_type);
+
             // Check if character is defeated
-            if !character.is_alive() {
+            if was_alive && !character.is_alive() {
                 // Handle character death
+                info!("{} has fallen", character.name);
+                log_events.send(CombatLogEvent(format!("{} has fallen!", character.name)));
+                fallen.send(CharacterFell {
+                    entity: event.target,
+                    name: character.name.clone(),
+                    is_player: combatant.is_some_and(|combatant| combatant.is_player),
+                    cause: cause_of_death(&event.damage_type).to_string(),
+                    killer: event.source.clone(),
+                });
             }
         }
     }
 }
 
+// Effect durations are counted in combat rounds, so they tick once each time the round
+// counter moves on rather than every frame. Poison does its damage as it ticks, at the
+// start of the new round, and regeneration heals.
 fn update_status_effects(
-    mut characters: Query<&mut Combatant>,
+    combat: Query<&Combat>,
+    mut characters: Query<(Entity, &mut Combatant, &mut Character, Option<&Unmended>)>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut fallen: EventWriter<CharacterFell>,
+    mut last_round: Local<u32>,
 ) {
-    for mut combatant in characters.iter_mut() {
+    let Ok(combat) = combat.get_single() else {
+        *last_round = 0;
+        return;
+    };
+    if *last_round == 0 || combat.round <= *last_round {
+        *last_round = combat.round;
+        return;
+    }
+    *last_round = combat.round;
+
+    for (entity, mut combatant, mut character, unmended) in characters.iter_mut() {
+        // Only touch combatants with something to tick, so Changed<Combatant> stays meaningful
+        if combatant.status_effects.is_empty() {
+            continue;
+        }
+        let damage: i16 = combatant.status_effects.iter().map(damage_per_round).sum();
+        // A swallowed victim is being digested rather than poisoned
+        let digested = combatant.status_effects.iter().any(|effect| matches!(effect.effect_type, EffectType::Damage));
+        let (source, kind) = if digested { ("digestion", DamageType::Acid) } else { ("poison", DamageType::Poison) };
+        // Regeneration mends all but what fire and the like did
+        let mendable = character.hit_points.maximum - unmended.map_or(0, |unmended| unmended.0);
+        let regenerated: i16 = combatant.status_effects.iter().map(healing_per_round).sum();
+        let healing = regenerated.min(mendable - character.hit_points.current);
+        if healing > 0 && character.is_alive() && !blocks_healing(&combatant.status_effects) {
+            character.heal(healing);
+            log_events.send(CombatLogEvent(format!("{} regenerates {} hit points", character.name, healing)));
+        }
+        if damage > 0 && character.is_alive() {
+            character.take_damage(damage);
+            log_events.send(CombatLogEvent(format!("{} suffers {} damage from {}", character.name, damage, source)));
+            if !character.is_alive() {
+                log_events.send(CombatLogEvent(format!("{} has fallen!", character.name)));
+                fallen.send(CharacterFell {
+                    entity,
+                    name: character.name.clone(),
+                    is_player: combatant.is_player,
+                    cause: cause_of_death(&kind).to_string(),
+                    killer: None,
+                });
+            }
+        }
+        // Diseases run by the day, not the round (see ailments.rs)
         combatant.status_effects.retain_mut(|effect| {
-            effect.duration -= 1;
+            if matches!(effect.effect_type, EffectType::Disease) || effect.duration == WHOLE_FIGHT {
+                return true;
+            }
+            effect.duration = effect.duration.saturating_sub(1);
             effect.duration > 0
         });
     }
 }
-
-// Combat UI helper functions
-pub fn get_combat_text(attacker: &Character, target: &Character, hit: bool, damage: i16) -> String {
-    if hit {
-        format!("{} hits {} for {} damage!", attacker.name, target.name, damage)
-    } else {
-        format!("{} misses {}!", attacker.name, target.name)
-    }
-}
-
-pub fn get_initiative_text(combatant: &Character, initiative: i8) -> String {
-    format!("{} rolls initiative: {}", combatant.name, initiative)
-} 
\ No newline at end of file
@@ -0,0 +1,503 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::ai_backend::{AiBackendKind, DEFAULT_OPENAI_MODEL, DEFAULT_OPENAI_URL};
+use crate::camera::CameraMode;
+use crate::ruleset::{AttackMethod, ExperienceTables};
+use crate::GameConfig;
+
+// Settings kept from one launch to the next in config.toml, in the directory the game is
+// run from. The first launch writes it out with the defaults, and a file that leaves
+// something out gets the default for that. Flags on the command line win over the file
+// for that launch only. While the game runs the file is looked at every couple of
+// seconds: new key bindings, window sizes, text mode, the camera, volumes and speech take
+// effect at once, and anything else changed waits for the next launch. The Settings
+// screen's own changes are written back with save_settings.
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+const WATCH_INTERVAL_SECS: f32 = 2.0;
+
+// The keys for exploring. The arrow keys always move the party as well, and Escape
+// always backs out. `end_turn` hands a campaign turn on (see campaign.rs).
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_up: KeyCode,
+    pub move_left: KeyCode,
+    pub move_down: KeyCode,
+    pub move_right: KeyCode,
+    pub force_door: KeyCode,
+    pub pick_lock: KeyCode,
+    pub search: KeyCode,
+    pub torch: KeyCode,
+    pub rest: KeyCode,
+    pub travel: KeyCode,
+    pub town: KeyCode,
+    pub map: KeyCode,
+    pub inventory: KeyCode,
+    pub market: KeyCode,
+    pub quests: KeyCode,
+    pub spellbook: KeyCode,
+    pub quick_save: KeyCode,
+    pub save_new_slot: KeyCode,
+    pub quick_load: KeyCode,
+    pub camera: KeyCode,
+    pub zoom_in: KeyCode,
+    pub zoom_out: KeyCode,
+    pub end_turn: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_up: KeyCode::W,
+            move_left: KeyCode::A,
+            move_down: KeyCode::S,
+            move_right: KeyCode::D,
+            force_door: KeyCode::F,
+            pick_lock: KeyCode::P,
+            search: KeyCode::X,
+            torch: KeyCode::T,
+            rest: KeyCode::R,
+            travel: KeyCode::J,
+            town: KeyCode::H,
+            map: KeyCode::Tab,
+            inventory: KeyCode::I,
+            market: KeyCode::M,
+            quests: KeyCode::Q,
+            spellbook: KeyCode::B,
+            quick_save: KeyCode::F5,
+            save_new_slot: KeyCode::F6,
+            quick_load: KeyCode::F9,
+            camera: KeyCode::C,
+            zoom_in: KeyCode::Equals,
+            zoom_out: KeyCode::Minus,
+            end_turn: KeyCode::End,
+        }
+    }
+}
+
+// How a key is written in config.toml, and shown on screen
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+impl KeyBindings {
+    // The controls line along the top of the exploration screen
+    pub fn hint(&self) -> String {
+        let movement = [self.move_up, self.move_left, self.move_down, self.move_right].map(key_name).concat();
+        let actions = [
+            (self.force_door, "Force door"),
+            (self.pick_lock, "Pick lock"),
+            (self.search, "Search"),
+            (self.travel, "Travel"),
+            (self.town, "Town"),
+            (self.map, "Map"),
+            (self.inventory, "Inventory"),
+            (self.quests, "Quests"),
+            (self.spellbook, "Spells"),
+            (self.market, "Market"),
+            (self.torch, "Torch"),
+            (self.rest, "Rest"),
+            (self.camera, "Camera"),
+        ];
+        let mut hint = format!("{}: Move", movement);
+        for (key, action) in actions {
+            hint.push_str(&format!(" | {}: {}", key_name(key), action));
+        }
+        hint.push_str(&format!(" | {}/{}: Zoom", key_name(self.zoom_in), key_name(self.zoom_out)));
+        hint.push_str(" | ESC: Menu");
+        hint
+    }
+
+    // Every binding, by its name in config.toml
+    fn all(&self) -> [(&'static str, KeyCode); 23] {
+        [
+            ("move_up", self.move_up),
+            ("move_left", self.move_left),
+            ("move_down", self.move_down),
+            ("move_right", self.move_right),
+            ("force_door", self.force_door),
+            ("pick_lock", self.pick_lock),
+            ("search", self.search),
+            ("torch", self.torch),
+            ("rest", self.rest),
+            ("travel", self.travel),
+            ("town", self.town),
+            ("map", self.map),
+            ("inventory", self.inventory),
+            ("market", self.market),
+            ("quests", self.quests),
+            ("spellbook", self.spellbook),
+            ("quick_save", self.quick_save),
+            ("save_new_slot", self.save_new_slot),
+            ("quick_load", self.quick_load),
+            ("camera", self.camera),
+            ("zoom_in", self.zoom_in),
+            ("zoom_out", self.zoom_out),
+            ("end_turn", self.end_turn),
+        ]
+    }
+
+    // "T is bound to both torch and end_turn", for each key bound twice
+    pub fn clashes(&self) -> Vec<String> {
+        let all = self.all();
+        all.iter()
+            .enumerate()
+            .flat_map(|(index, (name, key))| {
+                all[index + 1..].iter().filter(move |(_, other)| other == key).map(move |(other, _)| {
+                    format!("{} is bound to both {} and {}", key_name(*key), name, other)
+                })
+            })
+            .collect()
+    }
+}
+
+// config.toml, section by section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub ai: AiSettings,
+    pub saves: SaveSettings,
+    pub window: WindowSettings,
+    pub rules: RuleSettings,
+    pub audio: AudioSettings,
+    pub keys: KeyBindings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiSettings {
+    // "service", "openai" or "mock"
+    pub backend: String,
+    pub service_url: String,
+    pub openai_url: String,
+    pub openai_model: String,
+    pub timeout_secs: u64,
+    pub retries: u32,
+    pub concurrency: usize,
+    pub requests_per_minute: u32,
+    pub tokens_per_minute: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveSettings {
+    pub directory: String,
+    pub journal_directory: String,
+    pub journal_ai_recap: bool,
+    // New parties are played ironman: their saves go when they die
+    pub ironman: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+    pub text_mode: bool,
+    pub camera: CameraMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSettings {
+    pub attack_method: AttackMethod,
+    pub battle_map: bool,
+    pub encumbrance_penalties: bool,
+    pub phased_rounds: bool,
+    pub treasure_xp: bool,
+    // XP for each level by class; written out only once changed from B/X's
+    #[serde(skip_serializing_if = "is_bx_experience")]
+    pub experience_tables: ExperienceTables,
+    // Fixed dice seed; left out for a random one each launch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    // 0 to 1
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub speech: bool,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self::from_config(&GameConfig::default())
+    }
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        ConfigFile::default().ai
+    }
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        ConfigFile::default().saves
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        ConfigFile::default().window
+    }
+}
+
+impl Default for RuleSettings {
+    fn default() -> Self {
+        ConfigFile::default().rules
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        ConfigFile::default().audio
+    }
+}
+
+fn is_bx_experience(tables: &ExperienceTables) -> bool {
+    *tables == ExperienceTables::default()
+}
+
+impl ConfigFile {
+    pub fn from_config(config: &GameConfig) -> Self {
+        let (backend, openai_url, openai_model) = match &config.ai_backend {
+            AiBackendKind::Service => ("service", DEFAULT_OPENAI_URL, DEFAULT_OPENAI_MODEL),
+            AiBackendKind::OpenAi { base_url, model } => ("openai", base_url.as_str(), model.as_str()),
+            AiBackendKind::Mock => ("mock", DEFAULT_OPENAI_URL, DEFAULT_OPENAI_MODEL),
+        };
+        Self {
+            ai: AiSettings {
+                backend: backend.to_string(),
+                service_url: config.ai_service_url.clone(),
+                openai_url: openai_url.to_string(),
+                openai_model: openai_model.to_string(),
+                timeout_secs: config.ai_requests.timeout.as_secs(),
+                retries: config.ai_requests.retries,
+                concurrency: config.ai_limits.concurrent,
+                requests_per_minute: config.ai_limits.requests_per_minute,
+                tokens_per_minute: config.ai_limits.tokens_per_minute,
+            },
+            saves: SaveSettings {
+                directory: config.save_dir.clone(),
+                journal_directory: config.journal_dir.clone(),
+                journal_ai_recap: config.journal_ai_recap,
+                ironman: config.ironman,
+            },
+            window: WindowSettings {
+                width: config.resolution.0,
+                height: config.resolution.1,
+                text_mode: config.text_mode,
+                camera: config.camera,
+            },
+            rules: RuleSettings {
+                attack_method: config.ruleset.attack_method,
+                battle_map: config.ruleset.battle_map,
+                encumbrance_penalties: config.ruleset.encumbrance_penalties,
+                phased_rounds: config.ruleset.phased_rounds,
+                treasure_xp: config.ruleset.treasure_xp,
+                experience_tables: config.ruleset.experience_tables.clone(),
+                seed: config.rng_seed,
+            },
+            audio: AudioSettings {
+                music_volume: config.music_volume,
+                sfx_volume: config.sfx_volume,
+                speech: config.speech,
+            },
+            keys: config.keys.clone(),
+        }
+    }
+
+    pub fn apply(&self, config: &mut GameConfig) {
+        config.ai_backend = match AiBackendKind::parse(&self.ai.backend) {
+            Some(AiBackendKind::OpenAi { .. }) => AiBackendKind::OpenAi {
+                base_url: self.ai.openai_url.clone(),
+                model: self.ai.openai_model.clone(),
+            },
+            Some(kind) => kind,
+            None => {
+                warn!("Unknown AI backend {:?} in {}; using the service", self.ai.backend, CONFIG_FILE);
+                AiBackendKind::Service
+            }
+        };
+        config.ai_service_url = self.ai.service_url.clone();
+        config.ai_requests.timeout = Duration::from_secs(self.ai.timeout_secs);
+        config.ai_requests.retries = self.ai.retries;
+        config.ai_limits.concurrent = self.ai.concurrency;
+        config.ai_limits.requests_per_minute = self.ai.requests_per_minute;
+        config.ai_limits.tokens_per_minute = self.ai.tokens_per_minute;
+        config.save_dir = self.saves.directory.clone();
+        config.journal_dir = self.saves.journal_directory.clone();
+        config.journal_ai_recap = self.saves.journal_ai_recap;
+        config.ironman = self.saves.ironman;
+        config.resolution = (self.window.width, self.window.height);
+        config.text_mode = self.window.text_mode;
+        config.camera = self.window.camera;
+        config.ruleset.attack_method = self.rules.attack_method;
+        config.ruleset.battle_map = self.rules.battle_map;
+        config.ruleset.encumbrance_penalties = self.rules.encumbrance_penalties;
+        config.ruleset.phased_rounds = self.rules.phased_rounds;
+        config.ruleset.treasure_xp = self.rules.treasure_xp;
+        config.ruleset.experience_tables = self.rules.experience_tables.clone();
+        config.rng_seed = self.rules.seed;
+        config.music_volume = self.audio.music_volume.clamp(0.0, 1.0);
+        config.sfx_volume = self.audio.sfx_volume.clamp(0.0, 1.0);
+        config.speech = self.audio.speech;
+        config.keys = self.keys.clone();
+        for clash in self.keys.clashes() {
+            warn!("{} in {}", clash, CONFIG_FILE);
+        }
+    }
+
+    // None if there's no file at `path`
+    pub fn read(path: &Path) -> Result<Option<Self>, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        toml::from_str(&text).map(Some).map_err(|e| e.to_string())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+impl GameConfig {
+    // The settings in `path`, which is written out with the defaults if it isn't there.
+    // A file that can't be read leaves the defaults in place and is left alone.
+    pub fn from_file(path: &Path) -> Self {
+        let mut config = Self::default();
+        match ConfigFile::read(path) {
+            Ok(Some(file)) => file.apply(&mut config),
+            Ok(None) => match ConfigFile::from_config(&config).write(path) {
+                Ok(()) => info!("Wrote the default settings to {}", path.display()),
+                Err(e) => warn!("Could not write {}: {}", path.display(), e),
+            },
+            Err(e) => warn!("Could not read {}, using the defaults: {}", path.display(), e),
+        }
+        config
+    }
+}
+
+// Writes the settings as they are now to the file they came from
+pub fn save_settings(config: &GameConfig, path: Option<&ConfigPath>) {
+    let Some(ConfigPath(path)) = path else {
+        return;
+    };
+    if let Err(e) = ConfigFile::from_config(config).write(path) {
+        warn!("Could not save the settings to {}: {}", path.display(), e);
+    }
+}
+
+// Where the settings were read from, for showing the player
+#[derive(Resource, Clone, Debug)]
+pub struct ConfigPath(pub PathBuf);
+
+#[derive(Resource)]
+struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // The file as last read, to tell what changed
+    last: Option<ConfigFile>,
+    timer: Timer,
+}
+
+pub struct ConfigPlugin {
+    pub path: PathBuf,
+}
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        let keys = app.world.get_resource::<GameConfig>().map(|config| config.keys.clone()).unwrap_or_default();
+        app.insert_resource(keys)
+            .insert_resource(ConfigPath(self.path.clone()))
+            .insert_resource(ConfigWatch {
+                path: self.path.clone(),
+                modified: modified(&self.path),
+                last: ConfigFile::read(&self.path).ok().flatten(),
+                timer: Timer::from_seconds(WATCH_INTERVAL_SECS, TimerMode::Repeating),
+            })
+            .add_systems(Update, reload_config);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn reload_config(
+    time: Res<Time>,
+    mut watch: ResMut<ConfigWatch>,
+    mut config: ResMut<GameConfig>,
+    mut keys: ResMut<KeyBindings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !watch.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let now = modified(&watch.path);
+    if now.is_none() || now == watch.modified {
+        return;
+    }
+    watch.modified = now;
+    let file = match ConfigFile::read(&watch.path) {
+        Ok(Some(file)) => file,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Could not reload {}: {}", watch.path.display(), e);
+            return;
+        }
+    };
+
+    if *keys != file.keys {
+        info!("Key bindings reloaded from {}", watch.path.display());
+        for clash in file.keys.clashes() {
+            warn!("{} in {}", clash, CONFIG_FILE);
+        }
+        *keys = file.keys.clone();
+        config.keys = file.keys.clone();
+    }
+    if config.resolution != (file.window.width, file.window.height) {
+        config.resolution = (file.window.width, file.window.height);
+        for mut window in windows.iter_mut() {
+            window.resolution.set(file.window.width as f32, file.window.height as f32);
+        }
+    }
+    if config.text_mode != file.window.text_mode {
+        config.text_mode = file.window.text_mode;
+    }
+    if config.camera != file.window.camera {
+        config.camera = file.window.camera;
+    }
+    let volumes = (file.audio.music_volume.clamp(0.0, 1.0), file.audio.sfx_volume.clamp(0.0, 1.0));
+    if (config.music_volume, config.sfx_volume) != volumes {
+        (config.music_volume, config.sfx_volume) = volumes;
+    }
+    if config.speech != file.audio.speech {
+        config.speech = file.audio.speech;
+    }
+    if let Some(last) = &watch.last {
+        let rest_changed = ConfigFile {
+            keys: last.keys.clone(),
+            window: last.window.clone(),
+            audio: last.audio.clone(),
+            ..file.clone()
+        } != *last;
+        if rest_changed {
+            info!("Other changes to {} take effect the next time the game starts", watch.path.display());
+        }
+    }
+    watch.last = Some(file);
+}
@@ -0,0 +1,640 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::{Event, Subscriber};
+use bevy::window::ReceivedCharacter;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use crate::character::{Character, CharacterClass};
+use crate::combat::Combatant;
+use crate::ai_client::{AIClient, DungeonGenerationRequest, DungeonSize};
+use crate::data::{Bestiary, ItemCatalog, PromptLibrary, TreasureTables};
+use crate::dungeon::{DungeonMap, FogOfWar, PartyToken, Position};
+use crate::encounters::Monster;
+use crate::gossip::Grapevine;
+use crate::rng::GameRng;
+use crate::scripts::ScriptBook;
+use crate::simulation::parse_class;
+use crate::theme::{TextSize, UiTheme};
+use crate::wilderness::{Terrain, Travel};
+use crate::world::{CurrentSite, EnterDungeon, LeaveDungeon, WorldDatabase};
+use old_school_core::monster::{builtin_monster, monster_character};
+use old_school_core::treasure::{builtin_table, roll_hoard};
+use crate::GameState;
+
+// Developer console, toggled with the grave key. It shows the most recent log lines
+// (captured by a tracing layer installed alongside the usual stdout logger) and runs
+// debug commands against the world. While it is open, keyboard input goes to the
+// console instead of the game.
+
+pub const DEFAULT_LOG_FILTER: &str = "info,wgpu=error,naga=warn";
+pub const CONSOLE_HISTORY: usize = 200;
+const VISIBLE_LINES: usize = 20;
+
+const HELP: &str = "Commands:
+  help                             show this list
+  list                             list characters and their entity ids
+  inspect <entity|name>            show an entity's components
+  spawn hero <class> [name]        spawn a player character
+  spawn monster <id> [count]       spawn goblin, orc, skeleton or troll
+  spawn item <name> [character]    add an item to a character's inventory
+  treasure <type>                  roll a hoard of treasure type A-V
+  map                              draw the current dungeon map
+  note <text>                      note on the automap where the party stands
+  enter <site> [theme] [level]     go to a site's dungeon, generating it the first time
+  leave                            leave the current site's dungeon
+  sites                            list the dungeons the party has been to or heard of
+  gossip                           list the rumors going round town, who knows them and which are true
+  prompts                          list the AI prompts loaded from assets/prompts
+  scripts                          list the scripts loaded from assets/scripts and mods
+  queue                            show AI requests running and waiting, and this minute's usage
+  travel <terrain> [days]          travel overland (clear, woods, river, hills, ...)
+  travel town                      go back to town
+  teleport <entity|name> <x> <y>   move an entity
+  state <name>                     switch game state
+  clear                            clear the console";
+
+// Log lines shared between the tracing layer and the console UI
+#[derive(Clone, Default)]
+pub struct ConsoleLog {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl ConsoleLog {
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= CONSOLE_HISTORY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+}
+
+struct ConsoleLayer {
+    log: ConsoleLog,
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let module = metadata.target().rsplit("::").next().unwrap_or_default();
+        self.log.push(format!("{:>5} {}: {}", metadata.level(), module, visitor.message));
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.message, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+// Replaces Bevy's LogPlugin (which must be disabled): stdout logging filtered by
+// RUST_LOG, plus a copy of every event for the console
+pub fn init_tracing() -> ConsoleLog {
+    let log = ConsoleLog::default();
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(ConsoleLayer { log: log.clone() })
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Could not install the tracing subscriber: {}", e);
+    }
+
+    log
+}
+
+#[derive(Resource)]
+pub struct DebugConsole {
+    pub open: bool,
+    pub input: String,
+    pub log: ConsoleLog,
+    submitted: Vec<String>,
+}
+
+#[derive(Component)]
+struct ConsoleUI;
+
+#[derive(Component)]
+struct ConsoleLogText;
+
+#[derive(Component)]
+struct ConsoleInputText;
+
+pub struct DebugConsolePlugin {
+    pub log: ConsoleLog,
+}
+
+impl Plugin for DebugConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DebugConsole {
+            open: false,
+            input: String::new(),
+            log: self.log.clone(),
+            submitted: Vec::new(),
+        })
+        .add_systems(PreUpdate, capture_console_input.after(InputSystem))
+        .add_systems(Update, (run_console_commands, update_console_ui).chain());
+    }
+}
+
+pub(crate) fn capture_console_input(
+    mut console: ResMut<DebugConsole>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+) {
+    let toggled = keyboard_input.just_pressed(KeyCode::Grave);
+    if toggled {
+        console.open = !console.open;
+    }
+    if !console.open {
+        characters.clear();
+        return;
+    }
+
+    for event in characters.read() {
+        if event.char == '`' || event.char.is_control() {
+            continue;
+        }
+        console.input.push(event.char);
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        if !line.trim().is_empty() {
+            console.submitted.push(line);
+        }
+    }
+
+    // The game doesn't see keys typed into the console
+    keyboard_input.reset_all();
+}
+
+fn run_console_commands(world: &mut World) {
+    let submitted = std::mem::take(&mut world.resource_mut::<DebugConsole>().submitted);
+
+    for line in submitted {
+        let _span = info_span!("console_command", command = %line).entered();
+        info!("> {}", line);
+
+        match execute_command(world, &line) {
+            Ok(output) => {
+                for output_line in output.lines() {
+                    info!("{}", output_line);
+                }
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+fn execute_command(world: &mut World, line: &str) -> Result<String, String> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+
+    match args.as_slice() {
+        ["help"] => Ok(HELP.to_string()),
+        ["clear"] => {
+            world.resource::<DebugConsole>().log.clear();
+            Ok(String::new())
+        }
+        ["list"] => Ok(list_characters(world)),
+        ["inspect", target] => {
+            let entity = find_entity(world, target)?;
+            Ok(inspect_entity(world, entity))
+        }
+        ["spawn", "hero", class, name @ ..] => {
+            let class = parse_class(class).ok_or_else(|| format!("Unknown class: {}", class))?;
+            let name = if name.is_empty() { format!("{:?}", class) } else { name.join(" ") };
+            let entity = spawn_hero(world, name.clone(), class);
+            Ok(format!("Spawned {} as {:?}", name, entity))
+        }
+        ["spawn", "monster", id] => spawn_monsters(world, id, 1),
+        ["spawn", "monster", id, count] => {
+            let count = count.parse().map_err(|_| format!("Not a number: {}", count))?;
+            spawn_monsters(world, id, count)
+        }
+        ["spawn", "item", name] => give_item(world, name, None),
+        ["spawn", "item", name, target] => give_item(world, name, Some(target)),
+        ["treasure", treasure_type] => roll_treasure(world, treasure_type),
+        ["map"] => draw_map(world),
+        ["note", text @ ..] if !text.is_empty() => {
+            let mut tokens = world.query_filtered::<&Position, With<PartyToken>>();
+            let position = tokens.get_single(world).map_err(|_| "The party isn't in a dungeon")?.0;
+            world.resource_mut::<FogOfWar>().annotate(position, text.join(" "));
+            Ok(format!("Noted at ({}, {})", position.x, position.y))
+        }
+        ["enter", site, rest @ ..] => enter_site(world, site, rest),
+        ["leave"] => {
+            world.send_event(LeaveDungeon);
+            Ok(String::new())
+        }
+        ["sites"] => Ok(list_sites(world)),
+        ["gossip"] => Ok(list_gossip(world)),
+        ["prompts"] => {
+            let names = world.get_resource::<PromptLibrary>().map(PromptLibrary::names).unwrap_or_default();
+            if names.is_empty() {
+                return Ok("No prompts loaded".to_string());
+            }
+            Ok(names.join("\n"))
+        }
+        ["scripts"] => {
+            let names: Vec<String> = world
+                .get_resource::<ScriptBook>()
+                .map(|book| book.names().into_iter().map(str::to_string).collect())
+                .unwrap_or_default();
+            if names.is_empty() {
+                return Ok("No scripts loaded".to_string());
+            }
+            Ok(names.join("\n"))
+        }
+        ["queue"] => Ok(describe_queue(world)),
+        ["travel", "town"] => {
+            world.resource_mut::<NextState<GameState>>().set(GameState::Town);
+            Ok("The party is back in town".to_string())
+        }
+        ["travel", terrain, rest @ ..] => {
+            let terrain = Terrain::parse(terrain).ok_or_else(|| format!("Unknown terrain: {}", terrain))?;
+            let days: u32 = match rest.first() {
+                Some(days) => days.parse().map_err(|_| format!("Not a number: {}", days))?,
+                None => 1,
+            };
+            for _ in 0..days {
+                world.send_event(Travel(terrain));
+            }
+            Ok(String::new())
+        }
+        ["teleport", target, x, y] => {
+            let entity = find_entity(world, target)?;
+            let x: f32 = x.parse().map_err(|_| format!("Not a number: {}", x))?;
+            let y: f32 = y.parse().map_err(|_| format!("Not a number: {}", y))?;
+
+            let mut entity_mut = world.entity_mut(entity);
+            match entity_mut.get_mut::<Transform>() {
+                Some(mut transform) => {
+                    transform.translation.x = x;
+                    transform.translation.y = y;
+                }
+                None => {
+                    entity_mut.insert(Transform::from_xyz(x, y, 0.0));
+                }
+            }
+            Ok(format!("Moved {:?} to ({}, {})", entity, x, y))
+        }
+        ["state", name] => {
+            let state = parse_state(name).ok_or_else(|| format!("Unknown state: {}", name))?;
+            world.resource_mut::<NextState<GameState>>().set(state.clone());
+            Ok(format!("Switching to {:?}", state))
+        }
+        _ => Err(format!("Unknown command: {} (try `help`)", line)),
+    }
+}
+
+// Accepts an entity index (as shown by `list`) or a character name
+fn find_entity(world: &mut World, target: &str) -> Result<Entity, String> {
+    // Entity debug output looks like "12v0"; the generation is ignored
+    let index_text = target.split('v').next().unwrap_or(target);
+    if let Ok(index) = index_text.parse::<u32>() {
+        return world
+            .iter_entities()
+            .map(|entity| entity.id())
+            .find(|entity| entity.index() == index)
+            .ok_or_else(|| format!("No entity with index {}", index));
+    }
+
+    let mut characters = world.query::<(Entity, &Character)>();
+    characters
+        .iter(world)
+        .find(|(_, character)| character.name.eq_ignore_ascii_case(target))
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| format!("No character named {}", target))
+}
+
+fn list_characters(world: &mut World) -> String {
+    let mut characters = world.query::<(Entity, &Character, Option<&Combatant>)>();
+    let mut out = String::new();
+    for (entity, character, combatant) in characters.iter(world) {
+        let side = match combatant {
+            Some(c) if !c.is_player => "enemy",
+            _ => "party",
+        };
+        let _ = writeln!(
+            out,
+            "{:>4}  {} ({}, level {} {:?}, HP {}/{})",
+            entity.index(),
+            character.name,
+            side,
+            character.level,
+            character.class,
+            character.hit_points.current,
+            character.hit_points.maximum
+        );
+    }
+
+    if out.is_empty() {
+        "No characters".to_string()
+    } else {
+        out
+    }
+}
+
+fn inspect_entity(world: &World, entity: Entity) -> String {
+    let mut out = format!("{:?}", entity);
+    for component in world.inspect_entity(entity) {
+        let _ = write!(out, "\n  {}", component.name());
+    }
+
+    let entity_ref = world.entity(entity);
+    if let Some(character) = entity_ref.get::<Character>() {
+        let _ = write!(
+            out,
+            "\nCharacter: {} level {} {:?}, HP {}/{}, AC {}, XP {}, {:?}",
+            character.name,
+            character.level,
+            character.class,
+            character.hit_points.current,
+            character.hit_points.maximum,
+            character.armor_class,
+            character.experience,
+            character.stats
+        );
+        if !character.inventory.items.is_empty() {
+            let items: Vec<&str> = character.inventory.items.iter().map(|item| item.name.as_str()).collect();
+            let _ = write!(out, "\nInventory: {}", items.join(", "));
+        }
+    }
+    if let Some(combatant) = entity_ref.get::<Combatant>() {
+        let _ = write!(out, "\n{:?}", combatant);
+    }
+    if let Some(transform) = entity_ref.get::<Transform>() {
+        let _ = write!(out, "\nPosition: {}", transform.translation);
+    }
+
+    out
+}
+
+fn spawn_hero(world: &mut World, name: String, class: CharacterClass) -> Entity {
+    let character = Character::new_with(name, class, world.resource_mut::<GameRng>().as_mut());
+    world
+        .spawn((
+            character,
+            Combatant {
+                initiative: 0,
+                is_player: true,
+                actions_remaining: 1,
+                status_effects: Vec::new(),
+            },
+        ))
+        .id()
+}
+
+// The loaded bestiary is checked first so edited stat lines can be tried straight away
+pub(crate) fn spawn_monsters(world: &mut World, id: &str, count: u32) -> Result<String, String> {
+    let from_bestiary = world.get_resource::<Bestiary>().and_then(|bestiary| bestiary.get(id)).cloned();
+    let enemy = match from_bestiary {
+        Some(enemy) => enemy,
+        None => builtin_monster(id).ok_or_else(|| format!("Unknown monster: {}", id))?,
+    };
+    let name = monster_character(&enemy).name;
+
+    for _ in 0..count {
+        let rolled = enemy.roll(world.resource_mut::<GameRng>().as_mut());
+        world.spawn((
+            monster_character(&rolled),
+            Monster(rolled),
+            Combatant {
+                initiative: 0,
+                is_player: false,
+                actions_remaining: 1,
+                status_effects: Vec::new(),
+            },
+        ));
+    }
+    Ok(format!("Spawned {} {}", count, name))
+}
+
+// Like monsters, the loaded tables come first and the built-in ones fill the gaps
+fn roll_treasure(world: &mut World, treasure_type: &str) -> Result<String, String> {
+    let table = world
+        .get_resource::<TreasureTables>()
+        .map_or_else(|| builtin_table(treasure_type), |tables| tables.table(treasure_type))
+        .ok_or_else(|| format!("Unknown treasure type: {}", treasure_type))?;
+    let hoard = roll_hoard(&table, world.resource_mut::<GameRng>().as_mut()).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Treasure type {}: {} ({} gp)",
+        treasure_type.to_uppercase(),
+        hoard.describe(),
+        hoard.gold_value()
+    ))
+}
+
+// Theme and level only matter if the site's dungeon has to be generated; a site known
+// from a rumor has them from there unless they're given
+fn enter_site(world: &mut World, site: &str, rest: &[&str]) -> Result<String, String> {
+    let hook = world.resource::<WorldDatabase>().hook(site).cloned();
+    let theme = match (rest.first(), &hook) {
+        (Some(theme), _) => theme.to_string(),
+        (None, Some(hook)) => hook.theme.clone(),
+        (None, None) => "ruins".to_string(),
+    };
+    let level = match rest.get(1) {
+        Some(level) => level.parse().map_err(|_| format!("Not a number: {}", level))?,
+        None => hook.map_or(1, |hook| hook.level),
+    };
+    let request = DungeonGenerationRequest { level, theme, size: DungeonSize::Medium, difficulty: level };
+    world.send_event(EnterDungeon { site: site.to_string(), request });
+    Ok(String::new())
+}
+
+fn list_gossip(world: &World) -> String {
+    let mut output = String::new();
+    for rumor in &world.resource::<Grapevine>().rumors {
+        let truth = if rumor.truthful { "true" } else { "false" };
+        let _ = writeln!(output, "[{}, day {}] {}", truth, rumor.started, rumor.text);
+        let _ = writeln!(output, "  known to {}", rumor.known_to.join(", "));
+    }
+    if output.is_empty() {
+        output.push_str("Nothing is going round town");
+    }
+    output
+}
+
+fn describe_queue(world: &World) -> String {
+    let Some(client) = world.get_resource::<AIClient>() else {
+        return "No AI client".to_string();
+    };
+    let (status, limits) = (client.queue_status(), client.limits());
+    let mut output = String::new();
+    let _ = writeln!(output, "{} of {} running", status.running, limits.concurrent);
+    let waiting: Vec<&str> = status.waiting.iter().map(|priority| priority.name()).collect();
+    let waiting = if waiting.is_empty() { "none".to_string() } else { waiting.join(", ") };
+    let _ = writeln!(output, "{} waiting: {}", status.waiting.len(), waiting);
+    let _ = writeln!(output, "{} of {} requests this minute", status.requests_this_minute, limits.requests_per_minute);
+    let _ = write!(output, "{} of {} tokens this minute", status.tokens_this_minute, limits.tokens_per_minute);
+    output
+}
+
+fn list_sites(world: &World) -> String {
+    let current = world.resource::<CurrentSite>().0.as_deref();
+    let records = &world.resource::<WorldDatabase>().dungeons;
+    let mut output = String::new();
+    if let Some(site) = current {
+        let _ = writeln!(output, "{} (here)", site);
+    }
+    for (site, record) in records.iter().filter(|(site, _)| Some(site.as_str()) != current) {
+        let _ = writeln!(output, "{}: {}", site, record.dungeon.name);
+    }
+    for (site, hook) in &world.resource::<WorldDatabase>().hooks {
+        let _ = writeln!(output, "{}: rumored {} (level {})", site, hook.theme, hook.level);
+    }
+    if output.is_empty() {
+        output.push_str("The party hasn't been to any sites");
+    }
+    output
+}
+
+// '@' marks the party
+fn draw_map(world: &mut World) -> Result<String, String> {
+    let mut tokens = world.query_filtered::<&Position, With<PartyToken>>();
+    let markers: Vec<_> = tokens.iter(world).map(|position| (position.0, '@')).collect();
+    let map = world.get_resource::<DungeonMap>().ok_or("There is no dungeon")?;
+    Ok(map.render(&markers))
+}
+
+// Goes to the named character, or the first party member. Items not in the catalog
+// are made up on the spot, the way treasure is.
+pub(crate) fn give_item(world: &mut World, name: &str, target: Option<&str>) -> Result<String, String> {
+    let entity = match target {
+        Some(target) => find_entity(world, target)?,
+        None => {
+            let mut characters = world.query::<(Entity, &Character, Option<&Combatant>)>();
+            characters
+                .iter(world)
+                .find(|(_, _, combatant)| combatant.is_none_or(|c| c.is_player))
+                .map(|(entity, _, _)| entity)
+                .ok_or("There is no party member to give it to")?
+        }
+    };
+
+    let item = world.resource::<ItemCatalog>().treasure(name);
+    let mut character = world
+        .get_mut::<Character>(entity)
+        .ok_or_else(|| format!("{:?} is not a character", entity))?;
+    let item_name = item.name.clone();
+    character.inventory.add_item(item);
+    Ok(format!("Gave {} to {}", item_name, character.name))
+}
+
+fn parse_state(name: &str) -> Option<GameState> {
+    match name.to_lowercase().replace(['-', '_'], "").as_str() {
+        "mainmenu" | "menu" => Some(GameState::MainMenu),
+        "charactercreation" | "creation" => Some(GameState::CharacterCreation),
+        "ingame" | "game" => Some(GameState::InGame),
+        "town" => Some(GameState::Town),
+        "combat" => Some(GameState::Combat),
+        "inventory" => Some(GameState::Inventory),
+        "shop" | "market" => Some(GameState::Shop),
+        "automap" => Some(GameState::Map),
+        "quests" | "journal" => Some(GameState::Quests),
+        "spellbook" | "spells" => Some(GameState::Spellbook),
+        "settings" => Some(GameState::Settings),
+        "loadgame" | "load" => Some(GameState::LoadGame),
+        "gameover" => Some(GameState::GameOver),
+        "graveyard" => Some(GameState::Graveyard),
+        "loot" => Some(GameState::Loot),
+        "levelup" => Some(GameState::LevelUp),
+        _ => None,
+    }
+}
+
+fn update_console_ui(
+    mut commands: Commands,
+    console: Res<DebugConsole>,
+    theme: Res<UiTheme>,
+    existing: Query<Entity, With<ConsoleUI>>,
+    mut log_text: Query<&mut Text, (With<ConsoleLogText>, Without<ConsoleInputText>)>,
+    mut input_text: Query<&mut Text, (With<ConsoleInputText>, Without<ConsoleLogText>)>,
+) {
+    if !console.open {
+        for entity in existing.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let log = console.log.recent(VISIBLE_LINES).join("\n");
+    let input = format!("> {}_", console.input);
+
+    if existing.is_empty() {
+        spawn_console(&mut commands, &theme, log, input);
+        return;
+    }
+    if let Ok(mut text) = log_text.get_single_mut() {
+        text.sections[0].value = log;
+    }
+    if let Ok(mut text) = input_text.get_single_mut() {
+        text.sections[0].value = input;
+    }
+}
+
+fn spawn_console(commands: &mut Commands, theme: &UiTheme, log: String, input: String) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(45.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::FlexEnd,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            ConsoleUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(log, theme.mono(TextSize::Fine, theme.palette.soft)),
+                ConsoleLogText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(input, theme.mono(TextSize::Small, theme.palette.highlight)),
+                ConsoleInputText,
+            ));
+        });
+}
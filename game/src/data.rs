@@ -0,0 +1,568 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::io::{AssetSource, Reader};
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadedFolder, UntypedHandle};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::ai_client::EnemyData;
+use crate::mods::{ModList, ModPack};
+use crate::ruleset::Ruleset;
+
+pub use old_school_core::catalog::{ItemCatalog, ItemId};
+pub use old_school_core::spells::SpellCompendium;
+pub use old_school_core::treasure::TreasureRoll;
+pub use old_school_core::wilderness::{Terrain, WildernessEntry};
+use old_school_core::treasure::builtin_table;
+use old_school_core::wilderness::builtin_wilderness_table;
+
+// Game content that designers edit lives in JSON files under assets/data. Each kind
+// of file has its own double extension (goblins.bestiary.json, relics.items.json, ...)
+// so the matching loader is picked. With the `hot_reload` feature the asset server
+// watches the folder and swaps in edited files while the game is running. Each kind is
+// read into a resource of the same name (items into the ItemCatalog and spells into the
+// SpellCompendium, over the ones built into old_school_core): the game's own file first,
+// then each mod's in load order (see mods.rs), built again whenever any of them changes.
+// The core items and spells are kept with the rules in core/data and built into
+// old_school_core, which is what a game without the source tree has. Run from the source
+// tree, the folder is also the `core` asset source, read and (with `hot_reload`) watched
+// like assets/data, so core.items.json and core.spells.json go over the built-in ones as
+// the game's own files and edits to them show without a rebuild.
+// Systems should look content up there each time rather than caching copies.
+//
+// The prompts AI backends send are text files under assets/prompts, one for each kind of
+// request and named for it (conversation.prompt, rumor.prompt, ...), with system.prompt
+// going first. `{name}` in a prompt is filled in from the request: `{npc_data.name}`,
+// `{context.location}` and so on down into it, `{ruleset.battle_map}` from the rules
+// in play, and `{request}` with the whole request as JSON. Lists are written out
+// separated by commas; anything not found is left as written.
+
+// The asset source for core/data, and where that is from the game's folder
+pub const CORE_DATA_SOURCE: &str = "core";
+const CORE_DATA_DIR: &str = "../core/data";
+
+// One items file; together they make up the ItemCatalog resource
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemRegistry {
+    #[serde(flatten)]
+    pub catalog: ItemCatalog,
+}
+
+#[derive(Asset, TypePath, Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bestiary {
+    pub monsters: HashMap<String, EnemyData>,
+}
+
+// One spells file; together they make up the SpellCompendium resource
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpellList {
+    #[serde(flatten)]
+    pub compendium: SpellCompendium,
+}
+
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct PromptTemplate {
+    pub text: String,
+}
+
+// The prompts by name, shared with AI backends off the main thread and kept up to date
+// as the files load and change, along with the ruleset they can refer to
+#[derive(Resource, Clone, Default)]
+pub struct PromptLibrary(Arc<RwLock<PromptSet>>);
+
+#[derive(Default)]
+struct PromptSet {
+    // Each prompt with the layer it came from, so a mod's isn't put back by the game's
+    templates: HashMap<String, (usize, String)>,
+    ruleset: Value,
+}
+
+#[derive(Asset, TypePath, Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TreasureTables {
+    pub tables: HashMap<String, Vec<TreasureRoll>>,
+}
+
+#[derive(Asset, TypePath, Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WildernessTables {
+    pub tables: HashMap<Terrain, Vec<WildernessEntry>>,
+}
+
+// Which picture goes with what: classes by name, monsters by name or type and items by
+// name, weapon type or kind, each to an image under assets/ (see sprites.rs)
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpriteManifest {
+    #[serde(default)]
+    pub classes: HashMap<String, String>,
+    #[serde(default)]
+    pub monsters: HashMap<String, String>,
+    #[serde(default)]
+    pub items: HashMap<String, String>,
+}
+
+#[derive(Resource)]
+pub struct GameData {
+    // None without core/data to read
+    pub items: Option<Handle<ItemRegistry>>,
+    pub spells: Option<Handle<SpellList>>,
+    pub bestiary: Handle<Bestiary>,
+    pub prompts: Handle<LoadedFolder>,
+    pub scripts: Handle<LoadedFolder>,
+    pub treasure: Handle<TreasureTables>,
+    pub wilderness: Handle<WildernessTables>,
+    pub sprites: Handle<SpriteManifest>,
+    // Every mod's data files, and its prompts and scripts folders, in load order
+    pub mods: Vec<UntypedHandle>,
+    pub mod_folders: Vec<Handle<LoadedFolder>>,
+}
+
+// A kind of data file that mods can add to, and the resource its files make up
+trait Layered: Asset + Sized {
+    type Merged: Resource;
+
+    // What there is before any file
+    fn base() -> Self::Merged;
+    // This file over what came before it
+    fn add_to(&self, merged: &mut Self::Merged);
+    // The game's own file, unless the base is all of it
+    fn handle(data: &GameData) -> Option<&Handle<Self>>;
+}
+
+pub struct JsonDataLoader<A> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> A>,
+}
+
+impl<A> JsonDataLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for JsonDataLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(serde_json::from_slice(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+#[derive(Default)]
+pub struct PromptLoader;
+
+impl AssetLoader for PromptLoader {
+    type Asset = PromptTemplate;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<PromptTemplate, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            Ok(PromptTemplate { text })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["prompt"]
+    }
+}
+
+pub struct GameDataPlugin;
+
+impl Plugin for GameDataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ItemRegistry>()
+            .init_asset::<Bestiary>()
+            .init_asset::<SpellList>()
+            .init_asset::<PromptTemplate>()
+            .insert_resource(ItemCatalog::builtin().clone())
+            .insert_resource(SpellCompendium::builtin().clone())
+            .init_resource::<Bestiary>()
+            .init_resource::<TreasureTables>()
+            .init_resource::<WildernessTables>()
+            .init_resource::<PromptLibrary>()
+            .init_asset::<TreasureTables>()
+            .init_asset::<WildernessTables>()
+            .init_asset::<SpriteManifest>()
+            .register_asset_loader(JsonDataLoader::<ItemRegistry>::new(&["items.json"]))
+            .register_asset_loader(JsonDataLoader::<Bestiary>::new(&["bestiary.json"]))
+            .register_asset_loader(JsonDataLoader::<SpellList>::new(&["spells.json"]))
+            .register_asset_loader(PromptLoader)
+            .register_asset_loader(JsonDataLoader::<TreasureTables>::new(&["treasure.json"]))
+            .register_asset_loader(JsonDataLoader::<WildernessTables>::new(&["wilderness.json"]))
+            .register_asset_loader(JsonDataLoader::<SpriteManifest>::new(&["sprites.json"]))
+            .add_systems(PreStartup, load_game_data)
+            .add_systems(Update, (
+                report_data_changes::<ItemRegistry>,
+                layer_data::<ItemRegistry>,
+                report_data_changes::<Bestiary>,
+                layer_data::<Bestiary>,
+                report_data_changes::<SpellList>,
+                layer_data::<SpellList>,
+                report_data_changes::<PromptTemplate>,
+                sync_prompt_library,
+                report_data_changes::<TreasureTables>,
+                layer_data::<TreasureTables>,
+                report_data_changes::<WildernessTables>,
+                layer_data::<WildernessTables>,
+                report_data_changes::<SpriteManifest>,
+            ));
+    }
+}
+
+// core/data, relative to the game's folder as assets/ is
+fn core_data_dir() -> PathBuf {
+    FileAssetReader::get_base_path().join(CORE_DATA_DIR)
+}
+
+// Has to be called before the AssetPlugin is added. Only a folder that is there is
+// registered, as the file reader would make an empty one.
+pub fn register_core_data_source(app: &mut App) {
+    if !core_data_dir().is_dir() {
+        return;
+    }
+    app.register_asset_source(
+        CORE_DATA_SOURCE,
+        AssetSource::build()
+            .with_reader(AssetSource::get_default_reader(CORE_DATA_DIR.to_string()))
+            .with_watcher(AssetSource::get_default_watcher(CORE_DATA_DIR.to_string(), Duration::from_millis(300))),
+    );
+}
+
+fn load_game_data(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut mod_list = ModList::scan();
+    let mut mods = Vec::new();
+    let mut problems = Vec::new();
+    for pack in &mod_list.packs {
+        for file in &pack.data_files {
+            match load_data_file(&asset_server, file) {
+                Some(handle) => mods.push(handle),
+                None => {
+                    let problem = format!("{} isn't a kind of data file the game reads", file);
+                    warn!("{}", problem);
+                    problems.push(problem);
+                }
+            }
+        }
+    }
+    mod_list.problems.extend(problems);
+    let mod_folders = mod_list
+        .packs
+        .iter()
+        .flat_map(ModPack::folder_paths)
+        .map(|path| asset_server.load_folder(path))
+        .collect();
+    commands.insert_resource(mod_list);
+
+    let core_data = core_data_dir().is_dir();
+    commands.insert_resource(GameData {
+        items: core_data.then(|| asset_server.load(format!("{}://core.items.json", CORE_DATA_SOURCE))),
+        spells: core_data.then(|| asset_server.load(format!("{}://core.spells.json", CORE_DATA_SOURCE))),
+        bestiary: asset_server.load("data/core.bestiary.json"),
+        prompts: asset_server.load_folder("prompts"),
+        scripts: asset_server.load_folder("scripts"),
+        treasure: asset_server.load("data/core.treasure.json"),
+        wilderness: asset_server.load("data/core.wilderness.json"),
+        sprites: asset_server.load("data/core.sprites.json"),
+        mods,
+        mod_folders,
+    });
+}
+
+// A mod's data file by its extension
+fn load_data_file(asset_server: &AssetServer, path: &str) -> Option<UntypedHandle> {
+    let path = path.to_string();
+    let handle = match path {
+        _ if path.ends_with(".items.json") => asset_server.load::<ItemRegistry>(path).untyped(),
+        _ if path.ends_with(".bestiary.json") => asset_server.load::<Bestiary>(path).untyped(),
+        _ if path.ends_with(".spells.json") => asset_server.load::<SpellList>(path).untyped(),
+        _ if path.ends_with(".treasure.json") => asset_server.load::<TreasureTables>(path).untyped(),
+        _ if path.ends_with(".wilderness.json") => asset_server.load::<WildernessTables>(path).untyped(),
+        _ => return None,
+    };
+    Some(handle)
+}
+
+fn report_data_changes<A: Asset>(mut events: EventReader<AssetEvent<A>>, asset_server: Res<AssetServer>) {
+    for event in events.read() {
+        let (id, verb) = match event {
+            AssetEvent::Added { id } => (id, "Loaded"),
+            AssetEvent::Modified { id } => (id, "Reloaded"),
+            _ => continue,
+        };
+        match asset_server.get_path(*id) {
+            Some(path) => info!("{} {}", verb, path),
+            None => debug!("{} {}", verb, A::short_type_path()),
+        }
+    }
+}
+
+impl Bestiary {
+    pub fn get(&self, id: &str) -> Option<&EnemyData> {
+        self.monsters.get(&id.to_lowercase())
+    }
+
+    // A monster named only by id, filled in from here
+    pub fn resolve(&self, enemy: &EnemyData) -> EnemyData {
+        enemy.resolve(|id| self.get(id))
+    }
+
+    // Ids in order, so picking one at random goes the same way for the same seed
+    pub fn ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.monsters.keys().map(String::as_str).collect();
+        ids.sort();
+        ids
+    }
+}
+
+impl PromptLibrary {
+    // The prompt called `name` filled in from `request`, if there is one
+    pub fn render(&self, name: &str, request: &Value) -> Option<String> {
+        let prompts = self.0.read().ok()?;
+        let (_, template) = prompts.templates.get(name)?;
+        let mut variables = match request {
+            Value::Object(fields) => fields.clone(),
+            _ => Default::default(),
+        };
+        variables.insert("ruleset".to_string(), prompts.ruleset.clone());
+        variables.insert("request".to_string(), Value::String(request.to_string()));
+        Some(render_template(template, &Value::Object(variables)))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> =
+            self.0.read().map(|prompts| prompts.templates.keys().cloned().collect()).unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+// Fills each `{path.to.value}` in `template` from `variables`
+pub fn render_template(template: &str, variables: &Value) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let path = after.find('}').map(|close| &after[..close]).filter(|path| {
+            path.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && path.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        });
+        let value = path.and_then(|path| path.split('.').try_fold(variables, |value, key| value.get(key)));
+        match (path, value) {
+            (Some(path), Some(value)) => {
+                text.push_str(&write_value(value));
+                rest = &after[path.len() + 1..];
+            }
+            _ => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+fn write_value(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) if items.is_empty() => "nothing".to_string(),
+        Value::Array(items) => items.iter().map(write_value).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+// A mod's prompt replaces the game's of the same name, and a later mod's an earlier one's,
+// whichever loads first
+fn sync_prompt_library(
+    mut events: EventReader<AssetEvent<PromptTemplate>>,
+    prompts: Res<Assets<PromptTemplate>>,
+    asset_server: Res<AssetServer>,
+    ruleset: Option<Res<Ruleset>>,
+    mod_list: Option<Res<ModList>>,
+    library: Res<PromptLibrary>,
+) {
+    let Ok(mut set) = library.0.write() else {
+        return;
+    };
+    if let Some(ruleset) = ruleset.filter(|ruleset| ruleset.is_changed()) {
+        set.ruleset = serde_json::to_value(&*ruleset).unwrap_or_default();
+    }
+    for event in events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        let Some(path) = asset_server.get_path(*id) else {
+            continue;
+        };
+        let layer = mod_list.as_ref().map_or(0, |mod_list| mod_list.layer(path.path()));
+        let name = Path::new(path.path()).file_stem().map(|stem| stem.to_string_lossy().to_string());
+        let (Some(name), Some(prompt)) = (name, prompts.get(*id)) else {
+            continue;
+        };
+        if set.templates.get(&name).is_none_or(|(existing, _)| layer >= *existing) {
+            set.templates.insert(name, (layer, prompt.text.clone()));
+        }
+    }
+}
+
+// The game's own file and then the mods' go one over another into the resource, started
+// afresh whenever any of them loads or changes, so the load order holds however the files
+// come in and an item taken out of a file is gone after a reload
+fn layer_data<A: Layered>(
+    mut events: EventReader<AssetEvent<A>>,
+    assets: Res<Assets<A>>,
+    game_data: Option<Res<GameData>>,
+    mut merged: ResMut<A::Merged>,
+) {
+    let changed = events
+        .read()
+        .filter(|event| matches!(event, AssetEvent::Added { .. } | AssetEvent::Modified { .. }))
+        .count();
+    let Some(game_data) = game_data.filter(|_| changed > 0) else {
+        return;
+    };
+    let mods = game_data
+        .mods
+        .iter()
+        .filter(|handle| handle.type_id() == TypeId::of::<A>())
+        .map(|handle| handle.clone().typed::<A>());
+    let mut layered = A::base();
+    for handle in A::handle(&game_data).cloned().into_iter().chain(mods) {
+        if let Some(asset) = assets.get(&handle) {
+            asset.add_to(&mut layered);
+        }
+    }
+    *merged = layered;
+}
+
+impl Layered for ItemRegistry {
+    type Merged = ItemCatalog;
+
+    fn base() -> ItemCatalog {
+        ItemCatalog::builtin().clone()
+    }
+
+    fn add_to(&self, catalog: &mut ItemCatalog) {
+        catalog.extend(&self.catalog);
+    }
+
+    fn handle(data: &GameData) -> Option<&Handle<Self>> {
+        data.items.as_ref()
+    }
+}
+
+impl Layered for SpellList {
+    type Merged = SpellCompendium;
+
+    fn base() -> SpellCompendium {
+        SpellCompendium::builtin().clone()
+    }
+
+    fn add_to(&self, compendium: &mut SpellCompendium) {
+        compendium.extend(&self.compendium);
+    }
+
+    fn handle(data: &GameData) -> Option<&Handle<Self>> {
+        data.spells.as_ref()
+    }
+}
+
+impl Layered for Bestiary {
+    type Merged = Bestiary;
+
+    fn base() -> Bestiary {
+        Bestiary::default()
+    }
+
+    fn add_to(&self, bestiary: &mut Bestiary) {
+        bestiary.monsters.extend(self.monsters.iter().map(|(id, monster)| (id.to_lowercase(), monster.clone())));
+    }
+
+    fn handle(data: &GameData) -> Option<&Handle<Self>> {
+        Some(&data.bestiary)
+    }
+}
+
+impl Layered for TreasureTables {
+    type Merged = TreasureTables;
+
+    fn base() -> TreasureTables {
+        TreasureTables::default()
+    }
+
+    fn add_to(&self, tables: &mut TreasureTables) {
+        tables.tables.extend(self.tables.iter().map(|(kind, table)| (kind.to_uppercase(), table.clone())));
+    }
+
+    fn handle(data: &GameData) -> Option<&Handle<Self>> {
+        Some(&data.treasure)
+    }
+}
+
+impl Layered for WildernessTables {
+    type Merged = WildernessTables;
+
+    fn base() -> WildernessTables {
+        WildernessTables::default()
+    }
+
+    fn add_to(&self, tables: &mut WildernessTables) {
+        tables.tables.extend(self.tables.iter().map(|(terrain, table)| (*terrain, table.clone())));
+    }
+
+    fn handle(data: &GameData) -> Option<&Handle<Self>> {
+        Some(&data.wilderness)
+    }
+}
+
+impl TreasureTables {
+    pub fn get(&self, treasure_type: &str) -> Option<&[TreasureRoll]> {
+        self.tables.get(&treasure_type.to_uppercase()).map(Vec::as_slice)
+    }
+
+    // The loaded table, or the built-in B/X one for types the data doesn't define
+    pub fn table(&self, treasure_type: &str) -> Option<Vec<TreasureRoll>> {
+        self.get(treasure_type).map(<[TreasureRoll]>::to_vec).or_else(|| builtin_table(treasure_type))
+    }
+}
+
+impl WildernessTables {
+    // The loaded table, or the built-in one for terrain the data doesn't cover
+    pub fn table(&self, terrain: Terrain) -> Vec<WildernessEntry> {
+        self.tables.get(&terrain).cloned().unwrap_or_else(|| builtin_wilderness_table(terrain))
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::character::{Character, CharacterClass};
+use crate::clock::{ClockSet, DayPassed, GameClock};
+use crate::combat::{CharacterFell, Combatant};
+use crate::inventory::InventoryMessage;
+use crate::message_log::{LogMessage, MessageCategory};
+use crate::rng::GameRng;
+
+pub use old_school_core::death::{
+    party_movement_rate, survival_chance, Death, RaiseOutcome, BODY_COINS, RECOVERY_DAYS, SCROLL_CASTER_LEVEL,
+    TEMPLE_CASTER_LEVEL,
+};
+
+// The dead stay with the party (see old_school_core::death for the rules). A member who
+// falls is marked dead with the day and what did it; each day that ends takes a day off
+// the bed rest of anyone raised; and a cleric can read a scroll of Raise Dead over a
+// body from the inventory screen. The temple's priests raise and bury the dead as well
+// (see temple.rs).
+
+// A cleric reading the scroll at `item` in their pack
+#[derive(Event, Clone, Debug)]
+pub struct ReadRaiseDead {
+    pub reader: Entity,
+    pub item: usize,
+}
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ReadRaiseDead>()
+            .add_systems(Update, (mark_the_dead, read_raise_dead))
+            .add_systems(Update, convalesce.after(ClockSet));
+    }
+}
+
+// Whatever was ailing them goes with them
+fn mark_the_dead(
+    mut fallen: EventReader<CharacterFell>,
+    mut characters: Query<(&mut Character, &mut Combatant)>,
+    clock: Res<GameClock>,
+) {
+    for fell in fallen.read().filter(|fell| fell.is_player) {
+        if let Ok((mut character, mut combatant)) = characters.get_mut(fell.entity) {
+            character.die(clock.day(), &fell.cause);
+            combatant.status_effects.clear();
+        }
+    }
+}
+
+fn convalesce(
+    mut days: EventReader<DayPassed>,
+    mut characters: Query<&mut Character>,
+    mut log: EventWriter<LogMessage>,
+) {
+    for _ in days.read() {
+        for mut character in characters.iter_mut().filter(|character| character.is_recovering()) {
+            if character.recover_a_day() {
+                log.send(LogMessage {
+                    category: MessageCategory::Notice,
+                    text: format!("{} is back on their feet", character.name),
+                });
+            }
+        }
+    }
+}
+
+// Over the first body the party carries that the scroll can still help
+fn read_raise_dead(
+    mut commands: Commands,
+    mut reads: EventReader<ReadRaiseDead>,
+    mut party: Query<(Entity, &mut Character, &Combatant)>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
+    mut message: ResMut<InventoryMessage>,
+) {
+    for read in reads.read() {
+        let Ok((_, reader, _)) = party.get(read.reader) else {
+            continue;
+        };
+        let refusal = if reader.class != CharacterClass::Cleric {
+            Some(format!("Only a cleric can read the scroll, and {} is no cleric", reader.name))
+        } else if !reader.is_alive() || reader.is_recovering() {
+            Some(format!("{} is in no state to read a scroll", reader.name))
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            message.0 = Some(reason);
+            continue;
+        }
+
+        let bodies: Vec<(Entity, Option<String>)> = party
+            .iter()
+            .filter(|(_, character, combatant)| combatant.is_player && !character.is_alive())
+            .map(|(entity, character, _)| (entity, character.raise_refusal(SCROLL_CASTER_LEVEL, clock.day())))
+            .collect();
+        let Some(body) = bodies.iter().find(|(_, refusal)| refusal.is_none()).map(|(entity, _)| *entity) else {
+            let reason = bodies.into_iter().find_map(|(_, refusal)| refusal);
+            message.0 = Some(reason.unwrap_or_else(|| "There is nobody to raise".to_string()));
+            continue;
+        };
+
+        if let Ok((_, mut reader, _)) = party.get_mut(read.reader) {
+            reader.use_up(read.item);
+            reader.recalculate_weight();
+        }
+        let Ok((_, mut dead, _)) = party.get_mut(body) else {
+            continue;
+        };
+        let text = raise(&mut commands, body, &mut dead, rng.as_mut());
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
+
+// Raises `character`, whose entity is `entity`, and says how it went; one who doesn't
+// survive it is gone from the party
+pub fn raise(commands: &mut Commands, entity: Entity, character: &mut Character, rng: &mut GameRng) -> String {
+    let (outcome, text) = character.raise(rng);
+    if outcome == RaiseOutcome::Lost {
+        commands.entity(entity).despawn_recursive();
+    }
+    text
+}
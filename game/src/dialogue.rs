@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{
+    AIRequestFailed, AIRequests, CancelConversation, ConversationContext, NPCConversationEvent,
+    NPCConversationResponseEvent,
+};
+use crate::text_input::{CharFilter, FieldAction, TextField, Typing};
+use crate::town::TownInputSet;
+use crate::GameState;
+
+// The dialogue panel. Talking to someone in town opens it, and their reply is typed
+// out as the AI service streams it in, a few characters a frame. Space shows the rest
+// at once; ESC closes the panel, and stops waiting on a reply that isn't done. Once
+// they've finished, the player can type something back and Enter says it.
+
+// Characters a second the typewriter reveals
+const CHARS_PER_SECOND: f32 = 60.0;
+
+#[derive(Resource, Default, Debug)]
+pub struct Dialogue {
+    // None while the panel is closed
+    pub npc_id: Option<String>,
+    pub speaker: String,
+    // What the NPC has said so far, and how many characters of it are on screen
+    pub text: String,
+    pub shown: usize,
+    // The whole reply has arrived
+    pub finished: bool,
+    // Part of a character the typewriter owes from earlier frames
+    carry: f32,
+    // What the player last said to them
+    pub said: Option<String>,
+    // What the player is typing back, once the reply is done
+    pub reply: Option<TextField>,
+    // Sent along again with each thing the player says
+    context: Option<ConversationContext>,
+}
+
+impl Dialogue {
+    pub fn open(&mut self, npc_id: &str, speaker: &str, context: ConversationContext) {
+        *self = Dialogue {
+            npc_id: Some(npc_id.to_string()),
+            speaker: speaker.to_string(),
+            context: Some(context),
+            ..default()
+        };
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.npc_id.is_some()
+    }
+
+    pub fn is_with(&self, npc_id: &str) -> bool {
+        self.npc_id.as_deref() == Some(npc_id)
+    }
+
+    // Done typing, and nothing more to come
+    pub fn is_complete(&self) -> bool {
+        self.finished && self.shown >= self.text.chars().count()
+    }
+
+    // The part of the reply the typewriter has reached
+    pub fn visible(&self) -> &str {
+        match self.text.char_indices().nth(self.shown) {
+            Some((end, _)) => &self.text[..end],
+            None => &self.text,
+        }
+    }
+
+    pub fn close(&mut self) {
+        *self = Dialogue::default();
+    }
+}
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Dialogue>()
+            .add_systems(OnExit(GameState::Town), walk_away)
+            .add_systems(Update, (
+                dialogue_input.before(TownInputSet),
+                follow_reply,
+                typewriter,
+            ).chain().run_if(in_state(GameState::Town)));
+    }
+}
+
+fn dialogue_input(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut typing: ResMut<Typing>,
+    mut dialogue: ResMut<Dialogue>,
+    mut conversations: EventWriter<NPCConversationEvent>,
+    mut cancellations: EventWriter<CancelConversation>,
+) {
+    let Some(npc_id) = dialogue.npc_id.clone() else {
+        return;
+    };
+    if dialogue.is_complete() {
+        let dialogue = dialogue.as_mut();
+        let reply = dialogue.reply.get_or_insert_with(|| TextField::new(CharFilter::Speech));
+        match reply.edit(&mut typing) {
+            Some(FieldAction::Cancel) => dialogue.close(),
+            Some(FieldAction::Submit) if !reply.is_blank() => {
+                let said = reply.text().trim().to_string();
+                if let Some(context) = dialogue.context.clone() {
+                    conversations.send(NPCConversationEvent {
+                        npc_id,
+                        player_message: said.clone(),
+                        context,
+                    });
+                }
+                dialogue.said = Some(said);
+                dialogue.reply = None;
+                dialogue.text.clear();
+                dialogue.shown = 0;
+                dialogue.finished = false;
+            }
+            _ => {}
+        }
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        dialogue.shown = dialogue.text.chars().count();
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        // ESC is the panel's, not the town's
+        keyboard_input.clear_just_pressed(KeyCode::Escape);
+        if !dialogue.finished {
+            cancellations.send(CancelConversation { npc_id });
+        }
+        dialogue.close();
+    }
+}
+
+// Takes in what has streamed in of the reply, then the whole of it
+fn follow_reply(
+    requests: Res<AIRequests>,
+    mut responses: EventReader<NPCConversationResponseEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut dialogue: ResMut<Dialogue>,
+) {
+    for response in responses.read() {
+        if dialogue.is_with(&response.npc_id) {
+            dialogue.text = response.response.npc_response.clone();
+            dialogue.finished = true;
+        }
+    }
+    if failures.read().any(|failure| failure.endpoint == "conversation") && dialogue.is_open() && !dialogue.finished {
+        dialogue.close();
+        return;
+    }
+    let Some(npc_id) = dialogue.npc_id.as_deref() else {
+        return;
+    };
+    if dialogue.finished {
+        return;
+    }
+    if let Some(partial) = requests.partial_reply(npc_id) {
+        if partial.len() > dialogue.text.len() {
+            dialogue.text = partial;
+        }
+    }
+}
+
+fn typewriter(time: Res<Time>, mut dialogue: ResMut<Dialogue>) {
+    let length = dialogue.text.chars().count();
+    if !dialogue.is_open() || dialogue.shown >= length {
+        return;
+    }
+    let owed = dialogue.carry + time.delta_seconds() * CHARS_PER_SECOND;
+    let whole = owed.floor();
+    dialogue.carry = owed - whole;
+    dialogue.shown = (dialogue.shown + whole as usize).min(length);
+}
+
+// Leaving town ends the conversation
+fn walk_away(mut dialogue: ResMut<Dialogue>, mut cancellations: EventWriter<CancelConversation>) {
+    if let Some(npc_id) = dialogue.npc_id.clone() {
+        if !dialogue.finished {
+            cancellations.send(CancelConversation { npc_id });
+        }
+        dialogue.close();
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+
+use crate::character::{Character, SkillCheckEvent, SkillCheckResult, ThiefSkill};
+use crate::combat::{Combatant, GridPosition};
+use crate::config::KeyBindings;
+use crate::dungeon::{Door, DungeonMap, PartyToken, Position};
+use crate::rng::GameRng;
+use crate::wandering::DungeonNoise;
+use crate::GameState;
+
+pub use old_school_core::doors::{open_doors_chance, roll_stuck, DoorState};
+
+// Opening doors. Walking into a closed door tries it: most swing open, some turn out to
+// be stuck, and a locked one opens only for a party carrying a key. F has the party's
+// strongest member bash at a stuck door next to the party, which makes noise (see
+// wandering.rs) whether or not it gives; P has the party's thief try the lock on a
+// locked door next to it. The rules are in old_school_core::doors.
+
+const CLOSED_DOOR_COLOR: Color = Color::rgb(0.55, 0.35, 0.15);
+const OPEN_DOOR_COLOR: Color = Color::rgb(0.45, 0.4, 0.3);
+const LOCKED_DOOR_COLOR: Color = Color::rgb(0.6, 0.2, 0.15);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum DoorAction {
+    Open(GridPosition),
+    Force(GridPosition),
+    PickLock(GridPosition),
+}
+
+// What happened at the last door, for the exploration screen
+#[derive(Resource, Default, Debug)]
+pub struct DoorMessage(pub Option<String>);
+
+// Lock picks waiting on their skill check, by who is picking and where
+#[derive(Resource, Default, Debug)]
+struct PendingPicks(Vec<(Entity, GridPosition)>);
+
+pub struct DoorsPlugin;
+
+impl Plugin for DoorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DoorAction>()
+            .init_resource::<DoorMessage>()
+            .init_resource::<PendingPicks>()
+            .add_systems(Update, (
+                door_keys.run_if(in_state(GameState::InGame)),
+                handle_door_actions,
+                resolve_lock_picks,
+            ).chain());
+    }
+}
+
+pub fn door_color(door: &Door) -> Color {
+    match door.state {
+        DoorState::Open => OPEN_DOOR_COLOR,
+        DoorState::Locked => LOCKED_DOOR_COLOR,
+        DoorState::Closed | DoorState::Stuck => CLOSED_DOOR_COLOR,
+    }
+}
+
+// The first door beside the party, straight across, in the given state
+fn adjacent_door(map: &DungeonMap, position: &GridPosition, state: DoorState) -> Option<GridPosition> {
+    [(0, -1), (1, 0), (0, 1), (-1, 0)]
+        .into_iter()
+        .map(|(dx, dy)| position.offset(dx, dy))
+        .find(|square| map.door_at(square).is_some_and(|door| !door.is_secret && door.state == state))
+}
+
+fn door_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    map: Option<Res<DungeonMap>>,
+    tokens: Query<&Position, With<PartyToken>>,
+    mut actions: EventWriter<DoorAction>,
+    mut message: ResMut<DoorMessage>,
+) {
+    let (Some(map), Ok(position)) = (map, tokens.get_single()) else {
+        return;
+    };
+    if keyboard_input.just_pressed(keys.force_door) {
+        match adjacent_door(&map, &position.0, DoorState::Stuck) {
+            Some(door) => actions.send(DoorAction::Force(door)),
+            None => message.0 = Some("There is no stuck door here".to_string()),
+        }
+    } else if keyboard_input.just_pressed(keys.pick_lock) {
+        match adjacent_door(&map, &position.0, DoorState::Locked) {
+            Some(door) => actions.send(DoorAction::PickLock(door)),
+            None => message.0 = Some("There is no locked door here".to_string()),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_door_actions(
+    mut actions: EventReader<DoorAction>,
+    map: Option<ResMut<DungeonMap>>,
+    party: Query<(Entity, &Character, &Combatant)>,
+    mut noise: ResMut<DungeonNoise>,
+    mut message: ResMut<DoorMessage>,
+    mut pending: ResMut<PendingPicks>,
+    mut skill_checks: EventWriter<SkillCheckEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(mut map) = map else {
+        actions.clear();
+        return;
+    };
+    let members = || {
+        party
+            .iter()
+            .filter(|(_, character, combatant)| combatant.is_player && character.is_alive())
+    };
+
+    for action in actions.read() {
+        let (DoorAction::Open(position) | DoorAction::Force(position) | DoorAction::PickLock(position)) = *action;
+        let Some(state) = map.door_at(&position).map(|door| door.state) else {
+            continue;
+        };
+
+        let (new_state, text) = match (action, state) {
+            (_, DoorState::Open) => (None, "The door is already open".to_string()),
+            (DoorAction::Open(_), DoorState::Closed) => {
+                if roll_stuck(rng.as_mut()) {
+                    (Some(DoorState::Stuck), "The door is stuck. F to force it".to_string())
+                } else {
+                    (Some(DoorState::Open), "The door opens".to_string())
+                }
+            }
+            (DoorAction::Open(_), DoorState::Stuck) => (None, "The door is stuck. F to force it".to_string()),
+            (DoorAction::Open(_) | DoorAction::Force(_), DoorState::Locked) => {
+                match members().find(|(_, character, _)| character.has_key()) {
+                    Some((_, character, _)) => (Some(DoorState::Open), format!("{} unlocks the door", character.name)),
+                    None => (None, "The door is locked. P to pick the lock".to_string()),
+                }
+            }
+            (DoorAction::Force(_), DoorState::Closed | DoorState::Stuck) => {
+                let Some((_, character, _)) = members().max_by_key(|(_, character, _)| character.stats.strength) else {
+                    continue;
+                };
+                noise.0 = noise.0.saturating_add(1);
+                if character.force_door(rng.as_mut()) {
+                    (Some(DoorState::Open), format!("{} forces the door open with a crash", character.name))
+                } else {
+                    (None, format!("{} bashes at the door, but it holds", character.name))
+                }
+            }
+            (DoorAction::PickLock(_), DoorState::Locked) => {
+                let thief = members()
+                    .filter_map(|(entity, character, _)| character.skill_chance(ThiefSkill::OpenLocks).map(|chance| (entity, chance)))
+                    .max_by_key(|(_, chance)| *chance);
+                match thief {
+                    Some((entity, _)) => {
+                        skill_checks.send(SkillCheckEvent {
+                            character: entity,
+                            skill: ThiefSkill::OpenLocks,
+                            modifier: 0,
+                        });
+                        pending.0.push((entity, position));
+                        continue;
+                    }
+                    None => (None, "Nobody in the party can pick a lock".to_string()),
+                }
+            }
+            (DoorAction::PickLock(_), _) => (None, "The door isn't locked".to_string()),
+        };
+
+        info!("{}", text);
+        message.0 = Some(text);
+        if let Some(new_state) = new_state {
+            if let Some(door) = map.door_at_mut(&position) {
+                door.state = new_state;
+            }
+        }
+    }
+}
+
+fn resolve_lock_picks(
+    mut results: EventReader<SkillCheckResult>,
+    mut pending: ResMut<PendingPicks>,
+    map: Option<ResMut<DungeonMap>>,
+    characters: Query<&Character>,
+    mut message: ResMut<DoorMessage>,
+) {
+    let Some(mut map) = map else {
+        return;
+    };
+    for result in results.read() {
+        if result.check.skill != ThiefSkill::OpenLocks {
+            continue;
+        }
+        let Some(index) = pending.0.iter().position(|(entity, _)| *entity == result.character) else {
+            continue;
+        };
+        let (entity, position) = pending.0.remove(index);
+        let name = characters.get(entity).map_or("The thief".to_string(), |character| character.name.clone());
+        let text = if result.check.success {
+            if let Some(door) = map.door_at_mut(&position) {
+                door.state = DoorState::Open;
+            }
+            format!("{} picks the lock", name)
+        } else {
+            format!("{} can't get the lock open", name)
+        };
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
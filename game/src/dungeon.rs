@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+
+use crate::ai_client::CurrentDungeon;
+use crate::character::Character;
+use crate::clock::{AdvanceClock, ClockSet, TimeSpan};
+use crate::combat::{Combatant, GridPosition};
+use crate::death::party_movement_rate;
+use crate::config::KeyBindings;
+use crate::doors::{door_color, DoorAction, DoorMessage};
+use crate::encounters::MetEncounters;
+use crate::light::Darkness;
+use crate::treasure::LootedTreasure;
+use crate::world::PendingDungeonState;
+use crate::GameState;
+
+pub use old_school_core::dungeon_map::{Door, DungeonMap, RoomArea, Tile, FEET_PER_TILE};
+pub use old_school_core::traps::TrapState;
+pub use old_school_core::fog::{FogOfWar, Sight};
+
+// Draws the current dungeon. Whenever the AI service (or a loaded save) hands over a
+// new dungeon it's laid out as a tile map (see old_school_core::dungeon_map) and drawn
+// with one sprite per open tile, and the party's token is set down in the middle of the
+// entrance. WASD or the arrow keys move it a tile at a time through open floor;
+// walking into a closed door tries to open it (see doors.rs) and onto a hidden trap
+// sets it off (see traps.rs). Each 10' step takes time on the game clock at the
+// exploring pace of the slowest party member, and the camera follows (see camera.rs).
+// Each time the token comes into a different room a RoomEntered goes out and the room's
+// description is shown. Only tiles the party has seen are drawn, dimmed unless they're
+// in sight now (see old_school_core::fog). A dungeon the party comes back to is laid out
+// as they left it (see world.rs). The map is only drawn while exploring.
+
+pub const TILE_SIZE: f32 = 16.0;
+
+// Seconds a movement key has to be held before the token keeps walking, and then
+// between steps
+const STEP_REPEAT_DELAY: f32 = 0.3;
+const STEP_REPEAT_INTERVAL: f32 = 0.12;
+
+const FLOOR_COLOR: Color = Color::rgb(0.35, 0.33, 0.3);
+const STAIRS_COLOR: Color = Color::rgb(0.6, 0.6, 0.75);
+const TRAP_COLOR: Color = Color::rgb(0.7, 0.25, 0.5);
+pub const PARTY_COLOR: Color = Color::rgb(0.95, 0.85, 0.3);
+// Remembered tiles are drawn at this fraction of their brightness
+const REMEMBERED_BRIGHTNESS: f32 = 0.4;
+
+// Everything drawn for the dungeon hangs off this entity
+#[derive(Component)]
+pub struct DungeonView;
+
+#[derive(Component)]
+pub struct DungeonTile {
+    pub color: Color,
+}
+
+// The party as a whole, as it moves through the dungeon
+#[derive(Component, Debug)]
+pub struct PartyToken;
+
+// The tile something stands on in the dungeon. Separate from the battle map's
+// GridPosition, which only lasts as long as a fight.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position(pub GridPosition);
+
+// One step of the party, a tile in each direction at most
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MoveParty {
+    pub dx: i32,
+    pub dy: i32,
+}
+
+// The room the party is standing in; None in the passages between rooms
+#[derive(Resource, Default, Debug)]
+pub struct CurrentRoom(pub Option<u32>);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RoomEntered {
+    pub room_id: u32,
+}
+
+pub struct DungeonPlugin;
+
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentRoom>()
+            .init_resource::<FogOfWar>()
+            .add_event::<RoomEntered>()
+            .add_event::<MoveParty>()
+            .add_systems(OnEnter(GameState::InGame), show_dungeon::<true>)
+            .add_systems(OnExit(GameState::InGame), show_dungeon::<false>)
+            .add_systems(Update, (
+                build_dungeon_map,
+                movement_keys.run_if(in_state(GameState::InGame)),
+                move_party.before(ClockSet),
+                sync_positions,
+                track_current_room,
+                update_fog,
+                update_tile_colors,
+                shade_tiles,
+            ).chain());
+    }
+}
+
+// World position of the middle of a tile; map rows run down the screen
+pub fn tile_translation(tile: &GridPosition, z: f32) -> Vec3 {
+    Vec3::new(tile.x as f32 * TILE_SIZE, -(tile.y as f32) * TILE_SIZE, z)
+}
+
+fn tile_sprite(tile: &GridPosition, color: Color, scale: f32, z: f32) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(TILE_SIZE * scale)),
+            ..default()
+        },
+        transform: Transform::from_translation(tile_translation(tile, z)),
+        ..default()
+    }
+}
+
+// None for rock and secret doors, which aren't drawn
+fn tile_color(map: &DungeonMap, tile: &GridPosition) -> Option<Color> {
+    match map.tile(tile) {
+        Tile::Wall => None,
+        Tile::Door => map.door_at(tile).filter(|door| !door.is_secret).map(door_color),
+        Tile::Floor if map.stairs_at(tile).is_some() => Some(STAIRS_COLOR),
+        Tile::Floor => match map.trap_at(tile) {
+            Some(trap) if matches!(trap.state, TrapState::Found | TrapState::Sprung) => Some(TRAP_COLOR),
+            _ => Some(FLOOR_COLOR),
+        },
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_dungeon_map(
+    mut commands: Commands,
+    dungeon: Res<CurrentDungeon>,
+    views: Query<Entity, With<DungeonView>>,
+    mut current_room: ResMut<CurrentRoom>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+    mut fog: ResMut<FogOfWar>,
+    mut pending: ResMut<PendingDungeonState>,
+    mut met: ResMut<MetEncounters>,
+    mut looted: ResMut<LootedTreasure>,
+    state: Res<State<GameState>>,
+) {
+    if !dungeon.is_changed() {
+        return;
+    }
+    for view in views.iter() {
+        commands.entity(view).despawn_recursive();
+    }
+    current_room.0 = None;
+    let Some(data) = dungeon.0.as_ref() else {
+        commands.remove_resource::<DungeonMap>();
+        return;
+    };
+
+    let mut map = DungeonMap::build(data);
+    info!("Laid out {} on a {}x{} map", data.name, map.width, map.height);
+    // A dungeon the party has been in before comes back as they left it
+    let returning = pending.0.take().filter(|(name, _)| *name == data.name).map(|(_, state)| state).unwrap_or_default();
+    returning.apply(&mut map);
+    met.0 = returning.met_encounters;
+    looted.0 = returning.looted_treasure;
+    // A loaded save brings its own memory of the map along
+    if !fog.belongs_to(&data.name, &map) {
+        *fog = FogOfWar::new(&data.name, &map);
+    }
+
+    let visibility = if *state.get() == GameState::InGame {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    commands
+        .spawn((SpatialBundle { visibility, ..default() }, DungeonView))
+        .with_children(|parent| {
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let tile = GridPosition::new(x, y);
+                    let Some(color) = tile_color(&map, &tile) else {
+                        continue;
+                    };
+                    let mut sprite = tile_sprite(&tile, color, 0.95, 0.0);
+                    sprite.visibility = Visibility::Hidden;
+                    parent.spawn((sprite, DungeonTile { color }, Position(tile)));
+                }
+            }
+            parent.spawn((tile_sprite(&map.entrance, PARTY_COLOR, 0.7, 1.0), PartyToken, Position(map.entrance)));
+        });
+
+    for mut transform in cameras.iter_mut() {
+        transform.translation = tile_translation(&map.entrance, transform.translation.z);
+    }
+    commands.insert_resource(map);
+}
+
+fn movement_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    time: Res<Time>,
+    mut repeat: Local<f32>,
+    mut moves: EventWriter<MoveParty>,
+) {
+    let held = |keys: [KeyCode; 2]| keyboard_input.any_pressed(keys) as i32;
+    let dx = held([keys.move_right, KeyCode::Right]) - held([keys.move_left, KeyCode::Left]);
+    let dy = held([keys.move_down, KeyCode::Down]) - held([keys.move_up, KeyCode::Up]);
+    if dx == 0 && dy == 0 {
+        return;
+    }
+
+    let pressed = [keys.move_up, keys.move_left, keys.move_down, keys.move_right];
+    let pressed = pressed.into_iter().chain([KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right]);
+    if keyboard_input.any_just_pressed(pressed) {
+        *repeat = STEP_REPEAT_DELAY;
+    } else {
+        *repeat -= time.delta_seconds();
+        if *repeat > 0.0 {
+            return;
+        }
+        *repeat = STEP_REPEAT_INTERVAL;
+    }
+    moves.send(MoveParty { dx, dy });
+}
+
+// A diagonal step needs the way open along both sides as well, so the party can't
+// squeeze between the corners of two walls. Stepping straight at a door the party knows
+// about tries it instead.
+fn move_party(
+    mut moves: EventReader<MoveParty>,
+    map: Option<Res<DungeonMap>>,
+    mut tokens: Query<&mut Position, With<PartyToken>>,
+    party: Query<(&Character, &Combatant)>,
+    mut advance: EventWriter<AdvanceClock>,
+    mut door_actions: EventWriter<DoorAction>,
+    mut door_message: ResMut<DoorMessage>,
+) {
+    let (Some(map), Ok(mut position)) = (map, tokens.get_single_mut()) else {
+        moves.clear();
+        return;
+    };
+    let movement_rate =
+        party_movement_rate(party.iter().filter(|(_, combatant)| combatant.is_player).map(|(character, _)| character));
+
+    for step in moves.read() {
+        if movement_rate == 0 {
+            warn!("The party is too heavily laden to move");
+            continue;
+        }
+        let (dx, dy) = (step.dx.signum(), step.dy.signum());
+        let from = position.0;
+        let to = from.offset(dx, dy);
+        let clear = map.is_passable(&to)
+            && (dx == 0 || dy == 0 || (map.is_passable(&from.offset(dx, 0)) && map.is_passable(&from.offset(0, dy))));
+        if !clear {
+            let known_door = map.door_at(&to).is_some_and(|door| !door.is_secret);
+            if known_door && (dx == 0 || dy == 0) {
+                door_actions.send(DoorAction::Open(to));
+            }
+            continue;
+        }
+        position.0 = to;
+        if door_message.0.is_some() {
+            door_message.0 = None;
+        }
+        advance.send(AdvanceClock(TimeSpan::exploring(FEET_PER_TILE, movement_rate)));
+    }
+}
+
+fn sync_positions(mut moved: Query<(&Position, &mut Transform), Changed<Position>>) {
+    for (position, mut transform) in moved.iter_mut() {
+        transform.translation = tile_translation(&position.0, transform.translation.z);
+    }
+}
+
+fn track_current_room(
+    tokens: Query<&Position, (With<PartyToken>, Changed<Position>)>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    mut current_room: ResMut<CurrentRoom>,
+    mut entered: EventWriter<RoomEntered>,
+) {
+    let (Some(map), Ok(token)) = (map, tokens.get_single()) else {
+        return;
+    };
+    let room_id = map.room_at(&token.0);
+    if current_room.0 == room_id {
+        return;
+    }
+    current_room.0 = room_id;
+
+    let Some(room_id) = room_id else {
+        return;
+    };
+    if let Some(room) = dungeon.0.as_ref().and_then(|data| data.rooms.iter().find(|room| room.id == room_id)) {
+        info!("The party enters {}", room.name);
+    }
+    entered.send(RoomEntered { room_id });
+}
+
+// Sight is worked out again whenever the party moves, its light changes or a door
+// opens. The party sees as far as whoever in it sees furthest.
+fn update_fog(
+    tokens: Query<&Position, With<PartyToken>>,
+    moved: Query<(), (With<PartyToken>, Changed<Position>)>,
+    map: Option<Res<DungeonMap>>,
+    darkness: Res<Darkness>,
+    party: Query<(&Character, &Combatant)>,
+    mut fog: ResMut<FogOfWar>,
+) {
+    let (Some(map), Ok(position)) = (map, tokens.get_single()) else {
+        return;
+    };
+    if moved.is_empty() && !darkness.is_changed() && !map.is_changed() {
+        return;
+    }
+    let radius = party
+        .iter()
+        .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+        .map(|(character, _)| character.sight_radius(!darkness.0))
+        .max()
+        .unwrap_or(0);
+    fog.update(&map.in_sight(&position.0, (radius / FEET_PER_TILE as u16) as i32));
+}
+
+// Doors change colour as they're opened, and traps show up once they're found
+fn update_tile_colors(map: Option<Res<DungeonMap>>, mut tiles: Query<(&mut DungeonTile, &Position)>) {
+    let Some(map) = map.filter(|map| map.is_changed()) else {
+        return;
+    };
+    for (mut tile, position) in tiles.iter_mut() {
+        let Some(color) = tile_color(&map, &position.0) else {
+            continue;
+        };
+        if tile.color != color {
+            tile.color = color;
+        }
+    }
+}
+
+fn shade_tiles(
+    fog: Res<FogOfWar>,
+    mut tiles: Query<(Ref<DungeonTile>, &Position, &mut Sprite, &mut Visibility)>,
+) {
+    for (tile, position, mut sprite, mut visibility) in tiles.iter_mut() {
+        if !fog.is_changed() && !tile.is_changed() {
+            continue;
+        }
+        let brightness = match fog.sight(&position.0) {
+            Sight::Unexplored => {
+                *visibility = Visibility::Hidden;
+                continue;
+            }
+            Sight::Remembered => REMEMBERED_BRIGHTNESS,
+            Sight::Visible => 1.0,
+        };
+        let [red, green, blue, alpha] = tile.color.as_rgba_f32();
+        sprite.color = Color::rgba(red * brightness, green * brightness, blue * brightness, alpha);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn show_dungeon<const VISIBLE: bool>(mut views: Query<&mut Visibility, With<DungeonView>>) {
+    for mut visibility in views.iter_mut() {
+        *visibility = if VISIBLE { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use rand::Rng;
+
+use crate::ai_client::{CurrentDungeon, EnemyData};
+use crate::combat::Combatant;
+use crate::dungeon::{tile_translation, DungeonMap, DungeonView, FogOfWar, Position, RoomEntered, Sight, TILE_SIZE};
+use crate::rng::GameRng;
+use crate::GameState;
+use old_school_core::monster::monster_character;
+
+// The monsters the dungeon was generated with. Each encounter waits in its room, drawn
+// as a token that only shows while the party can see the square it stands on. When the
+// party walks into the room the fight starts, and the encounter is gone from the map.
+
+pub const MONSTER_COLOR: Color = Color::rgb(0.85, 0.2, 0.2);
+
+// A room's encounter waiting on the map, by its index in the dungeon's encounters
+#[derive(Component, Debug)]
+pub struct MonsterToken {
+    pub encounter: usize,
+    pub room_id: u32,
+}
+
+// The stat block a monster was made from, for what its Character doesn't carry: its type
+// (for its picture), hit dice, movement, morale and treasure
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Monster(pub EnemyData);
+
+// Encounters the party has already met in this dungeon
+#[derive(Resource, Default, Debug)]
+pub struct MetEncounters(pub Vec<usize>);
+
+pub struct EncountersPlugin;
+
+impl Plugin for EncountersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MetEncounters>()
+            .add_systems(Update, (
+                place_monster_tokens,
+                show_monster_tokens,
+                meet_room_encounters.run_if(in_state(GameState::InGame)),
+            ).chain());
+    }
+}
+
+// Each enemy joins the fight on its own side, with its hit points rolled
+pub fn spawn_enemies<'a>(
+    commands: &mut Commands,
+    enemies: impl IntoIterator<Item = &'a EnemyData>,
+    rng: &mut impl Rng,
+) -> usize {
+    let mut count = 0;
+    for enemy in enemies {
+        let enemy = enemy.roll(rng);
+        let innate_effects = enemy.innate_effects();
+        commands.spawn((
+            monster_character(&enemy),
+            Monster(enemy),
+            Combatant {
+                initiative: 0,
+                is_player: false,
+                actions_remaining: 1,
+                status_effects: innate_effects,
+            },
+        ));
+        count += 1;
+    }
+    count
+}
+
+// Tokens go down a square north of the middle of the room whenever a dungeon is laid out
+fn place_monster_tokens(
+    mut commands: Commands,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    views: Query<Entity, With<DungeonView>>,
+    met: Res<MetEncounters>,
+) {
+    let (Some(map), Some(data), Ok(view)) = (map, dungeon.0.as_ref(), views.get_single()) else {
+        return;
+    };
+    if !map.is_added() {
+        return;
+    }
+    commands.entity(view).with_children(|parent| {
+        for (index, encounter) in data.encounters.iter().enumerate() {
+            if met.0.contains(&index) {
+                continue;
+            }
+            let Some(area) = map.room_area(encounter.room_id) else {
+                continue;
+            };
+            let square = area.center().offset(0, -1);
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: MONSTER_COLOR,
+                        custom_size: Some(Vec2::splat(TILE_SIZE * 0.6)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(tile_translation(&square, 0.5)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                MonsterToken { encounter: index, room_id: encounter.room_id },
+                Position(square),
+            ));
+        }
+    });
+}
+
+// Monsters are only drawn while they're in sight, never from memory
+fn show_monster_tokens(fog: Res<FogOfWar>, mut tokens: Query<(Ref<Position>, &mut Visibility), With<MonsterToken>>) {
+    for (position, mut visibility) in tokens.iter_mut() {
+        if !fog.is_changed() && !position.is_added() {
+            continue;
+        }
+        let seen = fog.sight(&position.0) == Sight::Visible;
+        let wanted = if seen { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+fn meet_room_encounters(
+    mut commands: Commands,
+    mut entered: EventReader<RoomEntered>,
+    dungeon: Res<CurrentDungeon>,
+    tokens: Query<(Entity, &MonsterToken)>,
+    mut met: ResMut<MetEncounters>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(data) = dungeon.0.as_ref() else {
+        entered.clear();
+        return;
+    };
+    for event in entered.read() {
+        let mut spawned = 0;
+        for (entity, token) in tokens.iter().filter(|(_, token)| token.room_id == event.room_id) {
+            commands.entity(entity).despawn_recursive();
+            met.0.push(token.encounter);
+            if let Some(encounter) = data.encounters.get(token.encounter) {
+                spawned += spawn_enemies(&mut commands, &encounter.enemies, rng.as_mut());
+            }
+        }
+        if spawned > 0 {
+            info!("The party comes upon {} monsters", spawned);
+            next_state.set(GameState::Combat);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::combat::{CombatLogEvent, Combatant, FightWon};
+use crate::retainers::Retainer;
+use crate::ruleset::Ruleset;
+
+pub use old_school_core::experience::{monster_xp, share_out};
+
+// Experience (the rules are in old_school_core::experience). Winning a fight earns the
+// party the monsters' worth, and the treasure it has carried off since the last fight
+// was won, a point a gold piece if the ruleset counts treasure. The award goes to the
+// log, monster by monster, and is shared among those still standing, each share then
+// raised or lowered by the member's prime requisite bonus or penalty.
+
+// Gold pieces' worth of treasure taken since the last award
+#[derive(Resource, Default, Debug)]
+pub struct RecoveredTreasure(pub u32);
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RecoveredTreasure>().add_systems(Update, award_experience);
+    }
+}
+
+fn award_experience(
+    mut won: EventReader<FightWon>,
+    mut party: Query<(&mut Character, &Combatant, Option<&Retainer>)>,
+    mut recovered: ResMut<RecoveredTreasure>,
+    ruleset: Res<Ruleset>,
+    mut log_events: EventWriter<CombatLogEvent>,
+) {
+    for fight in won.read() {
+        // The slain, like ones together: "Goblin x3: 30 XP"
+        let mut lines: Vec<(String, u32, u32)> = Vec::new();
+        for monster in &fight.defeated {
+            match lines.iter_mut().find(|(name, _, _)| *name == monster.name) {
+                Some((_, count, xp)) => {
+                    *count += 1;
+                    *xp += monster_xp(monster);
+                }
+                None => lines.push((monster.name.clone(), 1, monster_xp(monster))),
+            }
+        }
+        let mut items: Vec<String> = lines
+            .iter()
+            .map(|(name, count, xp)| match count {
+                1 => format!("{} {}", name, xp),
+                _ => format!("{} x{} {}", name, count, xp),
+            })
+            .collect();
+        let mut total: u32 = lines.iter().map(|(_, _, xp)| xp).sum();
+        let treasure = std::mem::take(&mut recovered.0);
+        if ruleset.treasure_xp && treasure > 0 {
+            items.push(format!("treasure ({} gp) {}", treasure, treasure));
+            total += treasure;
+        }
+        if total == 0 {
+            continue;
+        }
+
+        let mut members: Vec<_> = party
+            .iter_mut()
+            .filter(|(character, combatant, _)| combatant.is_player && character.is_alive())
+            .collect();
+        let retainers: Vec<bool> = members.iter().map(|(_, _, retainer)| retainer.is_some()).collect();
+        log_events.send(CombatLogEvent(format!("Experience: {} = {} XP", items.join(", "), total)));
+        for ((character, _, retainer), award) in members.iter_mut().zip(share_out(total, &retainers)) {
+            let earned = character.gain_experience(award);
+            let mut notes = Vec::new();
+            if retainer.is_some() {
+                notes.push("half share".to_string());
+            }
+            if character.experience_bonus != 0 {
+                notes.push(format!("{:+}% prime requisite", character.experience_bonus));
+            }
+            let notes = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
+            log_events.send(CombatLogEvent(format!("{} earns {} XP{}", character.name, earned, notes)));
+            if character.level_up_due(&ruleset) {
+                let text = format!("{} is ready for level {}!", character.name, character.level + 1);
+                log_events.send(CombatLogEvent(text));
+            }
+        }
+    }
+}
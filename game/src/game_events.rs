@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::ai_client::{CurrentDungeon, NPCConversationEvent, NPCRoster, QuestLog};
+use crate::character::Character;
+use crate::combat::{CharacterFell, Combatant};
+use crate::dungeon::RoomEntered;
+use crate::inventory::EquipmentSlot;
+use crate::quests::{QuestResolved, Resolution};
+use crate::traps::{TrapKind, TrapSprung};
+use crate::world::EnterDungeon;
+
+// What happens in play, told once for anything that wants to hear it: the quest log,
+// scripts, the session journal, the sounds and what NPCs have heard of the party. Each
+// GameEvent is made here from the event or change it comes from, so something new can
+// hook into the game by reading GameEvents rather than reaching into combat, the
+// dungeon or characters. Anything can send one as well.
+//
+// The latest few, as the party would tell them, are kept in RecentEvents and passed to
+// the AI service with each conversation as things the NPC may have heard.
+
+// How many of the latest events NPCs get to hear about
+pub const RECENT_EVENTS: usize = 5;
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    RoomEntered { dungeon: String, room_id: u32, room: String },
+    SiteEntered { site: String },
+    MonsterSlain { name: String },
+    // One of the party dropped to 0 hit points, and how
+    CharacterFell { name: String, cause: String },
+    ItemAcquired { character: String, item: String },
+    TrapSprung { kind: TrapKind, victim: String },
+    QuestAccepted { title: String, giver: String },
+    QuestEnded { title: String, resolution: Resolution },
+    LevelUp { character: String, level: u8 },
+    // The party speaks to someone for the first time
+    NpcMet { npc_id: String, name: String },
+    // The whole party has fallen, to whoever was left standing
+    PartyDefeated { killers: Vec<String> },
+}
+
+impl GameEvent {
+    // A line for the NPCs' news, for the events worth telling
+    pub fn describe(&self) -> Option<String> {
+        let text = match self {
+            GameEvent::SiteEntered { site } => format!("The party went into {}", site),
+            GameEvent::MonsterSlain { name } => format!("The party slew a {}", name),
+            GameEvent::CharacterFell { name, cause } => format!("{} was {}", name, cause),
+            GameEvent::QuestAccepted { title, giver } => format!("The party took on \"{}\" for {}", title, giver),
+            GameEvent::LevelUp { character, level } => format!("{} reached level {}", character, level),
+            _ => return None,
+        };
+        Some(text)
+    }
+}
+
+// The latest events worth telling, oldest first
+#[derive(Resource, Default, Debug)]
+pub struct RecentEvents(pub VecDeque<String>);
+
+impl RecentEvents {
+    pub fn lines(&self) -> Vec<String> {
+        self.0.iter().cloned().collect()
+    }
+}
+
+// What was last seen of each member of the party, to tell when they gain a level or an item
+#[derive(Default)]
+struct Seen {
+    level: u8,
+    items: HashMap<String, usize>,
+}
+
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameEvent>()
+            .init_resource::<RecentEvents>()
+            .add_systems(Update, (
+                (
+                    relay_exploration,
+                    relay_deaths,
+                    relay_quests,
+                    relay_party_changes,
+                    relay_meetings,
+                ),
+                remember_recent_events,
+            ).chain());
+    }
+}
+
+fn relay_exploration(
+    mut rooms: EventReader<RoomEntered>,
+    mut sites: EventReader<EnterDungeon>,
+    mut traps: EventReader<TrapSprung>,
+    dungeon: Res<CurrentDungeon>,
+    mut events: EventWriter<GameEvent>,
+) {
+    for entered in rooms.read() {
+        let Some(data) = dungeon.0.as_ref() else {
+            continue;
+        };
+        if let Some(room) = data.rooms.iter().find(|room| room.id == entered.room_id) {
+            events.send(GameEvent::RoomEntered {
+                dungeon: data.name.clone(),
+                room_id: room.id,
+                room: room.name.clone(),
+            });
+        }
+    }
+    for entered in sites.read() {
+        events.send(GameEvent::SiteEntered { site: entered.site.clone() });
+    }
+    for sprung in traps.read() {
+        events.send(GameEvent::TrapSprung { kind: sprung.kind, victim: sprung.victim.clone() });
+    }
+}
+
+fn relay_deaths(mut fallen: EventReader<CharacterFell>, mut events: EventWriter<GameEvent>) {
+    for fell in fallen.read() {
+        let name = fell.name.clone();
+        events.send(if fell.is_player {
+            GameEvent::CharacterFell { name, cause: fell.cause.clone() }
+        } else {
+            GameEvent::MonsterSlain { name }
+        });
+    }
+}
+
+fn relay_quests(mut resolved: EventReader<QuestResolved>, log: Res<QuestLog>, mut events: EventWriter<GameEvent>) {
+    for QuestResolved(index) in resolved.read() {
+        let Some(quest) = log.quests.get(*index) else {
+            continue;
+        };
+        events.send(GameEvent::QuestEnded {
+            title: quest.data.title.clone(),
+            resolution: quest.resolution.unwrap_or(Resolution::Abandoned),
+        });
+    }
+}
+
+// Levels and items are noticed as a member of the party changes; what they had when
+// first seen (as the party is made or a game loads) doesn't count
+fn relay_party_changes(
+    party: Query<(Entity, &Character, &Combatant), Changed<Character>>,
+    mut seen: Local<HashMap<Entity, Seen>>,
+    mut events: EventWriter<GameEvent>,
+) {
+    for (entity, character, combatant) in party.iter() {
+        if !combatant.is_player {
+            continue;
+        }
+        let mut items: HashMap<String, usize> = HashMap::new();
+        let worn = EquipmentSlot::ALL.into_iter().filter_map(|slot| character.equipment.get(slot));
+        for item in character.inventory.items.iter().chain(worn) {
+            *items.entry(item.name.clone()).or_default() += 1;
+        }
+        let now = Seen { level: character.level, items };
+        let Some(before) = seen.insert(entity, now) else {
+            continue;
+        };
+
+        if character.level > before.level {
+            events.send(GameEvent::LevelUp { character: character.name.clone(), level: character.level });
+        }
+        let mut gained: Vec<(&String, usize)> = seen[&entity]
+            .items
+            .iter()
+            .map(|(name, count)| (name, count.saturating_sub(before.items.get(name).copied().unwrap_or(0))))
+            .filter(|(_, gained)| *gained > 0)
+            .collect();
+        gained.sort();
+        for (item, count) in gained {
+            for _ in 0..count {
+                events.send(GameEvent::ItemAcquired { character: character.name.clone(), item: item.clone() });
+            }
+        }
+    }
+}
+
+// Someone the roster knows nothing of the party from yet
+fn relay_meetings(
+    mut conversations: EventReader<NPCConversationEvent>,
+    roster: Res<NPCRoster>,
+    mut met: Local<HashSet<String>>,
+    mut events: EventWriter<GameEvent>,
+) {
+    for conversation in conversations.read() {
+        let Some(npc) = roster.npcs.get(&conversation.npc_id) else {
+            continue;
+        };
+        if npc.memory.is_empty() && npc.relationships.is_empty() && met.insert(conversation.npc_id.clone()) {
+            events.send(GameEvent::NpcMet { npc_id: conversation.npc_id.clone(), name: npc.name.clone() });
+        }
+    }
+}
+
+fn remember_recent_events(mut events: EventReader<GameEvent>, mut recent: ResMut<RecentEvents>) {
+    for line in events.read().filter_map(GameEvent::describe) {
+        recent.0.push_back(line);
+        while recent.0.len() > RECENT_EVENTS {
+            recent.0.pop_front();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::character::Character;
+use crate::clock::GameClock;
+use crate::combat::{Combat, Combatant};
+use crate::game_events::GameEvent;
+use crate::save::delete_run_saves;
+use crate::{GameConfig, GameState};
+
+// The end of a run. Once the whole party has fallen, combat hands over to GameOver:
+// the death screen sums up the run and offers to load a save or go back to the main
+// menu. A PartyDefeated GameEvent goes out as the screen opens, naming whoever was left
+// standing, for anything that treats the party's death as final.
+//
+// An ironman run (`ironman = true` under [saves] in config.toml, or --ironman, as the
+// party is made) ends for good: when the party falls every save of the run is deleted,
+// so there's nothing to go back to.
+
+// The run as a whole, from when the party was made, kept in its saves. Each run has its
+// own id so an ironman party's saves can be found in whatever slots they went to.
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunRecord {
+    pub id: u64,
+    pub ironman: bool,
+    pub monsters_slain: u32,
+}
+
+// A member of the party as they fell
+#[derive(Debug, Clone)]
+pub struct FallenMember {
+    pub name: String,
+    pub class: String,
+    pub level: u8,
+}
+
+// The run as the death screen tells it, taken when the party falls since the characters
+// are cleared away as the screen closes
+#[derive(Resource, Default, Debug)]
+pub struct RunSummary {
+    pub party: Vec<FallenMember>,
+    pub gold: u32,
+    pub monsters_slain: u32,
+    pub day: u64,
+    pub killers: Vec<String>,
+    // The saves of an ironman run that were deleted
+    pub deleted_saves: Option<Vec<String>>,
+}
+
+impl RunSummary {
+    pub fn describe(&self) -> String {
+        let mut lines: Vec<String> = self
+            .party
+            .iter()
+            .map(|member| format!("{}, level {} {}", member.name, member.level, member.class))
+            .collect();
+        lines.push(String::new());
+        if !self.killers.is_empty() {
+            lines.push(format!("Slain by: {}", self.killers.join(", ")));
+        }
+        lines.push(format!("Monsters slain: {}", self.monsters_slain));
+        lines.push(format!("Gold carried: {} gp", self.gold));
+        lines.push(format!("Fell on day {}", self.day));
+        if let Some(deleted) = &self.deleted_saves {
+            lines.push(String::new());
+            lines.push(match deleted.len() {
+                0 => "Ironman: the run had no saves to delete".to_string(),
+                count => format!("Ironman: the run's {} saves are gone ({})", count, deleted.join(", ")),
+            });
+        }
+        lines.join("\n")
+    }
+}
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunRecord>()
+            .init_resource::<RunSummary>()
+            .add_systems(OnEnter(GameState::CharacterCreation), start_run_record)
+            .add_systems(OnEnter(GameState::GameOver), summarize_run)
+            .add_systems(OnExit(GameState::GameOver), clear_fallen_party)
+            .add_systems(Update, (
+                count_monsters_slain,
+                game_over_input.run_if(in_state(GameState::GameOver)),
+            ));
+    }
+}
+
+// The id comes from outside the game's dice, so seeded sessions roll the same
+fn start_run_record(config: Res<GameConfig>, mut record: ResMut<RunRecord>) {
+    *record = RunRecord {
+        id: rand::random(),
+        ironman: config.ironman,
+        monsters_slain: 0,
+    };
+}
+
+fn count_monsters_slain(mut events: EventReader<GameEvent>, mut record: ResMut<RunRecord>) {
+    for event in events.read() {
+        if matches!(event, GameEvent::MonsterSlain { .. }) {
+            record.monsters_slain += 1;
+        }
+    }
+}
+
+fn summarize_run(
+    characters: Query<(&Character, &Combatant)>,
+    config: Res<GameConfig>,
+    record: Res<RunRecord>,
+    clock: Res<GameClock>,
+    mut summary: ResMut<RunSummary>,
+    mut events: EventWriter<GameEvent>,
+) {
+    let party: Vec<&Character> = characters
+        .iter()
+        .filter(|(_, combatant)| combatant.is_player)
+        .map(|(character, _)| character)
+        .collect();
+    let killers: Vec<String> = characters
+        .iter()
+        .filter(|(character, combatant)| !combatant.is_player && character.is_alive())
+        .map(|(character, _)| character.name.clone())
+        .collect();
+
+    *summary = RunSummary {
+        party: party
+            .iter()
+            .map(|character| FallenMember {
+                name: character.name.clone(),
+                class: character.class.name().to_string(),
+                level: character.level,
+            })
+            .collect(),
+        gold: party.iter().map(|character| character.inventory.gold).sum(),
+        monsters_slain: record.monsters_slain,
+        day: clock.day(),
+        killers: killers.clone(),
+        deleted_saves: record.ironman.then(|| delete_run_saves(&config.save_dir, record.id)),
+    };
+    info!("The party has fallen: {:?}", summary);
+    events.send(GameEvent::PartyDefeated { killers });
+}
+
+// L to load a save, Enter or ESC for the main menu
+fn game_over_input(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::L) {
+        next_state.set(GameState::LoadGame);
+    } else if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+// The fallen party and the monsters over them, so a new game starts from nothing
+fn clear_fallen_party(
+    mut commands: Commands,
+    characters: Query<Entity, With<Character>>,
+    combat: Query<Entity, With<Combat>>,
+) {
+    for entity in characters.iter().chain(combat.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,34 +1,170 @@
+use bevy::app::AppExit;
 use bevy::prelude::*;
-use crate::GameState;
+use crate::ai_client::CurrentDungeon;
+use crate::config::KeyBindings;
+use crate::save::{list_slots, LoadGameEvent};
+use crate::town::party_in_town;
+use crate::wilderness::Overland;
+use crate::{GameConfig, GameState};
 
 pub struct GameStatePlugin;
 
 impl Plugin for GameStatePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
+            .init_resource::<MainMenu>()
             .add_systems(Startup, setup_game)
+            .add_systems(OnEnter(GameState::MainMenu), refresh_main_menu)
+            .add_systems(PostUpdate, log_state_transitions)
             .add_systems(Update, (
-                handle_main_menu,
-                handle_character_creation,
-                handle_in_game,
-                handle_combat_state,
-                handle_inventory_state,
-                handle_settings_state,
+                handle_main_menu.run_if(in_state(GameState::MainMenu)),
+                handle_character_creation.run_if(in_state(GameState::CharacterCreation)),
+                handle_in_game.run_if(in_state(GameState::InGame)),
+                handle_combat_state.run_if(in_state(GameState::Combat)),
+                handle_inventory_state.run_if(in_state(GameState::Inventory)),
+                handle_shop_state.run_if(in_state(GameState::Shop)),
+                handle_map_state.run_if(in_state(GameState::Map)),
+                handle_settings_state.run_if(in_state(GameState::Settings)),
             ));
     }
 }
 
 fn setup_game(mut commands: Commands) {
-    // Initialize game with main menu state
-    commands.insert_resource(GameConfig::default());
+    // The binary inserts the config it parsed; fall back to defaults for anything else
+    commands.init_resource::<GameConfig>();
+    // One camera draws both the dungeon map and the UI over it
+    commands.spawn(Camera2dBundle::default());
 }
 
+fn log_state_transitions(state: Res<State<GameState>>, mut previous: Local<Option<GameState>>) {
+    if !state.is_changed() {
+        return;
+    }
+
+    let current = state.get().clone();
+    let _span = info_span!("state_transition", to = ?current).entered();
+    match previous.as_ref() {
+        Some(from) => info!("Game state changed from {:?} to {:?}", from, current),
+        None => debug!("Starting in {:?}", current),
+    }
+    *previous = Some(current);
+}
+
+// What the main menu offers, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuChoice {
+    NewGame,
+    Continue,
+    LoadGame,
+    Graveyard,
+    Settings,
+    Quit,
+}
+
+impl MenuChoice {
+    pub const ALL: [MenuChoice; 6] = [
+        MenuChoice::NewGame,
+        MenuChoice::Continue,
+        MenuChoice::LoadGame,
+        MenuChoice::Graveyard,
+        MenuChoice::Settings,
+        MenuChoice::Quit,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuChoice::NewGame => "New Game",
+            MenuChoice::Continue => "Continue",
+            MenuChoice::LoadGame => "Load Game",
+            MenuChoice::Graveyard => "Graveyard",
+            MenuChoice::Settings => "Settings",
+            MenuChoice::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MainMenuButton(pub MenuChoice);
+
+// The choice the keyboard is on (the mouse moves it too), and the save Continue loads
+#[derive(Resource, Default)]
+pub struct MainMenu {
+    pub selected: usize,
+    pub latest_save: Option<String>,
+}
+
+impl MainMenu {
+    // Continue needs a save to continue from
+    pub fn available(&self, choice: MenuChoice) -> bool {
+        choice != MenuChoice::Continue || self.latest_save.is_some()
+    }
+
+    // The next available choice up (-1) or down (1), round the ends
+    fn step(&mut self, direction: isize) {
+        let count = MenuChoice::ALL.len() as isize;
+        let mut index = self.selected as isize;
+        for _ in 0..count {
+            index = (index + direction).rem_euclid(count);
+            if self.available(MenuChoice::ALL[index as usize]) {
+                self.selected = index as usize;
+                return;
+            }
+        }
+    }
+}
+
+fn refresh_main_menu(config: Res<GameConfig>, mut menu: ResMut<MainMenu>) {
+    menu.latest_save = list_slots(&config.save_dir).into_iter().next().map(|summary| summary.slot);
+    menu.selected = 0;
+}
+
+// Up/Down (or W/S) and Enter, or the mouse. Enter on its own still starts a new game,
+// and L still opens the Load Game screen.
 fn handle_main_menu(
     keyboard_input: Res<Input<KeyCode>>,
+    buttons: Query<(&Interaction, &MainMenuButton), Changed<Interaction>>,
+    mut menu: ResMut<MainMenu>,
+    mut load_events: EventWriter<LoadGameEvent>,
+    mut exit: EventWriter<AppExit>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W]) {
+        menu.step(-1);
+    } else if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S]) {
+        menu.step(1);
+    }
+
+    let mut chosen = None;
+    for (interaction, button) in buttons.iter() {
+        let Some(index) = MenuChoice::ALL.iter().position(|choice| *choice == button.0) else {
+            continue;
+        };
+        match interaction {
+            Interaction::Hovered if menu.available(button.0) => menu.selected = index,
+            Interaction::Pressed => chosen = Some(button.0),
+            _ => {}
+        }
+    }
     if keyboard_input.just_pressed(KeyCode::Return) {
-        next_state.set(GameState::CharacterCreation);
+        chosen = Some(MenuChoice::ALL[menu.selected]);
+    } else if keyboard_input.just_pressed(KeyCode::L) {
+        chosen = Some(MenuChoice::LoadGame);
+    }
+
+    let Some(choice) = chosen.filter(|choice| menu.available(*choice)) else {
+        return;
+    };
+    match choice {
+        MenuChoice::NewGame => next_state.set(GameState::CharacterCreation),
+        MenuChoice::Continue => {
+            if let Some(slot) = menu.latest_save.clone() {
+                load_events.send(LoadGameEvent { slot });
+            }
+        }
+        MenuChoice::LoadGame => next_state.set(GameState::LoadGame),
+        MenuChoice::Graveyard => next_state.set(GameState::Graveyard),
+        MenuChoice::Settings => next_state.set(GameState::Settings),
+        MenuChoice::Quit => exit.send(AppExit),
     }
 }
 
@@ -44,10 +180,15 @@ fn handle_character_creation(
 
 fn handle_in_game(
     keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::I) {
+    if keyboard_input.just_pressed(keys.inventory) {
         next_state.set(GameState::Inventory);
+    } else if keyboard_input.just_pressed(keys.market) {
+        next_state.set(GameState::Shop);
+    } else if keyboard_input.just_pressed(keys.map) {
+        next_state.set(GameState::Map);
     } else if keyboard_input.just_pressed(KeyCode::Escape) {
         next_state.set(GameState::MainMenu);
     }
@@ -65,9 +206,34 @@ fn handle_combat_state(
 
 fn handle_inventory_state(
     keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(keys.inventory) || keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::InGame);
+    }
+}
+
+// Back to the town if that's where the party went shopping
+fn handle_shop_state(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    dungeon: Res<CurrentDungeon>,
+    overland: Res<Overland>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(keys.market) || keyboard_input.just_pressed(KeyCode::Escape) {
+        let back = if party_in_town(&dungeon, &overland) { GameState::Town } else { GameState::InGame };
+        next_state.set(back);
+    }
+}
+
+fn handle_map_state(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::I) || keyboard_input.just_pressed(KeyCode::Escape) {
+    if keyboard_input.just_pressed(keys.map) || keyboard_input.just_pressed(KeyCode::Escape) {
         next_state.set(GameState::InGame);
     }
 }
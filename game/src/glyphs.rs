@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+
+use crate::ai_client::CurrentDungeon;
+use crate::config::{save_settings, ConfigPath};
+use crate::dungeon::{DungeonMap, DungeonTile, PartyToken, Position, PARTY_COLOR};
+use crate::encounters::{MonsterToken, MONSTER_COLOR};
+use crate::theme::{TextSize, UiTheme};
+use crate::{GameConfig, GameState};
+
+// Text mode: the dungeon drawn the roguelike way, as coloured letters on a grid, instead
+// of squares and pictures. Every tile and token the dungeon draws gets a letter as well,
+// the one the automap uses for it ('.' floor, '+' and '/' doors, '>' and '<' stairs, '^'
+// traps), '@' for the party and a monster's initial for a waiting encounter. The letters
+// hang off the sprites, so they come and go with the fog of war just as the sprites do,
+// and a tile's letter takes the colour the sprite would have, dimmed when it's only
+// remembered. In text mode the sprites shrink to nothing and the letters show; out of
+// it, the other way round. `text_mode` in config.toml, or G on the Settings screen,
+// switches between the two.
+
+// Letters sit a little above their sprite
+const GLYPH_Z: f32 = 0.1;
+
+// A tile's or a token's letter. Tiles take their sprite's colour; tokens keep their own,
+// since their sprites turn white under a picture.
+#[derive(Component, Debug)]
+pub struct Glyph {
+    color: Option<Color>,
+    // The sprite's size, for when it comes back
+    size: Vec2,
+}
+
+pub struct GlyphsPlugin;
+
+impl Plugin for GlyphsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            toggle_text_mode.run_if(in_state(GameState::Settings)),
+            add_glyphs,
+            update_tile_glyphs,
+            color_glyphs,
+            switch_glyphs,
+        ).chain());
+    }
+}
+
+// G on the Settings screen, saved to config.toml straight away
+fn toggle_text_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    path: Option<Res<ConfigPath>>,
+    mut config: ResMut<GameConfig>,
+) {
+    if keyboard_input.just_pressed(KeyCode::G) {
+        config.text_mode = !config.text_mode;
+        save_settings(&config, path.as_deref());
+    }
+}
+
+fn spawn_glyph(
+    commands: &mut Commands,
+    theme: &UiTheme,
+    parent: Entity,
+    sprite: &Sprite,
+    letter: char,
+    color: Option<Color>,
+) {
+    let size = sprite.custom_size.unwrap_or(Vec2::ZERO);
+    let style = theme.mono(TextSize::Small, color.unwrap_or(Color::WHITE));
+    commands.entity(parent).with_children(|parent| {
+        parent.spawn((
+            Text2dBundle {
+                text: Text::from_section(letter.to_string(), style).with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0.0, 0.0, GLYPH_Z),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Glyph { color, size },
+        ));
+    });
+}
+
+fn add_glyphs(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    tiles: Query<(Entity, &Sprite, &Position), Added<DungeonTile>>,
+    party: Query<(Entity, &Sprite), Added<PartyToken>>,
+    monsters: Query<(Entity, &Sprite, &MonsterToken), Added<MonsterToken>>,
+) {
+    if let Some(map) = &map {
+        for (entity, sprite, position) in tiles.iter() {
+            spawn_glyph(&mut commands, &theme, entity, sprite, map.glyph(&position.0), None);
+        }
+    }
+    for (entity, sprite) in party.iter() {
+        spawn_glyph(&mut commands, &theme, entity, sprite, '@', Some(PARTY_COLOR));
+    }
+    for (entity, sprite, token) in monsters.iter() {
+        let first = dungeon.0.as_ref().and_then(|data| data.encounters.get(token.encounter)?.enemies.first());
+        let letter = first.and_then(|enemy| enemy.name.chars().next()).unwrap_or('m').to_ascii_lowercase();
+        spawn_glyph(&mut commands, &theme, entity, sprite, letter, Some(MONSTER_COLOR));
+    }
+}
+
+// Doors open and traps are found
+fn update_tile_glyphs(
+    map: Option<Res<DungeonMap>>,
+    tiles: Query<&Position, With<DungeonTile>>,
+    mut glyphs: Query<(&Parent, &mut Text), With<Glyph>>,
+) {
+    let Some(map) = map.filter(|map| map.is_changed()) else {
+        return;
+    };
+    for (parent, mut text) in glyphs.iter_mut() {
+        let Ok(position) = tiles.get(parent.get()) else {
+            continue;
+        };
+        let letter = map.glyph(&position.0).to_string();
+        if text.sections[0].value != letter {
+            text.sections[0].value = letter;
+        }
+    }
+}
+
+// Tiles change colour as they're seen, remembered, opened and found
+fn color_glyphs(sprites: Query<Ref<Sprite>>, mut glyphs: Query<(Ref<Glyph>, &Parent, &mut Text)>) {
+    for (glyph, parent, mut text) in glyphs.iter_mut() {
+        let Ok(sprite) = sprites.get(parent.get()) else {
+            continue;
+        };
+        if !glyph.is_added() && !sprite.is_changed() {
+            continue;
+        }
+        let color = glyph.color.unwrap_or(sprite.color);
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+// In text mode the sprite is still there, for its colour, but too small to see
+fn switch_glyphs(
+    config: Res<GameConfig>,
+    mut glyphs: Query<(Ref<Glyph>, &Parent, &mut Visibility)>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    for (glyph, parent, mut visibility) in glyphs.iter_mut() {
+        if !config.is_changed() && !glyph.is_added() {
+            continue;
+        }
+        let wanted = if config.text_mode { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        let Ok(mut sprite) = sprites.get_mut(parent.get()) else {
+            continue;
+        };
+        let size = Some(if config.text_mode { Vec2::ZERO } else { glyph.size });
+        if sprite.custom_size != size {
+            sprite.custom_size = size;
+        }
+    }
+}
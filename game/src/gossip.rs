@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+use crate::clock::{ClockSet, DayPassed, GameClock};
+use crate::rng::GameRng;
+use crate::town::Location;
+
+pub use old_school_core::gossip::{Gossip, Grapevine, RUMOR_LIFETIME_DAYS};
+
+// Town gossip (see old_school_core::gossip for how it spreads). Rumors heard at the inn
+// start with the innkeeper and make their way round everyone in town a day at a time.
+// Whoever the party talks to is told what they've heard, so the AI service can have
+// them pass it on.
+
+// Marks a line of a conversation's recent events as gossip the NPC has heard
+pub const RUMOR_PREFIX: &str = "Rumor: ";
+
+pub struct GossipPlugin;
+
+impl Plugin for GossipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Grapevine>()
+            .add_systems(Update, spread_gossip.after(ClockSet));
+    }
+}
+
+// What an NPC has heard, as recent events for a conversation with them
+pub fn gossip_for(grapevine: &Grapevine, npc_id: &str) -> Vec<String> {
+    grapevine
+        .known_to(npc_id)
+        .map(|rumor| format!("{}{}", RUMOR_PREFIX, rumor.text))
+        .collect()
+}
+
+fn spread_gossip(
+    mut days: EventReader<DayPassed>,
+    clock: Res<GameClock>,
+    mut grapevine: ResMut<Grapevine>,
+    mut rng: ResMut<GameRng>,
+) {
+    let people: Vec<&str> = Location::ALL
+        .iter()
+        .flat_map(|location| location.residents())
+        .map(|resident| resident.id)
+        .collect();
+    for _ in days.read() {
+        let told = grapevine.spread(&people, clock.day(), rng.as_mut());
+        if told > 0 {
+            debug!("Gossip reaches {} more ears in town", told);
+        }
+    }
+}
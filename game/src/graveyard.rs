@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::character::Character;
+use crate::clock::GameClock;
+use crate::combat::CharacterFell;
+use crate::journal::format_date;
+use crate::GameState;
+
+// Everyone from the party who has ever died, in any game, kept in graveyard.json in the
+// directory the game is run from and shown on the Graveyard screen off the main menu. A
+// grave is dug as a character falls (or is lost to lycanthropy, see ailments.rs), whether
+// or not the rest of the party lives on, and loading an earlier save doesn't dig them up
+// again.
+
+pub const GRAVEYARD_FILE: &str = "graveyard.json";
+
+// How many graves the screen lists, the newest first
+pub const GRAVES_SHOWN: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grave {
+    pub name: String,
+    pub class: String,
+    pub level: u8,
+    pub cause: String,
+    pub killer: Option<String>,
+    // The day of the campaign they died on
+    pub day: u64,
+    pub died_at: u64,
+}
+
+impl Grave {
+    pub fn epitaph(&self) -> String {
+        let killer = self.killer.as_ref().map(|killer| format!(" by {}", killer)).unwrap_or_default();
+        format!(
+            "{}, level {} {}: {}{}, on day {} ({})",
+            self.name,
+            self.level,
+            self.class,
+            self.cause,
+            killer,
+            self.day,
+            format_date(self.died_at)
+        )
+    }
+}
+
+#[derive(Resource, Default, Debug, Serialize, Deserialize)]
+pub struct Graveyard {
+    pub graves: Vec<Grave>,
+}
+
+impl Graveyard {
+    // An empty graveyard if there's no file yet
+    pub fn read(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    // A grave for `character`, who died (or was otherwise lost) on `day`
+    pub fn dig(&mut self, character: &Character, cause: &str, killer: Option<String>, day: u64) {
+        let died_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+        self.graves.push(Grave {
+            name: character.name.clone(),
+            class: character.class.name().to_string(),
+            level: character.level,
+            cause: cause.to_string(),
+            killer,
+            day,
+            died_at,
+        });
+    }
+
+    // Writes the graveyard out, warning if it can't
+    pub fn keep(&self) {
+        if let Err(e) = self.write(Path::new(GRAVEYARD_FILE)) {
+            warn!("Failed to write {}: {}", GRAVEYARD_FILE, e);
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        if self.graves.is_empty() {
+            return "Nobody lies here yet".to_string();
+        }
+        let mut lines: Vec<String> = self.graves.iter().rev().take(GRAVES_SHOWN).map(Grave::epitaph).collect();
+        if self.graves.len() > GRAVES_SHOWN {
+            lines.push(format!("...and {} more", self.graves.len() - GRAVES_SHOWN));
+        }
+        lines.join("\n")
+    }
+}
+
+pub struct GraveyardPlugin;
+
+impl Plugin for GraveyardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Graveyard>()
+            .add_systems(Startup, open_graveyard)
+            .add_systems(Update, (
+                bury_the_fallen,
+                graveyard_input.run_if(in_state(GameState::Graveyard)),
+            ));
+    }
+}
+
+fn open_graveyard(mut graveyard: ResMut<Graveyard>) {
+    match Graveyard::read(Path::new(GRAVEYARD_FILE)) {
+        Ok(read) => *graveyard = read,
+        Err(e) => warn!("Failed to read {}: {}", GRAVEYARD_FILE, e),
+    }
+}
+
+fn bury_the_fallen(
+    mut fallen: EventReader<CharacterFell>,
+    characters: Query<&Character>,
+    clock: Res<GameClock>,
+    mut graveyard: ResMut<Graveyard>,
+) {
+    let mut dug = false;
+    for fell in fallen.read().filter(|fell| fell.is_player) {
+        let Ok(character) = characters.get(fell.entity) else {
+            continue;
+        };
+        graveyard.dig(character, &fell.cause, fell.killer.clone(), clock.day());
+        dug = true;
+    }
+    if dug {
+        graveyard.keep();
+    }
+}
+
+fn graveyard_input(keyboard_input: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Return]) {
+        next_state.set(GameState::MainMenu);
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{AIRequestFailed, QuestLog, RumorEvent, RumorHeardEvent, RumorRequest};
+use crate::ailments::blocks_healing;
+use crate::character::{Character, RestEvent};
+use crate::clock::{AdvanceClock, GameClock, TimeSpan};
+use crate::combat::Combatant;
+use crate::gossip::{Gossip, Grapevine};
+use crate::rng::GameRng;
+use crate::town::{Location, Town, TownMessage};
+use crate::world::{SiteHook, WorldDatabase};
+use crate::GameState;
+
+pub use old_school_core::inn::{carouse, pay_from_party, Carousal, Mishap, ROOM_PRICE, ROUND_PRICE};
+
+// The inn (see old_school_core::inn for the rules). At the Green Dragon L takes rooms
+// for the night, R stands the house a round for a rumor from the AI service, and P
+// sends the party out carousing. A rumor that names a site is kept in the
+// WorldDatabase as a hook, so the site's dungeon is generated with the theme and level
+// the rumor gave it, and a rumor's quest goes into the quest log. Rumors can be false,
+// and a false one adds neither. Either way Marta has heard it too, and it goes round
+// town from her (see gossip.rs).
+
+// Rumors asked for and not yet heard
+#[derive(Resource, Default, Debug)]
+struct AwaitingRumors(usize);
+
+pub struct InnPlugin;
+
+impl Plugin for InnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AwaitingRumors>()
+            .add_systems(Update, (
+                inn_input.run_if(in_state(GameState::Town)),
+                hear_rumors,
+            ).chain());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn inn_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    town: Res<Town>,
+    world: Res<WorldDatabase>,
+    quest_log: Res<QuestLog>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    mut message: ResMut<TownMessage>,
+    mut awaiting: ResMut<AwaitingRumors>,
+    mut rest_events: EventWriter<RestEvent>,
+    mut rumors: EventWriter<RumorEvent>,
+    mut advance: EventWriter<AdvanceClock>,
+    mut rng: ResMut<GameRng>,
+) {
+    if town.location != Some(Location::Inn) {
+        return;
+    }
+    // Mummy rot keeps wounds open through the night
+    let heals: Vec<bool> = party
+        .iter()
+        .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+        .map(|(_, combatant)| !blocks_healing(&combatant.status_effects))
+        .collect();
+    let mut members: Vec<&mut Character> = party
+        .iter_mut()
+        .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+        .map(|(character, _)| character.into_inner())
+        .collect();
+    if members.is_empty() {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::L) {
+        let price = ROOM_PRICE * members.len() as u32;
+        if let Err(reason) = pay_from_party(&mut members, price) {
+            message.0 = Some(format!("Marta shakes her head: {}", reason));
+            return;
+        }
+        // A night in a bed heals as a day's rest does
+        let healed: i16 = members
+            .iter_mut()
+            .zip(&heals)
+            .filter(|(_, heals)| **heals)
+            .map(|(character, _)| character.natural_healing(rng.as_mut()))
+            .sum();
+        rest_events.send(RestEvent);
+        message.0 = Some(format!(
+            "The party takes rooms for {} gp and sleeps soundly, healing {} hit points",
+            price, healed
+        ));
+    } else if keyboard_input.just_pressed(KeyCode::R) {
+        if let Err(reason) = pay_from_party(&mut members, ROUND_PRICE) {
+            message.0 = Some(format!("Nobody talks to a party that can't stand a round: {}", reason));
+            return;
+        }
+        let party_level = members.iter().map(|character| character.level as u32).sum::<u32>() / members.len() as u32;
+        rumors.send(RumorEvent {
+            request: RumorRequest {
+                location: Location::Inn.name().to_string(),
+                party_level: party_level.max(1) as u8,
+                known_sites: world.dungeons.keys().chain(world.hooks.keys()).cloned().collect(),
+                known_npcs: Location::ALL
+                    .iter()
+                    .flat_map(|location| location.residents())
+                    .map(|resident| resident.name.to_string())
+                    .collect(),
+                quests: quest_log.quests.iter().map(|quest| quest.data.title.clone()).collect(),
+            },
+        });
+        awaiting.0 += 1;
+        message.0 = Some(format!("The party stands the house a round ({} gp) and listens...", ROUND_PRICE));
+    } else if keyboard_input.just_pressed(KeyCode::P) {
+        let mut lines = Vec::new();
+        for character in members.iter_mut() {
+            let Some(carousal) = carouse(character, rng.as_mut()) else {
+                lines.push(format!("{} can't afford a night out", character.name));
+                continue;
+            };
+            let mut line = format!("{} spends {} gp (+{} XP)", character.name, carousal.spent, carousal.experience);
+            if let Some(mishap) = &carousal.mishap {
+                line = format!("{} and {}", line, mishap.describe());
+            }
+            info!("{}", line);
+            lines.push(line);
+        }
+        advance.send(AdvanceClock(TimeSpan::Hours(8)));
+        message.0 = Some(lines.join("\n"));
+    }
+}
+
+fn hear_rumors(
+    mut heard: EventReader<RumorHeardEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut awaiting: ResMut<AwaitingRumors>,
+    mut world: ResMut<WorldDatabase>,
+    mut grapevine: ResMut<Grapevine>,
+    clock: Res<GameClock>,
+    mut message: ResMut<TownMessage>,
+) {
+    for event in heard.read() {
+        awaiting.0 = awaiting.0.saturating_sub(1);
+        let rumor = &event.rumor;
+        grapevine.start(Gossip {
+            text: rumor.text.clone(),
+            truthful: rumor.truthful,
+            site: rumor.site.clone(),
+            known_to: vec!["innkeeper".to_string()],
+            started: clock.day(),
+        });
+        let mut text = format!("Word in the common room: \"{}\"", rumor.text);
+        if !rumor.truthful {
+            debug!("The rumor is false");
+            message.0 = Some(text);
+            continue;
+        }
+        if let (Some(site), Some(theme)) = (&rumor.site, &rumor.theme) {
+            let hook = SiteHook {
+                theme: theme.clone(),
+                level: rumor.level.unwrap_or(1).max(1),
+                rumor: rumor.text.clone(),
+            };
+            if world.add_hook(site.clone(), hook) {
+                text = format!("{}\nA new site to explore: {}", text, site);
+            }
+        }
+        if let Some(quest) = &rumor.quest {
+            text = format!("{}\nA new quest: {}", text, quest.title);
+        }
+        message.0 = Some(text);
+    }
+    for failure in failures.read() {
+        if failure.endpoint == "rumor" && awaiting.0 > 0 {
+            awaiting.0 -= 1;
+            message.0 = Some("Nobody has anything worth repeating tonight".to_string());
+        }
+    }
+}
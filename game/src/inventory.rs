@@ -0,0 +1,736 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::ailments::blocks_healing;
+use crate::character::{ArmorType, Character, Item, ItemType};
+use crate::combat::Combatant;
+use crate::death::ReadRaiseDead;
+use crate::rng::GameRng;
+use crate::text_input::{CharFilter, FieldAction, TextField, Typing};
+use crate::tooltip::item_tooltip;
+use crate::GameState;
+
+pub use old_school_core::equipment::EquipmentSlot;
+
+// Equipping and unequipping. The rules (slots and class restrictions) live in
+// old_school_core::equipment; this module turns input on the inventory screen into
+// EquipEvents and applies them. Keys 1-9 equip that inventory item on the lead party
+// member, Shift+1-8 takes off the weapon, armour, shield, helmet, cloak, boots or left
+// or right ring.
+//
+// Containers in the pack are opened with O, which steps through them in turn. While
+// one is open, Ctrl+1-9 puts that pack item into it and Alt+1-9 takes that item out.
+//
+// With the mouse, items are dragged between the cells of the screen's grid (see ui.rs):
+// onto an equipment slot to wear it, back into the pack to take it off, onto a
+// container or into the open one to stow it, out of the container to take it out, and
+// onto another pack item to move it to that place, or onto a stack of the same thing to
+// add to it. Right-clicking an item opens a menu to use, split (a stack in two), drop
+// or examine it. The carried weight is worked out again after every move.
+//
+// The pack can be shown sorted (S, or the sort button), only one kind of item (F, or
+// the filter buttons) and only items whose name holds what is typed in the search box
+// (/ or a click on it; Enter keeps the search, ESC clears it). This changes only what is
+// shown: the items keep their places, and their numbers, in the pack.
+
+// Where an item is on the inventory screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemPlace {
+    Worn(EquipmentSlot),
+    // An index past the end of the pack is the free space after it
+    Pack(usize),
+    Stowed { container: usize, item: usize },
+}
+
+// A cell of the inventory grid; `filled` if there is an item in it
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InventoryCell {
+    pub place: ItemPlace,
+    pub filled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemAction {
+    Use,
+    // Halve a stack
+    Split,
+    Drop,
+    Examine,
+}
+
+impl ItemAction {
+    pub const ALL: [ItemAction; 4] = [ItemAction::Use, ItemAction::Split, ItemAction::Drop, ItemAction::Examine];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemAction::Use => "Use",
+            ItemAction::Split => "Split",
+            ItemAction::Drop => "Drop",
+            ItemAction::Examine => "Examine",
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ItemMenuButton(pub ItemAction);
+
+#[derive(Event, Clone, Debug)]
+pub enum EquipEvent {
+    // Into the item's own slot, or the one given (which hand for a ring)
+    Equip { character: Entity, item: usize, slot: Option<EquipmentSlot> },
+    Unequip { character: Entity, slot: EquipmentSlot },
+}
+
+#[derive(Event, Clone, Debug)]
+pub enum ContainerEvent {
+    Stow { character: Entity, item: usize, container: usize },
+    TakeOut { character: Entity, container: usize, item: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    // As they are in the pack
+    #[default]
+    Pack,
+    Type,
+    // Heaviest first
+    Weight,
+    // Most valuable first
+    Value,
+    Name,
+}
+
+impl SortMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Pack => "Pack order",
+            SortMode::Type => "Type",
+            SortMode::Weight => "Weight",
+            SortMode::Value => "Value",
+            SortMode::Name => "Name",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Pack => SortMode::Type,
+            SortMode::Type => SortMode::Weight,
+            SortMode::Weight => SortMode::Value,
+            SortMode::Value => SortMode::Name,
+            SortMode::Name => SortMode::Pack,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemFilter {
+    Weapons,
+    // Shields, helmets, cloaks and boots too
+    Armor,
+    Potions,
+    Scrolls,
+    // Rings, treasure and everything else
+    Misc,
+}
+
+impl ItemFilter {
+    pub const ALL: [ItemFilter; 5] = [
+        ItemFilter::Weapons,
+        ItemFilter::Armor,
+        ItemFilter::Potions,
+        ItemFilter::Scrolls,
+        ItemFilter::Misc,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemFilter::Weapons => "Weapons",
+            ItemFilter::Armor => "Armor",
+            ItemFilter::Potions => "Potions",
+            ItemFilter::Scrolls => "Scrolls",
+            ItemFilter::Misc => "Misc",
+        }
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        match item.item_type {
+            ItemType::Weapon(_) => *self == ItemFilter::Weapons,
+            ItemType::Armor(_) | ItemType::Shield | ItemType::Helmet | ItemType::Cloak | ItemType::Boots => {
+                *self == ItemFilter::Armor
+            }
+            ItemType::Potion => *self == ItemFilter::Potions,
+            ItemType::Scroll => *self == ItemFilter::Scrolls,
+            ItemType::Ring | ItemType::Treasure | ItemType::Misc => *self == ItemFilter::Misc,
+        }
+    }
+
+    // All, then each filter in turn
+    fn next(filter: Option<ItemFilter>) -> Option<ItemFilter> {
+        match filter {
+            None => Some(ItemFilter::ALL[0]),
+            Some(current) => ItemFilter::ALL.iter().skip_while(|filter| **filter != current).nth(1).copied(),
+        }
+    }
+}
+
+// Where an item's type comes when sorting by type
+fn type_rank(item: &Item) -> u8 {
+    match &item.item_type {
+        ItemType::Weapon(_) => 0,
+        ItemType::Armor(ArmorType::Plate) => 1,
+        ItemType::Armor(ArmorType::Chain) => 2,
+        ItemType::Armor(ArmorType::Leather) => 3,
+        ItemType::Armor(ArmorType::Robes) => 4,
+        ItemType::Shield => 5,
+        ItemType::Helmet => 6,
+        ItemType::Cloak => 7,
+        ItemType::Boots => 8,
+        ItemType::Ring => 9,
+        ItemType::Potion => 10,
+        ItemType::Scroll => 11,
+        ItemType::Treasure => 12,
+        ItemType::Misc => 13,
+    }
+}
+
+// How the pack is shown
+#[derive(Resource, Debug)]
+pub struct InventoryView {
+    pub sort: SortMode,
+    pub filter: Option<ItemFilter>,
+    pub search: TextField,
+    // The search box has the keyboard
+    pub searching: bool,
+}
+
+impl Default for InventoryView {
+    fn default() -> Self {
+        Self {
+            sort: SortMode::Pack,
+            filter: None,
+            search: TextField::new(CharFilter::Speech),
+            searching: false,
+        }
+    }
+}
+
+impl InventoryView {
+    // The pack indices of the items to show, in the order to show them
+    pub fn shown(&self, items: &[Item]) -> Vec<usize> {
+        let search = self.search.text().trim().to_lowercase();
+        let mut shown: Vec<usize> = (0..items.len())
+            .filter(|&index| self.filter.is_none_or(|filter| filter.matches(&items[index])))
+            .filter(|&index| items[index].name.to_lowercase().contains(&search))
+            .collect();
+        // Stable, so equal items stay in pack order
+        match self.sort {
+            SortMode::Pack => {}
+            SortMode::Type => shown.sort_by_key(|&index| type_rank(&items[index])),
+            SortMode::Weight => shown.sort_by(|&a, &b| items[b].total_weight().total_cmp(&items[a].total_weight())),
+            SortMode::Value => shown.sort_by_key(|&index| std::cmp::Reverse(items[index].total_value())),
+            SortMode::Name => shown.sort_by_key(|&index| items[index].name.to_lowercase()),
+        }
+        shown
+    }
+
+    pub fn is_narrowed(&self) -> bool {
+        self.filter.is_some() || !self.search.is_blank()
+    }
+}
+
+// The sort, filter and search controls above the pack
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryViewButton {
+    Sort,
+    Filter(Option<ItemFilter>),
+    Search,
+}
+
+// An item let go over another cell
+#[derive(Event, Clone, Debug)]
+pub struct MoveItemEvent {
+    pub character: Entity,
+    pub from: ItemPlace,
+    pub to: ItemPlace,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ItemActionEvent {
+    pub character: Entity,
+    pub place: ItemPlace,
+    pub action: ItemAction,
+}
+
+// The item being dragged, if the mouse button is down on one
+#[derive(Resource, Default, Debug)]
+pub struct DraggedItem(pub Option<ItemPlace>);
+
+// The right-click menu: the item it is for and where on the screen it opened
+#[derive(Resource, Default, Debug)]
+pub struct ItemMenu(pub Option<(ItemPlace, Vec2)>);
+
+// The pack index of the container shown on the inventory screen, if any
+#[derive(Resource, Default, Debug)]
+pub struct OpenContainer(pub Option<usize>);
+
+// The outcome of the last equip attempt, for the inventory screen
+#[derive(Resource, Default, Debug)]
+pub struct InventoryMessage(pub Option<String>);
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EquipEvent>()
+            .add_event::<ContainerEvent>()
+            .add_event::<MoveItemEvent>()
+            .add_event::<ItemActionEvent>()
+            .init_resource::<InventoryMessage>()
+            .init_resource::<OpenContainer>()
+            .init_resource::<DraggedItem>()
+            .init_resource::<ItemMenu>()
+            .init_resource::<InventoryView>()
+            .add_systems(OnEnter(GameState::Inventory), (clear_inventory_message, recalculate_party_weight))
+            .add_systems(Update, (
+                (inventory_view_input, inventory_input, item_menu_input, drag_items)
+                    .chain()
+                    .run_if(in_state(GameState::Inventory)),
+                handle_move_events,
+                handle_item_actions,
+                handle_equip_events,
+                handle_container_events,
+            ).chain());
+    }
+}
+
+const DIGIT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+fn clear_inventory_message(
+    mut message: ResMut<InventoryMessage>,
+    mut open: ResMut<OpenContainer>,
+    mut dragged: ResMut<DraggedItem>,
+    mut menu: ResMut<ItemMenu>,
+    mut view: ResMut<InventoryView>,
+) {
+    message.0 = None;
+    open.0 = None;
+    dragged.0 = None;
+    menu.0 = None;
+    view.searching = false;
+}
+
+// Sorting, filtering and the search box. While the box has the keyboard the other
+// inventory keys are typed into it instead.
+fn inventory_view_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    buttons: Query<(&Interaction, &InventoryViewButton), Changed<Interaction>>,
+    mut typing: ResMut<Typing>,
+    mut view: ResMut<InventoryView>,
+) {
+    if view.searching {
+        // Only a change to the text redraws the screen
+        let field = view.bypass_change_detection();
+        let before = field.search.display();
+        let action = field.search.edit(&mut typing);
+        match action {
+            Some(FieldAction::Submit) => field.searching = false,
+            Some(FieldAction::Cancel) => {
+                field.search.clear();
+                field.searching = false;
+            }
+            None => {}
+        }
+        if action.is_some() || field.search.display() != before {
+            view.set_changed();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::S) {
+        view.sort = view.sort.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::F) {
+        view.filter = ItemFilter::next(view.filter);
+    }
+    if keyboard_input.just_pressed(KeyCode::Slash) {
+        view.searching = true;
+    }
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            InventoryViewButton::Sort => view.sort = view.sort.next(),
+            InventoryViewButton::Filter(filter) => view.filter = *filter,
+            InventoryViewButton::Search => view.searching = true,
+        }
+    }
+}
+
+// So the screen opens on the right figure, whatever changed hands since it was last open
+fn recalculate_party_weight(mut characters: Query<&mut Character, With<Combatant>>) {
+    for mut character in characters.iter_mut() {
+        character.recalculate_weight();
+    }
+}
+
+fn inventory_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    party: Query<(Entity, &Character, &Combatant)>,
+    mut open: ResMut<OpenContainer>,
+    mut equip_events: EventWriter<EquipEvent>,
+    mut container_events: EventWriter<ContainerEvent>,
+) {
+    let Some((character, lead, _)) = party.iter().find(|(_, _, combatant)| combatant.is_player) else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::O) {
+        open.0 = next_container(&lead.inventory.items, open.0);
+    }
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    for (index, key) in DIGIT_KEYS.iter().enumerate() {
+        if !keyboard_input.just_pressed(*key) {
+            continue;
+        }
+        match (open.0, ctrl, alt) {
+            (Some(container), true, _) => {
+                container_events.send(ContainerEvent::Stow { character, item: index, container });
+            }
+            (Some(container), _, true) => {
+                container_events.send(ContainerEvent::TakeOut { character, container, item: index });
+            }
+            _ if !shift => equip_events.send(EquipEvent::Equip { character, item: index, slot: None }),
+            _ => {
+                if let Some(&slot) = EquipmentSlot::ALL.get(index) {
+                    equip_events.send(EquipEvent::Unequip { character, slot });
+                }
+            }
+        }
+    }
+}
+
+// Picks an item up when the left button goes down on it and lets it go over whichever
+// cell the button comes up on
+fn drag_items(
+    mouse: Res<Input<MouseButton>>,
+    cells: Query<(&Interaction, &InventoryCell)>,
+    party: Query<(Entity, &Combatant), With<Character>>,
+    mut dragged: ResMut<DraggedItem>,
+    mut move_events: EventWriter<MoveItemEvent>,
+) {
+    if mouse.just_pressed(MouseButton::Left) {
+        dragged.0 = cells
+            .iter()
+            .find(|(interaction, cell)| **interaction == Interaction::Pressed && cell.filled)
+            .map(|(_, cell)| cell.place);
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(from) = dragged.0.take() else {
+        return;
+    };
+    let Some((character, _)) = party.iter().find(|(_, combatant)| combatant.is_player) else {
+        return;
+    };
+    let target = cells.iter().find(|(interaction, _)| **interaction != Interaction::None).map(|(_, cell)| cell.place);
+    if let Some(to) = target.filter(|to| *to != from) {
+        move_events.send(MoveItemEvent { character, from, to });
+    }
+}
+
+// A right click on an item opens its menu by the cursor; choosing from it, or clicking
+// anywhere else, closes it
+fn item_menu_input(
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cells: Query<(&Interaction, &InventoryCell)>,
+    buttons: Query<(&Interaction, &ItemMenuButton), Changed<Interaction>>,
+    party: Query<(Entity, &Combatant), With<Character>>,
+    mut menu: ResMut<ItemMenu>,
+    mut action_events: EventWriter<ItemActionEvent>,
+) {
+    if let Some((place, _)) = menu.0 {
+        let chosen = buttons.iter().find(|(interaction, _)| **interaction == Interaction::Pressed);
+        if let Some((_, button)) = chosen {
+            if let Some((character, _)) = party.iter().find(|(_, combatant)| combatant.is_player) {
+                action_events.send(ItemActionEvent { character, place, action: button.0 });
+            }
+            menu.0 = None;
+        } else if mouse.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+            menu.0 = None;
+        }
+    }
+    if !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let cursor = windows.get_single().ok().and_then(|window| window.cursor_position());
+    let hovered = cells.iter().find(|(interaction, cell)| **interaction != Interaction::None && cell.filled);
+    if let (Some(cursor), Some((_, cell))) = (cursor, hovered) {
+        menu.0 = Some((cell.place, cursor));
+    }
+}
+
+// Where the pack item at `index` ends up when the one at `from` moves to `to`
+fn shifted(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        return to;
+    }
+    let index = if index > from { index - 1 } else { index };
+    if index >= to {
+        index + 1
+    } else {
+        index
+    }
+}
+
+// Turns a drop into an equip, a stow, a take-out or a merge, or moves the item within
+// the pack
+fn handle_move_events(
+    mut move_events: EventReader<MoveItemEvent>,
+    mut characters: Query<&mut Character>,
+    mut open: ResMut<OpenContainer>,
+    mut message: ResMut<InventoryMessage>,
+    mut equip_events: EventWriter<EquipEvent>,
+    mut container_events: EventWriter<ContainerEvent>,
+) {
+    for MoveItemEvent { character: entity, from, to } in move_events.read() {
+        let entity = *entity;
+        let Ok(mut character) = characters.get_mut(entity) else {
+            continue;
+        };
+        let len = character.inventory.items.len();
+        let items = &character.inventory.items;
+        let container_at = |index: usize| items.get(index).is_some_and(Item::is_container);
+        let stacks_at = |from: usize, to: usize| {
+            from != to && items.get(from).zip(items.get(to)).is_some_and(|(from, to)| to.stacks_with(from))
+        };
+
+        let note = match (*from, *to) {
+            (ItemPlace::Pack(item), ItemPlace::Worn(slot)) => match character.inventory.items.get(item) {
+                Some(worn) if slot.fits(worn) => {
+                    equip_events.send(EquipEvent::Equip { character: entity, item, slot: Some(slot) });
+                    None
+                }
+                Some(worn) => Some(format!("The {} doesn't go in the {} slot", worn.name, slot.name().to_lowercase())),
+                None => None,
+            },
+            (ItemPlace::Worn(slot), ItemPlace::Pack(_)) => {
+                equip_events.send(EquipEvent::Unequip { character: entity, slot });
+                None
+            }
+            (ItemPlace::Pack(item), ItemPlace::Pack(stack)) if stacks_at(item, stack) => {
+                let name = character.inventory.items[stack].name.clone();
+                let merged = character.inventory.merge_stacks(item, stack);
+                // An emptied stack leaves the pack
+                if character.inventory.items.len() < len {
+                    open.0 = open.0.map(|container| if container > item { container - 1 } else { container });
+                }
+                Some(merged.map_or_else(|reason| reason, |moved| format!("Added {} to the {}", moved, name)))
+            }
+            (ItemPlace::Pack(item), ItemPlace::Pack(container)) if container_at(container) => {
+                container_events.send(ContainerEvent::Stow { character: entity, item, container });
+                None
+            }
+            (ItemPlace::Pack(item), ItemPlace::Stowed { container, .. }) => {
+                container_events.send(ContainerEvent::Stow { character: entity, item, container });
+                None
+            }
+            (ItemPlace::Pack(item), ItemPlace::Pack(place)) => {
+                let place = place.min(len.saturating_sub(1));
+                match character.inventory.move_item(item, place) {
+                    Ok(()) => {
+                        open.0 = open.0.map(|container| shifted(container, item, place));
+                        None
+                    }
+                    Err(reason) => Some(reason),
+                }
+            }
+            (ItemPlace::Stowed { container, item }, ItemPlace::Pack(_)) => {
+                container_events.send(ContainerEvent::TakeOut { character: entity, container, item });
+                None
+            }
+            (ItemPlace::Stowed { .. }, ItemPlace::Worn(_)) => Some("Take it out into the pack first".to_string()),
+            (ItemPlace::Worn(_), ItemPlace::Stowed { .. }) => Some("Take it off first".to_string()),
+            (ItemPlace::Worn(_), ItemPlace::Worn(slot)) => {
+                Some(format!("That doesn't go in the {} slot", slot.name().to_lowercase()))
+            }
+            (ItemPlace::Stowed { .. }, ItemPlace::Stowed { .. }) => None,
+        };
+        character.recalculate_weight();
+        if note.is_some() {
+            message.0 = note;
+        }
+    }
+}
+
+// The item at `place`, wherever it is
+pub fn item_at(character: &Character, place: ItemPlace) -> Option<&Item> {
+    match place {
+        ItemPlace::Worn(slot) => character.equipment.get(slot),
+        ItemPlace::Pack(index) => character.inventory.items.get(index),
+        ItemPlace::Stowed { container, item } => {
+            character.inventory.items.get(container)?.container.as_ref()?.contents.get(item)
+        }
+    }
+}
+
+// Using a weapon or armour means equipping it; anything else has to be in the pack to
+// be used. Dropped items are left where the party stands.
+fn handle_item_actions(
+    mut action_events: EventReader<ItemActionEvent>,
+    mut characters: Query<(&mut Character, Option<&Combatant>)>,
+    mut rng: ResMut<GameRng>,
+    mut open: ResMut<OpenContainer>,
+    mut message: ResMut<InventoryMessage>,
+    mut equip_events: EventWriter<EquipEvent>,
+    mut raise_reads: EventWriter<ReadRaiseDead>,
+) {
+    for event in action_events.read() {
+        let Ok((mut character, combatant)) = characters.get_mut(event.character) else {
+            continue;
+        };
+        let Some((name, equippable, raises_dead, heals, described)) = item_at(&character, event.place).map(|item| {
+            let described = item_tooltip(item).replace('\n', "; ");
+            (item.name.clone(), item.slot().is_some(), item.raises_dead(), item.healing().is_some(), described)
+        }) else {
+            continue;
+        };
+        let rotting = combatant.is_some_and(|combatant| blocks_healing(&combatant.status_effects));
+
+        let result = match (event.action, event.place) {
+            (ItemAction::Examine, _) => Ok(described),
+            (ItemAction::Use, ItemPlace::Pack(index)) if equippable => {
+                equip_events.send(EquipEvent::Equip { character: event.character, item: index, slot: None });
+                continue;
+            }
+            (ItemAction::Use, ItemPlace::Pack(index)) if raises_dead => {
+                raise_reads.send(ReadRaiseDead { reader: event.character, item: index });
+                continue;
+            }
+            (ItemAction::Use, ItemPlace::Pack(_)) if heals && rotting => {
+                Err(format!("The rot won't let {}'s wounds close", character.name))
+            }
+            (ItemAction::Use, ItemPlace::Pack(index)) => character.use_item(index, rng.as_mut()),
+            (ItemAction::Use, ItemPlace::Worn(_)) => Err(format!("The {} is already in use", name)),
+            (ItemAction::Use, ItemPlace::Stowed { .. }) => Err(format!("Take the {} out first", name)),
+            (ItemAction::Split, ItemPlace::Pack(index)) => {
+                character.inventory.split_stack(index).map(|()| format!("Split the {}", name))
+            }
+            (ItemAction::Split, _) => Err(format!("Take the {} into the pack first", name)),
+            (ItemAction::Drop, ItemPlace::Pack(index)) => {
+                let dropped = character.drop_item(index);
+                if dropped.is_ok() {
+                    open.0 = open.0.filter(|&container| container != index).map(|container| {
+                        if container > index { container - 1 } else { container }
+                    });
+                }
+                dropped.map(|_| format!("Dropped {}", name))
+            }
+            (ItemAction::Drop, ItemPlace::Worn(slot)) => character.unequip_item(slot).and_then(|()| {
+                let last = character.inventory.items.len() - 1;
+                character.drop_item(last).map(|_| format!("Dropped {}", name))
+            }),
+            (ItemAction::Drop, ItemPlace::Stowed { container, item }) => {
+                character.inventory.take_out(container, item).and_then(|()| {
+                    let last = character.inventory.items.len() - 1;
+                    character.drop_item(last).map(|_| format!("Dropped {}", name))
+                })
+            }
+        };
+        character.recalculate_weight();
+        let text = result.unwrap_or_else(|reason| reason);
+        info!("{}: {}", character.name, text);
+        message.0 = Some(text);
+    }
+}
+
+// The next container in the pack after `current`, or None once past the last
+fn next_container(items: &[Item], current: Option<usize>) -> Option<usize> {
+    let start = current.map_or(0, |index| index + 1);
+    items.iter().enumerate().skip(start).find(|(_, item)| item.is_container()).map(|(index, _)| index)
+}
+
+// Equipping reshuffles the pack, so it closes any open container
+fn handle_equip_events(
+    mut equip_events: EventReader<EquipEvent>,
+    mut characters: Query<&mut Character>,
+    mut open: ResMut<OpenContainer>,
+    mut message: ResMut<InventoryMessage>,
+) {
+    for event in equip_events.read() {
+        let entity = match event {
+            EquipEvent::Equip { character, .. } | EquipEvent::Unequip { character, .. } => *character,
+        };
+        let Ok(mut character) = characters.get_mut(entity) else {
+            continue;
+        };
+
+        let result = match event {
+            EquipEvent::Equip { item, slot, .. } => {
+                let name = character.inventory.items.get(*item).map(|item| item.name.clone());
+                let equipped = match slot {
+                    Some(slot) => character.equip_item_to(*item, *slot),
+                    None => character.equip_item(*item),
+                };
+                equipped.map(|()| format!("Equipped {}", name.unwrap_or_default()))
+            }
+            EquipEvent::Unequip { slot, .. } => character
+                .unequip_item(*slot)
+                .map(|()| format!("Took off {}", slot.name().to_lowercase())),
+        };
+        if result.is_ok() {
+            open.0 = None;
+        }
+        character.recalculate_weight();
+        let text = result.unwrap_or_else(|reason| reason);
+        info!("{}: {} (AC {})", character.name, text, character.armor_class);
+        message.0 = Some(text);
+    }
+}
+
+// Stowing an item ahead of the open container moves it up the pack; the view follows it
+fn handle_container_events(
+    mut container_events: EventReader<ContainerEvent>,
+    mut characters: Query<&mut Character>,
+    mut open: ResMut<OpenContainer>,
+    mut message: ResMut<InventoryMessage>,
+) {
+    for event in container_events.read() {
+        let (ContainerEvent::Stow { character, .. } | ContainerEvent::TakeOut { character, .. }) = event;
+        let Ok(mut character) = characters.get_mut(*character) else {
+            continue;
+        };
+
+        let result = match *event {
+            ContainerEvent::Stow { item, container, .. } => {
+                let name = character.inventory.items.get(item).map(|item| item.name.clone()).unwrap_or_default();
+                character.inventory.stow(item, container).map(|container| {
+                    open.0 = Some(container);
+                    format!("Put {} in {}", name, character.inventory.items[container].name)
+                })
+            }
+            ContainerEvent::TakeOut { container, item, .. } => {
+                character.inventory.take_out(container, item).map(|()| {
+                    let taken = character.inventory.items.last().map(|item| item.name.clone()).unwrap_or_default();
+                    format!("Took {} out", taken)
+                })
+            }
+        };
+        character.recalculate_weight();
+        let text = result.unwrap_or_else(|reason| reason);
+        info!("{}: {}", character.name, text);
+        message.0 = Some(text);
+    }
+}
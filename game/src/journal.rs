@@ -0,0 +1,665 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::tasks::Task;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai_client::{AIClient, NPCConversationEvent, RecapRequest, RumorHeardEvent};
+use crate::character::{Character, SkillCheckResult, ThiefSkill};
+use crate::combat::Combatant;
+use crate::game_events::GameEvent;
+use crate::quests::Resolution;
+use crate::tasks::{poll_task, AsyncRuntime};
+use crate::GameState;
+
+// The session journal collects notable moments while playing and bundles them into
+// a "campaign diary" (diary.md and diary.html) in its own directory under the journal
+// folder, alongside any screenshots taken during the session. F10 exports on demand,
+// optionally asking the AI service for a prose recap first; the diary is also written
+// when the game closes.
+
+pub const DEFAULT_JOURNAL_DIR: &str = "journal";
+pub const MAX_AUTO_SCREENSHOTS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalCategory {
+    Exploration,
+    Combat,
+    Dialogue,
+    Rumor,
+    Milestone,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub elapsed_secs: f32,
+    pub category: JournalCategory,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExploredMap {
+    pub name: String,
+    pub rooms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogueLine {
+    pub elapsed_secs: f32,
+    pub speaker: String,
+    pub listener: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalScreenshot {
+    pub elapsed_secs: f32,
+    pub file_name: String,
+    pub caption: String,
+}
+
+type RecapTask = Task<Option<Result<String, String>>>;
+
+#[derive(Resource)]
+pub struct SessionJournal {
+    pub started_at: u64,
+    pub directory: PathBuf,
+    pub entries: Vec<JournalEntry>,
+    pub maps: Vec<ExploredMap>,
+    pub dialogue: Vec<DialogueLine>,
+    pub screenshots: Vec<JournalScreenshot>,
+    pub recap: Option<String>,
+    now: f32,
+    pending_screenshots: Vec<String>,
+    recap_request: Option<RecapTask>,
+}
+
+#[derive(Event)]
+pub struct ExportJournal {
+    pub with_recap: bool,
+}
+
+#[derive(Event)]
+pub struct JournalExported {
+    pub path: PathBuf,
+}
+
+pub struct JournalPlugin {
+    pub directory: PathBuf,
+    pub ai_recap: bool,
+}
+
+#[derive(Resource)]
+struct JournalSettings {
+    ai_recap: bool,
+}
+
+impl Plugin for JournalPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SessionJournal::new(&self.directory))
+            .insert_resource(JournalSettings { ai_recap: self.ai_recap })
+            .add_event::<ExportJournal>()
+            .add_event::<JournalExported>()
+            .add_systems(First, tick_journal_clock)
+            .add_systems(OnEnter(GameState::InGame), journal_adventure_start)
+            .add_systems(OnEnter(GameState::Combat), journal_combat_start)
+            .add_systems(OnExit(GameState::Combat), journal_combat_end)
+            .add_systems(Update, (
+                journal_game_events,
+                journal_falls,
+                journal_skill_checks,
+                journal_conversations,
+                journal_rumors,
+                journal_keys,
+                capture_screenshots,
+                start_journal_export,
+                finish_journal_export,
+            ).chain())
+            .add_systems(Last, export_journal_on_exit);
+    }
+}
+
+impl SessionJournal {
+    pub fn new(journal_dir: &Path) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let (year, month, day, hour, minute) = civil_time(started_at);
+
+        Self {
+            started_at,
+            directory: journal_dir.join(format!(
+                "session-{:04}-{:02}-{:02}-{:02}{:02}",
+                year, month, day, hour, minute
+            )),
+            entries: Vec::new(),
+            maps: Vec::new(),
+            dialogue: Vec::new(),
+            screenshots: Vec::new(),
+            recap: None,
+            now: 0.0,
+            pending_screenshots: Vec::new(),
+            recap_request: None,
+        }
+    }
+
+    pub fn record(&mut self, category: JournalCategory, text: impl Into<String>) {
+        self.entries.push(JournalEntry {
+            elapsed_secs: self.now,
+            category,
+            text: text.into(),
+        });
+    }
+
+    // Rooms are listed once each, in the order they were first entered
+    pub fn record_room(&mut self, dungeon: &str, room: &str) {
+        let index = match self.maps.iter().position(|map| map.name == dungeon) {
+            Some(index) => index,
+            None => {
+                self.maps.push(ExploredMap {
+                    name: dungeon.to_string(),
+                    rooms: Vec::new(),
+                });
+                self.maps.len() - 1
+            }
+        };
+
+        let map = &mut self.maps[index];
+        if !map.rooms.iter().any(|r| r == room) {
+            map.rooms.push(room.to_string());
+        }
+    }
+
+    pub fn record_dialogue(&mut self, speaker: &str, listener: Option<&str>, text: &str) {
+        self.dialogue.push(DialogueLine {
+            elapsed_secs: self.now,
+            speaker: speaker.to_string(),
+            listener: listener.map(str::to_string),
+            text: text.to_string(),
+        });
+    }
+
+    // Automatic captures stop after MAX_AUTO_SCREENSHOTS; F12 always captures
+    pub fn request_screenshot(&mut self, caption: impl Into<String>) {
+        if self.screenshots.len() + self.pending_screenshots.len() < MAX_AUTO_SCREENSHOTS {
+            self.pending_screenshots.push(caption.into());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.dialogue.is_empty() && self.screenshots.is_empty()
+    }
+
+    pub fn diary_path(&self) -> PathBuf {
+        self.directory.join("diary.md")
+    }
+
+    pub fn recap_request(&self, party: &[Character]) -> RecapRequest {
+        RecapRequest {
+            party: party.iter().map(describe_character).collect(),
+            events: self.entries.iter().map(|entry| entry.text.clone()).collect(),
+            dialogue: self
+                .dialogue
+                .iter()
+                .map(|line| format!("{}: {}", line.speaker, line.text))
+                .collect(),
+            locations: self.maps.iter().map(|map| map.name.clone()).collect(),
+        }
+    }
+
+    pub fn to_markdown(&self, party: &[Character]) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Campaign Diary — {}\n", format_date(self.started_at));
+
+        if let Some(recap) = &self.recap {
+            let _ = writeln!(out, "## Recap\n\n{}\n", recap.trim());
+        }
+
+        if !party.is_empty() {
+            out.push_str("## The Party\n\n");
+            for character in party {
+                let _ = writeln!(out, "- {}", describe_character(character));
+            }
+            out.push('\n');
+        }
+
+        if !self.entries.is_empty() {
+            out.push_str("## Adventure Log\n\n");
+            for entry in &self.entries {
+                let _ = writeln!(
+                    out,
+                    "- `{}` **{:?}** — {}",
+                    format_elapsed(entry.elapsed_secs),
+                    entry.category,
+                    entry.text
+                );
+            }
+            out.push('\n');
+        }
+
+        if !self.maps.is_empty() {
+            out.push_str("## Maps Explored\n\n");
+            for map in &self.maps {
+                let _ = writeln!(out, "### {}\n", map.name);
+                for room in &map.rooms {
+                    let _ = writeln!(out, "- {}", room);
+                }
+                out.push('\n');
+            }
+        }
+
+        if !self.dialogue.is_empty() {
+            out.push_str("## Notable Dialogue\n\n");
+            for line in &self.dialogue {
+                let _ = writeln!(out, "> **{}:** {}\n", speaker_label(line), line.text);
+            }
+        }
+
+        if !self.screenshots.is_empty() {
+            out.push_str("## Screenshots\n\n");
+            for shot in &self.screenshots {
+                let _ = writeln!(
+                    out,
+                    "![{caption}]({file})\n\n*{caption}* ({time})\n",
+                    caption = shot.caption,
+                    file = shot.file_name,
+                    time = format_elapsed(shot.elapsed_secs)
+                );
+            }
+        }
+
+        out
+    }
+
+    pub fn to_html(&self, party: &[Character]) -> String {
+        let title = format!("Campaign Diary — {}", format_date(self.started_at));
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+            escape_html(&title)
+        );
+        out.push_str(
+            "<style>body{max-width:48em;margin:2em auto;font-family:Georgia,serif;background:#f4ecd8;color:#2b2118}\
+             img{max-width:100%;border:1px solid #2b2118}blockquote{border-left:3px solid #8b6f47;margin-left:0;padding-left:1em}\
+             .time{font-family:monospace;color:#8b6f47}</style>\n</head>\n<body>\n",
+        );
+        let _ = writeln!(out, "<h1>{}</h1>", escape_html(&title));
+
+        if let Some(recap) = &self.recap {
+            out.push_str("<h2>Recap</h2>\n");
+            for paragraph in recap.split("\n\n").filter(|p| !p.trim().is_empty()) {
+                let _ = writeln!(out, "<p>{}</p>", escape_html(paragraph.trim()));
+            }
+        }
+
+        if !party.is_empty() {
+            out.push_str("<h2>The Party</h2>\n<ul>\n");
+            for character in party {
+                let _ = writeln!(out, "<li>{}</li>", escape_html(&describe_character(character)));
+            }
+            out.push_str("</ul>\n");
+        }
+
+        if !self.entries.is_empty() {
+            out.push_str("<h2>Adventure Log</h2>\n<ul>\n");
+            for entry in &self.entries {
+                let _ = writeln!(
+                    out,
+                    "<li><span class=\"time\">{}</span> <strong>{:?}</strong> — {}</li>",
+                    format_elapsed(entry.elapsed_secs),
+                    entry.category,
+                    escape_html(&entry.text)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+
+        if !self.maps.is_empty() {
+            out.push_str("<h2>Maps Explored</h2>\n");
+            for map in &self.maps {
+                let _ = writeln!(out, "<h3>{}</h3>\n<ul>", escape_html(&map.name));
+                for room in &map.rooms {
+                    let _ = writeln!(out, "<li>{}</li>", escape_html(room));
+                }
+                out.push_str("</ul>\n");
+            }
+        }
+
+        if !self.dialogue.is_empty() {
+            out.push_str("<h2>Notable Dialogue</h2>\n");
+            for line in &self.dialogue {
+                let _ = writeln!(
+                    out,
+                    "<blockquote><strong>{}:</strong> {}</blockquote>",
+                    escape_html(&speaker_label(line)),
+                    escape_html(&line.text)
+                );
+            }
+        }
+
+        if !self.screenshots.is_empty() {
+            out.push_str("<h2>Screenshots</h2>\n");
+            for shot in &self.screenshots {
+                let _ = writeln!(
+                    out,
+                    "<figure><img src=\"{}\" alt=\"{caption}\"><figcaption>{caption} \
+                     <span class=\"time\">{}</span></figcaption></figure>",
+                    escape_html(&shot.file_name),
+                    format_elapsed(shot.elapsed_secs),
+                    caption = escape_html(&shot.caption)
+                );
+            }
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    // Writes diary.md and diary.html into the session directory, returning the markdown path
+    pub fn write_diary(&self, party: &[Character]) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.diary_path();
+        std::fs::write(&path, self.to_markdown(party))?;
+        std::fs::write(self.directory.join("diary.html"), self.to_html(party))?;
+        Ok(path)
+    }
+}
+
+fn describe_character(character: &Character) -> String {
+    format!("{}, level {} {:?}", character.name, character.level, character.class)
+}
+
+fn speaker_label(line: &DialogueLine) -> String {
+    match &line.listener {
+        Some(listener) => format!("{} → {}", line.speaker, listener),
+        None => line.speaker.clone(),
+    }
+}
+
+fn format_elapsed(secs: f32) -> String {
+    let total = secs.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, (total / 60) % 60, total % 60)
+}
+
+pub(crate) fn format_date(unix_secs: u64) -> String {
+    let (year, month, day, hour, minute) = civil_time(unix_secs);
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, hour, minute)
+}
+
+// Days-to-civil conversion (Howard Hinnant's algorithm), to avoid pulling in a date crate
+fn civil_time(unix_secs: u64) -> (i64, u32, u32, u32, u32) {
+    let days = (unix_secs / 86_400) as i64;
+    let secs_of_day = unix_secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, (secs_of_day / 3600) as u32, ((secs_of_day / 60) % 60) as u32)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn party_members(characters: &Query<(&Character, Option<&Combatant>)>) -> Vec<Character> {
+    characters
+        .iter()
+        .filter(|(_, combatant)| combatant.is_none_or(|c| c.is_player))
+        .map(|(character, _)| character.clone())
+        .collect()
+}
+
+fn tick_journal_clock(mut journal: ResMut<SessionJournal>, time: Res<Time>) {
+    journal.now = time.elapsed_seconds();
+}
+
+fn journal_adventure_start(mut journal: ResMut<SessionJournal>) {
+    if journal.entries.is_empty() {
+        journal.record(JournalCategory::Milestone, "The adventure begins.");
+    }
+}
+
+fn journal_combat_start(mut journal: ResMut<SessionJournal>) {
+    journal.record(JournalCategory::Combat, "Battle is joined.");
+    journal.request_screenshot("Battle is joined");
+}
+
+fn journal_combat_end(mut journal: ResMut<SessionJournal>) {
+    journal.record(JournalCategory::Combat, "The fighting ends.");
+}
+
+// Rooms explored, quests taken on and ended, level-ups and the people the party meets
+fn journal_game_events(mut journal: ResMut<SessionJournal>, mut events: EventReader<GameEvent>) {
+    for event in events.read() {
+        match event {
+            GameEvent::RoomEntered { dungeon, room, .. } => journal.record_room(dungeon, room),
+            GameEvent::QuestAccepted { title, giver } => {
+                journal.record(JournalCategory::Milestone, format!("Took on \"{}\" for {}.", title, giver));
+            }
+            GameEvent::QuestEnded { title, resolution } => {
+                let ending = match resolution {
+                    Resolution::Fulfilled => "Completed",
+                    Resolution::Negotiated => "Settled",
+                    Resolution::Betrayed => "Betrayed the patron of",
+                    Resolution::Abandoned => "Gave up on",
+                    Resolution::Expired => "Ran out of time for",
+                };
+                journal.record(JournalCategory::Milestone, format!("{} \"{}\".", ending, title));
+            }
+            GameEvent::LevelUp { character, level } => {
+                journal.record(JournalCategory::Milestone, format!("{} reached level {}.", character, level));
+                journal.request_screenshot(format!("{} reaches level {}", character, level));
+            }
+            GameEvent::NpcMet { name, .. } => {
+                journal.record(JournalCategory::Dialogue, format!("Met {}.", name));
+            }
+            _ => {}
+        }
+    }
+}
+
+// Characters falling to 0 hit points, monsters as well as the party
+fn journal_falls(
+    mut journal: ResMut<SessionJournal>,
+    characters: Query<&Character, Changed<Character>>,
+    mut seen: Local<HashMap<String, bool>>,
+) {
+    for character in characters.iter() {
+        let fallen = character.hit_points.current <= 0;
+        let was_fallen = seen.insert(character.name.clone(), fallen);
+        if fallen && was_fallen == Some(false) {
+            journal.record(JournalCategory::Combat, format!("{} fell.", character.name));
+        }
+    }
+}
+
+// Successful lock-picking and trap-disarming; the rest of the skill rolls are too
+// routine to be worth a line
+fn journal_skill_checks(
+    mut journal: ResMut<SessionJournal>,
+    mut results: EventReader<SkillCheckResult>,
+    characters: Query<&Character>,
+) {
+    for result in results.read() {
+        if !result.check.success {
+            continue;
+        }
+        let Ok(character) = characters.get(result.character) else {
+            continue;
+        };
+        let text = match result.check.skill {
+            ThiefSkill::OpenLocks => format!("{} picked a lock.", character.name),
+            ThiefSkill::FindRemoveTraps => format!("{} found and disarmed a trap.", character.name),
+            _ => continue,
+        };
+        journal.record(JournalCategory::Exploration, text);
+    }
+}
+
+fn journal_conversations(
+    mut journal: ResMut<SessionJournal>,
+    mut conversation_events: EventReader<NPCConversationEvent>,
+) {
+    for event in conversation_events.read() {
+        journal.record_dialogue("Party", Some(&event.npc_id), &event.player_message);
+        journal.record(
+            JournalCategory::Dialogue,
+            format!("Spoke with {} at {}.", event.npc_id, event.context.location),
+        );
+    }
+}
+
+// Everything heard, true or not; the diary doesn't know which
+fn journal_rumors(mut journal: ResMut<SessionJournal>, mut heard: EventReader<RumorHeardEvent>) {
+    for event in heard.read() {
+        journal.record(JournalCategory::Rumor, format!("Heard at the inn: \"{}\"", event.rumor.text));
+    }
+}
+
+// F10 exports the diary, F12 takes a screenshot for it
+fn journal_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<JournalSettings>,
+    mut journal: ResMut<SessionJournal>,
+    mut export_events: EventWriter<ExportJournal>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F10) {
+        export_events.send(ExportJournal {
+            with_recap: settings.ai_recap,
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::F12) {
+        journal.pending_screenshots.push("Snapshot".to_string());
+    }
+}
+
+fn capture_screenshots(
+    mut journal: ResMut<SessionJournal>,
+    screenshot_manager: Option<ResMut<ScreenshotManager>>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+) {
+    if journal.pending_screenshots.is_empty() {
+        return;
+    }
+    // Without a window (headless) there's nothing to capture
+    let (Some(mut screenshot_manager), Ok(window)) = (screenshot_manager, windows.get_single()) else {
+        journal.pending_screenshots.clear();
+        return;
+    };
+
+    // One capture per window per frame, so the rest wait their turn in the frames after
+    let caption = journal.pending_screenshots.remove(0);
+    if let Err(e) = std::fs::create_dir_all(&journal.directory) {
+        error!("Could not create journal directory {}: {}", journal.directory.display(), e);
+        return;
+    }
+
+    let file_name = format!("screenshot-{:02}.png", journal.screenshots.len() + 1);
+    let path = journal.directory.join(&file_name);
+    if screenshot_manager.save_screenshot_to_disk(window, path).is_ok() {
+        let elapsed_secs = journal.now;
+        journal.screenshots.push(JournalScreenshot {
+            elapsed_secs,
+            file_name,
+            caption,
+        });
+    } else {
+        // Something else took this frame's capture
+        journal.pending_screenshots.insert(0, caption);
+    }
+}
+
+fn start_journal_export(
+    mut journal: ResMut<SessionJournal>,
+    mut export_events: EventReader<ExportJournal>,
+    mut exported: EventWriter<JournalExported>,
+    characters: Query<(&Character, Option<&Combatant>)>,
+    ai_client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+) {
+    let Some(with_recap) = export_events.read().last().map(|event| event.with_recap) else {
+        return;
+    };
+    if journal.recap_request.is_some() {
+        return;
+    }
+
+    let party = party_members(&characters);
+    if !with_recap {
+        write_journal(&journal, &party, &mut exported);
+        return;
+    }
+
+    info!("Asking the AI service for a session recap...");
+    let request = journal.recap_request(&party);
+    let client = ai_client.clone();
+    journal.recap_request = Some(runtime.spawn(async move {
+        client
+            .write_recap(request)
+            .await
+            .map(|response| response.recap)
+            .map_err(|e| e.to_string())
+    }));
+}
+
+fn finish_journal_export(
+    mut journal: ResMut<SessionJournal>,
+    mut exported: EventWriter<JournalExported>,
+    characters: Query<(&Character, Option<&Combatant>)>,
+) {
+    let Some(task) = journal.recap_request.as_mut() else {
+        return;
+    };
+    let Some(result) = poll_task(task) else {
+        return;
+    };
+    journal.recap_request = None;
+
+    match result {
+        Some(Ok(recap)) => journal.recap = Some(recap),
+        // The diary is still worth having without the recap
+        Some(Err(e)) => warn!("Session recap failed: {}", e),
+        None => warn!("Session recap task panicked"),
+    }
+
+    write_journal(&journal, &party_members(&characters), &mut exported);
+}
+
+fn export_journal_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    journal: Res<SessionJournal>,
+    characters: Query<(&Character, Option<&Combatant>)>,
+    mut exported: EventWriter<JournalExported>,
+) {
+    if exit_events.read().next().is_none() || journal.is_empty() {
+        return;
+    }
+    write_journal(&journal, &party_members(&characters), &mut exported);
+}
+
+fn write_journal(journal: &SessionJournal, party: &[Character], exported: &mut EventWriter<JournalExported>) {
+    match journal.write_diary(party) {
+        Ok(path) => {
+            info!("Wrote campaign diary to {}", path.display());
+            exported.send(JournalExported { path });
+        }
+        Err(e) => error!("Failed to write campaign diary to {}: {}", journal.directory.display(), e),
+    }
+}
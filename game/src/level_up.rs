@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::combat::{CombatLogEvent, Combatant};
+use crate::data::SpellCompendium;
+use crate::loot::looters;
+use crate::rng::GameRng;
+use crate::ruleset::Ruleset;
+use crate::GameState;
+
+pub use old_school_core::advancement::Advancement;
+
+// Going up a level (the rules are in old_school_core::advancement). Once a party member
+// has the experience for their next level, the level-up screen opens the next time the
+// party is exploring or in town and sets out what the level brings: the hit die to roll,
+// then the better attacks, saving throws, spells and skills. A magic-user or elf
+// reaching a new spell level picks the spell to write into their book with Up and Down,
+// and a cleric is told the prayers they can now call on. R rolls the die, and Enter
+// takes the level, rolling it first if it hasn't been. A member with the experience for
+// more than one level goes through the screen once for each.
+
+#[derive(Resource, Debug)]
+pub struct LevelUpScreen {
+    pub member: Option<Entity>,
+    pub advancement: Option<Advancement>,
+    // Where the screen goes back to
+    pub return_to: GameState,
+}
+
+impl Default for LevelUpScreen {
+    fn default() -> Self {
+        Self {
+            member: None,
+            advancement: None,
+            return_to: GameState::InGame,
+        }
+    }
+}
+
+pub struct LevelUpPlugin;
+
+impl Plugin for LevelUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelUpScreen>().add_systems(Update, (
+            offer_level_up.run_if(in_state(GameState::InGame).or_else(in_state(GameState::Town))),
+            level_up_input.run_if(in_state(GameState::LevelUp)),
+        ));
+    }
+}
+
+fn offer_level_up(
+    characters: Query<(Entity, &Character, &Combatant)>,
+    ruleset: Res<Ruleset>,
+    compendium: Res<SpellCompendium>,
+    state: Res<State<GameState>>,
+    mut screen: ResMut<LevelUpScreen>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let due = looters(characters.iter()).into_iter().find(|(_, character)| character.level_up_due(&ruleset));
+    let Some((entity, character)) = due else {
+        return;
+    };
+    *screen = LevelUpScreen {
+        member: Some(entity),
+        advancement: Some(character.advancement(&ruleset, &compendium)),
+        return_to: state.get().clone(),
+    };
+    next_state.set(GameState::LevelUp);
+}
+
+fn level_up_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut screen: ResMut<LevelUpScreen>,
+    mut characters: Query<&mut Character>,
+    compendium: Res<SpellCompendium>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let (Some(member), Some(advancement)) = (screen.member, screen.advancement.as_mut()) else {
+        next_state.set(screen.return_to.clone());
+        return;
+    };
+    let choices = advancement.spell_choices.len();
+    if keyboard_input.just_pressed(KeyCode::R) {
+        advancement.roll(rng.as_mut());
+    } else if keyboard_input.just_pressed(KeyCode::Up) && choices > 0 {
+        advancement.chosen = (advancement.chosen + choices - 1) % choices;
+    } else if keyboard_input.just_pressed(KeyCode::Down) && choices > 0 {
+        advancement.chosen = (advancement.chosen + 1) % choices;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        if let Ok(mut character) = characters.get_mut(member) {
+            let learned = character.advance(advancement, &compendium, rng.as_mut());
+            let gained = advancement.hit_points().unwrap_or_default();
+            let mut text = format!("{} reaches level {} (+{} hp)", character.name, character.level, gained);
+            if !learned.is_empty() {
+                text = format!("{} and learns {}", text, learned.join(", "));
+            }
+            info!("{}", text);
+            log_events.send(CombatLogEvent(text));
+        }
+        screen.member = None;
+        screen.advancement = None;
+        next_state.set(screen.return_to.clone());
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+
+pub mod game_state;
+pub mod game_events;
+pub mod game_over;
+pub mod graveyard;
+pub mod abilities;
+pub mod ailments;
+pub mod automap;
+pub mod character;
+pub mod clock;
+pub mod combat;
+pub mod death;
+pub mod dialogue;
+pub mod doors;
+pub mod dungeon;
+pub mod encounters;
+pub mod experience;
+pub mod gossip;
+pub mod inn;
+pub mod inventory;
+pub mod level_up;
+pub mod light;
+pub mod loot;
+pub mod message_log;
+pub mod narration;
+pub mod provisions;
+pub mod quests;
+pub mod retainers;
+pub mod shop;
+pub mod spellbook;
+pub mod temple;
+pub mod town;
+pub mod traps;
+pub mod treasure;
+pub mod wandering;
+pub mod wilderness;
+pub mod world;
+pub mod ui;
+pub mod theme;
+pub mod sprites;
+pub mod glyphs;
+pub mod camera;
+pub mod animation;
+pub mod text_input;
+pub mod tooltip;
+pub mod audio;
+pub mod speech;
+pub mod ai_client;
+pub mod ai_backend;
+pub mod ai_queue;
+pub mod ai_health;
+pub mod replay;
+pub mod tasks;
+pub mod campaign;
+pub mod journal;
+pub mod console;
+pub mod data;
+pub mod mods;
+pub mod config;
+pub mod cli;
+pub mod save;
+pub mod scripts;
+
+// Rules modules that don't need the ECS, shared with the TUI and headless tools
+pub use old_school_core::{rng, ruleset, simulation};
+
+// Core game data structures
+#[derive(Resource, Clone, Debug)]
+pub struct GameConfig {
+    pub ai_service_url: String,
+    pub ai_backend: ai_backend::AiBackendKind,
+    pub ai_requests: ai_client::RequestPolicy,
+    pub ai_limits: ai_queue::RateLimits,
+    pub save_dir: String,
+    pub journal_dir: String,
+    pub journal_ai_recap: bool,
+    // Parties made from now on are ironman: a party that dies takes its saves with it
+    pub ironman: bool,
+    // Fixed dice seed for reproducible sessions; a random one is picked when unset
+    pub rng_seed: Option<u64>,
+    pub ruleset: ruleset::Ruleset,
+    // Window size at launch
+    pub resolution: (u32, u32),
+    pub keys: config::KeyBindings,
+    // Music and sound effect volumes, from 0 (off) to 1
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    // Read NPCs' replies and the narration aloud
+    pub speech: bool,
+    // Draw the dungeon as letters on a grid, the old roguelike way
+    pub text_mode: bool,
+    pub camera: camera::CameraMode,
+    // Save slot to load as soon as the game starts
+    pub load_slot: Option<String>,
+    // No window or renderer
+    pub headless: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            ai_service_url: "http://localhost:8000".to_string(),
+            ai_backend: ai_backend::AiBackendKind::default(),
+            ai_requests: ai_client::RequestPolicy::default(),
+            ai_limits: ai_queue::RateLimits::default(),
+            save_dir: "saves".to_string(),
+            journal_dir: journal::DEFAULT_JOURNAL_DIR.to_string(),
+            journal_ai_recap: true,
+            ironman: false,
+            rng_seed: None,
+            ruleset: ruleset::Ruleset::default(),
+            resolution: (1280, 720),
+            keys: config::KeyBindings::default(),
+            music_volume: 0.5,
+            sfx_volume: 0.7,
+            speech: false,
+            text_mode: false,
+            camera: camera::CameraMode::default(),
+            load_slot: None,
+            headless: false,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn rng(&self) -> rng::GameRng {
+        self.rng_seed.map(rng::GameRng::from_seed).unwrap_or_default()
+    }
+}
+
+// Game states
+#[derive(States, Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    CharacterCreation,
+    InGame,
+    Town,
+    Combat,
+    Inventory,
+    Shop,
+    Map,
+    Quests,
+    Spellbook,
+    Settings,
+    LoadGame,
+    GameOver,
+    Graveyard,
+    Loot,
+    LevelUp,
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_client::CurrentDungeon;
+use crate::character::Character;
+use crate::clock::{ClockSet, TurnsPassed};
+use crate::combat::Combatant;
+use crate::GameState;
+use crate::config::KeyBindings;
+
+pub use old_school_core::light::{darkness_attack_penalty, describe_room, LightKind, LitLight};
+
+// The party's light. One torch or lantern lights the whole party; T lights a torch and
+// Shift+T a lantern from whoever carries the fuel. Lights burn down as turns pass on the
+// game clock. In a dungeon with no light the party is in darkness.
+
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+pub struct PartyLight(pub Option<LitLight>);
+
+// Whether the party is in a dungeon without light. Kept up to date by `update_darkness`
+// for systems that only need the answer.
+#[derive(Resource, Default, Debug)]
+pub struct Darkness(pub bool);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LightEvent {
+    pub kind: LightKind,
+}
+
+pub struct LightPlugin;
+
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LightEvent>()
+            .init_resource::<PartyLight>()
+            .init_resource::<Darkness>()
+            .add_systems(Update, (
+                light_keys.run_if(in_state(GameState::InGame)),
+                handle_light_events,
+                burn_light.after(ClockSet),
+                update_darkness,
+            ).chain());
+    }
+}
+
+fn light_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut light_events: EventWriter<LightEvent>,
+) {
+    if !keyboard_input.just_pressed(keys.torch) {
+        return;
+    }
+    let kind = if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        LightKind::Lantern
+    } else {
+        LightKind::Torch
+    };
+    light_events.send(LightEvent { kind });
+}
+
+// The first party member who has the fuel lights it, replacing any light already burning
+fn handle_light_events(
+    mut light_events: EventReader<LightEvent>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    mut light: ResMut<PartyLight>,
+) {
+    for event in light_events.read() {
+        let mut reasons = Vec::new();
+        let lit = party
+            .iter_mut()
+            .filter(|(_, combatant)| combatant.is_player)
+            .find_map(|(mut character, _)| match character.light(event.kind) {
+                Ok(lit) => {
+                    info!("{} lights a {}", character.name, event.kind.name().to_lowercase());
+                    Some(lit)
+                }
+                Err(reason) => {
+                    reasons.push(reason);
+                    None
+                }
+            });
+        match lit {
+            Some(lit) => light.0 = Some(lit),
+            None => warn!("No light: {}", reasons.join("; ")),
+        }
+    }
+}
+
+fn burn_light(mut turns: EventReader<TurnsPassed>, mut light: ResMut<PartyLight>) {
+    let passed: u64 = turns.read().map(|turns| turns.0).sum();
+    if passed == 0 {
+        return;
+    }
+    let Some(lit) = light.0.as_mut() else {
+        return;
+    };
+    if lit.burn(u16::try_from(passed).unwrap_or(u16::MAX)) {
+        debug!("{} has {} turns left", lit.kind.name(), lit.turns_left);
+    } else {
+        info!("The {} gutters out", lit.kind.name().to_lowercase());
+        light.0 = None;
+    }
+}
+
+fn update_darkness(dungeon: Res<CurrentDungeon>, light: Res<PartyLight>, mut darkness: ResMut<Darkness>) {
+    if !dungeon.is_changed() && !light.is_changed() {
+        return;
+    }
+    let dark = dungeon.0.is_some() && light.0.is_none();
+    if darkness.0 != dark {
+        darkness.0 = dark;
+    }
+}
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use crate::character::{Character, Item};
+use crate::combat::{handle_combat_turn, CombatLogEvent, Combatant, FightWon};
+use crate::data::{ItemCatalog, TreasureTables};
+use crate::experience::RecoveredTreasure;
+use crate::rng::GameRng;
+use crate::GameState;
+
+pub use old_school_core::treasure::{roll_spoils, Spoils};
+
+// What the monsters leave. A won fight rolls their treasure (see roll_spoils) and, if
+// there's any, the loot screen opens over exploration for the party to share it out:
+// Up and Down pick the coins or an item, Left and Right who takes it, and Enter or ESC
+// hands everything over and goes back to exploring. The coins are shared among the
+// party unless they go to one member, and each item goes to the first of the party
+// until someone else is picked, or is left behind. What's taken, magic items aside,
+// counts towards the experience for the next fight won, as a chest's treasure does.
+
+#[derive(Resource, Default, Debug)]
+pub struct LootScreen {
+    pub gold: u32,
+    pub items: Vec<Item>,
+    // Who takes each line, the coins first and then each item: an index into the party,
+    // or None for coins shared out or an item left behind
+    pub takers: Vec<Option<usize>>,
+    pub selected: usize,
+}
+
+impl LootScreen {
+    // The coins and then each item
+    pub fn lines(&self) -> usize {
+        self.items.len() + 1
+    }
+}
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootScreen>().add_systems(Update, (
+            roll_loot.after(handle_combat_turn),
+            loot_input.run_if(in_state(GameState::Loot)),
+        ));
+    }
+}
+
+// The living party, in a steady order
+pub fn looters<'a>(
+    characters: impl Iterator<Item = (Entity, &'a Character, &'a Combatant)>,
+) -> Vec<(Entity, &'a Character)> {
+    let mut members: Vec<(Entity, &Character)> = characters
+        .filter(|(_, character, combatant)| combatant.is_player && character.is_alive())
+        .map(|(entity, character, _)| (entity, character))
+        .collect();
+    members.sort_by_key(|(entity, _)| *entity);
+    members
+}
+
+// Combat has sent the party back to exploring; the loot screen comes first if there's
+// anything to share
+fn roll_loot(
+    mut won: EventReader<FightWon>,
+    tables: Res<TreasureTables>,
+    catalog: Res<ItemCatalog>,
+    mut screen: ResMut<LootScreen>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for fight in won.read() {
+        let spoils = match roll_spoils(&fight.defeated, |treasure_type| tables.table(treasure_type), rng.as_mut()) {
+            Ok(spoils) => spoils,
+            Err(e) => {
+                warn!("Failed to roll the monsters' treasure: {}", e);
+                continue;
+            }
+        };
+        if spoils.is_empty() {
+            continue;
+        }
+        let mut found: Vec<String> = Vec::new();
+        if spoils.gold > 0 {
+            found.push(format!("{} gp", spoils.gold));
+        }
+        found.extend(spoils.items.iter().cloned());
+        log_events.send(CombatLogEvent(format!("The monsters leave {}", found.join(", "))));
+
+        *screen = LootScreen {
+            gold: spoils.gold,
+            takers: std::iter::once(None).chain(spoils.items.iter().map(|_| Some(0))).collect(),
+            items: spoils.items.iter().map(|name| catalog.treasure(name)).collect(),
+            selected: if spoils.gold > 0 { 0 } else { 1 },
+        };
+        next_state.set(GameState::Loot);
+    }
+}
+
+fn loot_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut screen: ResMut<LootScreen>,
+    mut party: Query<(Entity, &mut Character, &Combatant)>,
+    mut recovered: ResMut<RecoveredTreasure>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let members: Vec<Entity> = looters(party.iter()).into_iter().map(|(entity, _)| entity).collect();
+    if members.is_empty() {
+        next_state.set(GameState::InGame);
+        return;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        hand_out(&mut screen, &members, &mut party, &mut recovered, &mut log_events);
+        next_state.set(GameState::InGame);
+        return;
+    }
+
+    // The coins line is skipped over when there are none
+    let first = if screen.gold > 0 { 0 } else { 1 };
+    let lines = screen.lines();
+    if keyboard_input.just_pressed(KeyCode::Up) && screen.selected > first {
+        screen.selected -= 1;
+    } else if keyboard_input.just_pressed(KeyCode::Down) && screen.selected + 1 < lines {
+        screen.selected += 1;
+    }
+
+    // Every member in turn, then shared coins or an item left behind
+    let step: usize = if keyboard_input.just_pressed(KeyCode::Right) {
+        1
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        members.len()
+    } else {
+        return;
+    };
+    let selected = screen.selected.min(lines - 1);
+    let position = screen.takers[selected].map_or(members.len(), |member| member.min(members.len() - 1));
+    let next = (position + step) % (members.len() + 1);
+    screen.takers[selected] = (next < members.len()).then_some(next);
+}
+
+// The coins and items to whoever they went to, and the log told who took what
+fn hand_out(
+    screen: &mut LootScreen,
+    members: &[Entity],
+    party: &mut Query<(Entity, &mut Character, &Combatant)>,
+    recovered: &mut RecoveredTreasure,
+    log_events: &mut EventWriter<CombatLogEvent>,
+) {
+    let takers: Vec<Option<Entity>> =
+        screen.takers.iter().map(|taker| taker.map(|member| members[member.min(members.len() - 1)])).collect();
+    if screen.gold > 0 {
+        let shares: Vec<(Entity, u32)> = match takers[0] {
+            Some(member) => vec![(member, screen.gold)],
+            None => {
+                let share = screen.gold / members.len() as u32;
+                let remainder = screen.gold % members.len() as u32;
+                let extra = |index: usize| if index == 0 { remainder } else { 0 };
+                members.iter().enumerate().map(|(index, member)| (*member, share + extra(index))).collect()
+            }
+        };
+        for (member, gold) in shares {
+            if let Ok((_, mut character, _)) = party.get_mut(member) {
+                character.inventory.gold += gold;
+            }
+        }
+        recovered.0 += screen.gold;
+        let text = match takers[0].and_then(|member| party.get(member).ok()) {
+            Some((_, character, _)) => format!("{} takes the {} gp", character.name, screen.gold),
+            None => format!("The party shares out {} gp", screen.gold),
+        };
+        log_events.send(CombatLogEvent(text));
+    }
+
+    let items = std::mem::take(&mut screen.items);
+    for (index, item) in items.into_iter().enumerate() {
+        let Some((_, mut character, _)) = takers[index + 1].and_then(|member| party.get_mut(member).ok()) else {
+            log_events.send(CombatLogEvent(format!("The {} is left behind", item.name)));
+            continue;
+        };
+        if !item.is_magic() {
+            recovered.0 += item.value;
+        }
+        log_events.send(CombatLogEvent(format!("{} takes the {}", character.name, item.name)));
+        character.inventory.add_item(item);
+    }
+    screen.gold = 0;
+}
@@ -1,61 +1,182 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use clap::Parser;
+use std::time::Duration;
 
-mod game_state;
-mod character;
-mod combat;
-mod ui;
-mod ai_client;
-
-use game_state::GameStatePlugin;
-use character::CharacterPlugin;
-use combat::CombatPlugin;
-use ui::UIPlugin;
-use ai_client::AIClientPlugin;
+use old_school_ai_game::ai_client::AIClientPlugin;
+use old_school_ai_game::ai_health::AiHealthPlugin;
+use old_school_ai_game::abilities::AbilitiesPlugin;
+use old_school_ai_game::ailments::AilmentsPlugin;
+use old_school_ai_game::experience::ExperiencePlugin;
+use old_school_ai_game::animation::AnimationPlugin;
+use old_school_ai_game::audio::AudioPlugin;
+use old_school_ai_game::automap::AutomapPlugin;
+use old_school_ai_game::camera::CameraPlugin;
+use old_school_ai_game::campaign::CampaignPlugin;
+use old_school_ai_game::character::CharacterPlugin;
+use old_school_ai_game::clock::ClockPlugin;
+use old_school_ai_game::combat::CombatPlugin;
+use old_school_ai_game::cli::Cli;
+use old_school_ai_game::config::ConfigPlugin;
+use old_school_ai_game::console::{init_tracing, DebugConsolePlugin};
+use old_school_ai_game::data::{register_core_data_source, GameDataPlugin};
+use old_school_ai_game::death::DeathPlugin;
+use old_school_ai_game::dialogue::DialoguePlugin;
+use old_school_ai_game::doors::DoorsPlugin;
+use old_school_ai_game::dungeon::DungeonPlugin;
+use old_school_ai_game::encounters::EncountersPlugin;
+use old_school_ai_game::game_events::GameEventsPlugin;
+use old_school_ai_game::game_over::GameOverPlugin;
+use old_school_ai_game::graveyard::GraveyardPlugin;
+use old_school_ai_game::game_state::GameStatePlugin;
+use old_school_ai_game::glyphs::GlyphsPlugin;
+use old_school_ai_game::gossip::GossipPlugin;
+use old_school_ai_game::inn::InnPlugin;
+use old_school_ai_game::inventory::InventoryPlugin;
+use old_school_ai_game::level_up::LevelUpPlugin;
+use old_school_ai_game::light::LightPlugin;
+use old_school_ai_game::loot::LootPlugin;
+use old_school_ai_game::message_log::MessageLogPlugin;
+use old_school_ai_game::narration::NarrationPlugin;
+use old_school_ai_game::provisions::ProvisionsPlugin;
+use old_school_ai_game::quests::QuestsPlugin;
+use old_school_ai_game::shop::ShopPlugin;
+use old_school_ai_game::speech::SpeechPlugin;
+use old_school_ai_game::spellbook::SpellbookPlugin;
+use old_school_ai_game::sprites::SpritesPlugin;
+use old_school_ai_game::temple::TemplePlugin;
+use old_school_ai_game::text_input::TextInputPlugin;
+use old_school_ai_game::theme::ThemePlugin;
+use old_school_ai_game::tooltip::TooltipPlugin;
+use old_school_ai_game::town::TownPlugin;
+use old_school_ai_game::journal::JournalPlugin;
+use old_school_ai_game::replay::ReplayPlugin;
+use old_school_ai_game::retainers::RetainersPlugin;
+use old_school_ai_game::save::SaveGamePlugin;
+use old_school_ai_game::scripts::ScriptsPlugin;
+use old_school_ai_game::tasks::AsyncRuntimePlugin;
+use old_school_ai_game::traps::TrapsPlugin;
+use old_school_ai_game::treasure::TreasurePlugin;
+use old_school_ai_game::ui::UIPlugin;
+use old_school_ai_game::wandering::WanderingMonstersPlugin;
+use old_school_ai_game::wilderness::WildernessPlugin;
+use old_school_ai_game::world::WorldPlugin;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Old School AI RPG".into(),
-                resolution: (1280, 720).into(),
-                ..default()
-            }),
-            ..default()
-        }))
+    // Before logging starts, so --help and mistyped flags print plainly
+    let cli = Cli::parse();
+    let console_log = init_tracing();
+    let config = cli.game_config();
+
+    let mut app = App::new();
+    register_core_data_source(&mut app);
+    if config.headless {
+        // No window and no GPU; the schedule runs on a timer instead
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings { backends: None, ..default() }.into(),
+                })
+                .disable::<WinitPlugin>()
+                .disable::<LogPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / 60.0)));
+    } else {
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "Old School AI RPG".into(),
+                        resolution: (config.resolution.0 as f32, config.resolution.1 as f32).into(),
+                        ..default()
+                    }),
+                    ..default()
+                })
+                // Logging is set up by init_tracing so the debug console can capture it
+                .disable::<LogPlugin>(),
+        );
+    }
+
+    app.insert_resource(config.clone())
+        .insert_resource(config.rng())
+        .insert_resource(config.ruleset.clone())
+        .add_plugins(DebugConsolePlugin { log: console_log })
+        .add_plugins(ConfigPlugin { path: cli.config.clone() })
         .add_plugins((
             GameStatePlugin,
+            GameDataPlugin,
             CharacterPlugin,
+            ClockPlugin,
             CombatPlugin,
+            DungeonPlugin,
+            AutomapPlugin,
+            DoorsPlugin,
+            EncountersPlugin,
+            WanderingMonstersPlugin,
+            TrapsPlugin,
+            InventoryPlugin,
+            ShopPlugin,
+            LightPlugin,
+            ProvisionsPlugin,
+        ))
+        .add_plugins((
+            TreasurePlugin,
+            WorldPlugin,
+            WildernessPlugin,
+            TownPlugin,
+            DialoguePlugin,
+            InnPlugin,
+            GossipPlugin,
+            TemplePlugin,
+            RetainersPlugin,
+            QuestsPlugin,
+            NarrationPlugin,
             UIPlugin,
             AIClientPlugin,
+            AsyncRuntimePlugin,
+            SaveGamePlugin,
         ))
+        .add_plugins(AiHealthPlugin)
+        .add_plugins(TextInputPlugin)
+        .add_plugins(SpellbookPlugin)
+        .add_plugins(MessageLogPlugin)
+        .add_plugins(ThemePlugin)
+        .add_plugins(SpritesPlugin)
+        .add_plugins(GlyphsPlugin)
+        .add_plugins(CameraPlugin)
+        .add_plugins(AnimationPlugin)
+        .add_plugins(TooltipPlugin)
+        .add_plugins(AudioPlugin)
+        .add_plugins(SpeechPlugin)
+        .add_plugins(GameEventsPlugin)
+        .add_plugins(GameOverPlugin)
+        .add_plugins(GraveyardPlugin)
+        .add_plugins(DeathPlugin)
+        .add_plugins(AilmentsPlugin)
+        .add_plugins(AbilitiesPlugin)
+        .add_plugins(ExperiencePlugin)
+        .add_plugins(LootPlugin)
+        .add_plugins(LevelUpPlugin)
+        .add_plugins(ScriptsPlugin)
+        .add_plugins(ReplayPlugin {
+            mode: cli.replay_mode(),
+        })
+        .add_plugins(CampaignPlugin {
+            config: cli.campaign(&config.ai_service_url),
+        })
+        .add_plugins(JournalPlugin {
+            directory: config.journal_dir.clone().into(),
+            ai_recap: config.journal_ai_recap,
+        })
         .run();
 }
-
-// Core game data structures
-#[derive(Resource, Clone, Debug)]
-pub struct GameConfig {
-    pub ai_service_url: String,
-    pub save_file_path: String,
-}
-
-impl Default for GameConfig {
-    fn default() -> Self {
-        Self {
-            ai_service_url: "http://localhost:8000".to_string(),
-            save_file_path: "save_game.json".to_string(),
-        }
-    }
-}
-
-// Game states
-#[derive(States, Debug, Clone, Eq, PartialEq, Hash)]
-pub enum GameState {
-    MainMenu,
-    CharacterCreation,
-    InGame,
-    Combat,
-    Inventory,
-    Settings,
-} 
\ No newline at end of file
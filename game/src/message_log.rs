@@ -0,0 +1,219 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::ai_client::{NPCConversationEvent, NPCConversationResponseEvent, NPCRoster};
+use crate::clock::GameClock;
+use crate::combat::CombatLogEvent;
+use crate::doors::DoorMessage;
+use crate::narration::Narrated;
+use crate::quests::QuestMessage;
+use crate::retainers::RetainerMessage;
+use crate::save::GameLoaded;
+use crate::town::TownMessage;
+use crate::traps::TrapMessage;
+use crate::treasure::TreasureMessage;
+use crate::wilderness::WildernessMessage;
+
+// The message log: what happened in fights, while exploring and in conversation, each
+// entry stamped with the time on the game clock. It runs for the whole session and is
+// kept in saves, up to MAX_ENTRIES. The panels showing it (see ui.rs) scroll back with
+// the mouse wheel or Page Up and forward with Page Down; while scrolled back, new
+// entries don't move what's on screen.
+//
+// Anything can write to it with a LogMessage. Combat lines (CombatLogEvent), the
+// Dungeon Master's narration, conversations and the one-line messages the exploration
+// and town screens show are picked up without that.
+
+pub const MAX_ENTRIES: usize = 500;
+
+// Lines a turn of the mouse wheel scrolls, and Page Up or Page Down
+const WHEEL_LINES: usize = 3;
+const PAGE_LINES: isize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageCategory {
+    Combat,
+    Exploration,
+    Dialogue,
+    // Quests, hirelings, saving and loading
+    Notice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub category: MessageCategory,
+    pub day: u64,
+    pub hour: u64,
+    pub minute: u64,
+    pub text: String,
+}
+
+impl LogEntry {
+    pub fn stamp(&self) -> String {
+        format!("Day {} {:02}:{:02}", self.day, self.hour, self.minute)
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct MessageLog {
+    pub entries: VecDeque<LogEntry>,
+    // How many entries back from the newest the panels are scrolled
+    pub scroll: usize,
+}
+
+impl MessageLog {
+    pub fn push(&mut self, category: MessageCategory, clock: &GameClock, text: impl Into<String>) {
+        self.entries.push_back(LogEntry {
+            category,
+            day: clock.day(),
+            hour: clock.hour(),
+            minute: clock.minute(),
+            text: text.into(),
+        });
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.scroll = self.scroll.min(self.entries.len().saturating_sub(1));
+    }
+
+    // Positive is back toward older entries
+    pub fn scroll_by(&mut self, lines: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+    }
+
+    // The `rows` entries a panel of that height shows, oldest first
+    pub fn visible(&self, rows: usize) -> impl Iterator<Item = &LogEntry> {
+        let end = self.entries.len().saturating_sub(self.scroll);
+        self.entries.range(end.saturating_sub(rows)..end)
+    }
+
+    pub fn restore(&mut self, entries: Vec<LogEntry>) {
+        self.entries = entries.into();
+        self.scroll = 0;
+    }
+}
+
+// A line for the log from anywhere in the game
+#[derive(Event, Clone, Debug)]
+pub struct LogMessage {
+    pub category: MessageCategory,
+    pub text: String,
+}
+
+pub struct MessageLogPlugin;
+
+impl Plugin for MessageLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MessageLog>()
+            .add_event::<LogMessage>()
+            .add_systems(Update, (
+                record_messages,
+                record_conversations,
+                record_screen_messages,
+                scroll_log,
+            ).chain());
+    }
+}
+
+fn record_messages(
+    clock: Res<GameClock>,
+    mut log: ResMut<MessageLog>,
+    mut messages: EventReader<LogMessage>,
+    mut combat: EventReader<CombatLogEvent>,
+    mut narrated: EventReader<Narrated>,
+    mut loaded: EventReader<GameLoaded>,
+) {
+    for message in messages.read() {
+        log.push(message.category, &clock, message.text.clone());
+    }
+    for CombatLogEvent(line) in combat.read() {
+        log.push(MessageCategory::Combat, &clock, line.clone());
+    }
+    for Narrated(text) in narrated.read() {
+        log.push(MessageCategory::Exploration, &clock, text.clone());
+    }
+    for game in loaded.read() {
+        log.push(MessageCategory::Notice, &clock, format!("Loaded {}", game.slot));
+    }
+}
+
+fn record_conversations(
+    clock: Res<GameClock>,
+    roster: Res<NPCRoster>,
+    mut log: ResMut<MessageLog>,
+    mut said: EventReader<NPCConversationEvent>,
+    mut replies: EventReader<NPCConversationResponseEvent>,
+) {
+    let name = |npc_id: &str| roster.npcs.get(npc_id).map_or_else(|| npc_id.to_string(), |npc| npc.name.clone());
+    for event in said.read() {
+        let text = format!("To {}: \"{}\"", name(&event.npc_id), event.player_message);
+        log.push(MessageCategory::Dialogue, &clock, text);
+    }
+    for reply in replies.read() {
+        let text = format!("{}: \"{}\"", name(&reply.npc_id), reply.response.npc_response);
+        log.push(MessageCategory::Dialogue, &clock, text);
+    }
+}
+
+// The one-line messages the exploration and town screens show. Only one that differs
+// from the last from the same place is logged, since some screens set theirs again
+// each time something changes.
+#[allow(clippy::too_many_arguments)]
+fn record_screen_messages(
+    clock: Res<GameClock>,
+    mut log: ResMut<MessageLog>,
+    door: Res<DoorMessage>,
+    trap: Res<TrapMessage>,
+    treasure: Res<TreasureMessage>,
+    wilderness: Res<WildernessMessage>,
+    town: Res<TownMessage>,
+    quest: Res<QuestMessage>,
+    retainer: Res<RetainerMessage>,
+    mut last: Local<[Option<String>; 7]>,
+) {
+    let sources = [
+        (door.is_changed(), &door.0, MessageCategory::Exploration),
+        (trap.is_changed(), &trap.0, MessageCategory::Exploration),
+        (treasure.is_changed(), &treasure.0, MessageCategory::Exploration),
+        (wilderness.is_changed(), &wilderness.0, MessageCategory::Exploration),
+        (town.is_changed(), &town.0, MessageCategory::Notice),
+        (quest.is_changed(), &quest.0, MessageCategory::Notice),
+        (retainer.is_changed(), &retainer.0, MessageCategory::Notice),
+    ];
+    for ((changed, message, category), last) in sources.into_iter().zip(last.iter_mut()) {
+        if !changed || message == last {
+            continue;
+        }
+        last.clone_from(message);
+        if let Some(message) = message {
+            log.push(category, &clock, message.clone());
+        }
+    }
+}
+
+fn scroll_log(keyboard_input: Res<Input<KeyCode>>, mut wheel: EventReader<MouseWheel>, mut log: ResMut<MessageLog>) {
+    let mut lines: isize = 0;
+    for event in wheel.read() {
+        let notches = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Touchpads scroll in pixels; call 40 of them a notch
+            MouseScrollUnit::Pixel => event.y / 40.0,
+        };
+        lines += (notches * WHEEL_LINES as f32).round() as isize;
+    }
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        lines += PAGE_LINES;
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        lines -= PAGE_LINES;
+    }
+    if lines != 0 {
+        log.scroll_by(lines);
+    }
+}
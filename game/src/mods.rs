@@ -0,0 +1,169 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// Mods: packs of content that add to or change the game's own, each a folder under
+// assets/mods. A pack can have any of
+//   data/     items, bestiary, spells, treasure and wilderness files, named like the
+//             game's own (goblins.bestiary.json, relics.items.json)
+//   prompts/  prompts, replacing the game's of the same name
+//   scripts/  scripts (see scripts.rs), replacing the game's of the same name
+//   mod.json  {"name", "version", "description", "load_after": [other packs' folders]}
+// The folder is scanned when the game starts. Packs load in order of their folder names,
+// except that a pack loads after any it names in `load_after`; the game's own content
+// goes first and each pack goes over what came before it, so a monster, item, spell or
+// table with the same id as an earlier one replaces it. The Settings screen lists the
+// packs in the order they loaded, with anything wrong with them.
+
+pub const MODS_DIR: &str = "mods";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+    load_after: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModPack {
+    // The folder's name
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub load_after: Vec<String>,
+    // The asset paths of the files under data/, in name order
+    pub data_files: Vec<String>,
+    // Which of the prompts and scripts folders it has
+    pub folders: Vec<&'static str>,
+}
+
+impl ModPack {
+    // Its prompts and scripts folders, as asset paths
+    pub fn folder_paths(&self) -> Vec<String> {
+        self.folders.iter().map(|folder| format!("{}/{}/{}", MODS_DIR, self.id, folder)).collect()
+    }
+}
+
+const FOLDERS: [&str; 2] = ["prompts", "scripts"];
+
+// The packs in load order
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ModList {
+    pub packs: Vec<ModPack>,
+    pub problems: Vec<String>,
+}
+
+impl ModList {
+    // Every pack under assets/mods, in load order
+    pub fn scan() -> ModList {
+        let dir = FileAssetReader::get_base_path().join("assets").join(MODS_DIR);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return ModList::default();
+        };
+        let mut problems = Vec::new();
+        let mut packs: Vec<ModPack> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| read_pack(&entry.path(), &mut problems))
+            .collect();
+        packs.sort_by(|a, b| a.id.cmp(&b.id));
+        let packs = load_order(packs, &mut problems);
+        for pack in &packs {
+            let folders: String = pack.folders.iter().map(|folder| format!(" and {}", folder)).collect();
+            info!("Mod {}: {} data files{}", pack.name, pack.data_files.len(), folders);
+        }
+        for problem in &problems {
+            warn!("{}", problem);
+        }
+        ModList { packs, problems }
+    }
+
+    // Where an asset sits in the layers of content: 0 for the game's own, or the pack's
+    // place in the load order counting from 1
+    pub fn layer(&self, path: &Path) -> usize {
+        let mut parts = path.components().map(|part| part.as_os_str().to_string_lossy());
+        if parts.next().as_deref() != Some(MODS_DIR) {
+            return 0;
+        }
+        let Some(id) = parts.next() else {
+            return 0;
+        };
+        self.packs.iter().position(|pack| pack.id == id).map_or(0, |index| index + 1)
+    }
+
+    // For the Settings screen
+    pub fn describe(&self) -> String {
+        let mut lines = Vec::new();
+        if self.packs.is_empty() {
+            lines.push(format!("Mods: none (put packs in assets/{}/)", MODS_DIR));
+        } else {
+            lines.push("Mods, in load order:".to_string());
+        }
+        for (index, pack) in self.packs.iter().enumerate() {
+            let version = pack.version.as_ref().map(|version| format!(" {}", version)).unwrap_or_default();
+            let mut line = format!("  {}. {}{}", index + 1, pack.name, version);
+            if let Some(description) = &pack.description {
+                line = format!("{} - {}", line, description);
+            }
+            lines.push(line);
+        }
+        lines.extend(self.problems.iter().map(|problem| format!("  ! {}", problem)));
+        lines.join("\n")
+    }
+}
+
+fn read_pack(path: &Path, problems: &mut Vec<String>) -> Option<ModPack> {
+    let id = path.file_name()?.to_string_lossy().to_string();
+    let manifest = match fs::read_to_string(path.join("mod.json")) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|error| {
+            problems.push(format!("{}/mod.json can't be read: {}", id, error));
+            Manifest::default()
+        }),
+        Err(_) => Manifest::default(),
+    };
+    let mut data_files: Vec<String> = fs::read_dir(path.join("data"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| format!("{}/{}/data/{}", MODS_DIR, id, entry.file_name().to_string_lossy()))
+        .collect();
+    data_files.sort();
+    Some(ModPack {
+        name: manifest.name.unwrap_or_else(|| id.clone()),
+        version: manifest.version,
+        description: manifest.description,
+        load_after: manifest.load_after,
+        data_files,
+        folders: FOLDERS.into_iter().filter(|folder| path.join(folder).is_dir()).collect(),
+        id,
+    })
+}
+
+// Packs in name order, each held back until those it loads after are in. Naming a
+// pack that isn't there is ignored; packs waiting on each other go in name order.
+fn load_order(mut waiting: Vec<ModPack>, problems: &mut Vec<String>) -> Vec<ModPack> {
+    let ids: Vec<String> = waiting.iter().map(|pack| pack.id.clone()).collect();
+    for pack in &waiting {
+        for after in pack.load_after.iter().filter(|after| !ids.contains(after)) {
+            problems.push(format!("{} loads after {}, which isn't installed", pack.id, after));
+        }
+    }
+
+    let mut ordered: Vec<ModPack> = Vec::new();
+    while !waiting.is_empty() {
+        let loaded = |id: &String| !ids.contains(id) || ordered.iter().any(|done| done.id == *id);
+        let ready = waiting.iter().position(|pack| pack.load_after.iter().all(loaded));
+        let index = ready.unwrap_or_else(|| {
+            problems.push(format!("{} and the packs it loads after wait on each other", waiting[0].id));
+            0
+        });
+        ordered.push(waiting.remove(index));
+    }
+    ordered
+}
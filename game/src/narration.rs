@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy::tasks::Task;
+use std::collections::VecDeque;
+
+use crate::ai_client::{AIClient, CurrentDungeon, NarrationMoment, NarrationRequest};
+use crate::character::Character;
+use crate::combat::{CharacterFell, CombatLogEvent, Combatant};
+use crate::dungeon::{CurrentRoom, RoomEntered};
+use crate::tasks::{poll_task, AsyncRuntime};
+use crate::treasure::TreasureMessage;
+
+// The Dungeon Master's voice. The first time the party goes into a room, whenever it
+// takes treasure and when a monster falls to a killing blow, a few lines of boxed text
+// describe the moment. The AI service writes them from the room, the state of the
+// party and what it has just been doing; if the service can't, a line from the
+// templates here stands in. Only the latest moment is narrated, so a quick run of them
+// doesn't queue up stale text.
+
+// How many of the party's latest doings go along with a request
+const RECENT_ACTIONS: usize = 6;
+
+const ENTER_ROOM: &[&str] = &[
+    "You step into {}. Dust stirs at your feet, and the silence feels watchful.",
+    "{} opens before you. Somewhere beyond the light, something drips.",
+    "The air grows colder as you enter {}. Old stone, old secrets.",
+];
+
+const TREASURE: &[&str] = &[
+    "{}. Coin and trinket catch the light, the reward of the bold.",
+    "{}. For a moment the dungeon seems a little less grim.",
+    "{}. Greedy hands make quick work of it.",
+];
+
+const KILLING_BLOW: &[&str] = &[
+    "With a final blow, {} crumples to the floor and moves no more.",
+    "{} staggers, and falls. The echoes of the fight fade into the dark.",
+    "Steel finds its mark; {} is slain.",
+];
+
+// Boxed text to show
+#[derive(Event, Clone, Debug)]
+pub struct Narrated(pub String);
+
+type NarrationTask = Task<Option<Result<String, String>>>;
+
+#[derive(Resource, Default)]
+pub struct Narrator {
+    // What the party has been doing lately, oldest first
+    recent: VecDeque<String>,
+    // Rooms of the current dungeon already described
+    narrated_rooms: Vec<u32>,
+    // The request in flight, with the template line to fall back on
+    pending: Option<(NarrationTask, String)>,
+}
+
+pub struct NarrationPlugin;
+
+impl Plugin for NarrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Narrator>()
+            .add_event::<Narrated>()
+            .add_systems(Update, (
+                note_actions,
+                forget_rooms,
+                narrate_moments,
+                receive_narration,
+            ).chain());
+    }
+}
+
+// The local stand-in for a moment. The line is picked from the subject rather than
+// the game's dice, so narration never changes a replay.
+pub fn template(moment: NarrationMoment, subject: &str) -> String {
+    let lines = match moment {
+        NarrationMoment::EnterRoom => ENTER_ROOM,
+        NarrationMoment::Treasure => TREASURE,
+        NarrationMoment::KillingBlow => KILLING_BLOW,
+    };
+    let pick = subject.bytes().map(usize::from).sum::<usize>() % lines.len();
+    let line = lines[pick].replace("{}", subject);
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => line,
+    }
+}
+
+fn note_actions(mut log: EventReader<CombatLogEvent>, mut narrator: ResMut<Narrator>) {
+    for CombatLogEvent(line) in log.read() {
+        narrator.recent.push_back(line.clone());
+        if narrator.recent.len() > RECENT_ACTIONS {
+            narrator.recent.pop_front();
+        }
+    }
+}
+
+fn forget_rooms(dungeon: Res<CurrentDungeon>, mut narrator: ResMut<Narrator>) {
+    if dungeon.is_changed() {
+        narrator.narrated_rooms.clear();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn narrate_moments(
+    mut entered: EventReader<RoomEntered>,
+    mut fallen: EventReader<CharacterFell>,
+    treasure: Res<TreasureMessage>,
+    dungeon: Res<CurrentDungeon>,
+    current_room: Res<CurrentRoom>,
+    party: Query<(&Character, &Combatant)>,
+    client: Res<AIClient>,
+    runtime: Res<AsyncRuntime>,
+    mut narrator: ResMut<Narrator>,
+) {
+    let rooms = dungeon.0.as_ref().map(|data| data.rooms.as_slice()).unwrap_or_default();
+    let mut moment = None;
+    for RoomEntered { room_id } in entered.read() {
+        if narrator.narrated_rooms.contains(room_id) {
+            continue;
+        }
+        if let Some(room) = rooms.iter().find(|room| room.id == *room_id) {
+            narrator.narrated_rooms.push(*room_id);
+            moment = Some((NarrationMoment::EnterRoom, room.name.clone()));
+        }
+    }
+    if treasure.is_changed() {
+        if let Some(taken) = treasure.0.as_ref().filter(|taken| !taken.is_empty()) {
+            moment = Some((NarrationMoment::Treasure, taken.clone()));
+        }
+    }
+    for fell in fallen.read().filter(|fell| !fell.is_player) {
+        moment = Some((NarrationMoment::KillingBlow, fell.name.clone()));
+    }
+    let Some((moment, subject)) = moment else {
+        return;
+    };
+
+    let request = NarrationRequest {
+        moment,
+        subject: subject.clone(),
+        room: current_room
+            .0
+            .and_then(|room_id| rooms.iter().find(|room| room.id == room_id))
+            .map(|room| format!("{}: {}", room.name, room.description)),
+        party: party
+            .iter()
+            .filter(|(_, combatant)| combatant.is_player)
+            .map(|(character, _)| {
+                format!(
+                    "{}, level {} {}, {}/{} hp",
+                    character.name,
+                    character.level,
+                    character.class.name(),
+                    character.hit_points.current,
+                    character.hit_points.maximum
+                )
+            })
+            .collect(),
+        recent_actions: narrator.recent.iter().cloned().collect(),
+    };
+    let client = client.clone();
+    // A request still in flight is dropped, and with it the moment it was for
+    narrator.pending = Some((
+        runtime.spawn(async move {
+            client.narrate(request).await.map(|response| response.text).map_err(|e| e.to_string())
+        }),
+        template(moment, &subject),
+    ));
+}
+
+fn receive_narration(mut narrator: ResMut<Narrator>, mut narrated: EventWriter<Narrated>) {
+    let Some((task, _)) = narrator.pending.as_mut() else {
+        return;
+    };
+    let Some(result) = poll_task(task) else {
+        return;
+    };
+    let Some((_, fallback)) = narrator.pending.take() else {
+        return;
+    };
+    let text = match result {
+        Some(Ok(text)) if !text.trim().is_empty() => text.trim().to_string(),
+        Some(Ok(_)) => fallback,
+        Some(Err(e)) => {
+            debug!("Narration fell back to a template: {}", e);
+            fallback
+        }
+        None => fallback,
+    };
+    narrated.send(Narrated(text));
+}
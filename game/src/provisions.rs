@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::ai_client::CurrentDungeon;
+use crate::ailments::blocks_healing;
+use crate::character::Character;
+use crate::combat::Combatant;
+use crate::clock::{ClockSet, DayPassed};
+use crate::rng::GameRng;
+use crate::town::party_in_town;
+use crate::wilderness::Overland;
+
+pub use old_school_core::provisions::{DailyUpkeep, Provision, Sustenance};
+
+// Daily food and water. As each day on the game clock ends, every party member eats
+// and drinks from their pack (or in town, for free), heals if fed, and suffers if not.
+// The rules are in old_school_core::provisions.
+
+pub struct ProvisionsPlugin;
+
+impl Plugin for ProvisionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, daily_upkeep.after(ClockSet));
+    }
+}
+
+fn daily_upkeep(
+    mut days: EventReader<DayPassed>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    dungeon: Res<CurrentDungeon>,
+    overland: Res<Overland>,
+    mut rng: ResMut<GameRng>,
+) {
+    for _ in days.read() {
+        let in_town = party_in_town(&dungeon, &overland);
+        for (mut character, combatant) in party.iter_mut() {
+            if !combatant.is_player || !character.is_alive() {
+                continue;
+            }
+            let upkeep = character.daily_upkeep(in_town, !blocks_healing(&combatant.status_effects), rng.as_mut());
+            if !upkeep.ate {
+                warn!("{} goes hungry ({} days)", character.name, character.sustenance.days_without_food);
+            }
+            if !upkeep.drank {
+                warn!("{} goes thirsty ({} days)", character.name, character.sustenance.days_without_water);
+            }
+            if upkeep.strength_change != 0 || upkeep.constitution_change != 0 {
+                info!(
+                    "{}: Strength {:+}, Constitution {:+}",
+                    character.name, upkeep.strength_change, upkeep.constitution_change
+                );
+            }
+            if upkeep.healed > 0 {
+                info!("{} recovers {} hit points", character.name, upkeep.healed);
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{NPCConversationResponseEvent, NPCRoster, QuestLog};
+use crate::character::Character;
+use crate::clock::{ClockSet, DayPassed, GameClock};
+use crate::combat::Combatant;
+use crate::config::KeyBindings;
+use crate::data::ItemCatalog;
+use crate::dialogue::Dialogue;
+use crate::game_events::GameEvent;
+use crate::inventory::EquipmentSlot;
+use crate::GameState;
+
+pub use old_school_core::quest::{Deed, Objective, Quest, QuestOffer, QuestStatus, Resolution, DEADLINE_WARNING_DAYS};
+
+// The quest journal (see old_school_core::quest for the log itself). Q opens it from
+// the dungeon or town. It lists the quests NPCs have offered, then the party's active,
+// completed and failed quests with their objectives; Up and Down pick one, Y accepts
+// an offer and N declines it, and Q or ESC goes back. An active quest can be ended
+// short of its objectives: G negotiates a settlement, B betrays whoever gave it and A
+// abandons it (see Resolution for what each is worth).
+//
+// Objectives tick off as the party goes about its business: monsters falling in a
+// fight, what the party carries, the sites and rooms it goes into, coming back to town
+// and whoever it talks to. When a quest's objectives are all done its reward is paid:
+// the experience shared among the living party, the gold shared as treasure is and
+// any items given to the first of the party, and the party's reputation changed.
+//
+// Quests with a time limit are due that many days after the party takes them on. Each
+// morning the party is reminded of quests due within DEADLINE_WARNING_DAYS, and those
+// whose day has gone fail: the party loses reputation and whoever gave the quest
+// remembers being let down.
+//
+// However a quest ends, whoever gave it remembers how, and may think less of the
+// party for it; what the party has made of its latest quests is among the news NPCs
+// in town have heard when the party talks to them.
+
+#[derive(Resource, Debug)]
+pub struct QuestJournal {
+    // Index into the offers followed by the party's quests
+    pub selected: usize,
+    // Where the journal goes back to
+    pub return_to: GameState,
+}
+
+// A line of the journal: an offer or one of the party's quests, by index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalLine {
+    Offer(usize),
+    Quest(usize),
+}
+
+// The last objective done or quest answered, for the exploration, town and journal
+// screens
+#[derive(Resource, Default, Debug)]
+pub struct QuestMessage(pub Option<String>);
+
+// A quest has come to an end, by index in the log
+#[derive(Event, Clone, Copy, Debug)]
+pub struct QuestResolved(pub usize);
+
+impl Default for QuestJournal {
+    fn default() -> Self {
+        Self { selected: 0, return_to: GameState::InGame }
+    }
+}
+
+pub struct QuestsPlugin;
+
+impl Plugin for QuestsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuestJournal>()
+            .init_resource::<QuestMessage>()
+            .add_event::<QuestResolved>()
+            .add_systems(OnEnter(GameState::Town), quest_return_to_town)
+            .add_systems(Update, (
+                open_quest_journal.run_if(in_state(GameState::InGame).or_else(in_state(GameState::Town))),
+                quest_journal_input.run_if(in_state(GameState::Quests)),
+            ))
+            .add_systems(Update, (
+                quest_kills,
+                quest_items,
+                quest_visits,
+                quest_conversations,
+                grant_rewards,
+                tell_givers,
+            ).chain())
+            .add_systems(Update, quest_deadlines.after(ClockSet));
+    }
+}
+
+// The journal lists offers first, then quests in the order the party took them on
+pub fn journal_order(log: &QuestLog) -> Vec<JournalLine> {
+    let quests = [QuestStatus::Active, QuestStatus::Completed, QuestStatus::Failed]
+        .into_iter()
+        .flat_map(|status| {
+            log.quests
+                .iter()
+                .enumerate()
+                .filter(move |(_, quest)| quest.status == status)
+                .map(|(index, _)| JournalLine::Quest(index))
+        });
+    (0..log.offers.len()).map(JournalLine::Offer).chain(quests).collect()
+}
+
+fn open_quest_journal(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    state: Res<State<GameState>>,
+    dialogue: Res<Dialogue>,
+    mut journal: ResMut<QuestJournal>,
+    mut message: ResMut<QuestMessage>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(keys.quests) || dialogue.is_open() {
+        return;
+    }
+    journal.return_to = state.get().clone();
+    journal.selected = 0;
+    message.0 = None;
+    next_state.set(GameState::Quests);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn quest_journal_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    clock: Res<GameClock>,
+    mut journal: ResMut<QuestJournal>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+    mut events: EventWriter<GameEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.any_just_pressed([keys.quests, KeyCode::Escape]) {
+        next_state.set(journal.return_to.clone());
+        return;
+    }
+    let lines = journal_order(&log);
+    if lines.is_empty() {
+        return;
+    }
+    journal.selected = journal.selected.min(lines.len() - 1);
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        journal.selected = (journal.selected + lines.len() - 1) % lines.len();
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        journal.selected = (journal.selected + 1) % lines.len();
+    }
+    let offer = match lines[journal.selected] {
+        JournalLine::Offer(offer) => offer,
+        JournalLine::Quest(index) => {
+            let resolution = [
+                (KeyCode::G, Resolution::Negotiated),
+                (KeyCode::B, Resolution::Betrayed),
+                (KeyCode::A, Resolution::Abandoned),
+            ]
+            .into_iter()
+            .find(|(key, _)| keyboard_input.just_pressed(*key))
+            .map(|(_, resolution)| resolution);
+            let Some(resolution) = resolution.filter(|_| log.quests[index].status == QuestStatus::Active) else {
+                return;
+            };
+            if log.resolve(index, resolution).is_some() {
+                resolved.send(QuestResolved(index));
+            } else if resolution == Resolution::Negotiated {
+                message.0 = Some("The party has done nothing yet to bargain with".to_string());
+            }
+            return;
+        }
+    };
+    if keyboard_input.just_pressed(KeyCode::Y) {
+        if let Some(quest) = log.accept(offer, clock.day()) {
+            info!("The party takes on {}", quest.data.title);
+            message.0 = Some(format!("The party takes on \"{}\"", quest.data.title));
+            let giver = quest.giver.clone().unwrap_or_else(|| "its patron".to_string());
+            events.send(GameEvent::QuestAccepted { title: quest.data.title.clone(), giver });
+        }
+    } else if keyboard_input.just_pressed(KeyCode::N) {
+        if let Some(declined) = log.decline(offer) {
+            message.0 = Some(format!("The party turns down {}'s \"{}\"", declined.giver, declined.quest.title));
+        }
+    }
+}
+
+// Ticks off what the deed satisfies and says so; completed quests are paid by grant_rewards
+fn note_progress(log: &mut QuestLog, deed: Deed, message: &mut QuestMessage, resolved: &mut EventWriter<QuestResolved>) {
+    for progress in log.record(&deed) {
+        let quest = &log.quests[progress.quest];
+        let mut text = format!("{} (\"{}\")", quest.data.objectives[progress.objective], quest.data.title);
+        if progress.completes {
+            text = format!("Quest complete: {}", quest.data.title);
+            resolved.send(QuestResolved(progress.quest));
+        }
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
+
+fn quest_kills(
+    mut events: EventReader<GameEvent>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    for event in events.read() {
+        if let GameEvent::MonsterSlain { name } = event {
+            note_progress(&mut log, Deed::Killed(name), &mut message, &mut resolved);
+        }
+    }
+}
+
+// Whenever what the party carries changes, it's checked against retrieve objectives
+fn quest_items(
+    changed: Query<(), (Changed<Character>, With<Combatant>)>,
+    party: Query<(&Character, &Combatant)>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    let held: Vec<String> = party
+        .iter()
+        .filter(|(_, combatant)| combatant.is_player)
+        .flat_map(|(character, _)| {
+            let worn = EquipmentSlot::ALL.into_iter().filter_map(|slot| character.equipment.get(slot));
+            character.inventory.items.iter().chain(worn).map(|item| item.name.clone())
+        })
+        .collect();
+    note_progress(&mut log, Deed::Holding(&held), &mut message, &mut resolved);
+}
+
+fn quest_visits(
+    mut events: EventReader<GameEvent>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    for event in events.read() {
+        let place = match event {
+            GameEvent::SiteEntered { site } => site,
+            GameEvent::RoomEntered { room, .. } => room,
+            _ => continue,
+        };
+        note_progress(&mut log, Deed::Visited(place), &mut message, &mut resolved);
+    }
+}
+
+fn quest_return_to_town(
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    note_progress(&mut log, Deed::Visited("town"), &mut message, &mut resolved);
+}
+
+fn quest_conversations(
+    mut responses: EventReader<NPCConversationResponseEvent>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    for response in responses.read() {
+        note_progress(&mut log, Deed::TalkedTo(&response.response.updated_npc_data.name), &mut message, &mut resolved);
+    }
+}
+
+fn grant_rewards(
+    mut resolved: EventReader<QuestResolved>,
+    log: Res<QuestLog>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    catalog: Res<ItemCatalog>,
+    mut message: ResMut<QuestMessage>,
+) {
+    for QuestResolved(index) in resolved.read() {
+        let Some(quest) = log.quests.get(*index) else {
+            continue;
+        };
+        let Some(resolution) = quest.resolution else {
+            continue;
+        };
+        let payout = quest.payout();
+        let mut members: Vec<Mut<Character>> = party
+            .iter_mut()
+            .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+            .map(|(character, _)| character)
+            .collect();
+
+        let mut paid = Vec::new();
+        if !members.is_empty() {
+            let count = members.len() as u32;
+            for (order, member) in members.iter_mut().enumerate() {
+                let first = order == 0;
+                member.gain_experience(payout.experience / count + if first { payout.experience % count } else { 0 });
+                member.inventory.gold += payout.gold / count + if first { payout.gold % count } else { 0 };
+            }
+            for name in &payout.items {
+                members[0].inventory.add_item(catalog.treasure(name));
+            }
+            if resolution == Resolution::Fulfilled || payout.experience > 0 {
+                paid.push(format!("{} XP", payout.experience));
+            }
+            if resolution == Resolution::Fulfilled || payout.gold > 0 {
+                paid.push(format!("{} gp", payout.gold));
+            }
+            paid.extend(payout.items.iter().cloned());
+        }
+
+        let title = &quest.data.title;
+        let mut text = match resolution {
+            Resolution::Fulfilled => format!("Quest complete: {}", title),
+            Resolution::Negotiated => format!("The party strikes a deal over \"{}\"", title),
+            Resolution::Betrayed => {
+                format!("The party betrays {} over \"{}\"", quest.giver.as_deref().unwrap_or("its patron"), title)
+            }
+            Resolution::Abandoned => format!("The party gives up on \"{}\"", title),
+            Resolution::Expired => format!("Time has run out for \"{}\"", title),
+        };
+        if !paid.is_empty() {
+            text = format!("{}. The party earns {}", text, paid.join(", "));
+        }
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
+
+// Whoever gave a quest remembers how it ended, and a quest let down sours them
+fn tell_givers(mut resolved: EventReader<QuestResolved>, log: Res<QuestLog>, mut roster: ResMut<NPCRoster>) {
+    for QuestResolved(index) in resolved.read() {
+        let Some(quest) = log.quests.get(*index) else {
+            continue;
+        };
+        let (Some(giver), Some(outcome)) = (quest.giver.as_ref(), quest.outcome()) else {
+            continue;
+        };
+        let Some(npc) = roster.npcs.values_mut().find(|npc| npc.name == *giver) else {
+            continue;
+        };
+        let mood = match quest.resolution {
+            Some(Resolution::Betrayed) => Some("hostile"),
+            Some(Resolution::Abandoned | Resolution::Expired) => Some("disappointed"),
+            _ => None,
+        };
+        if let Some(mood) = mood {
+            npc.current_mood = mood.to_string();
+        }
+        npc.memory.push(outcome);
+    }
+}
+
+fn quest_deadlines(
+    mut days: EventReader<DayPassed>,
+    clock: Res<GameClock>,
+    mut log: ResMut<QuestLog>,
+    mut message: ResMut<QuestMessage>,
+    mut resolved: EventWriter<QuestResolved>,
+) {
+    if days.read().count() == 0 {
+        return;
+    }
+    let today = clock.day();
+    for (quest, left) in log.due_soon(today) {
+        let when = match left {
+            0 => "today".to_string(),
+            1 => "tomorrow".to_string(),
+            _ => format!("in {} days", left),
+        };
+        message.0 = Some(format!("\"{}\" is due {}", quest.data.title, when));
+    }
+    for index in log.expire(today) {
+        resolved.send(QuestResolved(index));
+    }
+}
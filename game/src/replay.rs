@@ -0,0 +1,439 @@
+use bevy::app::AppExit;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use bevy::window::{PrimaryWindow, ReceivedCharacter};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::ai_client::AIClient;
+use crate::rng::GameRng;
+
+// Session replays: the RNG seed, how long every frame took, every raw keyboard/mouse
+// button event, pointer move, scroll of the wheel and character typed (stamped with the
+// frame it arrived on) and every AI service response body with the frame it came back
+// on. Playing a replay back reseeds the GameRng, steps the clock by the recorded frame
+// times, feeds the recorded events in place of live input, holds the pointer where the
+// recording had it and answers AI requests from the recording no sooner than they were
+// answered live, so the session re-runs deterministically.
+
+// 2 added the pointer and the wheel; 3 the frame times and when each response came back
+pub const REPLAY_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub version: u32,
+    pub seed: u64,
+    // How long each frame took, in nanoseconds, starting with frame 1
+    #[serde(default)]
+    pub frame_times: Vec<u64>,
+    pub frames: Vec<RecordedFrame>,
+    pub ai_responses: Vec<RecordedAiResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub frame: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<RecordedKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mouse_buttons: Vec<RecordedMouseButton>,
+    // Text typed into fields, which arrives apart from the key presses
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub typed: String,
+    // Where the pointer ended up in the frame, in logical pixels from the window's top left
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<[f32; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wheel: Vec<RecordedWheel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedKey {
+    pub scan_code: u32,
+    pub key_code: Option<KeyCode>,
+    pub state: ButtonState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMouseButton {
+    pub button: MouseButton,
+    pub state: ButtonState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedWheel {
+    pub unit: MouseScrollUnit,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAiResponse {
+    // The frame the response came back on
+    #[serde(default)]
+    pub frame: u64,
+    pub endpoint: String,
+    pub body: String,
+}
+
+// Shared with the AIClient so responses can be captured or served from inside its
+// async request methods, which have no access to ECS resources
+#[derive(Debug, Clone)]
+pub struct AiTranscript {
+    inner: Arc<Mutex<TranscriptState>>,
+    // The frame the game is on, kept up to date by advance_replay_frame
+    frame: Arc<watch::Sender<u64>>,
+}
+
+#[derive(Debug, Default)]
+struct TranscriptState {
+    playback: bool,
+    pending: VecDeque<RecordedAiResponse>,
+    recorded: Vec<RecordedAiResponse>,
+}
+
+#[derive(Resource)]
+pub struct ReplaySession {
+    pub mode: ReplayMode,
+    pub file: ReplayFile,
+    pub transcript: AiTranscript,
+    frame: u64,
+    cursor: usize,
+    // Where playback last put the pointer, held there against the live mouse
+    pointer: Option<Vec2>,
+}
+
+pub struct ReplayPlugin {
+    pub mode: ReplayMode,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let session = match &self.mode {
+            ReplayMode::Off => return,
+            ReplayMode::Record(path) => {
+                // Keep the seed the session was started with (`--seed`, or the config's),
+                // starting the dice over from it so playback sees the same rolls
+                let rng = app
+                    .world
+                    .get_resource::<GameRng>()
+                    .map_or_else(GameRng::from_entropy, |rng| GameRng::from_seed(rng.seed()));
+                info!("Recording replay to {} (seed {})", path.display(), rng.seed());
+
+                let session = ReplaySession {
+                    mode: self.mode.clone(),
+                    file: ReplayFile {
+                        version: REPLAY_VERSION,
+                        seed: rng.seed(),
+                        ..default()
+                    },
+                    transcript: AiTranscript::recording(),
+                    frame: 0,
+                    cursor: 0,
+                    pointer: None,
+                };
+
+                app.insert_resource(rng)
+                    .add_systems(First, record_frame_time.after(TimeSystem))
+                    .add_systems(PreUpdate, record_input.before(InputSystem))
+                    .add_systems(Last, save_replay_on_exit);
+                session
+            }
+            ReplayMode::Playback(path) => {
+                let file = match ReplayFile::load(path) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        warn!("Could not load replay {}: {}. Starting a live session.", path.display(), e);
+                        return;
+                    }
+                };
+                if file.version > REPLAY_VERSION {
+                    warn!(
+                        "Replay {} was recorded by a newer build (version {}); playback may diverge",
+                        path.display(),
+                        file.version
+                    );
+                }
+                info!("Playing back replay {} (seed {})", path.display(), file.seed);
+
+                app.insert_resource(GameRng::from_seed(file.seed))
+                    .add_systems(First, step_replay_time.after(advance_replay_frame).before(TimeSystem))
+                    .add_systems(PreUpdate, inject_replay_input.before(InputSystem));
+
+                ReplaySession {
+                    mode: self.mode.clone(),
+                    transcript: AiTranscript::playback(file.ai_responses.clone()),
+                    file,
+                    frame: 0,
+                    cursor: 0,
+                    pointer: None,
+                }
+            }
+        };
+
+        app.insert_resource(session)
+            .add_systems(First, advance_replay_frame.before(TimeSystem));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(transcript) = app.world.get_resource::<ReplaySession>().map(|s| s.transcript.clone()) else {
+            return;
+        };
+        if let Some(mut client) = app.world.get_resource_mut::<AIClient>() {
+            client.set_transcript(transcript);
+        }
+    }
+}
+
+impl ReplayFile {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)
+    }
+}
+
+impl AiTranscript {
+    pub fn recording() -> Self {
+        Self::default()
+    }
+
+    pub fn playback(responses: Vec<RecordedAiResponse>) -> Self {
+        let transcript = Self::default();
+        {
+            let mut state = transcript.inner.lock().unwrap();
+            state.playback = true;
+            state.pending = responses.into();
+        }
+        transcript
+    }
+
+    // The next recorded response for this endpoint, in the order they were received,
+    // held back until the frame it came back on
+    pub async fn next_playback(&self, endpoint: &str) -> Option<String> {
+        let response = {
+            let mut state = self.inner.lock().unwrap();
+            if !state.playback {
+                return None;
+            }
+            let index = state.pending.iter().position(|r| r.endpoint == endpoint)?;
+            state.pending.remove(index)?
+        };
+        // Only fails once the session is gone, and then there's nothing left to wait for
+        let _ = self.frame.subscribe().wait_for(|frame| *frame >= response.frame).await;
+        Some(response.body)
+    }
+
+    pub fn record(&self, endpoint: &str, body: &str) {
+        let frame = *self.frame.borrow();
+        let mut state = self.inner.lock().unwrap();
+        if !state.playback {
+            state.recorded.push(RecordedAiResponse {
+                frame,
+                endpoint: endpoint.to_string(),
+                body: body.to_string(),
+            });
+        }
+    }
+
+    pub fn recorded(&self) -> Vec<RecordedAiResponse> {
+        self.inner.lock().unwrap().recorded.clone()
+    }
+
+    fn set_frame(&self, frame: u64) {
+        self.frame.send_replace(frame);
+    }
+}
+
+impl Default for AiTranscript {
+    fn default() -> Self {
+        Self {
+            inner: Arc::default(),
+            frame: Arc::new(watch::channel(0).0),
+        }
+    }
+}
+
+impl ReplaySession {
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn playback_finished(&self) -> bool {
+        self.cursor >= self.file.frames.len()
+    }
+}
+
+fn advance_replay_frame(mut session: ResMut<ReplaySession>) {
+    session.frame += 1;
+    session.transcript.set_frame(session.frame);
+}
+
+fn record_frame_time(mut session: ResMut<ReplaySession>, time: Res<Time<Real>>) {
+    session.file.frame_times.push(time.delta().as_nanos() as u64);
+}
+
+// Steps the clock by the recorded frame times, then lets it run free once they're used up
+fn step_replay_time(session: Res<ReplaySession>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    let recorded = session.file.frame_times.get(session.frame as usize - 1);
+    *strategy = match recorded {
+        Some(&nanos) => TimeUpdateStrategy::ManualDuration(Duration::from_nanos(nanos)),
+        None => TimeUpdateStrategy::Automatic,
+    };
+}
+
+fn record_input(
+    mut session: ResMut<ReplaySession>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut characters: EventReader<ReceivedCharacter>,
+) {
+    let keys: Vec<RecordedKey> = keyboard_events
+        .read()
+        .map(|event| RecordedKey {
+            scan_code: event.scan_code,
+            key_code: event.key_code,
+            state: event.state,
+        })
+        .collect();
+    let mouse_buttons: Vec<RecordedMouseButton> = mouse_events
+        .read()
+        .map(|event| RecordedMouseButton {
+            button: event.button,
+            state: event.state,
+        })
+        .collect();
+
+    let cursor = cursor_events.read().last().map(|event| event.position.to_array());
+    let wheel: Vec<RecordedWheel> = wheel_events
+        .read()
+        .map(|event| RecordedWheel {
+            unit: event.unit,
+            x: event.x,
+            y: event.y,
+        })
+        .collect();
+
+    let typed: String = characters.read().map(|event| event.char).collect();
+
+    if keys.is_empty() && mouse_buttons.is_empty() && typed.is_empty() && cursor.is_none() && wheel.is_empty() {
+        return;
+    }
+
+    let frame = session.frame;
+    session.file.frames.push(RecordedFrame {
+        frame,
+        keys,
+        mouse_buttons,
+        typed,
+        cursor,
+        wheel,
+    });
+}
+
+fn inject_replay_input(
+    mut session: ResMut<ReplaySession>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+    mut mouse_events: ResMut<Events<MouseButtonInput>>,
+    mut cursor_events: ResMut<Events<CursorMoved>>,
+    mut wheel_events: ResMut<Events<MouseWheel>>,
+    mut characters: ResMut<Events<ReceivedCharacter>>,
+    mut windows: Query<(Entity, &mut Window), With<PrimaryWindow>>,
+) {
+    if session.playback_finished() {
+        return;
+    }
+
+    // Live input is ignored while the recording plays
+    keyboard_events.clear();
+    mouse_events.clear();
+    cursor_events.clear();
+    wheel_events.clear();
+    characters.clear();
+
+    let mut primary = windows.get_single_mut().ok();
+    let window = primary.as_ref().map_or(Entity::PLACEHOLDER, |(entity, _)| *entity);
+    while let Some(frame) = session.file.frames.get(session.cursor) {
+        if frame.frame > session.frame {
+            break;
+        }
+
+        for key in &frame.keys {
+            keyboard_events.send(KeyboardInput {
+                scan_code: key.scan_code,
+                key_code: key.key_code,
+                state: key.state,
+                window,
+            });
+        }
+        for button in &frame.mouse_buttons {
+            mouse_events.send(MouseButtonInput {
+                button: button.button,
+                state: button.state,
+                window,
+            });
+        }
+        for char in frame.typed.chars() {
+            characters.send(ReceivedCharacter { window, char });
+        }
+        for wheel in &frame.wheel {
+            wheel_events.send(MouseWheel {
+                unit: wheel.unit,
+                x: wheel.x,
+                y: wheel.y,
+                window,
+            });
+        }
+        if let Some(position) = frame.cursor.map(Vec2::from_array) {
+            cursor_events.send(CursorMoved { window, position });
+            session.pointer = Some(position);
+        }
+        session.cursor += 1;
+    }
+    if let (Some(pointer), Some((_, window))) = (session.pointer, primary.as_mut()) {
+        if window.cursor_position() != Some(pointer) {
+            window.set_cursor_position(Some(pointer));
+        }
+    }
+
+    if session.playback_finished() {
+        info!("Replay finished at frame {}; handing control back to live input", session.frame);
+    }
+}
+
+fn save_replay_on_exit(mut exit_events: EventReader<AppExit>, session: Res<ReplaySession>) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+    let ReplayMode::Record(path) = &session.mode else {
+        return;
+    };
+
+    let mut file = session.file.clone();
+    file.ai_responses = session.transcript.recorded();
+    match file.save(path) {
+        Ok(()) => info!("Saved replay with {} input frames to {}", file.frames.len(), path.display()),
+        Err(e) => error!("Failed to save replay to {}: {}", path.display(), e),
+    }
+}
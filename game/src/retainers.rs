@@ -0,0 +1,377 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{
+    create_conversation_context, create_npc, AIRequestFailed, CurrentDungeon, HirelingRequest, HirelingsEvent,
+    HirelingsFoundEvent, NPCConversationEvent, NPCConversationResponseEvent, NPCRoster,
+};
+use crate::character::Character;
+use crate::clock::{ClockSet, DayPassed, GameClock};
+use crate::combat::Combatant;
+use crate::data::ItemCatalog;
+use crate::inn::pay_from_party;
+use crate::rng::GameRng;
+use crate::ruleset::Ruleset;
+use crate::town::{Location, Town, TownInputSet, TownMessage};
+use crate::world::EnterDungeon;
+use crate::GameState;
+
+pub use old_school_core::retainers::{
+    base_loyalty, loyalty_check, max_retainers, roll_candidates, Candidate, Disloyalty, Grievance, Retainer,
+    RetainerKind,
+};
+
+// Hiring retainers (see old_school_core::retainers for the rules). At the guild R asks
+// the AI service who is looking for work, falling back on the local tables when it
+// can't be reached, and 1-3 hire one of them: they join the party as a Combatant of its
+// own, outfitted from the item data, and the first day's wage is paid up front. Each
+// day after that the party pays every living retainer their wage.
+//
+// Going unpaid, or a fight in which one of the party fell or a retainer was left at
+// half their hit points or less, tests every retainer's loyalty. One who fails says
+// why through the AI service (or a line of their own if it can't be reached) and then
+// deserts, steals from the party and deserts, or refuses to go into a dungeon: they
+// leave the party on the spot if it's in one, or stay behind when it next goes in, and
+// rejoin once it's back in town.
+
+const CANDIDATES: usize = 3;
+
+// The last thing a retainer said or did, for the exploration and town screens
+#[derive(Resource, Default, Debug)]
+pub struct RetainerMessage(pub Option<String>);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LoyaltyCheck {
+    pub retainer: Entity,
+    pub grievance: Grievance,
+}
+
+// Retainers who have aired a grievance and not been heard back from, by NPC id, with
+// what they'll say instead if the AI service can't be reached
+#[derive(Resource, Default, Debug)]
+struct AwaitingGrievances(Vec<(String, String)>);
+
+// Who is looking for work at the guild
+#[derive(Resource, Default, Debug)]
+pub struct HiringHall {
+    pub candidates: Vec<Candidate>,
+    // The party's level, while the AI service is asked
+    awaiting: Option<u8>,
+}
+
+pub struct RetainersPlugin;
+
+impl Plugin for RetainersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HiringHall>()
+            .init_resource::<RetainerMessage>()
+            .init_resource::<AwaitingGrievances>()
+            .add_event::<LoyaltyCheck>()
+            .add_systems(OnExit(GameState::Combat), test_loyalty_after_fight)
+            .add_systems(OnEnter(GameState::Town), rejoin_in_town)
+            .add_systems(Update, (
+                hiring_input.before(TownInputSet).run_if(in_state(GameState::Town)),
+                receive_candidates,
+                stay_behind,
+            ).chain())
+            .add_systems(Update, (pay_wages, check_loyalty, hear_grievances).chain().after(ClockSet));
+    }
+}
+
+const HIRE_KEYS: [KeyCode; CANDIDATES] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+// The party's own members, who hire and pay, and how many retainers they can keep
+pub fn retainer_limit<'a>(employers: impl IntoIterator<Item = &'a Character>) -> usize {
+    employers
+        .into_iter()
+        .map(|character| character.stats.charisma)
+        .max()
+        .map_or(0, max_retainers)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hiring_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    town: Res<Town>,
+    mut hall: ResMut<HiringHall>,
+    mut party: Query<(&mut Character, &Combatant, Option<&Retainer>)>,
+    mut message: ResMut<TownMessage>,
+    mut requests: EventWriter<HirelingsEvent>,
+    catalog: Res<ItemCatalog>,
+    ruleset: Res<Ruleset>,
+    mut rng: ResMut<GameRng>,
+) {
+    if town.location != Some(Location::Guild) {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::R) {
+        if hall.awaiting.is_some() {
+            return;
+        }
+        let levels: Vec<u32> = party
+            .iter()
+            .filter(|(_, combatant, retainer)| combatant.is_player && retainer.is_none())
+            .map(|(character, _, _)| character.level as u32)
+            .collect();
+        let party_level = (levels.iter().sum::<u32>() / levels.len().max(1) as u32).max(1) as u8;
+        requests.send(HirelingsEvent {
+            request: HirelingRequest {
+                location: Location::Guild.name().to_string(),
+                party_level,
+                count: CANDIDATES as u8,
+            },
+        });
+        hall.awaiting = Some(party_level);
+        message.0 = Some("The guildmaster sends word round for anyone looking for work...".to_string());
+        return;
+    }
+
+    let Some(index) = HIRE_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) else {
+        return;
+    };
+    let Some(candidate) = hall.candidates.get(index).cloned() else {
+        return;
+    };
+
+    let hired = party.iter().filter(|(_, _, retainer)| retainer.is_some()).count();
+    let charisma = party
+        .iter()
+        .filter(|(_, combatant, retainer)| combatant.is_player && retainer.is_none())
+        .map(|(character, _, _)| character.stats.charisma)
+        .max()
+        .unwrap_or_default();
+    let limit = max_retainers(charisma);
+    if hired >= limit {
+        message.0 = Some(format!("The party can't keep more than {} retainers", limit));
+        return;
+    }
+    let mut employers: Vec<&mut Character> = party
+        .iter_mut()
+        .filter(|(_, combatant, retainer)| combatant.is_player && retainer.is_none())
+        .map(|(character, _, _)| character.into_inner())
+        .collect();
+    if let Err(reason) = pay_from_party(&mut employers, candidate.wage()) {
+        message.0 = Some(format!("{} wants a day's wage up front: {}", candidate.name, reason));
+        return;
+    }
+
+    let mut character = candidate.character(&ruleset, rng.as_mut());
+    for id in candidate.kind.kit() {
+        if let Some(item) = catalog.create(*id) {
+            character.inventory.add_item(item);
+        }
+    }
+    // Whatever can be worn or wielded goes on
+    for index in (0..character.inventory.items.len()).rev() {
+        let _ = character.equip_item(index);
+    }
+    info!("{} joins the party as a {}", character.name, candidate.kind.name());
+    commands.spawn((
+        character,
+        Combatant {
+            initiative: 0,
+            is_player: true,
+            actions_remaining: 1,
+            status_effects: Vec::new(),
+        },
+        candidate.retainer(charisma),
+    ));
+    hall.candidates.remove(index);
+    message.0 = Some(format!("{} joins the party", candidate.name));
+}
+
+fn receive_candidates(
+    mut found: EventReader<HirelingsFoundEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut hall: ResMut<HiringHall>,
+    mut message: ResMut<TownMessage>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in found.read() {
+        hall.awaiting = None;
+        hall.candidates = event.candidates.iter().take(CANDIDATES).cloned().collect();
+        message.0 = Some(format!("{} come looking for work", hall.candidates.len()));
+    }
+    for failure in failures.read() {
+        if failure.endpoint != "hirelings" {
+            continue;
+        }
+        let Some(party_level) = hall.awaiting.take() else {
+            continue;
+        };
+        hall.candidates = roll_candidates(CANDIDATES, party_level, rng.as_mut());
+        message.0 = Some(format!("{} come looking for work", hall.candidates.len()));
+    }
+}
+
+fn pay_wages(
+    mut days: EventReader<DayPassed>,
+    mut party: Query<(Entity, &mut Character, &Combatant, Option<&Retainer>)>,
+    mut checks: EventWriter<LoyaltyCheck>,
+) {
+    for _ in days.read() {
+        let wages: u32 = party
+            .iter()
+            .filter_map(|(_, character, _, retainer)| retainer.filter(|_| character.is_alive()))
+            .map(|retainer| retainer.wage)
+            .sum();
+        if wages == 0 {
+            continue;
+        }
+        let mut employers: Vec<&mut Character> = party
+            .iter_mut()
+            .filter(|(_, _, combatant, retainer)| combatant.is_player && retainer.is_none())
+            .map(|(_, character, _, _)| character.into_inner())
+            .collect();
+        if pay_from_party(&mut employers, wages).is_ok() {
+            debug!("The party pays its retainers {} gp", wages);
+            continue;
+        }
+        warn!("The party can't pay its retainers {} gp", wages);
+        for (entity, character, _, retainer) in party.iter() {
+            if retainer.is_some() && character.is_alive() {
+                checks.send(LoyaltyCheck { retainer: entity, grievance: Grievance::Unpaid });
+            }
+        }
+    }
+}
+
+// Someone in the party fell, or a retainer came out of it at half their hit points or less
+fn test_loyalty_after_fight(
+    party: Query<(Entity, &Character, &Combatant, Option<&Retainer>)>,
+    mut checks: EventWriter<LoyaltyCheck>,
+) {
+    let members: Vec<_> = party.iter().filter(|(_, _, combatant, _)| combatant.is_player).collect();
+    let dangerous = members.iter().any(|(_, character, _, retainer)| {
+        !character.is_alive() || (retainer.is_some() && character.hit_points.current * 2 <= character.hit_points.maximum)
+    });
+    if !dangerous {
+        return;
+    }
+    for (entity, character, _, retainer) in members {
+        if retainer.is_some() && character.is_alive() {
+            checks.send(LoyaltyCheck { retainer: entity, grievance: Grievance::Danger });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_loyalty(
+    mut commands: Commands,
+    mut checks: EventReader<LoyaltyCheck>,
+    mut party: Query<(Entity, &mut Character, &Combatant, Option<&mut Retainer>)>,
+    dungeon: Res<CurrentDungeon>,
+    clock: Res<GameClock>,
+    mut roster: ResMut<NPCRoster>,
+    mut awaiting: ResMut<AwaitingGrievances>,
+    mut message: ResMut<RetainerMessage>,
+    mut conversations: EventWriter<NPCConversationEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    for check in checks.read() {
+        let Ok((entity, character, _, Some(mut retainer))) = party.get_mut(check.retainer) else {
+            continue;
+        };
+        let Some(disloyalty) = loyalty_check(&retainer, check.grievance, rng.as_mut()) else {
+            debug!("{} stays loyal despite {}", character.name, check.grievance.describe());
+            continue;
+        };
+        let name = character.name.clone();
+        let outcome = disloyalty.describe();
+        info!("{} {} after {}", name, outcome, check.grievance.describe());
+        let npc_id = format!("retainer:{}", name);
+        roster.npcs.entry(npc_id.clone()).or_insert_with(|| {
+            create_npc(
+                name.clone(),
+                retainer.personality.clone(),
+                format!("A {} in the party's pay", retainer.kind.name()),
+            )
+        });
+
+        match disloyalty {
+            Disloyalty::Desert => commands.entity(entity).despawn_recursive(),
+            Disloyalty::Steal(gold) => {
+                let mut employers: Vec<&mut Character> = party
+                    .iter_mut()
+                    .filter(|(_, _, combatant, retainer)| combatant.is_player && retainer.is_none())
+                    .map(|(_, character, _, _)| character.into_inner())
+                    .collect();
+                let purse: u32 = employers.iter().map(|character| character.inventory.gold).sum();
+                let _ = pay_from_party(&mut employers, gold.min(purse));
+                commands.entity(entity).despawn_recursive();
+            }
+            Disloyalty::Refuse => {
+                retainer.refusing = true;
+                if dungeon.0.is_some() {
+                    commands.entity(entity).remove::<Combatant>();
+                }
+            }
+        }
+
+        conversations.send(NPCConversationEvent {
+            npc_id: npc_id.clone(),
+            player_message: format!(
+                "(After {}, {} {}. Tell your employers why.)",
+                check.grievance.describe(),
+                name,
+                outcome
+            ),
+            context: create_conversation_context("with the party".to_string(), &clock, Vec::new(), 0),
+        });
+        awaiting.0.push((npc_id, format!("{} {}", name, outcome)));
+        message.0 = Some(format!("{} {}", name, outcome));
+    }
+}
+
+fn hear_grievances(
+    mut responses: EventReader<NPCConversationResponseEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut awaiting: ResMut<AwaitingGrievances>,
+    mut message: ResMut<RetainerMessage>,
+) {
+    for response in responses.read() {
+        let Some(index) = awaiting.0.iter().position(|(npc_id, _)| *npc_id == response.npc_id) else {
+            continue;
+        };
+        let (_, what_happened) = awaiting.0.remove(index);
+        message.0 = Some(format!("{}: \"{}\"", what_happened, response.response.npc_response));
+    }
+    // As in town, the oldest one waiting gives up
+    for failure in failures.read() {
+        if failure.endpoint == "conversation" && !awaiting.0.is_empty() {
+            let (_, what_happened) = awaiting.0.remove(0);
+            message.0 = Some(what_happened);
+        }
+    }
+}
+
+// Retainers refusing to go in are left outside when the party enters a dungeon
+fn stay_behind(
+    mut commands: Commands,
+    mut entering: EventReader<EnterDungeon>,
+    retainers: Query<(Entity, &Character, &Retainer), With<Combatant>>,
+) {
+    if entering.read().last().is_none() {
+        return;
+    }
+    for (entity, character, retainer) in retainers.iter() {
+        if retainer.refusing {
+            info!("{} won't go in and waits for the party to come back", character.name);
+            commands.entity(entity).remove::<Combatant>();
+        }
+    }
+}
+
+fn rejoin_in_town(mut commands: Commands, mut waiting: Query<(Entity, &Character, &mut Retainer), Without<Combatant>>) {
+    for (entity, character, mut retainer) in waiting.iter_mut() {
+        info!("{} rejoins the party", character.name);
+        retainer.refusing = false;
+        commands.entity(entity).insert(Combatant {
+            initiative: 0,
+            is_player: true,
+            actions_remaining: 1,
+            status_effects: Vec::new(),
+        });
+    }
+}
@@ -0,0 +1,756 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ai_client::{CurrentDungeon, DungeonData, EnemyData, NPCData, NPCRoster, Quest, QuestLog, QuestOffer};
+use crate::character::{Character, CharacterClass, CharacterStats};
+use crate::clock::GameClock;
+use crate::combat::{Combat, CombatState, Combatant, Declaration, RoundPhase, Side};
+use crate::config::KeyBindings;
+use crate::dungeon::{DungeonMap, FogOfWar};
+use crate::encounters::{MetEncounters, Monster};
+use crate::game_over::RunRecord;
+use crate::light::{LitLight, PartyLight};
+use crate::message_log::{LogEntry, MessageLog};
+use crate::retainers::Retainer;
+use crate::rng::GameRng;
+use crate::gossip::Grapevine;
+use crate::temple::TempleFavor;
+use crate::text_input::{CharFilter, FieldAction, TextField, Typing};
+use crate::treasure::LootedTreasure;
+use crate::world::{CurrentSite, DungeonState, PendingDungeonState, WorldDatabase};
+use crate::{GameConfig, GameState};
+
+// Saved games. Everything the party has accumulated is written as one JSON document per
+// slot in `GameConfig::save_dir`: every character in the world (party and any monsters
+// in a fight), the active combat, the current dungeon and what the party has explored
+// of it and what they've done there, every other dungeon they've been to, the quest
+// log, the NPCs met so far, the party's light, the game clock, what the party has given
+// the temple, the rumors going round town and the message log. F5 quick-saves and F6
+// asks for a slot name to save to (the next numbered slot unless the player types
+// another) while playing; F9 loads the most recent save, and L on the main menu opens
+// the Load Game screen.
+
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+
+// Bump this and add a step to `MIGRATIONS` whenever the save layout changes in a way
+// that filling in defaults can't cover (renamed or restructured fields). New fields
+// alone don't need a version bump; `fill_defaults` takes care of them.
+//   1: unversioned saves from before this field existed
+//   2: adds `version`
+//   3: adds each character's `experience_bonus`, worked out from their scores
+pub const SAVE_VERSION: u32 = 3;
+
+// Upgrades a raw save document from version `n` to `n + 1`, indexed by `n - 1`
+type Migration = fn(&mut Map<String, Value>, &mut MigrationLog);
+
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+// Everything about a character that goes into a save
+type CharacterParts = (
+    Entity,
+    &'static Character,
+    Option<&'static Combatant>,
+    Option<&'static Retainer>,
+    Option<&'static Monster>,
+);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub saved_at: u64,
+    // Dice pick up from here after loading, so a reloaded game rolls what the original would have
+    pub rng_seed: u64,
+    pub characters: Vec<SavedCharacter>,
+    pub combat: Option<SavedCombat>,
+    pub dungeon: Option<DungeonData>,
+    pub quests: Vec<Quest>,
+    // Quests NPCs have offered that the party hasn't answered yet
+    #[serde(default)]
+    pub quest_offers: Vec<QuestOffer>,
+    #[serde(default)]
+    pub reputation: i8,
+    // What each NPC remembers of the party and thinks of its members
+    #[serde(default)]
+    pub npcs: HashMap<String, NPCData>,
+    // The party's torch or lantern, if one is burning
+    #[serde(default)]
+    pub light: Option<LitLight>,
+    #[serde(default)]
+    pub clock: GameClock,
+    // What the party has explored of the current dungeon
+    #[serde(default)]
+    pub fog: FogOfWar,
+    // Doors, traps, encounters and treasure the party has been at in the current dungeon
+    #[serde(default)]
+    pub dungeon_state: DungeonState,
+    #[serde(default)]
+    pub site: Option<String>,
+    // The dungeons the party has left behind
+    #[serde(default)]
+    pub world: WorldDatabase,
+    #[serde(default)]
+    pub temple: TempleFavor,
+    #[serde(default)]
+    pub grapevine: Grapevine,
+    #[serde(default)]
+    pub messages: Vec<LogEntry>,
+    // Which run this is and whether it's played ironman
+    #[serde(default)]
+    pub run: RunRecord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedCharacter {
+    pub character: Character,
+    pub combatant: Option<Combatant>,
+    #[serde(default)]
+    pub retainer: Option<Retainer>,
+    // A monster's stat block, as rolled
+    #[serde(default)]
+    pub monster: Option<EnemyData>,
+    // What saves from before the bestiary kept instead, for the monster's picture
+    #[serde(default, skip_serializing)]
+    pub monster_type: Option<String>,
+}
+
+// Entities are stored as indices into `SaveGame::characters`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedCombat {
+    pub round: u32,
+    pub turn: u32,
+    pub combatants: Vec<usize>,
+    pub initiative_order: Vec<usize>,
+    pub current_combatant: Option<usize>,
+    pub state: CombatState,
+    #[serde(default)]
+    pub surprised: Option<Side>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<RoundPhase>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub declarations: Vec<(usize, Declaration)>,
+}
+
+// What the Load Game screen shows for a slot, read from the save file itself
+#[derive(Debug, Clone)]
+pub struct SlotSummary {
+    pub slot: String,
+    pub character_name: String,
+    pub level: u8,
+    pub location: String,
+    pub saved_at: u64,
+    pub run_id: u64,
+    pub ironman: bool,
+}
+
+// Slots found in the save directory, most recent first, and the one highlighted in the
+// Load Game screen
+#[derive(Resource, Default)]
+pub struct SaveSlots {
+    pub slots: Vec<SlotSummary>,
+    pub selected: usize,
+}
+
+// The F6 prompt, Some while the player is naming the slot
+#[derive(Resource, Default, Debug)]
+pub struct SaveAs(pub Option<TextField>);
+
+#[derive(Event)]
+pub struct SaveGameEvent {
+    pub slot: String,
+}
+
+#[derive(Event)]
+pub struct LoadGameEvent {
+    pub slot: String,
+}
+
+// What the migration layer had to do to read a save, for the log and the player
+#[derive(Debug, Clone, Default)]
+pub struct MigrationLog {
+    pub from_version: u32,
+    pub entries: Vec<String>,
+}
+
+impl MigrationLog {
+    fn note(&mut self, entry: impl Into<String>) {
+        self.entries.push(entry.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[derive(Event)]
+pub struct GameLoaded {
+    pub slot: String,
+    pub migrations: MigrationLog,
+}
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>()
+            .init_resource::<SaveAs>()
+            .add_event::<SaveGameEvent>()
+            .add_event::<LoadGameEvent>()
+            .add_event::<GameLoaded>()
+            .add_systems(Startup, load_slot_at_startup)
+            .add_systems(OnEnter(GameState::LoadGame), refresh_save_slots)
+            .add_systems(OnEnter(GameState::MainMenu), close_save_prompt)
+            .add_systems(Update, (
+                save_load_keys.run_if(in_state(GameState::InGame).or_else(in_state(GameState::Combat))),
+                load_menu_input.run_if(in_state(GameState::LoadGame)),
+                save_game,
+                load_game,
+            ).chain());
+    }
+}
+
+impl SaveGame {
+    // The first player character stands in for the party in slot listings
+    pub fn summary(&self, slot: &str) -> SlotSummary {
+        let leader = self
+            .characters
+            .iter()
+            .find(|saved| saved.combatant.as_ref().is_some_and(|combatant| combatant.is_player))
+            .or_else(|| self.characters.first());
+
+        SlotSummary {
+            slot: slot.to_string(),
+            character_name: leader.map(|saved| saved.character.name.clone()).unwrap_or_default(),
+            level: leader.map(|saved| saved.character.level).unwrap_or_default(),
+            location: self.dungeon.as_ref().map(|dungeon| dungeon.name.clone()).unwrap_or_else(|| "Town".to_string()),
+            saved_at: self.saved_at,
+            run_id: self.run.id,
+            ironman: self.run.ironman,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<(Self, MigrationLog), String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let document: Value = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        Self::migrate(document)
+    }
+
+    // Brings a save from any earlier build up to `SAVE_VERSION`, then fills in whatever
+    // fields are still missing before deserializing
+    pub fn migrate(document: Value) -> Result<(Self, MigrationLog), String> {
+        let Value::Object(mut document) = document else {
+            return Err("save file is not a JSON object".to_string());
+        };
+
+        let version = document.get("version").and_then(Value::as_u64).unwrap_or(1) as u32;
+        if version > SAVE_VERSION {
+            return Err(format!(
+                "save is from a newer build (version {}, this build reads up to {})",
+                version, SAVE_VERSION
+            ));
+        }
+
+        let mut log = MigrationLog {
+            from_version: version,
+            entries: Vec::new(),
+        };
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version.saturating_sub(1) as usize) {
+            migration(&mut document, &mut log);
+            document.insert("version".to_string(), Value::from(index as u32 + 2));
+        }
+
+        let mut document = Value::Object(document);
+        fill_defaults(&mut document, &save_template(), "save", &mut log);
+        let save = serde_json::from_value(document).map_err(|e| e.to_string())?;
+        Ok((save, log))
+    }
+}
+
+fn migrate_v1_to_v2(_document: &mut Map<String, Value>, log: &mut MigrationLog) {
+    log.note("version 1 -> 2: added save version");
+}
+
+// The bonus is fixed as a character is made, so the party's comes from the scores they
+// were saved with; monsters have none
+fn migrate_v2_to_v3(document: &mut Map<String, Value>, log: &mut MigrationLog) {
+    let Some(Value::Array(characters)) = document.get_mut("characters") else {
+        return;
+    };
+    for saved in characters.iter_mut() {
+        if saved.get("monster").is_some_and(|monster| !monster.is_null()) {
+            continue;
+        }
+        let Some(Value::Object(character)) = saved.get_mut("character") else {
+            continue;
+        };
+        let class = character.get("class").cloned().map(serde_json::from_value::<CharacterClass>);
+        let stats = character.get("stats").cloned().map(serde_json::from_value::<CharacterStats>);
+        let (Some(Ok(class)), Some(Ok(stats))) = (class, stats) else {
+            continue;
+        };
+        let bonus = class.experience_bonus(&stats);
+        let name = character.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+        character.insert("experience_bonus".to_string(), Value::from(bonus));
+        log.note(format!("version 2 -> 3: {}'s experience bonus is {:+}%", name, bonus));
+    }
+}
+
+// Scores for the template's character, which only stand in and are never rolled
+fn placeholder_stats() -> CharacterStats {
+    CharacterStats {
+        strength: 10,
+        dexterity: 10,
+        constitution: 10,
+        intelligence: 10,
+        wisdom: 10,
+        charisma: 10,
+    }
+}
+
+// A save with one of everything, serialized, whose values stand in for fields an older
+// save doesn't have. It holds no items, spells or status effects, so the payloads of
+// data-carrying enums are never merged with a different variant.
+fn save_template() -> Value {
+    let template = SaveGame {
+        version: SAVE_VERSION,
+        saved_at: 0,
+        rng_seed: 0,
+        characters: vec![SavedCharacter {
+            character: Character::with_stats(String::new(), CharacterClass::Fighter, placeholder_stats()),
+            combatant: Some(Combatant {
+                initiative: 0,
+                is_player: true,
+                actions_remaining: 1,
+                status_effects: Vec::new(),
+            }),
+            retainer: None,
+            monster: None,
+            monster_type: None,
+        }],
+        combat: Some(SavedCombat {
+            round: 1,
+            turn: 1,
+            combatants: Vec::new(),
+            initiative_order: Vec::new(),
+            current_combatant: None,
+            state: CombatState::Initiative,
+            surprised: None,
+            phase: None,
+            declarations: Vec::new(),
+        }),
+        dungeon: None,
+        quests: Vec::new(),
+        quest_offers: Vec::new(),
+        reputation: 0,
+        npcs: HashMap::new(),
+        light: None,
+        clock: GameClock::default(),
+        fog: FogOfWar::default(),
+        dungeon_state: DungeonState::default(),
+        site: None,
+        world: WorldDatabase::default(),
+        temple: TempleFavor::default(),
+        grapevine: Grapevine::default(),
+        messages: Vec::new(),
+        run: RunRecord::default(),
+    };
+    serde_json::to_value(template).unwrap_or(Value::Null)
+}
+
+// Copies any key the template has and the value lacks, recursing into nested objects
+// and into each element of arrays whose template has an example element
+fn fill_defaults(value: &mut Value, template: &Value, path: &str, log: &mut MigrationLog) {
+    match (value, template) {
+        (Value::Object(fields), Value::Object(template_fields)) => {
+            for (key, default) in template_fields {
+                let field_path = format!("{}.{}", path, key);
+                match fields.get_mut(key) {
+                    Some(existing) => fill_defaults(existing, default, &field_path, log),
+                    None => {
+                        log.note(format!("{}: missing, defaulted to {}", field_path, default));
+                        fields.insert(key.clone(), default.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(elements), Value::Array(template_elements)) => {
+            if let Some(element_template) = template_elements.first() {
+                for (index, element) in elements.iter_mut().enumerate() {
+                    fill_defaults(element, element_template, &format!("{}[{}]", path, index), log);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn slot_path(save_dir: &str, slot: &str) -> PathBuf {
+    Path::new(save_dir).join(format!("{}.json", slot))
+}
+
+// Every readable save in the directory, most recent first. Unreadable files are logged
+// and left out rather than hiding the rest.
+pub fn list_slots(save_dir: &str) -> Vec<SlotSummary> {
+    let Ok(entries) = std::fs::read_dir(save_dir) else {
+        return Vec::new();
+    };
+
+    let mut slots: Vec<SlotSummary> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|path| {
+            let slot = path.file_stem()?.to_string_lossy().into_owned();
+            match SaveGame::read(&path) {
+                Ok((save, _)) => Some(save.summary(&slot)),
+                Err(e) => {
+                    warn!("Skipping unreadable save {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    slots.sort_by_key(|slot| std::cmp::Reverse(slot.saved_at));
+    slots
+}
+
+// Deletes every save of the run, as an ironman party dies, and says which slots went
+pub fn delete_run_saves(save_dir: &str, run_id: u64) -> Vec<String> {
+    list_slots(save_dir)
+        .into_iter()
+        .filter(|summary| summary.run_id == run_id)
+        .filter_map(|summary| match std::fs::remove_file(slot_path(save_dir, &summary.slot)) {
+            Ok(()) => Some(summary.slot),
+            Err(e) => {
+                warn!("Failed to delete ironman save {}: {}", summary.slot, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// "slot-1", "slot-2", ... whichever is the first not on disk yet
+fn next_numbered_slot(save_dir: &str) -> String {
+    (1..)
+        .map(|number| format!("slot-{}", number))
+        .find(|slot| !slot_path(save_dir, slot).exists())
+        .unwrap_or_else(|| QUICKSAVE_SLOT.to_string())
+}
+
+// `--load-save <slot>` on the command line
+fn load_slot_at_startup(config: Res<GameConfig>, mut load_events: EventWriter<LoadGameEvent>) {
+    if let Some(slot) = &config.load_slot {
+        load_events.send(LoadGameEvent { slot: slot.clone() });
+    }
+}
+
+fn save_load_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    config: Res<GameConfig>,
+    mut typing: ResMut<Typing>,
+    mut save_as: ResMut<SaveAs>,
+    mut save_events: EventWriter<SaveGameEvent>,
+    mut load_events: EventWriter<LoadGameEvent>,
+) {
+    if let Some(name) = save_as.0.as_mut() {
+        match name.edit(&mut typing) {
+            Some(FieldAction::Submit) => {
+                let slot = if name.is_blank() {
+                    next_numbered_slot(&config.save_dir)
+                } else {
+                    name.text().to_string()
+                };
+                save_events.send(SaveGameEvent { slot });
+                save_as.0 = None;
+            }
+            Some(FieldAction::Cancel) => save_as.0 = None,
+            None => {}
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(keys.quick_save) {
+        save_events.send(SaveGameEvent {
+            slot: QUICKSAVE_SLOT.to_string(),
+        });
+    }
+    if keyboard_input.just_pressed(keys.save_new_slot) {
+        save_as.0 = Some(TextField::with_text(CharFilter::FileName, &next_numbered_slot(&config.save_dir)));
+    }
+    if keyboard_input.just_pressed(keys.quick_load) {
+        if let Some(latest) = list_slots(&config.save_dir).into_iter().next() {
+            load_events.send(LoadGameEvent { slot: latest.slot });
+        }
+    }
+}
+
+fn close_save_prompt(mut save_as: ResMut<SaveAs>) {
+    save_as.0 = None;
+}
+
+fn refresh_save_slots(config: Res<GameConfig>, mut slots: ResMut<SaveSlots>) {
+    slots.slots = list_slots(&config.save_dir);
+    slots.selected = 0;
+}
+
+fn load_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut slots: ResMut<SaveSlots>,
+    mut load_events: EventWriter<LoadGameEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+    if slots.slots.is_empty() {
+        return;
+    }
+
+    let count = slots.slots.len();
+    if keyboard_input.just_pressed(KeyCode::Up) || keyboard_input.just_pressed(KeyCode::W) {
+        slots.selected = (slots.selected + count - 1) % count;
+    } else if keyboard_input.just_pressed(KeyCode::Down) || keyboard_input.just_pressed(KeyCode::S) {
+        slots.selected = (slots.selected + 1) % count;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        load_events.send(LoadGameEvent {
+            slot: slots.slots[slots.selected].slot.clone(),
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_game(
+    mut save_events: EventReader<SaveGameEvent>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    characters: Query<CharacterParts>,
+    combat: Query<&Combat>,
+    dungeon: Res<CurrentDungeon>,
+    quest_log: Res<QuestLog>,
+    roster: Res<NPCRoster>,
+    light: Res<PartyLight>,
+    clock: Res<GameClock>,
+    fog: Res<FogOfWar>,
+    map: Option<Res<DungeonMap>>,
+    met: Res<MetEncounters>,
+    looted: Res<LootedTreasure>,
+    (site, world, temple, grapevine): (Res<CurrentSite>, Res<WorldDatabase>, Res<TempleFavor>, Res<Grapevine>),
+    (messages, run): (Res<MessageLog>, Res<RunRecord>),
+) {
+    let Some(slot) = save_events.read().last().map(|event| event.slot.clone()) else {
+        return;
+    };
+
+    let mut indices = HashMap::new();
+    let saved_characters = characters
+        .iter()
+        .enumerate()
+        .map(|(index, (entity, character, combatant, retainer, monster))| {
+            indices.insert(entity, index);
+            SavedCharacter {
+                character: character.clone(),
+                combatant: combatant.cloned(),
+                retainer: retainer.cloned(),
+                monster: monster.map(|monster| monster.0.clone()),
+                monster_type: None,
+            }
+        })
+        .collect();
+
+    // Reseed here too, so carrying on after a save matches loading it
+    let rng_seed = rng.gen();
+    *rng = GameRng::from_seed(rng_seed);
+
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+        rng_seed,
+        characters: saved_characters,
+        combat: combat.get_single().ok().map(|combat| SavedCombat::new(combat, &indices)),
+        dungeon: dungeon.0.clone(),
+        quests: quest_log.quests.clone(),
+        quest_offers: quest_log.offers.clone(),
+        reputation: quest_log.reputation,
+        npcs: roster.npcs.clone(),
+        light: light.0,
+        clock: *clock,
+        fog: fog.clone(),
+        dungeon_state: map
+            .map(|map| DungeonState::capture(&map, &met.0, &looted.0))
+            .unwrap_or_default(),
+        site: site.0.clone(),
+        world: world.clone(),
+        temple: *temple,
+        grapevine: grapevine.clone(),
+        messages: messages.entries.iter().cloned().collect(),
+        run: run.clone(),
+    };
+
+    let path = slot_path(&config.save_dir, &slot);
+    match save.write(&path) {
+        Ok(()) => info!("Game saved to {}", path.display()),
+        Err(e) => warn!("Failed to save game to {}: {}", path.display(), e),
+    }
+}
+
+// Replaces the current world with the saved one
+#[allow(clippy::too_many_arguments)]
+fn load_game(
+    mut commands: Commands,
+    mut load_events: EventReader<LoadGameEvent>,
+    mut loaded: EventWriter<GameLoaded>,
+    config: Res<GameConfig>,
+    existing_characters: Query<Entity, With<Character>>,
+    existing_combat: Query<Entity, With<Combat>>,
+    mut dungeon: ResMut<CurrentDungeon>,
+    mut quest_log: ResMut<QuestLog>,
+    mut roster: ResMut<NPCRoster>,
+    mut light: ResMut<PartyLight>,
+    mut clock: ResMut<GameClock>,
+    mut fog: ResMut<FogOfWar>,
+    mut pending: ResMut<PendingDungeonState>,
+    (mut site, mut world, mut temple, mut grapevine): (
+        ResMut<CurrentSite>,
+        ResMut<WorldDatabase>,
+        ResMut<TempleFavor>,
+        ResMut<Grapevine>,
+    ),
+    (mut messages, mut run): (ResMut<MessageLog>, ResMut<RunRecord>),
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(slot) = load_events.read().last().map(|event| event.slot.clone()) else {
+        return;
+    };
+
+    let path = slot_path(&config.save_dir, &slot);
+    let (save, migrations) = match SaveGame::read(&path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            warn!("Failed to load game from {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    for entity in existing_characters.iter().chain(existing_combat.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let entities: Vec<Entity> = save
+        .characters
+        .into_iter()
+        .map(|saved| {
+            let saved_name = saved.character.name.clone();
+            let mut entity = commands.spawn(saved.character);
+            if let Some(combatant) = saved.combatant {
+                entity.insert(combatant);
+            }
+            if let Some(retainer) = saved.retainer {
+                entity.insert(retainer);
+            }
+            let older = saved.monster_type.map(|monster_type| EnemyData {
+                name: saved_name.clone(),
+                monster_type,
+                ..EnemyData::default()
+            });
+            if let Some(monster) = saved.monster.or(older) {
+                entity.insert(Monster(monster));
+            }
+            entity.id()
+        })
+        .collect();
+
+    let in_combat = save.combat.is_some();
+    if let Some(saved_combat) = save.combat {
+        commands.spawn(saved_combat.restore(&entities));
+    }
+
+    commands.insert_resource(GameRng::from_seed(save.rng_seed));
+    pending.0 = save.dungeon.as_ref().map(|data| (data.name.clone(), save.dungeon_state));
+    dungeon.0 = save.dungeon;
+    quest_log.quests = save.quests;
+    quest_log.offers = save.quest_offers;
+    quest_log.reputation = save.reputation;
+    roster.npcs = save.npcs;
+    light.0 = save.light;
+    *clock = save.clock;
+    *fog = save.fog;
+    site.0 = save.site;
+    *world = save.world;
+    *temple = save.temple;
+    *grapevine = save.grapevine;
+    *run = save.run;
+    messages.restore(save.messages);
+
+    info!("Loaded {} characters from {}", entities.len(), path.display());
+    if !migrations.is_empty() {
+        info!("Save upgraded from version {}:", migrations.from_version);
+        for entry in &migrations.entries {
+            info!("  {}", entry);
+        }
+    }
+    let resume = if in_combat {
+        GameState::Combat
+    } else if dungeon.0.is_some() {
+        GameState::InGame
+    } else {
+        GameState::Town
+    };
+    next_state.set(resume);
+    loaded.send(GameLoaded { slot, migrations });
+}
+
+impl SavedCombat {
+    fn new(combat: &Combat, indices: &HashMap<Entity, usize>) -> Self {
+        let to_indices = |entities: &[Entity]| entities.iter().filter_map(|entity| indices.get(entity).copied()).collect();
+        Self {
+            round: combat.round,
+            turn: combat.turn,
+            combatants: to_indices(&combat.combatants),
+            initiative_order: to_indices(&combat.initiative_order),
+            current_combatant: combat.current_combatant.and_then(|entity| indices.get(&entity).copied()),
+            state: combat.state.clone(),
+            surprised: combat.surprised,
+            phase: combat.phase,
+            declarations: combat
+                .declarations
+                .iter()
+                .filter_map(|(entity, declaration)| Some((*indices.get(entity)?, declaration.clone())))
+                .collect(),
+        }
+    }
+
+    fn restore(self, entities: &[Entity]) -> Combat {
+        let to_entities = |indices: &[usize]| indices.iter().filter_map(|&index| entities.get(index).copied()).collect();
+        Combat {
+            round: self.round,
+            turn: self.turn,
+            combatants: to_entities(&self.combatants),
+            initiative_order: to_entities(&self.initiative_order),
+            current_combatant: self.current_combatant.and_then(|index| entities.get(index).copied()),
+            state: self.state,
+            surprised: self.surprised,
+            phase: self.phase,
+            declarations: self
+                .declarations
+                .into_iter()
+                .filter_map(|(index, declaration)| Some((*entities.get(index)?, declaration)))
+                .collect(),
+        }
+    }
+}
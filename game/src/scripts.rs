@@ -0,0 +1,404 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Scope, AST, INT};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::ai_client::QuestLog;
+use crate::character::Character;
+use crate::combat::Combatant;
+use crate::console::{give_item, spawn_monsters};
+use crate::game_events::GameEvent;
+use crate::message_log::{LogMessage, MessageCategory};
+use crate::mods::ModList;
+use crate::quests::Resolution;
+use crate::town::Summons;
+use crate::GameState;
+
+// Scripts: what happens when the party walks into a particular room, goes to a site,
+// sets off a trap, kills a monster or finishes a quest, written in Rhai in files under
+// assets/scripts (and a mod's scripts folder, where one replaces the game's of the same
+// name). A script is a set of functions, each run when its kind of thing happens, with
+// whatever the script likes done in between:
+//
+//   // The sage wants to hear about the tome, and pays better the second time
+//   fn on_quest_complete(title) {
+//       if title != "The Lost Tome" { return; }
+//       say("Word of the tome reaches the sage");
+//       add_reputation(if flag("sage_pleased") == true { 2 } else { 1 });
+//       set_flag("sage_pleased", true);
+//       if first_time("potion") { give("Potion of Healing"); }
+//       talk("sage");
+//   }
+//
+// The functions are on_room(room, dungeon), on_site(site), on_trap(kind, victim),
+// on_kill(monster), on_quest_complete(title) and on_quest_failed(title); the names come
+// as the game has them, so a script wanting any case compares `name.to_lower()`. On top
+// of the language itself a script has:
+//   say(text)                   a line in the message log
+//   spawn_monster(monster[, count])
+//                               monsters from the bestiary, which fight the party in a dungeon
+//   give(item[, to])            an item from the catalog, to the first of the party or the one named
+//   add_reputation(change)      the party's reputation up or down
+//   talk(npc)                   a resident of town asks to talk, as soon as the party is there
+//   reputation(), party()       the party's reputation, and the names of those still standing
+//   flag(name), set_flag(name, value)
+//                               values kept for the session and shared between scripts,
+//                               () for a flag never set
+//   first_time(key)             true only the first time the script asks in a session
+// and nothing else: no files, no modules, no eval, and a cap on how long a handler runs
+// and how much it builds, so a mod's script can't hang or reach past the game. Whatever
+// the handlers ask for is done afterwards, in order. A script is compiled when it loads,
+// and one that can't be is reported with its line and not loaded; a handler that fails
+// partway, or a request that can't be carried out, is logged and the rest go on.
+
+// How far one handler can go before it is stopped
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 10_000;
+const MAX_COLLECTION_SIZE: usize = 1_000;
+
+// Something that happened, as the handler it runs and what that is given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    Room { room: String, dungeon: String },
+    Site(String),
+    Trap { kind: String, victim: String },
+    Kill(String),
+    QuestComplete(String),
+    QuestFailed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Say(String),
+    Spawn { monster: String, count: u32 },
+    Give { item: String, to: Option<String> },
+    Reputation(i8),
+    Talk(String),
+}
+
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct Script {
+    pub ast: AST,
+}
+
+impl Trigger {
+    fn handler(&self) -> (&'static str, Vec<String>) {
+        match self {
+            Trigger::Room { room, dungeon } => ("on_room", vec![room.clone(), dungeon.clone()]),
+            Trigger::Site(site) => ("on_site", vec![site.clone()]),
+            Trigger::Trap { kind, victim } => ("on_trap", vec![kind.clone(), victim.clone()]),
+            Trigger::Kill(monster) => ("on_kill", vec![monster.clone()]),
+            Trigger::QuestComplete(title) => ("on_quest_complete", vec![title.clone()]),
+            Trigger::QuestFailed(title) => ("on_quest_failed", vec![title.clone()]),
+        }
+    }
+}
+
+impl Script {
+    fn handles(&self, function: &str, arguments: usize) -> bool {
+        self.ast.iter_functions().any(|f| f.name == function && f.params.len() == arguments)
+    }
+}
+
+// An engine with the limits every script runs under and nothing of the game's yet, which
+// is all compiling needs
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .on_print(|text| info!("Script: {}", text))
+        .on_debug(|text, _, position| debug!("Script at {}: {}", position, text));
+    engine.disable_symbol("eval");
+    engine
+}
+
+// What the scripts can see while they run, what they have asked for, and what they keep
+// for the session
+#[derive(Debug, Default)]
+struct ScriptState {
+    running: String,
+    reputation: i8,
+    party: Vec<String>,
+    requests: Vec<(String, Action)>,
+    first_times: HashSet<(String, String)>,
+    flags: HashMap<String, Dynamic>,
+}
+
+impl ScriptState {
+    fn ask(state: &Mutex<ScriptState>, action: Action) {
+        if let Ok(mut state) = state.lock() {
+            let running = state.running.clone();
+            state.requests.push((running, action));
+        }
+    }
+}
+
+// The sandboxed engine with the game's functions, each reading or writing `state`
+fn game_engine(state: &Arc<Mutex<ScriptState>>) -> Engine {
+    let mut engine = sandboxed_engine();
+    let shared = state.clone();
+    engine.register_fn("say", move |text: &str| ScriptState::ask(&shared, Action::Say(text.to_string())));
+    let shared = state.clone();
+    engine.register_fn("spawn_monster", move |monster: &str| {
+        ScriptState::ask(&shared, Action::Spawn { monster: monster.to_string(), count: 1 })
+    });
+    let shared = state.clone();
+    engine.register_fn("spawn_monster", move |monster: &str, count: INT| {
+        let count = count.clamp(0, u32::MAX as INT) as u32;
+        ScriptState::ask(&shared, Action::Spawn { monster: monster.to_string(), count })
+    });
+    let shared = state.clone();
+    engine.register_fn("give", move |item: &str| {
+        ScriptState::ask(&shared, Action::Give { item: item.to_string(), to: None })
+    });
+    let shared = state.clone();
+    engine.register_fn("give", move |item: &str, to: &str| {
+        ScriptState::ask(&shared, Action::Give { item: item.to_string(), to: Some(to.to_string()) })
+    });
+    let shared = state.clone();
+    engine.register_fn("add_reputation", move |change: INT| {
+        let change = change.clamp(i8::MIN as INT, i8::MAX as INT) as i8;
+        ScriptState::ask(&shared, Action::Reputation(change))
+    });
+    let shared = state.clone();
+    engine.register_fn("talk", move |npc: &str| ScriptState::ask(&shared, Action::Talk(npc.to_string())));
+    let shared = state.clone();
+    engine.register_fn("reputation", move || shared.lock().map_or(0, |state| state.reputation as INT));
+    let shared = state.clone();
+    engine.register_fn("party", move || -> Array {
+        shared.lock().map_or_else(|_| Array::new(), |state| state.party.iter().cloned().map(Dynamic::from).collect())
+    });
+    let shared = state.clone();
+    engine.register_fn("flag", move |name: &str| {
+        shared.lock().ok().and_then(|state| state.flags.get(name).cloned()).unwrap_or(Dynamic::UNIT)
+    });
+    let shared = state.clone();
+    engine.register_fn("set_flag", move |name: &str, value: Dynamic| {
+        if let Ok(mut state) = shared.lock() {
+            state.flags.insert(name.to_string(), value);
+        }
+    });
+    let shared = state.clone();
+    engine.register_fn("first_time", move |key: &str| {
+        shared.lock().is_ok_and(|mut state| {
+            let running = state.running.clone();
+            state.first_times.insert((running, key.to_string()))
+        })
+    });
+    engine
+}
+
+pub struct ScriptLoader {
+    engine: Engine,
+}
+
+impl Default for ScriptLoader {
+    fn default() -> Self {
+        ScriptLoader { engine: sandboxed_engine() }
+    }
+}
+
+impl AssetLoader for ScriptLoader {
+    type Asset = Script;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Script, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            Ok(Script { ast: self.engine.compile(&text)? })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+// The scripts in play by name, with the layer each came from (see ModList::layer), and
+// the engine they run in with what they keep for the session
+#[derive(Resource)]
+pub struct ScriptBook {
+    scripts: HashMap<String, (usize, AssetId<Script>)>,
+    engine: Engine,
+    state: Arc<Mutex<ScriptState>>,
+}
+
+impl Default for ScriptBook {
+    fn default() -> Self {
+        let state = Arc::default();
+        ScriptBook { scripts: HashMap::new(), engine: game_engine(&state), state }
+    }
+}
+
+impl ScriptBook {
+    // Names in order, so handlers for the same thing run the same way every time
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.scripts.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    // Runs every script's handler for what happened, and returns what they asked for, by
+    // script name
+    fn fire(
+        &self,
+        happened: &Trigger,
+        scripts: &Assets<Script>,
+        reputation: i8,
+        party: Vec<String>,
+    ) -> Vec<(String, Action)> {
+        let (function, arguments) = happened.handler();
+        if let Ok(mut state) = self.state.lock() {
+            state.reputation = reputation;
+            state.party = party;
+        }
+        for name in self.names() {
+            let script = scripts.get(self.scripts[name].1);
+            let Some(script) = script.filter(|script| script.handles(function, arguments.len())) else {
+                continue;
+            };
+            if let Ok(mut state) = self.state.lock() {
+                state.running = name.to_string();
+            }
+            // Only the handler runs; a script's top level never does
+            let options = CallFnOptions::new().eval_ast(false);
+            let ran = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                function,
+                arguments.clone(),
+            );
+            if let Err(e) = ran {
+                warn!("Script {}: {} failed: {}", name, function, e);
+            }
+        }
+        self.state.lock().map(|mut state| std::mem::take(&mut state.requests)).unwrap_or_default()
+    }
+}
+
+// What scripts have to do, done next with the run of the world
+#[derive(Resource, Default, Debug)]
+struct ScriptQueue(Vec<(String, Action)>);
+
+pub struct ScriptsPlugin;
+
+impl Plugin for ScriptsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Script>()
+            .register_asset_loader(ScriptLoader::default())
+            .init_resource::<ScriptBook>()
+            .init_resource::<ScriptQueue>()
+            .add_systems(Update, (sync_script_book, trigger_scripts, run_scripts).chain());
+    }
+}
+
+// Like prompts, a mod's script replaces the game's of the same name
+fn sync_script_book(
+    mut events: EventReader<AssetEvent<Script>>,
+    asset_server: Res<AssetServer>,
+    mod_list: Option<Res<ModList>>,
+    mut book: ResMut<ScriptBook>,
+) {
+    for event in events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        let Some(path) = asset_server.get_path(*id) else {
+            continue;
+        };
+        let layer = mod_list.as_ref().map_or(0, |mod_list| mod_list.layer(path.path()));
+        let Some(name) = Path::new(path.path()).file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+            continue;
+        };
+        if book.scripts.get(&name).is_none_or(|(existing, _)| layer >= *existing) {
+            book.scripts.insert(name, (layer, *id));
+        }
+    }
+}
+
+fn trigger_scripts(
+    mut events: EventReader<GameEvent>,
+    scripts: Res<Assets<Script>>,
+    book: Res<ScriptBook>,
+    quest_log: Res<QuestLog>,
+    party: Query<(&Character, &Combatant)>,
+    mut queue: ResMut<ScriptQueue>,
+) {
+    for event in events.read() {
+        let happened = match event {
+            GameEvent::RoomEntered { room, dungeon, .. } => {
+                Trigger::Room { room: room.clone(), dungeon: dungeon.clone() }
+            }
+            GameEvent::SiteEntered { site } => Trigger::Site(site.clone()),
+            GameEvent::TrapSprung { kind, victim } => {
+                Trigger::Trap { kind: kind.name().to_string(), victim: victim.clone() }
+            }
+            GameEvent::MonsterSlain { name } => Trigger::Kill(name.clone()),
+            GameEvent::QuestEnded { title, resolution: Resolution::Fulfilled } => Trigger::QuestComplete(title.clone()),
+            GameEvent::QuestEnded { title, .. } => Trigger::QuestFailed(title.clone()),
+            _ => continue,
+        };
+        let standing = party
+            .iter()
+            .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+            .map(|(character, _)| character.name.clone())
+            .collect();
+        let actions = book.fire(&happened, &scripts, quest_log.reputation, standing);
+        queue.0.extend(actions);
+    }
+}
+
+fn run_scripts(world: &mut World) {
+    let queue = std::mem::take(&mut world.resource_mut::<ScriptQueue>().0);
+    for (script, action) in queue {
+        match perform(world, &action) {
+            Ok(done) => info!("Script {}: {}", script, done),
+            Err(e) => warn!("Script {}: {}", script, e),
+        }
+    }
+}
+
+fn perform(world: &mut World, action: &Action) -> Result<String, String> {
+    match action {
+        Action::Say(text) => {
+            world.send_event(LogMessage { category: MessageCategory::Exploration, text: text.clone() });
+            Ok(text.clone())
+        }
+        Action::Spawn { monster, count } => {
+            let done = spawn_monsters(world, monster, *count)?;
+            if *world.resource::<State<GameState>>().get() == GameState::InGame {
+                world.resource_mut::<NextState<GameState>>().set(GameState::Combat);
+            }
+            Ok(done)
+        }
+        Action::Give { item, to } => give_item(world, item, to.as_deref()),
+        Action::Reputation(change) => {
+            let mut quest_log = world.resource_mut::<QuestLog>();
+            quest_log.reputation = quest_log.reputation.saturating_add(*change);
+            Ok(format!("Reputation {:+}, now {}", change, quest_log.reputation))
+        }
+        Action::Talk(npc_id) => {
+            world.resource_mut::<Summons>().0.push(npc_id.clone());
+            Ok(format!("{} sends for the party", npc_id))
+        }
+    }
+}
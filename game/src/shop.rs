@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::combat::Combatant;
+use crate::data::ItemCatalog;
+use crate::GameState;
+
+pub use old_school_core::shop::{buy_price, sell_price, Merchant, MerchantKind, StockEntry};
+
+// The market. Merchants are stocked from the item data the first time the party visits
+// and keep their stock and purse for the rest of the session, so what the party buys
+// stays bought. Tab moves between merchants, keys 1-9 buy from the one in front of
+// the party and Shift+1-9 sells them that item from the lead party member's pack.
+
+#[derive(Event, Clone, Debug)]
+pub enum TradeEvent {
+    Buy { character: Entity, merchant: usize, item: usize },
+    Sell { character: Entity, merchant: usize, item: usize },
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Market {
+    pub merchants: Vec<Merchant>,
+    pub selected: usize,
+}
+
+// The outcome of the last trade, for the shop screen
+#[derive(Resource, Default, Debug)]
+pub struct ShopMessage(pub Option<String>);
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TradeEvent>()
+            .init_resource::<Market>()
+            .init_resource::<ShopMessage>()
+            .add_systems(OnEnter(GameState::Shop), open_market)
+            .add_systems(Update, (
+                shop_input.run_if(in_state(GameState::Shop)),
+                handle_trade_events,
+            ).chain());
+    }
+}
+
+const DIGIT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+impl Market {
+    pub fn current(&self) -> Option<&Merchant> {
+        self.merchants.get(self.selected)
+    }
+}
+
+// Sorted by id so the merchants' stock comes out in the same order every run
+fn open_market(
+    mut market: ResMut<Market>,
+    mut message: ResMut<ShopMessage>,
+    catalog: Res<ItemCatalog>,
+) {
+    message.0 = None;
+    if !market.merchants.is_empty() {
+        return;
+    }
+    market.merchants = MerchantKind::ALL
+        .iter()
+        .map(|kind| Merchant::stocked(format!("The {}", kind.name()), *kind, catalog.iter()))
+        .collect();
+    market.selected = 0;
+}
+
+fn shop_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    party: Query<(Entity, &Combatant), With<Character>>,
+    mut market: ResMut<Market>,
+    mut trade_events: EventWriter<TradeEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Tab) && !market.merchants.is_empty() {
+        market.selected = (market.selected + 1) % market.merchants.len();
+    }
+    let Some(character) = party.iter().find(|(_, combatant)| combatant.is_player).map(|(entity, _)| entity) else {
+        return;
+    };
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let merchant = market.selected;
+
+    for (item, key) in DIGIT_KEYS.iter().enumerate() {
+        if !keyboard_input.just_pressed(*key) {
+            continue;
+        }
+        if shift {
+            trade_events.send(TradeEvent::Sell { character, merchant, item });
+        } else {
+            trade_events.send(TradeEvent::Buy { character, merchant, item });
+        }
+    }
+}
+
+fn handle_trade_events(
+    mut trade_events: EventReader<TradeEvent>,
+    mut characters: Query<&mut Character>,
+    mut market: ResMut<Market>,
+    mut message: ResMut<ShopMessage>,
+) {
+    for event in trade_events.read() {
+        let (TradeEvent::Buy { character, merchant, .. } | TradeEvent::Sell { character, merchant, .. }) = event;
+        let (Ok(mut character), Some(merchant)) = (characters.get_mut(*character), market.merchants.get_mut(*merchant))
+        else {
+            continue;
+        };
+
+        let result = match event {
+            TradeEvent::Buy { item, .. } => merchant.sell_to(*item, &mut character),
+            TradeEvent::Sell { item, .. } => merchant.buy_from(*item, &mut character),
+        };
+        let text = result.unwrap_or_else(|reason| reason);
+        info!("{} ({} gp left)", text, character.inventory.gold);
+        message.0 = Some(text);
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+
+use crate::ai_client::{NPCConversationResponseEvent, NPCData, NPCRoster};
+use crate::config::{save_settings, ConfigPath};
+use crate::narration::Narrated;
+use crate::{GameConfig, GameState};
+
+// Speech: NPCs' replies and the Dungeon Master's narration read aloud by the platform's
+// own text-to-speech (`say` on macOS, the System.Speech voices through PowerShell on
+// Windows, `espeak-ng` elsewhere). Each NPC gets a voice of their own from what they are
+// like: a woman's or a man's by how they are described, lower and slower for the old,
+// gruff and stern, higher and quicker for the young, nervous and excitable, and a
+// little different for each name besides. The narrator has a deep, unhurried voice.
+//
+// One line is spoken at a time; if lines come faster than they can be read, the oldest
+// waiting are dropped. Speech is off to begin with: `speech` in config.toml, or V on the
+// Settings screen, turns it on. There is none in headless runs, and if the program isn't
+// there the game gives up on speech for the session after saying so in the log.
+
+// Lines waiting to be spoken, at most
+const MAX_WAITING: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceGender {
+    Female,
+    Male,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Voice {
+    pub gender: VoiceGender,
+    // 0 to 99, 50 being an ordinary voice
+    pub pitch: u8,
+    // Words a minute
+    pub rate: u16,
+}
+
+impl Voice {
+    pub const NARRATOR: Voice = Voice {
+        gender: VoiceGender::Male,
+        pitch: 30,
+        rate: 150,
+    };
+
+    pub fn for_npc(npc: &NPCData) -> Self {
+        let description = format!("{} {}", npc.personality, npc.background).to_lowercase();
+        let words: Vec<&str> = description.split(|c: char| !c.is_alphabetic()).collect();
+        let any = |list: &[&str]| words.iter().any(|word| list.contains(word));
+
+        let gender = if any(&["she", "her", "woman", "lady", "girl", "wife", "mother", "widow", "queen", "priestess"]) {
+            VoiceGender::Female
+        } else {
+            VoiceGender::Male
+        };
+        // Up to 10 either side of the middle, the same every time for the same name
+        let spread = (npc.name.bytes().map(i32::from).sum::<i32>() % 21) - 10;
+        let mut pitch = 50 + spread;
+        let mut rate = 170;
+        if any(&["old", "elderly", "ancient", "aged", "grizzled", "gruff", "stern", "brooding", "weary"]) {
+            pitch -= 15;
+            rate -= 30;
+        }
+        if any(&["young", "child", "boy", "cheerful", "excitable", "nervous", "timid", "jittery"]) {
+            pitch += 15;
+            rate += 30;
+        }
+        if any(&["chatty", "talkative", "eager", "energetic", "hasty"]) {
+            rate += 25;
+        }
+        if any(&["calm", "wise", "slow", "deliberate", "lazy", "solemn"]) {
+            rate -= 20;
+        }
+        Voice {
+            gender,
+            pitch: pitch.clamp(0, 99) as u8,
+            rate: rate.clamp(90, 260) as u16,
+        }
+    }
+
+    // The platform's speech program, reading the text from its standard input
+    fn command(&self) -> Command {
+        if cfg!(target_os = "macos") {
+            let mut command = Command::new("say");
+            command.args(["-r", &self.rate.to_string(), "-f", "-"]);
+            command
+        } else if cfg!(target_os = "windows") {
+            let gender = match self.gender {
+                VoiceGender::Female => "Female",
+                VoiceGender::Male => "Male",
+            };
+            // System.Speech counts its rate from -10 to 10, 0 being about 170 words a minute
+            let rate = ((self.rate as i32 - 170) / 15).clamp(-10, 10);
+            let script = format!(
+                "Add-Type -AssemblyName System.Speech; \
+                 $voice = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+                 $voice.SelectVoiceByHints('{}'); $voice.Rate = {}; $voice.Speak([Console]::In.ReadToEnd())",
+                gender, rate
+            );
+            let mut command = Command::new("powershell");
+            command.args(["-NoProfile", "-Command", &script]);
+            command
+        } else {
+            let variant = match self.gender {
+                VoiceGender::Female => "en+f3",
+                VoiceGender::Male => "en+m3",
+            };
+            let mut command = Command::new("espeak-ng");
+            command.args(["-v", variant, "-p", &self.pitch.to_string(), "-s", &self.rate.to_string(), "--stdin"]);
+            command
+        }
+    }
+
+    fn speak(&self, text: &str) -> std::io::Result<Child> {
+        let mut child = self
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        // Dropping stdin closes it, which tells the program the text is all there
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(e) = stdin.write_all(text.as_bytes()) {
+                let _ = child.kill();
+                return Err(e);
+            }
+        }
+        Ok(child)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Speech {
+    waiting: VecDeque<(String, Voice)>,
+    speaking: Option<Child>,
+    // The speech program couldn't be started
+    unavailable: bool,
+}
+
+impl Speech {
+    pub fn say(&mut self, text: impl Into<String>, voice: Voice) {
+        self.waiting.push_back((text.into(), voice));
+        while self.waiting.len() > MAX_WAITING {
+            self.waiting.pop_front();
+        }
+    }
+
+    pub fn silence(&mut self) {
+        self.waiting.clear();
+        if let Some(mut child) = self.speaking.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+pub struct SpeechPlugin;
+
+impl Plugin for SpeechPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Speech>().add_systems(Update, (
+            toggle_speech.run_if(in_state(GameState::Settings)),
+            queue_speech,
+            speak,
+        ).chain());
+    }
+}
+
+// V on the Settings screen, saved to config.toml straight away
+fn toggle_speech(keyboard_input: Res<Input<KeyCode>>, path: Option<Res<ConfigPath>>, mut config: ResMut<GameConfig>) {
+    if keyboard_input.just_pressed(KeyCode::V) {
+        config.speech = !config.speech;
+        save_settings(&config, path.as_deref());
+    }
+}
+
+fn queue_speech(
+    config: Res<GameConfig>,
+    roster: Res<NPCRoster>,
+    mut speech: ResMut<Speech>,
+    mut replies: EventReader<NPCConversationResponseEvent>,
+    mut narrated: EventReader<Narrated>,
+) {
+    if !config.speech || config.headless || speech.unavailable {
+        replies.clear();
+        narrated.clear();
+        return;
+    }
+    for reply in replies.read() {
+        let voice = roster.npcs.get(&reply.npc_id).map_or(Voice::NARRATOR, Voice::for_npc);
+        speech.say(reply.response.npc_response.clone(), voice);
+    }
+    for Narrated(text) in narrated.read() {
+        speech.say(text.clone(), Voice::NARRATOR);
+    }
+}
+
+// Starts the next line once the last has finished, and stops at once if speech is
+// turned off
+fn speak(config: Res<GameConfig>, mut speech: ResMut<Speech>) {
+    if !config.speech {
+        if speech.speaking.is_some() || !speech.waiting.is_empty() {
+            speech.silence();
+        }
+        return;
+    }
+    let speech = speech.bypass_change_detection();
+    if let Some(child) = &mut speech.speaking {
+        match child.try_wait() {
+            Ok(None) => return,
+            Ok(Some(_)) | Err(_) => speech.speaking = None,
+        }
+    }
+    let Some((text, voice)) = speech.waiting.pop_front() else {
+        return;
+    };
+    match voice.speak(&text) {
+        Ok(child) => speech.speaking = Some(child),
+        Err(e) => {
+            warn!("Could not start text-to-speech, so there will be no speech this session: {}", e);
+            speech.unavailable = true;
+            speech.waiting.clear();
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+
+use crate::ailments::Ailment;
+use crate::character::{Character, RestEvent, Spell};
+use crate::combat::Combatant;
+use crate::config::KeyBindings;
+use crate::data::SpellCompendium;
+use crate::dialogue::Dialogue;
+use crate::GameState;
+use old_school_core::spells::SpellEffect;
+
+// The spellbook: the spells each caster in the party knows, by level, with what they do
+// and what is memorized today. B opens it from the dungeon or town; Tab goes to the
+// next caster, Up and Down pick a spell and B or ESC goes back.
+//
+// Spells are only chosen after a night's rest, so resting opens the spellbook to
+// prepare them. The slots come back filled as they were the day before (and any empty
+// ones with known spells in spellbook order, see Character::rest_and_memorize); Enter
+// memorizes the selected spell into a free slot of its level and Backspace forgets a
+// copy of it to free one. Leaving the book ends the preparation until the next rest.
+//
+// Outside a fight the only spells worth casting are the cures: C casts the selected
+// spell, if it's one and the caster has it memorized, on the first member of the party
+// with what it cures.
+
+#[derive(Resource, Debug)]
+pub struct Spellbook {
+    // Index into the party's casters
+    pub caster: usize,
+    // Index into that caster's spells, in spellbook order
+    pub selected: usize,
+    // Just after a rest, while today's spells can still be changed
+    pub preparing: bool,
+    pub message: Option<String>,
+    // Where the spellbook goes back to
+    pub return_to: GameState,
+}
+
+impl Default for Spellbook {
+    fn default() -> Self {
+        Self {
+            caster: 0,
+            selected: 0,
+            preparing: false,
+            message: None,
+            return_to: GameState::InGame,
+        }
+    }
+}
+
+pub struct SpellbookPlugin;
+
+impl Plugin for SpellbookPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Spellbook>().add_systems(Update, (
+            open_spellbook.run_if(in_state(GameState::InGame).or_else(in_state(GameState::Town))),
+            prepare_after_rest,
+            spellbook_input.run_if(in_state(GameState::Spellbook)),
+        ));
+    }
+}
+
+// The party's spellcasters, in a steady order
+pub fn party_casters<'a>(
+    characters: impl Iterator<Item = (Entity, &'a Character, &'a Combatant)>,
+) -> Vec<(Entity, &'a Character)> {
+    let mut casters: Vec<(Entity, &Character)> = characters
+        .filter(|(_, character, combatant)| combatant.is_player && character.class.spell_caster().is_some())
+        .map(|(entity, character, _)| (entity, character))
+        .collect();
+    casters.sort_by_key(|(entity, _)| *entity);
+    casters
+}
+
+// The spells a caster can cast from what they know, lowest level first
+pub fn spellbook_order(character: &Character) -> Vec<&Spell> {
+    let mut spells: Vec<&Spell> = character.spells.iter().filter(|spell| spell.castable_by(&character.class)).collect();
+    spells.sort_by_key(|spell| spell.level);
+    spells
+}
+
+fn open_book(book: &mut Spellbook, state: &State<GameState>, next_state: &mut NextState<GameState>, preparing: bool) {
+    book.return_to = state.get().clone();
+    book.selected = 0;
+    book.preparing = preparing;
+    book.message = None;
+    next_state.set(GameState::Spellbook);
+}
+
+fn open_spellbook(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    state: Res<State<GameState>>,
+    dialogue: Res<Dialogue>,
+    mut book: ResMut<Spellbook>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(keys.spellbook) && !dialogue.is_open() {
+        open_book(&mut book, &state, &mut next_state, false);
+    }
+}
+
+// A rest refills the slots; the spellbook opens for the casters to change them
+fn prepare_after_rest(
+    mut rest_events: EventReader<RestEvent>,
+    characters: Query<(Entity, &Character, &Combatant)>,
+    state: Res<State<GameState>>,
+    mut book: ResMut<Spellbook>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if rest_events.read().last().is_none() || *state.get() == GameState::Spellbook {
+        return;
+    }
+    let casters = party_casters(characters.iter());
+    if casters.iter().any(|(_, character)| character.is_alive()) {
+        open_book(&mut book, &state, &mut next_state, true);
+    }
+}
+
+fn spellbook_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut book: ResMut<Spellbook>,
+    mut characters: Query<(Entity, &mut Character, &mut Combatant)>,
+    compendium: Res<SpellCompendium>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.any_just_pressed([keys.spellbook, KeyCode::Escape]) {
+        book.preparing = false;
+        next_state.set(book.return_to.clone());
+        return;
+    }
+    let casters: Vec<Entity> = party_casters(characters.iter()).into_iter().map(|(entity, _)| entity).collect();
+    if casters.is_empty() {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        book.caster = (book.caster + 1) % casters.len();
+        book.selected = 0;
+        book.message = None;
+    }
+    book.caster = book.caster.min(casters.len() - 1);
+    if keyboard_input.just_pressed(KeyCode::C) {
+        book.message = Some(cast_cure(&mut characters, casters[book.caster], book.selected, &compendium));
+        return;
+    }
+    let Ok((_, mut character, _)) = characters.get_mut(casters[book.caster]) else {
+        return;
+    };
+    let names: Vec<String> = spellbook_order(&character).iter().map(|spell| spell.name.clone()).collect();
+    if names.is_empty() {
+        return;
+    }
+    book.selected = book.selected.min(names.len() - 1);
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        book.selected = (book.selected + names.len() - 1) % names.len();
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        book.selected = (book.selected + 1) % names.len();
+    }
+    let name = &names[book.selected];
+    let memorize = keyboard_input.just_pressed(KeyCode::Return);
+    let forget = keyboard_input.any_just_pressed([KeyCode::Back, KeyCode::Delete]);
+    if !memorize && !forget {
+        return;
+    }
+    if !book.preparing {
+        book.message = Some("Spells can only be changed after a night's rest".to_string());
+        return;
+    }
+    book.message = if memorize {
+        match character.memorize(name) {
+            Ok(()) => Some(format!("{} memorizes {}", character.name, name)),
+            Err(reason) => Some(reason),
+        }
+    } else if character.unmemorize(name) {
+        Some(format!("{} puts {} out of mind", character.name, name))
+    } else {
+        Some(format!("{} hasn't memorized {}", character.name, name))
+    };
+}
+
+// The caster's spell at `selected` in spellbook order, cast as a cure; says how it went
+fn cast_cure(
+    characters: &mut Query<(Entity, &mut Character, &mut Combatant)>,
+    caster: Entity,
+    selected: usize,
+    compendium: &SpellCompendium,
+) -> String {
+    let Ok((_, character, _)) = characters.get(caster) else {
+        return String::new();
+    };
+    let Some(name) = spellbook_order(character).get(selected).map(|spell| spell.name.clone()) else {
+        return String::new();
+    };
+    let cures: Option<Ailment> = compendium.descriptor(&name).and_then(|descriptor| match descriptor.effect {
+        SpellEffect::Cure { cures } => Some(cures),
+        _ => None,
+    });
+    let Some(cures) = cures else {
+        return format!("{} is for casting in a fight", name);
+    };
+    if !character.is_alive() || character.is_recovering() {
+        return format!("{} is in no state to cast", character.name);
+    }
+    let patient = characters
+        .iter()
+        .find(|(_, character, combatant)| {
+            combatant.is_player
+                && character.is_alive()
+                && combatant.status_effects.iter().any(|effect| cures.afflicts(effect))
+        })
+        .map(|(entity, _, _)| entity);
+    let Some(patient) = patient else {
+        return format!("Nobody in the party has any {} to cure", cures.name());
+    };
+
+    let Ok((_, mut character, _)) = characters.get_mut(caster) else {
+        return String::new();
+    };
+    if let Err(reason) = character.cast_spell(&name) {
+        return reason;
+    }
+    let caster_name = character.name.clone();
+    let Ok((_, patient, mut combatant)) = characters.get_mut(patient) else {
+        return String::new();
+    };
+    cures.cure(&mut combatant.status_effects);
+    format!("{} casts {} and {} is cured of {}", caster_name, name, patient.name, cures.name())
+}
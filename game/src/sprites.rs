@@ -0,0 +1,176 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::ai_client::CurrentDungeon;
+use crate::character::{Character, CharacterClass, Item, ItemType};
+use crate::combat::Combatant;
+use crate::data::{GameData, SpriteManifest};
+use crate::dungeon::{PartyToken, PARTY_COLOR};
+use crate::encounters::{Monster, MonsterToken, MONSTER_COLOR};
+
+// Sprites: pictures for the party, the monsters and the items, named in the manifest at
+// assets/data/core.sprites.json. It maps each class's name, each monster's name or type
+// (`goblin`, or `humanoid` for any humanoid without a picture of its own) and each
+// item's name, weapon type or kind (`iron rations`, `sword`, `armor`) to an image under
+// assets/. The party's token in the dungeon shows the leader's class and a waiting
+// encounter's token its first monster; the combat screen lines up everyone in the fight
+// and inventory cells show the item's icon. The game comes with plain lettered tiles for
+// every entry, to be drawn over; anything whose picture isn't there is drawn as before,
+// as a coloured square or a name alone.
+// The manifest reloads like the other data files, and with it the pictures.
+
+// The manifest's images that are there to load, by lower case key
+#[derive(Resource, Default, Debug)]
+pub struct SpriteImages {
+    classes: HashMap<String, Handle<Image>>,
+    monsters: HashMap<String, Handle<Image>>,
+    items: HashMap<String, Handle<Image>>,
+}
+
+impl SpriteImages {
+    pub fn class(&self, class: &CharacterClass) -> Option<Handle<Image>> {
+        self.classes.get(&class.name().to_lowercase()).cloned()
+    }
+
+    // By the monster's own name first, then its type
+    pub fn monster(&self, name: &str, monster_type: Option<&str>) -> Option<Handle<Image>> {
+        self.monsters
+            .get(&name.to_lowercase())
+            .or_else(|| monster_type.and_then(|monster_type| self.monsters.get(&monster_type.to_lowercase())))
+            .cloned()
+    }
+
+    // Party members by class, monsters by name and type
+    pub fn combatant(
+        &self,
+        character: &Character,
+        combatant: &Combatant,
+        monster: Option<&Monster>,
+    ) -> Option<Handle<Image>> {
+        if combatant.is_player {
+            self.class(&character.class)
+        } else {
+            self.monster(&character.name, monster.map(|monster| monster.0.monster_type.as_str()))
+        }
+    }
+
+    // By the item's name, then for weapons the kind of weapon, then the kind of item
+    pub fn item(&self, item: &Item) -> Option<Handle<Image>> {
+        let weapon = match &item.item_type {
+            ItemType::Weapon(weapon) => Some(format!("{:?}", weapon).to_lowercase()),
+            _ => None,
+        };
+        [Some(item.name.to_lowercase()), weapon, item_kind(&item.item_type).map(str::to_string)]
+            .into_iter()
+            .flatten()
+            .find_map(|key| self.items.get(&key).cloned())
+    }
+}
+
+fn item_kind(item_type: &ItemType) -> Option<&'static str> {
+    Some(match item_type {
+        ItemType::Weapon(_) => "weapon",
+        ItemType::Armor(_) => "armor",
+        ItemType::Shield => "shield",
+        ItemType::Helmet => "helmet",
+        ItemType::Ring => "ring",
+        ItemType::Cloak => "cloak",
+        ItemType::Boots => "boots",
+        ItemType::Potion => "potion",
+        ItemType::Scroll => "scroll",
+        ItemType::Treasure => "treasure",
+        ItemType::Misc => return None,
+    })
+}
+
+pub struct SpritesPlugin;
+
+impl Plugin for SpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpriteImages>()
+            .add_systems(Update, (load_sprite_images, dress_party_token, dress_monster_tokens).chain());
+    }
+}
+
+// Only the images that are there are loaded, so a manifest naming pictures nobody has
+// drawn yet costs nothing
+fn load_sprite_images(
+    mut events: EventReader<AssetEvent<SpriteManifest>>,
+    manifests: Res<Assets<SpriteManifest>>,
+    data: Res<GameData>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<SpriteImages>,
+) {
+    let loaded = events.read().any(|event| match event {
+        AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == data.sprites.id(),
+        _ => false,
+    });
+    let Some(manifest) = manifests.get(&data.sprites).filter(|_| loaded) else {
+        return;
+    };
+    let base = FileAssetReader::get_base_path().join("assets");
+    let load = |paths: &HashMap<String, String>| -> HashMap<String, Handle<Image>> {
+        paths
+            .iter()
+            .filter(|(_, path)| base.join(path).is_file())
+            .map(|(key, path)| (key.to_lowercase(), asset_server.load(path.clone())))
+            .collect()
+    };
+    *images = SpriteImages {
+        classes: load(&manifest.classes),
+        monsters: load(&manifest.monsters),
+        items: load(&manifest.items),
+    };
+    let count = images.classes.len() + images.monsters.len() + images.items.len();
+    info!("{} of the sprite manifest's pictures are there to draw", count);
+}
+
+// Pictures are drawn as they are, so the tint goes back to white; without one the
+// token goes back to the plain square and its colour
+fn dress(sprite: &mut Sprite, texture: &mut Handle<Image>, picture: Option<Handle<Image>>, color: Color) {
+    let (picture, color) = match picture {
+        Some(picture) => (picture, Color::WHITE),
+        None => (Handle::default(), color),
+    };
+    if *texture != picture {
+        *texture = picture;
+    }
+    if sprite.color != color {
+        sprite.color = color;
+    }
+}
+
+fn dress_party_token(
+    images: Res<SpriteImages>,
+    party: Query<(&Character, &Combatant)>,
+    changed: Query<(), Changed<Character>>,
+    mut tokens: Query<(Ref<PartyToken>, &mut Sprite, &mut Handle<Image>)>,
+) {
+    for (token, mut sprite, mut texture) in tokens.iter_mut() {
+        if !token.is_added() && !images.is_changed() && changed.is_empty() {
+            continue;
+        }
+        let leader = party.iter().find(|(_, combatant)| combatant.is_player).map(|(character, _)| character);
+        let picture = leader.and_then(|leader| images.class(&leader.class));
+        dress(&mut sprite, &mut texture, picture, PARTY_COLOR);
+    }
+}
+
+fn dress_monster_tokens(
+    images: Res<SpriteImages>,
+    dungeon: Res<CurrentDungeon>,
+    mut tokens: Query<(Ref<MonsterToken>, &mut Sprite, &mut Handle<Image>)>,
+) {
+    let Some(data) = dungeon.0.as_ref() else {
+        return;
+    };
+    for (token, mut sprite, mut texture) in tokens.iter_mut() {
+        if !token.is_added() && !images.is_changed() {
+            continue;
+        }
+        let first = data.encounters.get(token.encounter).and_then(|encounter| encounter.enemies.first());
+        let picture = first.and_then(|enemy| images.monster(&enemy.name, Some(&enemy.monster_type)));
+        dress(&mut sprite, &mut texture, picture, MONSTER_COLOR);
+    }
+}
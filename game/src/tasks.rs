@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::AbortHandle;
+
+// reqwest futures need a Tokio reactor, which Bevy doesn't provide. Network work is
+// spawned onto this runtime and the join handle is awaited from the AsyncComputeTaskPool,
+// so systems get an ordinary Bevy `Task` they can poll without blocking the frame.
+#[derive(Resource, Clone)]
+pub struct AsyncRuntime {
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+impl AsyncRuntime {
+    pub fn new() -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("network")
+            .enable_all()
+            .build()
+            .expect("failed to start the network runtime");
+
+        Self {
+            runtime: Arc::new(runtime),
+        }
+    }
+
+    // Resolves to None if the spawned future panicked
+    pub fn spawn<F>(&self, future: F) -> Task<Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = self.runtime.spawn(future);
+        AsyncComputeTaskPool::get().spawn(async move { handle.await.ok() })
+    }
+
+    // Like spawn, with a handle that stops the work on the runtime; a cancelled task
+    // resolves to None
+    pub fn spawn_cancellable<F>(&self, future: F) -> (Task<Option<F::Output>>, AbortHandle)
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = self.runtime.spawn(future);
+        let abort = handle.abort_handle();
+        (AsyncComputeTaskPool::get().spawn(async move { handle.await.ok() }), abort)
+    }
+}
+
+impl Default for AsyncRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Non-blocking check on a task; returns its output once it has finished
+pub fn poll_task<T>(task: &mut Task<T>) -> Option<T> {
+    if !task.is_finished() {
+        return None;
+    }
+    Some(block_on(task))
+}
+
+pub struct AsyncRuntimePlugin;
+
+impl Plugin for AsyncRuntimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AsyncRuntime>();
+    }
+}
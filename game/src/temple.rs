@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::ailments::blocks_healing;
+use crate::character::Character;
+use crate::clock::GameClock;
+use crate::combat::Combatant;
+use crate::death::TEMPLE_CASTER_LEVEL;
+use crate::inn::pay_from_party;
+use crate::rng::GameRng;
+use crate::town::{Location, Town, TownInputSet, TownMessage};
+use crate::GameState;
+
+pub use old_school_core::temple::{TempleFavor, TempleService, DONATION_PER_STANDING};
+
+// The temple (see old_school_core::temple for the services and what they cost). Up and
+// Down pick which member of the party the priests see to, 1-5 buy a service for them
+// from the party's purses, D gives the temple 100 gp towards the party's standing and G
+// has a dead member buried in the temple grounds, once Y confirms it: the body and
+// everything on it are gone for good.
+
+// Whom the priests are seeing to, by index among the party's members, and the dead
+// member waiting on Y to be buried
+#[derive(Resource, Default, Debug)]
+pub struct TempleVisit {
+    pub patient: usize,
+    pub burial: Option<Entity>,
+}
+
+pub struct TemplePlugin;
+
+impl Plugin for TemplePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TempleFavor>()
+            .init_resource::<TempleVisit>()
+            .add_systems(Update, temple_input.before(TownInputSet).run_if(in_state(GameState::Town)));
+    }
+}
+
+const SERVICE_KEYS: [KeyCode; 5] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+
+// The service list for the town screen
+pub fn describe_services(patient: &Character, favor: &TempleFavor) -> Vec<String> {
+    TempleService::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, service)| {
+            let terms = if favor.grants(*service) {
+                format!("{} gp", service.price(patient))
+            } else {
+                format!("needs standing {}", service.standing_required())
+            };
+            format!("{}. {} ({})", index + 1, service.name(), terms)
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn temple_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    town: Res<Town>,
+    clock: Res<GameClock>,
+    mut visit: ResMut<TempleVisit>,
+    mut favor: ResMut<TempleFavor>,
+    mut party: Query<(Entity, &mut Character, &mut Combatant)>,
+    mut message: ResMut<TownMessage>,
+    mut rng: ResMut<GameRng>,
+) {
+    if town.location != Some(Location::Temple) {
+        return;
+    }
+    let mut members: Vec<(Entity, Mut<Character>, Mut<Combatant>)> =
+        party.iter_mut().filter(|(_, _, combatant)| combatant.is_player).collect();
+    if members.is_empty() {
+        return;
+    }
+    visit.patient = visit.patient.min(members.len() - 1);
+
+    // Any key but Y keeps the body with the party
+    if let Some(burial) = visit.burial.take() {
+        let Some((entity, dead, _)) = members.iter().find(|(entity, _, _)| *entity == burial) else {
+            return;
+        };
+        if keyboard_input.just_pressed(KeyCode::Y) {
+            commands.entity(*entity).despawn_recursive();
+            message.0 = Some(format!("{} is laid to rest in the temple grounds, with all they carried", dead.name));
+        } else if keyboard_input.get_just_pressed().next().is_some() {
+            message.0 = Some(format!("{} stays with the party", dead.name));
+        } else {
+            visit.burial = Some(burial);
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        visit.patient = (visit.patient + members.len() - 1) % members.len();
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        visit.patient = (visit.patient + 1) % members.len();
+    } else if keyboard_input.just_pressed(KeyCode::G) {
+        let (entity, patient, _) = &members[visit.patient];
+        if patient.is_alive() {
+            message.0 = Some(format!("{} isn't ready for the grave yet", patient.name));
+            return;
+        }
+        visit.burial = Some(*entity);
+        message.0 = Some(format!(
+            "Lay {} to rest, with all they carried? Y to bury them, any other key not to",
+            patient.name
+        ));
+    } else if keyboard_input.just_pressed(KeyCode::D) {
+        let mut purses: Vec<&mut Character> =
+            members.iter_mut().map(|(_, character, _)| character.as_mut()).collect();
+        match pay_from_party(&mut purses, DONATION_PER_STANDING) {
+            Ok(()) => {
+                favor.donate(DONATION_PER_STANDING);
+                message.0 = Some(format!(
+                    "Father Aldous blesses the party's generosity (standing {})",
+                    favor.standing()
+                ));
+            }
+            Err(reason) => message.0 = Some(format!("The party can't spare it: {}", reason)),
+        }
+    } else if let Some(index) = SERVICE_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) {
+        let service = TempleService::ALL[index];
+        let (_, patient, effects) = &members[visit.patient];
+        if !favor.grants(service) {
+            message.0 = Some(format!(
+                "{} is only for friends of the temple (standing {}, the party has {})",
+                service.name(),
+                service.standing_required(),
+                favor.standing()
+            ));
+            return;
+        }
+        if !service.is_needed(patient, &effects.status_effects) {
+            message.0 = Some(format!("{} has no need of {}", patient.name, service.name().to_lowercase()));
+            return;
+        }
+        if service == TempleService::RaiseDead {
+            if let Some(reason) = patient.raise_refusal(TEMPLE_CASTER_LEVEL, clock.day()) {
+                message.0 = Some(reason);
+                return;
+            }
+        }
+        if service == TempleService::CureWounds && blocks_healing(&effects.status_effects) {
+            message.0 = Some(format!("No prayer closes wounds the rot is in: cure {}'s disease first", patient.name));
+            return;
+        }
+        let price = service.price(patient);
+        let mut purses: Vec<&mut Character> =
+            members.iter_mut().map(|(_, character, _)| character.as_mut()).collect();
+        if let Err(reason) = pay_from_party(&mut purses, price) {
+            message.0 = Some(format!("The priests ask {} gp: {}", price, reason));
+            return;
+        }
+        let (entity, patient, effects) = &mut members[visit.patient];
+        let text = service.perform(patient, &mut effects.status_effects, rng.as_mut());
+        // Failed the survival roll
+        if service == TempleService::RaiseDead && !patient.is_alive() {
+            commands.entity(*entity).despawn_recursive();
+        }
+        info!("{} ({} gp)", text, price);
+        message.0 = Some(format!("{} ({} gp)", text, price));
+    }
+}
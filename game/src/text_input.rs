@@ -0,0 +1,198 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::console::{capture_console_input, DebugConsole};
+
+// Typing into the game: a character's name, a reply to an NPC, a name for a save. A
+// TextField holds the text and a cursor; the system that owns one calls `edit` every
+// frame it has focus, and while any field does the keyboard is the field's, so the
+// letters typed don't also move the party or pick a class. Left/Right, Home and End
+// move the cursor, Backspace and Delete take out a character, Enter submits and ESC
+// cancels. Focus starts the frame after a field first asks for it, so the key that
+// opened the field isn't typed into it.
+
+const EDIT_KEYS: [KeyCode; 8] = [
+    KeyCode::Back,
+    KeyCode::Delete,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Return,
+    KeyCode::Escape,
+];
+
+// What a field lets through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharFilter {
+    // Letters, spaces, hyphens and apostrophes: "Mira Ash-Hand", "D'Arc"
+    Name,
+    // Anything printable
+    Speech,
+    // Letters, digits, hyphens and underscores, safe in a file name
+    FileName,
+}
+
+impl CharFilter {
+    pub fn accepts(self, c: char) -> bool {
+        match self {
+            CharFilter::Name => c.is_alphabetic() || matches!(c, ' ' | '-' | '\''),
+            // The console key, which reaches the field as the console closes
+            CharFilter::Speech => !c.is_control() && c != '`',
+            CharFilter::FileName => c.is_ascii_alphanumeric() || matches!(c, '-' | '_'),
+        }
+    }
+
+    pub fn max_chars(self) -> usize {
+        match self {
+            CharFilter::Name => 24,
+            CharFilter::Speech => 200,
+            CharFilter::FileName => 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldAction {
+    Submit,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextField {
+    text: String,
+    // In characters, not bytes
+    cursor: usize,
+    filter: CharFilter,
+}
+
+impl TextField {
+    pub fn new(filter: CharFilter) -> Self {
+        Self {
+            text: String::new(),
+            cursor: 0,
+            filter,
+        }
+    }
+
+    // Starts out holding `text`, with the cursor at the end
+    pub fn with_text(filter: CharFilter, text: &str) -> Self {
+        let mut field = Self::new(filter);
+        for c in text.chars() {
+            field.insert(c);
+        }
+        field
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text.char_indices().nth(cursor).map_or(self.text.len(), |(index, _)| index)
+    }
+
+    // False if the filter turns it away or the field is full
+    pub fn insert(&mut self, c: char) -> bool {
+        if !self.filter.accepts(c) || self.text.chars().count() >= self.filter.max_chars() {
+            return false;
+        }
+        let index = self.byte_index(self.cursor);
+        self.text.insert(index, c);
+        self.cursor += 1;
+        true
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete();
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let index = self.byte_index(self.cursor);
+            self.text.remove(index);
+        }
+    }
+
+    pub fn press(&mut self, key: KeyCode) -> Option<FieldAction> {
+        match key {
+            KeyCode::Back => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.text.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.chars().count(),
+            KeyCode::Return => return Some(FieldAction::Submit),
+            KeyCode::Escape => return Some(FieldAction::Cancel),
+            _ => {}
+        }
+        None
+    }
+
+    // Takes what was typed this frame, and keeps the keyboard for the next one
+    pub fn edit(&mut self, typing: &mut Typing) -> Option<FieldAction> {
+        typing.wanted = true;
+        for &c in &typing.chars {
+            self.insert(c);
+        }
+        typing.keys.iter().find_map(|&key| self.press(key))
+    }
+
+    // The text with the cursor in it, for showing on screen
+    pub fn display(&self) -> String {
+        let mut shown = self.text.clone();
+        shown.insert(self.byte_index(self.cursor), '|');
+        shown
+    }
+}
+
+// What was typed this frame, for whichever field has focus
+#[derive(Resource, Default, Debug)]
+pub struct Typing {
+    // A field asked for the keyboard since the last frame's input was read
+    wanted: bool,
+    chars: Vec<char>,
+    keys: Vec<KeyCode>,
+}
+
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Typing>()
+            .add_systems(PreUpdate, capture_typing.after(InputSystem).after(capture_console_input));
+    }
+}
+
+fn capture_typing(
+    mut typing: ResMut<Typing>,
+    console: Option<Res<DebugConsole>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+) {
+    typing.chars.clear();
+    typing.keys.clear();
+    let focused = std::mem::take(&mut typing.wanted);
+    if !focused || console.is_some_and(|console| console.open) {
+        characters.clear();
+        return;
+    }
+
+    typing.chars.extend(characters.read().map(|event| event.char).filter(|c| !c.is_control()));
+    typing.keys.extend(EDIT_KEYS.into_iter().filter(|key| keyboard_input.just_pressed(*key)));
+    // The game doesn't see keys typed into a field
+    keyboard_input.reset_all();
+}
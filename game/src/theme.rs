@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+// The look of the interface: the typefaces, the sizes text comes in and the palette.
+// Every screen takes its text styles and its common colours from the UiTheme resource
+// instead of spelling them out, so the whole look changes from here. Text is set in
+// DejaVu Serif, for a book-ish, old-fashioned feel, and anything laid out in columns
+// (the maps, the debug console) in DejaVu Sans Mono; both are bundled in assets/fonts
+// with their licence. The tints some screens have of their own, the shop's brown or the
+// spellbook's blue, stay with those screens.
+
+pub const FONT: &str = "fonts/DejaVuSerif.ttf";
+pub const MONO_FONT: &str = "fonts/DejaVuSansMono.ttf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSize {
+    // The game's name on the main menu
+    Title,
+    // Each screen's name
+    Heading,
+    Subheading,
+    Large,
+    Body,
+    Small,
+    Fine,
+    // Labels in the corners of inventory cells
+    Tiny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSizes {
+    pub title: f32,
+    pub heading: f32,
+    pub subheading: f32,
+    pub large: f32,
+    pub body: f32,
+    pub small: f32,
+    pub fine: f32,
+    pub tiny: f32,
+}
+
+impl Default for TextSizes {
+    fn default() -> Self {
+        TextSizes {
+            title: 48.0,
+            heading: 36.0,
+            subheading: 28.0,
+            large: 24.0,
+            body: 18.0,
+            small: 16.0,
+            fine: 14.0,
+            tiny: 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    // Text, from brightest to faintest
+    pub text: Color,
+    pub soft: Color,
+    pub muted: Color,
+    pub dim: Color,
+    pub faint: Color,
+    pub disabled: Color,
+    // Titles and things worth a second look
+    pub gold: Color,
+    // Whatever is selected, and what to do next
+    pub highlight: Color,
+    pub parchment: Color,
+    pub arcane: Color,
+    pub info: Color,
+    pub good: Color,
+    pub warning: Color,
+    pub danger: Color,
+    pub hostile: Color,
+    // Behind a whole screen, behind a panel on it, and over the game
+    pub background: Color,
+    pub panel: Color,
+    pub overlay: Color,
+    pub frame: Color,
+    pub button: Color,
+    pub button_selected: Color,
+    pub button_pressed: Color,
+    pub button_disabled: Color,
+    pub cell_empty: Color,
+    pub cell_filled: Color,
+    pub cell_hovered: Color,
+    // The cell an item is being dragged from, and the one it would be dropped on
+    pub cell_dragged: Color,
+    pub cell_target: Color,
+    pub cell_border: Color,
+    // An empty paper doll slot, fainter than a full one
+    pub slot_empty_border: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            text: Color::rgb(0.9, 0.9, 0.9),
+            soft: Color::rgb(0.8, 0.8, 0.8),
+            muted: Color::rgb(0.7, 0.7, 0.7),
+            dim: Color::rgb(0.6, 0.6, 0.6),
+            faint: Color::rgb(0.5, 0.5, 0.5),
+            disabled: Color::rgb(0.4, 0.4, 0.4),
+            gold: Color::rgb(1.0, 0.9, 0.6),
+            highlight: Color::rgb(0.9, 0.8, 0.3),
+            parchment: Color::rgb(0.9, 0.85, 0.7),
+            arcane: Color::rgb(0.75, 0.75, 0.95),
+            info: Color::rgb(0.6, 0.8, 0.9),
+            good: Color::rgb(0.4, 0.9, 0.4),
+            warning: Color::rgb(0.9, 0.6, 0.3),
+            danger: Color::rgb(0.9, 0.3, 0.3),
+            hostile: Color::rgb(0.95, 0.55, 0.45),
+            background: Color::rgb(0.1, 0.1, 0.2),
+            panel: Color::rgb(0.2, 0.2, 0.3),
+            overlay: Color::rgba(0.05, 0.05, 0.08, 0.92),
+            frame: Color::rgb(0.65, 0.58, 0.32),
+            button: Color::rgb(0.2, 0.2, 0.3),
+            button_selected: Color::rgb(0.35, 0.3, 0.55),
+            button_pressed: Color::rgb(0.55, 0.45, 0.2),
+            button_disabled: Color::rgb(0.14, 0.14, 0.18),
+            cell_empty: Color::rgb(0.12, 0.12, 0.18),
+            cell_filled: Color::rgb(0.2, 0.2, 0.28),
+            cell_hovered: Color::rgb(0.3, 0.3, 0.42),
+            cell_dragged: Color::rgb(0.35, 0.3, 0.15),
+            cell_target: Color::rgb(0.25, 0.4, 0.25),
+            cell_border: Color::rgb(0.35, 0.35, 0.45),
+            slot_empty_border: Color::rgb(0.22, 0.22, 0.28),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct UiTheme {
+    pub font: Handle<Font>,
+    pub mono_font: Handle<Font>,
+    pub sizes: TextSizes,
+    pub palette: Palette,
+}
+
+impl UiTheme {
+    pub fn size(&self, size: TextSize) -> f32 {
+        let sizes = &self.sizes;
+        match size {
+            TextSize::Title => sizes.title,
+            TextSize::Heading => sizes.heading,
+            TextSize::Subheading => sizes.subheading,
+            TextSize::Large => sizes.large,
+            TextSize::Body => sizes.body,
+            TextSize::Small => sizes.small,
+            TextSize::Fine => sizes.fine,
+            TextSize::Tiny => sizes.tiny,
+        }
+    }
+
+    pub fn text(&self, size: TextSize, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: self.size(size),
+            color,
+        }
+    }
+
+    // For text that lines up in columns
+    pub fn mono(&self, size: TextSize, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.mono_font.clone(),
+            ..self.text(size, color)
+        }
+    }
+}
+
+// Without an asset server the handles are left at the default, which is Bevy's own font
+impl FromWorld for UiTheme {
+    fn from_world(world: &mut World) -> Self {
+        let (font, mono_font) = match world.get_resource::<AssetServer>() {
+            Some(assets) => (assets.load(FONT), assets.load(MONO_FONT)),
+            None => Default::default(),
+        };
+        UiTheme {
+            font,
+            mono_font,
+            sizes: TextSizes::default(),
+            palette: Palette::default(),
+        }
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UiTheme>();
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::ailments::Disease;
+use crate::character::{Character, CharacterClass, Item, ItemType, Spell};
+use crate::combat::{EffectType, StatusEffect, WHOLE_FIGHT};
+use crate::data::SpellCompendium;
+use crate::theme::{TextSize, UiTheme};
+
+// Tooltips: any UI node with a Tooltip and an Interaction shows the tooltip's text in a
+// panel by the mouse cursor while the cursor is over it, unless the text is empty. The inventory's slots, the
+// conditions on the combat screen and the Cast Spell button carry them; the text for
+// an item, a spell or a status effect comes from the functions below.
+
+// Distance from the cursor to the panel's corner
+const CURSOR_OFFSET: f32 = 16.0;
+const MAX_WIDTH: f32 = 320.0;
+
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Tooltip(pub String);
+
+#[derive(Component)]
+struct TooltipPanel;
+
+#[derive(Component)]
+struct TooltipText;
+
+pub struct TooltipPlugin;
+
+impl Plugin for TooltipPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_tooltip_panel).add_systems(Update, show_tooltip);
+    }
+}
+
+// Name, damage or armour class, weight and value (of the whole stack, and of one), what
+// magic is known and who can't use it
+pub fn item_tooltip(item: &Item) -> String {
+    let magic = item.magic_bonus();
+    let known_bonus = if item.properties.identified && magic != 0 { magic } else { 0 };
+    let mut lines = vec![if known_bonus != 0 { format!("{} {:+}", item.name, known_bonus) } else { item.name.clone() }];
+    if item.is_stack() {
+        lines[0].push_str(&format!(" x{}", item.quantity));
+    }
+
+    if let Some(damage) = &item.properties.damage {
+        let bonus = if known_bonus != 0 { format!("{:+}", known_bonus) } else { String::new() };
+        lines.push(format!("Damage {}{}", damage, bonus));
+    }
+    // Rings, cloaks and boots protect only through their magic, shown once it is known
+    let protective = matches!(item.item_type, ItemType::Armor(_) | ItemType::Shield | ItemType::Helmet);
+    let worn = matches!(item.item_type, ItemType::Ring | ItemType::Cloak | ItemType::Boots);
+    if protective || worn && known_bonus != 0 {
+        let armor = item.armor_class_bonus() - magic + known_bonus;
+        lines.push(format!("AC {:+}", armor));
+    }
+    lines.push(format!("{} lb, worth {} gp", item.total_weight(), item.total_value()));
+    if item.max_stack() > 1 {
+        lines.push(format!(
+            "{} of up to {} in the stack, {} lb and {} gp each",
+            item.quantity,
+            item.max_stack(),
+            item.weight,
+            item.value
+        ));
+    }
+    if let Some(container) = &item.container {
+        lines.push(format!("Holds {} of {} lb", container.load(), container.capacity));
+    }
+    if let Some(uses) = item.uses {
+        lines.push(format!("{} uses left", uses));
+    }
+
+    if item.is_magic() {
+        if item.properties.identified {
+            if magic != 0 {
+                lines.push(format!("Magic {:+}{}", magic, if item.is_cursed() { ", cursed" } else { "" }));
+            }
+            for effect in &item.properties.effects {
+                lines.push(format!("Magic: {}", effect.replace(':', " ")));
+            }
+        } else {
+            lines.push("Magical, not yet identified".to_string());
+        }
+    }
+
+    if item.slot().is_some() {
+        let barred: Vec<&str> = CharacterClass::ALL
+            .iter()
+            .filter(|class| class.can_use(item).is_err())
+            .map(|class| class.name())
+            .collect();
+        if !barred.is_empty() {
+            lines.push(format!("Not for a {}", barred.join(", ")));
+        }
+    }
+    lines.join("\n")
+}
+
+// How `character` would get on with `item`, under the item's own tooltip
+pub fn item_tooltip_for(item: &Item, character: &Character) -> String {
+    let mut text = item_tooltip(item);
+    if item.slot().is_some() {
+        if let Err(reason) = character.class.can_use(item) {
+            text.push('\n');
+            text.push_str(&reason);
+        }
+    }
+    text
+}
+
+// With what it does in a fight, if the compendium says
+pub fn spell_tooltip(spell: &Spell, compendium: &SpellCompendium) -> String {
+    let mut text = format!(
+        "{} (level {} {:?})\n{}\nRange {}   Duration {}   Casting time {}",
+        spell.name, spell.level, spell.school, spell.description, spell.range, spell.duration, spell.casting_time
+    );
+    if let Some(descriptor) = compendium.descriptor(&spell.name) {
+        text.push('\n');
+        text.push_str(&descriptor.describe());
+    }
+    text
+}
+
+// A status effect as the combat lineup shows it: a letter for what it does and the rounds
+// it has left, if it runs by the round
+pub fn status_icon(effect: &StatusEffect) -> String {
+    let letter = match effect.effect_type {
+        EffectType::Damage => '!',
+        EffectType::Healing => '+',
+        EffectType::StatModifier if effect.magnitude >= 0 => '^',
+        EffectType::StatModifier => 'v',
+        EffectType::Stun => 'z',
+        EffectType::Poison => 'p',
+        EffectType::ArmorBonus => 'a',
+        EffectType::Disease => 'd',
+    };
+    match effect.effect_type {
+        EffectType::Disease => letter.to_string(),
+        _ if effect.duration == WHOLE_FIGHT => letter.to_string(),
+        _ => format!("{}{}", letter, effect.duration),
+    }
+}
+
+pub fn status_tooltip(effect: &StatusEffect) -> String {
+    let what = match effect.effect_type {
+        EffectType::Damage => format!("{} damage a round", effect.magnitude),
+        EffectType::Healing => format!("Heals {} a round", effect.magnitude),
+        EffectType::StatModifier => format!("{:+} to hit", effect.magnitude),
+        EffectType::Stun => "Can't act".to_string(),
+        EffectType::Poison => format!("Poisoned, {} damage a round", effect.magnitude),
+        EffectType::ArmorBonus => format!("AC {:+}", effect.magnitude),
+        EffectType::Disease => match Disease::of(effect) {
+            Some(disease) => disease.describe(effect),
+            None => "Diseased until cured".to_string(),
+        },
+    };
+    let lasts = match effect.effect_type {
+        EffectType::Disease => String::new(),
+        _ if effect.duration == WHOLE_FIGHT => "\nLasts the fight".to_string(),
+        _ if effect.duration == 1 => "\n1 round left".to_string(),
+        _ => format!("\n{} rounds left", effect.duration),
+    };
+    format!("{}\n{}{}", effect.name, what, lasts)
+}
+
+fn spawn_tooltip_panel(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    max_width: Val::Px(MAX_WIDTH),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    border: UiRect::all(Val::Px(1.0)),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: theme.palette.overlay.into(),
+                border_color: theme.palette.frame.into(),
+                // Over every screen's own nodes
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            TooltipPanel,
+        ))
+        .with_children(|parent| {
+            let style = theme.text(TextSize::Small, theme.palette.parchment);
+            parent.spawn((TextBundle::from_section("", style), TooltipText));
+        });
+}
+
+// The panel opens toward the middle of the window, so it stays on screen near the edges
+fn show_tooltip(
+    hovered: Query<(&Interaction, &Tooltip)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut panels: Query<&mut Style, With<TooltipPanel>>,
+    mut texts: Query<&mut Text, With<TooltipText>>,
+) {
+    let Ok(mut style) = panels.get_single_mut() else {
+        return;
+    };
+    let tooltip = hovered
+        .iter()
+        .find(|(interaction, tooltip)| **interaction != Interaction::None && !tooltip.0.is_empty())
+        .map(|(_, tooltip)| tooltip);
+    let window = windows.get_single().ok();
+    let cursor = window.and_then(|window| window.cursor_position());
+    let (Some(tooltip), Some(window), Some(cursor)) = (tooltip, window, cursor) else {
+        if style.display != Display::None {
+            style.display = Display::None;
+        }
+        return;
+    };
+
+    style.display = Display::Flex;
+    if cursor.x < window.width() / 2.0 {
+        style.left = Val::Px(cursor.x + CURSOR_OFFSET);
+        style.right = Val::Auto;
+    } else {
+        style.left = Val::Auto;
+        style.right = Val::Px(window.width() - cursor.x + CURSOR_OFFSET);
+    }
+    if cursor.y < window.height() / 2.0 {
+        style.top = Val::Px(cursor.y + CURSOR_OFFSET);
+        style.bottom = Val::Auto;
+    } else {
+        style.top = Val::Auto;
+        style.bottom = Val::Px(window.height() - cursor.y + CURSOR_OFFSET);
+    }
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != tooltip.0 {
+            text.sections[0].value = tooltip.0.clone();
+        }
+    }
+}
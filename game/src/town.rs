@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{
+    create_conversation_context, create_npc, AIRequestFailed, CancelConversation, CurrentDungeon,
+    NPCConversationEvent, NPCConversationResponseEvent, NPCRoster, QuestLog,
+};
+use crate::clock::{AdvanceClock, GameClock, TimeSpan};
+use crate::config::KeyBindings;
+use crate::dialogue::Dialogue;
+use crate::game_events::RecentEvents;
+use crate::gossip::{gossip_for, Grapevine};
+use crate::shop::{Market, MerchantKind};
+use crate::wilderness::{Overland, Terrain, Travel, WildernessEncounter};
+use crate::GameState;
+
+pub use old_school_core::town::{Location, Resident};
+
+// The town, the hub between expeditions. From the square 1-5 go to the inn, temple,
+// blacksmith, general store or guild; at each one Tab picks who to talk to and C
+// talks to them through the AI service (their reply shows in the dialogue panel, where
+// the player can answer back, see dialogue.rs), and at the blacksmith and the general store M
+// trades in the market; the inn has rooms, rumors and carousing besides (see inn.rs)
+// and the temple its services (see temple.rs).
+// J sets out into the wilds, and H out there (not in a dungeon) heads back, which
+// takes a day unless the party never left. A resident a script sends for (see
+// scripts.rs) talks to the party as soon as it's in town with nobody else to listen to.
+
+#[derive(Resource, Default, Debug)]
+pub struct Town {
+    // None on the square
+    pub location: Option<Location>,
+    // Whom the party is talking to at the location, by index in its residents
+    pub resident: usize,
+}
+
+// The town's own keys. Places in town with keys of their own read them before this,
+// so the key that picks a place on the square doesn't also do something there.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TownInputSet;
+
+// The last thing said or done in town, for the town screen
+#[derive(Resource, Default, Debug)]
+pub struct TownMessage(pub Option<String>);
+
+// Residents the party has spoken to and not yet heard back from, by NPC id
+#[derive(Resource, Default, Debug)]
+struct AwaitingReplies(Vec<&'static str>);
+
+// Residents who want a word with the party, by NPC id, first come first heard
+#[derive(Resource, Default, Debug)]
+pub struct Summons(pub Vec<String>);
+
+pub struct TownPlugin;
+
+impl Plugin for TownPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Town>()
+            .init_resource::<TownMessage>()
+            .init_resource::<AwaitingReplies>()
+            .init_resource::<Summons>()
+            .add_systems(OnEnter(GameState::Town), arrive_in_town)
+            .add_systems(Update, (
+                town_input.in_set(TownInputSet).run_if(in_state(GameState::Town)),
+                answer_summons.run_if(in_state(GameState::Town)),
+                hear_residents,
+                head_for_town.run_if(in_state(GameState::InGame)),
+            ).chain());
+    }
+}
+
+const LOCATION_KEYS: [KeyCode; 5] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+
+// The party is in town whenever it isn't in a dungeon or out in the wilds
+pub fn party_in_town(dungeon: &CurrentDungeon, overland: &Overland) -> bool {
+    dungeon.0.is_none() && overland.terrain.is_none()
+}
+
+fn find_resident(npc_id: &str) -> Option<&'static Resident> {
+    Location::ALL
+        .iter()
+        .flat_map(|location| location.residents())
+        .find(|resident| resident.id == npc_id)
+}
+
+// Everyone in town is known to the roster, so conversations carry what they remember
+fn arrive_in_town(mut overland: ResMut<Overland>, mut roster: ResMut<NPCRoster>, mut town: ResMut<Town>) {
+    overland.terrain = None;
+    overland.miles = 0;
+    town.location = None;
+    for resident in Location::ALL.iter().flat_map(|location| location.residents()) {
+        roster.npcs.entry(resident.id.to_string()).or_insert_with(|| {
+            create_npc(resident.name.to_string(), resident.personality.to_string(), resident.background.to_string())
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn town_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut town: ResMut<Town>,
+    mut message: ResMut<TownMessage>,
+    mut market: ResMut<Market>,
+    mut awaiting: ResMut<AwaitingReplies>,
+    mut dialogue: ResMut<Dialogue>,
+    grapevine: Res<Grapevine>,
+    recent: Res<RecentEvents>,
+    quest_log: Res<QuestLog>,
+    clock: Res<GameClock>,
+    mut conversations: EventWriter<NPCConversationEvent>,
+    mut journeys: EventWriter<Travel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if dialogue.is_open() {
+        return;
+    }
+    let Some(location) = town.location else {
+        if let Some(index) = LOCATION_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) {
+            town.location = Some(Location::ALL[index]);
+            town.resident = 0;
+            message.0 = None;
+        } else if keyboard_input.just_pressed(keys.travel) {
+            journeys.send(Travel(Terrain::Clear));
+            next_state.set(GameState::InGame);
+        } else if keyboard_input.just_pressed(KeyCode::Escape) {
+            next_state.set(GameState::MainMenu);
+        }
+        return;
+    };
+
+    let residents = location.residents();
+    if keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Back]) {
+        town.location = None;
+        message.0 = None;
+    } else if keyboard_input.just_pressed(KeyCode::Tab) && !residents.is_empty() {
+        town.resident = (town.resident + 1) % residents.len();
+    } else if keyboard_input.just_pressed(KeyCode::C) {
+        let Some(resident) = residents.get(town.resident) else {
+            return;
+        };
+        let opening = "Well met. What news?";
+        let news = (&*grapevine, &*quest_log, &*recent);
+        greet(resident, location, opening, &clock, news, &mut conversations, &mut awaiting, &mut dialogue);
+        message.0 = None;
+    } else if keyboard_input.just_pressed(KeyCode::M) {
+        let Some(kind) = location.merchant() else {
+            return;
+        };
+        market.selected = MerchantKind::ALL.iter().position(|merchant| *merchant == kind).unwrap_or(0);
+        next_state.set(GameState::Shop);
+    }
+}
+
+// Opens the dialogue panel on a resident, asking what they have to say to `opening`
+#[allow(clippy::too_many_arguments)]
+fn greet(
+    resident: &'static Resident,
+    location: Location,
+    opening: &str,
+    clock: &GameClock,
+    (grapevine, quest_log, recent): (&Grapevine, &QuestLog, &RecentEvents),
+    conversations: &mut EventWriter<NPCConversationEvent>,
+    awaiting: &mut AwaitingReplies,
+    dialogue: &mut Dialogue,
+) {
+    // Whatever they've heard going round town and of the party's doings, they may pass on
+    let heard = gossip_for(grapevine, resident.id).into_iter().chain(quest_log.outcomes()).chain(recent.lines());
+    let location = location.name().to_string();
+    let context = create_conversation_context(location, clock, heard.collect(), quest_log.reputation);
+    conversations.send(NPCConversationEvent {
+        npc_id: resident.id.to_string(),
+        player_message: opening.to_string(),
+        context: context.clone(),
+    });
+    awaiting.0.push(resident.id);
+    dialogue.open(resident.id, resident.name, context);
+}
+
+// The party goes to whoever sent for it
+#[allow(clippy::too_many_arguments)]
+fn answer_summons(
+    mut summons: ResMut<Summons>,
+    mut town: ResMut<Town>,
+    mut awaiting: ResMut<AwaitingReplies>,
+    mut dialogue: ResMut<Dialogue>,
+    grapevine: Res<Grapevine>,
+    recent: Res<RecentEvents>,
+    quest_log: Res<QuestLog>,
+    clock: Res<GameClock>,
+    mut conversations: EventWriter<NPCConversationEvent>,
+) {
+    if dialogue.is_open() || summons.0.is_empty() {
+        return;
+    }
+    let npc_id = summons.0.remove(0);
+    let found = Location::ALL.iter().find_map(|location| {
+        let residents = location.residents();
+        residents.iter().position(|resident| resident.id == npc_id).map(|index| (*location, index, &residents[index]))
+    });
+    let Some((location, index, resident)) = found else {
+        warn!("Nobody in town goes by {}", npc_id);
+        return;
+    };
+    town.location = Some(location);
+    town.resident = index;
+    let opening = "You sent for us?";
+    let news = (&*grapevine, &*quest_log, &*recent);
+    greet(resident, location, opening, &clock, news, &mut conversations, &mut awaiting, &mut dialogue);
+}
+
+fn hear_residents(
+    mut responses: EventReader<NPCConversationResponseEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut cancellations: EventReader<CancelConversation>,
+    mut awaiting: ResMut<AwaitingReplies>,
+    mut message: ResMut<TownMessage>,
+) {
+    // What they say is typed out in the dialogue panel; any quest they offer waits in
+    // the quest journal
+    for response in responses.read() {
+        awaiting.0.retain(|id| *id != response.npc_id);
+        if let (Some(resident), Some(quest)) = (find_resident(&response.npc_id), &response.response.quest_offered) {
+            message.0 = Some(format!("{} has a task for the party: {} (Q to answer)", resident.name, quest.title));
+        }
+    }
+    for cancellation in cancellations.read() {
+        awaiting.0.retain(|id| *id != cancellation.npc_id);
+    }
+    // Failures don't say who they were for, so the oldest one waiting gives up
+    for failure in failures.read() {
+        if failure.endpoint != "conversation" || awaiting.0.is_empty() {
+            continue;
+        }
+        if let Some(resident) = find_resident(awaiting.0.remove(0)) {
+            message.0 = Some(format!("{} has nothing to say just now", resident.name));
+        }
+    }
+}
+
+fn head_for_town(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    dungeon: Res<CurrentDungeon>,
+    overland: Res<Overland>,
+    encounter: Res<WildernessEncounter>,
+    mut advance: EventWriter<AdvanceClock>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(keys.town) || dungeon.0.is_some() || encounter.0.is_some() {
+        return;
+    }
+    if let Some(terrain) = overland.terrain {
+        info!("The party makes its way back to town through the {}", terrain.name());
+        advance.send(AdvanceClock(TimeSpan::Days(1)));
+    }
+    next_state.set(GameState::Town);
+}
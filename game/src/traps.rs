@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use rand::seq::IteratorRandom;
+
+use crate::character::{Character, SkillCheckEvent, SkillCheckResult, ThiefSkill};
+use crate::clock::{AdvanceClock, ClockSet, TimeSpan, TurnsPassed};
+use crate::combat::{cause_of_death, damage_per_round, CharacterFell, Combatant, DamageType, EffectType, GridPosition};
+use crate::config::KeyBindings;
+use crate::dungeon::{DungeonMap, PartyToken, Position};
+use crate::rng::GameRng;
+use crate::GameState;
+use old_school_core::skills::trap_modifier;
+
+pub use old_school_core::saves::{SaveRoll, SavingThrow};
+pub use old_school_core::traps::{Trap, TrapKind, TrapOutcome, TrapState};
+
+// Traps. X has the party spend a turn searching the room it's in (or the squares around
+// it in a passage) for traps, and Shift+X has its thief try to disarm a trap it has
+// found beside it, which takes a turn as well. A trap nobody has found goes off on a
+// random member of the party as it steps onto the square. Poison from a needle does its
+// damage round by round in a fight, and all at once as the next turn goes by otherwise.
+// The rules are in old_school_core::traps and old_school_core::saves.
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SearchForTraps;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DisarmTrap(pub GridPosition);
+
+// A trap has gone off on one of the party
+#[derive(Event, Clone, Debug)]
+pub struct TrapSprung {
+    pub kind: TrapKind,
+    pub victim: String,
+}
+
+// The last trap found, set off or disarmed, for the exploration screen
+#[derive(Resource, Default, Debug)]
+pub struct TrapMessage(pub Option<String>);
+
+// Disarm attempts waiting on their skill check, by who is working and where
+#[derive(Resource, Default, Debug)]
+struct PendingDisarms(Vec<(Entity, GridPosition)>);
+
+pub struct TrapsPlugin;
+
+impl Plugin for TrapsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SearchForTraps>()
+            .add_event::<DisarmTrap>()
+            .add_event::<TrapSprung>()
+            .init_resource::<TrapMessage>()
+            .init_resource::<PendingDisarms>()
+            .add_systems(Update, (
+                trap_keys.run_if(in_state(GameState::InGame)),
+                search_for_traps,
+                start_disarming,
+                resolve_disarms,
+                spring_traps,
+            ).chain())
+            .add_systems(Update, poison_runs_its_course.after(ClockSet).run_if(not(in_state(GameState::Combat))));
+    }
+}
+
+fn beside(a: &GridPosition, b: &GridPosition) -> bool {
+    (a.x - b.x).abs() <= 1 && (a.y - b.y).abs() <= 1
+}
+
+fn trap_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    map: Option<Res<DungeonMap>>,
+    tokens: Query<&Position, With<PartyToken>>,
+    mut searches: EventWriter<SearchForTraps>,
+    mut disarms: EventWriter<DisarmTrap>,
+    mut message: ResMut<TrapMessage>,
+) {
+    if !keyboard_input.just_pressed(keys.search) {
+        return;
+    }
+    if !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        searches.send(SearchForTraps);
+        return;
+    }
+    let (Some(map), Ok(position)) = (map, tokens.get_single()) else {
+        return;
+    };
+    match map.traps.iter().find(|trap| trap.state == TrapState::Found && beside(&trap.position, &position.0)) {
+        Some(trap) => disarms.send(DisarmTrap(trap.position)),
+        None => message.0 = Some("There is no trap here to disarm".to_string()),
+    }
+}
+
+fn search_for_traps(
+    mut searches: EventReader<SearchForTraps>,
+    map: Option<ResMut<DungeonMap>>,
+    tokens: Query<&Position, With<PartyToken>>,
+    party: Query<(&Character, &Combatant)>,
+    mut advance: EventWriter<AdvanceClock>,
+    mut message: ResMut<TrapMessage>,
+    mut rng: ResMut<GameRng>,
+) {
+    let (Some(mut map), Ok(position)) = (map, tokens.get_single()) else {
+        searches.clear();
+        return;
+    };
+    // A search covers the room the party is in, or the squares right around it in a passage
+    let room_id = map.room_at(&position.0);
+    let in_search_area = |trap: &Trap| match room_id {
+        Some(room_id) => trap.room_id == room_id,
+        None => beside(&trap.position, &position.0),
+    };
+
+    for _ in searches.read() {
+        advance.send(AdvanceClock(TimeSpan::Turns(1)));
+        let mut found = Vec::new();
+        for trap in map.traps.iter_mut() {
+            if trap.state != TrapState::Hidden || !in_search_area(trap) {
+                continue;
+            }
+            let spotted_by = party
+                .iter()
+                .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+                .find(|(character, _)| character.search_for_trap(trap, rng.as_mut()));
+            if let Some((character, _)) = spotted_by {
+                trap.state = TrapState::Found;
+                found.push(format!("{} finds a {}", character.name, trap.kind.name()));
+            }
+        }
+        let text = if found.is_empty() {
+            "The party searches for traps and finds nothing".to_string()
+        } else {
+            found.join(". ")
+        };
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
+
+fn start_disarming(
+    mut disarms: EventReader<DisarmTrap>,
+    map: Option<Res<DungeonMap>>,
+    party: Query<(Entity, &Character, &Combatant)>,
+    mut pending: ResMut<PendingDisarms>,
+    mut skill_checks: EventWriter<SkillCheckEvent>,
+    mut message: ResMut<TrapMessage>,
+) {
+    let Some(map) = map else {
+        disarms.clear();
+        return;
+    };
+    for DisarmTrap(position) in disarms.read() {
+        let Some(trap) = map.trap_at(position).filter(|trap| trap.is_armed()) else {
+            continue;
+        };
+        let thief = party
+            .iter()
+            .filter(|(_, character, combatant)| combatant.is_player && character.is_alive())
+            .filter_map(|(entity, character, _)| character.skill_chance(ThiefSkill::FindRemoveTraps).map(|chance| (entity, chance)))
+            .max_by_key(|(_, chance)| *chance);
+        match thief {
+            Some((entity, _)) => {
+                skill_checks.send(SkillCheckEvent {
+                    character: entity,
+                    skill: ThiefSkill::FindRemoveTraps,
+                    modifier: trap_modifier(trap.difficulty),
+                });
+                pending.0.push((entity, *position));
+            }
+            None => message.0 = Some("Nobody in the party knows how to disarm a trap".to_string()),
+        }
+    }
+}
+
+fn resolve_disarms(
+    mut results: EventReader<SkillCheckResult>,
+    mut pending: ResMut<PendingDisarms>,
+    map: Option<ResMut<DungeonMap>>,
+    characters: Query<&Character>,
+    mut message: ResMut<TrapMessage>,
+) {
+    let Some(mut map) = map else {
+        return;
+    };
+    for result in results.read() {
+        if result.check.skill != ThiefSkill::FindRemoveTraps {
+            continue;
+        }
+        let Some(index) = pending.0.iter().position(|(entity, _)| *entity == result.character) else {
+            continue;
+        };
+        let (entity, position) = pending.0.remove(index);
+        let Some(trap) = map.trap_at_mut(&position) else {
+            continue;
+        };
+        let name = characters.get(entity).map_or("The thief".to_string(), |character| character.name.clone());
+        let text = if result.check.success {
+            trap.state = TrapState::Disarmed;
+            format!("{} disarms the {}", name, trap.kind.name())
+        } else {
+            format!("{} can't disarm the {}", name, trap.kind.name())
+        };
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
+
+// A hidden trap goes off as the party steps onto it; found ones are stepped around
+fn spring_traps(
+    tokens: Query<&Position, (With<PartyToken>, Changed<Position>)>,
+    map: Option<ResMut<DungeonMap>>,
+    mut party: Query<(Entity, &mut Character, &mut Combatant)>,
+    mut message: ResMut<TrapMessage>,
+    mut sprung: EventWriter<TrapSprung>,
+    mut fallen: EventWriter<CharacterFell>,
+    mut rng: ResMut<GameRng>,
+) {
+    let (Some(mut map), Ok(position)) = (map, tokens.get_single()) else {
+        return;
+    };
+    if message.0.is_some() {
+        message.0 = None;
+    }
+    if !map.trap_at(&position.0).is_some_and(|trap| trap.state == TrapState::Hidden) {
+        return;
+    }
+    let Some((entity, mut victim, mut combatant)) = party
+        .iter_mut()
+        .filter(|(_, character, combatant)| combatant.is_player && character.is_alive())
+        .choose(rng.as_mut())
+    else {
+        return;
+    };
+    let Some(trap) = map.trap_at_mut(&position.0) else {
+        return;
+    };
+
+    trap.state = TrapState::Sprung;
+    let outcome = trap.spring(&victim, rng.as_mut());
+    victim.take_damage(outcome.damage);
+    let mut text = format!("A {} goes off on {}", trap.kind.name(), victim.name);
+    if outcome.save.success {
+        text.push_str(&format!(", who saves ({} vs {})", outcome.save.roll, outcome.save.target));
+    }
+    text.push_str(&format!(" for {} damage", outcome.damage));
+    if let Some(poison) = outcome.poison {
+        text.push_str(" and poisons them");
+        combatant.status_effects.retain(|existing| existing.name != poison.name);
+        combatant.status_effects.push(poison);
+    }
+    if !victim.is_alive() {
+        text.push_str(&format!(". {} is killed", victim.name));
+        fallen.send(CharacterFell {
+            entity,
+            name: victim.name.clone(),
+            is_player: true,
+            cause: format!("killed by a {} trap", trap.kind.name()),
+            killer: None,
+        });
+    }
+    info!("{}", text);
+    message.0 = Some(text);
+    sprung.send(TrapSprung { kind: trap.kind, victim: victim.name.clone() });
+}
+
+// Outside a fight a turn is long enough for poison to run its course, and for any other
+// effect counted in rounds to wear off
+fn poison_runs_its_course(
+    mut turns: EventReader<TurnsPassed>,
+    mut party: Query<(Entity, &mut Character, &mut Combatant)>,
+    mut message: ResMut<TrapMessage>,
+    mut fallen: EventWriter<CharacterFell>,
+) {
+    if turns.read().count() == 0 {
+        return;
+    }
+    for (entity, mut character, mut combatant) in party.iter_mut() {
+        let damage: i16 = combatant
+            .status_effects
+            .iter()
+            .map(|effect| damage_per_round(effect) * effect.duration as i16)
+            .sum();
+        // A turn outlasts anything counted in rounds, spells as well; only diseases stay
+        if combatant.status_effects.iter().any(|effect| !matches!(effect.effect_type, EffectType::Disease)) {
+            combatant.status_effects.retain(|effect| matches!(effect.effect_type, EffectType::Disease));
+        }
+        if damage == 0 || !character.is_alive() {
+            continue;
+        }
+        character.take_damage(damage);
+        let mut text = format!("{} suffers {} damage from poison", character.name, damage);
+        if !character.is_alive() {
+            text.push_str(" and dies");
+            fallen.send(CharacterFell {
+                entity,
+                name: character.name.clone(),
+                is_player: combatant.is_player,
+                cause: cause_of_death(&DamageType::Poison).to_string(),
+                killer: None,
+            });
+        }
+        info!("{}", text);
+        message.0 = Some(text);
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{CurrentDungeon, TreasureData};
+use crate::character::Character;
+use crate::combat::Combatant;
+use crate::data::ItemCatalog;
+use crate::dungeon::{DungeonMap, PartyToken, Position};
+use crate::experience::RecoveredTreasure;
+use crate::rng::GameRng;
+use crate::traps::SearchForTraps;
+use old_school_core::dice;
+
+// Taking the treasure the dungeon was generated with. A room's treasure sits in a chest
+// in the middle of the room and the party takes it by going there (after any trap on
+// the chest has had its say); hidden treasure turns up only when the party searches the
+// room with X, on the same 1 in 6 (2 in 6 for a dwarf) as finding a trap. Coins are
+// shared out among the living party and everything else goes to whoever's first in it.
+
+// Treasures taken in this dungeon, by index in its treasures
+#[derive(Resource, Default, Debug)]
+pub struct LootedTreasure(pub Vec<usize>);
+
+// The last treasure taken, for the exploration screen
+#[derive(Resource, Default, Debug)]
+pub struct TreasureMessage(pub Option<String>);
+
+pub struct TreasurePlugin;
+
+impl Plugin for TreasurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootedTreasure>()
+            .init_resource::<TreasureMessage>()
+            .add_systems(Update, (open_chests, find_hidden_treasure));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_chests(
+    tokens: Query<&Position, (With<PartyToken>, Changed<Position>)>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    mut looted: ResMut<LootedTreasure>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    catalog: Res<ItemCatalog>,
+    mut message: ResMut<TreasureMessage>,
+    mut recovered: ResMut<RecoveredTreasure>,
+) {
+    let (Some(map), Some(data), Ok(position)) = (map, dungeon.0.as_ref(), tokens.get_single()) else {
+        return;
+    };
+    if message.0.is_some() {
+        message.0 = None;
+    }
+    let Some(room_id) = map.room_at(&position.0) else {
+        return;
+    };
+    if map.room_area(room_id).map(|area| area.center()) != Some(position.0) {
+        return;
+    }
+    for (index, treasure) in data.treasures.iter().enumerate() {
+        if treasure.room_id != room_id || treasure.is_hidden || looted.0.contains(&index) {
+            continue;
+        }
+        looted.0.push(index);
+        message.0 = Some(take_treasure(treasure, &mut party, &catalog, &mut recovered));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn find_hidden_treasure(
+    mut searches: EventReader<SearchForTraps>,
+    tokens: Query<&Position, With<PartyToken>>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    mut looted: ResMut<LootedTreasure>,
+    mut party: Query<(&mut Character, &Combatant)>,
+    catalog: Res<ItemCatalog>,
+    mut message: ResMut<TreasureMessage>,
+    mut recovered: ResMut<RecoveredTreasure>,
+    mut rng: ResMut<GameRng>,
+) {
+    let (Some(map), Some(data), Ok(position)) = (map, dungeon.0.as_ref(), tokens.get_single()) else {
+        searches.clear();
+        return;
+    };
+    let Some(room_id) = map.room_at(&position.0) else {
+        searches.clear();
+        return;
+    };
+    for _ in searches.read() {
+        for (index, treasure) in data.treasures.iter().enumerate() {
+            if treasure.room_id != room_id || !treasure.is_hidden || looted.0.contains(&index) {
+                continue;
+            }
+            let found = party
+                .iter()
+                .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+                .any(|(character, _)| dice::d6(rng.as_mut()) <= character.search_chance());
+            if found {
+                looted.0.push(index);
+                let taken = take_treasure(treasure, &mut party, &catalog, &mut recovered);
+                message.0 = Some(format!("Hidden treasure! {}", taken));
+            }
+        }
+    }
+}
+
+// Shares the coins out, gives the rest to the first of the party, counts the coins and
+// any gems and jewellery towards the party's experience, and says what was taken
+fn take_treasure(
+    treasure: &TreasureData,
+    party: &mut Query<(&mut Character, &Combatant)>,
+    catalog: &ItemCatalog,
+    recovered: &mut RecoveredTreasure,
+) -> String {
+    let mut members: Vec<_> = party
+        .iter_mut()
+        .filter(|(character, combatant)| combatant.is_player && character.is_alive())
+        .map(|(character, _)| character)
+        .collect();
+    if members.is_empty() {
+        return String::new();
+    }
+
+    let share = treasure.gold / members.len() as u32;
+    let remainder = treasure.gold % members.len() as u32;
+    for (index, member) in members.iter_mut().enumerate() {
+        member.inventory.gold += share + if index == 0 { remainder } else { 0 };
+    }
+    recovered.0 += treasure.gold;
+    let lead = &mut members[0];
+    for name in &treasure.items {
+        let item = catalog.treasure(name);
+        if !item.is_magic() {
+            recovered.0 += item.value;
+        }
+        lead.inventory.add_item(item);
+    }
+
+    let mut taken = Vec::new();
+    if treasure.gold > 0 {
+        taken.push(format!("{} gp", treasure.gold));
+    }
+    taken.extend(treasure.items.iter().cloned());
+    let text = if taken.is_empty() {
+        "The chest is empty".to_string()
+    } else {
+        format!("The party takes {}", taken.join(", "))
+    };
+    info!("{}", text);
+    text
+}
@@ -1,6 +1,77 @@
 use bevy::prelude::*;
-use crate::{GameState, GameConfig};
-use crate::character::{Character, CharacterClass};
+use bevy::window::PrimaryWindow;
+use crate::{GameConfig, GameState};
+use crate::ai_client::{AIRequestFailed, CurrentDungeon, RoomData};
+use crate::ai_health::{AiStatus, Connection, ConnectionChanged};
+use crate::campaign::{CampaignSyncFailed, CampaignTurnStarted};
+use crate::character::{Ability, Character, CharacterClass, CharacterDraft, Item};
+use crate::clock::GameClock;
+use crate::ai_backend::AiBackendKind;
+use crate::config::{key_name, ConfigPath, KeyBindings, CONFIG_FILE};
+use crate::game_over::RunSummary;
+use crate::graveyard::Graveyard;
+use crate::level_up::LevelUpScreen;
+use crate::loot::{looters, LootScreen};
+use crate::game_state::{MainMenu, MainMenuButton, MenuChoice};
+use crate::dialogue::Dialogue;
+use crate::combat::{
+    default_spell_targets, defending_against, guard, hit_chance, is_beneficial, spell_template, AttackEvent,
+    BattleMap, CastSpellEvent, Combat, CombatLogEvent, CombatSelection, CombatState, Combatant, Declaration,
+    FleeEvent, GridPosition, RoundPhase, TurnMovement, PARRY_ARMOR_BONUS,
+};
+use old_school_core::battle_map::{missile_range, shot_reach};
+use old_school_core::spells::SpellCompendium;
+use crate::inventory::{
+    item_at, DraggedItem, EquipmentSlot, InventoryCell, InventoryMessage, InventoryView, InventoryViewButton, ItemAction,
+    ItemFilter, ItemMenu, ItemMenuButton, ItemPlace, OpenContainer,
+};
+use crate::journal::format_date;
+use crate::automap::AutomapView;
+use crate::doors::DoorMessage;
+use crate::traps::TrapMessage;
+use crate::treasure::TreasureMessage;
+use crate::wilderness::{Overland, WildernessMessage};
+use crate::dungeon::{CurrentRoom, DungeonMap, FogOfWar, PartyToken, Position, Sight};
+use crate::encounters::Monster;
+use crate::light::{describe_room, Darkness, PartyLight};
+use crate::ruleset::{ExperienceTables, Ruleset};
+use crate::narration::Narrated;
+use crate::ai_client::{QuestData, QuestLog};
+use crate::quests::{journal_order, JournalLine, Quest, QuestJournal, QuestMessage, QuestStatus, DEADLINE_WARNING_DAYS};
+use crate::retainers::{retainer_limit, HiringHall, Retainer, RetainerMessage};
+use crate::message_log::{MessageCategory, MessageLog};
+use crate::mods::ModList;
+use crate::save::{slot_path, GameLoaded, SaveAs, SaveSlots};
+use crate::spellbook::{party_casters, spellbook_order, Spellbook};
+use crate::sprites::SpriteImages;
+use crate::tooltip::{item_tooltip_for, spell_tooltip, status_icon, status_tooltip, Tooltip};
+use crate::shop::{buy_price, sell_price, Market, ShopMessage};
+use crate::temple::{describe_services, TempleFavor, TempleVisit};
+use crate::theme::{TextSize, UiTheme};
+use crate::town::{Location, Town, TownMessage};
+
+// Entries the message log panels show on the combat and exploration screens
+const COMBAT_LOG_ROWS: usize = 14;
+const EXPLORATION_LOG_ROWS: usize = 5;
+
+// The inventory grid. The pack shows free cells up to INVENTORY_CELLS, and always at
+// least one to drop things into.
+const INVENTORY_COLUMNS: usize = 6;
+const INVENTORY_CELLS: usize = 12;
+const CELL_WIDTH: f32 = 120.0;
+const CELL_HEIGHT: f32 = 52.0;
+const CELL_GAP: f32 = 6.0;
+// An item's icon in its cell, and a combatant's picture on the combat screen
+const ICON_SIZE: f32 = 24.0;
+const PORTRAIT_SIZE: f32 = 48.0;
+
+// The paper doll: where the equipment slots sit, row by row, with the head at the top,
+// the hands either side of the body and the feet at the bottom
+const PAPER_DOLL: [[Option<EquipmentSlot>; 3]; 3] = [
+    [Some(EquipmentSlot::Cloak), Some(EquipmentSlot::Helmet), None],
+    [Some(EquipmentSlot::Weapon), Some(EquipmentSlot::Armor), Some(EquipmentSlot::Shield)],
+    [Some(EquipmentSlot::LeftRing), Some(EquipmentSlot::Boots), Some(EquipmentSlot::RightRing)],
+];
 
 #[derive(Component)]
 pub struct MainMenuUI;
@@ -17,11 +88,152 @@ pub struct CombatUI;
 #[derive(Component)]
 pub struct InventoryUI;
 
+#[derive(Component)]
+pub struct ShopUI;
+
+#[derive(Component)]
+pub struct TownUI;
+
+#[derive(Component)]
+pub struct AutomapUI;
+
+#[derive(Component)]
+pub struct QuestsUI;
+
+#[derive(Component)]
+pub struct LoadGameUI;
+
+#[derive(Component)]
+pub struct SettingsUI;
+
+#[derive(Component)]
+pub struct GameOverUI;
+
+#[derive(Component)]
+pub struct GraveyardUI;
+
+#[derive(Component)]
+pub struct LootUI;
+
+#[derive(Component)]
+pub struct LevelUpUI;
+
+#[derive(Component)]
+struct SettingsText;
+
+// The F6 prompt for a save slot's name
+#[derive(Component)]
+pub struct SavePrompt;
+
+// Text nodes that show live data. Display systems look them up by marker and only
+// rewrite them when the data behind them changes or the node has just been spawned.
+#[derive(Component)]
+pub struct PartyNameText;
+
+#[derive(Component)]
+pub struct PartyHpText;
+
+#[derive(Component)]
+pub struct LightText;
+
+#[derive(Component)]
+pub struct ClockText;
+
+#[derive(Component)]
+pub struct RoomText;
+
+#[derive(Component)]
+pub struct CombatRoundText;
+
+#[derive(Component)]
+pub struct CombatSelectionText;
+
+#[derive(Component)]
+pub struct BattleMapText;
+
+// Under a combatant's name in the lineup, what they're under: an icon for each effect
+// with the rounds it has left, and its tooltip
+#[derive(Component)]
+pub struct LineupConditions(pub Entity);
+
+// Everyone in the fight, the party on the left
+#[derive(Component)]
+pub struct CombatLineup;
+
+// A combatant's picture in the lineup, or their plain square if they have none
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineupPortrait(pub Entity);
+
+#[derive(Component)]
+pub struct InventoryText;
+
+// The grid of equipment slots, pack and open container, rebuilt as items move
+#[derive(Component)]
+pub struct InventoryGrid;
+
+// The name of the item being dragged, following the cursor
+#[derive(Component)]
+pub struct DragGhost;
+
+// The right-click menu for an item
+#[derive(Component)]
+pub struct ItemMenuUI;
+
+#[derive(Component)]
+pub struct ShopText;
+
+#[derive(Component)]
+pub struct TownText;
+
+#[derive(Component)]
+pub struct DialoguePanel;
+
+#[derive(Component)]
+pub struct DialogueText;
+
+#[derive(Component)]
+pub struct AutomapText;
+
+#[derive(Component)]
+pub struct QuestText;
+
+#[derive(Component)]
+pub struct SpellbookUI;
+
+#[derive(Component)]
+pub struct SpellbookText;
+
+#[derive(Component)]
+pub struct AutomapInfoText;
+
+#[derive(Component)]
+pub struct SaveSlotList;
+
+#[derive(Component)]
+pub struct RunSummaryText;
+
+#[derive(Component)]
+pub struct GraveyardText;
+
+#[derive(Component)]
+pub struct LootText;
+
+#[derive(Component)]
+pub struct LevelUpText;
+
+#[derive(Component)]
+pub struct CharacterDraftText;
+
+// Whether the AI is answering, in the corner of every screen
+#[derive(Component)]
+pub struct ConnectionIndicator;
+
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+        app.add_systems(Startup, (spawn_connection_indicator, spawn_save_prompt))
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
             .add_systems(OnExit(GameState::MainMenu), despawn_ui::<MainMenuUI>)
             .add_systems(OnEnter(GameState::CharacterCreation), spawn_character_creation)
             .add_systems(OnExit(GameState::CharacterCreation), despawn_ui::<CharacterCreationUI>)
@@ -31,14 +243,78 @@ impl Plugin for UIPlugin {
             .add_systems(OnExit(GameState::Combat), despawn_ui::<CombatUI>)
             .add_systems(OnEnter(GameState::Inventory), spawn_inventory_ui)
             .add_systems(OnExit(GameState::Inventory), despawn_ui::<InventoryUI>)
+            .add_systems(OnEnter(GameState::Shop), spawn_shop_ui)
+            .add_systems(OnExit(GameState::Shop), despawn_ui::<ShopUI>)
+            .add_systems(OnEnter(GameState::Town), spawn_town_ui)
+            .add_systems(OnExit(GameState::Town), despawn_ui::<TownUI>)
+            .add_systems(OnEnter(GameState::Map), spawn_automap_ui)
+            .add_systems(OnExit(GameState::Map), despawn_ui::<AutomapUI>)
+            .add_systems(OnEnter(GameState::Quests), spawn_quests_ui)
+            .add_systems(OnExit(GameState::Quests), despawn_ui::<QuestsUI>)
+            .add_systems(OnEnter(GameState::Spellbook), spawn_spellbook_ui)
+            .add_systems(OnExit(GameState::Spellbook), despawn_ui::<SpellbookUI>)
+            .add_systems(OnEnter(GameState::LoadGame), spawn_load_game_ui)
+            .add_systems(OnExit(GameState::LoadGame), despawn_ui::<LoadGameUI>)
+            .add_systems(OnEnter(GameState::Settings), spawn_settings_ui)
+            .add_systems(OnExit(GameState::Settings), despawn_ui::<SettingsUI>)
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_ui)
+            .add_systems(OnExit(GameState::GameOver), despawn_ui::<GameOverUI>)
+            .add_systems(OnEnter(GameState::Graveyard), spawn_graveyard_ui)
+            .add_systems(OnExit(GameState::Graveyard), despawn_ui::<GraveyardUI>)
+            .add_systems(OnEnter(GameState::Loot), spawn_loot_ui)
+            .add_systems(OnExit(GameState::Loot), despawn_ui::<LootUI>)
+            .add_systems(OnEnter(GameState::LevelUp), spawn_level_up_ui)
+            .add_systems(OnExit(GameState::LevelUp), despawn_ui::<LevelUpUI>)
             .add_systems(Update, (
                 update_character_display,
-                update_combat_log,
-            ));
+                update_light_text,
+                update_clock_text,
+                update_room_text,
+                update_combat_round,
+                update_message_log,
+                update_save_slot_list,
+                update_character_draft,
+                show_campaign_notices,
+                show_load_notices,
+                show_ai_failures,
+                show_connection_changes,
+                update_connection_indicator,
+                update_save_prompt,
+                expire_notice_banners,
+                show_narration,
+                expire_narration,
+            ))
+            .add_systems(Update, (
+                cycle_combat_selection,
+                pick_lineup_target,
+                highlight_lineup_target,
+                handle_combat_actions,
+                update_combat_selection,
+                update_combat_conditions,
+                update_combat_lineup,
+                update_battle_map,
+            ).run_if(in_state(GameState::Combat)))
+            .add_systems(Update, (
+                update_inventory_text,
+                update_inventory_cells,
+                update_drag_ghost,
+                update_item_menu,
+            ).run_if(in_state(GameState::Inventory)))
+            .add_systems(Update, update_shop_text.run_if(in_state(GameState::Shop)))
+            .add_systems(Update, (update_town_text, update_dialogue_panel).run_if(in_state(GameState::Town)))
+            .add_systems(Update, update_automap_text.run_if(in_state(GameState::Map)))
+            .add_systems(Update, update_quest_text.run_if(in_state(GameState::Quests)))
+            .add_systems(Update, update_spellbook_text.run_if(in_state(GameState::Spellbook)))
+            .add_systems(Update, update_settings_text.run_if(in_state(GameState::Settings)))
+            .add_systems(Update, update_run_summary_text.run_if(in_state(GameState::GameOver)))
+            .add_systems(Update, update_graveyard_text.run_if(in_state(GameState::Graveyard)))
+            .add_systems(Update, update_loot_text.run_if(in_state(GameState::Loot)))
+            .add_systems(Update, update_level_up_text.run_if(in_state(GameState::LevelUp)))
+            .add_systems(Update, update_main_menu_buttons.run_if(in_state(GameState::MainMenu)));
     }
 }
 
-fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_main_menu(mut commands: Commands, theme: Res<UiTheme>) {
     commands
         .spawn((
             NodeBundle {
@@ -50,7 +326,7 @@ fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: Color::rgb(0.1, 0.1, 0.2).into(),
+                background_color: theme.palette.background.into(),
                 ..default()
             },
             MainMenuUI,
@@ -59,36 +335,60 @@ fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
             // Title
             parent.spawn(TextBundle::from_section(
                 "Old School AI RPG",
-                TextStyle {
-                    font_size: 48.0,
-                    color: Color::rgb(0.9, 0.9, 0.9),
-                    ..default()
-                },
+                theme.text(TextSize::Title, theme.palette.text),
             ));
 
-            // Subtitle
             parent.spawn(TextBundle::from_section(
-                "Press Enter to Start",
-                TextStyle {
-                    font_size: 24.0,
-                    color: Color::rgb(0.7, 0.7, 0.7),
-                    ..default()
-                },
+                "Up/Down or the mouse to choose, Enter or click to confirm",
+                theme.text(TextSize::Body, theme.palette.dim),
             ));
 
+            // Coloured by update_main_menu_buttons
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(10.0),
+                        margin: UiRect::vertical(Val::Px(30.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for choice in MenuChoice::ALL {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(240.0),
+                                        height: Val::Px(44.0),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: theme.palette.button.into(),
+                                    ..default()
+                                },
+                                MainMenuButton(choice),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    choice.label(),
+                                    theme.text(TextSize::Large, theme.palette.text),
+                                ));
+                            });
+                    }
+                });
+
             // Version info
             parent.spawn(TextBundle::from_section(
                 "v0.1.0 - Built with Rust + Bevy",
-                TextStyle {
-                    font_size: 16.0,
-                    color: Color::rgb(0.5, 0.5, 0.5),
-                    ..default()
-                },
+                theme.text(TextSize::Small, theme.palette.faint),
             ));
         });
 }
 
-fn spawn_character_creation(mut commands: Commands) {
+fn spawn_character_creation(mut commands: Commands, theme: Res<UiTheme>) {
     commands
         .spawn((
             NodeBundle {
@@ -100,7 +400,7 @@ fn spawn_character_creation(mut commands: Commands) {
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: Color::rgb(0.1, 0.1, 0.2).into(),
+                background_color: theme.palette.background.into(),
                 ..default()
             },
             CharacterCreationUI,
@@ -109,48 +409,21 @@ fn spawn_character_creation(mut commands: Commands) {
             // Title
             parent.spawn(TextBundle::from_section(
                 "Character Creation",
-                TextStyle {
-                    font_size: 36.0,
-                    color: Color::rgb(0.9, 0.9, 0.9),
-                    ..default()
-                },
+                theme.text(TextSize::Heading, theme.palette.text),
             ));
 
             // Instructions
             parent.spawn(TextBundle::from_section(
-                "Press 1-7 to select class, then Enter to confirm",
-                TextStyle {
-                    font_size: 20.0,
-                    color: Color::rgb(0.7, 0.7, 0.7),
-                    ..default()
-                },
+                "R to reroll, 1-7 to select class, then Enter to name your character",
+                theme.text(TextSize::Body, theme.palette.muted),
             ));
 
-            // Class options
-            let classes = [
-                "1. Fighter",
-                "2. Magic User", 
-                "3. Cleric",
-                "4. Thief",
-                "5. Dwarf",
-                "6. Elf",
-                "7. Halfling",
-            ];
-
-            for class in classes {
-                parent.spawn(TextBundle::from_section(
-                    class,
-                    TextStyle {
-                        font_size: 18.0,
-                        color: Color::rgb(0.8, 0.8, 0.8),
-                        ..default()
-                    },
-                ));
-            }
+            // Scores and class options, filled in by update_character_draft
+            parent.spawn((TextBundle::default(), CharacterDraftText));
         });
 }
 
-fn spawn_in_game_ui(mut commands: Commands) {
+fn spawn_in_game_ui(mut commands: Commands, theme: Res<UiTheme>, keys: Res<KeyBindings>) {
     commands
         .spawn((
             NodeBundle {
@@ -176,67 +449,88 @@ fn spawn_in_game_ui(mut commands: Commands) {
                     padding: UiRect::all(Val::Px(10.0)),
                     ..default()
                 },
-                background_color: Color::rgb(0.2, 0.2, 0.3).into(),
+                background_color: theme.palette.panel.into(),
                 ..default()
             })
             .with_children(|parent| {
                 // Character name and level
-                parent.spawn(TextBundle::from_section(
-                    "Character: [Name] Level 1",
-                    TextStyle {
-                        font_size: 18.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                        ..default()
-                    },
+                parent.spawn((
+                    TextBundle::from_section(
+                        "Character: [Name] Level 1",
+                        theme.text(TextSize::Body, theme.palette.text),
+                    ),
+                    PartyNameText,
                 ));
 
                 // HP display
-                parent.spawn(TextBundle::from_section(
-                    "HP: 10/10",
-                    TextStyle {
-                        font_size: 18.0,
-                        color: Color::rgb(0.9, 0.3, 0.3),
-                        ..default()
-                    },
+                parent.spawn((
+                    TextBundle::from_section("HP: 10/10", theme.text(TextSize::Body, theme.palette.danger)),
+                    PartyHpText,
                 ));
 
-                // Controls hint
-                parent.spawn(TextBundle::from_section(
-                    "I: Inventory | ESC: Menu",
-                    TextStyle {
-                        font_size: 14.0,
-                        color: Color::rgb(0.6, 0.6, 0.6),
-                        ..default()
-                    },
+                // Light, or darkness
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.warning)),
+                    LightText,
+                ));
+
+                // Day and time
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.info)),
+                    ClockText,
                 ));
+
+                // Controls hint
+                parent.spawn(TextBundle::from_section(keys.hint(), theme.text(TextSize::Fine, theme.palette.dim)));
             });
 
-            // Main game area (placeholder)
+            // Main game area: the dungeon map shows through, with the latest of the
+            // message log and the room the party is in described along the bottom
             parent.spawn(NodeBundle {
                 style: Style {
                     width: Val::Percent(100.0),
-                    flex: 1.0,
-                    justify_content: JustifyContent::Center,
-                    align_items: AlignItems::Center,
+                    flex_grow: 1.0,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::FlexEnd,
                     ..default()
                 },
-                background_color: Color::rgb(0.1, 0.1, 0.1).into(),
                 ..default()
             })
             .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Game World\n\nUse WASD to move\nClick to interact",
-                    TextStyle {
-                        font_size: 24.0,
-                        color: Color::rgb(0.8, 0.8, 0.8),
+                // The latest of the message log
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        padding: UiRect::axes(Val::Px(10.0), Val::Px(6.0)),
                         ..default()
                     },
-                ));
+                    background_color: Color::rgba(0.05, 0.05, 0.05, 0.7).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((TextBundle::default(), MessageLogPanel { rows: EXPLORATION_LOG_ROWS }));
+                });
+
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.1, 0.1, 0.1, 0.85).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.soft)),
+                        RoomText,
+                    ));
+                });
             });
         });
 }
 
-fn spawn_combat_ui(mut commands: Commands) {
+fn spawn_combat_ui(mut commands: Commands, theme: Res<UiTheme>) {
     commands
         .spawn((
             NodeBundle {
@@ -265,40 +559,59 @@ fn spawn_combat_ui(mut commands: Commands) {
                 ..default()
             })
             .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "COMBAT - Round 1",
-                    TextStyle {
-                        font_size: 24.0,
-                        color: Color::rgb(0.9, 0.3, 0.3),
-                        ..default()
-                    },
+                parent.spawn((
+                    TextBundle::from_section("COMBAT - Round 1", theme.text(TextSize::Large, theme.palette.danger)),
+                    CombatRoundText,
                 ));
             });
 
-            // Combat log
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::SpaceBetween,
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                CombatLineup,
+            ));
+
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.highlight)).with_style(Style {
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    ..default()
+                }),
+                CombatSelectionText,
+            ));
+
+            // Battle map, when the rule is on
+            parent.spawn((
+                TextBundle::from_section("", theme.mono(TextSize::Small, theme.palette.soft)).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(20.0),
+                    top: Val::Px(100.0),
+                    ..default()
+                }),
+                BattleMapText,
+            ));
+
+            // Message log
             parent.spawn(NodeBundle {
                 style: Style {
                     width: Val::Percent(70.0),
                     height: Val::Percent(60.0),
                     margin: UiRect::all(Val::Px(10.0)),
                     padding: UiRect::all(Val::Px(10.0)),
+                    overflow: Overflow::clip(),
                     ..default()
                 },
                 background_color: Color::rgb(0.1, 0.1, 0.1).into(),
                 ..default()
             })
             .with_children(|parent| {
-                parent.spawn((
-                    TextBundle::from_section(
-                        "Combat log will appear here...",
-                        TextStyle {
-                            font_size: 16.0,
-                            color: Color::rgb(0.8, 0.8, 0.8),
-                            ..default()
-                        },
-                    ),
-                    CombatLog,
-                ));
+                parent.spawn((TextBundle::default(), MessageLogPanel { rows: COMBAT_LOG_ROWS }));
             });
 
             // Action buttons
@@ -309,14 +622,15 @@ fn spawn_combat_ui(mut commands: Commands) {
                     flex_direction: FlexDirection::Row,
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
-                    gap: Size::new(Val::Px(10.0), Val::Px(0.0)),
+                    column_gap: Val::Px(10.0),
                     ..default()
                 },
                 ..default()
             })
             .with_children(|parent| {
-                let actions = ["Attack", "Cast Spell", "Use Item", "Flee"];
+                let actions = ["Attack", "Parry", "Cast Spell", "Use Item", "Flee"];
                 for action in actions {
+                    // Cast Spell's tooltip describes the chosen spell, see update_combat_selection
                     parent.spawn((
                         ButtonBundle {
                             style: Style {
@@ -330,23 +644,17 @@ fn spawn_combat_ui(mut commands: Commands) {
                             ..default()
                         },
                         CombatActionButton(action.to_string()),
+                        Tooltip(String::new()),
                     ))
                     .with_children(|parent| {
-                        parent.spawn(TextBundle::from_section(
-                            action,
-                            TextStyle {
-                                font_size: 14.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                                ..default()
-                            },
-                        ));
+                        parent.spawn(TextBundle::from_section(action, theme.text(TextSize::Fine, theme.palette.text)));
                     });
                 }
             });
         });
 }
 
-fn spawn_inventory_ui(mut commands: Commands) {
+fn spawn_inventory_ui(mut commands: Commands, theme: Res<UiTheme>) {
     commands
         .spawn((
             NodeBundle {
@@ -356,7 +664,7 @@ fn spawn_inventory_ui(mut commands: Commands) {
                     flex_direction: FlexDirection::Column,
                     ..default()
                 },
-                background_color: Color::rgb(0.1, 0.1, 0.2).into(),
+                background_color: theme.palette.background.into(),
                 ..default()
             },
             InventoryUI,
@@ -371,25 +679,19 @@ fn spawn_inventory_ui(mut commands: Commands) {
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: Color::rgb(0.2, 0.2, 0.3).into(),
+                background_color: theme.palette.panel.into(),
                 ..default()
             })
             .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Inventory",
-                    TextStyle {
-                        font_size: 24.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                        ..default()
-                    },
-                ));
+                parent.spawn(TextBundle::from_section("Inventory", theme.text(TextSize::Large, theme.palette.text)));
             });
 
-            // Inventory grid (placeholder)
+            // Equipment and carried items
             parent.spawn(NodeBundle {
                 style: Style {
                     width: Val::Percent(100.0),
-                    flex: 1.0,
+                    flex_grow: 1.0,
+                    flex_direction: FlexDirection::Column,
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     ..default()
@@ -397,41 +699,2703 @@ fn spawn_inventory_ui(mut commands: Commands) {
                 ..default()
             })
             .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "Inventory items will be displayed here\n\nPress I or ESC to close",
-                    TextStyle {
-                        font_size: 18.0,
-                        color: Color::rgb(0.7, 0.7, 0.7),
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(INVENTORY_COLUMNS as f32 * (CELL_WIDTH + CELL_GAP)),
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::FlexStart,
+                            row_gap: Val::Px(6.0),
+                            ..default()
+                        },
                         ..default()
                     },
+                    InventoryGrid,
+                ));
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.muted)).with_style(Style {
+                        margin: UiRect::top(Val::Px(12.0)),
+                        ..default()
+                    }),
+                    InventoryText,
                 ));
             });
+
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.gold)).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    display: Display::None,
+                    ..default()
+                }),
+                DragGhost,
+            ));
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(4.0)),
+                        row_gap: Val::Px(2.0),
+                        display: Display::None,
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.15, 0.15, 0.22).into(),
+                    z_index: ZIndex::Global(50),
+                    ..default()
+                },
+                ItemMenuUI,
+            ))
+            .with_children(|parent| {
+                for action in ItemAction::ALL {
+                    parent.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(90.0),
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: theme.palette.cell_empty.into(),
+                            ..default()
+                        },
+                        ItemMenuButton(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            action.name(),
+                            theme.text(TextSize::Small, theme.palette.text),
+                        ));
+                    });
+                }
+            });
         });
 }
 
-fn despawn_ui<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
+// A cell of the inventory grid, with the item's tooltip if there is one in it. An empty
+// equipment slot says so, and has a fainter border.
+fn spawn_inventory_cell(
+    parent: &mut ChildBuilder,
+    theme: &UiTheme,
+    place: ItemPlace,
+    label: &str,
+    item: Option<&Item>,
+    character: &Character,
+    images: &SpriteImages,
+) {
+    let empty_slot = item.is_none() && matches!(place, ItemPlace::Worn(_));
+    let mut cell = parent.spawn((
+        ButtonBundle {
+            style: Style {
+                width: Val::Px(CELL_WIDTH),
+                height: Val::Px(CELL_HEIGHT),
+                padding: UiRect::all(Val::Px(4.0)),
+                border: UiRect::all(Val::Px(1.0)),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: if item.is_some() { theme.palette.cell_filled } else { theme.palette.cell_empty }.into(),
+            border_color: if empty_slot { theme.palette.slot_empty_border } else { theme.palette.cell_border }.into(),
+            ..default()
+        },
+        InventoryCell { place, filled: item.is_some() },
+    ));
+    if let Some(item) = item {
+        cell.insert(Tooltip(item_tooltip_for(item, character)));
     }
+    cell.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, theme.text(TextSize::Tiny, theme.palette.faint)));
+        if let Some(item) = item {
+            let mut name = item.name.clone();
+            if item.is_stack() {
+                name.push_str(&format!(" x{}", item.quantity));
+            }
+            if let Some(uses) = item.uses {
+                name.push_str(&format!(" ({})", uses));
+            }
+            let name = TextBundle::from_section(name, theme.text(TextSize::Fine, theme.palette.soft));
+            let Some(icon) = images.item(item) else {
+                parent.spawn(name);
+                return;
+            };
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(ICON_SIZE),
+                            height: Val::Px(ICON_SIZE),
+                            flex_shrink: 0.0,
+                            ..default()
+                        },
+                        image: UiImage::new(icon),
+                        ..default()
+                    });
+                    parent.spawn(name);
+                });
+        } else if empty_slot {
+            parent.spawn(TextBundle::from_section("empty", theme.text(TextSize::Fine, theme.palette.disabled)));
+        }
+    });
 }
 
-#[derive(Component)]
-pub struct CombatLog;
+// The equipment slots laid out as a paper doll, and beside it what makes up the armour
+// class; the inventory grid is rebuilt whenever the character changes, so this keeps up
+// with what is worn
+fn spawn_paper_doll(parent: &mut ChildBuilder, theme: &UiTheme, images: &SpriteImages, character: &Character) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(CELL_GAP * 4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(CELL_GAP),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for row in PAPER_DOLL {
+                        spawn_inventory_row(parent, |parent| {
+                            for slot in row {
+                                let Some(slot) = slot else {
+                                    parent.spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(CELL_WIDTH),
+                                            height: Val::Px(CELL_HEIGHT),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    });
+                                    continue;
+                                };
+                                let item = character.equipment.get(slot);
+                                let place = ItemPlace::Worn(slot);
+                                spawn_inventory_cell(parent, theme, place, slot.name(), item, character, images);
+                            }
+                        });
+                    }
+                });
+            parent.spawn(TextBundle::from_sections([
+                TextSection::new(
+                    format!("AC {}\n", character.armor_class),
+                    theme.text(TextSize::Subheading, theme.palette.gold),
+                ),
+                TextSection::new(
+                    armor_class_breakdown(character).join("\n"),
+                    theme.text(TextSize::Fine, theme.palette.muted),
+                ),
+            ]));
+        });
+}
 
-#[derive(Component)]
-pub struct CombatActionButton(pub String);
+// Base 10, Dexterity and each worn item that changes the armour class
+fn armor_class_breakdown(character: &Character) -> Vec<String> {
+    let mut lines = vec!["Base 10".to_string()];
+    let dexterity = Character::get_dexterity_modifier(character.stats.dexterity);
+    if dexterity != 0 {
+        lines.push(format!("Dexterity {:+}", dexterity));
+    }
+    for item in character.equipment.worn() {
+        let bonus = item.armor_class_bonus();
+        if bonus != 0 {
+            lines.push(format!("{} {:+}", item.name, bonus));
+        }
+    }
+    lines
+}
 
-fn update_character_display(
-    characters: Query<&Character>,
-    mut text_query: Query<&mut Text>,
-) {
-    // This would update character info in the UI
-    // For now, it's a placeholder
+// The sort button, a button for each filter and the search box
+fn spawn_inventory_view_controls(parent: &mut ChildBuilder, theme: &UiTheme, view: &InventoryView) {
+    let button = |parent: &mut ChildBuilder, kind: InventoryViewButton, label: String, active: bool| {
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: if active { theme.palette.cell_target } else { theme.palette.cell_filled }.into(),
+                    ..default()
+                },
+                kind,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(label, theme.text(TextSize::Fine, theme.palette.soft)));
+            });
+    };
+    button(parent, InventoryViewButton::Sort, format!("Sort: {} (S)", view.sort.name()), false);
+    button(parent, InventoryViewButton::Filter(None), "All".to_string(), view.filter.is_none());
+    for filter in ItemFilter::ALL {
+        let active = view.filter == Some(filter);
+        button(parent, InventoryViewButton::Filter(Some(filter)), filter.name().to_string(), active);
+    }
+    let search = if view.searching {
+        view.search.display()
+    } else if view.search.is_blank() {
+        "(/)".to_string()
+    } else {
+        view.search.text().to_string()
+    };
+    button(parent, InventoryViewButton::Search, format!("Search: {}", search), view.searching);
 }
 
-fn update_combat_log(
-    mut text_query: Query<&mut Text, With<CombatLog>>,
-) {
-    // This would update the combat log
-    // For now, it's a placeholder
-} 
\ No newline at end of file
+fn spawn_inventory_heading(parent: &mut ChildBuilder, theme: &UiTheme, text: String) {
+    parent.spawn(TextBundle::from_section(text, theme.text(TextSize::Small, theme.palette.muted)));
+}
+
+fn spawn_inventory_row(parent: &mut ChildBuilder, cells: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::Wrap,
+                column_gap: Val::Px(CELL_GAP),
+                row_gap: Val::Px(CELL_GAP),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(cells);
+}
+
+fn spawn_shop_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgb(0.15, 0.1, 0.05).into(),
+                ..default()
+            },
+            ShopUI,
+        ))
+        .with_children(|parent| {
+            // Header
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(60.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.3, 0.2, 0.1).into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section("Market", theme.text(TextSize::Large, theme.palette.text)));
+            });
+
+            // The merchant's stock beside the party's pack
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    flex_grow: 1.0,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.muted)),
+                    ShopText,
+                ));
+            });
+        });
+}
+
+fn spawn_town_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgb(0.1, 0.12, 0.08).into(),
+                ..default()
+            },
+            TownUI,
+        ))
+        .with_children(|parent| {
+            // Header
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(60.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.2, 0.25, 0.15).into(),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section("Town", theme.text(TextSize::Large, theme.palette.text)));
+            });
+
+            // The square, or the location the party is visiting
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    flex_grow: 1.0,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.muted)),
+                    TownText,
+                ));
+            });
+
+            // What the person the party is talking to says, as it comes in
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        min_height: Val::Px(120.0),
+                        padding: UiRect::all(Val::Px(16.0)),
+                        display: Display::None,
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.15, 0.13, 0.1).into(),
+                    ..default()
+                },
+                DialoguePanel,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.parchment)),
+                    DialogueText,
+                ));
+            });
+        });
+}
+
+fn spawn_automap_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Row,
+                    padding: UiRect::all(Val::Px(20.0)),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.08).into(),
+                ..default()
+            },
+            AutomapUI,
+        ))
+        .with_children(|parent| {
+            // The map itself
+            parent.spawn(NodeBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", theme.mono(TextSize::Fine, theme.palette.muted)),
+                    AutomapText,
+                ));
+            });
+
+            // Legend, the square under the cursor and the party's notes
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.soft)).with_style(Style {
+                    width: Val::Px(280.0),
+                    ..default()
+                }),
+                AutomapInfoText,
+            ));
+        });
+}
+
+fn spawn_quests_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.12, 0.1, 0.07).into(),
+                ..default()
+            },
+            QuestsUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Quest Journal",
+                theme.text(TextSize::Subheading, theme.palette.parchment),
+            ));
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.parchment)),
+                QuestText,
+            ));
+        });
+}
+
+fn spawn_spellbook_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.08, 0.08, 0.16).into(),
+                ..default()
+            },
+            SpellbookUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Spellbook", theme.text(TextSize::Subheading, theme.palette.arcane)));
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.soft)),
+                SpellbookText,
+            ));
+        });
+}
+
+fn spawn_load_game_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            LoadGameUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Load Game", theme.text(TextSize::Heading, theme.palette.text)));
+
+            parent.spawn(TextBundle::from_section(
+                "Up/Down to choose, Enter to load, ESC to go back",
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+
+            // Filled in by update_save_slot_list
+            parent.spawn((TextBundle::default(), SaveSlotList));
+        });
+}
+
+fn spawn_game_over_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            GameOverUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "The Party Has Fallen",
+                theme.text(TextSize::Title, theme.palette.danger),
+            ));
+
+            // Filled in by update_run_summary_text
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.soft)),
+                RunSummaryText,
+            ));
+
+            parent.spawn(TextBundle::from_section(
+                "L to load a save, Enter or ESC for the main menu",
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+        });
+}
+
+fn update_run_summary_text(
+    summary: Res<RunSummary>,
+    respawned: Query<(), Added<RunSummaryText>>,
+    mut texts: Query<&mut Text, With<RunSummaryText>>,
+) {
+    if respawned.is_empty() && !summary.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = summary.describe();
+    }
+}
+
+fn spawn_graveyard_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            GraveyardUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Graveyard", theme.text(TextSize::Heading, theme.palette.text)));
+
+            parent.spawn(TextBundle::from_section(
+                "The fallen of every game, the newest first. ESC to go back",
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+
+            // Filled in by update_graveyard_text
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Small, theme.palette.soft)),
+                GraveyardText,
+            ));
+        });
+}
+
+fn update_graveyard_text(
+    graveyard: Res<Graveyard>,
+    respawned: Query<(), Added<GraveyardText>>,
+    mut texts: Query<&mut Text, With<GraveyardText>>,
+) {
+    if respawned.is_empty() && !graveyard.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = graveyard.describe();
+    }
+}
+
+fn spawn_loot_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            LootUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Spoils", theme.text(TextSize::Heading, theme.palette.text)));
+
+            parent.spawn(TextBundle::from_section(
+                "Up/Down to pick, Left/Right for who takes it, Enter to take it all and go on",
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+
+            // Filled in by update_loot_text
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.soft)),
+                LootText,
+            ));
+        });
+}
+
+fn update_loot_text(
+    screen: Res<LootScreen>,
+    characters: Query<(Entity, &Character, &Combatant)>,
+    respawned: Query<(), Added<LootText>>,
+    mut texts: Query<&mut Text, With<LootText>>,
+) {
+    if respawned.is_empty() && !screen.is_changed() {
+        return;
+    }
+    let members = looters(characters.iter());
+    let name = |taker: Option<usize>| {
+        let member = members.get(taker?.min(members.len().saturating_sub(1)))?;
+        Some(member.1.name.clone())
+    };
+    let marker = |line: usize| if line == screen.selected { ">" } else { " " };
+
+    let mut lines = Vec::new();
+    if screen.gold > 0 {
+        let taker = name(screen.takers[0]).unwrap_or_else(|| "shared out".to_string());
+        lines.push(format!("{} {} gp -> {}", marker(0), screen.gold, taker));
+    }
+    for (index, item) in screen.items.iter().enumerate() {
+        let taker = name(screen.takers[index + 1]).unwrap_or_else(|| "left behind".to_string());
+        lines.push(format!("{} {} -> {}", marker(index + 1), item.name, taker));
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn spawn_level_up_ui(mut commands: Commands, theme: Res<UiTheme>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            LevelUpUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Level Up", theme.text(TextSize::Heading, theme.palette.highlight)));
+
+            // Filled in by update_level_up_text
+            parent.spawn((
+                TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.soft)),
+                LevelUpText,
+            ));
+
+            parent.spawn(TextBundle::from_section(
+                "R to roll the hit die, Enter to take the level",
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+        });
+}
+
+fn update_level_up_text(
+    screen: Res<LevelUpScreen>,
+    characters: Query<&Character>,
+    compendium: Res<SpellCompendium>,
+    respawned: Query<(), Added<LevelUpText>>,
+    mut texts: Query<&mut Text, With<LevelUpText>>,
+) {
+    if respawned.is_empty() && !screen.is_changed() {
+        return;
+    }
+    let (Some(advancement), Some(character)) =
+        (&screen.advancement, screen.member.and_then(|member| characters.get(member).ok()))
+    else {
+        return;
+    };
+
+    let mut lines = vec![
+        format!("{} the {} reaches level {}!", character.name, character.class.name(), advancement.level),
+        String::new(),
+    ];
+    let maximum = character.hit_points.maximum;
+    lines.push(match (advancement.hit_die, advancement.roll, advancement.hit_points()) {
+        (Some(_), Some(roll), Some(gained)) => format!(
+            "Hit points: {} rolled [{}] = +{} ({} -> {})",
+            advancement.describe_hit_die(),
+            roll,
+            gained,
+            maximum,
+            maximum + gained
+        ),
+        (None, _, Some(gained)) => format!("Hit points: +{} ({} -> {})", gained, maximum, maximum + gained),
+        _ => format!("Hit points: {} to roll ({} now)", advancement.describe_hit_die(), maximum),
+    });
+    lines.extend(advancement.gains.iter().cloned());
+    if let Some(spell_level) = advancement.spell_level.filter(|_| !advancement.spell_choices.is_empty()) {
+        lines.push(String::new());
+        lines.push(format!("A level {} spell for the spellbook (Up/Down to choose):", spell_level));
+        for (index, name) in advancement.spell_choices.iter().enumerate() {
+            let marker = if index == advancement.chosen { ">" } else { " " };
+            lines.push(format!("{} {}", marker, name));
+            if index == advancement.chosen {
+                if let Some(spell) = compendium.get(name) {
+                    lines.push(format!("    {}", spell.description));
+                }
+            }
+        }
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn spawn_settings_ui(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    config: Res<GameConfig>,
+    path: Option<Res<ConfigPath>>,
+    mod_list: Option<Res<ModList>>,
+) {
+    let path = path.map(|path| path.0.display().to_string()).unwrap_or_else(|| CONFIG_FILE.to_string());
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(40.0)),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: theme.palette.background.into(),
+                ..default()
+            },
+            SettingsUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Settings", theme.text(TextSize::Heading, theme.palette.text)));
+
+            parent.spawn(TextBundle::from_section(
+                format!(
+                    "Edit {} to change these. Keys, window size, text mode and volume apply at once, the rest \
+                     next launch.\n- and + turn the music down and up, [ and ] the sound effects, V speech on or \
+                     off, G text mode. ESC to go back",
+                    path
+                ),
+                theme.text(TextSize::Body, theme.palette.dim),
+            ));
+
+            parent.spawn((
+                TextBundle::from_section(describe_settings(&config), theme.text(TextSize::Body, theme.palette.soft)),
+                SettingsText,
+            ));
+
+            let mods = mod_list.map(|mod_list| mod_list.describe()).unwrap_or_default();
+            parent.spawn(TextBundle::from_section(mods, theme.text(TextSize::Body, theme.palette.soft)));
+        });
+}
+
+// The volume changes from the Settings screen, and anything can be reloaded from the file
+fn update_settings_text(config: Res<GameConfig>, mut texts: Query<&mut Text, With<SettingsText>>) {
+    if !config.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = describe_settings(&config);
+    }
+}
+
+fn describe_settings(config: &GameConfig) -> String {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let backend = match &config.ai_backend {
+        AiBackendKind::Service => format!("AI service at {}", config.ai_service_url),
+        AiBackendKind::OpenAi { base_url, model } => format!("{} at {}", model, base_url),
+        AiBackendKind::Mock => "canned replies (offline)".to_string(),
+    };
+    let keys = &config.keys;
+    [
+        format!("AI: {}", backend),
+        format!(
+            "AI requests: {}s timeout, {} retries, {} at once, {} a minute",
+            config.ai_requests.timeout.as_secs(),
+            config.ai_requests.retries,
+            config.ai_limits.concurrent,
+            config.ai_limits.requests_per_minute
+        ),
+        format!(
+            "Saves: {}/   Journal: {}/   Ironman: {}",
+            config.save_dir,
+            config.journal_dir,
+            on_off(config.ironman)
+        ),
+        format!(
+            "Window: {} x {}   Text mode: {}   Camera: {}",
+            config.resolution.0,
+            config.resolution.1,
+            on_off(config.text_mode),
+            config.camera.describe()
+        ),
+        format!(
+            "Music: {}%   Sound effects: {}%   Speech: {}",
+            (config.music_volume * 100.0).round(),
+            (config.sfx_volume * 100.0).round(),
+            on_off(config.speech)
+        ),
+        format!(
+            "Rules: battle map {}, encumbrance penalties {}, phased rounds {}, treasure XP {}, XP tables {}",
+            on_off(config.ruleset.battle_map),
+            on_off(config.ruleset.encumbrance_penalties),
+            on_off(config.ruleset.phased_rounds),
+            on_off(config.ruleset.treasure_xp),
+            if config.ruleset.experience_tables == ExperienceTables::default() { "B/X" } else { "custom" }
+        ),
+        format!(
+            "Keys: move {}{}{}{}, quick save {}, quick load {}",
+            key_name(keys.move_up),
+            key_name(keys.move_left),
+            key_name(keys.move_down),
+            key_name(keys.move_right),
+            key_name(keys.quick_save),
+            key_name(keys.quick_load)
+        ),
+    ]
+    .join("\n")
+}
+
+fn update_main_menu_buttons(
+    menu: Res<MainMenu>,
+    theme: Res<UiTheme>,
+    mut buttons: Query<(&Interaction, &MainMenuButton, &mut BackgroundColor, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (interaction, button, mut background, children) in buttons.iter_mut() {
+        let available = menu.available(button.0);
+        let selected = MenuChoice::ALL.get(menu.selected) == Some(&button.0);
+        let color = match (available, interaction, selected) {
+            (false, _, _) => theme.palette.button_disabled,
+            (true, Interaction::Pressed, _) => theme.palette.button_pressed,
+            (true, _, true) => theme.palette.button_selected,
+            (true, _, false) => theme.palette.button,
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+        let text_color = if available { theme.palette.text } else { theme.palette.disabled };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                if text.sections[0].style.color != text_color {
+                    text.sections[0].style.color = text_color;
+                }
+            }
+        }
+    }
+}
+
+fn despawn_ui<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Shows the last `rows` entries of the message log, or as far back as it's scrolled
+#[derive(Component)]
+pub struct MessageLogPanel {
+    pub rows: usize,
+}
+
+#[derive(Component)]
+pub struct CombatActionButton(pub String);
+
+// Shows the lead party member. Runs every frame but returns straight away unless a
+// player character changed or the top bar was just respawned.
+fn update_character_display(
+    characters: Query<(&Character, &Combatant)>,
+    changed: Query<(), (Changed<Character>, With<Combatant>)>,
+    respawned: Query<(), Added<PartyNameText>>,
+    mut name_text: Query<&mut Text, (With<PartyNameText>, Without<PartyHpText>)>,
+    mut hp_text: Query<&mut Text, (With<PartyHpText>, Without<PartyNameText>)>,
+) {
+    if changed.is_empty() && respawned.is_empty() {
+        return;
+    }
+    let Some((character, _)) = characters.iter().find(|(_, combatant)| combatant.is_player) else {
+        return;
+    };
+
+    for mut text in name_text.iter_mut() {
+        text.sections[0].value = format!("Character: {} Level {}", character.name, character.level);
+    }
+    for mut text in hp_text.iter_mut() {
+        text.sections[0].value = format!("HP: {}/{}", character.hit_points.current, character.hit_points.maximum);
+    }
+}
+
+fn update_light_text(
+    light: Res<PartyLight>,
+    darkness: Res<Darkness>,
+    respawned: Query<(), Added<LightText>>,
+    mut text_query: Query<&mut Text, With<LightText>>,
+) {
+    if respawned.is_empty() && !light.is_changed() && !darkness.is_changed() {
+        return;
+    }
+    let value = match (light.0, darkness.0) {
+        (Some(lit), _) => format!("{}: {} turns", lit.kind.name(), lit.turns_left),
+        (None, true) => "Darkness".to_string(),
+        (None, false) => String::new(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn update_clock_text(
+    clock: Res<GameClock>,
+    respawned: Query<(), Added<ClockText>>,
+    mut text_query: Query<&mut Text, With<ClockText>>,
+) {
+    if respawned.is_empty() && !clock.is_changed() {
+        return;
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("{} ({})", clock.describe(), clock.time_of_day());
+    }
+}
+
+// What the party can see of the room it's in, which depends on its light
+#[allow(clippy::too_many_arguments)]
+fn update_room_text(
+    current_room: Res<CurrentRoom>,
+    dungeon: Res<CurrentDungeon>,
+    darkness: Res<Darkness>,
+    door_message: Res<DoorMessage>,
+    trap_message: Res<TrapMessage>,
+    treasure_message: Res<TreasureMessage>,
+    wilderness_message: Res<WildernessMessage>,
+    retainer_message: Res<RetainerMessage>,
+    quest_message: Res<QuestMessage>,
+    overland: Res<Overland>,
+    map: Option<Res<DungeonMap>>,
+    fog: Res<FogOfWar>,
+    characters: Query<(&Character, &Combatant)>,
+    respawned: Query<(), Added<RoomText>>,
+    mut text_query: Query<&mut Text, With<RoomText>>,
+) {
+    let messages_changed = door_message.is_changed()
+        || trap_message.is_changed()
+        || treasure_message.is_changed()
+        || wilderness_message.is_changed()
+        || retainer_message.is_changed()
+        || quest_message.is_changed();
+    let changed = current_room.is_changed() || darkness.is_changed() || fog.is_changed() || messages_changed;
+    if respawned.is_empty() && !changed {
+        return;
+    }
+    let mut value = match (&dungeon.0, current_room.0) {
+        (None, _) => match overland.terrain {
+            Some(terrain) => format!("Travelling through the {} ({} miles so far).", terrain.name(), overland.miles),
+            None => "No dungeon yet.".to_string(),
+        },
+        (Some(_), None) => "A passage between rooms.".to_string(),
+        (Some(data), Some(room_id)) => match data.rooms.iter().find(|room| room.id == room_id) {
+            Some(room) => {
+                let infravision = characters
+                    .iter()
+                    .any(|(character, combatant)| combatant.is_player && character.has_infravision());
+                // What's in the room shows once the middle of it comes into sight
+                let contents_seen = map
+                    .as_ref()
+                    .and_then(|map| map.room_area(room_id))
+                    .is_some_and(|area| fog.sight(&area.center()) == Sight::Visible);
+                if contents_seen {
+                    describe_room(room, !darkness.0, infravision)
+                } else {
+                    describe_room(&RoomData { contents: Vec::new(), ..room.clone() }, !darkness.0, infravision)
+                }
+            }
+            None => String::new(),
+        },
+    };
+    let messages = door_message.0.iter()
+        .chain(trap_message.0.iter())
+        .chain(treasure_message.0.iter())
+        .chain(wilderness_message.0.iter())
+        .chain(retainer_message.0.iter())
+        .chain(quest_message.0.iter());
+    for message in messages {
+        value = format!("{}\n\n{}", value, message);
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+// With phased rounds, the phases follow with the one under way in brackets, and below
+// them what the party has declared so far
+fn update_combat_round(
+    combat: Query<Ref<Combat>>,
+    combatants: Query<(&Combatant, &Character)>,
+    respawned: Query<(), Added<CombatRoundText>>,
+    mut text_query: Query<&mut Text, With<CombatRoundText>>,
+) {
+    let Ok(combat) = combat.get_single() else {
+        return;
+    };
+    if !combat.is_changed() && respawned.is_empty() {
+        return;
+    }
+
+    let mut value = format!("COMBAT - Round {}", combat.round);
+    if let Some(current) = combat.phase {
+        let phases: Vec<String> = RoundPhase::ALL
+            .iter()
+            .map(|phase| if *phase == current { format!("[{}]", phase.name()) } else { phase.name().to_string() })
+            .collect();
+        value.push_str(&format!("  {}", phases.join(" > ")));
+        let declared: Vec<String> = combat
+            .declarations
+            .iter()
+            .filter_map(|(entity, declaration)| {
+                let (combatant, character) = combatants.get(*entity).ok()?;
+                combatant.is_player.then(|| format!("{} will {}", character.name, declaration.describe()))
+            })
+            .collect();
+        if !declared.is_empty() {
+            value.push_str(&format!("\nDeclared: {}", declared.join(", ")));
+        }
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+// The player character whose turn it is, if they still have an action to spend
+fn acting_player(combat: &Query<&Combat>, combatants: &Query<(Entity, &Combatant, &Character)>) -> Option<Entity> {
+    let current = combat.get_single().ok()?.current_combatant?;
+    let (_, combatant, character) = combatants.get(current).ok()?;
+    (combatant.is_player && combatant.actions_remaining > 0 && character.is_alive()).then_some(current)
+}
+
+// The template of the spell the acting character has chosen, and who it would catch
+// where they stand, when it's an area spell and the battle map is on
+fn chosen_spell_area(
+    selection: &CombatSelection,
+    combat: &Query<&Combat>,
+    combatants: &Query<(Entity, &Combatant, &Character)>,
+    positions: &Query<&GridPosition>,
+    compendium: &SpellCompendium,
+) -> Option<(Vec<GridPosition>, Vec<Entity>)> {
+    let actor = acting_player(combat, combatants)?;
+    let (_, _, character) = combatants.get(actor).ok()?;
+    let spells = character.ready_spells();
+    let (spell, _) = spells.get(selection.spell % spells.len().max(1))?;
+    let descriptor = compendium.descriptor(spell)?;
+    let at = |entity| positions.get(entity).ok().copied();
+    let template = spell_template(descriptor, actor, selection.target, combatants.iter(), at)?;
+    let caught = default_spell_targets(compendium, spell, actor, selection.target, combatants.iter(), at);
+    Some((template, caught))
+}
+
+// Tab moves the selected target along the living enemies and Shift+Tab along the party,
+// F changes how the party fights, Q moves along the spells the acting character has
+// ready and 1-9 picks one of them outright, and D toggles dropping the treasure when
+// fleeing
+fn cycle_combat_selection(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut selection: ResMut<CombatSelection>,
+    combatants: Query<(Entity, &Combatant, &Character)>,
+) {
+    const QUICK_CAST_KEYS: [KeyCode; 9] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    if let Some(index) = QUICK_CAST_KEYS.iter().position(|key| keyboard_input.just_pressed(*key)) {
+        selection.spell = index;
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        let party = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let side: Vec<Entity> = combatants
+            .iter()
+            .filter(|(_, combatant, character)| combatant.is_player == party && character.is_alive())
+            .map(|(entity, _, _)| entity)
+            .collect();
+        let next = selection
+            .target
+            .and_then(|target| side.iter().position(|&entity| entity == target))
+            .map_or(0, |index| (index + 1) % side.len().max(1));
+        selection.target = side.get(next).copied();
+    }
+    if keyboard_input.just_pressed(KeyCode::F) {
+        selection.style = selection.style.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::Q) {
+        selection.spell = selection.spell.wrapping_add(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::D) {
+        selection.drop_treasure = !selection.drop_treasure;
+    }
+}
+
+// Clicking someone in the lineup targets them, friend or foe
+fn pick_lineup_target(
+    portraits: Query<(&Interaction, &LineupPortrait), Changed<Interaction>>,
+    characters: Query<&Character>,
+    mut selection: ResMut<CombatSelection>,
+) {
+    for (interaction, portrait) in portraits.iter() {
+        let living = characters.get(portrait.0).is_ok_and(|character| character.is_alive());
+        if *interaction == Interaction::Pressed && living {
+            selection.target = Some(portrait.0);
+        }
+    }
+}
+
+// The selected target's portrait is framed in the highlight colour
+fn highlight_lineup_target(
+    selection: Res<CombatSelection>,
+    theme: Res<UiTheme>,
+    respawned: Query<(), Added<LineupPortrait>>,
+    mut portraits: Query<(&LineupPortrait, &mut BorderColor)>,
+) {
+    if !selection.is_changed() && respawned.is_empty() {
+        return;
+    }
+    for (portrait, mut border) in portraits.iter_mut() {
+        let selected = Some(portrait.0) == selection.target;
+        border.0 = if selected { theme.palette.highlight } else { Color::NONE };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_combat_actions(
+    buttons: Query<(&Interaction, &CombatActionButton), Changed<Interaction>>,
+    mut combat: Query<&mut Combat>,
+    mut combatants: Query<(Entity, &mut Combatant, &Character)>,
+    positions: Query<&GridPosition>,
+    selection: Res<CombatSelection>,
+    mut attack_events: EventWriter<AttackEvent>,
+    mut cast_events: EventWriter<CastSpellEvent>,
+    mut flee_events: EventWriter<FleeEvent>,
+    mut log_events: EventWriter<CombatLogEvent>,
+    compendium: Res<SpellCompendium>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Ok(mut combat) = combat.get_single_mut() else {
+            continue;
+        };
+        // While the enemies are willing to talk, attacking starts the fight and fleeing
+        // backs away unharmed
+        if combat.state == CombatState::Parley {
+            match button.0.as_str() {
+                "Attack" => {
+                    log_events.send(CombatLogEvent("The party attacks!".to_string()));
+                    combat.state = CombatState::Initiative;
+                }
+                "Flee" => flee_events.send(FleeEvent { dropped_treasure: false }),
+                _ => {}
+            }
+            continue;
+        }
+
+        let Some(actor) = combat.current_combatant else {
+            continue;
+        };
+        let Ok((_, combatant, character)) = combatants.get(actor) else {
+            continue;
+        };
+        if !combatant.is_player
+            || combatant.actions_remaining == 0
+            || !character.is_alive()
+            || combat.is_surprised(true)
+        {
+            continue;
+        }
+
+        // In a phased round the buttons declare what each member will do, and then in its
+        // phase carry it out; fleeing is open to them any time
+        match (combat.phase, button.0.as_str()) {
+            (_, "Flee") | (None, _) => {}
+            // Parrying is declared and done at once, the guard held through the round
+            (Some(RoundPhase::Declarations), "Parry") => {
+                log_events.send(CombatLogEvent(format!("{} will {}", character.name, Declaration::Parry.describe())));
+                combat.declare(actor, Declaration::Parry);
+            }
+            (Some(RoundPhase::Declarations), "Attack" | "Cast Spell") => {
+                let declaration = if button.0 == "Cast Spell" {
+                    let spells = character.ready_spells();
+                    let Some((spell, _)) = spells.get(selection.spell % spells.len().max(1)) else {
+                        log_events.send(CombatLogEvent(format!("{} has no spells ready", character.name)));
+                        continue;
+                    };
+                    Declaration::Cast(spell.to_string())
+                } else if character.equipment.weapon.as_ref().is_some_and(|item| missile_range(&item.name).is_some()) {
+                    Declaration::Missile
+                } else {
+                    Declaration::Melee
+                };
+                log_events.send(CombatLogEvent(format!("{} will {}", character.name, declaration.describe())));
+                combat.declare(actor, declaration);
+                continue;
+            }
+            (Some(RoundPhase::Missiles | RoundPhase::Melee), "Attack") | (Some(RoundPhase::Magic), "Cast Spell") => {}
+            (Some(RoundPhase::Movement), _) => {
+                log_events.send(CombatLogEvent("Move with the arrow keys, then Space when done".to_string()));
+                continue;
+            }
+            (Some(_), _) => {
+                let declared = combat.declaration(actor).map_or("nothing".to_string(), |declared| declared.describe());
+                log_events.send(CombatLogEvent(format!("{} is to {} this round", character.name, declared)));
+                continue;
+            }
+        }
+
+        let acted = match button.0.as_str() {
+            "Attack" if character.style_refusal(selection.style).is_some() => {
+                log_events.send(CombatLogEvent(character.style_refusal(selection.style).unwrap_or_default()));
+                false
+            }
+            "Attack" => {
+                let target = combatants
+                    .iter()
+                    .filter(|(_, combatant, character)| !combatant.is_player && character.is_alive())
+                    .find(|(entity, _, _)| Some(*entity) == selection.target)
+                    .or_else(|| {
+                        combatants
+                            .iter()
+                            .find(|(_, combatant, character)| !combatant.is_player && character.is_alive())
+                    })
+                    .map(|(entity, _, _)| entity);
+                let weapon = character.equipment.weapon.as_ref().map(|item| item.name.to_lowercase());
+
+                // Don't spend the action on a swing that can't land; move first
+                let occupied: Vec<GridPosition> = combatants
+                    .iter()
+                    .filter(|(_, _, character)| character.is_alive())
+                    .filter_map(|(entity, _, _)| positions.get(entity).ok().copied())
+                    .collect();
+                let reach = target
+                    .and_then(|target| Some((positions.get(actor).ok()?, positions.get(target).ok()?)))
+                    .map_or(Ok(0), |(from, to)| shot_reach(weapon.as_deref(), from, to, &occupied));
+                match (target, reach) {
+                    (Some(target), Ok(_)) => {
+                        let style = selection.style;
+                        attack_events.send(AttackEvent { attacker: actor, target, weapon, spell: None, style });
+                        true
+                    }
+                    (Some(_), Err(reason)) => {
+                        log_events.send(CombatLogEvent(format!("Target is {}; move with the arrow keys", reason)));
+                        false
+                    }
+                    (None, _) => false,
+                }
+            }
+            "Parry" => {
+                log_events.send(CombatLogEvent(format!("{} parries, giving up the attack", character.name)));
+                if let Ok((_, mut combatant, _)) = combatants.get_mut(actor) {
+                    guard(&mut combatant, "Parrying", PARRY_ARMOR_BONUS);
+                }
+                true
+            }
+            "Cast Spell" => {
+                let spells = character.ready_spells();
+                if spells.is_empty() {
+                    log_events.send(CombatLogEvent(format!("{} has no spells ready", character.name)));
+                    false
+                } else {
+                    // What was declared, in a phased round
+                    let spell = match combat.declaration(actor) {
+                        Some(Declaration::Cast(spell)) => spell.clone(),
+                        _ => spells[selection.spell % spells.len()].0.to_string(),
+                    };
+                    let at = |entity| positions.get(entity).ok().copied();
+                    let targets =
+                        default_spell_targets(&compendium, &spell, actor, selection.target, combatants.iter(), at);
+                    cast_events.send(CastSpellEvent { caster: actor, spell, targets });
+                    true
+                }
+            }
+            "Flee" => {
+                flee_events.send(FleeEvent { dropped_treasure: selection.drop_treasure });
+                true
+            }
+            _ => false,
+        };
+
+        if acted {
+            if let Ok((_, mut combatant, _)) = combatants.get_mut(actor) {
+                combatant.actions_remaining = combatant.actions_remaining.saturating_sub(1);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_combat_selection(
+    selection: Res<CombatSelection>,
+    combat: Query<&Combat>,
+    combatants: Query<(Entity, &Combatant, &Character)>,
+    positions: Query<&GridPosition>,
+    changed: Query<(), Changed<Character>>,
+    moved: Query<(), Changed<GridPosition>>,
+    turns: Query<(), Changed<Combat>>,
+    respawned: Query<(), Added<CombatSelectionText>>,
+    mut text_query: Query<&mut Text, With<CombatSelectionText>>,
+    mut buttons: Query<(&CombatActionButton, &mut Tooltip)>,
+    compendium: Res<SpellCompendium>,
+    ruleset: Res<Ruleset>,
+    darkness: Res<Darkness>,
+) {
+    let unchanged = changed.is_empty() && moved.is_empty() && turns.is_empty() && respawned.is_empty();
+    if !selection.is_changed() && unchanged {
+        return;
+    }
+
+    // The acting character's chance of hitting `target`, worked out the way the attack
+    // will be, or why they can't reach it from where they stand
+    let odds = |actor: Entity, target: Entity| -> Option<String> {
+        let (_, attacker_combatant, attacker) = combatants.get(actor).ok()?;
+        let (_, target_combatant, target_character) = combatants.get(target).ok()?;
+        let weapon = attacker.equipment.weapon.as_ref().map(|item| item.name.to_lowercase());
+        let occupied: Vec<GridPosition> = combatants
+            .iter()
+            .filter(|(_, _, character)| character.is_alive())
+            .filter_map(|(entity, _, _)| positions.get(entity).ok().copied())
+            .collect();
+        let range = match (positions.get(actor), positions.get(target)) {
+            (Ok(from), Ok(to)) => match shot_reach(weapon.as_deref(), from, to, &occupied) {
+                Ok(modifier) => modifier,
+                Err(reason) => return Some(reason),
+            },
+            _ => 0,
+        };
+        let mut defender = defending_against(
+            attacker,
+            Some(attacker_combatant),
+            target_character,
+            Some(target_combatant),
+            range,
+            &ruleset,
+            darkness.0,
+        );
+        defender.armor_class -= selection.style.to_hit() as i8;
+        Some(format!("{}% to hit", hit_chance(&ruleset, attacker, &defender, weapon.as_deref())))
+    };
+    // A party member picked for a spell, or else the enemy an attack would go at
+    let living = |(_, _, character): &(Entity, &Combatant, &Character)| character.is_alive();
+    let ally = selection
+        .target
+        .and_then(|target| combatants.get(target).ok())
+        .filter(|fighter| living(fighter) && fighter.1.is_player);
+    let enemies = || combatants.iter().filter(living).filter(|(_, combatant, _)| !combatant.is_player);
+    let enemy = enemies().find(|(entity, _, _)| Some(*entity) == selection.target).or_else(|| enemies().next());
+    let target = match (ally, enemy) {
+        (Some((_, _, character)), _) => format!("{} (ally)", character.name),
+        (None, Some((entity, _, character))) => {
+            match acting_player(&combat, &combatants).and_then(|actor| odds(actor, entity)) {
+                Some(odds) => format!("{}, {}", character.name, odds),
+                None => character.name.clone(),
+            }
+        }
+        (None, None) => "none".to_string(),
+    };
+    let caster = acting_player(&combat, &combatants)
+        .and_then(|actor| combatants.get(actor).ok())
+        .map(|(_, _, character)| character);
+    let chosen_spell = caster.and_then(|character| {
+        let spells = character.ready_spells();
+        let (name, _) = spells.get(selection.spell % spells.len().max(1))?;
+        character.spells.iter().find(|spell| spell.name == *name)
+    });
+    for (button, mut tooltip) in buttons.iter_mut() {
+        if button.0 == "Cast Spell" {
+            tooltip.0 = chosen_spell.map(|spell| spell_tooltip(spell, &compendium)).unwrap_or_default();
+        }
+    }
+    let spell = caster
+        .map(|character| {
+            let spells = character.ready_spells();
+            let chosen = selection.spell % spells.len().max(1);
+            let listed: Vec<String> = spells
+                .iter()
+                .enumerate()
+                .map(|(index, (name, copies))| {
+                    let copies = if *copies > 1 { format!(" x{}", copies) } else { String::new() };
+                    let marker = if index == chosen { "*" } else { "" };
+                    format!("{}{} {}{}", marker, index + 1, name, copies)
+                })
+                .collect();
+            listed.join("  ")
+        })
+        .filter(|listed| !listed.is_empty())
+        .unwrap_or_else(|| "none".to_string());
+    // Who an area spell would catch, friends included, marked '*' on the battle map
+    let caught = chosen_spell_area(&selection, &combat, &combatants, &positions, &compendium)
+        .map(|(_, caught)| {
+            let names: Vec<&str> = caught
+                .iter()
+                .filter_map(|entity| combatants.get(*entity).ok())
+                .map(|(_, _, character)| character.name.as_str())
+                .collect();
+            let names = if names.is_empty() { "nobody".to_string() } else { names.join(", ") };
+            format!("\nThe spell would catch: {}", names)
+        })
+        .unwrap_or_default();
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "Target: {} (Tab, Shift+Tab for the party, or click)  Fighting: {} (F)  Spells: {} (Q, 1-9)  \
+             Drop treasure when fleeing: {} (D){}",
+            target,
+            selection.style.name(),
+            spell,
+            if selection.drop_treasure { "yes" } else { "no" },
+            caught
+        );
+    }
+}
+
+// The icons under each combatant in the lineup, kept up with their effects: green for
+// what helps them, red for what ails them
+fn update_combat_conditions(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    combatants: Query<&Combatant>,
+    changed: Query<(), Changed<Combatant>>,
+    respawned: Query<(), Added<LineupConditions>>,
+    rows: Query<(Entity, &LineupConditions)>,
+) {
+    if changed.is_empty() && respawned.is_empty() {
+        return;
+    }
+    for (row, conditions) in rows.iter() {
+        let Ok(combatant) = combatants.get(conditions.0) else {
+            continue;
+        };
+        commands.entity(row).despawn_descendants().with_children(|parent| {
+            for effect in &combatant.status_effects {
+                let color = if is_beneficial(effect) { theme.palette.good } else { theme.palette.danger };
+                parent.spawn((
+                    TextBundle::from_section(status_icon(effect), theme.mono(TextSize::Fine, color)),
+                    Interaction::default(),
+                    Tooltip(status_tooltip(effect)),
+                ));
+            }
+        });
+    }
+}
+
+// The living, each side in a row of its own with their pictures and names; the fallen
+// drop out of it. Anyone without a picture is a square of their side's colour. The rows
+// are only rebuilt when someone joins or falls, so the animations on the portraits play
+// out through the rest.
+#[allow(clippy::too_many_arguments)]
+fn update_combat_lineup(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    images: Res<SpriteImages>,
+    combatants: Query<(Entity, &Combatant, &Character, Option<&Monster>)>,
+    hurt: Query<(), Changed<Character>>,
+    joined: Query<(), Changed<Combatant>>,
+    respawned: Query<(), Added<CombatLineup>>,
+    rows: Query<Entity, With<CombatLineup>>,
+    mut shown: Local<Vec<Entity>>,
+) {
+    if hurt.is_empty() && joined.is_empty() && respawned.is_empty() && !images.is_changed() {
+        return;
+    }
+    let mut living: Vec<(Entity, bool, &str, Option<Handle<Image>>)> = combatants
+        .iter()
+        .filter(|(_, _, character, _)| character.is_alive())
+        .map(|(entity, combatant, character, monster)| {
+            let picture = images.combatant(character, combatant, monster);
+            (entity, combatant.is_player, character.name.as_str(), picture)
+        })
+        .collect();
+    living.sort_by_key(|(entity, is_player, _, _)| (!is_player, *entity));
+    let entities: Vec<Entity> = living.iter().map(|(entity, _, _, _)| *entity).collect();
+    if entities == *shown && respawned.is_empty() && !images.is_changed() {
+        return;
+    }
+    *shown = entities;
+
+    for row in rows.iter() {
+        commands.entity(row).despawn_descendants().with_children(|parent| {
+            for side in [true, false] {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            column_gap: Val::Px(12.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for (entity, _, name, picture) in living.iter().filter(|(_, is_player, _, _)| *is_player == side) {
+                            let color = if side { theme.palette.info } else { theme.palette.hostile };
+                            parent
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        flex_direction: FlexDirection::Column,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    // The default image is plain white, so the square takes the colour whole
+                                    let (texture, tint) = match picture {
+                                        Some(picture) => (picture.clone(), Color::WHITE),
+                                        None => (Handle::default(), color),
+                                    };
+                                    parent.spawn((
+                                        ImageBundle {
+                                            style: Style {
+                                                width: Val::Px(PORTRAIT_SIZE),
+                                                height: Val::Px(PORTRAIT_SIZE),
+                                                border: UiRect::all(Val::Px(2.0)),
+                                                ..default()
+                                            },
+                                            background_color: tint.into(),
+                                            image: UiImage::new(texture),
+                                            ..default()
+                                        },
+                                        BorderColor(Color::NONE),
+                                        Interaction::default(),
+                                        LineupPortrait(*entity),
+                                    ));
+                                    parent.spawn(TextBundle::from_section(*name, theme.text(TextSize::Fine, color)));
+                                    parent.spawn((
+                                        NodeBundle {
+                                            style: Style {
+                                                flex_direction: FlexDirection::Row,
+                                                column_gap: Val::Px(4.0),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        LineupConditions(*entity),
+                                    ));
+                                });
+                        }
+                    });
+            }
+        });
+    }
+}
+
+// Party members are drawn by the first letter of their name in upper case, enemies in
+// lower case and the fallen as 'x'; the empty squares a chosen area spell would cover
+// are '*'
+#[allow(clippy::too_many_arguments)]
+fn update_battle_map(
+    map: Option<Res<BattleMap>>,
+    combatants: Query<(&GridPosition, &Combatant, &Character)>,
+    moved: Query<(), Changed<GridPosition>>,
+    movement: Res<TurnMovement>,
+    respawned: Query<(), Added<BattleMapText>>,
+    mut text_query: Query<&mut Text, With<BattleMapText>>,
+    selection: Res<CombatSelection>,
+    combat: Query<&Combat>,
+    turns: Query<(), Changed<Combat>>,
+    fighters: Query<(Entity, &Combatant, &Character)>,
+    positions: Query<&GridPosition>,
+    compendium: Res<SpellCompendium>,
+) {
+    let map_changed = map.as_ref().is_some_and(|map| map.is_changed());
+    let aim_changed = selection.is_changed() || !turns.is_empty();
+    if moved.is_empty() && respawned.is_empty() && !movement.is_changed() && !map_changed && !aim_changed {
+        return;
+    }
+
+    let value = match map {
+        Some(map) => {
+            let template = chosen_spell_area(&selection, &combat, &fighters, &positions, &compendium)
+                .map(|(template, _)| template)
+                .unwrap_or_default();
+            let mut markers: Vec<(GridPosition, char)> = combatants
+                .iter()
+                .map(|(position, combatant, character)| {
+                    let initial = character.name.chars().next().unwrap_or('?');
+                    let marker = match (character.is_alive(), combatant.is_player) {
+                        (false, _) => 'x',
+                        (true, true) => initial.to_ascii_uppercase(),
+                        (true, false) => initial.to_ascii_lowercase(),
+                    };
+                    (*position, marker)
+                })
+                .collect();
+            markers.extend(template.into_iter().map(|square| (square, '*')));
+            format!("{}\nMovement left: {}'", map.render(&markers), movement.feet_left)
+        }
+        None => String::new(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+// The lead party member's equipment, pack and open container as a grid of cells, then
+// their purse and load and the keys
+#[allow(clippy::too_many_arguments)]
+fn update_inventory_text(
+    mut commands: Commands,
+    characters: Query<(&Character, &Combatant)>,
+    changed: Query<(), (Changed<Character>, With<Combatant>)>,
+    message: Res<InventoryMessage>,
+    open: Res<OpenContainer>,
+    view: Res<InventoryView>,
+    theme: Res<UiTheme>,
+    images: Res<SpriteImages>,
+    respawned: Query<(), Added<InventoryText>>,
+    grids: Query<Entity, With<InventoryGrid>>,
+    mut text_query: Query<&mut Text, With<InventoryText>>,
+) {
+    if changed.is_empty()
+        && respawned.is_empty()
+        && !message.is_changed()
+        && !open.is_changed()
+        && !view.is_changed()
+        && !images.is_changed()
+    {
+        return;
+    }
+    let Some((character, _)) = characters.iter().find(|(_, combatant)| combatant.is_player) else {
+        return;
+    };
+
+    let items = &character.inventory.items;
+    let shown = view.shown(items);
+    let pack_cells = INVENTORY_CELLS.max(shown.len() + 1).next_multiple_of(INVENTORY_COLUMNS);
+    let opened = open.0.and_then(|index| items.get(index).map(|item| (index, item)));
+    for grid in grids.iter() {
+        commands.entity(grid).despawn_descendants().with_children(|parent| {
+            spawn_inventory_heading(
+                parent,
+                &theme,
+                format!(
+                    "{} the {}, level {}  {} XP ({:+}% prime requisite)  carrying {} lb",
+                    character.name,
+                    character.class.name(),
+                    character.level,
+                    character.experience,
+                    character.experience_bonus,
+                    character.inventory.current_weight
+                ),
+            );
+            spawn_paper_doll(parent, &theme, &images, character);
+            spawn_inventory_row(parent, |parent| spawn_inventory_view_controls(parent, &theme, &view));
+            spawn_inventory_heading(
+                parent,
+                &theme,
+                if view.is_narrowed() {
+                    format!("Pack ({} of {} shown)", shown.len(), items.len())
+                } else {
+                    "Pack".to_string()
+                },
+            );
+            // The free cells after the items stand for the end of the pack
+            spawn_inventory_row(parent, |parent| {
+                for &index in &shown {
+                    let label = format!("{}", index + 1);
+                    let item = items.get(index);
+                    spawn_inventory_cell(parent, &theme, ItemPlace::Pack(index), &label, item, character, &images);
+                }
+                for _ in shown.len()..pack_cells {
+                    spawn_inventory_cell(parent, &theme, ItemPlace::Pack(items.len()), "", None, character, &images);
+                }
+            });
+            let Some((index, item)) = opened else {
+                return;
+            };
+            let Some(container) = &item.container else {
+                return;
+            };
+            spawn_inventory_heading(
+                parent,
+                &theme,
+                format!("In the {} ({} of {} lb)", item.name.to_lowercase(), container.load(), container.capacity),
+            );
+            spawn_inventory_row(parent, |parent| {
+                for inside in 0..=container.contents.len() {
+                    let item = container.contents.get(inside);
+                    let label = if item.is_some() { format!("{}", inside + 1) } else { String::new() };
+                    let place = ItemPlace::Stowed { container: index, item: inside };
+                    spawn_inventory_cell(parent, &theme, place, &label, item, character, &images);
+                }
+            });
+        });
+    }
+
+    let mut lines = vec![format!("{} gp", character.inventory.gold)];
+    let sustenance = &character.sustenance;
+    if sustenance.days_without_food > 0 {
+        lines.push(format!("Hungry for {} days", sustenance.days_without_food));
+    }
+    if sustenance.days_without_water > 0 {
+        lines.push(format!("Thirsty for {} days", sustenance.days_without_water));
+    }
+    lines.push(format!(
+        "Load: {} coins, {} ({}' per round)",
+        character.carried_coins(),
+        character.encumbrance().name(),
+        character.movement_rate()
+    ));
+    lines.push(String::new());
+    if let Some(message) = &message.0 {
+        lines.push(message.clone());
+    }
+    lines.push("Drag items between cells   Right-click to use, drop or examine   I or ESC to close".to_string());
+    lines.push("S sort   F filter   / search (Enter keeps it, ESC clears it)".to_string());
+    lines.push("1-9 equip   Shift+1-8 take off weapon/armor/shield/helmet/cloak/boots/rings".to_string());
+    lines.push("O open next container   Ctrl+1-9 put in   Alt+1-9 take out".to_string());
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+// While an item is dragged its cell is marked and the cell under the cursor lights up
+// as the place it would go
+fn update_inventory_cells(
+    dragged: Res<DraggedItem>,
+    theme: Res<UiTheme>,
+    mut cells: Query<(&Interaction, &InventoryCell, &mut BackgroundColor)>,
+) {
+    for (interaction, cell, mut background) in cells.iter_mut() {
+        let color = match (dragged.0, interaction) {
+            (Some(from), _) if from == cell.place => theme.palette.cell_dragged,
+            (Some(_), Interaction::Hovered) => theme.palette.cell_target,
+            (None, Interaction::Hovered | Interaction::Pressed) => theme.palette.cell_hovered,
+            _ if cell.filled => theme.palette.cell_filled,
+            _ => theme.palette.cell_empty,
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
+
+fn update_drag_ghost(
+    dragged: Res<DraggedItem>,
+    characters: Query<(&Character, &Combatant)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut ghosts: Query<(&mut Style, &mut Text), With<DragGhost>>,
+) {
+    let cursor = windows.get_single().ok().and_then(|window| window.cursor_position());
+    let lead = characters.iter().find(|(_, combatant)| combatant.is_player).map(|(character, _)| character);
+    let name = dragged
+        .0
+        .zip(lead)
+        .and_then(|(place, character)| item_at(character, place))
+        .map(|item| item.name.clone());
+    for (mut style, mut text) in ghosts.iter_mut() {
+        let (Some(name), Some(cursor)) = (&name, cursor) else {
+            if style.display != Display::None {
+                style.display = Display::None;
+            }
+            continue;
+        };
+        style.display = Display::Flex;
+        style.left = Val::Px(cursor.x + 12.0);
+        style.top = Val::Px(cursor.y + 4.0);
+        if text.sections[0].value != *name {
+            text.sections[0].value = name.clone();
+        }
+    }
+}
+
+fn update_item_menu(
+    menu: Res<ItemMenu>,
+    theme: Res<UiTheme>,
+    mut menus: Query<&mut Style, With<ItemMenuUI>>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), With<ItemMenuButton>>,
+) {
+    if menu.is_changed() {
+        for mut style in menus.iter_mut() {
+            match menu.0 {
+                Some((_, at)) => {
+                    style.display = Display::Flex;
+                    style.left = Val::Px(at.x);
+                    style.top = Val::Px(at.y);
+                }
+                None => style.display = Display::None,
+            }
+        }
+    }
+    for (interaction, mut background) in buttons.iter_mut() {
+        let palette = &theme.palette;
+        let color = if *interaction == Interaction::None { palette.cell_empty } else { palette.cell_hovered };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
+
+// The selected merchant's wares and the lead party member's pack, priced for trade
+fn update_shop_text(
+    characters: Query<(&Character, &Combatant)>,
+    changed: Query<(), (Changed<Character>, With<Combatant>)>,
+    market: Res<Market>,
+    message: Res<ShopMessage>,
+    respawned: Query<(), Added<ShopText>>,
+    mut text_query: Query<&mut Text, With<ShopText>>,
+) {
+    if changed.is_empty() && respawned.is_empty() && !market.is_changed() && !message.is_changed() {
+        return;
+    }
+
+    let mut lines = Vec::new();
+    if let Some(merchant) = market.current() {
+        lines.push(format!("{} ({} gp in the till)", merchant.name, merchant.purse));
+        for (index, entry) in merchant.stock.iter().enumerate().take(9) {
+            lines.push(format!(
+                "{}. {} - {} gp ({} left)",
+                index + 1,
+                entry.item.name,
+                buy_price(&entry.item),
+                entry.quantity
+            ));
+        }
+    }
+    lines.push(String::new());
+    if let Some((character, _)) = characters.iter().find(|(_, combatant)| combatant.is_player) {
+        lines.push(format!("{}: {} gp", character.name, character.inventory.gold));
+        for (index, item) in character.inventory.items.iter().enumerate().take(9) {
+            lines.push(format!("Shift+{}. {} - sells for {} gp", index + 1, item.name, sell_price(item)));
+        }
+    }
+    lines.push(String::new());
+    if let Some(message) = &message.0 {
+        lines.push(message.clone());
+    }
+    lines.push("1-9 buy   Shift+1-9 sell   Tab next merchant   M or ESC to leave".to_string());
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+// The square's locations, or who's at the one the party is visiting
+#[allow(clippy::too_many_arguments)]
+fn update_town_text(
+    town: Res<Town>,
+    message: Res<TownMessage>,
+    clock: Res<GameClock>,
+    visit: Res<TempleVisit>,
+    favor: Res<TempleFavor>,
+    hall: Res<HiringHall>,
+    retainer_message: Res<RetainerMessage>,
+    quest_message: Res<QuestMessage>,
+    characters: Query<(&Character, &Combatant, Option<&Retainer>)>,
+    respawned: Query<(), Added<TownText>>,
+    mut text_query: Query<&mut Text, With<TownText>>,
+) {
+    if respawned.is_empty()
+        && !town.is_changed()
+        && !message.is_changed()
+        && !clock.is_changed()
+        && !visit.is_changed()
+        && !favor.is_changed()
+        && !hall.is_changed()
+        && !retainer_message.is_changed()
+        && !quest_message.is_changed()
+    {
+        return;
+    }
+
+    let gold: u32 = characters
+        .iter()
+        .filter(|(_, combatant, _)| combatant.is_player)
+        .map(|(character, _, _)| character.inventory.gold)
+        .sum();
+    let mut lines = vec![format!("{} ({})   Party purse: {} gp", clock.describe(), clock.time_of_day(), gold), String::new()];
+    match town.location {
+        None => {
+            lines.push("The town square. Where to?".to_string());
+            for (index, location) in Location::ALL.iter().enumerate() {
+                lines.push(format!("{}. {}", index + 1, location.name()));
+            }
+        }
+        Some(location) => {
+            lines.push(location.name().to_string());
+            lines.push(location.description().to_string());
+            lines.push(String::new());
+            for (index, resident) in location.residents().iter().enumerate() {
+                let marker = if index == town.resident { ">" } else { " " };
+                lines.push(format!("{} {}", marker, resident.name));
+            }
+            if location == Location::Temple {
+                lines.push(String::new());
+                let members: Vec<&Character> = characters
+                    .iter()
+                    .filter(|(_, combatant, _)| combatant.is_player)
+                    .map(|(character, _, _)| character)
+                    .collect();
+                for (index, character) in members.iter().enumerate() {
+                    let marker = if index == visit.patient { "*" } else { " " };
+                    let state = if character.is_alive() { "" } else { ", dead" };
+                    lines.push(format!(
+                        "{} {} ({}/{} hp{})",
+                        marker, character.name, character.hit_points.current, character.hit_points.maximum, state
+                    ));
+                }
+                if let Some(patient) = members.get(visit.patient) {
+                    lines.push(String::new());
+                    lines.extend(describe_services(patient, &favor));
+                }
+                lines.push(format!("Standing with the temple: {}", favor.standing()));
+            }
+            if location == Location::Guild {
+                lines.push(String::new());
+                for (index, candidate) in hall.candidates.iter().enumerate() {
+                    lines.push(format!("{}. {}", index + 1, candidate.describe()));
+                    lines.push(format!("   {}", candidate.personality));
+                }
+                let hired = characters.iter().filter(|(_, _, retainer)| retainer.is_some()).count();
+                let limit = retainer_limit(
+                    characters
+                        .iter()
+                        .filter(|(_, combatant, retainer)| combatant.is_player && retainer.is_none())
+                        .map(|(character, _, _)| character),
+                );
+                lines.push(format!("Retainers: {} of {}", hired, limit));
+            }
+        }
+    }
+    lines.push(String::new());
+    for message in message.0.iter().chain(retainer_message.0.iter()).chain(quest_message.0.iter()) {
+        lines.push(message.clone());
+        lines.push(String::new());
+    }
+    let keys = match town.location {
+        None => "1-5 visit   J set out   Q quests   ESC menu".to_string(),
+        Some(Location::Temple) => {
+            "C talk   Up/Down patient   1-5 service   D donate 100 gp   B bury   ESC back".to_string()
+        }
+        Some(Location::Guild) => "C talk   Tab next   R seek hirelings   1-3 hire   ESC back".to_string(),
+        Some(Location::Inn) => "C talk   Tab next   L lodge   R rumors   P carouse   ESC back".to_string(),
+        Some(location) if location.merchant().is_some() => "C talk   Tab next   M trade   ESC back".to_string(),
+        Some(_) => "C talk   Tab next   ESC back".to_string(),
+    };
+    lines.push(keys);
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+// The dialogue panel, shown while it's open with as much of the reply as has been typed
+fn update_dialogue_panel(
+    dialogue: Res<Dialogue>,
+    respawned: Query<(), Added<DialogueText>>,
+    mut panel_query: Query<&mut Style, With<DialoguePanel>>,
+    mut text_query: Query<&mut Text, With<DialogueText>>,
+) {
+    if respawned.is_empty() && !dialogue.is_changed() {
+        return;
+    }
+
+    for mut style in panel_query.iter_mut() {
+        style.display = if dialogue.is_open() { Display::Flex } else { Display::None };
+    }
+    let waiting = if dialogue.text.is_empty() { "..." } else { "" };
+    let mut lines = Vec::new();
+    if let Some(said) = &dialogue.said {
+        lines.push(format!("You: \"{}\"", said));
+    }
+    lines.push(format!("{}: \"{}{}\"", dialogue.speaker, dialogue.visible(), waiting));
+    match &dialogue.reply {
+        Some(reply) => lines.push(format!("\n> {}\nEnter say   ESC close", reply.display())),
+        None => lines.push("\nSpace skip   ESC close".to_string()),
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+// Offers, then the party's quests by status, with the selected one's details
+fn update_quest_text(
+    log: Res<QuestLog>,
+    clock: Res<GameClock>,
+    journal: Res<QuestJournal>,
+    message: Res<QuestMessage>,
+    respawned: Query<(), Added<QuestText>>,
+    mut text_query: Query<&mut Text, With<QuestText>>,
+) {
+    if respawned.is_empty() && !log.is_changed() && !journal.is_changed() && !message.is_changed() {
+        return;
+    }
+
+    let mut lines = Vec::new();
+    let mut heading = "";
+    for (index, line) in journal_order(&log).into_iter().enumerate() {
+        let marker = if index == journal.selected { ">" } else { " " };
+        let (section, title, quest) = match line {
+            JournalLine::Offer(offer) => {
+                let offer = &log.offers[offer];
+                ("Offered", format!("{} (from {})", offer.quest.title, offer.giver), None)
+            }
+            JournalLine::Quest(quest) => {
+                let quest = &log.quests[quest];
+                (quest.status.name(), quest.data.title.clone(), Some(quest))
+            }
+        };
+        if section != heading {
+            heading = section;
+            lines.push(String::new());
+            lines.push(section.to_string());
+        }
+        lines.push(format!("{} {}", marker, title));
+        if index == journal.selected {
+            let data = match line {
+                JournalLine::Offer(offer) => &log.offers[offer].quest,
+                JournalLine::Quest(quest) => &log.quests[quest].data,
+            };
+            lines.extend(describe_quest(data, quest, clock.day()));
+        }
+    }
+    if lines.is_empty() {
+        lines.push("The party has no quests.".to_string());
+    }
+    lines.push(String::new());
+    if let Some(message) = &message.0 {
+        lines.push(message.clone());
+        lines.push(String::new());
+    }
+    lines.push("Up/Down select   Y accept   N decline   G negotiate   B betray   A abandon   Q/ESC back".to_string());
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+// The caster's slots for each level, then their spells by level with the selected one
+// described
+fn update_spellbook_text(
+    book: Res<Spellbook>,
+    compendium: Res<SpellCompendium>,
+    characters: Query<(Entity, &Character, &Combatant)>,
+    changed: Query<(), Changed<Character>>,
+    respawned: Query<(), Added<SpellbookText>>,
+    mut text_query: Query<&mut Text, With<SpellbookText>>,
+) {
+    if respawned.is_empty() && changed.is_empty() && !book.is_changed() && !compendium.is_changed() {
+        return;
+    }
+
+    let casters = party_casters(characters.iter());
+    let mut lines = Vec::new();
+    match casters.get(book.caster.min(casters.len().saturating_sub(1))) {
+        None => lines.push("No one in the party casts spells.".to_string()),
+        Some((_, character)) => {
+            let names: Vec<&str> = casters.iter().map(|(_, caster)| caster.name.as_str()).collect();
+            lines.push(format!("{} the {} (level {})", character.name, character.class.name(), character.level));
+            if names.len() > 1 {
+                lines.push(format!("Casters: {}", names.join(", ")));
+            }
+            lines.push(String::new());
+            let slots = character.class.spell_slots(character.level);
+            for (index, &count) in slots.iter().enumerate() {
+                let level = index as u8 + 1;
+                let held: Vec<String> = character
+                    .memorized_spells
+                    .iter()
+                    .filter(|spell| spell.level == level)
+                    .map(|spell| if spell.cast { format!("({})", spell.name) } else { spell.name.clone() })
+                    .collect();
+                let empty = (count as usize).saturating_sub(held.len());
+                let mut shown = held;
+                shown.extend(std::iter::repeat_n("-".to_string(), empty));
+                lines.push(format!("Level {} slots: {}", level, shown.join(", ")));
+            }
+            if slots.is_empty() {
+                lines.push(format!("No spells yet at level {}", character.level));
+            }
+
+            let mut heading = 0;
+            for (index, spell) in spellbook_order(character).into_iter().enumerate() {
+                if spell.level != heading {
+                    heading = spell.level;
+                    lines.push(String::new());
+                    lines.push(format!("Level {}", spell.level));
+                }
+                let marker = if index == book.selected { ">" } else { " " };
+                let held = character.memorized_spells.iter().filter(|held| held.name == spell.name).count();
+                let held = if held > 0 { format!("  x{}", held) } else { String::new() };
+                lines.push(format!("{} {}{}", marker, spell.name, held));
+                if index == book.selected {
+                    lines.push(format!("    {}", spell.description));
+                    lines.push(format!(
+                        "    Range {}   Duration {}   Casting time {}",
+                        spell.range, spell.duration, spell.casting_time
+                    ));
+                    if let Some(descriptor) = compendium.descriptor(&spell.name) {
+                        lines.push(format!("    In a fight: {}", descriptor.describe()));
+                    }
+                }
+            }
+        }
+    }
+    lines.push(String::new());
+    if let Some(message) = &book.message {
+        lines.push(message.clone());
+        lines.push(String::new());
+    }
+    lines.push(if book.preparing {
+        "Preparing after rest: Enter memorize   Backspace forget   Up/Down select   Tab next caster   B/ESC done"
+    } else {
+        "Up/Down select   C cast a cure   Tab next caster   B/ESC back   (spells are chosen after a night's rest)"
+    }.to_string());
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn describe_quest(data: &QuestData, quest: Option<&Quest>, today: u64) -> Vec<String> {
+    let mut lines = vec![format!("    {}", data.description)];
+    for (index, objective) in data.objectives.iter().enumerate() {
+        let done = quest.is_some_and(|quest| quest.is_done(index));
+        lines.push(format!("    [{}] {}", if done { "x" } else { " " }, objective));
+    }
+    let mut reward = format!("    Reward: {} XP, {} gp", data.reward.experience, data.reward.gold);
+    if !data.reward.items.is_empty() {
+        reward = format!("{}, {}", reward, data.reward.items.join(", "));
+    }
+    lines.push(reward);
+    if let Some(resolution) = quest.and_then(|quest| quest.resolution) {
+        lines.push(format!("    Outcome: {}", resolution.name()));
+    }
+    let left = quest.filter(|quest| quest.status == QuestStatus::Active).and_then(|quest| quest.days_left(today));
+    match (left, data.time_limit) {
+        (Some(0), _) => lines.push("    Due today!".to_string()),
+        (Some(1), _) => lines.push("    Due tomorrow!".to_string()),
+        (Some(left), _) if left <= DEADLINE_WARNING_DAYS => lines.push(format!("    Due in {} days!", left)),
+        (Some(left), _) => lines.push(format!("    Due in {} days", left)),
+        (None, Some(days)) if quest.is_none() => lines.push(format!("    Within {} days", days)),
+        _ => {}
+    }
+    lines
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_automap_text(
+    view: Res<AutomapView>,
+    fog: Res<FogOfWar>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    tokens: Query<&Position, With<PartyToken>>,
+    respawned: Query<(), Added<AutomapText>>,
+    mut map_text: Query<&mut Text, (With<AutomapText>, Without<AutomapInfoText>)>,
+    mut info_text: Query<&mut Text, With<AutomapInfoText>>,
+) {
+    if respawned.is_empty() && !view.is_changed() && !fog.is_changed() {
+        return;
+    }
+    let Some(map) = map else {
+        for mut text in map_text.iter_mut() {
+            text.sections[0].value = "No dungeon yet.".to_string();
+        }
+        return;
+    };
+
+    let (columns, rows) = view.size();
+    let mut markers = vec![(view.cursor, 'X')];
+    if let Ok(position) = tokens.get_single() {
+        markers.push((position.0, '@'));
+    }
+    let drawn = fog.render_automap(&map, view.top_left(), columns, rows, &markers);
+    for mut text in map_text.iter_mut() {
+        text.sections[0].value = drawn.clone();
+        text.sections[0].style.font_size = view.font_size();
+    }
+
+    let mut lines = vec![
+        dungeon.0.as_ref().map_or_else(String::new, |data| data.name.clone()),
+        String::new(),
+        "@ party   X cursor".to_string(),
+        "+ door   / open door   ^ trap".to_string(),
+        "> down   < up".to_string(),
+        String::new(),
+    ];
+    let room_name = map
+        .room_at(&view.cursor)
+        .filter(|_| fog.is_explored(&view.cursor))
+        .and_then(|room_id| dungeon.0.as_ref()?.rooms.iter().find(|room| room.id == room_id))
+        .map(|room| room.name.clone());
+    lines.push(format!(
+        "Cursor ({}, {}): {}",
+        view.cursor.x,
+        view.cursor.y,
+        room_name.as_deref().unwrap_or("-")
+    ));
+    lines.push(String::new());
+    lines.push("Notes:".to_string());
+    for (index, annotation) in fog.annotations.iter().enumerate() {
+        lines.push(format!(
+            "{}. {} ({}, {})",
+            index + 1,
+            annotation.text,
+            annotation.position.x,
+            annotation.position.y
+        ));
+    }
+    lines.push(String::new());
+    lines.push("WASD move cursor (Shift: 5)".to_string());
+    lines.push("+/- zoom   N note   Del remove".to_string());
+    lines.push("Tab or ESC to close".to_string());
+
+    for mut text in info_text.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+fn message_color(theme: &UiTheme, category: MessageCategory) -> Color {
+    match category {
+        MessageCategory::Combat => theme.palette.hostile,
+        MessageCategory::Exploration => theme.palette.soft,
+        MessageCategory::Dialogue => theme.palette.parchment,
+        MessageCategory::Notice => theme.palette.info,
+    }
+}
+
+// Each entry in its category's colour after a dim time stamp, with a note when the log
+// is scrolled back
+fn update_message_log(
+    log: Res<MessageLog>,
+    theme: Res<UiTheme>,
+    respawned: Query<(), Added<MessageLogPanel>>,
+    mut panels: Query<(&mut Text, &MessageLogPanel)>,
+) {
+    if !log.is_changed() && respawned.is_empty() {
+        return;
+    }
+
+    let style = |color| theme.text(TextSize::Small, color);
+    for (mut text, panel) in panels.iter_mut() {
+        let mut sections = Vec::new();
+        for entry in log.visible(panel.rows) {
+            sections.push(TextSection::new(format!("{}  ", entry.stamp()), style(theme.palette.faint)));
+            sections.push(TextSection::new(format!("{}\n", entry.text), style(message_color(&theme, entry.category))));
+        }
+        if log.scroll > 0 {
+            sections.push(TextSection::new(
+                format!("-- {} newer (Page Down) --", log.scroll),
+                style(theme.palette.dim),
+            ));
+        }
+        text.sections = sections;
+    }
+}
+
+// One line per slot, the selected one highlighted
+fn update_save_slot_list(
+    slots: Res<SaveSlots>,
+    theme: Res<UiTheme>,
+    respawned: Query<(), Added<SaveSlotList>>,
+    mut text_query: Query<&mut Text, With<SaveSlotList>>,
+) {
+    if !slots.is_changed() && respawned.is_empty() {
+        return;
+    }
+
+    let sections: Vec<TextSection> = if slots.slots.is_empty() {
+        vec![TextSection::new("No saved games", theme.text(TextSize::Body, theme.palette.muted))]
+    } else {
+        slots
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| {
+                let selected = index == slots.selected;
+                TextSection::new(
+                    format!(
+                        "{} {:<12} {} (level {}) - {} - {}{}\n",
+                        if selected { ">" } else { " " },
+                        slot.slot,
+                        slot.character_name,
+                        slot.level,
+                        slot.location,
+                        format_date(slot.saved_at),
+                        if slot.ironman { " - ironman" } else { "" },
+                    ),
+                    theme.text(
+                        TextSize::Body,
+                        if selected { theme.palette.highlight } else { theme.palette.soft },
+                    ),
+                )
+            })
+            .collect()
+    };
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// Ability scores, then the classes with the ones the scores don't qualify for dimmed
+fn update_character_draft(
+    draft: Option<Res<CharacterDraft>>,
+    theme: Res<UiTheme>,
+    respawned: Query<(), Added<CharacterDraftText>>,
+    mut text_query: Query<&mut Text, With<CharacterDraftText>>,
+) {
+    let Some(draft) = draft else {
+        return;
+    };
+    if !draft.is_changed() && respawned.is_empty() {
+        return;
+    }
+
+    let style = |color| theme.text(TextSize::Body, color);
+    let abilities = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Intelligence,
+        Ability::Wisdom,
+        Ability::Charisma,
+    ];
+    let scores: Vec<String> = abilities
+        .iter()
+        .map(|&ability| format!("{} {}", ability.abbreviation(), draft.stats.get(ability)))
+        .collect();
+
+    let mut sections = vec![TextSection::new(
+        format!("\n{}\n\n", scores.join("   ")),
+        style(theme.palette.text),
+    )];
+    for (index, class) in CharacterClass::ALL.iter().enumerate() {
+        let color = if draft.class.as_ref() == Some(class) {
+            theme.palette.highlight
+        } else if class.accepts(&draft.stats) {
+            theme.palette.soft
+        } else {
+            theme.palette.disabled
+        };
+        let primes: Vec<&str> = class.prime_requisites().iter().map(Ability::abbreviation).collect();
+        let bonus = class.experience_bonus(&draft.stats);
+        sections.push(TextSection::new(
+            format!("{}. {} (prime requisite {}, XP {:+}%)\n", index + 1, class.name(), primes.join(", "), bonus),
+            style(color),
+        ));
+    }
+    if let Some(name) = &draft.name {
+        sections.push(TextSection::new(
+            format!("\nName: {}\nEnter to begin, ESC to go back\n", name.display()),
+            style(theme.palette.highlight),
+        ));
+    }
+    if let Some(message) = &draft.message {
+        sections.push(TextSection::new(format!("\n{}", message), style(theme.palette.danger)));
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections = sections.clone();
+    }
+}
+
+// Short-lived banner across the top of the screen for out-of-band notifications
+#[derive(Component)]
+pub struct NoticeBanner {
+    timer: Timer,
+}
+
+fn spawn_notice(commands: &mut Commands, theme: &UiTheme, text: String, color: Color) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(70.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            NoticeBanner {
+                timer: Timer::from_seconds(6.0, TimerMode::Once),
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(text, theme.text(TextSize::Body, color))
+                    .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.8)),
+            );
+        });
+}
+
+fn show_campaign_notices(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut turn_started: EventReader<CampaignTurnStarted>,
+    mut sync_failed: EventReader<CampaignSyncFailed>,
+) {
+    for event in turn_started.read() {
+        spawn_notice(
+            &mut commands,
+            &theme,
+            format!("It's your turn in campaign {} - press T to end your turn", event.campaign_id),
+            theme.palette.highlight,
+        );
+    }
+    for event in sync_failed.read() {
+        spawn_notice(
+            &mut commands,
+            &theme,
+            format!("Campaign sync failed: {}", event.message),
+            theme.palette.danger,
+        );
+    }
+}
+
+fn show_load_notices(mut commands: Commands, theme: Res<UiTheme>, mut loaded: EventReader<GameLoaded>) {
+    for event in loaded.read() {
+        if event.migrations.is_empty() {
+            continue;
+        }
+        spawn_notice(
+            &mut commands,
+            &theme,
+            format!(
+                "Save '{}' was made by an older version and has been upgraded ({} changes, see log)",
+                event.slot,
+                event.migrations.entries.len()
+            ),
+            theme.palette.highlight,
+        );
+    }
+}
+
+// Anything waiting on the service falls back on its own, so a failure only needs saying
+fn show_ai_failures(mut commands: Commands, theme: Res<UiTheme>, mut failures: EventReader<AIRequestFailed>) {
+    if let Some(failure) = failures.read().last() {
+        spawn_notice(
+            &mut commands,
+            &theme,
+            format!("{} ({})", failure.error.summary(), failure.endpoint),
+            theme.palette.warning,
+        );
+    }
+}
+
+fn show_connection_changes(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut changes: EventReader<ConnectionChanged>,
+) {
+    if let Some(change) = changes.read().last() {
+        let (text, color) = if change.online {
+            ("The AI is answering again", theme.palette.good)
+        } else {
+            ("The AI isn't answering; carrying on without it", theme.palette.warning)
+        };
+        spawn_notice(&mut commands, &theme, text.to_string(), color);
+    }
+}
+
+fn spawn_connection_indicator(mut commands: Commands, theme: Res<UiTheme>) {
+    let mut indicator = TextBundle::from_section("", theme.text(TextSize::Fine, theme.palette.text)).with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(4.0),
+        right: Val::Px(8.0),
+        ..default()
+    });
+    indicator.z_index = ZIndex::Global(10);
+    commands.spawn((indicator, ConnectionIndicator));
+}
+
+fn spawn_save_prompt(mut commands: Commands, theme: Res<UiTheme>) {
+    let mut prompt = NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            left: Val::Percent(30.0),
+            width: Val::Percent(40.0),
+            padding: UiRect::all(Val::Px(12.0)),
+            display: Display::None,
+            ..default()
+        },
+        background_color: theme.palette.overlay.into(),
+        ..default()
+    };
+    prompt.z_index = ZIndex::Global(10);
+    commands.spawn((prompt, SavePrompt)).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("", theme.text(TextSize::Body, theme.palette.text)));
+    });
+}
+
+fn update_save_prompt(
+    save_as: Res<SaveAs>,
+    config: Res<GameConfig>,
+    mut prompts: Query<(&mut Style, &Children), With<SavePrompt>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !save_as.is_changed() {
+        return;
+    }
+    for (mut style, children) in prompts.iter_mut() {
+        let Some(name) = &save_as.0 else {
+            style.display = Display::None;
+            continue;
+        };
+        style.display = Display::Flex;
+        let taken = !name.is_blank() && slot_path(&config.save_dir, name.text()).exists();
+        let overwrite = if taken { "   (saves over this slot)" } else { "" };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value =
+                    format!("Save as: {}{}\nEnter save   ESC cancel", name.display(), overwrite);
+            }
+        }
+    }
+}
+
+fn update_connection_indicator(
+    status: Res<AiStatus>,
+    theme: Res<UiTheme>,
+    mut text_query: Query<&mut Text, With<ConnectionIndicator>>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+    let (value, color) = match status.connection {
+        Connection::Checking => ("AI: connecting...".to_string(), theme.palette.highlight),
+        Connection::Online => {
+            let latency = status.latency.map(|latency| format!(" ({} ms)", latency.as_millis())).unwrap_or_default();
+            (format!("AI: online{}", latency), theme.palette.good)
+        }
+        Connection::Offline(_) => ("AI: offline, using stand-ins".to_string(), theme.palette.danger),
+    };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+        text.sections[0].style.color = color;
+    }
+}
+
+fn expire_notice_banners(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut banners: Query<(Entity, &mut NoticeBanner)>,
+) {
+    for (entity, mut banner) in banners.iter_mut() {
+        if banner.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// The Dungeon Master's boxed text, over the exploration and combat screens alike
+#[derive(Component)]
+pub struct NarrationBox {
+    timer: Timer,
+}
+
+fn show_narration(
+    mut commands: Commands,
+    theme: Res<UiTheme>,
+    mut narrated: EventReader<Narrated>,
+    boxes: Query<Entity, With<NarrationBox>>,
+) {
+    let Some(Narrated(text)) = narrated.read().last() else {
+        return;
+    };
+    for entity in boxes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(110.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(9),
+                ..default()
+            },
+            NarrationBox {
+                timer: Timer::from_seconds(12.0, TimerMode::Once),
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(60.0),
+                        padding: UiRect::all(Val::Px(12.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.08, 0.06, 0.03, 0.92).into(),
+                    border_color: theme.palette.frame.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        text.clone(),
+                        theme.text(TextSize::Body, theme.palette.parchment),
+                    ));
+                });
+        });
+}
+
+fn expire_narration(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut boxes: Query<(Entity, &mut NarrationBox)>,
+) {
+    for (entity, mut narration) in boxes.iter_mut() {
+        if narration.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::ai_client::{CurrentDungeon, EnemyData};
+use crate::clock::{ClockSet, TurnsPassed};
+use crate::data::Bestiary;
+use crate::dungeon::DungeonMap;
+use crate::encounters::spawn_enemies;
+use crate::rng::GameRng;
+use crate::GameState;
+use old_school_core::dice;
+use old_school_core::monster::builtin_monster;
+
+pub use old_school_core::wandering::{roll_wandering, wandering_chance, WANDERING_CHECK_TURNS};
+
+// Wandering monsters. While the party is in a dungeon, every second turn on the game
+// clock rolls for something coming along (see old_school_core::wandering). Noise the
+// party has made since the last roll, like bashing at a stuck door, makes it likelier.
+// What turns up is one of the dungeon's own encounters, or if it has none a few of one of
+// the bestiary's weakest monsters, and the fight starts straight away.
+
+// Noise made since the last wandering monster roll
+#[derive(Resource, Default, Debug)]
+pub struct DungeonNoise(pub u8);
+
+// Turns gone by since the last roll
+#[derive(Resource, Default, Debug)]
+struct TurnsSinceCheck(u64);
+
+pub struct WanderingMonstersPlugin;
+
+impl Plugin for WanderingMonstersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DungeonNoise>()
+            .init_resource::<TurnsSinceCheck>()
+            .add_systems(Update, check_wandering_monsters.after(ClockSet).run_if(in_state(GameState::InGame)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn check_wandering_monsters(
+    mut commands: Commands,
+    mut turns: EventReader<TurnsPassed>,
+    map: Option<Res<DungeonMap>>,
+    dungeon: Res<CurrentDungeon>,
+    bestiary: Res<Bestiary>,
+    mut noise: ResMut<DungeonNoise>,
+    mut since_check: ResMut<TurnsSinceCheck>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let passed: u64 = turns.read().map(|event| event.0).sum();
+    if passed == 0 || map.is_none() {
+        return;
+    }
+
+    since_check.0 += passed;
+    let mut arrived = false;
+    while since_check.0 >= WANDERING_CHECK_TURNS && !arrived {
+        since_check.0 -= WANDERING_CHECK_TURNS;
+        arrived = roll_wandering(noise.0, rng.as_mut());
+        noise.0 = 0;
+    }
+    if !arrived {
+        return;
+    }
+    // Whatever time was left over is spent fighting
+    since_check.0 = 0;
+
+    let encounter = dungeon.0.as_ref().and_then(|dungeon| dungeon.encounters.choose(rng.as_mut()));
+    let monsters = match encounter {
+        Some(encounter) => encounter.enemies.clone(),
+        None => {
+            let count = dice::roll(rng.as_mut(), 1, 4).max(1) as usize;
+            let monster = weakest_monster(&bestiary, rng.as_mut());
+            monster.or_else(|| builtin_monster("goblin")).map(|monster| vec![monster; count]).unwrap_or_default()
+        }
+    };
+    let Some(first) = monsters.first() else {
+        return;
+    };
+
+    info!("Wandering monsters: {} {} come upon the party", monsters.len(), first.name);
+    spawn_enemies(&mut commands, &monsters, rng.as_mut());
+    next_state.set(GameState::Combat);
+}
+
+// One of the lowest level monsters in the bestiary, at random
+fn weakest_monster(bestiary: &Bestiary, rng: &mut GameRng) -> Option<EnemyData> {
+    let ids = bestiary.ids();
+    let lowest = ids.iter().filter_map(|id| bestiary.get(id)).map(|monster| monster.level).min()?;
+    let weakest: Vec<&str> =
+        ids.into_iter().filter(|id| bestiary.get(id).is_some_and(|monster| monster.level == lowest)).collect();
+    weakest.choose(rng).and_then(|id| bestiary.get(id)).cloned()
+}
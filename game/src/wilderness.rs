@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{
+    AIRequestFailed, CurrentDungeon, EncounterGeneratedEvent, EncounterGenerationEvent, EncounterGenerationRequest,
+    EnemyData,
+};
+use crate::character::Character;
+use crate::clock::{AdvanceClock, TimeSpan};
+use crate::combat::{Combatant, PresetSurprise, Side};
+use crate::death::party_movement_rate;
+use crate::config::KeyBindings;
+use crate::data::{Bestiary, WildernessTables};
+use crate::encounters::spawn_enemies;
+use crate::rng::GameRng;
+use crate::GameState;
+use old_school_core::monster::builtin_monster;
+
+pub use old_school_core::wilderness::{attempt_evasion, roll_sighting, roll_wilderness_encounter, Sighting, Terrain};
+
+// Overland travel. Outside a dungeon J has the party travel for a day through the
+// terrain it's in (clear country, setting out from town), and each day rolls for an
+// encounter on the terrain's table (see old_school_core::wilderness and the
+// core.wilderness.json data file). A monster the bestiary doesn't know is made up by
+// the AI service instead. Once the two sides have sighted each other the party can
+// try to evade with E, on relative pace, or close to fight with Space; surprise rolled
+// at the sighting carries into the fight.
+
+// Where the party is travelling; None while it's in town
+#[derive(Resource, Default, Debug)]
+pub struct Overland {
+    pub terrain: Option<Terrain>,
+    pub miles: u32,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Travel(pub Terrain);
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncounterChoice {
+    Evade,
+    Fight,
+}
+
+#[derive(Debug, Clone)]
+pub struct SightedMonsters {
+    pub sighting: Sighting,
+    pub enemies: Vec<EnemyData>,
+}
+
+// Monsters the party has sighted and not yet fought or got away from
+#[derive(Resource, Default, Debug)]
+pub struct WildernessEncounter(pub Option<SightedMonsters>);
+
+// The last day's travel or encounter, for the exploration screen
+#[derive(Resource, Default, Debug)]
+pub struct WildernessMessage(pub Option<String>);
+
+// A sighting waiting on the AI service for its monsters
+#[derive(Resource, Default, Debug)]
+struct AwaitingMonsters(Option<Sighting>);
+
+pub struct WildernessPlugin;
+
+impl Plugin for WildernessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Overland>()
+            .init_resource::<WildernessEncounter>()
+            .init_resource::<WildernessMessage>()
+            .init_resource::<AwaitingMonsters>()
+            .add_event::<Travel>()
+            .add_event::<EncounterChoice>()
+            .add_systems(Update, (
+                wilderness_keys.run_if(in_state(GameState::InGame)),
+                travel,
+                receive_generated_monsters,
+                resolve_encounter_choice,
+            ).chain());
+    }
+}
+
+fn wilderness_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    dungeon: Res<CurrentDungeon>,
+    overland: Res<Overland>,
+    encounter: Res<WildernessEncounter>,
+    mut journeys: EventWriter<Travel>,
+    mut choices: EventWriter<EncounterChoice>,
+) {
+    if encounter.0.is_some() {
+        if keyboard_input.just_pressed(KeyCode::E) {
+            choices.send(EncounterChoice::Evade);
+        } else if keyboard_input.just_pressed(KeyCode::Space) {
+            choices.send(EncounterChoice::Fight);
+        }
+    } else if keyboard_input.just_pressed(keys.travel) && dungeon.0.is_none() {
+        journeys.send(Travel(overland.terrain.unwrap_or(Terrain::Clear)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn travel(
+    mut journeys: EventReader<Travel>,
+    dungeon: Res<CurrentDungeon>,
+    party: Query<(&Character, &Combatant)>,
+    mut overland: ResMut<Overland>,
+    mut encounter: ResMut<WildernessEncounter>,
+    mut awaiting: ResMut<AwaitingMonsters>,
+    mut message: ResMut<WildernessMessage>,
+    mut advance: EventWriter<AdvanceClock>,
+    mut generation: EventWriter<EncounterGenerationEvent>,
+    tables: Res<WildernessTables>,
+    bestiary: Res<Bestiary>,
+    mut rng: ResMut<GameRng>,
+) {
+    for &Travel(terrain) in journeys.read() {
+        if dungeon.0.is_some() {
+            message.0 = Some("The party has to leave the dungeon first".to_string());
+            continue;
+        }
+        // Whatever the party has run into has to be dealt with first
+        if encounter.0.is_some() || awaiting.0.is_some() {
+            continue;
+        }
+        let players: Vec<&Character> = party
+            .iter()
+            .filter(|(_, combatant)| combatant.is_player)
+            .map(|(character, _)| character)
+            .collect();
+        let members: Vec<_> = players.iter().copied().filter(|character| character.is_alive()).collect();
+        if members.is_empty() {
+            continue;
+        }
+        // The dead slow the living down
+        let movement_rate = party_movement_rate(players);
+        if movement_rate == 0 {
+            message.0 = Some("The party is carrying too much to travel".to_string());
+            continue;
+        }
+
+        let miles = terrain.miles_per_day(movement_rate);
+        overland.terrain = Some(terrain);
+        overland.miles += miles;
+        advance.send(AdvanceClock(TimeSpan::Days(1)));
+        let mut text = format!("The party travels {} miles through the {}", miles, terrain.name());
+        info!("{}", text);
+
+        if roll_wilderness_encounter(terrain, rng.as_mut()) {
+            let table = tables.table(terrain);
+            if let Some(mut sighting) = roll_sighting(&table, rng.as_mut()) {
+                let known = bestiary.get(&sighting.monster).cloned().or_else(|| builtin_monster(&sighting.monster));
+                match known {
+                    Some(monster) => {
+                        sighting.monster = monster.name.clone();
+                        let enemies = vec![monster; sighting.count as usize];
+                        let sighted = SightedMonsters { sighting, enemies };
+                        text = format!("{}. {}", text, describe_sighting(&sighted));
+                        encounter.0 = Some(sighted);
+                    }
+                    None => {
+                        info!("No {} in the bestiary; asking the AI service", sighting.monster);
+                        let level = members.iter().map(|character| character.level as u32).sum::<u32>() / members.len() as u32;
+                        generation.send(EncounterGenerationEvent {
+                            request: EncounterGenerationRequest {
+                                difficulty: level.max(1) as u8,
+                                location: terrain.name().to_string(),
+                                party_size: members.len() as u8,
+                            },
+                        });
+                        text = format!("{}. Something is coming...", text);
+                        awaiting.0 = Some(sighting);
+                    }
+                }
+            }
+        }
+        message.0 = Some(text);
+    }
+}
+
+// The service's monsters stand in for the ones the table named, at the same distance
+fn receive_generated_monsters(
+    mut generated: EventReader<EncounterGeneratedEvent>,
+    mut failures: EventReader<AIRequestFailed>,
+    mut awaiting: ResMut<AwaitingMonsters>,
+    mut encounter: ResMut<WildernessEncounter>,
+    mut message: ResMut<WildernessMessage>,
+) {
+    for event in generated.read() {
+        let Some(mut sighting) = awaiting.0.take() else {
+            continue;
+        };
+        let Some(first) = event.encounter.enemies.first() else {
+            message.0 = Some("Whatever it was passes the party by".to_string());
+            continue;
+        };
+        sighting.monster = first.name.clone();
+        sighting.count = event.encounter.enemies.len() as u32;
+        let sighted = SightedMonsters { sighting, enemies: event.encounter.enemies.clone() };
+        message.0 = Some(describe_sighting(&sighted));
+        encounter.0 = Some(sighted);
+    }
+    for failure in failures.read() {
+        if failure.endpoint == "generate_encounter" && awaiting.0.take().is_some() {
+            message.0 = Some("Whatever it was passes the party by".to_string());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resolve_encounter_choice(
+    mut commands: Commands,
+    mut choices: EventReader<EncounterChoice>,
+    party: Query<(&Character, &Combatant)>,
+    mut encounter: ResMut<WildernessEncounter>,
+    mut message: ResMut<WildernessMessage>,
+    mut preset_surprise: ResMut<PresetSurprise>,
+    mut rng: ResMut<GameRng>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for &choice in choices.read() {
+        let Some(sighted) = encounter.0.take() else {
+            continue;
+        };
+        let name = &sighted.sighting.monster;
+
+        if choice == EncounterChoice::Evade {
+            let players = party.iter().filter(|(_, combatant)| combatant.is_player).map(|(character, _)| character);
+            let party_rate = party_movement_rate(players);
+            let monster_rate = sighted
+                .enemies
+                .iter()
+                .map(EnemyData::movement_rate)
+                .max()
+                .unwrap_or(0);
+            let evaded = attempt_evasion(sighted.sighting.surprised, party_rate, monster_rate, rng.as_mut());
+            info!("Evasion at {}' against {}': {}", party_rate, monster_rate, evaded);
+            if evaded {
+                message.0 = Some(format!("The party slips away from the {}", name));
+                continue;
+            }
+            message.0 = Some(format!("The {} close in before the party can get away", name));
+        } else {
+            message.0 = None;
+        }
+
+        spawn_enemies(&mut commands, &sighted.enemies, rng.as_mut());
+        preset_surprise.0 = Some(sighted.sighting.surprised);
+        next_state.set(GameState::Combat);
+    }
+}
+
+fn describe_sighting(sighted: &SightedMonsters) -> String {
+    let sighting = &sighted.sighting;
+    let surprise = match sighting.surprised {
+        Some(Side::Party) => " The party is caught unawares!",
+        Some(Side::Enemies) => " They haven't seen the party.",
+        None => "",
+    };
+    format!(
+        "{} {} sighted {} yards off.{} E: Evade | Space: Fight",
+        sighting.count, sighting.monster, sighting.distance_yards, surprise
+    )
+}
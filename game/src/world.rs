@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use crate::ai_client::{CurrentDungeon, DungeonGenerationEvent, DungeonGenerationRequest};
+use crate::dungeon::{DungeonMap, FogOfWar};
+use crate::encounters::MetEncounters;
+use crate::treasure::LootedTreasure;
+
+pub use old_school_core::world::{DungeonRecord, DungeonState, SiteHook, WorldDatabase};
+
+// Going in and out of dungeons. Each dungeon stands at a named site; the first time the
+// party goes to a site its dungeon is asked of the AI service, and when they leave it
+// goes into the WorldDatabase as they left it. Going back to the site brings it out of
+// there instead of generating a new one, and build_dungeon_map in dungeon.rs puts the
+// doors, traps, encounters and treasure back the way they were.
+
+// Where the party is, if they're in a dungeon they went to by name
+#[derive(Resource, Default, Debug)]
+pub struct CurrentSite(pub Option<String>);
+
+// How the party left the dungeon that's about to be laid out, by the dungeon's name
+#[derive(Resource, Default, Debug)]
+pub struct PendingDungeonState(pub Option<(String, DungeonState)>);
+
+#[derive(Event, Clone, Debug)]
+pub struct EnterDungeon {
+    pub site: String,
+    // Only asked for if the party has never been to the site
+    pub request: DungeonGenerationRequest,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LeaveDungeon;
+
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldDatabase>()
+            .init_resource::<CurrentSite>()
+            .init_resource::<PendingDungeonState>()
+            .add_event::<EnterDungeon>()
+            .add_event::<LeaveDungeon>()
+            .add_systems(Update, (enter_dungeon, leave_dungeon).chain());
+    }
+}
+
+// The current dungeon as the party would leave it now
+pub fn current_record(
+    dungeon: &CurrentDungeon,
+    map: Option<&DungeonMap>,
+    met: &MetEncounters,
+    looted: &LootedTreasure,
+    fog: &FogOfWar,
+) -> Option<DungeonRecord> {
+    let data = dungeon.0.as_ref()?;
+    let map = map?;
+    Some(DungeonRecord {
+        dungeon: data.clone(),
+        state: DungeonState::capture(map, &met.0, &looted.0),
+        fog: fog.clone(),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn enter_dungeon(
+    mut entering: EventReader<EnterDungeon>,
+    mut world: ResMut<WorldDatabase>,
+    mut site: ResMut<CurrentSite>,
+    mut dungeon: ResMut<CurrentDungeon>,
+    mut fog: ResMut<FogOfWar>,
+    mut pending: ResMut<PendingDungeonState>,
+    map: Option<Res<DungeonMap>>,
+    met: Res<MetEncounters>,
+    looted: Res<LootedTreasure>,
+    mut generation: EventWriter<DungeonGenerationEvent>,
+) {
+    let Some(event) = entering.read().last() else {
+        return;
+    };
+    if site.0.as_deref() == Some(event.site.as_str()) {
+        info!("The party is already at {}", event.site);
+        return;
+    }
+
+    if let Some(left) = site.0.take() {
+        if let Some(record) = current_record(&dungeon, map.as_deref(), &met, &looted, &fog) {
+            world.store(left, record);
+        }
+    }
+    site.0 = Some(event.site.clone());
+
+    match world.get(&event.site) {
+        Some(record) => {
+            info!("The party returns to {}", record.dungeon.name);
+            pending.0 = Some((record.dungeon.name.clone(), record.state.clone()));
+            *fog = record.fog.clone();
+            dungeon.0 = Some(record.dungeon.clone());
+        }
+        None => {
+            info!("The party sets out for {}", event.site);
+            dungeon.0 = None;
+            generation.send(DungeonGenerationEvent { request: event.request.clone() });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn leave_dungeon(
+    mut leaving: EventReader<LeaveDungeon>,
+    mut world: ResMut<WorldDatabase>,
+    mut site: ResMut<CurrentSite>,
+    mut dungeon: ResMut<CurrentDungeon>,
+    fog: Res<FogOfWar>,
+    map: Option<Res<DungeonMap>>,
+    met: Res<MetEncounters>,
+    looted: Res<LootedTreasure>,
+) {
+    if leaving.read().last().is_none() {
+        return;
+    }
+    let Some(left) = site.0.take() else {
+        info!("The party isn't at a site to leave");
+        return;
+    };
+    if let Some(record) = current_record(&dungeon, map.as_deref(), &met, &looted, &fog) {
+        world.store(left.clone(), record);
+    }
+    info!("The party leaves {}", left);
+    dungeon.0 = None;
+}
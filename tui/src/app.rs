@@ -0,0 +1,437 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use old_school_core::character::{Character, CharacterClass};
+use old_school_core::combat::{attempt_flight, get_combat_text, roll_attack, FleeOutcome};
+use old_school_core::dice;
+use old_school_core::encounter::{open_encounter, Side};
+use old_school_core::experience::hit_dice_xp;
+use old_school_core::monster::builtin_monster;
+use old_school_core::rng::GameRng;
+use old_school_core::ruleset::Ruleset;
+use old_school_core::simulation::SimCombatant;
+use old_school_core::skills::{best_at, ThiefSkill};
+
+// A dungeon crawl played entirely in the terminal. Turn flow follows B/X group
+// initiative: each round both sides roll 1d6, the winner acts first, and the player
+// picks a target for each living party member in turn.
+
+pub const LOG_LIMIT: usize = 200;
+
+const PARTY: &[(&str, CharacterClass)] = &[
+    ("Aldric", CharacterClass::Fighter),
+    ("Brother Tam", CharacterClass::Cleric),
+    ("Mirela", CharacterClass::MagicUser),
+    ("Quick Wil", CharacterClass::Thief),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Exploring,
+    // The monsters are willing to talk rather than fight
+    Parley,
+    Combat,
+    Defeated,
+}
+
+pub struct App {
+    pub party: Vec<SimCombatant>,
+    pub enemies: Vec<SimCombatant>,
+    pub log: Vec<String>,
+    pub mode: Mode,
+    pub round: u32,
+    pub active: usize,
+    pub target: usize,
+    pub rooms_explored: u32,
+    pub gold: u32,
+    pub quit: bool,
+    enemies_acted: bool,
+    rng: GameRng,
+}
+
+impl App {
+    pub fn new(rng: GameRng) -> Self {
+        let mut app = Self {
+            party: Vec::new(),
+            enemies: Vec::new(),
+            log: Vec::new(),
+            mode: Mode::Exploring,
+            round: 0,
+            active: 0,
+            target: 0,
+            rooms_explored: 0,
+            gold: 0,
+            quit: false,
+            enemies_acted: false,
+            rng,
+        };
+        app.new_party();
+        app
+    }
+
+    pub fn new_party(&mut self) {
+        self.party = PARTY
+            .iter()
+            .map(|(name, class)| SimCombatant::hero(Character::new_with(name.to_string(), class.clone(), &mut self.rng)))
+            .collect();
+        self.enemies.clear();
+        self.mode = Mode::Exploring;
+        self.rooms_explored = 0;
+        self.gold = 0;
+        self.log(format!("A new company gathers at the dungeon mouth (seed {}).", self.rng.seed()));
+    }
+
+    pub fn log(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > LOG_LIMIT {
+            self.log.remove(0);
+        }
+    }
+
+    // Each room is an encounter on 1-3, treasure on 4-5 and empty on a 6
+    pub fn explore(&mut self) {
+        if self.mode != Mode::Exploring {
+            return;
+        }
+        self.rooms_explored += 1;
+
+        match dice::d6(&mut self.rng) {
+            1..=3 => self.start_encounter(),
+            4..=5 => {
+                let gold = dice::roll(&mut self.rng, 2, 6) as u32 * 10;
+                self.gold += gold;
+                self.log(format!("Room {}: a mouldering chest holds {} gp.", self.rooms_explored, gold));
+                if dice::d6(&mut self.rng) <= 2 {
+                    self.check_chest_for_traps();
+                }
+            }
+            _ => self.log(format!("Room {}: dust and silence.", self.rooms_explored)),
+        }
+    }
+
+    // A trapped chest: the party's thief gets a Find/Remove Traps roll, otherwise a
+    // needle or blade catches whoever lifts the lid for 1d6
+    fn check_chest_for_traps(&mut self) {
+        let thief = best_at(self.party.iter().map(|member| &member.character), ThiefSkill::FindRemoveTraps);
+        if let Some(thief) = thief {
+            let check = thief.check_skill(ThiefSkill::FindRemoveTraps, 0, &mut self.rng);
+            let name = thief.name.clone();
+            if check.success {
+                self.log(format!("{} finds and disarms a trap on the lock ({}/{}%).", name, check.roll, check.chance));
+                return;
+            }
+            self.log(format!("{} misses the trap ({}/{}%).", name, check.roll, check.chance));
+        }
+
+        let damage = dice::d6(&mut self.rng) as i16;
+        if let Some(victim) = self.party.iter_mut().find(|member| member.is_alive()) {
+            victim.character.take_damage(damage);
+            let text = format!("A poisoned needle springs out at {} for {} damage!", victim.character.name, damage);
+            self.log(text);
+        }
+        if !self.party.iter().any(SimCombatant::is_alive) {
+            self.log(format!(
+                "The company is lost after {} rooms, with {} gp unspent. Press n for a new party.",
+                self.rooms_explored, self.gold
+            ));
+            self.mode = Mode::Defeated;
+        }
+    }
+
+    // Resting heals 1d3 per character but risks a wandering monster
+    pub fn rest(&mut self) {
+        if self.mode != Mode::Exploring {
+            return;
+        }
+
+        for index in 0..self.party.len() {
+            if self.party[index].is_alive() {
+                let amount = dice::roll(&mut self.rng, 1, 3);
+                self.party[index].character.heal(amount);
+                self.party[index].character.rest_and_memorize();
+            }
+        }
+        self.log("The party rests and binds its wounds.");
+
+        if dice::d6(&mut self.rng) == 1 {
+            self.log("Something heard you...");
+            self.start_encounter();
+        }
+    }
+
+    fn start_encounter(&mut self) {
+        // Deeper rooms draw from tougher monsters
+        let pool: &[&str] = match self.rooms_explored {
+            0..=3 => &["goblin", "goblin", "skeleton"],
+            4..=8 => &["goblin", "orc", "skeleton"],
+            _ => &["orc", "skeleton", "troll"],
+        };
+        let id = *pool.choose(&mut self.rng).unwrap_or(&"goblin");
+        let Some(monster) = builtin_monster(id) else {
+            return;
+        };
+        let count = if id == "troll" { 1 } else { self.rng.gen_range(1..=4) };
+
+        self.enemies = (0..count)
+            .map(|index| {
+                let mut enemy = SimCombatant::monster(&monster);
+                if count > 1 {
+                    enemy.character.name = format!("{} {}", monster.name, index + 1);
+                }
+                enemy
+            })
+            .collect();
+        self.log(format!("Room {}: {} {}!", self.rooms_explored, count, monster.name));
+
+        // The most charismatic survivor does the talking
+        let charisma = self
+            .party
+            .iter()
+            .filter(|member| member.is_alive())
+            .map(|member| member.character.stats.charisma)
+            .max()
+            .unwrap_or(10);
+        let start = open_encounter(charisma, &mut self.rng);
+        self.log(format!("Reaction {}: the {} {}.", start.reaction_roll, monster.name, start.reaction.describe()));
+
+        if start.reaction.fights() {
+            self.begin_fight(start.surprised);
+        } else if start.reaction.parleys() {
+            self.mode = Mode::Parley;
+        } else {
+            self.log("They go about their business.");
+            self.enemies.clear();
+        }
+    }
+
+    // Attack monsters that were willing to talk
+    pub fn attack_parley(&mut self) {
+        if self.mode == Mode::Parley {
+            self.log("The party attacks!");
+            self.begin_fight(None);
+        }
+    }
+
+    pub fn leave_parley(&mut self) {
+        if self.mode == Mode::Parley {
+            self.escape("The party leaves them be.");
+        }
+    }
+
+    // The surprised side loses the first round
+    fn begin_fight(&mut self, surprised: Option<Side>) {
+        self.mode = Mode::Combat;
+        self.round = 0;
+        self.target = 0;
+
+        match surprised {
+            Some(Side::Party) => {
+                self.log("The party is surprised!");
+                self.enemies_act();
+                if self.mode == Mode::Combat {
+                    self.start_round();
+                }
+            }
+            Some(Side::Enemies) => {
+                self.log("The monsters are caught unawares: a free round for the party.");
+                self.round = 1;
+                self.enemies_acted = true;
+                self.active = 0;
+                self.skip_fallen_heroes();
+            }
+            None => self.start_round(),
+        }
+    }
+
+    fn start_round(&mut self) {
+        self.round += 1;
+        let party_roll = dice::d6(&mut self.rng);
+        let monster_roll = dice::d6(&mut self.rng);
+        self.log(format!("Round {}: initiative party {} vs monsters {}.", self.round, party_roll, monster_roll));
+
+        self.enemies_acted = false;
+        if monster_roll > party_roll {
+            self.enemies_act();
+        }
+
+        self.active = 0;
+        self.skip_fallen_heroes();
+        if self.mode == Mode::Combat && self.active >= self.party.len() {
+            self.end_round();
+        }
+    }
+
+    // The active hero attacks the selected enemy
+    pub fn attack(&mut self) {
+        if self.mode != Mode::Combat || self.active >= self.party.len() {
+            return;
+        }
+        self.select_living_target();
+        if !self.enemies.get(self.target).is_some_and(SimCombatant::is_alive) {
+            return;
+        }
+
+        let hero = &self.party[self.active];
+        let enemy = &self.enemies[self.target];
+        let (hit, damage) = roll_attack(&hero.character, &enemy.character, hero.weapon.as_deref(), &mut self.rng);
+        let text = get_combat_text(&hero.character, &enemy.character, hit, damage);
+        self.log(text);
+
+        if hit {
+            let enemy = &mut self.enemies[self.target];
+            enemy.character.take_damage(damage);
+            if !enemy.is_alive() {
+                let name = enemy.character.name.clone();
+                self.log(format!("{} is slain.", name));
+            }
+        }
+        if self.check_victory() {
+            return;
+        }
+
+        self.active += 1;
+        self.skip_fallen_heroes();
+        if self.active >= self.party.len() {
+            self.end_round();
+        }
+    }
+
+    // Run for it, optionally throwing the party's gold behind to slow the pursuit. If
+    // the monsters catch up they get a free round.
+    pub fn flee(&mut self, drop_gold: bool) {
+        if self.mode != Mode::Combat {
+            return;
+        }
+
+        let slowest = |side: &[SimCombatant]| {
+            side.iter().filter(|c| c.is_alive()).map(|c| c.character.movement_rate()).min().unwrap_or(0)
+        };
+        let fastest = |side: &[SimCombatant]| {
+            side.iter().filter(|c| c.is_alive()).map(|c| c.character.movement_rate()).max().unwrap_or(0)
+        };
+        let dropped = drop_gold && self.gold > 0;
+        if dropped {
+            self.log(format!("The party scatters {} gp behind it.", self.gold));
+            self.gold = 0;
+        }
+
+        match attempt_flight(slowest(&self.party), fastest(&self.enemies), dropped, &mut self.rng) {
+            FleeOutcome::Escaped => self.escape("The party flees back the way it came."),
+            FleeOutcome::Distracted => self.escape("The monsters stop to squabble over the coins."),
+            FleeOutcome::Caught => {
+                self.log("The monsters cut off the retreat!");
+                self.enemies_act();
+            }
+        }
+    }
+
+    fn escape(&mut self, text: &str) {
+        self.log(text);
+        self.enemies.clear();
+        self.mode = Mode::Exploring;
+    }
+
+    pub fn next_target(&mut self, step: isize) {
+        let living: Vec<usize> = (0..self.enemies.len()).filter(|&i| self.enemies[i].is_alive()).collect();
+        if living.is_empty() {
+            return;
+        }
+        let current = living.iter().position(|&i| i == self.target).unwrap_or(0) as isize;
+        let next = (current + step).rem_euclid(living.len() as isize) as usize;
+        self.target = living[next];
+    }
+
+    fn end_round(&mut self) {
+        if self.mode != Mode::Combat {
+            return;
+        }
+        if !self.enemies_acted {
+            self.enemies_act();
+        }
+        if self.mode == Mode::Combat {
+            self.start_round();
+        }
+    }
+
+    fn enemies_act(&mut self) {
+        self.enemies_acted = true;
+
+        for index in 0..self.enemies.len() {
+            if !self.enemies[index].is_alive() {
+                continue;
+            }
+            let living: Vec<usize> = (0..self.party.len()).filter(|&i| self.party[i].is_alive()).collect();
+            let Some(&victim) = living.choose(&mut self.rng) else {
+                break;
+            };
+
+            let enemy = &self.enemies[index];
+            let hero = &self.party[victim];
+            let (hit, damage) = roll_attack(&enemy.character, &hero.character, enemy.weapon.as_deref(), &mut self.rng);
+            let text = get_combat_text(&enemy.character, &hero.character, hit, damage);
+            self.log(text);
+
+            if hit {
+                let hero = &mut self.party[victim];
+                hero.character.take_damage(damage);
+                if !hero.is_alive() {
+                    let name = hero.character.name.clone();
+                    self.log(format!("{} falls!", name));
+                }
+            }
+        }
+
+        if !self.party.iter().any(SimCombatant::is_alive) {
+            self.log(format!(
+                "The company is lost after {} rooms, with {} gp unspent. Press n for a new party.",
+                self.rooms_explored, self.gold
+            ));
+            self.mode = Mode::Defeated;
+        }
+    }
+
+    fn check_victory(&mut self) -> bool {
+        if self.enemies.iter().any(SimCombatant::is_alive) {
+            return false;
+        }
+
+        // B/X experience by monster level, shared among the survivors
+        let xp: u32 = self.enemies.iter().map(|enemy| monster_xp(enemy.character.level)).sum();
+        let survivors: Vec<usize> = (0..self.party.len()).filter(|&i| self.party[i].is_alive()).collect();
+        let share = xp / survivors.len().max(1) as u32;
+        self.log(format!("Victory! Each survivor earns {} XP.", share));
+
+        for index in survivors {
+            let before = self.party[index].character.level;
+            self.party[index].character.gain_experience(share);
+            self.party[index].character.check_level_up(&Ruleset::default());
+            let character = &self.party[index].character;
+            if character.level > before {
+                let text = format!("{} reaches level {}!", character.name, character.level);
+                self.log(text);
+            }
+        }
+
+        self.enemies.clear();
+        self.mode = Mode::Exploring;
+        true
+    }
+
+    fn skip_fallen_heroes(&mut self) {
+        while self.active < self.party.len() && !self.party[self.active].is_alive() {
+            self.active += 1;
+        }
+    }
+
+    fn select_living_target(&mut self) {
+        if !self.enemies.get(self.target).is_some_and(SimCombatant::is_alive) {
+            if let Some(index) = self.enemies.iter().position(SimCombatant::is_alive) {
+                self.target = index;
+            }
+        }
+    }
+}
+
+fn monster_xp(level: u8) -> u32 {
+    hit_dice_xp(level.max(1), false).0
+}
@@ -0,0 +1,59 @@
+use std::io;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+use old_school_core::rng::GameRng;
+
+mod app;
+mod ui;
+
+use app::{App, Mode};
+
+// Terminal frontend for the core rules: `old-school-tui [--seed <n>]`
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut seed = None;
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            seed = args.next().and_then(|value| value.parse().ok());
+        }
+    }
+    let rng = seed.map(GameRng::from_seed).unwrap_or_default();
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, App::new(rng));
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, mut app: App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                handle_key(&mut app, key.code);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_key(app: &mut App, key: KeyCode) {
+    match (app.mode, key) {
+        (_, KeyCode::Char('q') | KeyCode::Esc) => app.quit = true,
+        (Mode::Exploring, KeyCode::Char('e')) => app.explore(),
+        (Mode::Exploring, KeyCode::Char('r')) => app.rest(),
+        (Mode::Parley, KeyCode::Char('a')) => app.attack_parley(),
+        (Mode::Parley, KeyCode::Char('l')) => app.leave_parley(),
+        (Mode::Combat, KeyCode::Char('a') | KeyCode::Enter) => app.attack(),
+        (Mode::Combat, KeyCode::Char('f')) => app.flee(false),
+        (Mode::Combat, KeyCode::Char('d')) => app.flee(true),
+        (Mode::Combat, KeyCode::Up | KeyCode::Char('k')) => app.next_target(-1),
+        (Mode::Combat, KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab) => app.next_target(1),
+        (Mode::Defeated, KeyCode::Char('n')) => app.new_party(),
+        _ => {}
+    }
+}
@@ -0,0 +1,122 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+
+use old_school_core::simulation::SimCombatant;
+
+use crate::app::{App, Mode};
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [header, main, log, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(12),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [party, enemies] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(main);
+
+    draw_header(frame, app, header);
+    draw_party(frame, app, party);
+    draw_enemies(frame, app, enemies);
+    draw_log(frame, app, log);
+    draw_footer(frame, app, footer);
+}
+
+fn draw_header(frame: &mut Frame, app: &App, area: Rect) {
+    let status = match app.mode {
+        Mode::Exploring => "Exploring".to_string(),
+        Mode::Parley => "Parley".to_string(),
+        Mode::Combat => format!("Combat, round {}", app.round),
+        Mode::Defeated => "Defeated".to_string(),
+    };
+    let line = Line::from(vec![
+        Span::styled("Old School AI", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(format!("   Rooms explored: {}   Gold: {} gp   {}", app.rooms_explored, app.gold, status)),
+    ]);
+    frame.render_widget(Paragraph::new(line).block(Block::default().borders(Borders::ALL)), area);
+}
+
+fn draw_party(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .party
+        .iter()
+        .enumerate()
+        .map(|(index, member)| {
+            let character = &member.character;
+            let marker = if app.mode == Mode::Combat && index == app.active { "▶ " } else { "  " };
+            ListItem::new(Line::from(vec![
+                Span::raw(marker),
+                Span::styled(format!("{:<12}", character.name), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" L{} {:<9?} AC {:>2} ", character.level, character.class, character.armor_class)),
+                hp_span(member),
+            ]))
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(Block::default().title(" Party ").borders(Borders::ALL)), area);
+}
+
+fn draw_enemies(frame: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .enemies
+        .iter()
+        .map(|enemy| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<12} ", enemy.character.name)),
+                hp_span(enemy),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default();
+    if app.mode == Mode::Combat {
+        state.select(Some(app.target));
+    }
+    let list = List::new(items)
+        .block(Block::default().title(" Monsters ").borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol("» ");
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_log(frame: &mut Frame, app: &App, area: Rect) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = app
+        .log
+        .iter()
+        .skip(app.log.len().saturating_sub(visible))
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+
+    let log = Paragraph::new(lines)
+        .block(Block::default().title(" Log ").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(log, area);
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let keys = match app.mode {
+        Mode::Exploring => "e explore   r rest   q quit",
+        Mode::Parley => "a attack   l leave them be   q quit",
+        Mode::Combat => "a/Enter attack   ↑/↓ target   f flee   d drop gold and flee   q quit",
+        Mode::Defeated => "n new party   q quit",
+    };
+    frame.render_widget(Paragraph::new(keys).dark_gray(), area);
+}
+
+fn hp_span(combatant: &SimCombatant) -> Span<'static> {
+    let hp = &combatant.character.hit_points;
+    let color = if hp.current <= 0 {
+        Color::DarkGray
+    } else if hp.current * 3 <= hp.maximum {
+        Color::Red
+    } else if hp.current * 3 <= hp.maximum * 2 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+    Span::styled(format!("HP {:>3}/{:<3}", hp.current, hp.maximum), Style::default().fg(color))
+}